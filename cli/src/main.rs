@@ -1,14 +1,45 @@
 use anyhow::Result;
-use clap::{Parser, Subcommand};
-use ontoenv::config::{Config, EnvironmentConfig};
-use ontoenv::ontology::{GraphIdentifier, OntologyLocation};
-use ontoenv::util::write_dataset_to_file;
-use ontoenv::OntoEnv;
-use oxigraph::model::{NamedNode, NamedNodeRef};
+use clap::{Args, Parser, Subcommand};
+use ontoenv::cancel::{CancellationToken, Cancelled};
+use ontoenv::config::{
+    self, Config, DuplicateStrategy, EnvironmentConfig, ImportConstraint, Profile, ResourceLimits,
+};
+use ontoenv::depgraph::petgraph::graph::NodeIndex;
+use ontoenv::depgraph::petgraph::visit::Dfs;
+use ontoenv::depgraph::DependencyNode;
+use ontoenv::diff::DiffFormat;
+use ontoenv::docgen::{self, DocFormat};
+use ontoenv::doctor::DoctorReport;
+use ontoenv::events::{EnvironmentListener, Progress};
+use ontoenv::iri;
+use ontoenv::legacy;
+use ontoenv::oci::{self, Reference};
+use ontoenv::ontology::{GraphIdentifier, NameSource, OntologyLocation};
+use ontoenv::reasoner::{self, ExternalReasoner, Reasoner};
+use ontoenv::release::{self, ReleaseOptions};
+use ontoenv::report;
+use ontoenv::resolution::{self, ReportFormat};
+use ontoenv::schema;
+#[cfg(feature = "shacl")]
+use ontoenv::shacl::Severity;
+use ontoenv::sparql::SparqlAuth;
+use ontoenv::util::{
+    dataset_to_graph, read_file, write_dataset_to_file_with, write_dataset_to_trig_with,
+    write_graph_to_file_with, SerializerOptions,
+};
+use ontoenv::watch::WatchOptions;
+use ontoenv::{MergePrecedence, OntoEnv, RefreshStrategy, RemoveMode};
+use oxigraph::model::Graph;
 use serde_json;
+use std::any::Any;
+use std::collections::{BTreeMap, HashSet};
 use std::env::current_dir;
 use std::fs::File;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing_subscriber::fmt::format::FmtSpan;
+use tracing_subscriber::EnvFilter;
 
 #[derive(Debug, Parser)]
 #[command(name = "ontoenv")]
@@ -17,15 +48,32 @@ use std::path::PathBuf;
 struct Cli {
     #[command(subcommand)]
     command: Commands,
-    /// Verbose mode - sets the RUST_LOG level to info, defaults to warning level
+    /// Verbose mode - sets the log level to info, defaults to warning level
     #[clap(long, short, action, default_value = "false")]
     verbose: bool,
-    /// Debug mode - sets the RUST_LOG level to debug, defaults to warning level
+    /// Debug mode - sets the log level to debug, defaults to warning level
     #[clap(long, action, default_value = "false")]
     debug: bool,
-    /// Resolution policy for determining which ontology to use when there are multiple with the same name
+    /// Resolution policy for determining which ontology to use when there are multiple with the same name:
+    /// default, latest, version or compatible
     #[clap(long, short, default_value = "default")]
     policy: Option<String>,
+    /// Another environment (a directory containing .ontoenv) to resolve against, after the one
+    /// in the current directory; may be repeated. Commands which change the environment ignore it
+    #[clap(long = "env", value_name = "PATH", global = true)]
+    envs: Vec<PathBuf>,
+    /// Which environment provides an ontology found in several of them: first (the current
+    /// environment, then the --env environments in order) or policy (the resolution policy picks)
+    #[clap(long, default_value = "first", global = true)]
+    env_precedence: String,
+    /// The profile (see `init --define-profile`) to resolve ontologies in, instead of the whole
+    /// environment
+    #[clap(long, global = true)]
+    profile: Option<String>,
+    /// Report the progress of init, refresh, add and get-closure on stderr: 'json' writes one
+    /// JSON object per line with the phase, item, current, total and percent
+    #[clap(long, value_name = "FORMAT", value_parser = ["json"], global = true)]
+    progress: Option<String>,
 }
 
 #[derive(Debug, Subcommand)]
@@ -40,6 +88,27 @@ enum Commands {
         /// Strict mode - will raise an error if an ontology is not found
         #[clap(long, short, action, default_value = "false")]
         strict: bool,
+        /// Fail on files which cannot be read or parsed, regardless of --strict
+        #[clap(long, value_name = "BOOL", num_args = 0..=1, default_missing_value = "true")]
+        strict_parse: Option<bool>,
+        /// Fail on imports which do not resolve, regardless of --strict
+        #[clap(long, value_name = "BOOL", num_args = 0..=1, default_missing_value = "true")]
+        strict_missing_imports: Option<bool>,
+        /// Fail on remote ontologies which cannot be fetched, regardless of --strict; e.g.
+        /// '--strict --strict-network false' tolerates unreachable imports
+        #[clap(long, value_name = "BOOL", num_args = 0..=1, default_missing_value = "true")]
+        strict_network: Option<bool>,
+        /// Fail on ontologies with the same name as one at another location (not implied by
+        /// --strict)
+        #[clap(long, value_name = "BOOL", num_args = 0..=1, default_missing_value = "true")]
+        strict_duplicates: Option<bool>,
+        /// Fail on violations of the import and version constraints, regardless of --strict
+        #[clap(long, value_name = "BOOL", num_args = 0..=1, default_missing_value = "true")]
+        strict_constraints: Option<bool>,
+        /// How imports of an ontology found at several locations resolve: 'pick' one of them
+        /// with the resolution policy, or 'merge' all of them, for ontologies split across files
+        #[clap(long, value_name = "STRATEGY", default_value = "pick")]
+        duplicate_strategy: DuplicateStrategy,
         /// Offline mode - will not attempt to fetch ontologies from the web
         #[clap(long, short, action, default_value = "false")]
         offline: bool,
@@ -58,13 +127,171 @@ enum Commands {
         /// Do not search for ontologies in the search directories
         #[clap(long = "no-search", short = 'n', action)]
         no_search: bool,
+        /// Keep only the imports closure of this ontology (a file, URL or the name of an
+        /// ontology in the search directories). Later refreshes follow its imports instead of
+        /// searching the directories
+        #[clap(long, value_name = "LOCATION")]
+        root_ontology: Option<String>,
+        /// The name of an entry-point ontology of the environment (repeatable). Ontologies
+        /// outside the imports closures of all roots are listed by 'list-ontologies --orphans'
+        #[clap(long = "root", value_name = "IRI")]
+        roots: Vec<String>,
+        /// Forbid ontologies at some locations from (transitively) importing other ontologies,
+        /// given as '<location glob>=<ontology name glob>', e.g. 'models/*=http://example.com/deprecated/*'
+        #[clap(long = "forbid-import", num_args = 1..)]
+        forbid_imports: Vec<String>,
+        /// Require the ontologies with a name to have a matching version (their owl:versionInfo,
+        /// or the version in their owl:versionIRI), given as '<ontology><requirement>', e.g.
+        /// 'https://brickschema.org/schema/Brick>=1.3' or 'http://qudt.org/schema/qudt>=2.1,<3'
+        #[clap(long = "require-version", value_name = "CONSTRAINT", num_args = 1..)]
+        version_constraints: Vec<String>,
+        /// Follow these predicates from ontologies like owl:imports, e.g. 'dcterms:requires' for
+        /// vocabularies which do not use owl:imports. IRIs, or CURIEs with the prefixes rdfs, owl,
+        /// dcterms, skos, prov or void
+        #[clap(long = "follow", value_name = "PREDICATE", num_args = 1..)]
+        traversal_predicates: Vec<String>,
+        /// Base IRI for relative IRIs in files which do not declare one with @base. Defaults to
+        /// the file:// URL of each file
+        #[clap(long)]
+        base_iri: Option<String>,
+        /// In strict mode, reject files with relative IRIs which do not declare a base IRI
+        #[clap(long, action, default_value = "false")]
+        reject_relative_iris: bool,
+        /// Read files with an extension in a format, given as '<extension>=<format>', e.g.
+        /// 'owl=rdfxml', or '=sniff' for files without an extension. Files with these extensions
+        /// are searched for in addition to the includes
+        #[clap(long = "format", num_args = 1..)]
+        formats: Vec<String>,
+        /// Read owl:imports of an IRI from another location, given as '<import>=<location>',
+        /// e.g. 'http://qudt.org/2.1/schema/qudt=vendor/qudt.ttl'. Locations are URLs or paths
+        /// relative to the root; the ontology read there keeps the name of the import
+        #[clap(long = "map-import", num_args = 1..)]
+        import_map: Vec<String>,
+        /// Skip files larger than this many bytes
+        #[clap(long, value_name = "BYTES")]
+        max_file_size: Option<u64>,
+        /// Give up on reading an ontology after this many seconds
+        #[clap(long, value_name = "SECONDS")]
+        parse_timeout: Option<u64>,
+        /// Fetch remote ontologies again on every update once they were fetched this many
+        /// seconds ago, if the server reports that they changed
+        #[clap(long, value_name = "SECONDS")]
+        cache_ttl: Option<u64>,
+        /// Follow symbolic links when searching for ontologies
+        #[clap(long, action, default_value = "false")]
+        follow_symlinks: bool,
+        /// Write the graphs each update changes, with fingerprints of their content, as JSON to
+        /// this file (relative to the root)
+        #[clap(long, value_name = "PATH")]
+        change_manifest: Option<PathBuf>,
+        /// POST the graphs each update changes, as JSON, to this URL
+        #[clap(long, value_name = "URL")]
+        change_webhook: Option<String>,
+        /// Read and parse at most this many files at once, defaults to the number of CPUs
+        #[clap(long, value_name = "N")]
+        max_parallel: Option<usize>,
+        /// Keep documents read at once and unions kept in memory within this many bytes, reading
+        /// fewer documents in parallel and keeping fewer unions as needed
+        #[clap(long, value_name = "BYTES")]
+        max_memory: Option<u64>,
+        /// Keep at most this many unions in memory (0 for none)
+        #[clap(long, value_name = "N")]
+        memory_cache_unions: Option<usize>,
+        /// Fetch at most this many remote ontologies at once
+        #[clap(long, value_name = "N")]
+        max_downloads: Option<usize>,
+        /// Keep the unions cached in .ontoenv/cache, and the graphs kept for snapshots, each
+        /// within this many bytes
+        #[clap(long, value_name = "BYTES")]
+        max_cache_size: Option<u64>,
+        /// Keep a copy of the graphs of the snapshot recorded by every update, so it can be
+        /// loaded and rolled back to once its ontologies changed
+        #[clap(long, action, default_value = "false")]
+        snapshot_graphs: bool,
+        /// Resolve imports which differ from an ontology's name only in a trailing '/' or '#'
+        /// (e.g. 'http://example.org/ont/' for 'http://example.org/ont') to that ontology
+        #[clap(long, action, default_value = "false")]
+        ignore_trailing_separators: bool,
+        /// Name files without an owl:Ontology declaration after the first of these declarations
+        /// they contain: void:Dataset, skos:ConceptScheme or dcterms:identifier
+        #[clap(long = "infer-name-from", value_name = "SOURCE", num_args = 1..)]
+        name_inference: Vec<String>,
+        /// Define a named subset of the environment, given as
+        /// '<name>=<key>=<value>[;<key>=<value>...]' with the keys dir, include, exclude and
+        /// policy, e.g. 'release=dir=ontologies/release;policy=latest'. Select it with --profile
+        #[clap(long = "define-profile", value_name = "PROFILE", num_args = 1..)]
+        profiles: Vec<String>,
     },
     /// Prints the version of the ontoenv binary
     Version,
     /// Prints the status of the ontology environment
-    Status,
+    Status {
+        /// Print the status as JSON (see `ontoenv schema status`)
+        #[clap(long, action, default_value = "false")]
+        json: bool,
+    },
     /// Update the ontology environment
-    Refresh,
+    #[clap(alias = "update")]
+    Refresh {
+        /// Print a report of the added, updated and removed ontologies as JSON (see `ontoenv
+        /// schema update`)
+        #[clap(long, action, default_value = "false")]
+        json: bool,
+        /// Only refresh the ontologies whose location matches this glob or prefix, e.g.
+        /// 'http://qudt.org/*' or ./models
+        #[clap(long)]
+        only: Option<String>,
+        /// Read every (matching) ontology again, fetching remote ones, not just the files which
+        /// changed
+        #[clap(long, action, default_value = "false")]
+        all: bool,
+        /// With --only, read the matching files whose content changed since they were last read,
+        /// whatever their modification times
+        #[clap(long, action, default_value = "false", requires = "only")]
+        hash_only: bool,
+        /// Fetch the remote ontologies which were fetched more than this many seconds ago, if the
+        /// server reports that they changed. Overrides the cache TTL of the configuration
+        #[clap(long, value_name = "SECONDS", conflicts_with_all = ["only", "all"])]
+        max_age: Option<u64>,
+        /// Print what the refresh would do (files to read, ontologies to remove and remote
+        /// ontologies to fetch) without changing the environment. Cannot be combined with --only
+        #[clap(long, action, default_value = "false", conflicts_with = "only")]
+        dry_run: bool,
+    },
+    /// Watch the search directories and update the environment whenever ontology files are
+    /// added, changed or removed, until interrupted with Ctrl-C
+    Watch {
+        /// Seconds between two checks of the search directories
+        #[clap(long, value_name = "SECONDS", default_value = "1")]
+        interval: f64,
+    },
+    /// Make the environment hold exactly the ontologies recorded in ontoenv.lock, reading or
+    /// fetching again only those whose content does not match the recorded hash
+    Sync {
+        /// Print a report of the added, updated and removed ontologies as JSON (see `ontoenv
+        /// schema update`)
+        #[clap(long, action, default_value = "false")]
+        json: bool,
+    },
+    /// Print the JSON Schema of a machine-readable output, of ontoenv.lock or of change
+    /// manifests, or list the available schemas
+    Schema {
+        /// The name of the schema: status, doctor, update, plan, missing, lockfile or changes
+        name: Option<String>,
+    },
+    /// Write the graph of an ontology, without its imports, to a file
+    Get {
+        /// The name (URI) of the ontology
+        ontology: String,
+        /// Write this version of the ontology instead of the one the resolution policy picks:
+        /// its versionIRI, location, version or (a prefix of) its content hash
+        #[clap(long)]
+        version: Option<String>,
+        #[clap(flatten)]
+        output: OutputArgs,
+        /// The file to write the graph to, defaults to 'output.ttl'
+        destination: Option<String>,
+    },
     /// Compute the owl:imports closure of an ontology and write it to a file
     GetClosure {
         /// The name (URI) of the ontology to compute the closure for
@@ -75,9 +302,125 @@ enum Commands {
         /// Remove owl:imports statements from the closure, defaults to true
         #[clap(long, short, action, default_value = "true")]
         remove_owl_imports: Option<bool>,
-        /// The file to write the closure to, defaults to 'output.ttl'
+        /// Keep triples which occur in several graphs of the closure only once
+        #[clap(long, action, default_value = "false")]
+        deduplicate: bool,
+        /// Include the optional imports of these features (see `optional_imports` in
+        /// .ontoenv/ontoenv.json)
+        #[clap(long = "feature")]
+        features: Vec<String>,
+        /// Include this ontology but not its imports (may be repeated)
+        #[clap(long = "cut-at", value_name = "IRI")]
+        cut_points: Vec<String>,
+        /// Also follow this predicate from the ontologies of the closure, e.g. 'rdfs:seeAlso' or
+        /// 'owl:priorVersion' (may be repeated)
+        #[clap(long = "follow", value_name = "PREDICATE")]
+        predicates: Vec<String>,
+        /// Keep each ontology of the closure in its own named graph and write TriG
+        #[clap(long, action, default_value = "false", conflicts_with = "deduplicate")]
+        named_graphs: bool,
+        /// Assemble the union again instead of reusing the one cached in .ontoenv/cache
+        #[clap(long, action, default_value = "false")]
+        no_cache: bool,
+        /// Print the ontologies of the closure in load order (every ontology after the
+        /// ontologies it imports) instead of writing the closure
+        #[clap(
+            long,
+            action,
+            default_value = "false",
+            conflicts_with_all = ["features", "cut_points", "predicates"]
+        )]
+        order: bool,
+        /// With --order, print one line per layer of ontologies which do not depend on each
+        /// other
+        #[clap(long, action, default_value = "false", requires = "order")]
+        layers: bool,
+        /// Do not declare the prefixes the ontologies of the closure declare in their source
+        /// documents
+        #[clap(long, action, default_value = "false")]
+        no_source_prefixes: bool,
+        /// Record how the closure was resolved (the chosen locations and versions, unresolved
+        /// imports and the environment's fingerprint): 'graph' adds it to the TriG output as the
+        /// named graph urn:ontoenv:resolution (requires --named-graphs), 'json' writes it next
+        /// to the closure as <destination>.resolution.json
+        #[clap(long, value_name = "FORMAT")]
+        resolution_report: Option<String>,
+        #[clap(flatten)]
+        output: OutputArgs,
+        /// The file to write the closure to, defaults to 'output.ttl' ('output.trig' with
+        /// --named-graphs)
+        destination: Option<String>,
+    },
+    /// Generate a JSON-LD context from the prefixes, classes and properties of an ontology's
+    /// imports closure
+    Context {
+        /// The name (URI) of the ontology
+        ontology: String,
+        /// The file to write the context to, defaults to 'context.jsonld'
         destination: Option<String>,
     },
+    /// Generate HTML or Markdown documentation (classes, properties, hierarchy, prefixes) for an
+    /// ontology from the graphs in the environment
+    Docgen {
+        /// The name (URI) of the ontology to document
+        ontology: String,
+        /// The directory to write the documentation to, defaults to 'docs'
+        #[clap(long, short)]
+        out: Option<String>,
+        /// Output format: html or markdown
+        #[clap(long, short, default_value = "html")]
+        format: String,
+        /// Document the union of the ontology's imports closure instead of just the ontology
+        #[clap(long, action, default_value = "false")]
+        closure: bool,
+    },
+    /// Write a summary of the environment (ontologies and versions, missing imports, doctor
+    /// problems and the dependency graph as a mermaid diagram) as Markdown or HTML
+    Report {
+        /// Output format: markdown or html
+        #[clap(long, short, default_value = "markdown")]
+        format: String,
+        /// The file to write the report to; printed to stdout if not given
+        #[clap(long, short)]
+        out: Option<String>,
+    },
+    /// Write versioned release artifacts (full closure, slim closure and reasoned closure) for an
+    /// ontology into a dist directory
+    Release {
+        /// The name (URI) of the ontology to release
+        ontology: String,
+        /// The version of the release, used in the version IRIs
+        version: String,
+        /// The directory to write the artifacts to, defaults to 'dist'
+        #[clap(long, short)]
+        dist: Option<String>,
+        /// Reasoner for the reasoned artifact: rdfs or owlrl
+        #[clap(long, default_value = "rdfs")]
+        reasoner: String,
+        /// Command line of an external reasoner to use instead, with {input} and {output}
+        /// placeholders, e.g. 'robot reason --reasoner ELK --input {input} --output {output}'
+        #[clap(long)]
+        reasoner_command: Option<String>,
+        #[clap(flatten)]
+        output: OutputArgs,
+    },
+    /// Compute the owl:imports closure of an ontology together with the triples a reasoner
+    /// infers from it, and write it to a file
+    Materialize {
+        /// The name (URI) of the ontology to materialize the closure of
+        ontology: String,
+        /// The file to write the materialized closure to, defaults to 'output.ttl'
+        destination: Option<String>,
+        /// Reasoner to use: rdfs or owlrl
+        #[clap(long, default_value = "rdfs")]
+        reasoner: String,
+        /// Command line of an external reasoner to use instead, with {input} and {output}
+        /// placeholders, e.g. 'robot reason --reasoner ELK --input {input} --output {output}'
+        #[clap(long)]
+        reasoner_command: Option<String>,
+        #[clap(flatten)]
+        output: OutputArgs,
+    },
     /// Add an ontology to the environment
     Add {
         /// The URL of the ontology to add
@@ -87,10 +430,131 @@ enum Commands {
         #[clap(long, short)]
         file: Option<String>,
     },
+    /// Remove every version of an ontology from the environment. Fails if other ontologies
+    /// still import it, unless --cascade or --detach is given
+    Remove {
+        /// The name (URI) of the ontology
+        ontology: String,
+        /// Also remove the ontologies which import it, directly or transitively
+        #[clap(long, action, default_value = "false")]
+        cascade: bool,
+        /// Remove only the ontology, leaving the imports of its importers missing
+        #[clap(long, action, default_value = "false", conflicts_with = "cascade")]
+        detach: bool,
+    },
     /// List the ontologies in the environment sorted by name
-    ListOntologies,
+    ListOntologies {
+        /// Print the ontologies as JSON, with their locations and annotations
+        #[clap(long, action, default_value = "false")]
+        json: bool,
+        /// Only list the ontologies with this annotation, given as 'key' or 'key=value'
+        #[clap(long)]
+        annotation: Option<String>,
+        /// Group the ontologies under the directory of their file or the origin of their URL
+        #[clap(
+            long,
+            action,
+            default_value = "false",
+            conflicts_with_all = ["annotation", "duplicates", "orphans"]
+        )]
+        by_location: bool,
+        /// Only list the names declared at several locations, marking the location the
+        /// resolution policy picks with '*'
+        #[clap(
+            long,
+            action,
+            default_value = "false",
+            conflicts_with_all = ["annotation", "orphans"]
+        )]
+        duplicates: bool,
+        /// Only list the ontologies nothing uses: those outside the imports closures of the
+        /// roots (see 'init --root'), or with no roots, those no other ontology imports
+        #[clap(long, action, default_value = "false", conflicts_with = "annotation")]
+        orphans: bool,
+    },
+    /// Attach metadata (e.g. owner=controls-team or status=deprecated) to every version of an
+    /// ontology, or remove it
+    Annotate {
+        /// The name (URI) of the ontology
+        ontology: String,
+        /// The annotations to set, given as 'key=value'
+        annotations: Vec<String>,
+        /// Remove the annotation with this key (may be repeated)
+        #[clap(long = "remove", value_name = "KEY")]
+        remove: Vec<String>,
+    },
     /// List the locations of the ontologies in the environment sorted by location
     ListLocations,
+    /// List the imports which cannot be resolved to an ontology in the environment, along with
+    /// why fetching them failed and when they are retried
+    ListMissing {
+        /// Print the missing imports as JSON (see `ontoenv schema missing`)
+        #[clap(long, action, default_value = "false")]
+        json: bool,
+    },
+    /// Print the ontology which declares a term (a class or property)
+    Which {
+        /// The IRI of the term
+        term: String,
+        /// List every ontology which declares the term
+        #[clap(long, action, default_value = "false")]
+        all: bool,
+    },
+    /// Print the name and location of the ontology the resolution policy picks for a name
+    Resolve {
+        /// The name (URI) of the ontology
+        ontology: String,
+        /// Print the name and location as JSON
+        #[clap(long, action, default_value = "false")]
+        json: bool,
+    },
+    /// Print the file backing an ontology or a term, with the line declaring it, as FILE:LINE
+    /// for editor jump-to-definition
+    WhichFile {
+        /// The IRI of the ontology or term
+        iri: String,
+        /// Print the location as JSON
+        #[clap(long, action, default_value = "false")]
+        json: bool,
+    },
+    /// Print how many graphs and triples of the environment use an IRI as subject, predicate or
+    /// object, e.g. before renaming or deprecating a class or property
+    Usage {
+        /// The IRI of the term
+        iri: String,
+        /// Print the usage as JSON
+        #[clap(long, action, default_value = "false")]
+        json: bool,
+    },
+    /// Suggest which ontologies in the environment a model should import, based on the classes
+    /// and properties it uses, and list the imports it does not use
+    SuggestImports {
+        /// The RDF file containing the model
+        file: PathBuf,
+        /// Print the suggestions as JSON
+        #[clap(long, action, default_value = "false")]
+        json: bool,
+    },
+    /// List the prefixes the ontologies in the environment bind their namespaces to, suggest
+    /// prefixes for the IRIs used in a file, or expand and shrink CURIEs
+    Prefixes {
+        /// Print @prefix declarations for the namespaces used in this RDF file
+        #[clap(long, value_name = "FILE")]
+        suggest: Option<PathBuf>,
+        /// List the environment-wide registry instead: one prefix per namespace, with conflicts
+        /// between the ontologies resolved
+        #[clap(long, action, default_value = "false", conflicts_with = "suggest")]
+        merged: bool,
+        /// Print the IRI a CURIE such as brick:Sensor expands to (may be repeated)
+        #[clap(long, value_name = "CURIE", conflicts_with_all = ["suggest", "merged"])]
+        expand: Vec<String>,
+        /// Print the CURIE an IRI shrinks to (may be repeated)
+        #[clap(long, value_name = "IRI", conflicts_with_all = ["suggest", "merged"])]
+        shrink: Vec<String>,
+        /// Print the prefixes as JSON
+        #[clap(long, action, default_value = "false")]
+        json: bool,
+    },
     // TODO: dump all ontologies; nest by ontology name (sorted), w/n each ontology name list all
     // the places where that graph can be found. List basic stats: the metadata field in the
     // Ontology struct and # of triples in the graph; last updated; etc
@@ -107,42 +571,514 @@ enum Commands {
         /// The output file to write the PDF to, defaults to 'dep_graph.pdf'
         #[clap(long, short)]
         output: Option<String>,
+        /// Print the graph as JSON nodes and edges, including missing imports, instead of
+        /// generating a PDF
+        #[clap(long, action, default_value = "false")]
+        json: bool,
     },
-    /// Lists all ontologies which depend on the given ontology
+    /// Lists all ontologies which depend on the given ontology, whether they import its name or
+    /// the version IRI, prior version or URL of one of its versions
     Dependents {
-        /// The name (URI) of the ontology to find dependents for
+        /// The name (URI) of the ontology to find dependents for, or an IRI of one of its versions
         ontologies: Vec<String>,
+        /// Also print the IRI each dependent imports and how it refers to the ontology
+        #[clap(long, action, default_value = "false")]
+        explain: bool,
+    },
+    /// Show the triples added and removed going from an ontology in the environment to another
+    /// version of it, e.g. its upstream release before refreshing
+    Diff {
+        /// The name (URI) of the ontology
+        ontology: String,
+        /// The file or URL of the other version
+        #[clap(long, value_name = "LOCATION")]
+        against: String,
+        /// Output format: unified (every changed triple) or summary (changes per subject)
+        #[clap(long, short, default_value = "unified")]
+        format: String,
+    },
+    /// Show how the number of direct and transitive dependencies of an ontology changed over time
+    History {
+        /// The name (URI) of the ontology
+        ontology: String,
+    },
+    /// Serve line-delimited JSON-RPC requests on stdin/stdout, e.g. for editor integrations.
+    /// Methods: resolve, closure, missingImports, searchTerms, prefix
+    Rpc,
+    /// Keep the environment open and serve requests from other processes on
+    /// .ontoenv/daemon.sock until stopped. While it runs, `ontoenv refresh`, `resolve`, `get` and
+    /// `get-closure` are done by the daemon
+    #[cfg(unix)]
+    Daemon {
+        /// Stop the running daemon
+        #[clap(long, action, default_value = "false")]
+        stop: bool,
+    },
+    /// Write an XML catalog (as used by Protégé and ROBOT) mapping ontology names to their locations
+    ExportCatalog {
+        /// The file to write the catalog to, defaults to 'catalog-v001.xml'
+        output: Option<String>,
+    },
+    /// Write the flat ontology listing (name, location, imports, last updated) of the original
+    /// ontoenv, for scripts which still read onto.json
+    Export {
+        /// Write the listing as the JSON of the original ontoenv
+        #[clap(long, action, required = true)]
+        legacy_json: bool,
+        /// The file to write the listing to, defaults to 'onto.json'
+        output: Option<String>,
+    },
+    /// Add all ontologies listed in an XML catalog to the environment
+    ImportCatalog {
+        /// The catalog file to read
+        catalog: String,
+    },
+    /// Add all ontologies listed in an inventory to the environment: a DCAT or VoID description,
+    /// an XML catalog, or a CSV ('<iri>,<location>') or YAML ('<iri>: <location>') manifest
+    AddCatalog {
+        /// The URL or file of the inventory
+        location: String,
+    },
+    /// Upload ontologies to a SPARQL Graph Store Protocol endpoint (e.g.
+    /// http://localhost:3030/ds/data), each as a named graph named after the ontology
+    Push {
+        /// The Graph Store Protocol endpoint
+        endpoint: String,
+        /// The names (URIs) of the ontologies to upload; uploads all ontologies if none are given
+        ontologies: Vec<String>,
+        /// Also upload the imports closure of each ontology
+        #[clap(long, action, default_value = "false")]
+        closure: bool,
+        /// Username for HTTP basic authentication
+        #[clap(long)]
+        username: Option<String>,
+        /// Password for HTTP basic authentication
+        #[clap(long)]
+        password: Option<String>,
+        /// Token for bearer authentication
+        #[clap(long)]
+        token: Option<String>,
+    },
+    /// Publish the environment as a bundle to an OCI registry, e.g. ghcr.io/org/ontologies:1.4.
+    /// Credentials are read from ONTOENV_REGISTRY_USERNAME and ONTOENV_REGISTRY_PASSWORD
+    Publish {
+        /// The registry reference to push to
+        reference: String,
+    },
+    /// Pull an environment bundle from an OCI registry into the current directory
+    Pull {
+        /// The registry reference to pull from
+        reference: String,
+        /// Replace an existing environment in the current directory
+        #[clap(long, action, default_value = "false")]
+        recreate: bool,
+    },
+    /// Serve environment metadata (ontologies, imports, versions, problems) as a JSON API over HTTP
+    Serve {
+        /// The address to listen on, defaults to 127.0.0.1:8080
+        #[clap(long, short, default_value = "127.0.0.1:8080")]
+        address: String,
+    },
+    /// List, take and restore snapshots of the environment, e.g. to revert an update of remote
+    /// ontologies which broke a closure
+    Snapshot {
+        #[command(subcommand)]
+        command: SnapshotCommands,
+    },
+    /// Run or install git hooks
+    Hook {
+        #[command(subcommand)]
+        hook: HookCommands,
     },
     /// Run the doctor to check the environment for issues
-    Doctor,
+    Doctor {
+        /// Print the problems as JSON (see `ontoenv schema doctor`)
+        #[clap(long, action, default_value = "false")]
+        json: bool,
+    },
+    /// Check the environment against its import constraints; fails if any are violated
+    Verify,
+    /// Validate the imports closure of an ontology with the SHACL shapes of its imports; fails if
+    /// any node violates a shape
+    #[cfg(feature = "shacl")]
+    Validate {
+        /// The name (URI) of the ontology
+        ontology: String,
+        /// Print the validation report as JSON
+        #[clap(long, action, default_value = "false")]
+        json: bool,
+    },
     /// Reset the ontology environment by removing the .ontoenv directory
     Reset,
 }
 
-fn main() -> Result<()> {
+#[derive(Debug, Subcommand)]
+enum SnapshotCommands {
+    /// List the snapshots recorded by updates and taken with `snapshot create`, oldest first
+    List,
+    /// Record the current ontologies of the environment as a snapshot with the given tag
+    Create {
+        /// The name of the snapshot, e.g. 'before-qudt-upgrade'
+        tag: String,
+    },
+    /// Restore the ontologies of a snapshot, selected by its tag, a prefix of its ID, a
+    /// timestamp or a date
+    Restore {
+        /// The snapshot to restore
+        snapshot: String,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+enum HookCommands {
+    /// Check staged RDF files: parseability, ontology declarations, resolvable imports,
+    /// duplicate ontology names and import constraints
+    PreCommit {
+        /// Install the hook into the current git repository instead of running it
+        #[clap(long, action, default_value = "false")]
+        install: bool,
+        /// Files to check instead of the staged files
+        files: Vec<String>,
+    },
+}
+
+/// How the graphs written by get-closure, materialize and release are laid out
+#[derive(Args, Debug)]
+struct OutputArgs {
+    /// Declare a prefix in the written files, given as 'prefix=namespace' (may be repeated)
+    #[clap(long = "prefix", value_name = "PREFIX=NAMESPACE")]
+    prefixes: Vec<String>,
+    /// Declare the prefixes the ontologies in the environment bind the namespaces of the written
+    /// graph to
+    #[clap(long, action, default_value = "false")]
+    env_prefixes: bool,
+    /// Write the IRIs under this IRI relative to it (through the empty prefix)
+    #[clap(long)]
+    base_iri: Option<String>,
+    /// Write one triple per line with full IRIs, which gives the smallest diffs
+    #[clap(long, action, default_value = "false")]
+    compact: bool,
+}
+
+impl OutputArgs {
+    // the serializer options for a graph written from the environment; --prefix bindings take
+    // precedence over the prefixes of the environment
+    fn serializer_options(&self, env: &OntoEnv, graph: &Graph) -> Result<SerializerOptions> {
+        let mut options = self.serializer_options_without_env()?;
+        if self.env_prefixes {
+            let bound = std::mem::take(&mut options.prefixes);
+            for suggestion in env.suggest_prefixes(graph) {
+                options
+                    .prefixes
+                    .insert(suggestion.prefix, suggestion.namespace);
+            }
+            options.prefixes.extend(bound);
+        }
+        Ok(options)
+    }
+
+    // the serializer options without the prefixes of the environment (--env-prefixes), e.g. for
+    // a graph the daemon returned
+    fn serializer_options_without_env(&self) -> Result<SerializerOptions> {
+        let mut options = SerializerOptions {
+            base_iri: self.base_iri.clone(),
+            compact: self.compact,
+            ..Default::default()
+        };
+        for binding in &self.prefixes {
+            let (prefix, namespace) = binding.split_once('=').ok_or_else(|| {
+                anyhow::anyhow!("Invalid prefix '{}', expected prefix=namespace", binding)
+            })?;
+            options
+                .prefixes
+                .insert(prefix.to_string(), namespace.to_string());
+        }
+        Ok(options)
+    }
+}
+
+// declares the prefixes the source documents of a closure declare, unless other declarations
+// bind their namespaces or the prefixes
+fn add_source_prefixes(options: &mut SerializerOptions, prefixes: BTreeMap<String, String>) {
+    let bound: HashSet<String> = options.prefixes.values().cloned().collect();
+    for (prefix, namespace) in prefixes {
+        if !bound.contains(&namespace) {
+            options.prefixes.entry(prefix).or_insert(namespace);
+        }
+    }
+}
+
+// the daemon serving the environment in the current directory, if one is running. Commands
+// given other environments (--env) or a profile do not use it, since it serves neither
+#[cfg(unix)]
+fn daemon_client(
+    envs: &[PathBuf],
+    profile: Option<&str>,
+) -> Result<Option<ontoenv::daemon::Client>> {
+    if !envs.is_empty() || profile.is_some() {
+        return Ok(None);
+    }
+    ontoenv::daemon::Client::connect(&current_dir()?)
+}
+
+fn make_reasoner(name: &str, command: Option<String>) -> Result<Box<dyn Reasoner>> {
+    match command {
+        Some(command) => Ok(Box::new(ExternalReasoner::from_command_line(&command)?)),
+        None => reasoner::reasoner_by_name(name),
+    }
+}
+
+// writes progress events to stderr as JSON lines, for --progress json
+struct JsonProgress;
+
+impl EnvironmentListener for JsonProgress {
+    fn on_progress(&self, progress: &Progress) {
+        if let Ok(line) = serde_json::to_string(progress) {
+            eprintln!("{}", line);
+        }
+    }
+}
+
+// adds the --progress listener, if any, to the environment
+fn add_progress_listener(env: &mut OntoEnv, progress: Option<&Arc<dyn EnvironmentListener>>) {
+    if let Some(listener) = progress {
+        env.add_listener(listener.clone());
+    }
+}
+
+fn main() {
+    // a panic in the library is a bug, but it should still end in an error message and an exit
+    // code rather than a backtrace
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        // the first panic is the cause; a panic in a worker thread reaches catch_unwind only as
+        // the panic of the thread which joined it
+        if let Ok(mut first) = FIRST_PANIC.lock() {
+            if first.is_none() {
+                let message = panic_message(info.payload());
+                *first = Some(match info.location() {
+                    Some(location) => format!("{} at {}", message, location),
+                    None => message,
+                });
+            }
+        }
+        if std::env::var_os("RUST_BACKTRACE").is_some() {
+            default_hook(info);
+        }
+    }));
+    match std::panic::catch_unwind(run) {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) if e.is::<Cancelled>() => {
+            eprintln!("{}", e);
+            std::process::exit(130);
+        }
+        Ok(Err(e)) => {
+            eprintln!("Error: {:?}", e);
+            std::process::exit(1);
+        }
+        Err(panic) => {
+            let message = FIRST_PANIC
+                .lock()
+                .ok()
+                .and_then(|mut first| first.take())
+                .unwrap_or_else(|| panic_message(panic.as_ref()));
+            eprintln!(
+                "Internal error: {}\nThis is a bug in ontoenv; please report it at https://github.com/gtfierro/ontoenv-rs/issues",
+                message
+            );
+            std::process::exit(2);
+        }
+    }
+}
+
+// the message and location of the first panic, recorded by the panic hook
+static FIRST_PANIC: Mutex<Option<String>> = Mutex::new(None);
+
+fn panic_message(payload: &(dyn Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown error".to_string())
+}
+
+/// Logs go to stderr as text, or as one JSON object per line when ONTOENV_LOG=json. JSON logs
+/// also record when each span (update, scan, fetch, parse, closure) closes and how long it took
+fn init_logging(level: &str) {
+    let filter = EnvFilter::new(level);
+    let subscriber = tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr);
+    if std::env::var("ONTOENV_LOG").is_ok_and(|format| format == "json") {
+        subscriber
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .with_span_events(FmtSpan::CLOSE)
+            .init();
+    } else {
+        subscriber.init();
+    }
+}
+
+/// Opens the environment at path read-only. With --env, returns a view which also resolves
+/// against the other environments
+// loads the environment and selects the profile, or the whole environment without one
+fn load_environment(path: &Path, read_only: bool, profile: Option<&str>) -> Result<OntoEnv> {
+    let mut env = OntoEnv::from_file(path, read_only)?;
+    // switching profiles rebuilds the dependency graph
+    if env.profile() != profile {
+        env.set_profile(profile)?;
+    }
+    Ok(env)
+}
+
+fn open_environment(
+    path: &Path,
+    envs: &[PathBuf],
+    precedence: MergePrecedence,
+    profile: Option<&str>,
+) -> Result<OntoEnv> {
+    let env = load_environment(path, true, profile)?;
+    if envs.is_empty() {
+        return Ok(env);
+    }
+    let others = envs
+        .iter()
+        .map(|dir| OntoEnv::from_file(&dir.join(".ontoenv/ontoenv.json"), true))
+        .collect::<Result<Vec<_>>>()?;
+    let mut all = vec![&env];
+    all.extend(others.iter());
+    OntoEnv::merge_view_with(&all, precedence)
+}
+
+// the dependency graph as {"nodes": [...], "edges": [...]}, restricted to the ontologies
+// reachable from the roots if there are any
+fn dependency_graph_json(env: &OntoEnv, roots: Option<&[GraphIdentifier]>) -> serde_json::Value {
+    let deps = env.dependency_graph();
+    let graph = deps.graph();
+    let reachable: Option<HashSet<NodeIndex>> = roots.map(|roots| {
+        let mut reachable = HashSet::new();
+        for index in roots.iter().filter_map(|root| deps.node_index(root)) {
+            let mut dfs = Dfs::new(graph, index);
+            while let Some(node) = dfs.next(graph) {
+                reachable.insert(node);
+            }
+        }
+        reachable
+    });
+    let included = |index: NodeIndex| reachable.as_ref().map_or(true, |r| r.contains(&index));
+    let name = |node: &DependencyNode| match node {
+        DependencyNode::Ontology(id) => id.name().as_str().to_string(),
+        DependencyNode::Missing(import) => import.as_str().to_string(),
+    };
+    let node_json = |node: &DependencyNode| match node {
+        DependencyNode::Ontology(id) => serde_json::json!({
+            "name": id.name().as_str(),
+            "location": id.location().to_string(),
+        }),
+        DependencyNode::Missing(import) => serde_json::json!({
+            "name": import.as_str(),
+            "missing": true,
+        }),
+    };
+    let nodes: Vec<serde_json::Value> = graph
+        .node_indices()
+        .filter(|index| included(*index))
+        .map(|index| node_json(&graph[index]))
+        .collect();
+    let edges: Vec<serde_json::Value> = graph
+        .edge_indices()
+        .filter_map(|edge| graph.edge_endpoints(edge).map(|ends| (edge, ends)))
+        .filter(|(_, (from, _))| included(*from))
+        .map(|(edge, (from, to))| {
+            serde_json::json!({
+                "from": name(&graph[from]),
+                "to": name(&graph[to]),
+                "import": graph[edge].import.as_str(),
+                "status": graph[edge].status,
+            })
+        })
+        .collect();
+    serde_json::json!({ "nodes": nodes, "edges": edges })
+}
+
+fn run() -> Result<()> {
     let cmd = Cli::parse();
 
     let log_level = if cmd.verbose { "info" } else { "warn" };
     let log_level = if cmd.debug { "debug" } else { log_level };
-    std::env::set_var("RUST_LOG", log_level);
-    env_logger::init();
+    init_logging(log_level);
 
     let policy = cmd.policy.unwrap_or_else(|| "default".to_string());
+    let envs = cmd.envs;
+    let profile = cmd.profile;
+    let progress: Option<Arc<dyn EnvironmentListener>> = cmd
+        .progress
+        .map(|_| Arc::new(JsonProgress) as Arc<dyn EnvironmentListener>);
+    let precedence = MergePrecedence::from_name(&cmd.env_precedence).ok_or(anyhow::anyhow!(
+        "Unknown environment precedence: {}",
+        cmd.env_precedence
+    ))?;
+
+    // the first Ctrl-C cancels the running operation before the next ontology is read, so
+    // the environment is left as it was last saved; a second Ctrl-C exits immediately
+    let token = CancellationToken::new();
+    {
+        let token = token.clone();
+        ctrlc::set_handler(move || {
+            if token.is_cancelled() {
+                std::process::exit(130);
+            }
+            eprintln!("Cancelling; press Ctrl-C again to exit immediately");
+            token.cancel();
+        })?;
+    }
 
     match cmd.command {
         Commands::Init {
             search_directories,
             require_ontology_names,
             strict,
+            strict_parse,
+            strict_missing_imports,
+            strict_network,
+            strict_duplicates,
+            strict_constraints,
+            duplicate_strategy,
             offline,
             includes,
             excludes,
             recreate,
             ontology_list_file,
             no_search,
+            root_ontology,
+            roots,
+            forbid_imports,
+            version_constraints,
+            traversal_predicates,
+            base_iri,
+            reject_relative_iris,
+            formats,
+            import_map,
+            max_file_size,
+            parse_timeout,
+            cache_ttl,
+            follow_symlinks,
+            change_manifest,
+            change_webhook,
+            max_parallel,
+            max_memory,
+            memory_cache_unions,
+            max_downloads,
+            max_cache_size,
+            snapshot_graphs,
+            ignore_trailing_separators,
+            name_inference,
+            profiles,
         } => {
             // if search_directories is empty, use the current directory
-            let config = Config::new(
+            let mut config = Config::new(
                 current_dir()?,
                 search_directories,
                 &includes,
@@ -153,7 +1089,63 @@ fn main() -> Result<()> {
                 policy,
                 no_search,
             )?;
+            config.strict_parse = strict_parse;
+            config.strict_missing_imports = strict_missing_imports;
+            config.strict_network = strict_network;
+            config.strict_duplicates = strict_duplicates;
+            config.strict_constraints = strict_constraints;
+            config.duplicate_strategy = duplicate_strategy;
+            config.import_constraints = forbid_imports
+                .iter()
+                .map(|c| ImportConstraint::from_str(c))
+                .collect::<Result<Vec<_>>>()?;
+            for constraint in version_constraints {
+                config.add_version_constraint_str(&constraint)?;
+            }
+            for predicate in traversal_predicates {
+                config.add_traversal_predicate(&predicate)?;
+            }
+            for root in roots {
+                config.add_root(&root)?;
+            }
+            if let Some(base_iri) = base_iri {
+                config.use_location_base_iri = false;
+                config.default_base_iri = Some(iri::parse_iri(&base_iri)?.into_string());
+            }
+            config.reject_relative_iris = reject_relative_iris;
+            for format in formats {
+                config.add_format_str(&format)?;
+            }
+            for mapping in import_map {
+                config.add_import_mapping_str(&mapping)?;
+            }
+            config.max_file_size = max_file_size;
+            config.parse_timeout_secs = parse_timeout;
+            config.cache_ttl = cache_ttl;
+            config.follow_symlinks = follow_symlinks;
+            config.change_manifest = change_manifest;
+            config.change_webhook = change_webhook;
+            config.max_parallel = max_parallel;
+            config.limits = ResourceLimits {
+                max_memory,
+                memory_cache_unions,
+                max_downloads,
+                max_cache_size,
+            };
+            config.snapshot_graphs = snapshot_graphs;
+            config.ignore_trailing_separators = ignore_trailing_separators;
+            config.name_inference = name_inference
+                .iter()
+                .map(|source| source.parse())
+                .collect::<Result<Vec<NameSource>>>()?;
+            for profile in profiles {
+                let (name, profile) = Profile::from_str(&profile)?;
+                config.profiles.insert(name, profile);
+            }
             let mut env = OntoEnv::new(config, recreate)?;
+            env.set_cancellation_token(Some(token));
+            add_progress_listener(&mut env, progress.as_ref());
+            env.set_profile(profile.as_deref())?;
 
             // if an ontology config file is provided, load it and add the ontologies
             if let Some(file) = ontology_list_file {
@@ -165,6 +1157,17 @@ fn main() -> Result<()> {
             }
 
             env.update()?;
+            if let Some(root) = root_ontology {
+                let named = iri::parse_iri(&root)
+                    .ok()
+                    .and_then(|name| env.get_ontology_by_name(name.as_ref()))
+                    .map(|ontology| ontology.id().location().clone());
+                let location = match named {
+                    Some(location) => location,
+                    None => OntologyLocation::from_str(&root)?,
+                };
+                env.restrict_to_root(location)?;
+            }
             env.save_to_directory()?;
         }
         Commands::Version => {
@@ -174,25 +1177,201 @@ fn main() -> Result<()> {
                 env!("GIT_HASH")
             );
         }
-        Commands::Status => {
+        Commands::Status { json } => {
             // load env from .ontoenv/ontoenv.json
             let path = current_dir()?.join(".ontoenv/ontoenv.json");
-            let env = OntoEnv::from_file(&path, true)?;
+            let env = load_environment(&path, true, profile.as_deref())?;
             let status = env.status()?;
-            // pretty print the status
-            println!("{}", status);
+            if json {
+                println!("{}", serde_json::to_string_pretty(&status)?);
+            } else {
+                // pretty print the status
+                println!("{}", status);
+            }
         }
-        Commands::Refresh => {
+        Commands::Refresh {
+            json,
+            only,
+            all,
+            hash_only,
+            max_age,
+            dry_run,
+        } => {
             // load env from .ontoenv/ontoenv.json
             let path = current_dir()?.join(".ontoenv/ontoenv.json");
-            let mut env = OntoEnv::from_file(&path, false)?;
-            env.update()?;
+            if dry_run {
+                let env = load_environment(&path, true, profile.as_deref())?;
+                let plan = match max_age {
+                    Some(max_age) => env.plan_update_with_max_age(all, Some(max_age))?,
+                    None => env.plan_update(all)?,
+                };
+                if json {
+                    println!("{}", serde_json::to_string_pretty(&plan)?);
+                    return Ok(());
+                }
+                for location in &plan.to_parse {
+                    println!("read   {}", location);
+                }
+                for id in &plan.to_remove {
+                    println!("remove {}", id);
+                }
+                for location in &plan.to_fetch {
+                    println!("fetch  {}", location);
+                }
+                if plan.is_empty() {
+                    println!("Nothing to do");
+                }
+                return Ok(());
+            }
+            // the daemon holds the store open, so it has to do the refresh
+            #[cfg(unix)]
+            if only.is_none() && max_age.is_none() {
+                if let Some(mut client) = ontoenv::daemon::Client::connect(&current_dir()?)? {
+                    let report = client.update(all)?;
+                    if json {
+                        println!("{}", serde_json::to_string_pretty(&report)?);
+                    }
+                    return Ok(());
+                }
+            }
+            let mut env = load_environment(&path, false, profile.as_deref())?;
+            env.set_cancellation_token(Some(token));
+            add_progress_listener(&mut env, progress.as_ref());
+            let report = match only {
+                Some(pattern) => {
+                    let strategy = if all {
+                        RefreshStrategy::All
+                    } else if hash_only {
+                        RefreshStrategy::HashOnly
+                    } else {
+                        RefreshStrategy::Changed
+                    };
+                    env.refresh_matching(&pattern, strategy)?
+                }
+                None => match max_age {
+                    Some(max_age) => env.update_with_max_age(max_age)?,
+                    None => env.update_all(all)?,
+                },
+            };
             env.save_to_directory()?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            }
+        }
+        Commands::Watch { interval } => {
+            let path = current_dir()?.join(".ontoenv/ontoenv.json");
+            let mut env = load_environment(&path, false, profile.as_deref())?;
+            env.set_cancellation_token(Some(token));
+            let options = WatchOptions {
+                interval: Duration::try_from_secs_f64(interval)?,
+            };
+            println!("Watching for changes, press Ctrl-C to stop");
+            env.watch(&options, |env, result| {
+                let report = match result {
+                    Ok(report) => report,
+                    Err(e) => {
+                        eprintln!("Update failed: {:?}", e);
+                        return Ok(());
+                    }
+                };
+                env.save_to_directory()?;
+                for id in &report.added {
+                    println!("added   {}", id);
+                }
+                for id in &report.updated {
+                    println!("updated {}", id);
+                }
+                for id in &report.removed {
+                    println!("removed {}", id);
+                }
+                for failure in &report.failed {
+                    println!("failed  {}: {}", failure.location, failure.error);
+                }
+                Ok(())
+            })?;
+        }
+        Commands::Sync { json } => {
+            let path = current_dir()?.join(".ontoenv/ontoenv.json");
+            let mut env = load_environment(&path, false, profile.as_deref())?;
+            env.set_cancellation_token(Some(token));
+            let report = env.sync_from_lock()?;
+            env.save_to_directory()?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+                return Ok(());
+            }
+            for id in &report.added {
+                println!("added   {}", id);
+            }
+            for id in &report.updated {
+                println!("updated {}", id);
+            }
+            for id in &report.removed {
+                println!("removed {}", id);
+            }
+            for failure in &report.failed {
+                println!("failed  {}: {}", failure.location, failure.error);
+            }
+        }
+        Commands::Schema { name } => match name {
+            Some(name) => {
+                let schema = schema::schema(&name).ok_or(anyhow::anyhow!(
+                    "Unknown schema {}; available schemas: {}",
+                    name,
+                    schema::SCHEMA_NAMES.join(", ")
+                ))?;
+                println!("{}", serde_json::to_string_pretty(&schema)?);
+            }
+            None => {
+                for name in schema::SCHEMA_NAMES {
+                    println!("{}", name);
+                }
+            }
+        },
+        Commands::Get {
+            ontology,
+            version,
+            output,
+            destination,
+        } => {
+            let iri = iri::parse_iri(&ontology)?;
+            let destination =
+                PathBuf::from(destination.unwrap_or_else(|| "output.ttl".to_string()));
+            // the daemon holds the store open, so it has to read the graph
+            #[cfg(unix)]
+            if !output.env_prefixes {
+                if let Some(mut client) = daemon_client(&envs, profile.as_deref())? {
+                    let graph = client.get_graph_version(iri.as_str(), version.as_deref())?;
+                    let options = output.serializer_options_without_env()?;
+                    write_graph_to_file_with(&graph, &destination, &options)?;
+                    return Ok(());
+                }
+            }
+            // load env from .ontoenv/ontoenv.json
+            let path = current_dir()?.join(".ontoenv/ontoenv.json");
+            let env = open_environment(&path, &envs, precedence, profile.as_deref())?;
+            let graph = match &version {
+                Some(version) => env.get_graph_version(iri.as_ref(), version)?,
+                None => env.get_graph_by_name(iri.as_ref())?,
+            };
+            let options = output.serializer_options(&env, &graph)?;
+            write_graph_to_file_with(&graph, &destination, &options)?;
         }
         Commands::GetClosure {
             ontology,
             rewrite_sh_prefixes,
             remove_owl_imports,
+            deduplicate,
+            features,
+            cut_points,
+            predicates,
+            named_graphs,
+            no_cache,
+            order,
+            layers,
+            no_source_prefixes,
+            resolution_report,
+            output,
             destination,
         } => {
             // load env from .ontoenv/ontoenv.json
@@ -203,84 +1382,686 @@ fn main() -> Result<()> {
                     "OntoEnv not found. Run `ontoenv init` to create a new OntoEnv."
                 ));
             }
-            let env = OntoEnv::from_file(&path, true)?;
-
             // make ontology an IRI
-            let iri = NamedNode::new(ontology).map_err(|e| anyhow::anyhow!(e.to_string()))?;
+            let iri = iri::parse_iri(&ontology)?;
+
+            // the daemon holds the store open, so it has to assemble the closure, unless it is
+            // assembled in a way the daemon does not support
+            #[cfg(unix)]
+            if !(order
+                || named_graphs
+                || deduplicate
+                || no_cache
+                || resolution_report.is_some()
+                || output.env_prefixes)
+                && features.is_empty()
+                && cut_points.is_empty()
+                && predicates.is_empty()
+            {
+                if let Some(mut client) = daemon_client(&envs, profile.as_deref())? {
+                    let union =
+                        client.union(iri.as_str(), rewrite_sh_prefixes, remove_owl_imports)?;
+                    for imp in &union.failed_imports {
+                        eprintln!("{}", imp);
+                    }
+                    let mut options = output.serializer_options_without_env()?;
+                    if !no_source_prefixes {
+                        add_source_prefixes(&mut options, union.prefixes);
+                    }
+                    let destination =
+                        PathBuf::from(destination.unwrap_or_else(|| "output.ttl".to_string()));
+                    write_graph_to_file_with(&union.graph, &destination, &options)?;
+                    return Ok(());
+                }
+            }
+            let mut env = open_environment(&path, &envs, precedence, profile.as_deref())?;
+            env.set_cancellation_token(Some(token));
+            add_progress_listener(&mut env, progress.as_ref());
 
             let ont = env
                 .get_ontology_by_name(iri.as_ref())
                 .ok_or(anyhow::anyhow!(format!("Ontology {} not found", iri)))?;
-            let closure = env.get_dependency_closure(ont.id())?;
-            let (graph, _successful, failed_imports) = env.get_union_graph(&closure, rewrite_sh_prefixes, remove_owl_imports)?;
+            let features: Vec<&str> = features.iter().map(String::as_str).collect();
+            let cut_points = cut_points
+                .iter()
+                .map(String::as_str)
+                .map(iri::parse_iri)
+                .collect::<Result<Vec<_>>>()?;
+            let cut_points: Vec<_> = cut_points.iter().map(|iri| iri.as_ref()).collect();
+            let predicates = predicates
+                .iter()
+                .map(String::as_str)
+                .map(config::parse_predicate)
+                .collect::<Result<Vec<_>>>()?;
+            let predicates: Vec<_> = predicates.iter().map(|iri| iri.as_ref()).collect();
+            let resolution_report: Option<ReportFormat> =
+                resolution_report.map(|f| f.parse()).transpose()?;
+            if resolution_report == Some(ReportFormat::Graph) && !named_graphs {
+                return Err(anyhow::anyhow!(
+                    "--resolution-report graph requires --named-graphs"
+                ));
+            }
+            if order {
+                for layer in env.closure_layers(ont.id())? {
+                    let names: Vec<&str> = layer.iter().map(|id| id.name().as_str()).collect();
+                    if layers {
+                        println!("{}", names.join(" "));
+                    } else {
+                        for name in names {
+                            println!("{}", name);
+                        }
+                    }
+                }
+                return Ok(());
+            }
+            let closure =
+                env.get_closure_following(ont.id(), &features, &cut_points, &predicates)?;
+            let (mut graph, _successful, failed_imports) = if named_graphs {
+                env.get_union_dataset(&closure, rewrite_sh_prefixes, remove_owl_imports)?
+            } else if deduplicate {
+                let (graph, successful, failed_imports, duplicates) = env
+                    .get_deduplicated_union_graph(
+                        &closure,
+                        rewrite_sh_prefixes,
+                        remove_owl_imports,
+                    )?;
+                eprintln!("Dropped {} duplicate triples", duplicates);
+                (graph, successful, failed_imports)
+            } else if no_cache {
+                env.get_union_graph(&closure, rewrite_sh_prefixes, remove_owl_imports)?
+            } else {
+                let (graph, failed_imports, _) = env.get_cached_union_graph(
+                    &closure,
+                    rewrite_sh_prefixes,
+                    remove_owl_imports,
+                    false,
+                )?;
+                (graph, closure.clone(), failed_imports)
+            };
             if let Some(failed_imports) = failed_imports {
                 for imp in failed_imports {
                     eprintln!("{}", imp);
                 }
             }
-            // write the graph to a file
-            if let Some(destination) = destination {
-                write_dataset_to_file(&graph, &destination)?;
+            // write the graph to a file, declaring the prefixes of the closure's source documents
+            // unless other declarations bind them
+            let mut options = output.serializer_options(&env, &dataset_to_graph(&graph))?;
+            if !no_source_prefixes {
+                add_source_prefixes(&mut options, env.closure_prefixes(&closure));
+            }
+            let report = match resolution_report {
+                Some(_) => Some(env.resolution_report(&closure)?),
+                None => None,
+            };
+            let destination = if named_graphs {
+                let destination = destination.unwrap_or_else(|| "output.trig".to_string());
+                if let (Some(ReportFormat::Graph), Some(report)) = (resolution_report, &report) {
+                    report.add_to_dataset(&mut graph)?;
+                }
+                write_dataset_to_trig_with(&graph, &destination, &options)?;
+                destination
             } else {
-                write_dataset_to_file(&graph, "output.ttl")?;
+                let destination = destination.unwrap_or_else(|| "output.ttl".to_string());
+                write_dataset_to_file_with(&graph, &destination, &options)?;
+                destination
+            };
+            if let (Some(ReportFormat::Json), Some(report)) = (resolution_report, &report) {
+                let path = resolution::sidecar_path(Path::new(&destination));
+                report.save(&path)?;
+                println!("Wrote resolution report to {}", path.display());
+            }
+        }
+        Commands::Context {
+            ontology,
+            destination,
+        } => {
+            // load env from .ontoenv/ontoenv.json
+            let path = current_dir()?.join(".ontoenv/ontoenv.json");
+            let env = open_environment(&path, &envs, precedence, profile.as_deref())?;
+            let iri = iri::parse_iri(&ontology)?;
+            let ont = env
+                .get_ontology_by_name(iri.as_ref())
+                .ok_or(anyhow::anyhow!(format!("Ontology {} not found", iri)))?;
+            let closure = env.get_dependency_closure(ont.id())?;
+            let context = env.jsonld_context(&closure)?;
+            let destination = destination.unwrap_or_else(|| "context.jsonld".to_string());
+            std::fs::write(&destination, serde_json::to_string_pretty(&context)?)?;
+            println!(
+                "Wrote a context with {} entries to {}",
+                context["@context"].as_object().map_or(0, |c| c.len()),
+                destination
+            );
+        }
+        Commands::Docgen {
+            ontology,
+            out,
+            format,
+            closure,
+        } => {
+            // load env from .ontoenv/ontoenv.json
+            let path = current_dir()?.join(".ontoenv/ontoenv.json");
+            let env = open_environment(&path, &envs, precedence, profile.as_deref())?;
+            let iri = iri::parse_iri(&ontology)?;
+            let ont = env
+                .get_ontology_by_name(iri.as_ref())
+                .ok_or(anyhow::anyhow!(format!("Ontology {} not found", iri)))?;
+            let format: DocFormat = format.parse()?;
+            let out = PathBuf::from(out.unwrap_or_else(|| "docs".to_string()));
+            let doc = docgen::document(&env, ont.id(), closure)?;
+            let written = docgen::write_docs(&doc, format, &out)?;
+            println!(
+                "Documented {} classes and {} properties in {}",
+                doc.classes.len(),
+                doc.properties.len(),
+                written.display()
+            );
+        }
+        Commands::Report { format, out } => {
+            // load env from .ontoenv/ontoenv.json
+            let path = current_dir()?.join(".ontoenv/ontoenv.json");
+            let env = open_environment(&path, &envs, precedence, profile.as_deref())?;
+            let format: DocFormat = format.parse()?;
+            let rendered = report::build(&env)?.render(format);
+            match out {
+                Some(out) => {
+                    std::fs::write(&out, rendered)?;
+                    println!("Wrote environment report to {}", out);
+                }
+                None => print!("{}", rendered),
+            }
+        }
+        Commands::Release {
+            ontology,
+            version,
+            dist,
+            reasoner,
+            reasoner_command,
+            output,
+        } => {
+            // load env from .ontoenv/ontoenv.json
+            let path = current_dir()?.join(".ontoenv/ontoenv.json");
+            let env = open_environment(&path, &envs, precedence, profile.as_deref())?;
+            let iri = iri::parse_iri(&ontology)?;
+            let ont = env
+                .get_ontology_by_name(iri.as_ref())
+                .ok_or(anyhow::anyhow!(format!("Ontology {} not found", iri)))?;
+            let dist = PathBuf::from(dist.unwrap_or_else(|| "dist".to_string()));
+            let mut options = ReleaseOptions::new(&version, dist);
+            options.reasoner = make_reasoner(&reasoner, reasoner_command)?;
+            options.serializer = output.serializer_options(&env, &env.get_graph(ont.id())?)?;
+            for artifact in release::release(&env, ont.id(), &options)? {
+                println!(
+                    "{:<9} {} ({} triples, versionIRI {})",
+                    artifact.kind.to_string(),
+                    artifact.path.display(),
+                    artifact.triples,
+                    artifact.version_iri.as_str()
+                );
             }
         }
+        Commands::Materialize {
+            ontology,
+            destination,
+            reasoner,
+            reasoner_command,
+            output,
+        } => {
+            // load env from .ontoenv/ontoenv.json
+            let path = current_dir()?.join(".ontoenv/ontoenv.json");
+            let env = open_environment(&path, &envs, precedence, profile.as_deref())?;
+            let iri = iri::parse_iri(&ontology)?;
+            let ont = env
+                .get_ontology_by_name(iri.as_ref())
+                .ok_or(anyhow::anyhow!(format!("Ontology {} not found", iri)))?;
+            let reasoner = make_reasoner(&reasoner, reasoner_command)?;
+            let graph = env.materialize(ont.id(), reasoner.as_ref())?;
+            let destination = PathBuf::from(destination.unwrap_or_else(|| "output.ttl".to_string()));
+            let options = output.serializer_options(&env, &graph)?;
+            write_graph_to_file_with(&graph, &destination, &options)?;
+        }
         Commands::Add { url, file } => {
             // load env from .ontoenv/ontoenv.json
             let path = current_dir()?.join(".ontoenv/ontoenv.json");
-            let mut env = OntoEnv::from_file(&path, false)?;
+            let mut env = load_environment(&path, false, profile.as_deref())?;
+            env.set_cancellation_token(Some(token));
+            add_progress_listener(&mut env, progress.as_ref());
 
             let location: OntologyLocation = match (url, file) {
                 (Some(url), None) => OntologyLocation::Url(url),
-                (None, Some(file)) => OntologyLocation::File(PathBuf::from(file)),
+                (None, Some(file)) => OntologyLocation::from_path(Path::new(&file))?,
                 _ => return Err(anyhow::anyhow!("Must specify either --url or --file")),
             };
 
             env.add(location)?;
             env.save_to_directory()?;
         }
-        Commands::ListOntologies => {
+        Commands::Remove {
+            ontology,
+            cascade,
+            detach,
+        } => {
+            let path = current_dir()?.join(".ontoenv/ontoenv.json");
+            let mut env = load_environment(&path, false, profile.as_deref())?;
+            let iri = iri::parse_iri(&ontology)?;
+            let ids: Vec<GraphIdentifier> = env
+                .ontologies()
+                .keys()
+                .filter(|id| id.name() == iri.as_ref())
+                .cloned()
+                .collect();
+            if ids.is_empty() {
+                return Err(anyhow::anyhow!("Ontology {} not found", iri));
+            }
+            let mode = if cascade {
+                RemoveMode::Cascade
+            } else if detach {
+                RemoveMode::Detach
+            } else {
+                RemoveMode::Error
+            };
+            let mut newly_missing = vec![];
+            for id in &ids {
+                // an earlier cascade may have removed it already
+                if !env.ontologies().contains_key(id) {
+                    continue;
+                }
+                let report = env.remove(id, mode)?;
+                for removed in &report.removed {
+                    println!("Removed {}", removed);
+                }
+                newly_missing.extend(report.newly_missing);
+            }
+            env.save_to_directory()?;
+            for import in newly_missing {
+                println!("Import no longer resolves: {}", import);
+            }
+        }
+        Commands::ListOntologies {
+            json,
+            annotation,
+            by_location,
+            duplicates,
+            orphans,
+        } => {
             // load env from .ontoenv/ontoenv.json
             let path = current_dir()?.join(".ontoenv/ontoenv.json");
-            let env = OntoEnv::from_file(&path, true)?;
+            let env = open_environment(&path, &envs, precedence, profile.as_deref())?;
+            if by_location {
+                let sources = env.ontologies_by_source();
+                if json {
+                    let sources: Vec<serde_json::Value> = sources
+                        .iter()
+                        .map(|(source, ids)| {
+                            serde_json::json!({
+                                "source": source,
+                                "ontologies": ids,
+                            })
+                        })
+                        .collect();
+                    println!("{}", serde_json::to_string_pretty(&sources)?);
+                    return Ok(());
+                }
+                for (source, ids) in sources {
+                    println!("{}", source);
+                    for id in ids {
+                        println!("  {} ({})", id.name().as_str(), id.location().as_str());
+                    }
+                }
+                return Ok(());
+            }
+            if duplicates {
+                let duplicates = env.duplicate_names()?;
+                if json {
+                    println!("{}", serde_json::to_string_pretty(&duplicates)?);
+                    return Ok(());
+                }
+                for duplicate in duplicates {
+                    println!("{}", duplicate.name);
+                    for id in duplicate.candidates.iter() {
+                        let marker = if *id == duplicate.selected { "*" } else { " " };
+                        println!("  {} {}", marker, id.location().as_str());
+                    }
+                }
+                return Ok(());
+            }
+            if orphans {
+                let orphans = env.orphans()?;
+                if json {
+                    println!("{}", serde_json::to_string_pretty(&orphans)?);
+                    return Ok(());
+                }
+                for id in orphans {
+                    println!("{} ({})", id.name().as_str(), id.location().as_str());
+                }
+                return Ok(());
+            }
             // print list of ontology URLs from env.onologies.values() sorted alphabetically
-            let mut ontologies: Vec<&GraphIdentifier> = env.ontologies().keys().collect();
-            ontologies.sort_by(|a, b| a.name().cmp(&b.name()));
+            let mut ontologies: Vec<GraphIdentifier> = match &annotation {
+                Some(annotation) => match annotation.split_once('=') {
+                    Some((key, value)) => env.ontologies_with_annotation(key, Some(value)),
+                    None => env.ontologies_with_annotation(annotation, None),
+                },
+                None => env.ontologies().keys().cloned().collect(),
+            };
+            ontologies.sort();
+            if json {
+                let ontologies: Vec<serde_json::Value> = ontologies
+                    .iter()
+                    .map(|id| {
+                        serde_json::json!({
+                            "name": id.name().as_str(),
+                            "location": id.location(),
+                            "annotations": env.annotations(id),
+                        })
+                    })
+                    .collect();
+                println!("{}", serde_json::to_string_pretty(&ontologies)?);
+                return Ok(());
+            }
             ontologies.dedup_by(|a, b| a.name() == b.name());
             for ont in ontologies {
                 println!("{}", ont.name().as_str());
             }
         }
+        Commands::Annotate {
+            ontology,
+            annotations,
+            remove,
+        } => {
+            let path = current_dir()?.join(".ontoenv/ontoenv.json");
+            let mut env = load_environment(&path, false, profile.as_deref())?;
+            let iri = iri::parse_iri(&ontology)?;
+            let ids: Vec<GraphIdentifier> = env
+                .ontologies()
+                .keys()
+                .filter(|id| id.name() == iri.as_ref())
+                .cloned()
+                .collect();
+            if ids.is_empty() {
+                return Err(anyhow::anyhow!("Ontology {} not found", iri));
+            }
+            for id in &ids {
+                for annotation in &annotations {
+                    let (key, value) = annotation.split_once('=').ok_or(anyhow::anyhow!(
+                        "Annotations must be of the form <key>=<value>: {}",
+                        annotation
+                    ))?;
+                    env.set_annotation(id, key.trim(), value.trim())?;
+                }
+                for key in &remove {
+                    env.remove_annotation(id, key)?;
+                }
+            }
+            env.save_to_directory()?;
+        }
         Commands::ListLocations => {
             // load env from .ontoenv/ontoenv.json
             let path = current_dir()?.join(".ontoenv/ontoenv.json");
-            let env = OntoEnv::from_file(&path, true)?;
+            let env = open_environment(&path, &envs, precedence, profile.as_deref())?;
             let mut ontologies: Vec<&GraphIdentifier> = env.ontologies().keys().collect();
             ontologies.sort_by(|a, b| a.location().as_str().cmp(b.location().as_str()));
             for ont in ontologies {
                 println!("{}", ont.location().as_str());
             }
         }
+        Commands::ListMissing { json } => {
+            // load env from .ontoenv/ontoenv.json
+            let path = current_dir()?.join(".ontoenv/ontoenv.json");
+            let env = open_environment(&path, &envs, precedence, profile.as_deref())?;
+            let missing = env.missing_import_details();
+            if json {
+                println!("{}", serde_json::to_string_pretty(&missing)?);
+            } else {
+                for missing in missing {
+                    match missing.failure {
+                        Some(failure) => println!(
+                            "{}: {} ({} attempts, next retry after {})",
+                            missing.import,
+                            failure.error,
+                            failure.attempts,
+                            failure.next_attempt().with_timezone(&chrono::Local)
+                        ),
+                        None => println!("{}", missing.import),
+                    }
+                }
+            }
+        }
+        Commands::Which { term, all } => {
+            // load env from .ontoenv/ontoenv.json
+            let path = current_dir()?.join(".ontoenv/ontoenv.json");
+            let env = open_environment(&path, &envs, precedence, profile.as_deref())?;
+            let term = iri::parse_iri(&term)?;
+            let ids = if all {
+                env.term_index()?
+                    .declaring_ontologies(term.as_ref())
+                    .to_vec()
+            } else {
+                env.defining_ontology(term.as_ref()).into_iter().collect()
+            };
+            if ids.is_empty() {
+                return Err(anyhow::anyhow!(
+                    "No ontology in the environment declares {}",
+                    term
+                ));
+            }
+            for id in ids {
+                println!("{} ({})", id.name().as_str(), id.location());
+            }
+        }
+        Commands::Resolve { ontology, json } => {
+            let iri = iri::parse_iri(&ontology)?;
+            #[cfg(unix)]
+            let resolved = match daemon_client(&envs, profile.as_deref())? {
+                Some(mut client) => Some(client.resolve(iri.as_str())?),
+                None => None,
+            };
+            #[cfg(not(unix))]
+            let resolved = None;
+            let resolved = match resolved {
+                Some(resolved) => resolved,
+                None => {
+                    // load env from .ontoenv/ontoenv.json
+                    let path = current_dir()?.join(".ontoenv/ontoenv.json");
+                    let env = open_environment(&path, &envs, precedence, profile.as_deref())?;
+                    env.get_ontology_by_name(iri.as_ref()).map(|ontology| {
+                        (
+                            ontology.name().as_str().to_string(),
+                            ontology.id().location().to_string(),
+                        )
+                    })
+                }
+            };
+            let (name, location) =
+                resolved.ok_or_else(|| anyhow::anyhow!("Ontology {} not found", iri))?;
+            if json {
+                let resolved = serde_json::json!({"name": name, "location": location});
+                println!("{}", serde_json::to_string_pretty(&resolved)?);
+            } else {
+                println!("{} ({})", name, location);
+            }
+        }
+        Commands::WhichFile { iri, json } => {
+            // load env from .ontoenv/ontoenv.json
+            let path = current_dir()?.join(".ontoenv/ontoenv.json");
+            let env = open_environment(&path, &envs, precedence, profile.as_deref())?;
+            let iri = iri::parse_iri(&iri)?;
+            let source = env.which_file(iri.as_ref())?.ok_or_else(|| {
+                anyhow::anyhow!(
+                    "No ontology in the environment is named or declares {}",
+                    iri
+                )
+            })?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&source)?);
+            } else {
+                match (&source.file, source.line) {
+                    (Some(file), Some(line)) => println!("{}:{}", file.display(), line),
+                    (Some(file), None) => println!("{}", file.display()),
+                    (None, _) => println!("{}", source.location),
+                }
+            }
+        }
+        Commands::Usage { iri, json } => {
+            // load env from .ontoenv/ontoenv.json
+            let path = current_dir()?.join(".ontoenv/ontoenv.json");
+            let env = open_environment(&path, &envs, precedence, profile.as_deref())?;
+            let iri = iri::parse_iri(&iri)?;
+            let usage = env.term_usage(iri.as_ref())?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&usage)?);
+            } else {
+                println!(
+                    "{} triples in {} graphs ({} as subject, {} as predicate, {} as object)",
+                    usage.triples,
+                    usage.graphs.len(),
+                    usage.as_subject,
+                    usage.as_predicate,
+                    usage.as_object
+                );
+                for graph in &usage.graphs {
+                    println!(
+                        "{:>6}  {} ({})",
+                        graph.triples,
+                        graph.ontology.name().as_str(),
+                        graph.ontology.location()
+                    );
+                }
+            }
+        }
+        Commands::SuggestImports { file, json } => {
+            // load env from .ontoenv/ontoenv.json
+            let path = current_dir()?.join(".ontoenv/ontoenv.json");
+            let env = open_environment(&path, &envs, precedence, profile.as_deref())?;
+            let graph = read_file(&file)?;
+            let missing = env.suggest_imports(&graph)?;
+            let unused = env.unused_imports(&graph)?;
+            if json {
+                let suggestions = serde_json::json!({
+                    "missing": missing.iter().map(|name| name.as_str()).collect::<Vec<_>>(),
+                    "unused": unused.iter().map(|name| name.as_str()).collect::<Vec<_>>(),
+                });
+                println!("{}", serde_json::to_string_pretty(&suggestions)?);
+            } else {
+                for name in missing {
+                    println!("import {}", name.as_str());
+                }
+                for name in unused {
+                    println!("unused {}", name.as_str());
+                }
+            }
+        }
+        Commands::Prefixes {
+            suggest,
+            merged,
+            expand,
+            shrink,
+            json,
+        } => {
+            // load env from .ontoenv/ontoenv.json
+            let path = current_dir()?.join(".ontoenv/ontoenv.json");
+            let env = open_environment(&path, &envs, precedence, profile.as_deref())?;
+            if !expand.is_empty() || !shrink.is_empty() {
+                let registry = env.prefixes();
+                let mut results = serde_json::Map::new();
+                for curie in &expand {
+                    let iri = registry.expand(curie);
+                    if !json {
+                        println!("{} {}", curie, iri.as_deref().unwrap_or("(unknown prefix)"));
+                    }
+                    results.insert(curie.clone(), serde_json::json!(iri));
+                }
+                for iri in &shrink {
+                    let curie = registry.shrink(iri);
+                    if !json {
+                        println!("{} {}", iri, curie.as_deref().unwrap_or("(no namespace)"));
+                    }
+                    results.insert(iri.clone(), serde_json::json!(curie));
+                }
+                if json {
+                    println!("{}", serde_json::to_string_pretty(&results)?);
+                }
+                return Ok(());
+            }
+            if merged {
+                let registry = env.prefixes();
+                if json {
+                    println!("{}", serde_json::to_string_pretty(registry.prefixes())?);
+                } else {
+                    for (prefix, namespace) in registry.prefixes() {
+                        println!("{}: {}", prefix, namespace);
+                    }
+                }
+                return Ok(());
+            }
+            match suggest {
+                Some(file) => {
+                    let graph = read_file(&file)?;
+                    let suggestions = env.suggest_prefixes(&graph);
+                    if json {
+                        println!("{}", serde_json::to_string_pretty(&suggestions)?);
+                    } else {
+                        for suggestion in suggestions {
+                            println!(
+                                "@prefix {}: <{}> .",
+                                suggestion.prefix, suggestion.namespace
+                            );
+                        }
+                    }
+                }
+                None => {
+                    let usage = env.prefix_usage();
+                    let mut bindings = vec![];
+                    for namespace in usage.namespaces() {
+                        for (prefix, ontologies) in usage.prefixes(namespace) {
+                            if !json {
+                                println!("{}: {} ({} ontologies)", prefix, namespace, ontologies);
+                            }
+                            bindings.push(serde_json::json!({
+                                "prefix": prefix,
+                                "namespace": namespace,
+                                "ontologies": ontologies,
+                            }));
+                        }
+                    }
+                    if json {
+                        println!("{}", serde_json::to_string_pretty(&bindings)?);
+                    }
+                }
+            }
+        }
         Commands::Dump { contains } => {
             // load env from .ontoenv/ontoenv.json
             let path = current_dir()?.join(".ontoenv/ontoenv.json");
-            let env = OntoEnv::from_file(&path, true)?;
-            env.dump(contains.as_deref());
+            let env = open_environment(&path, &envs, precedence, profile.as_deref())?;
+            env.dump(contains.as_deref())?;
         }
-        Commands::DepGraph { roots, output } => {
+        Commands::DepGraph {
+            roots,
+            output,
+            json,
+        } => {
             // load env from .ontoenv/ontoenv.json
             let path = current_dir()?.join(".ontoenv/ontoenv.json");
-            let env = OntoEnv::from_file(&path, true)?;
+            let env = open_environment(&path, &envs, precedence, profile.as_deref())?;
+            let roots: Option<Vec<GraphIdentifier>> = roots
+                .map(|roots| {
+                    roots
+                        .iter()
+                        .map(|name| {
+                            let iri = iri::parse_iri(name)?;
+                            env.get_ontology_by_name(iri.as_ref())
+                                .map(|o| o.id().clone())
+                                .ok_or(anyhow::anyhow!("Ontology {} not found", iri))
+                        })
+                        .collect::<Result<_>>()
+                })
+                .transpose()?;
+            if json {
+                let graph = dependency_graph_json(&env, roots.as_deref());
+                println!("{}", serde_json::to_string_pretty(&graph)?);
+                return Ok(());
+            }
             let dot = if let Some(roots) = roots {
-                let roots: Vec<GraphIdentifier> = roots
-                    .iter()
-                    .map(|iri| {
-                        env.get_ontology_by_name(NamedNodeRef::new(iri).unwrap())
-                            .unwrap()
-                            .id()
-                            .clone()
-                    })
-                    .collect();
                 env.rooted_dep_graph_to_dot(roots)?
             } else {
                 env.dep_graph_to_dot()?
@@ -299,24 +2080,332 @@ fn main() -> Result<()> {
                 ));
             }
         }
-        Commands::Dependents { ontologies } => {
+        Commands::Dependents {
+            ontologies,
+            explain,
+        } => {
             // load env from .ontoenv/ontoenv.json
             let path = current_dir()?.join(".ontoenv/ontoenv.json");
-            let env = OntoEnv::from_file(&path, true)?;
+            let env = open_environment(&path, &envs, precedence, profile.as_deref())?;
             for ont in ontologies {
-                let iri = NamedNode::new(ont).map_err(|e| anyhow::anyhow!(e.to_string()))?;
-                let dependents = env.get_dependents(&iri)?;
+                let iri = iri::parse_iri(&ont)?;
                 println!("Dependents of {}: ", iri);
+                if explain {
+                    for reference in env.explain_import(iri.as_ref()) {
+                        println!(
+                            "{} imports {} by its {}",
+                            reference.importer, reference.import, reference.matched
+                        );
+                    }
+                    continue;
+                }
+                let dependents = env.get_dependents(&iri)?;
                 for dep in dependents {
                     println!("{}", dep);
                 }
             }
         }
-        Commands::Doctor => {
+        Commands::Diff {
+            ontology,
+            against,
+            format,
+        } => {
+            // load env from .ontoenv/ontoenv.json
+            let path = current_dir()?.join(".ontoenv/ontoenv.json");
+            let env = open_environment(&path, &envs, precedence, profile.as_deref())?;
+            let iri = iri::parse_iri(&ontology)?;
+            let ont = env
+                .get_ontology_by_name(iri.as_ref())
+                .ok_or(anyhow::anyhow!(format!("Ontology {} not found", iri)))?;
+            let format: DiffFormat = format.parse()?;
+            let location = OntologyLocation::from_str(&against)?;
+            let diff = env.diff_against(ont.id(), &location)?;
+            let old = ont.id().location().as_str();
+            print!("{}", diff.render(format, old, location.as_str()));
+        }
+        Commands::History { ontology } => {
+            // load env from .ontoenv/ontoenv.json
+            let path = current_dir()?.join(".ontoenv/ontoenv.json");
+            let env = load_environment(&path, true, profile.as_deref())?;
+            let iri = iri::parse_iri(&ontology)?;
+            let history = env.dependency_history(iri.as_ref())?;
+            if history.is_empty() {
+                println!("No dependency history recorded for {}", iri);
+                return Ok(());
+            }
+            println!("{:<26} {:>8} {:>10}  location", "timestamp", "direct", "transitive");
+            for (timestamp, count) in history {
+                println!(
+                    "{:<26} {:>8} {:>10}  {}",
+                    timestamp.format("%Y-%m-%d %H:%M:%S %Z"),
+                    count.direct,
+                    count.transitive,
+                    count.location
+                );
+            }
+        }
+        Commands::Rpc => {
+            // load env from .ontoenv/ontoenv.json
+            let path = current_dir()?.join(".ontoenv/ontoenv.json");
+            let env = open_environment(&path, &envs, precedence, profile.as_deref())?;
+            ontoenv::rpc::serve(&env, std::io::stdin().lock(), std::io::stdout().lock())?;
+        }
+        #[cfg(unix)]
+        Commands::Daemon { stop } => {
+            if stop {
+                match ontoenv::daemon::Client::connect(&current_dir()?)? {
+                    Some(mut client) => client.shutdown()?,
+                    None => println!("No daemon is running"),
+                }
+                return Ok(());
+            }
+            let path = current_dir()?.join(".ontoenv/ontoenv.json");
+            let mut env = load_environment(&path, false, profile.as_deref())?;
+            println!(
+                "Serving environment on {}",
+                ontoenv::daemon::socket_path(&current_dir()?).display()
+            );
+            ontoenv::daemon::serve(&mut env)?;
+        }
+        Commands::ExportCatalog { output } => {
+            // load env from .ontoenv/ontoenv.json
+            let path = current_dir()?.join(".ontoenv/ontoenv.json");
+            let env = open_environment(&path, &envs, precedence, profile.as_deref())?;
+            let output = output.unwrap_or_else(|| "catalog-v001.xml".to_string());
+            env.export_catalog_xml(&PathBuf::from(output))?;
+        }
+        Commands::Export {
+            legacy_json: _,
+            output,
+        } => {
+            // load env from .ontoenv/ontoenv.json
+            let path = current_dir()?.join(".ontoenv/ontoenv.json");
+            let env = open_environment(&path, &envs, precedence, profile.as_deref())?;
+            let output = output.unwrap_or_else(|| legacy::LEGACY_MANIFEST_NAME.to_string());
+            legacy::write_legacy_manifest(&PathBuf::from(output), &env.export_legacy_manifest())?;
+        }
+        Commands::ImportCatalog { catalog } => {
+            // load env from .ontoenv/ontoenv.json
+            let path = current_dir()?.join(".ontoenv/ontoenv.json");
+            let mut env = load_environment(&path, false, profile.as_deref())?;
+            let ids = env.import_catalog_xml(&PathBuf::from(catalog))?;
+            env.save_to_directory()?;
+            println!("Added {} ontologies from catalog", ids.len());
+        }
+        Commands::AddCatalog { location } => {
+            // load env from .ontoenv/ontoenv.json
+            let path = current_dir()?.join(".ontoenv/ontoenv.json");
+            let mut env = load_environment(&path, false, profile.as_deref())?;
+            env.set_cancellation_token(Some(token));
+            add_progress_listener(&mut env, progress.as_ref());
+            let ids = env.add_catalog(OntologyLocation::from_str(&location)?)?;
+            env.save_to_directory()?;
+            println!("Added {} ontologies from catalog", ids.len());
+        }
+        Commands::Push {
+            endpoint,
+            ontologies,
+            closure,
+            username,
+            password,
+            token,
+        } => {
             // load env from .ontoenv/ontoenv.json
             let path = current_dir()?.join(".ontoenv/ontoenv.json");
-            let env = OntoEnv::from_file(&path, true)?;
-            env.doctor();
+            let env = open_environment(&path, &envs, precedence, profile.as_deref())?;
+            let auth = match (username, token) {
+                (Some(username), _) => SparqlAuth::Basic { username, password },
+                (None, Some(token)) => SparqlAuth::Bearer(token),
+                (None, None) => SparqlAuth::None,
+            };
+            let graphs: Vec<GraphIdentifier> = if ontologies.is_empty() {
+                env.graph_ids()
+            } else {
+                ontologies
+                    .iter()
+                    .map(|name| {
+                        let iri = iri::parse_iri(name)?;
+                        env.get_ontology_by_name(iri.as_ref())
+                            .map(|o| o.id().clone())
+                            .ok_or(anyhow::anyhow!("Ontology {} not found", iri))
+                    })
+                    .collect::<Result<_>>()?
+            };
+            let pushed = env.push_to_sparql_endpoint(&endpoint, &auth, &graphs, closure)?;
+            for id in pushed.iter() {
+                println!("{}", id.name());
+            }
+            println!("Uploaded {} graphs to {}", pushed.len(), endpoint);
+        }
+        Commands::Publish { reference } => {
+            // load env from .ontoenv/ontoenv.json
+            let path = current_dir()?.join(".ontoenv/ontoenv.json");
+            let env = load_environment(&path, true, profile.as_deref())?;
+            let reference = Reference::parse(&reference)?;
+            let digest = oci::push(&reference, &env.to_bundle()?)?;
+            println!("Published {} ({})", reference, digest);
+        }
+        Commands::Pull { reference, recreate } => {
+            let reference = Reference::parse(&reference)?;
+            let files = oci::pull(&reference)?;
+            let env = OntoEnv::from_bundle(&current_dir()?, &files, recreate)?;
+            println!(
+                "Pulled {} ontologies from {}",
+                env.ontologies().len(),
+                reference
+            );
+        }
+        Commands::Serve { address } => {
+            // load env from .ontoenv/ontoenv.json
+            let path = current_dir()?.join(".ontoenv/ontoenv.json");
+            let env = open_environment(&path, &envs, precedence, profile.as_deref())?;
+            println!("Serving environment metadata on http://{}/api", address);
+            ontoenv::server::serve(&env, &address)?;
+        }
+        Commands::Snapshot { command } => {
+            // load env from .ontoenv/ontoenv.json
+            let path = current_dir()?.join(".ontoenv/ontoenv.json");
+            match command {
+                SnapshotCommands::List => {
+                    let env = load_environment(&path, true, profile.as_deref())?;
+                    for snapshot in env.snapshots()? {
+                        println!(
+                            "{}  {}  {:>4} ontologies  {}",
+                            &snapshot.id[..snapshot.id.len().min(12)],
+                            snapshot.timestamp.format("%Y-%m-%d %H:%M:%S %Z"),
+                            snapshot.ontologies.len(),
+                            snapshot.tag.as_deref().unwrap_or("")
+                        );
+                    }
+                }
+                SnapshotCommands::Create { tag } => {
+                    let env = load_environment(&path, true, profile.as_deref())?;
+                    let snapshot = env.snapshot(&tag)?;
+                    println!("Recorded snapshot {} ({})", tag, snapshot.id);
+                }
+                SnapshotCommands::Restore { snapshot } => {
+                    let mut env = load_environment(&path, false, profile.as_deref())?;
+                    let report = env.rollback(&snapshot)?;
+                    env.save_to_directory()?;
+                    for id in &report.added {
+                        println!("added   {}", id);
+                    }
+                    for id in &report.updated {
+                        println!("updated {}", id);
+                    }
+                    for id in &report.removed {
+                        println!("removed {}", id);
+                    }
+                }
+            }
+        }
+        Commands::Hook { hook } => match hook {
+            HookCommands::PreCommit { install, files } => {
+                if install {
+                    let path = ontoenv::precommit::install_hook(&current_dir()?)?;
+                    println!("Installed pre-commit hook at {}", path.display());
+                    return Ok(());
+                }
+                // load env from .ontoenv/ontoenv.json
+                let path = current_dir()?.join(".ontoenv/ontoenv.json");
+                let env = load_environment(&path, true, profile.as_deref())?;
+                // without explicit files, check what is about to be committed
+                let staged = files.is_empty();
+                let files: Vec<PathBuf> = if staged {
+                    ontoenv::precommit::staged_files(&current_dir()?)?
+                } else {
+                    files.into_iter().map(PathBuf::from).collect()
+                };
+                let files: Vec<PathBuf> = files
+                    .into_iter()
+                    .filter(|f| env.is_included(f))
+                    .collect();
+                let problems = if staged {
+                    ontoenv::precommit::check_staged_files(&env, &files)?
+                } else {
+                    ontoenv::precommit::check_files(&env, &files)?
+                };
+                for problem in problems.iter() {
+                    println!("Problem: {}", problem.message);
+                    for location in problem.locations.iter() {
+                        println!("  - {}", location);
+                    }
+                }
+                if !problems.is_empty() {
+                    return Err(anyhow::anyhow!(
+                        "{} problem(s) in {} checked file(s)",
+                        problems.len(),
+                        files.len()
+                    ));
+                }
+            }
+        },
+        Commands::Doctor { json } => {
+            // load env from .ontoenv/ontoenv.json
+            let path = current_dir()?.join(".ontoenv/ontoenv.json");
+            let env = open_environment(&path, &envs, precedence, profile.as_deref())?;
+            if json {
+                let report = DoctorReport {
+                    problems: env.problems()?,
+                };
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                env.doctor()?;
+            }
+        }
+        Commands::Verify => {
+            // load env from .ontoenv/ontoenv.json
+            let path = current_dir()?.join(".ontoenv/ontoenv.json");
+            let env = load_environment(&path, true, profile.as_deref())?;
+            let violations = env.check_import_constraints()?;
+            for violation in violations.iter() {
+                println!("{}", violation);
+            }
+            if !violations.is_empty() {
+                return Err(anyhow::anyhow!(
+                    "{} import constraint violation(s)",
+                    violations.len()
+                ));
+            }
+        }
+        #[cfg(feature = "shacl")]
+        Commands::Validate { ontology, json } => {
+            // load env from .ontoenv/ontoenv.json
+            let path = current_dir()?.join(".ontoenv/ontoenv.json");
+            let env = open_environment(&path, &envs, precedence, profile.as_deref())?;
+            let iri = iri::parse_iri(&ontology)?;
+            let ont = env
+                .get_ontology_by_name(iri.as_ref())
+                .ok_or(anyhow::anyhow!(format!("Ontology {} not found", iri)))?;
+            let report = env.validate(ont.id())?;
+            if json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                for result in report.results.iter() {
+                    println!("{}", result);
+                }
+                for skipped in report.skipped.iter() {
+                    println!("{}", skipped);
+                }
+                println!(
+                    "{} violation(s), {} warning(s), {} info, {} skipped constraint(s)",
+                    report.count(Severity::Violation),
+                    report.count(Severity::Warning),
+                    report.count(Severity::Info),
+                    report.skipped.len()
+                );
+            }
+            let violations = report.count(Severity::Violation);
+            if violations > 0 {
+                return Err(anyhow::anyhow!("{} SHACL violation(s)", violations));
+            }
+            // the data was not fully checked, so it cannot be reported as valid
+            if !report.skipped.is_empty() {
+                return Err(anyhow::anyhow!(
+                    "{} SHACL constraint(s) are not supported and were skipped",
+                    report.skipped.len()
+                ));
+            }
         }
         Commands::Reset => {
             // remove .ontoenv directory
@@ -0,0 +1,110 @@
+#ifndef ONTOENV_H
+#define ONTOENV_H
+
+/* Generated with cbindgen; regenerate with the command in cbindgen.toml */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+typedef enum OntoEnvStatus {
+  OntoEnvStatus_Ok = 0,
+  OntoEnvStatus_Error = 1,
+  OntoEnvStatus_NotFound = 2,
+  OntoEnvStatus_InvalidArgument = 3,
+  OntoEnvStatus_Panic = 4,
+} OntoEnvStatus;
+
+// An open environment
+typedef struct OntoEnvHandle OntoEnvHandle;
+
+// An array of strings owned by the caller; release with `ontoenv_string_array_free`
+typedef struct OntoEnvStringArray {
+  char **items;
+  uintptr_t len;
+} OntoEnvStringArray;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Returns the version of the library. The string is static and must not be freed.
+const char *ontoenv_version(void);
+
+// Returns the message of the last error on this thread, or null if the last call succeeded.
+// The string is owned by the library and valid until the next call on this thread.
+const char *ontoenv_last_error_message(void);
+
+// Opens the environment in `<root>/.ontoenv` and writes its handle to `out`.
+//
+// # Safety
+// `root` must be a valid NUL-terminated string and `out` a valid pointer.
+OntoEnvStatus ontoenv_open(const char *root, bool read_only, OntoEnvHandle **out);
+
+// Closes the environment and releases the handle. Null handles are ignored.
+//
+// # Safety
+// `env` must be null or a handle returned by `ontoenv_open` which has not been closed.
+void ontoenv_close(OntoEnvHandle *env);
+
+// Refreshes the environment from its search directories and saves it.
+//
+// # Safety
+// `env` must be a handle returned by `ontoenv_open` with `read_only` false.
+OntoEnvStatus ontoenv_update(OntoEnvHandle *env);
+
+// Writes the names of all ontologies in the environment, sorted, to `out`.
+//
+// # Safety
+// `env` must be a valid handle and `out` a valid pointer.
+OntoEnvStatus ontoenv_list_ontologies(const OntoEnvHandle *env, OntoEnvStringArray *out);
+
+// Writes the names of the ontologies in the imports closure of `iri` to `out`, starting with
+// `iri` itself.
+//
+// # Safety
+// `env` must be a valid handle, `iri` a valid NUL-terminated string and `out` a valid pointer.
+OntoEnvStatus ontoenv_closure(const OntoEnvHandle *env,
+                              const char *iri,
+                              OntoEnvStringArray *out);
+
+// Serializes the graph of the ontology `iri` and writes it to `out`. `format` is one of
+// "turtle", "ntriples", "rdfxml" or a media type.
+//
+// # Safety
+// `env` must be a valid handle, `iri` and `format` valid NUL-terminated strings and `out` a
+// valid pointer.
+OntoEnvStatus ontoenv_get_graph_serialized(const OntoEnvHandle *env,
+                                           const char *iri,
+                                           const char *format,
+                                           char **out);
+
+// Serializes the union of the imports closure of `iri`, with owl:imports removed and
+// sh:prefixes rewritten to `iri`, and writes it to `out`.
+//
+// # Safety
+// `env` must be a valid handle, `iri` and `format` valid NUL-terminated strings and `out` a
+// valid pointer.
+OntoEnvStatus ontoenv_get_closure_serialized(const OntoEnvHandle *env,
+                                             const char *iri,
+                                             const char *format,
+                                             char **out);
+
+// Releases a string returned by the library. Null strings are ignored.
+//
+// # Safety
+// `s` must be null or a string returned by the library which has not been freed.
+void ontoenv_string_free(char *s);
+
+// Releases the strings of an array returned by the library and resets it to empty.
+//
+// # Safety
+// `array` must be null or point to an array filled by the library which has not been freed.
+void ontoenv_string_array_free(OntoEnvStringArray *array);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif // ONTOENV_H
@@ -0,0 +1,376 @@
+//! C API for ontoenv.
+//!
+//! Conventions:
+//! - every fallible function returns an `OntoEnvStatus`; on anything other than `Ok` the
+//!   message is available from `ontoenv_last_error_message` on the same thread
+//! - results are written to out-parameters, which are only written on success
+//! - strings and string arrays returned by the library are owned by the caller and must be
+//!   released with `ontoenv_string_free` and `ontoenv_string_array_free`
+//! - environments are opaque handles released with `ontoenv_close`
+//! - panics never cross the FFI boundary; they are reported as `Panic`
+
+use anyhow::Result;
+use ontoenv::iri::parse_iri;
+use ontoenv::OntoEnv;
+use oxigraph::io::{RdfFormat, RdfSerializer};
+use oxigraph::model::Graph;
+use std::cell::RefCell;
+use std::ffi::{c_char, CStr, CString};
+use std::fmt::Display;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::path::PathBuf;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OntoEnvStatus {
+    Ok = 0,
+    Error = 1,
+    NotFound = 2,
+    InvalidArgument = 3,
+    Panic = 4,
+}
+
+/// An open environment
+pub struct OntoEnvHandle {
+    env: OntoEnv,
+}
+
+/// An array of strings owned by the caller; release with `ontoenv_string_array_free`
+#[repr(C)]
+pub struct OntoEnvStringArray {
+    pub items: *mut *mut c_char,
+    pub len: usize,
+}
+
+struct FfiError {
+    status: OntoEnvStatus,
+    message: String,
+}
+
+impl FfiError {
+    fn new(status: OntoEnvStatus, message: impl Display) -> Self {
+        FfiError {
+            status,
+            message: message.to_string(),
+        }
+    }
+}
+
+impl From<anyhow::Error> for FfiError {
+    fn from(e: anyhow::Error) -> Self {
+        FfiError::new(OntoEnvStatus::Error, e)
+    }
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: &str) {
+    let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|e| *e.borrow_mut() = Some(message));
+}
+
+/// Runs the body, converting errors and panics into a status and the thread's last error
+fn guard(body: impl FnOnce() -> Result<(), FfiError>) -> OntoEnvStatus {
+    match catch_unwind(AssertUnwindSafe(body)) {
+        Ok(Ok(())) => {
+            LAST_ERROR.with(|e| *e.borrow_mut() = None);
+            OntoEnvStatus::Ok
+        }
+        Ok(Err(e)) => {
+            set_last_error(&e.message);
+            e.status
+        }
+        Err(_) => {
+            set_last_error("ontoenv panicked");
+            OntoEnvStatus::Panic
+        }
+    }
+}
+
+unsafe fn str_arg<'a>(s: *const c_char, name: &str) -> Result<&'a str, FfiError> {
+    if s.is_null() {
+        return Err(FfiError::new(
+            OntoEnvStatus::InvalidArgument,
+            format!("{} is null", name),
+        ));
+    }
+    CStr::from_ptr(s).to_str().map_err(|e| {
+        FfiError::new(
+            OntoEnvStatus::InvalidArgument,
+            format!("{} is not valid UTF-8: {}", name, e),
+        )
+    })
+}
+
+unsafe fn env_arg<'a>(env: *const OntoEnvHandle) -> Result<&'a OntoEnv, FfiError> {
+    env.as_ref()
+        .map(|handle| &handle.env)
+        .ok_or(FfiError::new(OntoEnvStatus::InvalidArgument, "env is null"))
+}
+
+fn check_out<T>(out: *mut T) -> Result<(), FfiError> {
+    if out.is_null() {
+        return Err(FfiError::new(OntoEnvStatus::InvalidArgument, "out is null"));
+    }
+    Ok(())
+}
+
+fn to_c_string(s: String) -> Result<*mut c_char, FfiError> {
+    CString::new(s)
+        .map(|s| s.into_raw())
+        .map_err(|e| FfiError::new(OntoEnvStatus::Error, e))
+}
+
+fn to_string_array(strings: Vec<String>) -> Result<OntoEnvStringArray, FfiError> {
+    let strings = strings
+        .into_iter()
+        .map(CString::new)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| FfiError::new(OntoEnvStatus::Error, e))?;
+    let items: Box<[*mut c_char]> = strings.into_iter().map(|s| s.into_raw()).collect();
+    let len = items.len();
+    Ok(OntoEnvStringArray {
+        items: Box::into_raw(items) as *mut *mut c_char,
+        len,
+    })
+}
+
+fn find_ontology<'a>(
+    env: &'a OntoEnv,
+    iri: &str,
+) -> Result<&'a ontoenv::ontology::Ontology, FfiError> {
+    let iri = parse_iri(iri).map_err(|e| FfiError::new(OntoEnvStatus::InvalidArgument, e))?;
+    env.get_ontology_by_name(iri.as_ref()).ok_or(FfiError::new(
+        OntoEnvStatus::NotFound,
+        format!("Ontology {} not found", iri),
+    ))
+}
+
+fn rdf_format(format: &str) -> Result<RdfFormat, FfiError> {
+    match format.to_lowercase().as_str() {
+        "turtle" | "ttl" => Ok(RdfFormat::Turtle),
+        "ntriples" | "nt" => Ok(RdfFormat::NTriples),
+        "rdfxml" | "xml" | "rdf" => Ok(RdfFormat::RdfXml),
+        other => RdfFormat::from_media_type(other).ok_or(FfiError::new(
+            OntoEnvStatus::InvalidArgument,
+            format!("Unsupported format: {}", other),
+        )),
+    }
+}
+
+fn serialize_graph(graph: &Graph, format: RdfFormat) -> Result<String, FfiError> {
+    let mut buffer = Vec::new();
+    let mut serializer = RdfSerializer::from_format(format).for_writer(&mut buffer);
+    for triple in util::sorted_triples(graph) {
+        serializer
+            .serialize_triple(&triple)
+            .map_err(|e| FfiError::new(OntoEnvStatus::Error, e))?;
+    }
+    serializer
+        .finish()
+        .map_err(|e| FfiError::new(OntoEnvStatus::Error, e))?;
+    String::from_utf8(buffer).map_err(|e| FfiError::new(OntoEnvStatus::Error, e))
+}
+
+/// Returns the version of the library. The string is static and must not be freed.
+#[no_mangle]
+pub extern "C" fn ontoenv_version() -> *const c_char {
+    concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr() as *const c_char
+}
+
+/// Returns the message of the last error on this thread, or null if the last call succeeded.
+/// The string is owned by the library and valid until the next call on this thread.
+#[no_mangle]
+pub extern "C" fn ontoenv_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|e| {
+        e.borrow()
+            .as_ref()
+            .map(|m| m.as_ptr())
+            .unwrap_or(std::ptr::null())
+    })
+}
+
+/// Opens the environment in `<root>/.ontoenv` and writes its handle to `out`.
+///
+/// # Safety
+/// `root` must be a valid NUL-terminated string and `out` a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn ontoenv_open(
+    root: *const c_char,
+    read_only: bool,
+    out: *mut *mut OntoEnvHandle,
+) -> OntoEnvStatus {
+    guard(|| {
+        let root = PathBuf::from(str_arg(root, "root")?);
+        check_out(out)?;
+        let path = root.join(".ontoenv/ontoenv.json");
+        let env = OntoEnv::from_file(&path, read_only)?;
+        *out = Box::into_raw(Box::new(OntoEnvHandle { env }));
+        Ok(())
+    })
+}
+
+/// Closes the environment and releases the handle. Null handles are ignored.
+///
+/// # Safety
+/// `env` must be null or a handle returned by `ontoenv_open` which has not been closed.
+#[no_mangle]
+pub unsafe extern "C" fn ontoenv_close(env: *mut OntoEnvHandle) {
+    if !env.is_null() {
+        let _ = catch_unwind(AssertUnwindSafe(|| drop(Box::from_raw(env))));
+    }
+}
+
+/// Refreshes the environment from its search directories and saves it.
+///
+/// # Safety
+/// `env` must be a handle returned by `ontoenv_open` with `read_only` false.
+#[no_mangle]
+pub unsafe extern "C" fn ontoenv_update(env: *mut OntoEnvHandle) -> OntoEnvStatus {
+    guard(|| {
+        let handle = env
+            .as_mut()
+            .ok_or(FfiError::new(OntoEnvStatus::InvalidArgument, "env is null"))?;
+        if handle.env.is_read_only() {
+            return Err(FfiError::new(
+                OntoEnvStatus::InvalidArgument,
+                "environment was opened read-only",
+            ));
+        }
+        handle.env.update()?;
+        handle.env.save_to_directory()?;
+        Ok(())
+    })
+}
+
+/// Writes the names of all ontologies in the environment, sorted, to `out`.
+///
+/// # Safety
+/// `env` must be a valid handle and `out` a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn ontoenv_list_ontologies(
+    env: *const OntoEnvHandle,
+    out: *mut OntoEnvStringArray,
+) -> OntoEnvStatus {
+    guard(|| {
+        let env = env_arg(env)?;
+        check_out(out)?;
+        let mut names: Vec<String> = env
+            .ontologies()
+            .values()
+            .map(|o| o.name().as_str().to_string())
+            .collect();
+        names.sort();
+        names.dedup();
+        *out = to_string_array(names)?;
+        Ok(())
+    })
+}
+
+/// Writes the names of the ontologies in the imports closure of `iri` to `out`, starting with
+/// `iri` itself.
+///
+/// # Safety
+/// `env` must be a valid handle, `iri` a valid NUL-terminated string and `out` a valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn ontoenv_closure(
+    env: *const OntoEnvHandle,
+    iri: *const c_char,
+    out: *mut OntoEnvStringArray,
+) -> OntoEnvStatus {
+    guard(|| {
+        let env = env_arg(env)?;
+        let ontology = find_ontology(env, str_arg(iri, "iri")?)?;
+        check_out(out)?;
+        let names = env
+            .get_dependency_closure(ontology.id())?
+            .iter()
+            .map(|id| id.name().as_str().to_string())
+            .collect();
+        *out = to_string_array(names)?;
+        Ok(())
+    })
+}
+
+/// Serializes the graph of the ontology `iri` and writes it to `out`. `format` is one of
+/// "turtle", "ntriples", "rdfxml" or a media type.
+///
+/// # Safety
+/// `env` must be a valid handle, `iri` and `format` valid NUL-terminated strings and `out` a
+/// valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn ontoenv_get_graph_serialized(
+    env: *const OntoEnvHandle,
+    iri: *const c_char,
+    format: *const c_char,
+    out: *mut *mut c_char,
+) -> OntoEnvStatus {
+    guard(|| {
+        let env = env_arg(env)?;
+        let ontology = find_ontology(env, str_arg(iri, "iri")?)?;
+        let format = rdf_format(str_arg(format, "format")?)?;
+        check_out(out)?;
+        let graph = env.get_graph(ontology.id())?;
+        *out = to_c_string(serialize_graph(&graph, format)?)?;
+        Ok(())
+    })
+}
+
+/// Serializes the union of the imports closure of `iri`, with owl:imports removed and
+/// sh:prefixes rewritten to `iri`, and writes it to `out`.
+///
+/// # Safety
+/// `env` must be a valid handle, `iri` and `format` valid NUL-terminated strings and `out` a
+/// valid pointer.
+#[no_mangle]
+pub unsafe extern "C" fn ontoenv_get_closure_serialized(
+    env: *const OntoEnvHandle,
+    iri: *const c_char,
+    format: *const c_char,
+    out: *mut *mut c_char,
+) -> OntoEnvStatus {
+    guard(|| {
+        let env = env_arg(env)?;
+        let ontology = find_ontology(env, str_arg(iri, "iri")?)?;
+        let format = rdf_format(str_arg(format, "format")?)?;
+        check_out(out)?;
+        let closure = env.get_dependency_closure(ontology.id())?;
+        let (union, _, _) = env.get_union_graph(&closure, Some(true), Some(true))?;
+        let graph = ontoenv::util::dataset_to_graph(&union);
+        *out = to_c_string(serialize_graph(&graph, format)?)?;
+        Ok(())
+    })
+}
+
+/// Releases a string returned by the library. Null strings are ignored.
+///
+/// # Safety
+/// `s` must be null or a string returned by the library which has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn ontoenv_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+/// Releases the strings of an array returned by the library and resets it to empty.
+///
+/// # Safety
+/// `array` must be null or point to an array filled by the library which has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn ontoenv_string_array_free(array: *mut OntoEnvStringArray) {
+    let array = match array.as_mut() {
+        Some(array) => array,
+        None => return,
+    };
+    if !array.items.is_null() {
+        let items = Box::from_raw(std::ptr::slice_from_raw_parts_mut(array.items, array.len));
+        for item in items.iter() {
+            drop(CString::from_raw(*item));
+        }
+    }
+    array.items = std::ptr::null_mut();
+    array.len = 0;
+}
@@ -0,0 +1,30 @@
+use ontoenv_c::*;
+use std::ffi::{CStr, CString};
+
+#[test]
+fn test_ffi_errors() {
+    unsafe {
+        let mut handle: *mut OntoEnvHandle = std::ptr::null_mut();
+        let status = ontoenv_open(std::ptr::null(), true, &mut handle);
+        assert_eq!(status, OntoEnvStatus::InvalidArgument);
+        assert!(handle.is_null());
+        let message = CStr::from_ptr(ontoenv_last_error_message());
+        assert_eq!(message.to_str().unwrap(), "root is null");
+
+        let root = CString::new("/nonexistent/ontoenv").unwrap();
+        let status = ontoenv_open(root.as_ptr(), true, &mut handle);
+        assert_eq!(status, OntoEnvStatus::Error);
+        assert!(handle.is_null());
+
+        // freeing null values is a no-op
+        ontoenv_close(std::ptr::null_mut());
+        ontoenv_string_free(std::ptr::null_mut());
+        ontoenv_string_array_free(std::ptr::null_mut());
+    }
+}
+
+#[test]
+fn test_ffi_version() {
+    let version = unsafe { CStr::from_ptr(ontoenv_version()) };
+    assert_eq!(version.to_str().unwrap(), env!("CARGO_PKG_VERSION"));
+}
@@ -0,0 +1,101 @@
+// benchmarks for the operations whose cost grows with the size of an environment. Run with
+// `cargo bench -p ontoenv --features bench`; save a baseline with `-- --save-baseline <name>`
+// and compare a later run against it with `-- --baseline <name>`
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use ontoenv::bench::{bench_config, copy_brick_fixtures, generate_ontologies, load_environment};
+use ontoenv::OntoEnv;
+use oxigraph::model::{NamedNodeRef, Term};
+use tempdir::TempDir;
+
+const BRICK: &str = "https://brickschema.org/schema/1.3/Brick";
+
+fn brick_environment() -> (TempDir, OntoEnv) {
+    let dir = TempDir::new("ontoenv-bench").unwrap();
+    copy_brick_fixtures(dir.path()).unwrap();
+    let env = load_environment(dir.path()).unwrap();
+    (dir, env)
+}
+
+fn scan(c: &mut Criterion) {
+    let dir = TempDir::new("ontoenv-bench").unwrap();
+    generate_ontologies(dir.path(), 1000, 3, 10).unwrap();
+    let mut group = c.benchmark_group("scan");
+    group.sample_size(10);
+    group.bench_function("update 1k files", |b| {
+        b.iter_batched(
+            || OntoEnv::new(bench_config(dir.path()).unwrap(), true).unwrap(),
+            |mut env| {
+                env.update().unwrap();
+                env
+            },
+            BatchSize::PerIteration,
+        )
+    });
+    group.finish();
+}
+
+fn closure(c: &mut Criterion) {
+    let (_dir, env) = brick_environment();
+    let brick = env
+        .get_ontology_by_name(NamedNodeRef::new(BRICK).unwrap())
+        .unwrap()
+        .id()
+        .clone();
+    c.bench_function("closure brick", |b| {
+        b.iter(|| env.get_dependency_closure(black_box(&brick)).unwrap())
+    });
+}
+
+fn union(c: &mut Criterion) {
+    let (_dir, env) = brick_environment();
+    let brick = env
+        .get_ontology_by_name(NamedNodeRef::new(BRICK).unwrap())
+        .unwrap()
+        .id()
+        .clone();
+    let closure = env.get_dependency_closure(&brick).unwrap();
+    let mut group = c.benchmark_group("union");
+    group.sample_size(20);
+    group.bench_function("brick closure", |b| {
+        b.iter(|| {
+            env.get_union_graph(black_box(&closure), Some(true), Some(true))
+                .unwrap()
+        })
+    });
+    group.finish();
+}
+
+// the Rust half of handing a closure to Python: the union graph is split into owned terms,
+// which the bindings then turn into rdflib terms
+fn python_transfer(c: &mut Criterion) {
+    let (_dir, env) = brick_environment();
+    let brick = env
+        .get_ontology_by_name(NamedNodeRef::new(BRICK).unwrap())
+        .unwrap()
+        .id()
+        .clone();
+    let closure = env.get_dependency_closure(&brick).unwrap();
+    let mut group = c.benchmark_group("python");
+    group.sample_size(20);
+    group.bench_function("transfer brick closure", |b| {
+        b.iter(|| {
+            let (graph, _, _) = env
+                .get_union_graph(&closure, Some(true), Some(true))
+                .unwrap();
+            let mut count = 0;
+            for quad in graph.iter() {
+                let s: Term = quad.subject.into_owned().into();
+                let p: Term = quad.predicate.into_owned().into();
+                let o: Term = quad.object.into_owned();
+                black_box((s, p, o));
+                count += 1;
+            }
+            count
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, scan, closure, union, python_transfer);
+criterion_main!(benches);
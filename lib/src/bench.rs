@@ -0,0 +1,116 @@
+// fixtures for the benchmarks in benches/ (enabled with the "bench" feature). Generated
+// environments are deterministic, so timings from different commits can be compared
+
+use crate::config::Config;
+use crate::OntoEnv;
+use anyhow::Result;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Writes `count` Turtle ontologies named `http://example.org/bench/ontN` to `dir`. Each one
+/// imports the (up to) `imports` ontologies before it and declares `classes` classes, so the
+/// environment has a deep import closure as well as many files to scan
+pub fn generate_ontologies(
+    dir: &Path,
+    count: usize,
+    imports: usize,
+    classes: usize,
+) -> Result<Vec<PathBuf>> {
+    fs::create_dir_all(dir)?;
+    let mut files = Vec::with_capacity(count);
+    for i in 0..count {
+        let mut content = String::new();
+        writeln!(content, "@prefix owl: <http://www.w3.org/2002/07/owl#> .")?;
+        writeln!(
+            content,
+            "@prefix rdfs: <http://www.w3.org/2000/01/rdf-schema#> ."
+        )?;
+        writeln!(
+            content,
+            "@prefix ex: <http://example.org/bench/ont{}#> .",
+            i
+        )?;
+        writeln!(content)?;
+        write!(
+            content,
+            "<http://example.org/bench/ont{}> a owl:Ontology",
+            i
+        )?;
+        for j in i.saturating_sub(imports)..i {
+            write!(
+                content,
+                " ;\n    owl:imports <http://example.org/bench/ont{}>",
+                j
+            )?;
+        }
+        writeln!(content, " .\n")?;
+        for c in 0..classes {
+            writeln!(
+                content,
+                "ex:Class{} a owl:Class ;\n    rdfs:label \"Class {} of ontology {}\" .",
+                c, c, i
+            )?;
+        }
+        let path = dir.join(format!("ont{}.ttl", i));
+        fs::write(&path, content)?;
+        files.push(path);
+    }
+    Ok(files)
+}
+
+/// Copies Brick 1.3 and the ontologies it imports from the crate's fixtures to `dir`
+pub fn copy_brick_fixtures(dir: &Path) -> Result<()> {
+    let source = Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures/brick-stuff");
+    fs::create_dir_all(dir.join("support"))?;
+    fs::copy(source.join("Brick-1.3.ttl"), dir.join("Brick-1.3.ttl"))?;
+    for entry in fs::read_dir(source.join("support"))? {
+        let entry = entry?;
+        fs::copy(entry.path(), dir.join("support").join(entry.file_name()))?;
+    }
+    Ok(())
+}
+
+/// Returns the configuration of an offline, non-strict environment rooted at `dir` which
+/// searches `dir` for Turtle and RDF/XML files
+pub fn bench_config(dir: &Path) -> Result<Config> {
+    Config::new(
+        dir.to_path_buf(),
+        Some(vec![dir.to_path_buf()]),
+        &["*.ttl", "*.xml"],
+        &[""],
+        false,
+        false,
+        true,
+        "default".to_string(),
+        false,
+    )
+}
+
+/// Creates a new environment rooted at `dir` and loads the ontologies in it
+pub fn load_environment(dir: &Path) -> Result<OntoEnv> {
+    let mut env = OntoEnv::new(bench_config(dir)?, true)?;
+    env.update()?;
+    Ok(env)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use oxigraph::model::NamedNode;
+
+    #[test]
+    fn test_generated_ontologies() -> Result<()> {
+        let dir = tempdir::TempDir::new("ontoenv-bench")?;
+        let files = generate_ontologies(dir.path(), 10, 3, 5)?;
+        assert_eq!(files.len(), 10);
+        let env = load_environment(dir.path())?;
+        assert_eq!(env.num_graphs(), 10);
+        let name = NamedNode::new("http://example.org/bench/ont9")?;
+        let ontology = env.get_ontology_by_name(name.as_ref()).unwrap();
+        // ont9 imports ont6..ont8, which import their three predecessors and so on
+        let closure = env.get_dependency_closure(ontology.id())?;
+        assert_eq!(closure.len(), 10);
+        Ok(())
+    }
+}
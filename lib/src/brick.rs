@@ -0,0 +1,285 @@
+// helpers for Brick, ASHRAE 223P, REC and QUDT users: locating those ontologies in an
+// environment, building the closure a Brick/223P model is validated against, and checking that
+// the Brick version a model imports matches the one in the environment
+
+use crate::consts::{IMPORTS, ONTOLOGY, TYPE, VERSION_INFO};
+use crate::ontology::{GraphIdentifier, Ontology};
+use crate::util;
+use crate::OntoEnv;
+use anyhow::Result;
+use oxigraph::model::{Graph, NamedNode, NamedNodeRef, SubjectRef, TermRef};
+use std::fmt;
+use std::str::FromStr;
+
+pub const BRICK_NAMESPACE: &str = "https://brickschema.org/schema/Brick#";
+pub const S223_NAMESPACE: &str = "http://data.ashrae.org/standard223#";
+const BRICK_SCHEMA: &str = "https://brickschema.org/schema/";
+const REC_SCHEMA: &str = "https://w3id.org/rec";
+const S223_SCHEMA: &str = "http://data.ashrae.org/standard223";
+const QUDT_SCHEMAS: [&str; 2] = ["http://qudt.org/", "https://qudt.org/"];
+
+/// A Brick version like 1.3 or 1.4.1
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct BrickVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub patch: Option<u32>,
+}
+
+impl BrickVersion {
+    /// Versions are compatible if their major and minor versions are the same
+    pub fn is_compatible_with(&self, other: &BrickVersion) -> bool {
+        self.major == other.major && self.minor == other.minor
+    }
+}
+
+impl FromStr for BrickVersion {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parts = s.trim().trim_start_matches('v').split('.');
+        let mut next = || -> Option<Result<u32>> { parts.next().map(|p| Ok(p.parse::<u32>()?)) };
+        let major = next().ok_or(anyhow::anyhow!("Empty version"))??;
+        let minor = next().ok_or(anyhow::anyhow!("Invalid version: {}", s))??;
+        let patch = next().transpose()?;
+        Ok(BrickVersion {
+            major,
+            minor,
+            patch,
+        })
+    }
+}
+
+impl fmt::Display for BrickVersion {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.patch {
+            Some(patch) => write!(f, "{}.{}.{}", self.major, self.minor, patch),
+            None => write!(f, "{}.{}", self.major, self.minor),
+        }
+    }
+}
+
+/// Returns true if the IRI names the Brick ontology, e.g. https://brickschema.org/schema/Brick
+/// or https://brickschema.org/schema/1.4/Brick
+pub fn is_brick_ontology(name: NamedNodeRef) -> bool {
+    name.as_str()
+        .strip_prefix(BRICK_SCHEMA)
+        .map(|rest| rest.trim_end_matches(['/', '#']))
+        .is_some_and(|rest| rest == "Brick" || rest.strip_suffix("/Brick").is_some())
+}
+
+/// Returns the version in a versioned Brick ontology IRI like
+/// https://brickschema.org/schema/1.4/Brick
+pub fn version_from_name(name: NamedNodeRef) -> Option<BrickVersion> {
+    if !is_brick_ontology(name) {
+        return None;
+    }
+    let rest = name.as_str().strip_prefix(BRICK_SCHEMA)?;
+    rest.split('/').next()?.parse().ok()
+}
+
+/// Returns the version of the Brick ontology from its owl:versionInfo, falling back to the
+/// version in its name
+pub fn ontology_version(ontology: &Ontology) -> Option<BrickVersion> {
+    ontology
+        .version_properties()
+        .get(&VERSION_INFO.into_owned())
+        .and_then(|v| v.parse().ok())
+        .or_else(|| version_from_name(ontology.name().as_ref()))
+}
+
+/// The Brick, 223P, REC and QUDT ontologies found in an environment
+#[derive(Debug, Clone, Default)]
+pub struct BrickGraphs {
+    pub brick: Option<GraphIdentifier>,
+    pub s223: Option<GraphIdentifier>,
+    pub rec: Option<GraphIdentifier>,
+    pub qudt: Vec<GraphIdentifier>,
+}
+
+/// Locates the Brick, 223P, REC and QUDT ontologies in the environment. If there are several
+/// Brick ontologies, the one with the highest version is returned
+pub fn locate(env: &OntoEnv) -> BrickGraphs {
+    let mut graphs = BrickGraphs::default();
+    let mut brick_version: Option<BrickVersion> = None;
+    let mut ontologies: Vec<&Ontology> = env.ontologies().values().collect();
+    ontologies.sort_by_key(|o| o.name());
+    for ontology in ontologies {
+        let name = ontology.name();
+        let id = ontology.id().clone();
+        if is_brick_ontology(name.as_ref()) {
+            let version = ontology_version(ontology);
+            if graphs.brick.is_none() || version > brick_version {
+                graphs.brick = Some(id);
+                brick_version = version;
+            }
+        } else if name.as_str().trim_end_matches(['/', '#']) == S223_SCHEMA {
+            graphs.s223 = Some(id);
+        } else if name.as_str().trim_end_matches(['/', '#']) == REC_SCHEMA {
+            graphs.rec = Some(id);
+        } else if QUDT_SCHEMAS.iter().any(|p| name.as_str().starts_with(p)) {
+            graphs.qudt.push(id);
+        }
+    }
+    graphs
+}
+
+/// Returns the owl:imports of the ontologies declared in the model
+fn model_imports(model: &Graph) -> Vec<NamedNode> {
+    model
+        .subjects_for_predicate_object(TYPE, ONTOLOGY)
+        .flat_map(|s| model.objects_for_subject_predicate(s, IMPORTS))
+        .filter_map(|o| match o {
+            TermRef::NamedNode(n) => Some(n.into_owned()),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Returns true if the model uses a term in the namespace
+fn uses_namespace(model: &Graph, namespace: &str) -> bool {
+    model.iter().any(|t| {
+        matches!(t.subject, SubjectRef::NamedNode(n) if n.as_str().starts_with(namespace))
+            || t.predicate.as_str().starts_with(namespace)
+            || matches!(t.object, TermRef::NamedNode(n) if n.as_str().starts_with(namespace))
+    })
+}
+
+/// Builds the graph a Brick or 223P model is validated against: the model together with the
+/// imports closures of everything it imports. If the model uses Brick or 223P terms without
+/// importing the ontology, the environment's Brick or 223P ontology is added as well
+pub fn validation_closure(env: &OntoEnv, model: &Graph) -> Result<Graph> {
+    let brick_graphs = locate(env);
+    let mut roots: Vec<GraphIdentifier> = vec![];
+    for import in model_imports(model) {
+        let ontology = env
+            .get_ontology_by_name(import.as_ref())
+            .ok_or(anyhow::anyhow!("Ontology {} not found", import))?;
+        roots.push(ontology.id().clone());
+    }
+    for (namespace, id) in [
+        (BRICK_NAMESPACE, &brick_graphs.brick),
+        (S223_NAMESPACE, &brick_graphs.s223),
+    ] {
+        if let Some(id) = id {
+            if !roots.contains(id) && uses_namespace(model, namespace) {
+                roots.push(id.clone());
+            }
+        }
+    }
+
+    let mut ids: Vec<GraphIdentifier> = vec![];
+    for root in roots.iter() {
+        for id in env.get_dependency_closure(root)? {
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+    }
+    let mut closure = model.clone();
+    if ids.is_empty() {
+        return Ok(closure);
+    }
+    let (union, _, failed_imports) = env.get_union_graph(&ids, Some(false), Some(true))?;
+    if let Some(imp) = failed_imports.and_then(|f| f.into_iter().next()) {
+        return Err(anyhow::anyhow!("Cannot build validation closure: {}", imp));
+    }
+    for triple in util::dataset_to_graph(&union).iter() {
+        closure.insert(triple);
+    }
+    Ok(closure)
+}
+
+/// Whether the Brick version a model imports matches the Brick version in the environment
+#[derive(Debug, Clone)]
+pub struct VersionCompatibility {
+    // the version in the Brick IRI the model imports, if it imports a versioned Brick IRI
+    pub model_version: Option<BrickVersion>,
+    // the version of the Brick ontology in the environment
+    pub env_version: Option<BrickVersion>,
+    pub compatible: bool,
+    pub message: String,
+}
+
+/// Compares the Brick version imported by the model with the Brick version in the environment
+pub fn check_compatibility(env: &OntoEnv, model: &Graph) -> VersionCompatibility {
+    let env_version = locate(env)
+        .brick
+        .and_then(|id| env.ontologies().get(&id))
+        .map(|o| (o.id().clone(), ontology_version(o)));
+    let model_version = model_imports(model)
+        .iter()
+        .find_map(|i| version_from_name(i.as_ref()));
+    let (compatible, message, env_version) = match (env_version, model_version) {
+        (None, _) => (
+            false,
+            "No Brick ontology found in the environment".to_string(),
+            None,
+        ),
+        (Some((id, None)), _) => (
+            true,
+            format!("Brick ontology {} has no version", id.name()),
+            None,
+        ),
+        (Some((_, Some(env_version))), None) => (
+            true,
+            format!("Using Brick {}", env_version),
+            Some(env_version),
+        ),
+        (Some((_, Some(env_version))), Some(model_version)) => {
+            if model_version.is_compatible_with(&env_version) {
+                (
+                    true,
+                    format!(
+                        "Model imports Brick {} and the environment has Brick {}",
+                        model_version, env_version
+                    ),
+                    Some(env_version),
+                )
+            } else {
+                (
+                    false,
+                    format!(
+                        "Model imports Brick {} but the environment has Brick {}",
+                        model_version, env_version
+                    ),
+                    Some(env_version),
+                )
+            }
+        }
+    };
+    VersionCompatibility {
+        model_version,
+        env_version,
+        compatible,
+        message,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_brick_version() {
+        let version: BrickVersion = "1.4.1".parse().unwrap();
+        assert_eq!(version.to_string(), "1.4.1");
+        assert!(version.is_compatible_with(&"1.4".parse().unwrap()));
+        assert!(!version.is_compatible_with(&"1.3".parse().unwrap()));
+        assert!("v1.3".parse::<BrickVersion>().unwrap() < version);
+        assert!("1".parse::<BrickVersion>().is_err());
+        assert!("latest".parse::<BrickVersion>().is_err());
+    }
+
+    #[test]
+    fn test_brick_names() {
+        let versioned = NamedNodeRef::new("https://brickschema.org/schema/1.4/Brick").unwrap();
+        let unversioned = NamedNodeRef::new("https://brickschema.org/schema/Brick#").unwrap();
+        let other = NamedNodeRef::new("https://brickschema.org/schema/1.4/BrickShape").unwrap();
+        assert!(is_brick_ontology(versioned));
+        assert!(is_brick_ontology(unversioned));
+        assert!(!is_brick_ontology(other));
+        assert_eq!(version_from_name(versioned), Some("1.4".parse().unwrap()));
+        assert_eq!(version_from_name(unversioned), None);
+    }
+}
@@ -0,0 +1,279 @@
+// assembled union graphs of imports closures, persisted in .ontoenv/cache/closures so the union
+// of an unchanged stack of ontologies (e.g. Brick and QUDT) is not merged again every time. Each
+// entry is keyed by the root of the closure and the union options, and records a fingerprint of
+// the ontologies in the closure; it is stale as soon as the content of any of them changes.
+// MemoryCache keeps closures and unions computed by a running environment, so repeated requests
+// do not go back to the store or to disk at all
+
+use crate::config::ResourceLimits;
+use crate::ontology::{GraphIdentifier, Ontology};
+use anyhow::Result;
+use oxigraph::io::{RdfFormat, RdfParser, RdfSerializer};
+use oxigraph::model::Dataset;
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Number of unions a MemoryCache keeps; unions of large closures take a lot of memory
+pub const MEMORY_CACHE_UNIONS: usize = 8;
+
+/// Rough number of bytes a quad of a Dataset takes in memory, for `ResourceLimits::max_memory`
+pub const QUAD_MEMORY_ESTIMATE: u64 = 200;
+
+/// A directory of cached union graphs
+#[derive(Debug, Clone)]
+pub struct ClosureCache {
+    dir: PathBuf,
+}
+
+/// Returns the fingerprint of the ontologies of a closure, in order: their names, locations,
+/// graph IDs and content hashes. Ontologies read before content hashes were recorded contribute
+/// when they were last read instead, so their entries are stale once they are read again
+pub fn closure_fingerprint<'a>(ontologies: impl IntoIterator<Item = &'a Ontology>) -> String {
+    let mut hasher = Sha256::new();
+    for ontology in ontologies {
+        let id = ontology.id();
+        let content = match ontology.content_hash() {
+            Some(hash) => hash.to_string(),
+            None => ontology
+                .last_updated
+                .map(|t| t.to_rfc3339())
+                .unwrap_or_default(),
+        };
+        for part in [
+            id.name().as_str().to_string(),
+            id.location().to_string(),
+            id.graph_id().unwrap_or_default().to_string(),
+            content,
+        ] {
+            hasher.update(part.as_bytes());
+            hasher.update([0]);
+        }
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+impl ClosureCache {
+    /// The cache of the environment rooted at the directory
+    pub fn new(root: &Path) -> Self {
+        Self::in_dir(&root.join(".ontoenv"))
+    }
+
+    /// The cache of the environment whose store and manifest are in the directory (see
+    /// `OntoEnv::ontoenv_dir`)
+    pub fn in_dir(ontoenv_dir: &Path) -> Self {
+        ClosureCache {
+            dir: ontoenv_dir.join("cache").join("closures"),
+        }
+    }
+
+    // entries are named after a hash of their key, which may contain any characters
+    fn entry_path(&self, key: &str, extension: &str) -> PathBuf {
+        let name = format!("{:x}", Sha256::digest(key.as_bytes()));
+        self.dir.join(name).with_extension(extension)
+    }
+
+    /// Returns the cached union for the key, if its fingerprint matches
+    pub fn get(&self, key: &str, fingerprint: &str) -> Result<Option<Dataset>> {
+        let recorded = match fs::read_to_string(self.entry_path(key, "fingerprint")) {
+            Ok(recorded) => recorded,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        if recorded.trim() != fingerprint {
+            return Ok(None);
+        }
+        let file = match fs::File::open(self.entry_path(key, "nq")) {
+            Ok(file) => file,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let mut dataset = Dataset::new();
+        for quad in RdfParser::from_format(RdfFormat::NQuads).for_reader(BufReader::new(file)) {
+            dataset.insert(&quad?);
+        }
+        Ok(Some(dataset))
+    }
+
+    /// Caches the union for the key, replacing any earlier entry. The union is written to a
+    /// temporary file first, so readers never see a partial entry. With `max_size`, the oldest
+    /// entries are removed while the cached unions take more bytes than that, which removes the
+    /// new entry too if it is larger on its own
+    pub fn put(
+        &self,
+        key: &str,
+        fingerprint: &str,
+        dataset: &Dataset,
+        max_size: Option<u64>,
+    ) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        // invalidate the earlier entry before replacing its union
+        let fingerprint_path = self.entry_path(key, "fingerprint");
+        if fingerprint_path.exists() {
+            fs::remove_file(&fingerprint_path)?;
+        }
+        let path = self.entry_path(key, "nq");
+        let partial = path.with_extension("nq.partial");
+        {
+            let mut writer = BufWriter::new(fs::File::create(&partial)?);
+            let mut serializer =
+                RdfSerializer::from_format(RdfFormat::NQuads).for_writer(&mut writer);
+            for quad in dataset.iter() {
+                serializer.serialize_quad(quad)?;
+            }
+            serializer.finish()?;
+            writer.flush()?;
+        }
+        fs::rename(&partial, &path)?;
+        fs::write(fingerprint_path, fingerprint)?;
+        match max_size {
+            Some(max_size) => self.evict(max_size),
+            None => Ok(()),
+        }
+    }
+
+    // removes the oldest entries while the cached unions take more than max_size bytes
+    fn evict(&self, max_size: u64) -> Result<()> {
+        let mut entries = vec![];
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            if path.extension().is_some_and(|ext| ext == "nq") {
+                let metadata = entry.metadata()?;
+                entries.push((metadata.modified()?, metadata.len(), path));
+            }
+        }
+        entries.sort();
+        let mut total: u64 = entries.iter().map(|(_, size, _)| size).sum();
+        for (_, size, path) in entries {
+            if total <= max_size {
+                break;
+            }
+            // invalidate the entry before removing its union
+            match fs::remove_file(path.with_extension("fingerprint")) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e.into()),
+                _ => {}
+            }
+            fs::remove_file(&path)?;
+            total -= size;
+        }
+        Ok(())
+    }
+
+    /// Removes every cached union, returning how many there were
+    pub fn clear(&self) -> Result<usize> {
+        let mut removed = 0;
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(e.into()),
+        };
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "nq") {
+                removed += 1;
+            }
+            fs::remove_file(path)?;
+        }
+        Ok(removed)
+    }
+}
+
+/// Closures and unions computed by an environment, kept in memory and keyed by the graphs they
+/// involve (with their content hashes, see `closure_fingerprint`) and the options they were
+/// computed with. The environment clears it whenever it adds, updates or removes ontologies
+#[derive(Debug, Default)]
+pub struct MemoryCache {
+    closures: HashMap<String, Vec<GraphIdentifier>>,
+    // the union, the graphs it contains and the number of duplicates dropped from it; the most
+    // recently used entry is last
+    unions: VecDeque<(String, (Dataset, Vec<GraphIdentifier>, usize))>,
+}
+
+impl MemoryCache {
+    pub fn closure(&self, key: &str) -> Option<Vec<GraphIdentifier>> {
+        self.closures.get(key).cloned()
+    }
+
+    pub fn put_closure(&mut self, key: String, closure: Vec<GraphIdentifier>) {
+        self.closures.insert(key, closure);
+    }
+
+    pub fn union(&mut self, key: &str) -> Option<(Dataset, Vec<GraphIdentifier>, usize)> {
+        let index = self.unions.iter().position(|(k, _)| k == key)?;
+        let entry = self.unions.remove(index)?;
+        let union = entry.1.clone();
+        self.unions.push_back(entry);
+        Some(union)
+    }
+
+    /// Keeps the union, dropping the least recently used ones while there are more than the
+    /// limits allow (see `ResourceLimits::memory_cache_unions` and `max_memory`). A union which
+    /// does not fit in `max_memory` on its own is not kept
+    pub fn put_union(
+        &mut self,
+        key: String,
+        union: (Dataset, Vec<GraphIdentifier>, usize),
+        limits: &ResourceLimits,
+    ) {
+        self.unions.retain(|(k, _)| *k != key);
+        let size = |dataset: &Dataset| dataset.len() as u64 * QUAD_MEMORY_ESTIMATE;
+        if limits.max_memory.is_some_and(|max| size(&union.0) > max) {
+            return;
+        }
+        self.unions.push_back((key, union));
+        let max_unions = limits.memory_cache_unions();
+        let mut total: u64 = self.unions.iter().map(|(_, union)| size(&union.0)).sum();
+        while self.unions.len() > max_unions || limits.max_memory.is_some_and(|max| total > max) {
+            if let Some((_, dropped)) = self.unions.pop_front() {
+                total -= size(&dropped.0);
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.closures.clear();
+        self.unions.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use oxigraph::model::NamedNodeRef;
+
+    #[test]
+    fn test_memory_cache_keeps_recent_unions() {
+        let id = GraphIdentifier::new(NamedNodeRef::new("http://example.org/ontology1").unwrap());
+        let limits = ResourceLimits::default();
+        let mut cache = MemoryCache::default();
+        for i in 0..MEMORY_CACHE_UNIONS {
+            cache.put_union(
+                i.to_string(),
+                (Dataset::new(), vec![id.clone()], 0),
+                &limits,
+            );
+        }
+        // using the oldest union makes the second oldest the one to drop
+        assert!(cache.union("0").is_some());
+        cache.put_union("new".to_string(), (Dataset::new(), vec![], 0), &limits);
+        assert!(cache.union("0").is_some());
+        assert!(cache.union("1").is_none());
+        assert!(cache.union("new").is_some());
+
+        cache.put_closure("closure".to_string(), vec![id.clone()]);
+        assert_eq!(cache.closure("closure"), Some(vec![id]));
+        cache.clear();
+        assert!(cache.closure("closure").is_none() && cache.union("0").is_none());
+
+        // with no unions allowed, none are kept
+        let limits = ResourceLimits {
+            memory_cache_unions: Some(0),
+            ..Default::default()
+        };
+        cache.put_union("0".to_string(), (Dataset::new(), vec![id], 0), &limits);
+        assert!(cache.union("0").is_none());
+    }
+}
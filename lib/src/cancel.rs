@@ -0,0 +1,101 @@
+// cancellation of long-running operations (updating an environment, adding ontologies and
+// computing closures). Operations check the token between ontologies, so a cancelled operation
+// never leaves a half-written graph in the store; it stops with a Cancelled error
+
+use anyhow::Result;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// The error returned by operations which were cancelled or timed out. Use
+/// `error.downcast_ref::<Cancelled>()` to tell it apart from other errors
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cancelled {
+    pub timed_out: bool,
+}
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.timed_out {
+            write!(f, "Operation timed out")
+        } else {
+            write!(f, "Operation cancelled")
+        }
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// A token which cancels the operations of an environment. Clones share the same state, so one
+/// clone can be handed to the environment while another is cancelled from a different thread
+/// (e.g. a GUI or a signal handler)
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+    deadline: Option<Instant>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a token which is cancelled automatically once the timeout has passed
+    pub fn with_timeout(timeout: Duration) -> Self {
+        CancellationToken {
+            cancelled: Arc::new(AtomicBool::new(false)),
+            deadline: Some(Instant::now() + timeout),
+        }
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    fn timed_out(&self) -> bool {
+        self.deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst) || self.timed_out()
+    }
+
+    /// Returns a Cancelled error if the token was cancelled or has timed out
+    pub fn check(&self) -> Result<()> {
+        if self.cancelled.load(Ordering::SeqCst) {
+            return Err(Cancelled { timed_out: false }.into());
+        }
+        if self.timed_out() {
+            return Err(Cancelled { timed_out: true }.into());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cancellation_token() {
+        let token = CancellationToken::new();
+        assert!(token.check().is_ok());
+        let clone = token.clone();
+        clone.cancel();
+        assert!(token.is_cancelled());
+        let err = token.check().unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<Cancelled>(),
+            Some(&Cancelled { timed_out: false })
+        );
+
+        let token = CancellationToken::with_timeout(Duration::ZERO);
+        let err = token.check().unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<Cancelled>(),
+            Some(&Cancelled { timed_out: true })
+        );
+    }
+}
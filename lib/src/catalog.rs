@@ -0,0 +1,268 @@
+// reading and writing OASIS XML catalogs (catalog-v001.xml), as used by Protégé and ROBOT to
+// resolve owl:imports to local files, and reading other inventories of ontologies: DCAT and
+// VoID descriptions, and CSV or YAML manifests
+
+use crate::consts::{DCAT_ACCESS_URL, DCAT_DISTRIBUTION, DCAT_DOWNLOAD_URL, VOID_DATA_DUMP};
+use crate::io::http::{self, HttpOptions};
+use crate::iri::parse_iri;
+use crate::ontology::{file_iri_to_path, OntologyLocation};
+use anyhow::Result;
+use lazy_static::lazy_static;
+use oxigraph::model::{SubjectRef, TermRef};
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+const CATALOG_NAMESPACE: &str = "urn:oasis:names:tc:entity:xmlns:xml:catalog";
+
+lazy_static! {
+    static ref URI_ELEMENT: Regex = Regex::new(r"<uri\s([^>]*)>").unwrap();
+    static ref ATTRIBUTE: Regex = Regex::new(r#"([\w:]+)\s*=\s*"([^"]*)""#).unwrap();
+}
+
+/// A single <uri> entry of a catalog: the ontology IRI and where it can be found
+#[derive(Debug, Clone, PartialEq)]
+pub struct CatalogEntry {
+    pub name: String,
+    pub location: OntologyLocation,
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+fn unescape(s: &str) -> String {
+    s.replace("&quot;", "\"")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Writes the entries as an XML catalog to the given path. Files below the directory of the
+/// catalog are written relative to it, as Protégé does
+pub fn write_catalog(path: &Path, entries: &[CatalogEntry]) -> Result<()> {
+    let catalog_dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.canonicalize()?,
+        _ => std::env::current_dir()?,
+    };
+    let mut xml = String::new();
+    xml.push_str("<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"no\"?>\n");
+    xml.push_str(
+        "<catalog prefer=\"public\" xmlns=\"urn:oasis:names:tc:entity:xmlns:xml:catalog\">\n",
+    );
+    for entry in entries {
+        let uri = match &entry.location {
+            OntologyLocation::File(p) => match p.strip_prefix(&catalog_dir) {
+                Ok(relative) => relative.to_string_lossy().to_string(),
+                Err(_) => entry.location.to_string(),
+            },
+            OntologyLocation::Url(u) => u.clone(),
+        };
+        xml.push_str(&format!(
+            "    <uri id=\"ontoenv\" name=\"{}\" uri=\"{}\"/>\n",
+            escape(&entry.name),
+            escape(&uri)
+        ));
+    }
+    xml.push_str("</catalog>\n");
+    std::fs::write(path, xml)?;
+    Ok(())
+}
+
+/// Reads the <uri> entries from an XML catalog. Relative file locations are resolved against
+/// the directory of the catalog
+pub fn read_catalog(path: &Path) -> Result<Vec<CatalogEntry>> {
+    let xml = std::fs::read_to_string(path)?;
+    parse_catalog(&xml, &OntologyLocation::File(path.canonicalize()?))
+}
+
+fn parse_catalog(xml: &str, base: &OntologyLocation) -> Result<Vec<CatalogEntry>> {
+    let mut entries = vec![];
+    for element in URI_ELEMENT.captures_iter(xml) {
+        let mut name = None;
+        let mut uri = None;
+        for attribute in ATTRIBUTE.captures_iter(&element[1]) {
+            match &attribute[1] {
+                "name" => name = Some(unescape(&attribute[2])),
+                "uri" => uri = Some(unescape(&attribute[2])),
+                _ => {}
+            }
+        }
+        let (name, uri) = match (name, uri) {
+            (Some(name), Some(uri)) => (name, uri),
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "Catalog entry without name or uri: {}",
+                    &element[0]
+                ))
+            }
+        };
+        let location = resolve_location(&uri, base)?;
+        entries.push(CatalogEntry { name, location });
+    }
+    Ok(entries)
+}
+
+// resolves a location listed in an inventory: URLs and file IRIs are taken as they are, anything
+// else is relative to the location of the inventory
+fn resolve_location(uri: &str, base: &OntologyLocation) -> Result<OntologyLocation> {
+    if uri.starts_with("http://") || uri.starts_with("https://") {
+        return Ok(OntologyLocation::Url(uri.to_string()));
+    }
+    if uri.starts_with("file://") {
+        if let Ok(path) = file_iri_to_path(uri) {
+            return Ok(OntologyLocation::File(path));
+        }
+    }
+    let relative = uri
+        .strip_prefix("file://")
+        .or_else(|| uri.strip_prefix("file:"))
+        .unwrap_or(uri);
+    match base {
+        OntologyLocation::File(path) => Ok(OntologyLocation::File(
+            path.parent()
+                .map(PathBuf::from)
+                .unwrap_or_default()
+                .join(relative),
+        )),
+        OntologyLocation::Url(url) => Ok(OntologyLocation::Url(
+            url::Url::parse(url)?.join(relative)?.to_string(),
+        )),
+    }
+}
+
+/// Reads the ontologies listed in an inventory of ontologies, by the extension of its location:
+/// - `.csv`: a manifest with one `<iri>,<location>` line per ontology, after an optional header
+/// - `.yaml` or `.yml`: a manifest with one `<iri>: <location>` line per ontology
+/// - `.xml`: an XML catalog, or else an RDF/XML document as below
+/// - anything else: a DCAT or VoID description in an RDF format, listing the datasets with a
+///   dcat:downloadURL (or else dcat:accessURL) of one of their distributions, or a
+///   void:dataDump. The IRI of each dataset is taken as the name of the ontology
+///
+/// Relative locations are resolved against the location of the inventory
+pub fn read_inventory(location: &OntologyLocation) -> Result<Vec<CatalogEntry>> {
+    let path = match location {
+        OntologyLocation::File(path) => path.clone(),
+        OntologyLocation::Url(url) => match url::Url::parse(url) {
+            Ok(url) => PathBuf::from(url.path()),
+            Err(_) => PathBuf::from(url),
+        },
+    };
+    let extension = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+    match extension.as_str() {
+        "csv" => parse_manifest(&read_text(location)?, location, ","),
+        "yaml" | "yml" => parse_manifest(&read_text(location)?, location, ": "),
+        "xml" => {
+            let xml = read_text(location)?;
+            if xml.contains(CATALOG_NAMESPACE) {
+                parse_catalog(&xml, location)
+            } else {
+                read_dataset_descriptions(location)
+            }
+        }
+        _ => read_dataset_descriptions(location),
+    }
+}
+
+fn read_text(location: &OntologyLocation) -> Result<String> {
+    match location {
+        OntologyLocation::File(path) => Ok(std::fs::read_to_string(path)?),
+        OntologyLocation::Url(url) => {
+            let response = http::fetch(url, &HttpOptions::default())?;
+            Ok(String::from_utf8(response.body)?)
+        }
+    }
+}
+
+// a manifest with one '<iri><separator><location>' line per ontology. Keys and values may be
+// quoted; empty lines, comments ('#'), YAML document markers and a header line whose first
+// column is not an IRI are skipped
+fn parse_manifest(
+    text: &str,
+    base: &OntologyLocation,
+    separator: &str,
+) -> Result<Vec<CatalogEntry>> {
+    let mut entries = vec![];
+    for (number, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line == "---" {
+            continue;
+        }
+        let mut fields = line
+            .split(separator)
+            .map(|field| field.trim().trim_matches('"').trim_matches('\''));
+        let (name, uri) = match (fields.next(), fields.next()) {
+            (Some(name), Some(uri)) if !name.is_empty() && !uri.is_empty() => (name, uri),
+            _ => {
+                return Err(anyhow::anyhow!(
+                    "Line {} of {} does not map an IRI to a location: {}",
+                    number + 1,
+                    base,
+                    line
+                ))
+            }
+        };
+        if entries.is_empty() && !name.contains(':') {
+            continue;
+        }
+        entries.push(CatalogEntry {
+            name: parse_iri(name)?.into_string(),
+            location: resolve_location(uri, base)?,
+        });
+    }
+    Ok(entries)
+}
+
+// the datasets of a DCAT or VoID description with the location of their content, by name
+fn read_dataset_descriptions(location: &OntologyLocation) -> Result<Vec<CatalogEntry>> {
+    let graph = location.graph()?;
+    let mut entries: BTreeMap<String, OntologyLocation> = BTreeMap::new();
+    for triple in graph.triples_for_predicate(DCAT_DISTRIBUTION) {
+        let SubjectRef::NamedNode(dataset) = triple.subject else {
+            continue;
+        };
+        let distribution: SubjectRef = match triple.object {
+            TermRef::NamedNode(node) => node.into(),
+            TermRef::BlankNode(node) => node.into(),
+            _ => continue,
+        };
+        let url = [DCAT_DOWNLOAD_URL, DCAT_ACCESS_URL]
+            .into_iter()
+            .find_map(|property| {
+                match graph.object_for_subject_predicate(distribution, property) {
+                    Some(TermRef::NamedNode(url)) => Some(url),
+                    _ => None,
+                }
+            });
+        if let (Some(url), false) = (url, entries.contains_key(dataset.as_str())) {
+            entries.insert(
+                dataset.as_str().to_string(),
+                resolve_location(url.as_str(), location)?,
+            );
+        }
+    }
+    for triple in graph.triples_for_predicate(VOID_DATA_DUMP) {
+        if let (SubjectRef::NamedNode(dataset), TermRef::NamedNode(url)) =
+            (triple.subject, triple.object)
+        {
+            if !entries.contains_key(dataset.as_str()) {
+                entries.insert(
+                    dataset.as_str().to_string(),
+                    resolve_location(url.as_str(), location)?,
+                );
+            }
+        }
+    }
+    Ok(entries
+        .into_iter()
+        .map(|(name, location)| CatalogEntry { name, location })
+        .collect())
+}
@@ -0,0 +1,122 @@
+// change manifests: after an update which changed some graphs, an environment writes a
+// machine-readable list of them, with the fingerprint of their content, to the file configured
+// as `change_manifest` and POSTs it to the `change_webhook` URL, so downstream services (SPARQL
+// mirrors, search indexes) can sync just the changed graphs instead of polling the environment
+
+use crate::ontology::{GraphIdentifier, Ontology, OntologyLocation};
+use crate::UpdateReport;
+use anyhow::Result;
+use chrono::prelude::*;
+use oxigraph::model::GraphName;
+use reqwest::blocking::Client;
+use reqwest::header::CONTENT_TYPE;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+/// Version of the change manifest format
+pub const CHANGE_MANIFEST_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeKind {
+    Added,
+    Updated,
+    Removed,
+}
+
+/// A graph added, updated or removed by an update
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChangedGraph {
+    pub change: ChangeKind,
+    // name of the ontology
+    pub name: String,
+    // name of the graph holding the ontology in the store
+    pub graph: String,
+    pub location: OntologyLocation,
+    // SHA-256 hash of the content (see `lockfile::content_hash`); none for removed graphs
+    pub fingerprint: Option<String>,
+}
+
+/// The graphs changed by one update
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChangeManifest {
+    pub version: u32,
+    pub root: String,
+    pub timestamp: DateTime<Utc>,
+    pub changes: Vec<ChangedGraph>,
+}
+
+impl ChangeManifest {
+    /// Builds the manifest of an update report. The ontologies are those of the environment
+    /// after the update, which hold the fingerprints of the added and updated graphs
+    pub fn from_report(
+        report: &UpdateReport,
+        ontologies: &BTreeMap<GraphIdentifier, Ontology>,
+        root: &Path,
+    ) -> Result<Self> {
+        let mut changes = vec![];
+        for (change, ids) in [
+            (ChangeKind::Added, &report.added),
+            (ChangeKind::Updated, &report.updated),
+            (ChangeKind::Removed, &report.removed),
+        ] {
+            for id in ids {
+                let ontology = ontologies.get(id);
+                // the stored identifier carries the graph ID the graph is named after
+                let stored = ontology.map_or(id, |o| o.id());
+                changes.push(ChangedGraph {
+                    change,
+                    name: id.name().as_str().to_string(),
+                    graph: match stored.graphname()? {
+                        GraphName::NamedNode(node) => node.into_string(),
+                        graph_name => graph_name.to_string(),
+                    },
+                    location: id.location().clone(),
+                    fingerprint: ontology
+                        .and_then(Ontology::content_hash)
+                        .map(str::to_string),
+                });
+            }
+        }
+        Ok(ChangeManifest {
+            version: CHANGE_MANIFEST_VERSION,
+            root: root.display().to_string(),
+            timestamp: Utc::now(),
+            changes,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Writes the manifest to the file, replacing the manifest of the previous update
+    pub fn write(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)? + "\n")?;
+        Ok(())
+    }
+
+    /// POSTs the manifest as JSON to the URL
+    pub fn post(&self, url: &str) -> Result<()> {
+        let client = Client::builder().timeout(Duration::from_secs(30)).build()?;
+        let response = client
+            .post(url)
+            .header(CONTENT_TYPE, "application/json")
+            .body(serde_json::to_string(self)?)
+            .send()?;
+        if !response.status().is_success() {
+            return Err(anyhow::anyhow!(
+                "Webhook {} answered with status {}",
+                url,
+                response.status()
+            ));
+        }
+        Ok(())
+    }
+}
@@ -1,8 +1,12 @@
-use crate::ontology::OntologyLocation;
-use crate::policy::{DefaultPolicy, ResolutionPolicy};
+use crate::iri::parse_iri;
+use crate::ontology::{NameSource, OntologyLocation};
+use crate::policy::{DefaultPolicy, ResolutionPolicy, VersionRequirement};
+use crate::util::{rdf_format_from_name, BaseIriOptions};
 use anyhow::Result;
 use glob::{Pattern, PatternError};
+use oxigraph::model::NamedNode;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::io::{BufReader, Write};
 use std::path::{Path, PathBuf};
 
@@ -26,6 +30,21 @@ where
     patterns.map_err(serde::de::Error::custom)
 }
 
+fn pattern_ser<S>(pattern: &Pattern, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    serializer.serialize_str(pattern.as_str())
+}
+
+fn pattern_de<'de, D>(deserializer: D) -> Result<Pattern, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let pattern = String::deserialize(deserializer)?;
+    Pattern::new(&pattern).map_err(serde::de::Error::custom)
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub struct EnvironmentConfig {
     pub ontologies: Vec<OntologyConfig>,
@@ -38,6 +57,221 @@ pub struct OntologyConfig {
     pub version: Option<String>,
 }
 
+/// A rule which forbids ontologies at certain locations from (transitively) importing
+/// certain ontologies, e.g. "nothing under ./models may depend on http://example.com/deprecated/*"
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ImportConstraint {
+    // glob pattern over the locations of the ontologies this rule applies to. Relative
+    // patterns are matched against the location relative to the environment root
+    #[serde(serialize_with = "pattern_ser", deserialize_with = "pattern_de")]
+    pub from: Pattern,
+    // glob pattern over the names of ontologies which must not appear in the imports closure
+    #[serde(serialize_with = "pattern_ser", deserialize_with = "pattern_de")]
+    pub forbid: Pattern,
+}
+
+impl ImportConstraint {
+    pub fn new(from: &str, forbid: &str) -> Result<Self> {
+        let from = from.trim_start_matches("./");
+        Ok(ImportConstraint {
+            from: Pattern::new(from)?,
+            forbid: Pattern::new(forbid)?,
+        })
+    }
+
+    /// Parses a constraint of the form `<location glob>=<ontology name glob>`
+    pub fn from_str(s: &str) -> Result<Self> {
+        let (from, forbid) = s.split_once('=').ok_or(anyhow::anyhow!(
+            "Import constraint must be of the form <location>=<forbidden ontology>: {}",
+            s
+        ))?;
+        Self::new(from.trim(), forbid.trim())
+    }
+
+    /// Returns true if this rule applies to an ontology at the given location
+    pub fn applies_to(&self, location: &OntologyLocation, root: &Path) -> bool {
+        match location {
+            OntologyLocation::File(path) => {
+                self.from.matches_path(path)
+                    || path
+                        .strip_prefix(root)
+                        .map(|rel| self.from.matches_path(rel))
+                        .unwrap_or(false)
+            }
+            OntologyLocation::Url(url) => self.from.matches(url),
+        }
+    }
+
+    /// Returns true if the given ontology name may not be imported under this rule
+    pub fn forbids(&self, name: &str) -> bool {
+        self.forbid.matches(name)
+    }
+}
+
+impl std::fmt::Display for ImportConstraint {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{} must not import {}", self.from, self.forbid)
+    }
+}
+
+/// An optional group of imports of an ontology (e.g. the inference rules shipped alongside
+/// Brick). Closures only include the group when its feature is requested
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct OptionalImports {
+    // name of the ontology the group belongs to
+    pub ontology: String,
+    // name of the feature which enables the group
+    pub feature: String,
+    // names of the ontologies in the group. If the ontology also owl:imports one of them, the
+    // import is left out of closures unless the feature is requested
+    pub imports: Vec<String>,
+}
+
+impl OptionalImports {
+    pub fn new(ontology: &str, feature: &str, imports: &[&str]) -> Self {
+        OptionalImports {
+            ontology: ontology.to_string(),
+            feature: feature.to_string(),
+            imports: imports.iter().map(|i| i.to_string()).collect(),
+        }
+    }
+}
+
+/// A named subset of an environment, e.g. the "dev", "release" or "experimental" ontologies of
+/// a repository: the local files it sees and how it resolves imports. All profiles of an
+/// environment share its store (see `OntoEnv::set_profile`)
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct Profile {
+    // directories whose files belong to the profile, relative to the root or absolute; empty for
+    // the search directories of the environment. They are searched for ontologies in addition
+    // to those of the environment
+    #[serde(default)]
+    pub search_directories: Vec<PathBuf>,
+    // include patterns; empty for the includes of the environment
+    #[serde(
+        default,
+        serialize_with = "vec_pattern_ser",
+        deserialize_with = "vec_pattern_de"
+    )]
+    includes: Vec<Pattern>,
+    // exclude patterns, in addition to the excludes of the environment
+    #[serde(
+        default,
+        serialize_with = "vec_pattern_ser",
+        deserialize_with = "vec_pattern_de"
+    )]
+    excludes: Vec<Pattern>,
+    // resolution policy; None for the policy of the environment
+    #[serde(default)]
+    pub resolution_policy: Option<String>,
+}
+
+impl Profile {
+    pub fn new<I, J>(
+        search_directories: Vec<PathBuf>,
+        includes: I,
+        excludes: J,
+        resolution_policy: Option<String>,
+    ) -> Result<Self>
+    where
+        I: IntoIterator,
+        I::Item: AsRef<str>,
+        J: IntoIterator,
+        J::Item: AsRef<str>,
+    {
+        let includes = includes
+            .into_iter()
+            .map(|p| Pattern::new(p.as_ref()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let excludes = excludes
+            .into_iter()
+            .map(|p| Pattern::new(p.as_ref()))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Profile {
+            search_directories,
+            includes,
+            excludes,
+            resolution_policy,
+        })
+    }
+
+    /// Parses a profile given as `<name>=<key>=<value>[;<key>=<value>...]`, with the keys dir,
+    /// include and exclude (which may be repeated) and policy, e.g.
+    /// `release=dir=ontologies/release;policy=latest`
+    pub fn from_str(s: &str) -> Result<(String, Self)> {
+        let (name, settings) = s.split_once('=').ok_or(anyhow::anyhow!(
+            "Profile must be of the form <name>=<key>=<value>[;<key>=<value>...]: {}",
+            s
+        ))?;
+        let mut directories = vec![];
+        let mut includes = vec![];
+        let mut excludes = vec![];
+        let mut policy = None;
+        for setting in settings.split(';').filter(|s| !s.trim().is_empty()) {
+            let (key, value) = setting.split_once('=').ok_or(anyhow::anyhow!(
+                "Profile settings must be of the form <key>=<value>: {}",
+                setting
+            ))?;
+            let value = value.trim();
+            match key.trim() {
+                "dir" => directories.push(PathBuf::from(value)),
+                "include" => includes.push(value),
+                "exclude" => excludes.push(value),
+                "policy" => policy = Some(value.to_string()),
+                key => return Err(anyhow::anyhow!("Unknown profile setting: {}", key)),
+            }
+        }
+        let profile = Profile::new(directories, includes, excludes, policy)?;
+        Ok((name.trim().to_string(), profile))
+    }
+}
+
+// parsed documents take several times the size of their source in memory
+const DOCUMENT_MEMORY_FACTOR: u64 = 10;
+
+/// Caps on the resources an environment uses, e.g. to run in a small CI container. Unset caps
+/// leave the resource unlimited (or at its default). Work which would exceed a cap degrades
+/// instead of failing: documents are read one after the other rather than in parallel, and
+/// unions are not cached
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct ResourceLimits {
+    // bytes of memory for documents read at once and for the unions kept in memory. Updates
+    // read fewer documents in parallel (down to one at a time) to stay within it, and unions
+    // which do not fit are not kept
+    #[serde(default)]
+    pub max_memory: Option<u64>,
+    // number of unions kept in memory, see cache::MEMORY_CACHE_UNIONS; 0 keeps none
+    #[serde(default)]
+    pub memory_cache_unions: Option<usize>,
+    // remote documents fetched at once
+    #[serde(default)]
+    pub max_downloads: Option<usize>,
+    // bytes of unions cached on disk in .ontoenv/cache/closures; the oldest are removed to stay
+    // within it, and unions larger than it are not cached. Also caps the graphs kept for
+    // snapshots in .ontoenv/snapshots/graphs, see snapshots.rs
+    #[serde(default)]
+    pub max_cache_size: Option<u64>,
+}
+
+impl ResourceLimits {
+    /// Returns how many documents of at most `largest` bytes can be read at once within
+    /// `max_memory`, at most `workers` and at least one
+    pub fn read_workers(&self, workers: usize, largest: u64) -> usize {
+        let Some(max_memory) = self.max_memory else {
+            return workers;
+        };
+        let per_document = largest.saturating_mul(DOCUMENT_MEMORY_FACTOR).max(1);
+        let fitting = usize::try_from(max_memory / per_document).unwrap_or(usize::MAX);
+        fitting.clamp(1, workers.max(1))
+    }
+
+    /// Returns the number of unions to keep in memory
+    pub fn memory_cache_unions(&self) -> usize {
+        self.memory_cache_unions
+            .unwrap_or(crate::cache::MEMORY_CACHE_UNIONS)
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct Config {
     pub root: PathBuf,
@@ -59,10 +293,199 @@ pub struct Config {
     pub require_ontology_names: bool,
     // strict mode (does not allow for any errors in the ontology files)
     pub strict: bool,
+    // override `strict` for one kind of error each, see StrictRule
+    #[serde(default)]
+    pub strict_parse: Option<bool>,
+    #[serde(default)]
+    pub strict_missing_imports: Option<bool>,
+    #[serde(default)]
+    pub strict_network: Option<bool>,
+    #[serde(default)]
+    pub strict_duplicates: Option<bool>,
+    #[serde(default)]
+    pub strict_constraints: Option<bool>,
     // offline mode (does not fetch remote ontologies)
     pub offline: bool,
     // resolution policy
     pub resolution_policy: String,
+    // whether imports of duplicated ontologies resolve to one of the duplicates or to all of
+    // them, see DuplicateStrategy
+    #[serde(default)]
+    pub duplicate_strategy: DuplicateStrategy,
+    // rules restricting which ontologies may be (transitively) imported
+    #[serde(default)]
+    pub import_constraints: Vec<ImportConstraint>,
+    // ontology name -> requirement on its version (e.g. ">=1.3", see VersionRequirement) which
+    // the ontologies of that name in the environment must satisfy
+    #[serde(default)]
+    pub version_constraints: BTreeMap<String, String>,
+    // optional import groups, enabled per closure by feature name
+    #[serde(default)]
+    pub optional_imports: Vec<OptionalImports>,
+    // predicates (IRIs) whose objects on an ontology are followed like its owl:imports, e.g.
+    // dcterms:requires for vocabularies which do not use owl:imports: updates fetch them and
+    // closures include them
+    #[serde(default)]
+    pub traversal_predicates: Vec<String>,
+    // resolve relative IRIs in documents which do not declare a base IRI against their location:
+    // the file:// URL of a local file or the URL of a remote document
+    #[serde(default = "default_true")]
+    pub use_location_base_iri: bool,
+    // base IRI for relative IRIs in documents which do not declare one, if
+    // use_location_base_iri is false
+    #[serde(default)]
+    pub default_base_iri: Option<String>,
+    // in strict mode, reject files with relative IRIs which do not declare a base IRI
+    #[serde(default)]
+    pub reject_relative_iris: bool,
+    // file extension (without the dot, lowercase) -> format of the files with that extension:
+    // turtle, ntriples, nquads, trig, rdfxml, n3 or sniff. The empty extension stands for files
+    // without one. Files with a configured extension are searched for in addition to the
+    // includes, and take precedence over the built-in extensions
+    #[serde(default)]
+    pub formats: BTreeMap<String, String>,
+    // import IRI -> location (a URL, or a path relative to the root) owl:imports of the IRI are
+    // read from, e.g. to pin a vocabulary to a mirrored copy. The ontology read there is named
+    // after the import, whatever it declares itself
+    #[serde(default)]
+    pub import_map: BTreeMap<String, String>,
+    // files larger than this many bytes are not read
+    #[serde(default)]
+    pub max_file_size: Option<u64>,
+    // give up on reading an ontology after this many seconds
+    #[serde(default)]
+    pub parse_timeout_secs: Option<u64>,
+    // remote ontologies fetched more than this many seconds ago are fetched again by every
+    // update, with a conditional request so unchanged ontologies are not downloaded again
+    #[serde(default)]
+    pub cache_ttl: Option<u64>,
+    // follow symbolic links (and junctions) when searching for ontologies. Files are then
+    // recorded under their canonical path, and links which lead back to one of their own parent
+    // directories are skipped
+    #[serde(default)]
+    pub follow_symlinks: bool,
+    // file (relative to the root) to write the change manifest of every update to, see
+    // changes.rs
+    #[serde(default)]
+    pub change_manifest: Option<PathBuf>,
+    // URL to POST the change manifest of every update to
+    #[serde(default)]
+    pub change_webhook: Option<String>,
+    // number of documents read and parsed at once during updates; defaults to the number of
+    // CPUs. 1 reads them one after the other
+    #[serde(default)]
+    pub max_parallel: Option<usize>,
+    // caps on memory, downloads and cache files, see ResourceLimits
+    #[serde(default)]
+    pub limits: ResourceLimits,
+    // keep a copy of the graphs of the snapshots recorded by updates, so they can be loaded and
+    // rolled back to once their ontologies changed. Otherwise only tagged snapshots keep copies
+    #[serde(default)]
+    pub snapshot_graphs: bool,
+    // resolve imports which differ from the name of an ontology only in a trailing '/' or '#'
+    // (e.g. http://example.org/ont/ for http://example.org/ont) to that ontology, when no
+    // ontology has exactly the imported name
+    #[serde(default)]
+    pub ignore_trailing_separators: bool,
+    // the environment holds only the imports closure of the ontology at this location (see
+    // `OntoEnv::restrict_to_root`): the search directories are no longer scanned, and
+    // ontologies which drop out of the closure are removed by updates
+    #[serde(default)]
+    pub root_ontology: Option<OntologyLocation>,
+    // names of the entry-point ontologies of the environment; ontologies outside all of their
+    // imports closures are orphans (see `OntoEnv::orphans`)
+    #[serde(default)]
+    pub roots: Vec<String>,
+    // declarations to infer the names of ontologies without an owl:Ontology declaration from,
+    // in order of priority. Empty by default, so such ontologies are anonymous
+    #[serde(default)]
+    pub name_inference: Vec<NameSource>,
+    // named subsets of the environment, see Profile
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Profile>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// The kinds of errors strict mode makes fatal. Each can be enabled or disabled on its own with
+/// the corresponding `strict_*` setting; unset, it follows `strict`, except for `Duplicates`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StrictRule {
+    // files which cannot be read or parsed, or which an inspector rejects
+    Parse,
+    // owl:imports which do not resolve to an ontology in the environment
+    MissingImports,
+    // remote ontologies which cannot be fetched, or are skipped in offline mode
+    Network,
+    // ontologies with the same name as one at another location. Off unless enabled on its
+    // own, since environments often hold several versions of an ontology
+    Duplicates,
+    // violations of the import and version constraints
+    Constraints,
+}
+
+impl std::str::FromStr for StrictRule {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().replace('-', "_").as_str() {
+            "parse" => Ok(StrictRule::Parse),
+            "missing_imports" => Ok(StrictRule::MissingImports),
+            "network" => Ok(StrictRule::Network),
+            "duplicates" => Ok(StrictRule::Duplicates),
+            "constraints" => Ok(StrictRule::Constraints),
+            _ => Err(anyhow::anyhow!("Unknown strict rule: {}", s)),
+        }
+    }
+}
+
+// prefixes which may be used in traversal predicates, e.g. 'dcterms:requires'
+const PREDICATE_PREFIXES: [(&str, &str); 6] = [
+    ("rdfs", "http://www.w3.org/2000/01/rdf-schema#"),
+    ("owl", "http://www.w3.org/2002/07/owl#"),
+    ("dcterms", "http://purl.org/dc/terms/"),
+    ("skos", "http://www.w3.org/2004/02/skos/core#"),
+    ("prov", "http://www.w3.org/ns/prov#"),
+    ("void", "http://rdfs.org/ns/void#"),
+];
+
+/// Parses a predicate given as an IRI or as a CURIE with one of the prefixes rdfs, owl,
+/// dcterms, skos, prov or void, e.g. `rdfs:seeAlso` or `owl:priorVersion`
+pub fn parse_predicate(predicate: &str) -> Result<NamedNode> {
+    let expanded = predicate.split_once(':').and_then(|(prefix, local)| {
+        PREDICATE_PREFIXES
+            .iter()
+            .find(|(known, _)| *known == prefix)
+            .map(|(_, namespace)| format!("{}{}", namespace, local))
+    });
+    parse_iri(expanded.as_deref().unwrap_or(predicate))
+}
+
+/// How imports of an ontology with several locations in the environment (duplicates) are
+/// resolved
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum DuplicateStrategy {
+    /// Imports resolve to one of the duplicates, chosen by the resolution policy
+    #[default]
+    Pick,
+    /// Imports resolve to all of the duplicates, so closures and unions hold the triples of
+    /// every location. For ontologies split across several files
+    Merge,
+}
+
+impl std::str::FromStr for DuplicateStrategy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "pick" => Ok(DuplicateStrategy::Pick),
+            "merge" => Ok(DuplicateStrategy::Merge),
+            _ => Err(anyhow::anyhow!("Unknown duplicate strategy: {}", s)),
+        }
+    }
 }
 
 impl Config {
@@ -104,8 +527,37 @@ impl Config {
             excludes: vec![],
             require_ontology_names,
             strict,
+            strict_parse: None,
+            strict_missing_imports: None,
+            strict_network: None,
+            strict_duplicates: None,
+            strict_constraints: None,
             offline,
             resolution_policy,
+            duplicate_strategy: DuplicateStrategy::Pick,
+            import_constraints: vec![],
+            version_constraints: BTreeMap::new(),
+            optional_imports: vec![],
+            traversal_predicates: vec![],
+            use_location_base_iri: true,
+            default_base_iri: None,
+            reject_relative_iris: false,
+            formats: BTreeMap::new(),
+            import_map: BTreeMap::new(),
+            max_file_size: None,
+            parse_timeout_secs: None,
+            cache_ttl: None,
+            follow_symlinks: false,
+            change_manifest: None,
+            change_webhook: None,
+            max_parallel: None,
+            limits: ResourceLimits::default(),
+            snapshot_graphs: false,
+            ignore_trailing_separators: false,
+            root_ontology: None,
+            roots: vec![],
+            name_inference: vec![],
+            profiles: BTreeMap::new(),
         };
         let includes: Vec<String> = includes
             .into_iter()
@@ -160,6 +612,156 @@ impl Config {
         )
     }
 
+    /// Returns the number of documents read at once during updates: `max_parallel`, or the
+    /// number of CPUs
+    pub fn parallelism(&self) -> usize {
+        self.max_parallel
+            .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |n| n.get()))
+            .max(1)
+    }
+
+    /// Returns whether errors of the given kind are fatal
+    pub fn is_strict(&self, rule: StrictRule) -> bool {
+        let setting = match rule {
+            StrictRule::Parse => self.strict_parse,
+            StrictRule::MissingImports => self.strict_missing_imports,
+            StrictRule::Network => self.strict_network,
+            StrictRule::Constraints => self.strict_constraints,
+            StrictRule::Duplicates => return self.strict_duplicates.unwrap_or(false),
+        };
+        setting.unwrap_or(self.strict)
+    }
+
+    /// Enables or disables one kind of strictness, independently of `strict`
+    pub fn set_strict(&mut self, rule: StrictRule, enabled: bool) {
+        let setting = match rule {
+            StrictRule::Parse => &mut self.strict_parse,
+            StrictRule::MissingImports => &mut self.strict_missing_imports,
+            StrictRule::Network => &mut self.strict_network,
+            StrictRule::Duplicates => &mut self.strict_duplicates,
+            StrictRule::Constraints => &mut self.strict_constraints,
+        };
+        *setting = Some(enabled);
+    }
+
+    /// Parses a strictness setting of the form `<rule>=<true|false>` (e.g. `network=false`),
+    /// or just `<rule>` to enable it
+    pub fn set_strict_str(&mut self, s: &str) -> Result<()> {
+        let (rule, enabled) = match s.split_once('=') {
+            Some((rule, enabled)) => (rule, enabled.trim().parse::<bool>()?),
+            None => (s, true),
+        };
+        self.set_strict(rule.trim().parse()?, enabled);
+        Ok(())
+    }
+
+    /// Returns how relative IRIs are resolved when reading ontologies
+    pub fn base_iri_options(&self) -> BaseIriOptions {
+        BaseIriOptions {
+            use_document_url: self.use_location_base_iri,
+            default_base_iri: self.default_base_iri.clone(),
+            reject_relative_iris: self.is_strict(StrictRule::Parse) && self.reject_relative_iris,
+            formats: self.formats.clone(),
+            deadline: None,
+        }
+    }
+
+    /// Reads files with the given extension (e.g. "owl", or "" for files without an extension)
+    /// in the given format, see `formats`
+    pub fn add_format(&mut self, extension: &str, format: &str) -> Result<()> {
+        // validate the name
+        rdf_format_from_name(format)?;
+        let extension = extension.trim_start_matches('.').to_lowercase();
+        self.formats.insert(extension, format.to_lowercase());
+        Ok(())
+    }
+
+    /// Parses a format mapping of the form `<extension>=<format>`, e.g. `owl=rdfxml`
+    pub fn add_format_str(&mut self, s: &str) -> Result<()> {
+        let (extension, format) = s.split_once('=').ok_or(anyhow::anyhow!(
+            "Format mapping must be of the form <extension>=<format>: {}",
+            s
+        ))?;
+        self.add_format(extension.trim(), format.trim())
+    }
+
+    /// Follows the predicate (see `parse_predicate`) like owl:imports, see `traversal_predicates`
+    pub fn add_traversal_predicate(&mut self, predicate: &str) -> Result<()> {
+        let predicate = parse_predicate(predicate)?.into_string();
+        if !self.traversal_predicates.contains(&predicate) {
+            self.traversal_predicates.push(predicate);
+        }
+        Ok(())
+    }
+
+    /// Adds the name of an entry-point ontology, see `roots`
+    pub fn add_root(&mut self, name: &str) -> Result<()> {
+        let name = parse_iri(name)?.into_string();
+        if !self.roots.contains(&name) {
+            self.roots.push(name);
+        }
+        Ok(())
+    }
+
+    /// Returns the traversal predicates, leaving out invalid ones
+    pub fn traversal_predicates(&self) -> Vec<NamedNode> {
+        self.traversal_predicates
+            .iter()
+            .filter_map(|predicate| NamedNode::new(predicate).ok())
+            .collect()
+    }
+
+    /// Reads owl:imports of the IRI from the location instead, see `import_map`
+    pub fn add_import_mapping(&mut self, import: &str, location: &str) -> Result<()> {
+        let import = parse_iri(import)?;
+        self.import_map
+            .insert(import.into_string(), location.to_string());
+        Ok(())
+    }
+
+    /// Parses an import mapping of the form `<import>=<location>`, e.g.
+    /// `http://qudt.org/2.1/schema/qudt=vendor/qudt.ttl`
+    pub fn add_import_mapping_str(&mut self, s: &str) -> Result<()> {
+        let (import, location) = s.split_once('=').ok_or(anyhow::anyhow!(
+            "Import mapping must be of the form <import>=<location>: {}",
+            s
+        ))?;
+        self.add_import_mapping(import.trim(), location.trim())
+    }
+
+    /// Requires the versions of the ontologies with the given name to satisfy the requirement,
+    /// e.g. `>=1.3` or `>=1.3, <2`, see `version_constraints`
+    pub fn add_version_constraint(&mut self, name: &str, requirement: &str) -> Result<()> {
+        let name = parse_iri(name)?;
+        let requirement: VersionRequirement = requirement.parse()?;
+        self.version_constraints
+            .insert(name.into_string(), requirement.to_string());
+        Ok(())
+    }
+
+    /// Parses a version constraint of the form `<name><requirement>`, e.g.
+    /// `https://brickschema.org/schema/Brick>=1.3`
+    pub fn add_version_constraint_str(&mut self, s: &str) -> Result<()> {
+        let index = s.find(['<', '>', '=', '!']).ok_or(anyhow::anyhow!(
+            "Version constraint must be of the form <ontology><requirement>: {}",
+            s
+        ))?;
+        self.add_version_constraint(s[..index].trim(), &s[index..])
+    }
+
+    /// it. Relative paths are resolved against the root
+    pub fn mapped_location(&self, import: &str) -> Result<Option<OntologyLocation>> {
+        let Some(target) = self.import_map.get(import) else {
+            return Ok(None);
+        };
+        let location = if target.contains("://") {
+            OntologyLocation::from_str(target)?
+        } else {
+            OntologyLocation::File(self.root.join(target))
+        };
+        Ok(Some(location))
+    }
+
     /// Determines if a file is included in the ontology environment configuration
     pub fn is_included(&self, path: &Path) -> bool {
         for exclude in self.excludes.iter() {
@@ -172,10 +774,86 @@ impl Config {
                 return true;
             }
         }
+        // the environment's own files (e.g. the store) have no extension, so they are never
+        // included by a mapping for extensionless files
+        let in_environment = path.components().any(|c| c.as_os_str() == ".ontoenv");
+        if !self.formats.is_empty() && !in_environment {
+            let extension = path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .unwrap_or_default()
+                .to_lowercase();
+            if self.formats.contains_key(&extension) {
+                return true;
+            }
+        }
         // default: if no includes are defined, then include everything
         self.includes.is_empty()
     }
 
+    /// Returns the profile with the given name
+    pub fn profile(&self, name: &str) -> Result<&Profile> {
+        self.profiles
+            .get(name)
+            .ok_or(anyhow::anyhow!("Unknown profile: {}", name))
+    }
+
+    /// Returns the directories searched for ontologies: the search directories of the
+    /// environment and of each of its profiles, without duplicates
+    pub fn scanned_directories(&self) -> Vec<PathBuf> {
+        let mut directories = self.search_directories.clone();
+        for profile in self.profiles.values() {
+            for directory in &profile.search_directories {
+                let directory = self.root.join(directory);
+                if !directories.contains(&directory) {
+                    directories.push(directory);
+                }
+            }
+        }
+        directories
+    }
+
+    /// Returns true if the file belongs to the profile: it is in one of the profile's search
+    /// directories, matches its includes and is not excluded by it or by the environment
+    pub fn in_profile(&self, profile: &Profile, path: &Path) -> bool {
+        let directories = if profile.search_directories.is_empty() {
+            &self.search_directories
+        } else {
+            &profile.search_directories
+        };
+        if !directories
+            .iter()
+            .any(|dir| path.starts_with(dir) || path.starts_with(self.root.join(dir)))
+        {
+            return false;
+        }
+        if self
+            .excludes
+            .iter()
+            .chain(profile.excludes.iter())
+            .any(|exclude| exclude.matches_path(path))
+        {
+            return false;
+        }
+        if profile.includes.is_empty() {
+            return self.is_included(path);
+        }
+        profile
+            .includes
+            .iter()
+            .any(|include| include.matches_path(path))
+    }
+
+    /// Returns true if the file is read into the environment: it is included by the
+    /// environment (see `is_included`) or belongs to one of its profiles
+    pub fn is_indexed(&self, path: &Path) -> bool {
+        self.is_included(path)
+            || self
+                .profiles
+                .values()
+                .any(|profile| self.in_profile(profile, path))
+    }
+
     pub fn save_to_file(&self, file: &Path) -> Result<()> {
         let config_str = serde_json::to_string_pretty(&self)?;
         let mut file = std::fs::File::create(file)?;
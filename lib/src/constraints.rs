@@ -0,0 +1,150 @@
+// evaluation of the import and version constraints declared in the environment configuration
+// against the owl:imports closure of each ontology
+
+use crate::config::ImportConstraint;
+use crate::ontology::GraphIdentifier;
+use crate::policy::VersionRequirement;
+use crate::OntoEnv;
+use anyhow::Result;
+use oxigraph::model::NamedNode;
+use std::collections::HashSet;
+use std::fmt;
+
+/// An ontology whose imports closure contains an ontology forbidden by an ImportConstraint
+#[derive(Debug, Clone)]
+pub struct ConstraintViolation {
+    pub ontology: GraphIdentifier,
+    pub forbidden: NamedNode,
+    pub constraint: ImportConstraint,
+}
+
+impl fmt::Display for ConstraintViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} depends on {} (rule: {})",
+            self.ontology, self.forbidden, self.constraint
+        )
+    }
+}
+
+/// Checks every ontology in the environment against the configured import constraints.
+/// Both resolved and unresolved imports in the closure are considered.
+pub fn check_import_constraints(env: &OntoEnv) -> Result<Vec<ConstraintViolation>> {
+    let mut violations = vec![];
+    if env.config.import_constraints.is_empty() {
+        return Ok(violations);
+    }
+
+    let mut ids: Vec<&GraphIdentifier> = env.ontologies.keys().collect();
+    ids.sort_by_key(|id| id.to_string());
+    for id in ids {
+        let constraints: Vec<&ImportConstraint> = env
+            .config
+            .import_constraints
+            .iter()
+            .filter(|c| c.applies_to(id.location(), &env.config.root))
+            .collect();
+        if constraints.is_empty() {
+            continue;
+        }
+
+        // collect the names of everything reachable through owl:imports, including imports
+        // which could not be resolved to an ontology in the environment
+        let mut reachable: HashSet<NamedNode> = HashSet::new();
+        for member in env.get_dependency_closure(id)? {
+            if let Some(ontology) = env.ontologies.get(&member) {
+                if &member != id {
+                    reachable.insert(ontology.name());
+                }
+                reachable.extend(ontology.imports.iter().cloned());
+            }
+        }
+        let mut reachable: Vec<NamedNode> = reachable.into_iter().collect();
+        reachable.sort();
+
+        for constraint in constraints {
+            for name in reachable.iter() {
+                if constraint.forbids(name.as_str()) {
+                    violations.push(ConstraintViolation {
+                        ontology: id.clone(),
+                        forbidden: name.clone(),
+                        constraint: constraint.clone(),
+                    });
+                }
+            }
+        }
+    }
+    Ok(violations)
+}
+
+/// An ontology whose version does not satisfy the version constraint on its name, together
+/// with the ontologies whose imports closure resolves to it
+#[derive(Debug, Clone)]
+pub struct VersionViolation {
+    pub ontology: GraphIdentifier,
+    // the version the ontology declares, see Ontology::version
+    pub version: Option<String>,
+    pub requirement: VersionRequirement,
+    pub dependents: Vec<GraphIdentifier>,
+}
+
+impl fmt::Display for VersionViolation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.version {
+            Some(version) => write!(f, "{} has version {}", self.ontology, version)?,
+            None => write!(f, "{} declares no version", self.ontology)?,
+        }
+        write!(f, " (required: {})", self.requirement)?;
+        if !self.dependents.is_empty() {
+            let names: Vec<String> = self
+                .dependents
+                .iter()
+                .map(|d| d.name().as_str().to_string())
+                .collect();
+            write!(f, ", imported by {}", names.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks the versions of the ontologies in the environment against the configured version
+/// constraints. Ontologies which declare no version violate any constraint on their name
+pub fn check_version_constraints(env: &OntoEnv) -> Result<Vec<VersionViolation>> {
+    let mut violations = vec![];
+    if env.config.version_constraints.is_empty() {
+        return Ok(violations);
+    }
+
+    let mut ids: Vec<&GraphIdentifier> = env.ontologies.keys().collect();
+    ids.sort_by_key(|id| id.to_string());
+    for (name, requirement) in env.config.version_constraints.iter() {
+        let requirement: VersionRequirement = requirement.parse()?;
+        for id in ids.iter().filter(|id| id.name().as_str() == name) {
+            let version = env.ontologies.get(*id).and_then(|o| o.version());
+            if version.as_deref().is_some_and(|v| requirement.matches(v)) {
+                continue;
+            }
+            violations.push(VersionViolation {
+                ontology: (*id).clone(),
+                version,
+                requirement: requirement.clone(),
+                dependents: vec![],
+            });
+        }
+    }
+    if violations.is_empty() {
+        return Ok(violations);
+    }
+
+    // flag the closures which resolve to a violating version
+    for id in ids {
+        let closure = env.get_dependency_closure(id)?;
+        for violation in violations.iter_mut() {
+            if &violation.ontology != id && closure.contains(&violation.ontology) {
+                violation.dependents.push(id.clone());
+            }
+        }
+    }
+    Ok(violations)
+}
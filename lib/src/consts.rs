@@ -7,12 +7,42 @@ pub const IMPORTS: NamedNodeRef<'_> =
 pub const TYPE: NamedNodeRef<'_> =
     NamedNodeRef::new_unchecked("http://www.w3.org/1999/02/22-rdf-syntax-ns#type");
 
+// owl and rdfs terms used for documentation
+pub const CLASS: NamedNodeRef<'_> =
+    NamedNodeRef::new_unchecked("http://www.w3.org/2002/07/owl#Class");
+pub const OBJECT_PROPERTY: NamedNodeRef<'_> =
+    NamedNodeRef::new_unchecked("http://www.w3.org/2002/07/owl#ObjectProperty");
+pub const DATATYPE_PROPERTY: NamedNodeRef<'_> =
+    NamedNodeRef::new_unchecked("http://www.w3.org/2002/07/owl#DatatypeProperty");
+pub const ANNOTATION_PROPERTY: NamedNodeRef<'_> =
+    NamedNodeRef::new_unchecked("http://www.w3.org/2002/07/owl#AnnotationProperty");
+pub const SKOS_DEFINITION: NamedNodeRef<'_> =
+    NamedNodeRef::new_unchecked("http://www.w3.org/2004/02/skos/core#definition");
+pub const RDFS_CLASS: NamedNodeRef<'_> =
+    NamedNodeRef::new_unchecked("http://www.w3.org/2000/01/rdf-schema#Class");
+pub const RDF_PROPERTY: NamedNodeRef<'_> =
+    NamedNodeRef::new_unchecked("http://www.w3.org/1999/02/22-rdf-syntax-ns#Property");
+
+// the types whose instances count as terms declared by an ontology
+pub const DECLARATION_TYPES: [NamedNodeRef<'_>; 6] = [
+    CLASS,
+    RDFS_CLASS,
+    OBJECT_PROPERTY,
+    DATATYPE_PROPERTY,
+    ANNOTATION_PROPERTY,
+    RDF_PROPERTY,
+];
+
 // uris for ontology versioning
 // owl
 pub const VERSION_INFO: NamedNodeRef<'_> =
     NamedNodeRef::new_unchecked("http://www.w3.org/2002/07/owl#versionInfo");
 pub const VERSION_IRI: NamedNodeRef<'_> =
     NamedNodeRef::new_unchecked("http://www.w3.org/2002/07/owl#versionIRI");
+pub const PRIOR_VERSION: NamedNodeRef<'_> =
+    NamedNodeRef::new_unchecked("http://www.w3.org/2002/07/owl#priorVersion");
+pub const BACKWARD_COMPATIBLE_WITH: NamedNodeRef<'_> =
+    NamedNodeRef::new_unchecked("http://www.w3.org/2002/07/owl#backwardCompatibleWith");
 // rdfs
 pub const DEFINED_BY: NamedNodeRef<'_> =
     NamedNodeRef::new_unchecked("http://www.w3.org/2000/01/rdf-schema#isDefinedBy");
@@ -25,19 +55,44 @@ pub const CREATED: NamedNodeRef<'_> =
     NamedNodeRef::new_unchecked("http://purl.org/dc/terms/created");
 pub const MODIFIED: NamedNodeRef<'_> =
     NamedNodeRef::new_unchecked("http://purl.org/dc/terms/modified");
+pub const ISSUED: NamedNodeRef<'_> =
+    NamedNodeRef::new_unchecked("http://purl.org/dc/terms/issued");
 pub const HAS_VERSION: NamedNodeRef<'_> =
     NamedNodeRef::new_unchecked("http://purl.org/dc/terms/hasVersion");
 pub const TITLE: NamedNodeRef<'_> = NamedNodeRef::new_unchecked("http://purl.org/dc/terms/title");
+pub const DC_IDENTIFIER: NamedNodeRef<'_> =
+    NamedNodeRef::new_unchecked("http://purl.org/dc/terms/identifier");
+// declarations of datasets and vocabularies, see ontology::NameSource
+pub const VOID_DATASET: NamedNodeRef<'_> =
+    NamedNodeRef::new_unchecked("http://rdfs.org/ns/void#Dataset");
+pub const SKOS_CONCEPT_SCHEME: NamedNodeRef<'_> =
+    NamedNodeRef::new_unchecked("http://www.w3.org/2004/02/skos/core#ConceptScheme");
+// inventories of ontologies, see catalog::read_inventory
+pub const DCAT_DISTRIBUTION: NamedNodeRef<'_> =
+    NamedNodeRef::new_unchecked("http://www.w3.org/ns/dcat#distribution");
+pub const DCAT_DOWNLOAD_URL: NamedNodeRef<'_> =
+    NamedNodeRef::new_unchecked("http://www.w3.org/ns/dcat#downloadURL");
+pub const DCAT_ACCESS_URL: NamedNodeRef<'_> =
+    NamedNodeRef::new_unchecked("http://www.w3.org/ns/dcat#accessURL");
+pub const VOID_DATA_DUMP: NamedNodeRef<'_> =
+    NamedNodeRef::new_unchecked("http://rdfs.org/ns/void#dataDump");
 // vaem
 pub const HAS_GRAPH_METADATA: NamedNodeRef<'_> =
     NamedNodeRef::new_unchecked("http://www.linkedmodel.org/schema/vaem#hasGraphMetadata");
 pub const REVISION: NamedNodeRef<'_> =
     NamedNodeRef::new_unchecked("http://www.linkedmodel.org/schema/vaem#revision");
+// prov
+pub const PROV_WAS_DERIVED_FROM: NamedNodeRef<'_> =
+    NamedNodeRef::new_unchecked("http://www.w3.org/ns/prov#wasDerivedFrom");
 // shacl
 pub const PREFIXES: NamedNodeRef<'_> =
     NamedNodeRef::new_unchecked("http://www.w3.org/ns/shacl#prefixes");
 pub const DECLARE: NamedNodeRef<'_> =
     NamedNodeRef::new_unchecked("http://www.w3.org/ns/shacl#declare");
+pub const SH_PREFIX: NamedNodeRef<'_> =
+    NamedNodeRef::new_unchecked("http://www.w3.org/ns/shacl#prefix");
+pub const SH_NAMESPACE: NamedNodeRef<'_> =
+    NamedNodeRef::new_unchecked("http://www.w3.org/ns/shacl#namespace");
 
 pub const ONTOLOGY_VERSION_IRIS: [NamedNodeRef<'_>; 10] = [
     VERSION_INFO,
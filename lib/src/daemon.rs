@@ -0,0 +1,354 @@
+// `ontoenv daemon`: keeps an environment and its store open and serves requests from other
+// processes over a Unix socket in the environment's directory (.ontoenv/daemon.sock), so many
+// short-lived CLI invocations or notebook sessions neither pay for opening the store every time
+// nor contend for its lock. Connections are handled one at a time and carry a single request, so
+// clients connect for each request (see `Client`) and a long-lived client does not keep others
+// waiting; a client gets IO_TIMEOUT to send its request and read the response.
+//
+// The protocol is the JSON-RPC 2.0 of `rpc`, one request per line, with these additional methods:
+//
+// - get {"iri", "version"?}: the graph of the ontology with the given name, as N-Triples; with a
+//   version, that version of it (see `OntoEnv::get_ontology_version`)
+// - union {"iri", "rewriteShPrefixes"?, "removeOwlImports"?}: the union of the imports closure of
+//   the ontology (see `OntoEnv::get_cached_union_graph`) as {"graph": N-Triples, "prefixes",
+//   "failedImports"}, with the prefixes its ontologies declare (see `OntoEnv::closure_prefixes`)
+// - update {"all"?}: updates the environment (see `OntoEnv::update_all`) and saves it, returning
+//   the update report
+// - shutdown {}: stops the daemon once the response is written
+
+use crate::rpc;
+use crate::util::dataset_to_graph;
+use crate::OntoEnv;
+use anyhow::Result;
+use oxigraph::io::{RdfFormat, RdfParser, RdfSerializer};
+use oxigraph::model::{Graph, NamedNode};
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{BufRead, BufReader, ErrorKind, Write};
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Name of the daemon's socket in the environment's directory
+pub const SOCKET_NAME: &str = "daemon.sock";
+
+// how long a client may take to send its request or to read the response
+const IO_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Returns the path of the daemon's socket of the environment rooted at the directory
+pub fn socket_path(root: &Path) -> PathBuf {
+    root.join(".ontoenv").join(SOCKET_NAME)
+}
+
+/// Serves requests on the environment's socket until a client asks the daemon to shut down. Fails
+/// if another daemon is already serving the environment; a socket left behind by a daemon which
+/// did not shut down cleanly is replaced
+pub fn serve(env: &mut OntoEnv) -> Result<()> {
+    let path = env.ontoenv_dir().join(SOCKET_NAME);
+    if path.exists() {
+        if UnixStream::connect(&path).is_ok() {
+            return Err(anyhow::anyhow!(
+                "A daemon is already serving this environment at {}",
+                path.display()
+            ));
+        }
+        fs::remove_file(&path)?;
+    }
+    let listener = UnixListener::bind(&path)?;
+    info!("Serving environment on {}", path.display());
+    let result = accept_connections(env, &listener);
+    if let Err(e) = fs::remove_file(&path) {
+        warn!("Could not remove socket {}: {}", path.display(), e);
+    }
+    result
+}
+
+fn accept_connections(env: &mut OntoEnv, listener: &UnixListener) -> Result<()> {
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                warn!("Failed to accept connection: {}", e);
+                continue;
+            }
+        };
+        match handle_connection(env, stream) {
+            Ok(true) => {
+                info!("Daemon shutting down");
+                break;
+            }
+            Ok(false) => {}
+            Err(e) => warn!("Failed to handle connection: {}", e),
+        }
+    }
+    Ok(())
+}
+
+// answers the request of one client; returns true if it asked the daemon to shut down
+fn handle_connection(env: &mut OntoEnv, stream: UnixStream) -> Result<bool> {
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    stream.set_write_timeout(Some(IO_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = stream;
+    let mut line = String::new();
+    // a client which disconnects without sending a request (see `Client::connect_to`) is done
+    while line.trim().is_empty() {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(false);
+        }
+    }
+    let (response, shutdown) = match serde_json::from_str::<Value>(&line) {
+        Ok(request) => handle_request(env, &request),
+        Err(e) => (
+            Some(rpc::error_response(
+                Value::Null,
+                rpc::PARSE_ERROR,
+                &e.to_string(),
+            )),
+            false,
+        ),
+    };
+    if let Some(response) = response {
+        writeln!(writer, "{}", response)?;
+        writer.flush()?;
+    }
+    Ok(shutdown)
+}
+
+/// Handles a single request, returning the response (none for a notification, see
+/// `rpc::handle_request`) and whether the daemon should shut down. Requests for methods other
+/// than those of the daemon are handled by `rpc::handle_request`
+pub fn handle_request(env: &mut OntoEnv, request: &Value) -> (Option<Value>, bool) {
+    let id = request.get("id").cloned();
+    let empty = Map::new();
+    let params = request
+        .get("params")
+        .and_then(|p| p.as_object())
+        .unwrap_or(&empty);
+    let result = match request.get("method").and_then(|m| m.as_str()) {
+        Some("get") => get(env, params),
+        Some("union") => union(env, params),
+        Some("update") => update(env, params),
+        Some("shutdown") => {
+            let response = id.map(|id| json!({"jsonrpc": "2.0", "id": id, "result": null}));
+            return (response, true);
+        }
+        _ => return (rpc::handle_request(env, request), false),
+    };
+    let response = id.map(|id| match result {
+        Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+        Err(e) => rpc::error_response(id, rpc::INTERNAL_ERROR, &e.to_string()),
+    });
+    (response, false)
+}
+
+fn iri_param(params: &Map<String, Value>) -> Result<NamedNode> {
+    let iri = params
+        .get("iri")
+        .and_then(|v| v.as_str())
+        .ok_or(anyhow::anyhow!("missing 'iri' parameter"))?;
+    Ok(NamedNode::new(iri)?)
+}
+
+fn to_ntriples(graph: &Graph) -> Result<String> {
+    let mut body = Vec::new();
+    let mut serializer = RdfSerializer::from_format(RdfFormat::NTriples).for_writer(&mut body);
+    for triple in graph.iter() {
+        serializer.serialize_triple(triple)?;
+    }
+    serializer.finish()?;
+    Ok(String::from_utf8(body)?)
+}
+
+fn from_ntriples(ntriples: &str) -> Result<Graph> {
+    let mut graph = Graph::new();
+    for quad in RdfParser::from_format(RdfFormat::NTriples).for_reader(ntriples.as_bytes()) {
+        let quad = quad?;
+        graph.insert(quad.as_ref());
+    }
+    Ok(graph)
+}
+
+fn get(env: &OntoEnv, params: &Map<String, Value>) -> Result<Value> {
+    let iri = iri_param(params)?;
+    let graph = match params.get("version").and_then(|v| v.as_str()) {
+        Some(version) => env.get_graph_version(iri.as_ref(), version)?,
+        None => env.get_graph_by_name(iri.as_ref())?,
+    };
+    Ok(json!(to_ntriples(&graph)?))
+}
+
+fn union(env: &OntoEnv, params: &Map<String, Value>) -> Result<Value> {
+    let iri = iri_param(params)?;
+    let ontology = env
+        .get_ontology_by_name(iri.as_ref())
+        .ok_or(anyhow::anyhow!("Ontology {} not found", iri))?;
+    let flag = |name: &str| params.get(name).and_then(|v| v.as_bool());
+    let closure = env.get_dependency_closure(ontology.id())?;
+    let (union, failed_imports, _) = env.get_cached_union_graph(
+        &closure,
+        flag("rewriteShPrefixes"),
+        flag("removeOwlImports"),
+        false,
+    )?;
+    let failed_imports: Vec<String> = failed_imports
+        .unwrap_or_default()
+        .iter()
+        .map(|imp| imp.to_string())
+        .collect();
+    Ok(json!({
+        "graph": to_ntriples(&dataset_to_graph(&union))?,
+        "prefixes": env.closure_prefixes(&closure),
+        "failedImports": failed_imports,
+    }))
+}
+
+fn update(env: &mut OntoEnv, params: &Map<String, Value>) -> Result<Value> {
+    let all = params.get("all").and_then(|v| v.as_bool()).unwrap_or(false);
+    let report = env.update_all(all)?;
+    env.save_to_directory()?;
+    Ok(serde_json::to_value(report)?)
+}
+
+/// The closure of an ontology as assembled by the daemon (see `Client::union`)
+pub struct Union {
+    /// The union of the graphs of the closure
+    pub graph: Graph,
+    /// The prefixes the ontologies of the closure declare (see `OntoEnv::closure_prefixes`)
+    pub prefixes: BTreeMap<String, String>,
+    /// The imports of the closure which could not be resolved, as their messages
+    pub failed_imports: Vec<String>,
+}
+
+/// A client of the daemon of an environment. Every request is sent over a connection of its own,
+/// so a client can be kept around without keeping other clients waiting
+pub struct Client {
+    socket: PathBuf,
+    next_id: u64,
+}
+
+impl Client {
+    /// Connects to the daemon serving the environment rooted at the directory. Returns None if
+    /// no daemon is running
+    pub fn connect(root: &Path) -> Result<Option<Self>> {
+        Self::connect_to(&socket_path(root))
+    }
+
+    /// Connects to the daemon listening on the socket. Returns None if no daemon is listening
+    pub fn connect_to(socket: &Path) -> Result<Option<Self>> {
+        match UnixStream::connect(socket) {
+            Ok(_) => {}
+            // no socket, or one left behind by a daemon which is gone
+            Err(e) if matches!(e.kind(), ErrorKind::NotFound | ErrorKind::ConnectionRefused) => {
+                return Ok(None)
+            }
+            Err(e) => return Err(e.into()),
+        }
+        Ok(Some(Client {
+            socket: socket.to_path_buf(),
+            next_id: 0,
+        }))
+    }
+
+    /// Sends a request and returns its result, or its error as an error
+    pub fn call(&mut self, method: &str, params: Value) -> Result<Value> {
+        self.next_id += 1;
+        let request =
+            json!({"jsonrpc": "2.0", "id": self.next_id, "method": method, "params": params});
+        let mut writer = UnixStream::connect(&self.socket)?;
+        let mut reader = BufReader::new(writer.try_clone()?);
+        writeln!(writer, "{}", request)?;
+        writer.flush()?;
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(anyhow::anyhow!("The daemon closed the connection"));
+        }
+        let mut response: Value = serde_json::from_str(&line)?;
+        if let Some(error) = response.get("error") {
+            let message = error
+                .get("message")
+                .and_then(|m| m.as_str())
+                .unwrap_or("unknown error");
+            return Err(anyhow::anyhow!("Daemon error: {}", message));
+        }
+        Ok(response
+            .get_mut("result")
+            .map(Value::take)
+            .unwrap_or(Value::Null))
+    }
+
+    /// Returns the name and location of the ontology with the given name, if there is one
+    pub fn resolve(&mut self, iri: &str) -> Result<Option<(String, String)>> {
+        let result = self.call("resolve", json!({ "iri": iri }))?;
+        let field = |name: &str| result.get(name).and_then(|v| v.as_str()).map(String::from);
+        Ok(field("name").zip(field("location")))
+    }
+
+    /// Returns the names of the ontologies in the imports closure of the ontology
+    pub fn closure(&mut self, iri: &str) -> Result<Vec<String>> {
+        Ok(serde_json::from_value(
+            self.call("closure", json!({ "iri": iri }))?,
+        )?)
+    }
+
+    /// Returns the graph of the ontology with the given name
+    pub fn get_graph(&mut self, iri: &str) -> Result<Graph> {
+        self.get_graph_version(iri, None)
+    }
+
+    /// Returns the graph of the ontology with the given name, or of the given version of it
+    /// (see `OntoEnv::get_ontology_version`)
+    pub fn get_graph_version(&mut self, iri: &str, version: Option<&str>) -> Result<Graph> {
+        let mut params = json!({ "iri": iri });
+        if let Some(version) = version {
+            params["version"] = json!(version);
+        }
+        let result = self.call("get", params)?;
+        let ntriples = result
+            .as_str()
+            .ok_or(anyhow::anyhow!("The daemon did not return a graph"))?;
+        from_ntriples(ntriples)
+    }
+
+    /// Returns the union of the imports closure of the ontology with the given name (see
+    /// `OntoEnv::get_cached_union_graph`)
+    pub fn union(
+        &mut self,
+        iri: &str,
+        rewrite_sh_prefixes: Option<bool>,
+        remove_owl_imports: Option<bool>,
+    ) -> Result<Union> {
+        let mut result = self.call(
+            "union",
+            json!({
+                "iri": iri,
+                "rewriteShPrefixes": rewrite_sh_prefixes,
+                "removeOwlImports": remove_owl_imports,
+            }),
+        )?;
+        let graph = result
+            .get("graph")
+            .and_then(|v| v.as_str())
+            .ok_or(anyhow::anyhow!("The daemon did not return a graph"))?;
+        Ok(Union {
+            graph: from_ntriples(graph)?,
+            prefixes: serde_json::from_value(result["prefixes"].take())?,
+            failed_imports: serde_json::from_value(result["failedImports"].take())?,
+        })
+    }
+
+    /// Makes the daemon update and save its environment, returning the update report (see
+    /// `UpdateReport`) as JSON
+    pub fn update(&mut self, all: bool) -> Result<Value> {
+        self.call("update", json!({ "all": all }))
+    }
+
+    /// Asks the daemon to shut down
+    pub fn shutdown(&mut self) -> Result<()> {
+        self.call("shutdown", json!({}))?;
+        Ok(())
+    }
+}
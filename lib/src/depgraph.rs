@@ -0,0 +1,118 @@
+// the owl:imports relationships of an environment as a petgraph graph (see
+// `OntoEnv::dependency_graph`), so tools can run their own traversals, topological sorts and
+// cycle analyses. Unlike the graph the environment keeps internally, it also has a node for
+// every import which does not resolve to an ontology in the environment
+
+use crate::ontology::GraphIdentifier;
+use oxigraph::model::NamedNode;
+pub use petgraph;
+use petgraph::graph::{DiGraph, NodeIndex};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+
+/// A node of the dependency graph
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DependencyNode {
+    /// An ontology in the environment
+    Ontology(GraphIdentifier),
+    /// An owl:imports IRI which no ontology in the environment has
+    Missing(NamedNode),
+}
+
+impl Display for DependencyNode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DependencyNode::Ontology(id) => write!(f, "{}", id),
+            DependencyNode::Missing(import) => write!(f, "{} (missing)", import),
+        }
+    }
+}
+
+/// Whether an import resolved to an ontology in the environment
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportStatus {
+    Resolved,
+    Missing,
+}
+
+/// An edge from an ontology to an ontology it imports
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportEdge {
+    // the IRI as it is written in the owl:imports
+    pub import: NamedNode,
+    pub status: ImportStatus,
+}
+
+/// The dependency graph of an environment. Edges point from importing ontologies to the
+/// ontologies (or missing imports) they import
+#[derive(Debug, Clone, Default)]
+pub struct DependencyGraph {
+    graph: DiGraph<DependencyNode, ImportEdge>,
+    indexes: HashMap<DependencyNode, NodeIndex>,
+}
+
+impl DependencyGraph {
+    pub(crate) fn add_node(&mut self, node: DependencyNode) -> NodeIndex {
+        if let Some(index) = self.indexes.get(&node) {
+            return *index;
+        }
+        let index = self.graph.add_node(node.clone());
+        self.indexes.insert(node, index);
+        index
+    }
+
+    pub(crate) fn add_import(
+        &mut self,
+        from: &GraphIdentifier,
+        to: DependencyNode,
+        import: NamedNode,
+    ) {
+        let status = match to {
+            DependencyNode::Ontology(_) => ImportStatus::Resolved,
+            DependencyNode::Missing(_) => ImportStatus::Missing,
+        };
+        let from = self.add_node(DependencyNode::Ontology(from.clone()));
+        let to = self.add_node(to);
+        self.graph.add_edge(from, to, ImportEdge { import, status });
+    }
+
+    /// Returns the underlying petgraph graph
+    pub fn graph(&self) -> &DiGraph<DependencyNode, ImportEdge> {
+        &self.graph
+    }
+
+    pub fn into_graph(self) -> DiGraph<DependencyNode, ImportEdge> {
+        self.graph
+    }
+
+    /// Returns the index of the node of an ontology
+    pub fn node_index(&self, id: &GraphIdentifier) -> Option<NodeIndex> {
+        self.indexes
+            .get(&DependencyNode::Ontology(id.clone()))
+            .copied()
+    }
+
+    /// Returns the edges as (importer, imported, edge) triples, in the order they were added
+    pub fn edges(&self) -> impl Iterator<Item = (&DependencyNode, &DependencyNode, &ImportEdge)> {
+        self.graph.edge_indices().filter_map(|edge| {
+            let (from, to) = self.graph.edge_endpoints(edge)?;
+            Some((&self.graph[from], &self.graph[to], &self.graph[edge]))
+        })
+    }
+
+    /// Returns the imports which did not resolve, sorted
+    pub fn missing(&self) -> Vec<&NamedNode> {
+        let mut missing: Vec<&NamedNode> = self
+            .graph
+            .node_weights()
+            .filter_map(|node| match node {
+                DependencyNode::Missing(import) => Some(import),
+                DependencyNode::Ontology(_) => None,
+            })
+            .collect();
+        missing.sort();
+        missing
+    }
+}
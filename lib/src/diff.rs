@@ -0,0 +1,153 @@
+// triple-level differences between two versions of an ontology, e.g. the copy in an
+// environment and a newer release upstream, to see what a refresh would change
+
+use crate::util::sorted_triples;
+use oxigraph::model::{Graph, Subject, Triple};
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+
+/// How the diff is printed: every added and removed triple, or the number of them per subject
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffFormat {
+    Unified,
+    Summary,
+}
+
+impl std::str::FromStr for DiffFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s.to_lowercase().as_str() {
+            "unified" => Ok(DiffFormat::Unified),
+            "summary" => Ok(DiffFormat::Summary),
+            _ => Err(anyhow::anyhow!("Unknown diff format: {}", s)),
+        }
+    }
+}
+
+/// The triples added and removed between two graphs, in the stable order of
+/// `util::sorted_triples`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GraphDiff {
+    pub added: Vec<Triple>,
+    pub removed: Vec<Triple>,
+}
+
+/// The number of triples added and removed about one subject
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct SubjectChanges {
+    pub added: usize,
+    pub removed: usize,
+}
+
+impl GraphDiff {
+    /// Compares the graphs. Blank nodes are matched by the triples they appear in (see
+    /// `util::sorted_triples`) rather than by their labels, so an unchanged blank node structure
+    /// is not reported as changed; this is a best effort, not a full graph isomorphism check,
+    /// and a changed blank node can make its neighbours show up as changed too
+    pub fn between(old: &Graph, new: &Graph) -> Self {
+        let old = sorted_triples(old);
+        let new = sorted_triples(new);
+        let old_set: BTreeSet<String> = old.iter().map(|t| t.to_string()).collect();
+        let new_set: BTreeSet<String> = new.iter().map(|t| t.to_string()).collect();
+        GraphDiff {
+            added: new
+                .into_iter()
+                .filter(|t| !old_set.contains(&t.to_string()))
+                .collect(),
+            removed: old
+                .into_iter()
+                .filter(|t| !new_set.contains(&t.to_string()))
+                .collect(),
+        }
+    }
+
+    /// Returns true if the graphs hold the same triples
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+
+    /// Returns the number of triples added and removed per subject. Triples about blank nodes
+    /// are counted under `_:`, since their labels are not stable across versions
+    pub fn by_subject(&self) -> BTreeMap<String, SubjectChanges> {
+        let key = |triple: &Triple| match &triple.subject {
+            Subject::NamedNode(node) => node.as_str().to_string(),
+            _ => "_:".to_string(),
+        };
+        let mut subjects: BTreeMap<String, SubjectChanges> = BTreeMap::new();
+        for triple in self.added.iter() {
+            subjects.entry(key(triple)).or_default().added += 1;
+        }
+        for triple in self.removed.iter() {
+            subjects.entry(key(triple)).or_default().removed += 1;
+        }
+        subjects
+    }
+
+    /// Renders the diff with the old and new versions labelled as given: in the unified format
+    /// a `---`/`+++` header followed by the removed triples prefixed with `-` and the added ones
+    /// with `+`, in the summary format the changes per subject and the totals
+    pub fn render(&self, format: DiffFormat, old: &str, new: &str) -> String {
+        let mut out = String::new();
+        match format {
+            DiffFormat::Unified => {
+                out.push_str(&format!("--- {}\n+++ {}\n", old, new));
+                for triple in self.removed.iter() {
+                    out.push_str(&format!("-{} .\n", triple));
+                }
+                for triple in self.added.iter() {
+                    out.push_str(&format!("+{} .\n", triple));
+                }
+            }
+            DiffFormat::Summary => {
+                for (subject, changes) in self.by_subject() {
+                    let added = format!("+{}", changes.added);
+                    let removed = format!("-{}", changes.removed);
+                    out.push_str(&format!("{:>6} {:>6}  {}\n", added, removed, subject));
+                }
+                out.push_str(&format!(
+                    "{} triples added, {} removed ({} -> {})\n",
+                    self.added.len(),
+                    self.removed.len(),
+                    old,
+                    new
+                ));
+            }
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use oxigraph::model::{BlankNode, Literal, NamedNode, TripleRef};
+
+    #[test]
+    fn test_blank_node_labels_are_ignored() {
+        let class = NamedNode::new_unchecked("urn:Class");
+        let label = NamedNode::new_unchecked("urn:label");
+        let restriction = NamedNode::new_unchecked("urn:restriction");
+        let graph = |blank: &str, value: &str| {
+            let blank = BlankNode::new_unchecked(blank);
+            let value = Literal::new_simple_literal(value);
+            let mut graph = Graph::new();
+            graph.insert(TripleRef::new(
+                class.as_ref(),
+                restriction.as_ref(),
+                blank.as_ref(),
+            ));
+            graph.insert(TripleRef::new(
+                blank.as_ref(),
+                label.as_ref(),
+                value.as_ref(),
+            ));
+            graph
+        };
+        assert!(GraphDiff::between(&graph("a", "x"), &graph("b", "x")).is_empty());
+
+        let diff = GraphDiff::between(&graph("a", "x"), &graph("b", "y"));
+        assert_eq!(diff.added.len(), diff.removed.len());
+        assert!(diff.by_subject().contains_key("_:"));
+    }
+}
@@ -0,0 +1,515 @@
+// generation of static documentation (HTML or Markdown) for an ontology or its imports closure
+// from the graphs in the environment, so the documentation always matches the resolved versions
+
+use crate::consts::{
+    ANNOTATION_PROPERTY, CLASS, DATATYPE_PROPERTY, OBJECT_PROPERTY, SKOS_DEFINITION, TYPE,
+};
+use crate::ontology::GraphIdentifier;
+use crate::util;
+use crate::OntoEnv;
+use anyhow::Result;
+use oxigraph::model::vocab::{rdf, rdfs};
+use oxigraph::model::{Graph, NamedNode, NamedNodeRef, SubjectRef, TermRef};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DocFormat {
+    Html,
+    Markdown,
+}
+
+impl std::str::FromStr for DocFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "html" => Ok(DocFormat::Html),
+            "md" | "markdown" => Ok(DocFormat::Markdown),
+            _ => Err(anyhow::anyhow!("Unknown documentation format: {}", s)),
+        }
+    }
+}
+
+impl DocFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            DocFormat::Html => "html",
+            DocFormat::Markdown => "md",
+        }
+    }
+}
+
+/// Documentation for a single class or property
+#[derive(Debug, Clone)]
+pub struct TermDoc {
+    pub iri: NamedNode,
+    pub label: Option<String>,
+    pub comment: Option<String>,
+    // rdfs:subClassOf or rdfs:subPropertyOf
+    pub parents: Vec<NamedNode>,
+    pub children: Vec<NamedNode>,
+    pub domain: Vec<NamedNode>,
+    pub range: Vec<NamedNode>,
+}
+
+/// Documentation for an ontology (or the union of its imports closure)
+#[derive(Debug, Clone)]
+pub struct OntologyDoc {
+    pub name: NamedNode,
+    pub title: Option<String>,
+    pub classes: Vec<TermDoc>,
+    pub properties: Vec<TermDoc>,
+    pub prefixes: BTreeMap<String, String>,
+}
+
+/// Returns the preferred literal value: English first, then untagged, then any
+fn literal_value(graph: &Graph, subject: NamedNodeRef, predicate: NamedNodeRef) -> Option<String> {
+    let mut best: Option<(u8, String)> = None;
+    for object in graph.objects_for_subject_predicate(subject, predicate) {
+        if let TermRef::Literal(literal) = object {
+            let rank = match literal.language() {
+                Some(lang) if lang.starts_with("en") => 0,
+                None => 1,
+                Some(_) => 2,
+            };
+            match &best {
+                Some((r, _)) if *r <= rank => {}
+                _ => best = Some((rank, literal.value().to_string())),
+            }
+        }
+    }
+    best.map(|(_, value)| value)
+}
+
+fn named_objects(graph: &Graph, subject: NamedNodeRef, predicate: NamedNodeRef) -> Vec<NamedNode> {
+    let mut objects: Vec<NamedNode> = graph
+        .objects_for_subject_predicate(subject, predicate)
+        .filter_map(|o| match o {
+            TermRef::NamedNode(n) => Some(n.into_owned()),
+            _ => None,
+        })
+        .collect();
+    objects.sort();
+    objects.dedup();
+    objects
+}
+
+fn named_subjects_of_types(graph: &Graph, types: &[NamedNodeRef]) -> BTreeSet<NamedNode> {
+    let mut subjects = BTreeSet::new();
+    for t in types {
+        for subject in graph.subjects_for_predicate_object(TYPE, *t) {
+            if let SubjectRef::NamedNode(n) = subject {
+                subjects.insert(n.into_owned());
+            }
+        }
+    }
+    subjects
+}
+
+fn term_docs(graph: &Graph, terms: &BTreeSet<NamedNode>, parent: NamedNodeRef) -> Vec<TermDoc> {
+    let mut children: HashMap<NamedNode, Vec<NamedNode>> = HashMap::new();
+    for term in terms {
+        for p in named_objects(graph, term.as_ref(), parent) {
+            children.entry(p).or_default().push(term.clone());
+        }
+    }
+    terms
+        .iter()
+        .map(|term| TermDoc {
+            iri: term.clone(),
+            label: literal_value(graph, term.as_ref(), rdfs::LABEL),
+            comment: literal_value(graph, term.as_ref(), rdfs::COMMENT)
+                .or_else(|| literal_value(graph, term.as_ref(), SKOS_DEFINITION)),
+            parents: named_objects(graph, term.as_ref(), parent),
+            children: children.remove(term).unwrap_or_default(),
+            domain: named_objects(graph, term.as_ref(), rdfs::DOMAIN),
+            range: named_objects(graph, term.as_ref(), rdfs::RANGE),
+        })
+        .collect()
+}
+
+impl OntologyDoc {
+    /// Collects the classes and properties defined in the graph
+    pub fn from_graph(
+        name: NamedNodeRef,
+        graph: &Graph,
+        prefixes: BTreeMap<String, String>,
+    ) -> Self {
+        let classes = named_subjects_of_types(graph, &[CLASS, rdfs::CLASS]);
+        let properties = named_subjects_of_types(
+            graph,
+            &[
+                OBJECT_PROPERTY,
+                DATATYPE_PROPERTY,
+                ANNOTATION_PROPERTY,
+                rdf::PROPERTY,
+            ],
+        );
+        OntologyDoc {
+            name: name.into_owned(),
+            title: literal_value(graph, name, crate::consts::TITLE)
+                .or_else(|| literal_value(graph, name, rdfs::LABEL)),
+            classes: term_docs(graph, &classes, rdfs::SUB_CLASS_OF),
+            properties: term_docs(graph, &properties, rdfs::SUB_PROPERTY_OF),
+            prefixes,
+        }
+    }
+
+    /// Returns the IRI as a prefixed name if one of the prefixes matches
+    fn compact(&self, iri: &NamedNode) -> String {
+        self.prefixes
+            .iter()
+            .filter(|(_, namespace)| !namespace.is_empty() && iri.as_str().starts_with(*namespace))
+            .max_by_key(|(_, namespace)| namespace.len())
+            .map(|(prefix, namespace)| format!("{}:{}", prefix, &iri.as_str()[namespace.len()..]))
+            .unwrap_or_else(|| iri.as_str().to_string())
+    }
+
+    fn anchor(&self, iri: &NamedNode) -> String {
+        self.compact(iri)
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+            .collect()
+    }
+
+    fn documented(&self) -> HashSet<&NamedNode> {
+        self.classes
+            .iter()
+            .chain(self.properties.iter())
+            .map(|t| &t.iri)
+            .collect()
+    }
+
+    /// Roots of the class hierarchy: classes with no documented superclass
+    fn hierarchy_roots(&self) -> Vec<&TermDoc> {
+        let documented = self.documented();
+        self.classes
+            .iter()
+            .filter(|c| {
+                !c.parents
+                    .iter()
+                    .any(|p| documented.contains(p) && p != &c.iri)
+            })
+            .collect()
+    }
+
+    fn class(&self, iri: &NamedNode) -> Option<&TermDoc> {
+        self.classes.iter().find(|c| &c.iri == iri)
+    }
+
+    pub fn render(&self, format: DocFormat) -> String {
+        match format {
+            DocFormat::Html => self.to_html(),
+            DocFormat::Markdown => self.to_markdown(),
+        }
+    }
+
+    fn md_link(&self, iri: &NamedNode, documented: &HashSet<&NamedNode>) -> String {
+        let text = self.compact(iri).replace('|', "\\|");
+        if documented.contains(iri) {
+            format!("[{}](#{})", text, self.anchor(iri))
+        } else {
+            format!("[{}]({})", text, iri.as_str())
+        }
+    }
+
+    fn md_hierarchy(
+        &self,
+        term: &TermDoc,
+        depth: usize,
+        seen: &mut HashSet<NamedNode>,
+        out: &mut String,
+    ) {
+        if !seen.insert(term.iri.clone()) {
+            return;
+        }
+        out.push_str(&format!(
+            "{}- [{}](#{})\n",
+            "  ".repeat(depth),
+            self.compact(&term.iri),
+            self.anchor(&term.iri)
+        ));
+        for child in term.children.iter() {
+            if let Some(child) = self.class(child) {
+                self.md_hierarchy(child, depth + 1, seen, out);
+            }
+        }
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let documented = self.documented();
+        let links = |iris: &[NamedNode]| {
+            iris.iter()
+                .map(|i| self.md_link(i, &documented))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let mut out = String::new();
+        out.push_str(&format!(
+            "# {}\n\n`{}`\n\n",
+            self.title.as_deref().unwrap_or(self.name.as_str()),
+            self.name.as_str()
+        ));
+
+        if !self.prefixes.is_empty() {
+            out.push_str("## Prefixes\n\n| Prefix | Namespace |\n| --- | --- |\n");
+            for (prefix, namespace) in self.prefixes.iter() {
+                out.push_str(&format!("| `{}` | `{}` |\n", prefix, namespace));
+            }
+            out.push('\n');
+        }
+
+        if !self.classes.is_empty() {
+            out.push_str("## Class Hierarchy\n\n");
+            let mut seen = HashSet::new();
+            for root in self.hierarchy_roots() {
+                self.md_hierarchy(root, 0, &mut seen, &mut out);
+            }
+            out.push('\n');
+        }
+
+        for (heading, terms) in [("Classes", &self.classes), ("Properties", &self.properties)] {
+            if terms.is_empty() {
+                continue;
+            }
+            out.push_str(&format!("## {}\n\n", heading));
+            for term in terms.iter() {
+                out.push_str(&format!(
+                    "### <a id=\"{}\"></a>{}\n\n",
+                    self.anchor(&term.iri),
+                    self.compact(&term.iri)
+                ));
+                out.push_str(&format!("IRI: <{}>\n\n", term.iri.as_str()));
+                if let Some(label) = &term.label {
+                    out.push_str(&format!("Label: {}\n\n", label));
+                }
+                if let Some(comment) = &term.comment {
+                    out.push_str(&format!("{}\n\n", comment));
+                }
+                for (name, iris) in [
+                    ("Parents", &term.parents),
+                    ("Children", &term.children),
+                    ("Domain", &term.domain),
+                    ("Range", &term.range),
+                ] {
+                    if !iris.is_empty() {
+                        out.push_str(&format!("- {}: {}\n", name, links(iris)));
+                    }
+                }
+                out.push('\n');
+            }
+        }
+        out
+    }
+
+    fn html_link(&self, iri: &NamedNode, documented: &HashSet<&NamedNode>) -> String {
+        let href = if documented.contains(iri) {
+            format!("#{}", self.anchor(iri))
+        } else {
+            iri.as_str().to_string()
+        };
+        format!(
+            "<a href=\"{}\">{}</a>",
+            escape_html(&href),
+            escape_html(&self.compact(iri))
+        )
+    }
+
+    fn html_hierarchy(&self, term: &TermDoc, seen: &mut HashSet<NamedNode>, out: &mut String) {
+        if !seen.insert(term.iri.clone()) {
+            return;
+        }
+        out.push_str(&format!(
+            "<li><a href=\"#{}\">{}</a>",
+            self.anchor(&term.iri),
+            escape_html(&self.compact(&term.iri))
+        ));
+        let children: Vec<&TermDoc> = term
+            .children
+            .iter()
+            .filter_map(|c| self.class(c))
+            .filter(|c| !seen.contains(&c.iri))
+            .collect();
+        if !children.is_empty() {
+            out.push_str("<ul>");
+            for child in children {
+                self.html_hierarchy(child, seen, out);
+            }
+            out.push_str("</ul>");
+        }
+        out.push_str("</li>\n");
+    }
+
+    pub fn to_html(&self) -> String {
+        let documented = self.documented();
+        let title = escape_html(self.title.as_deref().unwrap_or(self.name.as_str()));
+        let mut out = String::new();
+        out.push_str(&format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n</head>\n<body>\n",
+            title
+        ));
+        out.push_str(&format!(
+            "<h1>{}</h1>\n<p><code>{}</code></p>\n",
+            title,
+            escape_html(self.name.as_str())
+        ));
+
+        if !self.prefixes.is_empty() {
+            out.push_str(
+                "<h2>Prefixes</h2>\n<table>\n<tr><th>Prefix</th><th>Namespace</th></tr>\n",
+            );
+            for (prefix, namespace) in self.prefixes.iter() {
+                out.push_str(&format!(
+                    "<tr><td><code>{}</code></td><td><code>{}</code></td></tr>\n",
+                    escape_html(prefix),
+                    escape_html(namespace)
+                ));
+            }
+            out.push_str("</table>\n");
+        }
+
+        if !self.classes.is_empty() {
+            out.push_str("<h2>Class Hierarchy</h2>\n<ul>\n");
+            let mut seen = HashSet::new();
+            for root in self.hierarchy_roots() {
+                self.html_hierarchy(root, &mut seen, &mut out);
+            }
+            out.push_str("</ul>\n");
+        }
+
+        for (heading, terms) in [("Classes", &self.classes), ("Properties", &self.properties)] {
+            if terms.is_empty() {
+                continue;
+            }
+            out.push_str(&format!("<h2>{}</h2>\n", heading));
+            for term in terms.iter() {
+                out.push_str(&format!(
+                    "<section id=\"{}\">\n<h3>{}</h3>\n<p>IRI: <code>{}</code></p>\n",
+                    self.anchor(&term.iri),
+                    escape_html(&self.compact(&term.iri)),
+                    escape_html(term.iri.as_str())
+                ));
+                if let Some(label) = &term.label {
+                    out.push_str(&format!("<p>Label: {}</p>\n", escape_html(label)));
+                }
+                if let Some(comment) = &term.comment {
+                    out.push_str(&format!("<p>{}</p>\n", escape_html(comment)));
+                }
+                let mut rows = String::new();
+                for (name, iris) in [
+                    ("Parents", &term.parents),
+                    ("Children", &term.children),
+                    ("Domain", &term.domain),
+                    ("Range", &term.range),
+                ] {
+                    if !iris.is_empty() {
+                        let links: Vec<String> = iris
+                            .iter()
+                            .map(|i| self.html_link(i, &documented))
+                            .collect();
+                        rows.push_str(&format!("<li>{}: {}</li>\n", name, links.join(", ")));
+                    }
+                }
+                if !rows.is_empty() {
+                    out.push_str(&format!("<ul>\n{}</ul>\n", rows));
+                }
+                out.push_str("</section>\n");
+            }
+        }
+        out.push_str("</body>\n</html>\n");
+        out
+    }
+}
+
+/// Builds the documentation for the ontology, or for the union of its imports closure if
+/// `closure` is true. The prefix table combines the prefixes declared by the documented
+/// ontologies; the root ontology's declarations win on conflicts
+pub fn document(env: &OntoEnv, id: &GraphIdentifier, closure: bool) -> Result<OntologyDoc> {
+    let ids = if closure {
+        env.get_dependency_closure(id)?
+    } else {
+        vec![id.clone()]
+    };
+    let graph = if closure {
+        let (union, _, _) = env.get_union_graph(&ids, Some(false), Some(false))?;
+        util::dataset_to_graph(&union)
+    } else {
+        env.get_graph(id)?
+    };
+    let mut prefixes: BTreeMap<String, String> = BTreeMap::new();
+    for member in ids.iter() {
+        if let Some(ontology) = env.ontologies().get(member) {
+            for (prefix, namespace) in ontology.namespace_map() {
+                prefixes
+                    .entry(prefix.clone())
+                    .or_insert_with(|| namespace.clone());
+            }
+        }
+    }
+    Ok(OntologyDoc::from_graph(id.name(), &graph, prefixes))
+}
+
+/// Writes the documentation to index.html or index.md in the output directory and returns
+/// the path of the written file
+pub fn write_docs(doc: &OntologyDoc, format: DocFormat, out_dir: &Path) -> Result<PathBuf> {
+    std::fs::create_dir_all(out_dir)?;
+    let path = out_dir.join(format!("index.{}", format.extension()));
+    std::fs::write(&path, doc.render(format))?;
+    Ok(path)
+}
+
+pub(crate) fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use oxigraph::model::{Literal, Triple, TripleRef};
+
+    fn example() -> OntologyDoc {
+        let ont = NamedNodeRef::new("urn:ex").unwrap();
+        let a = NamedNodeRef::new("urn:ex#A").unwrap();
+        let b = NamedNodeRef::new("urn:ex#B").unwrap();
+        let p = NamedNodeRef::new("urn:ex#p").unwrap();
+        let mut graph = Graph::new();
+        graph.insert(TripleRef::new(a, TYPE, CLASS));
+        graph.insert(TripleRef::new(b, TYPE, CLASS));
+        graph.insert(TripleRef::new(b, rdfs::SUB_CLASS_OF, a));
+        graph.insert(TripleRef::new(p, TYPE, OBJECT_PROPERTY));
+        graph.insert(TripleRef::new(p, rdfs::DOMAIN, a));
+        graph.insert(&Triple::new(
+            a,
+            rdfs::LABEL,
+            Literal::new_language_tagged_literal_unchecked("A <thing>", "en"),
+        ));
+        let prefixes = BTreeMap::from([("ex".to_string(), "urn:ex#".to_string())]);
+        OntologyDoc::from_graph(ont, &graph, prefixes)
+    }
+
+    #[test]
+    fn test_docgen_model() {
+        let doc = example();
+        assert_eq!(doc.classes.len(), 2);
+        assert_eq!(doc.properties.len(), 1);
+        assert_eq!(doc.classes[0].children.len(), 1);
+        assert_eq!(doc.classes[0].label.as_deref(), Some("A <thing>"));
+        assert_eq!(doc.hierarchy_roots().len(), 1);
+        assert_eq!(doc.compact(&doc.classes[1].iri), "ex:B");
+    }
+
+    #[test]
+    fn test_docgen_render() {
+        let doc = example();
+        let md = doc.to_markdown();
+        assert!(md.contains("- [ex:A](#ex-A)\n  - [ex:B](#ex-B)\n"));
+        assert!(md.contains("- Domain: [ex:A](#ex-A)"));
+        let html = doc.to_html();
+        assert!(html.contains("<section id=\"ex-B\">"));
+        assert!(html.contains("A &lt;thing&gt;"));
+    }
+}
@@ -1,15 +1,31 @@
 use crate::consts::*;
-use crate::ontology::OntologyLocation;
+use crate::depgraph::petgraph::algo::tarjan_scc;
+use crate::depgraph::petgraph::graph::NodeIndex;
+use crate::depgraph::DependencyNode;
+use crate::ontology::{GraphIdentifier, OntologyLocation};
+use crate::util::find_iri_in_text;
 use crate::OntoEnv;
 use anyhow::Result;
-use oxigraph::model::NamedNode;
-use std::collections::HashMap;
+use oxigraph::model::vocab::rdfs;
+use oxigraph::model::{NamedNode, NamedNodeRef, SubjectRef, TermRef, TripleRef};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 
+#[derive(Debug, Clone, Serialize)]
 pub struct OntologyProblem {
     pub locations: Vec<OntologyLocation>,
+    // the ontology whose graph has the problem, for problems within one graph
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub graph: Option<String>,
     pub message: String,
 }
 
+/// The problems found by the doctor, as printed by `ontoenv doctor --json`
+#[derive(Debug, Clone, Serialize)]
+pub struct DoctorReport {
+    pub problems: Vec<OntologyProblem>,
+}
+
 pub trait EnvironmentCheck {
     fn name(&self) -> &str;
     fn check(&mut self, env: &OntoEnv, problems: &mut Vec<OntologyProblem>) -> Result<()>;
@@ -52,11 +68,12 @@ impl EnvironmentCheck for OntologyDeclaration {
 
     fn check(&mut self, env: &OntoEnv, problems: &mut Vec<OntologyProblem>) -> Result<()> {
         for location in env.find_files()? {
-            let g = match location.graph() {
-                Ok(g) => g,
+            let g = match location.read_document(&env.config.base_iri_options()) {
+                Ok(document) => document.graph,
                 Err(e) => {
                     problems.push(OntologyProblem {
                         locations: vec![location.clone()],
+                        graph: None,
                         message: format!("Failed to load graph: {}", e),
                     });
                     continue;
@@ -67,13 +84,23 @@ impl EnvironmentCheck for OntologyDeclaration {
                 .subjects_for_predicate_object(TYPE, ONTOLOGY)
                 .collect::<Vec<_>>();
             if decls.is_empty() {
+                // list the name the environment made up for the anonymous ontology
+                let message = match env.get_ontology_by_location(&location) {
+                    Some(ontology) if ontology.is_anonymous() => format!(
+                        "No ontology declaration found; using the anonymous name {}",
+                        ontology.name()
+                    ),
+                    _ => "No ontology declaration found".to_string(),
+                };
                 problems.push(OntologyProblem {
                     locations: vec![location.clone()],
-                    message: "No ontology declaration found".to_string(),
+                    graph: None,
+                    message,
                 });
             } else if decls.len() > 1 {
                 problems.push(OntologyProblem {
                     locations: vec![location.clone()],
+                    graph: None,
                     message: "Multiple ontology declarations found".to_string(),
                 });
             }
@@ -91,18 +118,22 @@ impl EnvironmentCheck for DuplicateOntology {
 
     fn check(&mut self, env: &OntoEnv, problems: &mut Vec<OntologyProblem>) -> Result<()> {
         // group ontologies by name; if there are more than one in a group, report an error
-        let mut names: HashMap<NamedNode, Vec<OntologyLocation>> = HashMap::new();
+        let mut names: BTreeMap<NamedNode, Vec<OntologyLocation>> = BTreeMap::new();
         for ontology in env.ontologies.values() {
-            let name = ontology.name();
+            // anonymous ontologies are named after their location, so they cannot clash
+            if ontology.is_anonymous() {
+                continue;
+            }
             names
-                .entry(name)
+                .entry(ontology.name())
                 .or_default()
-                .push(ontology.location().unwrap().clone());
+                .push(ontology.id().location().clone());
         }
         for (name, locations) in names {
             if locations.len() > 1 {
                 problems.push(OntologyProblem {
                     locations,
+                    graph: None,
                     message: format!("Multiple ontologies with name {}", name),
                 });
             }
@@ -111,3 +142,385 @@ impl EnvironmentCheck for DuplicateOntology {
         Ok(())
     }
 }
+
+pub struct LocationCasing {}
+
+impl EnvironmentCheck for LocationCasing {
+    fn name(&self) -> &str {
+        "Location Casing"
+    }
+
+    fn check(&mut self, env: &OntoEnv, problems: &mut Vec<OntologyProblem>) -> Result<()> {
+        // group file locations by their lowercased path; on case-insensitive file systems
+        // (Windows, macOS) the locations in a group are the same file
+        let mut paths: BTreeMap<String, Vec<OntologyLocation>> = BTreeMap::new();
+        for ontology in env.ontologies.values() {
+            let location = ontology.id().location();
+            if location.is_file() {
+                let group = paths.entry(location.as_str().to_lowercase()).or_default();
+                if !group.contains(location) {
+                    group.push(location.clone());
+                }
+            }
+        }
+        for locations in paths.into_values() {
+            if locations.len() > 1 {
+                problems.push(OntologyProblem {
+                    locations,
+                    graph: None,
+                    message: "Locations differ only by case; they are the same file on \
+                              case-insensitive file systems"
+                        .to_string(),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+pub struct ImportConstraintCheck {}
+
+impl EnvironmentCheck for ImportConstraintCheck {
+    fn name(&self) -> &str {
+        "Import Constraints"
+    }
+
+    fn check(&mut self, env: &OntoEnv, problems: &mut Vec<OntologyProblem>) -> Result<()> {
+        for violation in env.check_import_constraints()? {
+            problems.push(OntologyProblem {
+                locations: vec![violation.ontology.location().clone()],
+                graph: None,
+                message: format!(
+                    "Forbidden import of {} ({})",
+                    violation.forbidden, violation.constraint
+                ),
+            });
+        }
+        Ok(())
+    }
+}
+
+pub struct VersionConstraintCheck {}
+
+impl EnvironmentCheck for VersionConstraintCheck {
+    fn name(&self) -> &str {
+        "Version Constraints"
+    }
+
+    fn check(&mut self, env: &OntoEnv, problems: &mut Vec<OntologyProblem>) -> Result<()> {
+        for violation in env.check_version_constraints()? {
+            let mut locations = vec![violation.ontology.location().clone()];
+            locations.extend(violation.dependents.iter().map(|d| d.location().clone()));
+            let version = match &violation.version {
+                Some(version) => format!("version {}", version),
+                None => "no version".to_string(),
+            };
+            problems.push(OntologyProblem {
+                locations,
+                graph: None,
+                message: format!(
+                    "{} has {}, but {} is required",
+                    violation.ontology.name(),
+                    version,
+                    violation.requirement
+                ),
+            });
+        }
+        Ok(())
+    }
+}
+
+pub struct ImportCycles {}
+
+impl EnvironmentCheck for ImportCycles {
+    fn name(&self) -> &str {
+        "Import Cycles"
+    }
+
+    fn check(&mut self, env: &OntoEnv, problems: &mut Vec<OntologyProblem>) -> Result<()> {
+        let deps = env.dependency_graph();
+        let graph = deps.graph();
+        let name = |index: NodeIndex| match &graph[index] {
+            DependencyNode::Ontology(id) => id.name().as_str().to_string(),
+            DependencyNode::Missing(import) => import.as_str().to_string(),
+        };
+        let mut cycles: Vec<OntologyProblem> = vec![];
+        for component in tarjan_scc(graph) {
+            // a component of one ontology is a cycle only if the ontology imports itself
+            if component.len() == 1 && !graph.contains_edge(component[0], component[0]) {
+                continue;
+            }
+            // report the shortest cycle through the first ontology by name, which is found by a
+            // breadth-first search within the component
+            let start = *component.iter().min_by_key(|index| name(**index)).unwrap();
+            let mut previous: HashMap<NodeIndex, NodeIndex> = HashMap::new();
+            let mut queue = VecDeque::from([start]);
+            while let Some(index) = queue.pop_front() {
+                if previous.contains_key(&start) {
+                    break;
+                }
+                for next in graph.neighbors(index) {
+                    if component.contains(&next) && !previous.contains_key(&next) {
+                        previous.insert(next, index);
+                        queue.push_back(next);
+                    }
+                }
+            }
+            let mut path = vec![start];
+            let mut current = previous[&start];
+            while current != start {
+                path.push(current);
+                current = previous[&current];
+            }
+            path.push(start);
+            path.reverse();
+
+            let mut locations: Vec<OntologyLocation> = component
+                .iter()
+                .filter_map(|index| match &graph[*index] {
+                    DependencyNode::Ontology(id) => Some(id.location().clone()),
+                    DependencyNode::Missing(_) => None,
+                })
+                .collect();
+            locations.sort();
+            let names: Vec<String> = path.into_iter().map(name).collect();
+            cycles.push(OntologyProblem {
+                locations,
+                graph: None,
+                message: format!("Import cycle: {}", names.join(" -> ")),
+            });
+        }
+        cycles.sort_by(|a, b| a.message.cmp(&b.message));
+        problems.extend(cycles);
+        Ok(())
+    }
+}
+
+const EQUIVALENT_CLASS: NamedNodeRef<'_> =
+    NamedNodeRef::new_unchecked("http://www.w3.org/2002/07/owl#equivalentClass");
+const DISJOINT_WITH: NamedNodeRef<'_> =
+    NamedNodeRef::new_unchecked("http://www.w3.org/2002/07/owl#disjointWith");
+const SOME_VALUES_FROM: NamedNodeRef<'_> =
+    NamedNodeRef::new_unchecked("http://www.w3.org/2002/07/owl#someValuesFrom");
+const ALL_VALUES_FROM: NamedNodeRef<'_> =
+    NamedNodeRef::new_unchecked("http://www.w3.org/2002/07/owl#allValuesFrom");
+const ON_PROPERTY: NamedNodeRef<'_> =
+    NamedNodeRef::new_unchecked("http://www.w3.org/2002/07/owl#onProperty");
+const EQUIVALENT_PROPERTY: NamedNodeRef<'_> =
+    NamedNodeRef::new_unchecked("http://www.w3.org/2002/07/owl#equivalentProperty");
+const INVERSE_OF: NamedNodeRef<'_> =
+    NamedNodeRef::new_unchecked("http://www.w3.org/2002/07/owl#inverseOf");
+
+// the namespaces whose terms RDF, RDFS, OWL and XSD define themselves
+const BUILTIN_NAMESPACES: [&str; 4] = [
+    "http://www.w3.org/1999/02/22-rdf-syntax-ns#",
+    "http://www.w3.org/2000/01/rdf-schema#",
+    "http://www.w3.org/2002/07/owl#",
+    "http://www.w3.org/2001/XMLSchema#",
+];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum TermKind {
+    Class,
+    Property,
+    // a position which is not checked
+    Any,
+}
+
+// axioms with the kinds of terms expected as their subject and object; blank nodes (e.g.
+// restrictions) are not checked
+const AXIOMS: [(NamedNodeRef<'_>, TermKind, TermKind); 11] = [
+    (rdfs::SUB_CLASS_OF, TermKind::Class, TermKind::Class),
+    (EQUIVALENT_CLASS, TermKind::Class, TermKind::Class),
+    (DISJOINT_WITH, TermKind::Class, TermKind::Class),
+    (SOME_VALUES_FROM, TermKind::Any, TermKind::Class),
+    (ALL_VALUES_FROM, TermKind::Any, TermKind::Class),
+    (rdfs::DOMAIN, TermKind::Property, TermKind::Class),
+    (rdfs::RANGE, TermKind::Property, TermKind::Class),
+    (ON_PROPERTY, TermKind::Any, TermKind::Property),
+    (
+        rdfs::SUB_PROPERTY_OF,
+        TermKind::Property,
+        TermKind::Property,
+    ),
+    (EQUIVALENT_PROPERTY, TermKind::Property, TermKind::Property),
+    (INVERSE_OF, TermKind::Property, TermKind::Property),
+];
+
+// the predicates only ontologies are the subject of
+const ONTOLOGY_PROPERTIES: [NamedNodeRef<'_>; 4] = [
+    IMPORTS,
+    VERSION_IRI,
+    PRIOR_VERSION,
+    BACKWARD_COMPATIBLE_WITH,
+];
+
+/// Structural OWL checks of the graph of every ontology: classes and properties used in axioms
+/// which no ontology in its imports closure declares (gives a type), nodes described with
+/// ontology properties such as owl:imports which are not typed owl:Ontology, and owl:imports
+/// whose values are not IRIs. Problems name the line of the file where the offending subject is
+/// described, if it can be found (see `util::find_iri_in_text`)
+pub struct OwlStructure {}
+
+impl EnvironmentCheck for OwlStructure {
+    fn name(&self) -> &str {
+        "OWL Structure"
+    }
+
+    fn check(&mut self, env: &OntoEnv, problems: &mut Vec<OntologyProblem>) -> Result<()> {
+        let mut graphs = vec![];
+        let mut declared: HashMap<GraphIdentifier, HashSet<NamedNode>> = HashMap::new();
+        let no_namespaces = BTreeMap::new();
+        for ontology in env.ontologies.values() {
+            let graph = env.get_graph(ontology.id())?;
+            let terms = graph
+                .triples_for_predicate(TYPE)
+                .filter_map(|t| match t.subject {
+                    SubjectRef::NamedNode(n) => Some(n.into_owned()),
+                    _ => None,
+                })
+                .collect();
+            declared.insert(ontology.id().clone(), terms);
+            graphs.push((ontology.id().clone(), graph));
+        }
+
+        for (id, graph) in graphs {
+            let problem = |message: String| OntologyProblem {
+                locations: vec![id.location().clone()],
+                graph: Some(id.name().as_str().to_string()),
+                message,
+            };
+            let text = match id.location() {
+                OntologyLocation::File(path) => std::fs::read_to_string(path).ok(),
+                OntologyLocation::Url(_) => None,
+            };
+            let namespaces = env
+                .ontologies
+                .get(&id)
+                .map_or(&no_namespaces, |ontology| ontology.namespace_map());
+            // " (line N)" for the line describing the subject, if it is found
+            let at_line = |subject: SubjectRef| {
+                let SubjectRef::NamedNode(subject) = subject else {
+                    return String::new();
+                };
+                text.as_deref()
+                    .and_then(|text| find_iri_in_text(text, subject.as_str(), namespaces))
+                    .map(|(line, _)| format!(" (line {})", line))
+                    .unwrap_or_default()
+            };
+            let closure = env.get_dependency_closure(&id)?;
+            let is_defined = |term: NamedNodeRef| {
+                BUILTIN_NAMESPACES
+                    .iter()
+                    .any(|ns| term.as_str().starts_with(ns))
+                    || closure.iter().any(|member| {
+                        declared
+                            .get(member)
+                            .is_some_and(|terms| terms.contains(&term.into_owned()))
+                    })
+            };
+            // the undefined terms, each with the line of the first axiom using it
+            let mut undefined: [BTreeMap<String, String>; 2] = Default::default();
+            let mut check_term = |term: TermRef, kind: TermKind, subject: SubjectRef| {
+                let index = match kind {
+                    TermKind::Class => 0,
+                    TermKind::Property => 1,
+                    TermKind::Any => return,
+                };
+                if let TermRef::NamedNode(term) = term {
+                    if !is_defined(term) {
+                        undefined[index]
+                            .entry(term.to_string())
+                            .or_insert_with(|| at_line(subject));
+                    }
+                }
+            };
+            for (predicate, subject_kind, object_kind) in AXIOMS {
+                for triple in graph.triples_for_predicate(predicate) {
+                    check_term(triple.subject.into(), subject_kind, triple.subject);
+                    check_term(triple.object, object_kind, triple.subject);
+                }
+            }
+            for (kind, terms) in ["classes", "properties"].iter().zip(undefined) {
+                if !terms.is_empty() {
+                    let terms: Vec<String> = terms
+                        .into_iter()
+                        .map(|(term, line)| format!("{}{}", term, line))
+                        .collect();
+                    problems.push(problem(format!(
+                        "Undefined {} used in axioms: {}",
+                        kind,
+                        terms.join(", ")
+                    )));
+                }
+            }
+
+            let mut untyped: BTreeMap<String, String> = BTreeMap::new();
+            for predicate in ONTOLOGY_PROPERTIES {
+                for triple in graph.triples_for_predicate(predicate) {
+                    if !graph.contains(TripleRef::new(triple.subject, TYPE, ONTOLOGY)) {
+                        untyped
+                            .entry(triple.subject.to_string())
+                            .or_insert_with(|| at_line(triple.subject));
+                    }
+                }
+            }
+            for (node, line) in untyped {
+                problems.push(problem(format!(
+                    "{} has ontology properties but is not typed owl:Ontology{}",
+                    node, line
+                )));
+            }
+
+            for triple in graph.triples_for_predicate(IMPORTS) {
+                if !matches!(triple.object, TermRef::NamedNode(_)) {
+                    problems.push(problem(format!(
+                        "owl:imports of {} is not an IRI: {}{}",
+                        triple.subject,
+                        triple.object,
+                        at_line(triple.subject)
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reports files which failed to parse at the last update because of a literal in subject
+/// position (e.g. a statement starting with a string), with the line of the literal
+pub struct LiteralSubjects {}
+
+impl EnvironmentCheck for LiteralSubjects {
+    fn name(&self) -> &str {
+        "Literal Subjects"
+    }
+
+    fn check(&mut self, env: &OntoEnv, problems: &mut Vec<OntologyProblem>) -> Result<()> {
+        // the update recorded the line each file failed to parse at
+        for failure in &env.read_failures {
+            let (OntologyLocation::File(path), Some(line)) = (&failure.location, failure.line)
+            else {
+                continue;
+            };
+            let Ok(text) = std::fs::read_to_string(path) else {
+                continue;
+            };
+            let Some(statement) = text.lines().nth(line.saturating_sub(1)) else {
+                continue;
+            };
+            if statement.trim_start().starts_with(['"', '\'']) {
+                problems.push(OntologyProblem {
+                    locations: vec![failure.location.clone()],
+                    graph: None,
+                    message: format!(
+                        "Literal in subject position at line {}: {}",
+                        line,
+                        statement.trim()
+                    ),
+                });
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,4 +1,4 @@
-// OfflineRetrieval error
+// OfflineRetrieval and Fetch errors
 
 use std::fmt;
 
@@ -14,3 +14,54 @@ impl fmt::Display for OfflineRetrievalError {
 }
 
 impl std::error::Error for OfflineRetrievalError {}
+
+/// The error returned when a remote ontology could not be fetched because the server answered
+/// with an unsuccessful HTTP status
+#[derive(Debug)]
+pub struct FetchError {
+    pub url: String,
+    pub status: u16,
+}
+
+impl fmt::Display for FetchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Failed to fetch ontology from {} (HTTP {})",
+            self.url, self.status
+        )
+    }
+}
+
+impl std::error::Error for FetchError {}
+
+/// The error returned when an ontology was not read because it exceeds the size or time budget
+/// of the configuration (`max_file_size` and `parse_timeout_secs`)
+#[derive(Debug)]
+pub struct ReadLimitError {
+    pub location: String,
+    pub reason: String,
+}
+
+impl fmt::Display for ReadLimitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Skipped {}: {}", self.location, self.reason)
+    }
+}
+
+impl std::error::Error for ReadLimitError {}
+
+/// The error returned when a remote ontology was fetched conditionally and the server answered
+/// that it did not change since it was last fetched
+#[derive(Debug)]
+pub struct NotModifiedError {
+    pub url: String,
+}
+
+impl fmt::Display for NotModifiedError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} is not modified", self.url)
+    }
+}
+
+impl std::error::Error for NotModifiedError {}
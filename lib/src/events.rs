@@ -0,0 +1,63 @@
+// notifications about the ontologies an environment adds, updates and removes, so applications
+// keeping derived state (reasoner results, search indexes) can invalidate only what changed
+// instead of rebuilding everything after an update, and about the progress of long operations,
+// so user interfaces can show it
+
+use crate::ontology::GraphIdentifier;
+use serde::Serialize;
+
+/// The parts of an operation progress is reported for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Phase {
+    /// Reading (or fetching) the documents of an update
+    Read,
+    /// Following the imports of the updated ontologies, fetching the missing ones
+    Resolve,
+    /// Computing the imports closure of an ontology
+    Closure,
+    /// Merging the graphs of a closure into one
+    Union,
+}
+
+/// How far a phase of an operation has come: it is at `item` (a location or an ontology), the
+/// `current`th of `total` items. While imports are discovered the total is the work known so
+/// far, so it grows along with `current`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Progress {
+    pub phase: Phase,
+    pub item: String,
+    pub current: usize,
+    pub total: usize,
+    pub percent: f64,
+}
+
+impl Progress {
+    pub fn new(phase: Phase, item: String, current: usize, total: usize) -> Self {
+        let percent = if total == 0 {
+            100.0
+        } else {
+            100.0 * current as f64 / total as f64
+        };
+        Progress {
+            phase,
+            item,
+            current,
+            total,
+            percent,
+        }
+    }
+}
+
+/// Receives the changes to an environment as `update` and `add` make them. All methods default
+/// to doing nothing, so listeners only need to handle what they are interested in
+pub trait EnvironmentListener: Send + Sync {
+    /// An ontology which was not in the environment before was added
+    fn on_add(&self, _id: &GraphIdentifier) {}
+    /// An ontology already in the environment was read again from its location
+    fn on_update(&self, _id: &GraphIdentifier) {}
+    /// An ontology was removed from the environment, e.g. because its file was deleted
+    fn on_remove(&self, _id: &GraphIdentifier) {}
+    /// An update, closure or union made progress
+    fn on_progress(&self, _progress: &Progress) {}
+}
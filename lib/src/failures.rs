@@ -0,0 +1,106 @@
+// imports which could not be fetched are remembered between updates, along with why they failed,
+// so later updates retry them with exponential backoff instead of fetching an unreachable
+// ontology every time
+
+use crate::errors::FetchError;
+use chrono::prelude::*;
+use chrono::Duration;
+use serde::{Deserialize, Serialize};
+
+/// Delay in seconds before the first retry of a failed import; it doubles with every failed
+/// attempt
+pub const RETRY_BASE_DELAY_SECS: i64 = 60;
+/// Longest delay in seconds between two attempts to fetch a failed import
+pub const RETRY_MAX_DELAY_SECS: i64 = 24 * 60 * 60;
+
+/// Why an import could not be fetched, and when it was last attempted
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ImportFailure {
+    pub error: String,
+    // the HTTP status the server answered with, if it answered
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    // number of failed attempts in a row
+    pub attempts: u32,
+    pub last_attempt: DateTime<Utc>,
+}
+
+fn http_status(error: &anyhow::Error) -> Option<u16> {
+    if let Some(error) = error.downcast_ref::<FetchError>() {
+        return Some(error.status);
+    }
+    error
+        .downcast_ref::<reqwest::Error>()
+        .and_then(|e| e.status())
+        .map(|status| status.as_u16())
+}
+
+impl ImportFailure {
+    pub fn new(error: &anyhow::Error) -> Self {
+        ImportFailure {
+            error: error.to_string(),
+            status: http_status(error),
+            attempts: 1,
+            last_attempt: Utc::now(),
+        }
+    }
+
+    /// Records another failed attempt
+    pub fn record_attempt(&mut self, error: &anyhow::Error) {
+        self.error = error.to_string();
+        self.status = http_status(error);
+        self.attempts += 1;
+        self.last_attempt = Utc::now();
+    }
+
+    /// Returns how long to wait after the last attempt before trying again
+    pub fn retry_delay(&self) -> Duration {
+        let doublings = self.attempts.saturating_sub(1).min(20);
+        let delay = RETRY_BASE_DELAY_SECS * 2i64.pow(doublings);
+        Duration::seconds(delay.min(RETRY_MAX_DELAY_SECS))
+    }
+
+    pub fn next_attempt(&self) -> DateTime<Utc> {
+        self.last_attempt + self.retry_delay()
+    }
+
+    /// Returns true if the import is due to be fetched again at the given time
+    pub fn should_retry(&self, now: DateTime<Utc>) -> bool {
+        now >= self.next_attempt()
+    }
+}
+
+/// An owl:import which cannot be resolved to an ontology in the environment, along with why
+/// fetching it failed, if it was attempted
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct MissingImport {
+    pub import: String,
+    pub failure: Option<ImportFailure>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_backoff() {
+        let error = anyhow::Error::new(FetchError {
+            url: "http://example.org/ontology".to_string(),
+            status: 404,
+        });
+        let mut failure = ImportFailure::new(&error);
+        assert_eq!(failure.status, Some(404));
+        assert_eq!(failure.retry_delay(), Duration::minutes(1));
+        assert!(!failure.should_retry(failure.last_attempt));
+        assert!(failure.should_retry(failure.last_attempt + Duration::minutes(1)));
+
+        failure.record_attempt(&anyhow::anyhow!("Connection refused"));
+        failure.record_attempt(&anyhow::anyhow!("Connection refused"));
+        assert_eq!(failure.attempts, 3);
+        assert_eq!(failure.status, None);
+        assert_eq!(failure.retry_delay(), Duration::minutes(4));
+
+        failure.attempts = 100;
+        assert_eq!(failure.retry_delay(), Duration::days(1));
+    }
+}
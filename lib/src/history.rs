@@ -0,0 +1,57 @@
+// lightweight historical snapshots of the dependency graph, stored one snapshot per line in
+// .ontoenv/history.jsonl so that dependency creep can be tracked over time
+
+use anyhow::Result;
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+
+/// Direct and transitive dependency counts for a single ontology
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DependencyCount {
+    pub name: String,
+    pub location: String,
+    // number of owl:imports declared by the ontology
+    pub direct: usize,
+    // number of ontologies in the imports closure, not counting the ontology itself
+    pub transitive: usize,
+}
+
+/// The dependency counts of every ontology in the environment at one point in time
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DependencySnapshot {
+    pub timestamp: DateTime<Utc>,
+    pub counts: Vec<DependencyCount>,
+}
+
+/// Appends the snapshot to the history file, unless the counts are identical to the most
+/// recently recorded snapshot
+pub fn record_snapshot(path: &Path, snapshot: &DependencySnapshot) -> Result<bool> {
+    if let Some(last) = load_history(path)?.last() {
+        if last.counts == snapshot.counts {
+            return Ok(false);
+        }
+    }
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(snapshot)?)?;
+    Ok(true)
+}
+
+/// Reads all snapshots from the history file, oldest first. A missing file is an empty history
+pub fn load_history(path: &Path) -> Result<Vec<DependencySnapshot>> {
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    let reader = BufReader::new(std::fs::File::open(path)?);
+    let mut snapshots = vec![];
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        snapshots.push(serde_json::from_str(&line)?);
+    }
+    Ok(snapshots)
+}
@@ -0,0 +1,83 @@
+// a hook for applications to extract their own metadata from ontologies (e.g. dcat distribution
+// info or custom version predicates). Inspectors run whenever an ontology is parsed; what they
+// return is stored on the Ontology and saved with the environment
+
+use crate::consts::{DECLARE, SH_NAMESPACE, SH_PREFIX};
+use anyhow::Result;
+use oxigraph::model::{Graph, NamedNodeRef, SubjectRef, TermRef};
+use serde_json::{Map, Value};
+
+/// Extracts metadata from an ontology when it is parsed. The returned value is stored under
+/// the inspector's name (see `Ontology::metadata`); returning None stores nothing
+pub trait OntologyInspector: Send + Sync {
+    fn name(&self) -> &str;
+    fn inspect(&self, ontology: NamedNodeRef, graph: &Graph) -> Result<Option<Value>>;
+}
+
+/// Collects the prefixes an ontology declares for SHACL-SPARQL with sh:declare, as an object
+/// mapping each prefix to its namespace
+pub struct ShaclDeclarations;
+
+impl OntologyInspector for ShaclDeclarations {
+    fn name(&self) -> &str {
+        "shacl_declarations"
+    }
+
+    fn inspect(&self, ontology: NamedNodeRef, graph: &Graph) -> Result<Option<Value>> {
+        let mut prefixes = Map::new();
+        for declaration in graph.objects_for_subject_predicate(ontology, DECLARE) {
+            let declaration: SubjectRef = match declaration {
+                TermRef::NamedNode(n) => n.into(),
+                TermRef::BlankNode(b) => b.into(),
+                _ => continue,
+            };
+            let prefix = graph.object_for_subject_predicate(declaration, SH_PREFIX);
+            let namespace = graph.object_for_subject_predicate(declaration, SH_NAMESPACE);
+            if let (Some(TermRef::Literal(prefix)), Some(TermRef::Literal(namespace))) =
+                (prefix, namespace)
+            {
+                prefixes.insert(
+                    prefix.value().to_string(),
+                    Value::String(namespace.value().to_string()),
+                );
+            }
+        }
+        if prefixes.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Value::Object(prefixes)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use oxigraph::io::{RdfFormat, RdfParser};
+
+    #[test]
+    fn test_shacl_declarations() {
+        let turtle = r#"
+            @prefix owl: <http://www.w3.org/2002/07/owl#> .
+            @prefix sh: <http://www.w3.org/ns/shacl#> .
+            @prefix xsd: <http://www.w3.org/2001/XMLSchema#> .
+            <http://example.org/shapes> a owl:Ontology ;
+                sh:declare [
+                    sh:prefix "ex" ;
+                    sh:namespace "http://example.org/ns#"^^xsd:anyURI ;
+                ] .
+        "#;
+        let mut graph = Graph::new();
+        for quad in RdfParser::from_format(RdfFormat::Turtle).for_reader(turtle.as_bytes()) {
+            graph.insert(quad.unwrap().as_ref());
+        }
+        let ontology = NamedNodeRef::new("http://example.org/shapes").unwrap();
+        let value = ShaclDeclarations.inspect(ontology, &graph).unwrap();
+        assert_eq!(
+            value,
+            Some(serde_json::json!({"ex": "http://example.org/ns#"}))
+        );
+
+        let other = NamedNodeRef::new("http://example.org/other").unwrap();
+        assert_eq!(ShaclDeclarations.inspect(other, &graph).unwrap(), None);
+    }
+}
@@ -0,0 +1,359 @@
+// fetching remote ontologies over HTTP. Servers of W3C, OGC and similar vocabularies often
+// negotiate the format of a document, redirect to a versioned URL and rate limit clients, so
+// requests advertise every RDF format ontoenv reads, follow redirects, and retry transient
+// failures with exponential backoff. Ontologies which are fetched again are requested
+// conditionally with the ETag and Last-Modified of the last response, so unchanged documents
+// are not sent again
+
+use crate::errors::{FetchError, NotModifiedError};
+use anyhow::Result;
+use oxigraph::io::RdfFormat;
+use reqwest::blocking::{Client, Response};
+use reqwest::header::{
+    ACCEPT, CONTENT_TYPE, ETAG, IF_MODIFIED_SINCE, IF_NONE_MATCH, LAST_MODIFIED, RETRY_AFTER,
+};
+use reqwest::redirect::Policy;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::sync::{Condvar, Mutex};
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// The Accept header of ontology requests. JSON-LD is accepted last, since ontoenv cannot
+/// parse it and only reads such a document if its content turns out to be another format
+pub const ACCEPT_RDF: &str = "text/turtle, application/x-turtle;q=0.95, \
+application/rdf+xml;q=0.9, application/n-triples;q=0.8, text/n3;q=0.7, \
+application/ld+json;q=0.5, text/plain;q=0.2, */*;q=0.1";
+
+/// How remote ontologies are fetched
+#[derive(Debug, Clone)]
+pub struct HttpOptions {
+    // retries of a request after a 429, a 5xx or a connection error
+    pub max_retries: u32,
+    // delay before the first retry; it doubles with every retry
+    pub initial_backoff: Duration,
+    // longest delay between two attempts, also for delays asked for with Retry-After
+    pub max_backoff: Duration,
+    pub max_redirects: usize,
+}
+
+impl Default for HttpOptions {
+    fn default() -> Self {
+        HttpOptions {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(500),
+            max_backoff: Duration::from_secs(30),
+            max_redirects: 10,
+        }
+    }
+}
+
+impl HttpOptions {
+    /// Returns how long to wait before the given retry (starting at 1)
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let doublings = retry.saturating_sub(1).min(16);
+        self.initial_backoff
+            .saturating_mul(2u32.pow(doublings))
+            .min(self.max_backoff)
+    }
+}
+
+/// The ETag and Last-Modified headers of a fetched document, sent back when it is fetched
+/// again so the server can answer 304 Not Modified if it did not change
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct CacheValidators {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub etag: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_modified: Option<String>,
+}
+
+impl CacheValidators {
+    fn from_response(response: &Response) -> Self {
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+        CacheValidators {
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none()
+    }
+}
+
+/// A fetched document
+#[derive(Debug, Clone)]
+pub struct HttpResponse {
+    // the URL the document was served from, after following redirects
+    pub url: String,
+    pub content_type: Option<String>,
+    pub body: Vec<u8>,
+    pub validators: CacheValidators,
+}
+
+impl HttpResponse {
+    /// Returns the RDF format of the document according to its Content-Type, if known
+    pub fn format(&self) -> Option<RdfFormat> {
+        self.content_type.as_deref().and_then(format_for_media_type)
+    }
+}
+
+/// Returns the RDF format of a media type, ignoring parameters such as the charset
+pub fn format_for_media_type(media_type: &str) -> Option<RdfFormat> {
+    let essence = media_type.split(';').next()?.trim().to_ascii_lowercase();
+    match essence.as_str() {
+        "text/turtle" | "application/x-turtle" | "application/turtle" => Some(RdfFormat::Turtle),
+        "application/rdf+xml" => Some(RdfFormat::RdfXml),
+        // N-Triples used to be served as text/plain
+        "application/n-triples" | "text/plain" => Some(RdfFormat::NTriples),
+        "text/n3" | "text/rdf+n3" => Some(RdfFormat::N3),
+        "application/n-quads" => Some(RdfFormat::NQuads),
+        "application/trig" => Some(RdfFormat::TriG),
+        _ => {
+            debug!("Unknown content type: {}", media_type);
+            None
+        }
+    }
+}
+
+// 429 and 5xx answers are worth another attempt; other failures are not going to change
+fn is_transient(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+// the delay a server asked for with Retry-After, if given in seconds
+fn retry_after(response: &Response) -> Option<Duration> {
+    response
+        .headers()
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .map(Duration::from_secs)
+}
+
+/// Limits how many documents are fetched at once by several threads, see
+/// `ResourceLimits::max_downloads`
+pub(crate) struct DownloadSlots {
+    free: Mutex<usize>,
+    released: Condvar,
+}
+
+/// A slot taken from `DownloadSlots`, given back when dropped
+pub(crate) struct DownloadSlot<'a>(&'a DownloadSlots);
+
+impl DownloadSlots {
+    pub(crate) fn new(slots: usize) -> Self {
+        DownloadSlots {
+            free: Mutex::new(slots.max(1)),
+            released: Condvar::new(),
+        }
+    }
+
+    /// Waits until a slot is free and takes it
+    pub(crate) fn acquire(&self) -> DownloadSlot<'_> {
+        let mut free = self.free.lock().unwrap_or_else(|e| e.into_inner());
+        while *free == 0 {
+            free = self.released.wait(free).unwrap_or_else(|e| e.into_inner());
+        }
+        *free -= 1;
+        DownloadSlot(self)
+    }
+}
+
+impl Drop for DownloadSlot<'_> {
+    fn drop(&mut self) {
+        *self.0.free.lock().unwrap_or_else(|e| e.into_inner()) += 1;
+        self.0.released.notify_one();
+    }
+}
+
+/// Fetches the URL, negotiating an RDF format and following redirects. Requests answered with
+/// 429 or a 5xx status, or which fail to connect, are retried after a growing delay (or the
+/// delay the server asks for with Retry-After); once the retries run out, the last failure is
+/// returned
+pub fn fetch(url: &str, options: &HttpOptions) -> Result<HttpResponse> {
+    fetch_if_modified(url, options, &CacheValidators::default())
+}
+
+/// Like `fetch`, but sends the validators of an earlier response as If-None-Match and
+/// If-Modified-Since. Fails with `NotModifiedError` if the server answers that the document
+/// did not change
+pub fn fetch_if_modified(
+    url: &str,
+    options: &HttpOptions,
+    validators: &CacheValidators,
+) -> Result<HttpResponse> {
+    debug!("Fetching url: {}", url);
+    let client = Client::builder()
+        .redirect(Policy::limited(options.max_redirects))
+        .build()?;
+    let mut retry = 0;
+    loop {
+        let mut request = client.get(url).header(ACCEPT, ACCEPT_RDF);
+        if let Some(etag) = &validators.etag {
+            request = request.header(IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &validators.last_modified {
+            request = request.header(IF_MODIFIED_SINCE, last_modified);
+        }
+        let delay = match request.send() {
+            Ok(response) if response.status() == StatusCode::NOT_MODIFIED => {
+                debug!("{} is not modified", url);
+                return Err(NotModifiedError {
+                    url: url.to_string(),
+                }
+                .into());
+            }
+            Ok(response) if response.status().is_success() => {
+                let final_url = response.url().to_string();
+                if final_url != url {
+                    debug!("{} redirected to {}", url, final_url);
+                }
+                let content_type = response
+                    .headers()
+                    .get(CONTENT_TYPE)
+                    .and_then(|ct| ct.to_str().ok())
+                    .map(str::to_string);
+                let validators = CacheValidators::from_response(&response);
+                return Ok(HttpResponse {
+                    url: final_url,
+                    content_type,
+                    validators,
+                    body: response.bytes()?.to_vec(),
+                });
+            }
+            Ok(response) if is_transient(response.status()) && retry < options.max_retries => {
+                warn!(url, status = response.status().as_u16(), "Retrying fetch");
+                retry_after(&response)
+                    .map(|delay| delay.min(options.max_backoff))
+                    .unwrap_or_else(|| options.backoff(retry + 1))
+            }
+            Ok(response) => {
+                return Err(FetchError {
+                    url: url.to_string(),
+                    status: response.status().as_u16(),
+                }
+                .into())
+            }
+            Err(e) if (e.is_connect() || e.is_timeout()) && retry < options.max_retries => {
+                warn!(url, error = %e, "Retrying fetch");
+                options.backoff(retry + 1)
+            }
+            Err(e) => return Err(e.into()),
+        };
+        retry += 1;
+        std::thread::sleep(delay);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    // serves the canned responses, one per connection, and returns the server's base URL
+    fn serve(responses: Vec<String>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 0 && line.trim() != "" {
+                    line.clear();
+                }
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+        base
+    }
+
+    fn response(status: &str, headers: &[&str], body: &str) -> String {
+        let mut response = format!("HTTP/1.1 {}\r\nConnection: close\r\n", status);
+        for header in headers {
+            response.push_str(&format!("{}\r\n", header));
+        }
+        response.push_str(&format!("Content-Length: {}\r\n\r\n{}", body.len(), body));
+        response
+    }
+
+    #[test]
+    fn test_format_for_media_type() {
+        assert_eq!(
+            format_for_media_type("text/turtle; charset=utf-8"),
+            Some(RdfFormat::Turtle)
+        );
+        assert_eq!(
+            format_for_media_type("Application/RDF+XML"),
+            Some(RdfFormat::RdfXml)
+        );
+        assert_eq!(format_for_media_type("text/html"), None);
+    }
+
+    #[test]
+    fn test_backoff() {
+        let options = HttpOptions {
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(5),
+            ..HttpOptions::default()
+        };
+        assert_eq!(options.backoff(1), Duration::from_secs(1));
+        assert_eq!(options.backoff(3), Duration::from_secs(4));
+        assert_eq!(options.backoff(4), Duration::from_secs(5));
+    }
+
+    #[test]
+    fn test_fetch_retries_and_redirects() {
+        let body = "<urn:a> <urn:b> <urn:c> .\n";
+        let base = serve(vec![
+            response("503 Service Unavailable", &["Retry-After: 0"], ""),
+            response("429 Too Many Requests", &[], ""),
+            response("302 Found", &["Location: /v2/ontology.ttl"], ""),
+            response(
+                "200 OK",
+                &["Content-Type: text/turtle; charset=utf-8"],
+                body,
+            ),
+        ]);
+        let options = HttpOptions {
+            initial_backoff: Duration::from_millis(10),
+            ..HttpOptions::default()
+        };
+        let fetched = fetch(&format!("{}/ontology", base), &options).unwrap();
+        assert_eq!(fetched.url, format!("{}/v2/ontology.ttl", base));
+        assert_eq!(fetched.format(), Some(RdfFormat::Turtle));
+        assert_eq!(fetched.body, body.as_bytes());
+
+        // failures which are not transient are not retried
+        let base = serve(vec![response("404 Not Found", &[], "")]);
+        let error = fetch(&format!("{}/missing", base), &options).unwrap_err();
+        assert_eq!(error.downcast_ref::<FetchError>().unwrap().status, 404);
+    }
+
+    #[test]
+    fn test_fetch_if_modified() {
+        let base = serve(vec![
+            response("200 OK", &["ETag: \"v1\""], "<urn:a> <urn:b> <urn:c> .\n"),
+            response("304 Not Modified", &[], ""),
+        ]);
+        let url = format!("{}/ontology.ttl", base);
+        let fetched = fetch(&url, &HttpOptions::default()).unwrap();
+        assert_eq!(fetched.validators.etag.as_deref(), Some("\"v1\""));
+        assert!(fetched.validators.last_modified.is_none());
+        let error =
+            fetch_if_modified(&url, &HttpOptions::default(), &fetched.validators).unwrap_err();
+        assert!(error.is::<NotModifiedError>());
+    }
+}
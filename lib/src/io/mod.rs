@@ -0,0 +1,2 @@
+// reading ontologies from outside the environment
+pub mod http;
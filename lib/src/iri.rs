@@ -0,0 +1,255 @@
+// normalization of IRIs, so the same ontology referenced with minor variations (the case of the
+// scheme or host, percent-encodings, Unicode normalization forms, stray spaces) resolves to a
+// single entry. Ontology names and imports are normalized when a graph is parsed and stored, and
+// names are normalized again when they are looked up
+
+use crate::consts::{IMPORTS, ONTOLOGY, TYPE};
+use anyhow::Result;
+use oxigraph::model::{Graph, NamedNode, Subject, SubjectRef, Term, TermRef, Triple};
+use std::collections::HashMap;
+use unicode_normalization::UnicodeNormalization;
+
+const HEX: &[u8; 16] = b"0123456789ABCDEF";
+
+/// Returns true for the characters which can appear in an IRI without percent-encoding
+fn is_allowed(c: char) -> bool {
+    !(c.is_control()
+        || c.is_whitespace()
+        || matches!(c, '<' | '>' | '"' | '{' | '}' | '|' | '\\' | '^' | '`'))
+}
+
+fn is_unreserved(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~')
+}
+
+fn hex_value(b: u8) -> Option<u8> {
+    (b as char).to_digit(16).map(|d| d as u8)
+}
+
+/// Normalizes percent-encodings: escapes of unreserved characters are decoded, other escapes
+/// are upper-cased, and characters which are not allowed in IRIs are encoded
+fn normalize_percent_encoding(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = String::with_capacity(s.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            if let (Some(hi), Some(lo)) = (hex_value(bytes[i + 1]), hex_value(bytes[i + 2])) {
+                let decoded = hi * 16 + lo;
+                if is_unreserved(decoded) {
+                    out.push(decoded as char);
+                } else {
+                    out.push('%');
+                    out.push(HEX[hi as usize] as char);
+                    out.push(HEX[lo as usize] as char);
+                }
+                i += 3;
+                continue;
+            }
+        }
+        // i is always at a char boundary here since '%' and hex digits are ASCII
+        let c = s[i..].chars().next().expect("index is within the string");
+        if c == '%' {
+            // a '%' which does not start an escape
+            out.push_str("%25");
+        } else if is_allowed(c) {
+            out.push(c);
+        } else {
+            let mut buf = [0; 4];
+            for b in c.encode_utf8(&mut buf).bytes() {
+                out.push('%');
+                out.push(HEX[(b >> 4) as usize] as char);
+                out.push(HEX[(b & 0xf) as usize] as char);
+            }
+        }
+        i += c.len_utf8();
+    }
+    out
+}
+
+/// Normalizes an IRI:
+/// - surrounding whitespace and angle brackets are removed
+/// - the IRI is converted to Unicode normalization form C
+/// - the scheme and host are lower-cased and default HTTP(S) ports are removed
+/// - percent-encodings are normalized and disallowed characters (e.g. spaces) are encoded
+///
+/// The path, query and fragment are otherwise unchanged, since they are case-sensitive
+pub fn normalize_iri(iri: &str) -> String {
+    let iri = iri.trim().trim_start_matches('<').trim_end_matches('>');
+    let iri: String = iri.nfc().collect();
+    let iri = normalize_percent_encoding(&iri);
+
+    let (scheme, rest) = match iri.split_once(':') {
+        Some((scheme, rest))
+            if !scheme.is_empty()
+                && scheme
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.')) =>
+        {
+            (scheme.to_ascii_lowercase(), rest)
+        }
+        _ => return iri,
+    };
+    let authority_and_path = match rest.strip_prefix("//") {
+        Some(authority_and_path) => authority_and_path,
+        None => return format!("{}:{}", scheme, rest),
+    };
+    let end = authority_and_path
+        .find(['/', '?', '#'])
+        .unwrap_or(authority_and_path.len());
+    let (authority, path) = authority_and_path.split_at(end);
+    let (userinfo, hostport) = match authority.rsplit_once('@') {
+        Some((userinfo, hostport)) => (Some(userinfo), hostport),
+        None => (None, authority),
+    };
+    let mut hostport = hostport.to_lowercase();
+    let default_port = match scheme.as_str() {
+        "http" => Some(":80"),
+        "https" => Some(":443"),
+        _ => None,
+    };
+    if let Some(port) = default_port {
+        if let Some(host) = hostport.strip_suffix(port) {
+            hostport = host.to_string();
+        }
+    }
+    match userinfo {
+        Some(userinfo) => format!("{}://{}@{}{}", scheme, userinfo, hostport, path),
+        None => format!("{}://{}{}", scheme, hostport, path),
+    }
+}
+
+/// Returns the IRI without its trailing '/' and '#' characters, so `http://example.org/ont`,
+/// `http://example.org/ont/` and `http://example.org/ont#` have the same key. Used to resolve
+/// imports which differ from ontology names only in their trailing separator (see
+/// `Config::ignore_trailing_separators`)
+pub fn trailing_separator_key(iri: &str) -> &str {
+    iri.trim_end_matches(['/', '#'])
+}
+
+/// Parses a user-provided IRI after normalizing it
+pub fn parse_iri(iri: &str) -> Result<NamedNode> {
+    NamedNode::new(normalize_iri(iri)).map_err(|e| anyhow::anyhow!("Invalid IRI {}: {}", iri, e))
+}
+
+/// Returns the normalized form of the named node
+pub fn normalize_named_node(node: &NamedNode) -> NamedNode {
+    let normalized = normalize_iri(node.as_str());
+    if normalized == node.as_str() {
+        return node.clone();
+    }
+    NamedNode::new(normalized).unwrap_or_else(|_| node.clone())
+}
+
+/// Normalizes the names of the ontologies declared in the graph and the IRIs they import,
+/// wherever those IRIs appear in the graph. Returns the number of IRIs which were changed
+pub fn normalize_ontology_iris(graph: &mut Graph) -> usize {
+    let mut renames: HashMap<NamedNode, NamedNode> = HashMap::new();
+    for declaration in graph.subjects_for_predicate_object(TYPE, ONTOLOGY) {
+        let SubjectRef::NamedNode(name) = declaration else {
+            continue;
+        };
+        let mut iris = vec![name.into_owned()];
+        for import in graph.objects_for_subject_predicate(name, IMPORTS) {
+            if let TermRef::NamedNode(import) = import {
+                iris.push(import.into_owned());
+            }
+        }
+        for iri in iris {
+            let normalized = normalize_named_node(&iri);
+            if normalized != iri {
+                renames.insert(iri, normalized);
+            }
+        }
+    }
+    if renames.is_empty() {
+        return 0;
+    }
+
+    let changed: Vec<Triple> = graph
+        .iter()
+        .filter(|t| {
+            matches!(t.subject, SubjectRef::NamedNode(n) if renames.contains_key(&n.into_owned()))
+                || matches!(t.object, TermRef::NamedNode(n) if renames.contains_key(&n.into_owned()))
+        })
+        .map(|t| t.into_owned())
+        .collect();
+    for triple in changed {
+        graph.remove(&triple);
+        let mut renamed = triple.clone();
+        if let Subject::NamedNode(n) = &triple.subject {
+            if let Some(new) = renames.get(n) {
+                renamed.subject = new.clone().into();
+            }
+        }
+        if let Term::NamedNode(n) = &triple.object {
+            if let Some(new) = renames.get(n) {
+                renamed.object = new.clone().into();
+            }
+        }
+        graph.insert(&renamed);
+    }
+    renames.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use oxigraph::model::TripleRef;
+
+    #[test]
+    fn test_normalize_iri() {
+        assert_eq!(
+            normalize_iri("HTTP://Example.ORG:80/Ontology#Class"),
+            "http://example.org/Ontology#Class"
+        );
+        assert_eq!(
+            normalize_iri(" <https://example.org:443/a%2fb%7Ec> "),
+            "https://example.org/a%2Fb~c"
+        );
+        assert_eq!(
+            normalize_iri("http://example.org/my ontology"),
+            "http://example.org/my%20ontology"
+        );
+        assert_eq!(
+            normalize_iri("http://example.org:8080/x"),
+            "http://example.org:8080/x"
+        );
+        // e + combining acute accent is composed into é
+        assert_eq!(
+            normalize_iri("http://example.org/caf\u{0065}\u{0301}"),
+            "http://example.org/caf\u{00e9}"
+        );
+        assert_eq!(normalize_iri("URN:Example:Ont"), "urn:Example:Ont");
+        assert_eq!(
+            normalize_iri("http://example.org/100%"),
+            "http://example.org/100%25"
+        );
+        let normalized = normalize_iri("HTTP://Example.org/a b");
+        assert_eq!(normalize_iri(&normalized), normalized);
+    }
+
+    #[test]
+    fn test_trailing_separator_key() {
+        let key = trailing_separator_key("http://example.org/ont");
+        assert_eq!(trailing_separator_key("http://example.org/ont/"), key);
+        assert_eq!(trailing_separator_key("http://example.org/ont#"), key);
+        assert_eq!(trailing_separator_key("http://example.org/ont/#"), key);
+        assert_ne!(trailing_separator_key("http://example.org/ont2"), key);
+    }
+
+    #[test]
+    fn test_normalize_ontology_iris() {
+        let name = NamedNode::new("HTTP://Example.org/ontology").unwrap();
+        let import = NamedNode::new("http://EXAMPLE.org/other").unwrap();
+        let mut graph = Graph::new();
+        graph.insert(TripleRef::new(&name, TYPE, ONTOLOGY));
+        graph.insert(TripleRef::new(&name, IMPORTS, &import));
+        assert_eq!(normalize_ontology_iris(&mut graph), 2);
+        let name = NamedNode::new("http://example.org/ontology").unwrap();
+        let import = NamedNode::new("http://example.org/other").unwrap();
+        assert!(graph.contains(TripleRef::new(&name, TYPE, ONTOLOGY)));
+        assert!(graph.contains(TripleRef::new(&name, IMPORTS, &import)));
+        assert_eq!(graph.len(), 2);
+    }
+}
@@ -0,0 +1,105 @@
+// the journal of the changes an update makes to the store, one entry per line in
+// .ontoenv/journal. The store is written as the ontologies are read, while their metadata only
+// reaches ontoenv.json when the environment is saved, so an update which is killed halfway
+// leaves graphs in the store which the saved environment does not know about (or knows about
+// with different contents). The journal records every graph before it is loaded or dropped and
+// every ontology once its graph is loaded; it is deleted when the environment is saved, and an
+// environment opened with a journal left over finishes the interrupted update from it (see
+// `OntoEnv::recover_from_journal`)
+
+use crate::ontology::Ontology;
+use anyhow::Result;
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+
+/// One change to the store, recorded before it is made (`Begin`, `Add`, `Remove`) or once it is
+/// complete (`Added`). Graphs are named as in the store, e.g. `urn:ontoenv:graph:<uuid>`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "op", rename_all = "lowercase")]
+pub enum JournalEntry {
+    // an update started
+    Begin { timestamp: DateTime<Utc> },
+    // the graph is about to be (re)loaded
+    Add { graph: String },
+    // the graph of the ontology was loaded completely
+    Added { ontology: Box<Ontology> },
+    // the graph is about to be dropped
+    Remove { graph: String },
+}
+
+/// Appends the entry to the journal, making sure it is on disk before the change is made
+pub fn record(path: &Path, entry: &JournalEntry) -> Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(entry)?)?;
+    file.sync_data()?;
+    Ok(())
+}
+
+/// Reads the entries of the journal, oldest first. A missing journal has no entries, and a last
+/// line cut short by the interruption is ignored
+pub fn load(path: &Path) -> Result<Vec<JournalEntry>> {
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    let reader = BufReader::new(fs::File::open(path)?);
+    let lines = reader.lines().collect::<std::io::Result<Vec<String>>>()?;
+    let last = lines.len().saturating_sub(1);
+    let mut entries = vec![];
+    for (i, line) in lines.iter().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        match serde_json::from_str(line) {
+            Ok(entry) => entries.push(entry),
+            Err(_) if i == last => break,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(entries)
+}
+
+/// Deletes the journal, once the changes it records are saved
+pub fn clear(path: &Path) -> Result<()> {
+    if path.exists() {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    #[test]
+    fn test_truncated_last_entry_is_ignored() -> Result<()> {
+        let dir = TempDir::new("ontoenv-journal")?;
+        let path = dir.path().join("journal");
+        record(
+            &path,
+            &JournalEntry::Begin {
+                timestamp: Utc::now(),
+            },
+        )?;
+        record(
+            &path,
+            &JournalEntry::Add {
+                graph: "urn:ontoenv:graph:a".to_string(),
+            },
+        )?;
+        let mut file = OpenOptions::new().append(true).open(&path)?;
+        write!(file, "{{\"op\":\"remove\",\"gra")?;
+        let entries = load(&path)?;
+        assert_eq!(entries.len(), 2);
+        assert!(
+            matches!(&entries[1], JournalEntry::Add { graph } if graph == "urn:ontoenv:graph:a")
+        );
+
+        clear(&path)?;
+        assert!(load(&path)?.is_empty());
+        Ok(())
+    }
+}
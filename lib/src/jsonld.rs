@@ -0,0 +1,181 @@
+// JSON-LD @context documents generated from the environment (see `OntoEnv::jsonld_context`): the
+// prefixes declared by the ontologies of an imports closure, plus a term definition for every
+// class and property the closure declares, so payloads can use short names such as "Sensor" or
+// "hasPoint" without a hand-maintained context
+
+use crate::consts::{
+    ANNOTATION_PROPERTY, CLASS, DATATYPE_PROPERTY, OBJECT_PROPERTY, RDFS_CLASS, RDF_PROPERTY, TYPE,
+};
+use oxigraph::model::vocab::rdfs;
+use oxigraph::model::{Graph, NamedNode, NamedNodeRef, SubjectRef, TermRef};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use tracing::warn;
+
+// how values of a term are interpreted
+#[derive(Debug, Clone, PartialEq)]
+enum TermKind {
+    Class,
+    // values are IRIs
+    ObjectProperty,
+    // values are literals, of the datatype if the property has a single range
+    DatatypeProperty(Option<NamedNode>),
+    // values are left as they are written
+    Property,
+}
+
+/// Returns the term name of an IRI: the part after the last '#' or '/'
+pub fn local_name(iri: &str) -> Option<&str> {
+    let name = iri.rsplit(['#', '/']).next()?;
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '-' || c == '.');
+    valid.then_some(name)
+}
+
+// the IRI as a compact IRI if one of the prefixes matches it, so the context stays readable
+fn compact(prefixes: &BTreeMap<String, String>, iri: &str) -> String {
+    prefixes
+        .iter()
+        .filter(|(prefix, namespace)| !prefix.is_empty() && iri.starts_with(namespace.as_str()))
+        .max_by_key(|(_, namespace)| namespace.len())
+        .map(|(prefix, namespace)| format!("{}:{}", prefix, &iri[namespace.len()..]))
+        .unwrap_or_else(|| iri.to_string())
+}
+
+fn subjects_of_type(graph: &Graph, class: NamedNodeRef) -> BTreeSet<NamedNode> {
+    graph
+        .subjects_for_predicate_object(TYPE, class)
+        .filter_map(|s| match s {
+            SubjectRef::NamedNode(n) => Some(n.into_owned()),
+            _ => None,
+        })
+        .collect()
+}
+
+// the single named rdfs:range of the property, if it has exactly one
+fn single_range(graph: &Graph, property: NamedNodeRef) -> Option<NamedNode> {
+    let ranges: Vec<NamedNode> = graph
+        .objects_for_subject_predicate(property, rdfs::RANGE)
+        .filter_map(|o| match o {
+            TermRef::NamedNode(n) => Some(n.into_owned()),
+            _ => None,
+        })
+        .collect();
+    match ranges.as_slice() {
+        [range] => Some(range.clone()),
+        _ => None,
+    }
+}
+
+/// Builds a JSON-LD context ({"@context": {...}}) declaring the prefixes, and a term for each
+/// class and property declared in the graph, named after its local name. Object properties are
+/// typed "@id", and datatype properties with a single range are typed with it. Local names
+/// shared by several IRIs or by a prefix are ambiguous and left out
+pub fn context(prefixes: &BTreeMap<String, String>, graph: &Graph) -> Value {
+    let mut terms: BTreeMap<NamedNode, TermKind> = BTreeMap::new();
+    for class in [CLASS, RDFS_CLASS] {
+        for term in subjects_of_type(graph, class) {
+            terms.insert(term, TermKind::Class);
+        }
+    }
+    // more specific declarations take precedence
+    for property in [ANNOTATION_PROPERTY, RDF_PROPERTY] {
+        for term in subjects_of_type(graph, property) {
+            terms.insert(term, TermKind::Property);
+        }
+    }
+    for term in subjects_of_type(graph, DATATYPE_PROPERTY) {
+        let range = single_range(graph, term.as_ref());
+        terms.insert(term, TermKind::DatatypeProperty(range));
+    }
+    for term in subjects_of_type(graph, OBJECT_PROPERTY) {
+        terms.insert(term, TermKind::ObjectProperty);
+    }
+
+    let mut names: BTreeMap<&str, Vec<(&NamedNode, &TermKind)>> = BTreeMap::new();
+    for (term, kind) in &terms {
+        if let Some(name) = local_name(term.as_str()) {
+            names.entry(name).or_default().push((term, kind));
+        }
+    }
+
+    let mut context = Map::new();
+    for (prefix, namespace) in prefixes {
+        if !prefix.is_empty() {
+            context.insert(prefix.clone(), json!(namespace));
+        }
+    }
+    for (name, candidates) in names {
+        if context.contains_key(name) {
+            warn!(
+                term = name,
+                "Term name is also a prefix, leaving it out of the context"
+            );
+            continue;
+        }
+        let (term, kind) = match candidates.as_slice() {
+            [candidate] => *candidate,
+            _ => {
+                warn!(
+                    term = name,
+                    "Term name is ambiguous, leaving it out of the context"
+                );
+                continue;
+            }
+        };
+        let id = compact(prefixes, term.as_str());
+        let definition = match kind {
+            TermKind::Class | TermKind::Property => json!(id),
+            TermKind::ObjectProperty => json!({"@id": id, "@type": "@id"}),
+            TermKind::DatatypeProperty(Some(range)) => {
+                json!({"@id": id, "@type": compact(prefixes, range.as_str())})
+            }
+            TermKind::DatatypeProperty(None) => json!({ "@id": id }),
+        };
+        context.insert(name.to_string(), definition);
+    }
+    json!({ "@context": context })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use oxigraph::model::vocab::xsd;
+    use oxigraph::model::TripleRef;
+
+    #[test]
+    fn test_context() {
+        let ns = "https://brickschema.org/schema/Brick#";
+        let term = |name: &str| NamedNode::new(format!("{}{}", ns, name)).unwrap();
+        let other = NamedNode::new("http://example.org/other#Sensor").unwrap();
+        let mut graph = Graph::new();
+        graph.insert(TripleRef::new(&term("Sensor"), TYPE, CLASS));
+        graph.insert(TripleRef::new(&other, TYPE, CLASS));
+        graph.insert(TripleRef::new(&term("Point"), TYPE, CLASS));
+        graph.insert(TripleRef::new(&term("hasPoint"), TYPE, OBJECT_PROPERTY));
+        graph.insert(TripleRef::new(&term("value"), TYPE, DATATYPE_PROPERTY));
+        graph.insert(TripleRef::new(&term("value"), rdfs::RANGE, xsd::DOUBLE));
+        graph.insert(TripleRef::new(&term("brick"), TYPE, CLASS));
+        let prefixes = BTreeMap::from([
+            ("brick".to_string(), ns.to_string()),
+            (
+                "xsd".to_string(),
+                "http://www.w3.org/2001/XMLSchema#".to_string(),
+            ),
+        ]);
+
+        let context = context(&prefixes, &graph);
+        assert_eq!(
+            context,
+            json!({"@context": {
+                "brick": ns,
+                "xsd": "http://www.w3.org/2001/XMLSchema#",
+                "Point": "brick:Point",
+                "hasPoint": {"@id": "brick:hasPoint", "@type": "@id"},
+                "value": {"@id": "brick:value", "@type": "xsd:double"},
+            }})
+        );
+    }
+}
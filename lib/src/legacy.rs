@@ -0,0 +1,28 @@
+// the flat ontology listing (onto.json) written by the original single-binary ontoenv, kept so
+// downstream scripts reading it keep working while they move to `ontoenv list-ontologies --json`
+// or the library. Only the fields of the old listing are written
+
+use anyhow::Result;
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Name of the listing the original ontoenv wrote
+pub const LEGACY_MANIFEST_NAME: &str = "onto.json";
+
+/// An ontology as listed in the old onto.json
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LegacyOntology {
+    pub name: String,
+    // a file path or a URL
+    pub location: String,
+    // the owl:imports of the ontology, as written
+    pub imports: Vec<String>,
+    pub last_updated: Option<DateTime<Utc>>,
+}
+
+/// Writes the listing as a JSON array
+pub fn write_legacy_manifest(path: &Path, ontologies: &[LegacyOntology]) -> Result<()> {
+    std::fs::write(path, serde_json::to_string_pretty(ontologies)?)?;
+    Ok(())
+}
@@ -1,40 +1,115 @@
 extern crate derive_builder;
 
+#[cfg(feature = "bench")]
+pub mod bench;
+#[cfg(feature = "brick")]
+pub mod brick;
+pub mod cache;
+pub mod cancel;
+pub mod catalog;
+pub mod changes;
 pub mod config;
+pub mod constraints;
 pub mod consts;
+#[cfg(unix)]
+pub mod daemon;
+pub mod depgraph;
+pub mod diff;
+pub mod docgen;
 pub mod doctor;
 pub mod errors;
+pub mod events;
+pub mod failures;
+pub mod history;
+pub mod inspector;
+pub mod io;
+pub mod iri;
+pub mod journal;
+pub mod jsonld;
+pub mod legacy;
+pub mod lockfile;
+pub mod metrics;
+pub mod oci;
 pub mod ontology;
 pub mod policy;
+pub mod precommit;
+pub mod prefixes;
+pub mod reasoner;
+pub mod release;
+pub mod report;
+pub mod resolution;
+pub mod rpc;
+pub mod schema;
+pub mod scratch;
+pub mod server;
+#[cfg(feature = "shacl")]
+pub mod shacl;
+pub mod snapshots;
+pub mod sparql;
+pub mod terms;
+#[cfg(feature = "testing")]
+pub mod testing;
 #[macro_use]
 pub mod util;
 pub mod transform;
+pub mod watch;
 
-use crate::config::{Config, HowCreated};
-use crate::doctor::{Doctor, DuplicateOntology, OntologyDeclaration};
-use crate::ontology::{GraphIdentifier, Ontology, OntologyLocation};
-use crate::consts::{TYPE, ONTOLOGY};
+use crate::cancel::{CancellationToken, Cancelled};
+use crate::catalog::CatalogEntry;
+use crate::changes::ChangeManifest;
+use crate::config::{Config, DuplicateStrategy, HowCreated, StrictRule};
+use crate::constraints::{ConstraintViolation, VersionViolation};
+use crate::depgraph::{DependencyGraph, DependencyNode};
+use crate::diff::GraphDiff;
+use crate::doctor::{
+    Doctor, DuplicateOntology, ImportConstraintCheck, ImportCycles, LiteralSubjects,
+    LocationCasing, OntologyDeclaration, OntologyProblem, OwlStructure, VersionConstraintCheck,
+};
+use crate::errors::{NotModifiedError, ReadLimitError};
+use crate::events::{EnvironmentListener, Phase, Progress};
+use crate::failures::{ImportFailure, MissingImport};
+use crate::history::{DependencyCount, DependencySnapshot};
+use crate::inspector::OntologyInspector;
+use crate::io::http::{CacheValidators, DownloadSlots};
+use crate::journal::JournalEntry;
+use crate::legacy::LegacyOntology;
+use crate::metrics::{Counter, MetricsSink, Timer};
+use crate::oci::{ArtifactFile, BUNDLE_GRAPHS, BUNDLE_MANIFEST};
+use crate::ontology::{on_disk_casing, GraphIdentifier, Ontology, OntologyLocation};
+use crate::prefixes::{PrefixRegistry, PrefixSuggestion, PrefixUsage};
+use crate::reasoner::Reasoner;
+use crate::snapshots::{EnvironmentSnapshot, GraphStore};
+use crate::sparql::SparqlAuth;
+use crate::terms::{TermIndex, TermUsage};
+use crate::watch::WatchOptions;
+use crate::consts::{TYPE, ONTOLOGY, PROV_WAS_DERIVED_FROM};
 use anyhow::Result;
 use chrono::prelude::*;
-use log::{debug, error, info, warn};
+use tracing::{debug, error, info, info_span, instrument, warn};
 use oxigraph::model::{
-    Dataset, Graph, GraphName, NamedNode, NamedNodeRef, NamedOrBlankNode, QuadRef, SubjectRef, Subject
+    Dataset, Graph, GraphName, NamedNode, NamedNodeRef, NamedOrBlankNode, Quad, QuadRef, SubjectRef, Subject,
+    Term, TermRef, Triple,
 };
+use oxigraph::io::{RdfFormat, RdfParser, RdfSerializer};
 use oxigraph::store::Store;
 use petgraph::graph::{Graph as DiGraph, NodeIndex};
 use pretty_bytes::converter::convert as pretty_bytes;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::collections::{HashSet, VecDeque};
 use std::fs;
 use std::io::{BufReader, Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Condvar, Mutex, PoisonError};
+use std::time::{Duration, Instant};
 use walkdir::WalkDir;
 use std::fmt::{self, Display};
 
 // custom derive for ontologies field as vec of Ontology
 fn ontologies_ser<S>(
-    ontologies: &HashMap<GraphIdentifier, Ontology>,
+    ontologies: &BTreeMap<GraphIdentifier, Ontology>,
     s: S,
 ) -> Result<S::Ok, S::Error>
 where
@@ -44,18 +119,47 @@ where
     vec.serialize(s)
 }
 
-fn ontologies_de<'de, D>(d: D) -> Result<HashMap<GraphIdentifier, Ontology>, D::Error>
+fn ontologies_de<'de, D>(d: D) -> Result<BTreeMap<GraphIdentifier, Ontology>, D::Error>
 where
     D: serde::Deserializer<'de>,
 {
     let vec: Vec<Ontology> = Vec::deserialize(d)?;
-    let mut map = HashMap::new();
+    let mut map = BTreeMap::new();
     for ontology in vec {
         map.insert(ontology.id().clone(), ontology);
     }
     Ok(map)
 }
 
+// the kind of strictness which decides whether failing to read an ontology is fatal: web
+// locations fail on network errors, files on parse errors
+fn read_rule(location: &OntologyLocation) -> StrictRule {
+    if location.is_url() {
+        StrictRule::Network
+    } else {
+        StrictRule::Parse
+    }
+}
+
+// tells the workers of `OntoEnv::read_locations` that no more documents will be added, so those
+// waiting for their turn stop, when the thread adding them is done (or failed)
+struct StopReading<'a>(&'a Mutex<(usize, bool)>, &'a Condvar);
+
+impl Drop for StopReading<'_> {
+    fn drop(&mut self) {
+        self.0.lock().unwrap_or_else(PoisonError::into_inner).1 = true;
+        self.1.notify_all();
+    }
+}
+
+// the IRI of the graph of the ontology in the store, as recorded in the journal
+fn graph_iri(id: &GraphIdentifier) -> Result<String> {
+    match id.graphname()? {
+        GraphName::NamedNode(node) => Ok(node.into_string()),
+        _ => Err(anyhow::anyhow!("Graph name not found")),
+    }
+}
+
 pub struct FailedImport {
     ontology: GraphIdentifier,
     error: String,
@@ -73,6 +177,18 @@ impl Display for FailedImport {
     }
 }
 
+/// The graphs needed to validate an ontology with SHACL (e.g. with pySHACL)
+pub struct ShaclInputs {
+    // the graph being validated
+    pub data_graph: Graph,
+    // the union of the imports closure containing the shapes
+    pub shapes_graph: Graph,
+    // the union of the data graph's imports closure, without the data graph itself. Can be
+    // mixed into the data graph before validation to support inference
+    pub ont_graph: Graph,
+}
+
+#[derive(Debug, Clone, Serialize)]
 pub struct EnvironmentStatus {
     // true if there is an environment that ontoenv can find
     exists: bool,
@@ -86,6 +202,203 @@ pub struct EnvironmentStatus {
     how_created: HowCreated,
 }
 
+/// A location which could not be read during an update
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateFailure {
+    pub location: OntologyLocation,
+    pub error: String,
+    // the line (counted from 1) the parser stopped at, for documents which failed to parse
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+}
+
+impl UpdateFailure {
+    fn new(location: OntologyLocation, error: &anyhow::Error) -> Self {
+        UpdateFailure {
+            location,
+            error: error.to_string(),
+            line: util::parse_error_line(error),
+        }
+    }
+}
+
+/// What changed in an environment during an update
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct UpdateReport {
+    // ontologies read from files which were not in the environment before
+    pub added: Vec<GraphIdentifier>,
+    // ontologies read again because their files changed
+    pub updated: Vec<GraphIdentifier>,
+    // ontologies whose files were deleted or are no longer included
+    pub removed: Vec<GraphIdentifier>,
+    // locations which could not be read; only reported when not in strict mode, since strict
+    // mode fails the update instead
+    pub failed: Vec<UpdateFailure>,
+}
+
+/// What an update would do, computed by `OntoEnv::plan_update` without changing the
+/// environment. `OntoEnv::apply_update_plan` carries out a plan, so callers can preview it (e.g.
+/// for a dry run or to show progress totals) or drop actions from it first
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct UpdatePlan {
+    // files to read: new files and files which changed, or every file when planning a full
+    // update. Sorted by location
+    pub to_parse: Vec<OntologyLocation>,
+    // ontologies whose files were deleted or are no longer included; their graphs are dropped
+    pub to_remove: Vec<GraphIdentifier>,
+    // remote ontologies to fetch again: all of them for full updates, otherwise those fetched
+    // longer ago than the cache TTL. Never planned in offline mode
+    pub to_fetch: Vec<OntologyLocation>,
+}
+
+impl UpdateReport {
+    /// Returns true if the update changed nothing and no location failed
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.updated.is_empty()
+            && self.removed.is_empty()
+            && self.failed.is_empty()
+    }
+}
+
+impl UpdatePlan {
+    /// Returns the number of planned actions
+    pub fn len(&self) -> usize {
+        self.to_parse.len() + self.to_remove.len() + self.to_fetch.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// How an owl:imports IRI refers to an ontology (see `OntoEnv::explain_import`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportMatch {
+    /// The import is the name of the ontology
+    Name,
+    /// The import is the owl:versionIRI of a version of the ontology
+    VersionIri,
+    /// The import is an owl:priorVersion declared by a version of the ontology
+    PriorVersion,
+    /// The import is the URL a version of the ontology was read from
+    Location,
+}
+
+impl Display for ImportMatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            ImportMatch::Name => "name",
+            ImportMatch::VersionIri => "version IRI",
+            ImportMatch::PriorVersion => "prior version",
+            ImportMatch::Location => "location",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// An owl:imports of one ontology which refers to another
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportReference {
+    pub importer: GraphIdentifier,
+    // the IRI as it is written in the owl:imports, normalized
+    pub import: String,
+    pub matched: ImportMatch,
+}
+
+/// Where an ontology or a term is defined (see `OntoEnv::which_file`)
+#[derive(Debug, Clone, Serialize)]
+pub struct SourceLocation {
+    // the ontology named by the IRI, or the ontology defining the term
+    pub ontology: GraphIdentifier,
+    pub location: OntologyLocation,
+    // the local file backing the ontology; None for ontologies read from URLs
+    pub file: Option<PathBuf>,
+    // 1-based line and byte offset of the IRI in the file, see util::find_iri_in_text
+    pub line: Option<usize>,
+    pub offset: Option<usize>,
+}
+
+/// An ontology of a dependency closure and how it entered the closure (see
+/// `OntoEnv::get_closure_nodes`)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ClosureNode {
+    pub id: GraphIdentifier,
+    // length of the shortest import chain from the root of the closure
+    pub depth: usize,
+    // the ontology importing this one on that chain; None for the root
+    pub parent: Option<GraphIdentifier>,
+}
+
+/// An ontology name declared at several locations, with the location the resolution policy
+/// picks (see `OntoEnv::duplicate_names`)
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DuplicateName {
+    pub name: String,
+    pub selected: GraphIdentifier,
+    // every ontology with the name, sorted by location
+    pub candidates: Vec<GraphIdentifier>,
+}
+
+/// What `OntoEnv::remove` does when other ontologies still import the removed one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RemoveMode {
+    /// Refuse to remove it
+    #[default]
+    Error,
+    /// Also remove the ontologies which import it, directly or transitively
+    Cascade,
+    /// Remove only it; the imports of the ontologies importing it become missing. Not allowed
+    /// in strict mode, where imports may not be missing
+    Detach,
+}
+
+/// What `OntoEnv::remove` removed
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RemoveReport {
+    // the removed ontologies, sorted
+    pub removed: Vec<GraphIdentifier>,
+    // imports of the remaining ontologies which resolved before the removal but no longer do
+    pub newly_missing: Vec<String>,
+}
+
+/// Which of the ontologies matching a pattern `OntoEnv::refresh_matching` reads again
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RefreshStrategy {
+    /// Only files which changed since they were last read, like `update`. Remote ontologies are
+    /// not fetched again
+    #[default]
+    Changed,
+    /// Every matching ontology, including remote ones, which are fetched again
+    All,
+    /// Only files whose content changed since they were last read, whatever their modification
+    /// times. Remote ontologies are not fetched again
+    HashOnly,
+}
+
+/// Which ontology `OntoEnv::merge_view_with` keeps when several of the merged environments
+/// provide an ontology with the same name
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MergePrecedence {
+    /// The ontology of the first environment (in the given order) which provides it; the
+    /// versions in later environments are hidden
+    #[default]
+    First,
+    /// Every version is kept, and the resolution policy of the first environment picks one
+    Policy,
+}
+
+impl MergePrecedence {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "first" => Some(MergePrecedence::First),
+            "policy" => Some(MergePrecedence::Policy),
+            _ => None,
+        }
+    }
+}
+
 // impl Display pretty print for EnvironmentStatus
 impl std::fmt::Display for EnvironmentStatus {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
@@ -120,13 +433,48 @@ impl std::fmt::Display for EnvironmentStatus {
 pub struct OntoEnv {
     config: Config,
     #[serde(serialize_with = "ontologies_ser", deserialize_with = "ontologies_de")]
-    ontologies: HashMap<GraphIdentifier, Ontology>,
+    ontologies: BTreeMap<GraphIdentifier, Ontology>,
     dependency_graph: DiGraph<GraphIdentifier, (), petgraph::Directed>,
+    // imports which could not be fetched, by IRI, so they are retried with backoff
+    #[serde(default)]
+    failed_imports: BTreeMap<String, ImportFailure>,
+    // locations which failed to read the last time an update read them, e.g. so the doctor can
+    // report where files fail to parse without parsing them again
+    #[serde(default)]
+    read_failures: Vec<UpdateFailure>,
+    // the active profile (see set_profile), which the dependency graph was built for
+    #[serde(default)]
+    profile: Option<String>,
     #[serde(skip)]
     read_only: bool,
     how_created: HowCreated,
     #[serde(skip)]
     inner_store: Option<Store>,
+    #[serde(skip)]
+    metrics: Option<Arc<dyn MetricsSink>>,
+    #[serde(skip)]
+    cancellation: Option<CancellationToken>,
+    // graph IDs of the ontologies removed during an update, by ontology name
+    #[serde(skip)]
+    retired_graph_ids: HashMap<NamedNode, String>,
+    #[serde(skip)]
+    inspectors: Vec<Arc<dyn OntologyInspector>>,
+    #[serde(skip)]
+    listeners: Vec<Arc<dyn EnvironmentListener>>,
+    // set on views created by merge_view and load_snapshot, whose store only exists in memory
+    #[serde(skip)]
+    merged_view: bool,
+    // set on environments created by from_locations, which hold only the given ontologies and
+    // their closures in memory and never scan the search directories
+    #[serde(skip)]
+    temporary: bool,
+    // set on environments created by scratch, which keep their state in this directory instead
+    // of root/.ontoenv
+    #[serde(skip)]
+    scratch_dir: Option<PathBuf>,
+    // closures and unions computed since the ontologies last changed
+    #[serde(skip)]
+    memory_cache: Mutex<cache::MemoryCache>,
 }
 
 // probably need some graph "identifier" that incorporates location and version..
@@ -146,9 +494,17 @@ impl OntoEnv {
     /// Create a new OntoEnv with the given configuration. Will error if the
     /// environment already exists and recreate is false.
     pub fn new(config: Config, recreate: bool) -> Result<Self> {
+        Self::create(config, recreate, None)
+    }
+
+    // creates the environment in the scratch directory if there is one, and in root/.ontoenv
+    // otherwise
+    fn create(config: Config, recreate: bool, scratch_dir: Option<PathBuf>) -> Result<Self> {
         // create the config.root/.ontoenv directory so it exists before the store
         // is created
-        let ontoenv_dir = config.root.join(".ontoenv");
+        let ontoenv_dir = scratch_dir
+            .clone()
+            .unwrap_or_else(|| config.root.join(".ontoenv"));
         let config_path = ontoenv_dir.join("ontoenv.json");
         let mut how_created = HowCreated::New;
         info!("Creating OntoEnv with config: {:?}", config);
@@ -168,7 +524,7 @@ impl OntoEnv {
             if env.config != config {
                 info!("OntoEnv configuration has changed. Recreating environment.");
                 fs::remove_dir_all(&ontoenv_dir)?;
-                env = Self::new(config, true)?;
+                env = Self::create(config, true, scratch_dir)?;
                 env.how_created = HowCreated::RecreatedDifferentConfig;
                 return Ok(env);
             }
@@ -195,28 +551,187 @@ impl OntoEnv {
         // create the store in the root/.ontoenv/store.db directory
         let mut env = Self {
             config,
-            ontologies: HashMap::new(),
+            ontologies: BTreeMap::new(),
             dependency_graph: DiGraph::new(),
+            failed_imports: BTreeMap::new(),
+            read_failures: vec![],
+            profile: None,
             read_only: false,
             how_created,
             inner_store: None,
+            metrics: None,
+            cancellation: None,
+            retired_graph_ids: HashMap::new(),
+            inspectors: vec![],
+            listeners: vec![],
+            memory_cache: Default::default(),
+            merged_view: false,
+            temporary: false,
+            scratch_dir,
         };
         env.inner_store = Some(env.get_store(env.read_only)?);
         Ok(env)
     }
 
+    /// Creates a scratch environment: a regular environment whose store and manifest live in a
+    /// private per-user cache directory keyed by a hash of the project root (see `scratch`) instead
+    /// of root/.ontoenv, so nothing is written into the project tree, not even the lockfile.
+    /// Constructing the scratch environment of the same project and configuration again on the
+    /// same day reuses it, e.g. across restarts of a notebook session, so only the ontologies
+    /// which changed are read again by `update`. Scratch directories of earlier days are removed
+    pub fn scratch(config: Config, recreate: bool) -> Result<Self> {
+        let dir = scratch::scratch_dir(&config.root)?;
+        if let Err(e) = scratch::remove_stale(&config.root) {
+            warn!("Could not remove stale scratch environments: {}", e);
+        }
+        let manifest = dir.join("ontoenv.json");
+        if !recreate && manifest.exists() {
+            match Self::load(&manifest, false, Some(dir.clone())) {
+                Ok(mut env) if env.config == config => {
+                    info!("Reusing scratch environment in {:?}", dir);
+                    env.how_created = HowCreated::SameConfig;
+                    return Ok(env);
+                }
+                Ok(_) => {
+                    info!("Scratch environment configuration has changed. Recreating environment.");
+                    let mut env = Self::create(config, true, Some(dir))?;
+                    env.how_created = HowCreated::RecreatedDifferentConfig;
+                    return Ok(env);
+                }
+                Err(e) => warn!("Could not load scratch environment, recreating it: {}", e),
+            }
+        }
+        Self::create(config, recreate || manifest.exists(), Some(dir))
+    }
+
+    /// Returns true for environments created by `scratch`
+    pub fn is_scratch(&self) -> bool {
+        self.scratch_dir.is_some()
+    }
+
+    /// Returns the directory holding the store and manifest of the environment: root/.ontoenv,
+    /// or the scratch directory of a scratch environment
+    pub fn ontoenv_dir(&self) -> PathBuf {
+        self.scratch_dir
+            .clone()
+            .unwrap_or_else(|| self.config.root.join(".ontoenv"))
+    }
+
+    /// Sets the sink which receives the environment's metrics: fetches, parses, cache hits and
+    /// the durations of updates and closure computations
+    pub fn set_metrics_sink(&mut self, sink: Arc<dyn MetricsSink>) {
+        self.metrics = Some(sink);
+    }
+
+    /// Sets the token which cancels update, add and closure computations. Cancelled operations
+    /// return a `cancel::Cancelled` error
+    pub fn set_cancellation_token(&mut self, token: Option<CancellationToken>) {
+        self.cancellation = token;
+    }
+
+    /// Adds an inspector which extracts metadata from every ontology parsed from now on. The
+    /// metadata is stored on the Ontology (see `Ontology::metadata`) and saved with the
+    /// environment; ontologies which are already in the environment are not inspected again
+    /// until they are updated
+    pub fn add_inspector(&mut self, inspector: Arc<dyn OntologyInspector>) {
+        self.inspectors.push(inspector);
+    }
+
+    /// Adds a listener which is told about every ontology added, updated or removed by `update`
+    /// and `add` from now on, and about the progress of updates and closures
+    pub fn add_listener(&mut self, listener: Arc<dyn EnvironmentListener>) {
+        self.listeners.push(listener);
+    }
+
+    // tells the listeners how far the running operation has come
+    fn report_progress(&self, phase: Phase, item: &dyn Display, current: usize, total: usize) {
+        if self.listeners.is_empty() {
+            return;
+        }
+        let progress = Progress::new(phase, item.to_string(), current, total);
+        for listener in &self.listeners {
+            listener.on_progress(&progress);
+        }
+    }
+
+    /// Runs the inspectors on a newly parsed ontology. A failing inspector is an error if parse
+    /// errors are strict (see `StrictRule::Parse`) and a warning otherwise
+    fn inspect(&self, ontology: &mut Ontology, graph: &Graph) -> Result<()> {
+        for inspector in &self.inspectors {
+            match inspector.inspect(ontology.name().as_ref(), graph) {
+                Ok(Some(value)) => ontology.with_metadata(inspector.name(), value),
+                Ok(None) => {}
+                Err(e) if self.config.is_strict(StrictRule::Parse) => return Err(e),
+                Err(e) => warn!(
+                    inspector = inspector.name(),
+                    error = %e,
+                    "Failed to inspect ontology"
+                ),
+            }
+        }
+        Ok(())
+    }
+
+    // forgets the closures and unions computed so far; called whenever ontologies are added,
+    // updated or removed
+    fn clear_memory_cache(&self) {
+        if let Ok(mut cache) = self.memory_cache.lock() {
+            cache.clear();
+        }
+    }
+
+    // the key of the graphs in the in-memory cache: their names, locations, graph IDs and
+    // content hashes
+    fn memory_cache_key(&self, graph_ids: &[GraphIdentifier]) -> String {
+        cache::closure_fingerprint(graph_ids.iter().filter_map(|id| self.ontologies.get(id)))
+    }
+
+    fn check_cancelled(&self) -> Result<()> {
+        match &self.cancellation {
+            Some(token) => token.check(),
+            None => Ok(()),
+        }
+    }
+
+    fn count(&self, counter: Counter) {
+        if let Some(metrics) = &self.metrics {
+            metrics.increment(counter, 1);
+        }
+    }
+
+    fn record_duration(&self, timer: Timer, start: Instant) {
+        self.record_elapsed(timer, start.elapsed());
+    }
+
+    fn record_elapsed(&self, timer: Timer, elapsed: Duration) {
+        if let Some(metrics) = &self.metrics {
+            metrics.record_duration(timer, elapsed);
+        }
+    }
+
     /// Returns if the ontoenv instance is configured as read-only
     pub fn is_read_only(&self) -> bool {
         self.read_only
     }
 
+    /// Returns the root directory of the environment, as configured
+    pub fn root(&self) -> &Path {
+        &self.config.root
+    }
+
+    /// Returns true if the file matches the environment's include and exclude patterns, or
+    /// belongs to one of its profiles
+    pub fn is_included(&self, path: &Path) -> bool {
+        self.config.is_indexed(path)
+    }
+
     fn store(&self) -> Store {
         self.inner_store.clone().unwrap()
     }
 
     // TODO: add a read-only version? make this thread-safe?
     fn get_store(&self, read_only: bool) -> Result<Store> {
-        let ontoenv_dir = self.config.root.join(".ontoenv");
+        let ontoenv_dir = self.ontoenv_dir();
         std::fs::create_dir_all(&ontoenv_dir)?;
         if read_only {
             return Store::open_read_only(ontoenv_dir.join("store.db"))
@@ -234,7 +749,7 @@ impl OntoEnv {
                 Err(_) => continue,
             };
             if entry.file_type().is_file() {
-                size += entry.path().metadata().unwrap().len();
+                size += entry.path().metadata()?.len();
             }
         }
         Ok(size)
@@ -261,7 +776,7 @@ impl OntoEnv {
     }
 
     pub fn store_path(&self) -> Result<String> {
-        let ontoenv_dir = self.config.root.join(".ontoenv");
+        let ontoenv_dir = self.ontoenv_dir();
         Ok(ontoenv_dir.join("store.db").to_string_lossy().to_string())
     }
 
@@ -288,18 +803,246 @@ impl OntoEnv {
         name: NamedNodeRef,
         policy: &dyn policy::ResolutionPolicy,
     ) -> Option<Ontology> {
-        let ontologies = self.ontologies.values().collect::<Vec<&Ontology>>();
+        let ontologies = self.visible_ontologies().collect::<Vec<&Ontology>>();
         policy
-            .resolve(name.as_str(), ontologies.as_slice())
+            .resolve(&iri::normalize_iri(name.as_str()), ontologies.as_slice())
             .cloned()
     }
 
-    /// Returns the first ontology with the given name
+    /// Returns the first ontology with the given name. The name is normalized (see
+    /// iri::normalize_iri) before it is looked up. Ontologies outside the active profile are
+    /// not considered
     pub fn get_ontology_by_name(&self, name: NamedNodeRef) -> Option<&Ontology> {
+        let name = iri::normalize_iri(name.as_str());
         // choose the first ontology with the given name
+        self.visible_ontologies()
+            .find(|&ontology| ontology.name().as_str() == name)
+    }
+
+    /// Selects the profile (see `config::Profile`) the environment resolves imports in, or the
+    /// whole environment for None. Only the ontologies of the profile, and remote ontologies,
+    /// are found by name and resolve imports, using the profile's resolution policy; the
+    /// ontologies of all profiles stay in the store. The dependency graph is rebuilt for the
+    /// profile, and the profile is saved with the environment
+    pub fn set_profile(&mut self, profile: Option<&str>) -> Result<()> {
+        if let Some(name) = profile {
+            self.config.profile(name)?;
+        }
+        let previous = std::mem::replace(&mut self.profile, profile.map(String::from));
+        if let Err(e) = self.build_dependency_graph() {
+            self.profile = previous;
+            self.build_dependency_graph()?;
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Returns the active profile, if any
+    pub fn profile(&self) -> Option<&str> {
+        self.profile.as_deref()
+    }
+
+    // true for the ontologies of the active profile and for remote ontologies, which all
+    // profiles share
+    fn is_visible(&self, ontology: &Ontology) -> bool {
+        let Some(profile) = self
+            .profile
+            .as_ref()
+            .and_then(|name| self.config.profiles.get(name))
+        else {
+            return true;
+        };
+        match ontology.location() {
+            Some(OntologyLocation::File(path)) => self.config.in_profile(profile, path),
+            _ => true,
+        }
+    }
+
+    fn visible_ontologies(&self) -> impl Iterator<Item = &Ontology> {
         self.ontologies
             .values()
-            .find(|&ontology| ontology.name() == name)
+            .filter(|ontology| self.is_visible(ontology))
+    }
+
+    // the ontology and, with the merge duplicate strategy, the other visible ontologies with its
+    // name
+    fn merge_parts(&self, id: &GraphIdentifier) -> Vec<GraphIdentifier> {
+        if self.config.duplicate_strategy != DuplicateStrategy::Merge {
+            return vec![id.clone()];
+        }
+        let mut parts: Vec<GraphIdentifier> = self
+            .visible_ontologies()
+            .filter(|ontology| ontology.name() == id.name() && ontology.id() != id)
+            .map(|ontology| ontology.id().clone())
+            .collect();
+        parts.sort();
+        parts.insert(0, id.clone());
+        parts
+    }
+
+    // the resolution policy of the active profile, or of the environment
+    fn resolution_policy_name(&self) -> &str {
+        self.profile
+            .as_ref()
+            .and_then(|name| self.config.profiles.get(name))
+            .and_then(|profile| profile.resolution_policy.as_deref())
+            .unwrap_or(&self.config.resolution_policy)
+    }
+
+    /// Resolves an owl:imports IRI to an ontology in the environment using the configured
+    /// resolution policy. The IRI is either the name of an ontology or the owl:versionIRI of one
+    /// of its versions; either way, the policy picks among all versions of the ontology. With
+    /// `ignore_trailing_separators`, an IRI no ontology matches exactly also matches names and
+    /// version IRIs which differ from it only in a trailing '/' or '#'
+    fn resolve_import(&self, import: NamedNodeRef) -> Option<&Ontology> {
+        let import = iri::normalize_iri(import.as_str());
+        self.resolve_normalized_import(&import, |iri| iri == import)
+            .or_else(|| {
+                if !self.config.ignore_trailing_separators {
+                    return None;
+                }
+                let key = iri::trailing_separator_key(&import);
+                self.resolve_normalized_import(&import, |iri| {
+                    iri::trailing_separator_key(iri) == key
+                })
+            })
+    }
+
+    // resolves an import to the ontology whose name or version IRI matches it according to
+    // `matches`
+    fn resolve_normalized_import(
+        &self,
+        import: &str,
+        matches: impl Fn(&str) -> bool,
+    ) -> Option<&Ontology> {
+        let name = match self.visible_ontologies().find(|ontology| {
+            !matches(ontology.name().as_str())
+                && ontology
+                    .version_iri()
+                    .is_some_and(|version| matches(version.as_str()))
+        }) {
+            Some(version) => version.name().as_str().to_string(),
+            None => match self
+                .visible_ontologies()
+                .find(|ontology| matches(ontology.name().as_str()))
+            {
+                Some(ontology) => ontology.name().as_str().to_string(),
+                None => import.to_string(),
+            },
+        };
+        let candidates: Vec<&Ontology> = self
+            .visible_ontologies()
+            .filter(|ontology| ontology.name().as_str() == name)
+            .collect();
+        if candidates.is_empty() {
+            return None;
+        }
+        let policy = policy::policy_from_name(self.resolution_policy_name())
+            .unwrap_or_else(|| Box::new(policy::DefaultPolicy));
+        let picked = policy.resolve(&name, &candidates)?.id().clone();
+        self.ontologies.get(&picked)
+    }
+
+    /// Like `resolve_import`, but warns if the importer requires a version of the ontology (by
+    /// importing its owl:versionIRI) and the policy picked another version which does not
+    /// declare that it is backward compatible with it
+    fn resolve_import_of(
+        &self,
+        importer: &GraphIdentifier,
+        import: NamedNodeRef,
+    ) -> Option<&Ontology> {
+        let resolved = self.resolve_import(import)?;
+        if resolved.name().as_ref() == import || resolved.is_version(import) {
+            return Some(resolved);
+        }
+        let versions: Vec<&Ontology> = self
+            .ontologies
+            .values()
+            .filter(|ontology| ontology.name() == resolved.name())
+            .collect();
+        if let Some(required) = versions.iter().find(|ontology| ontology.is_version(import)) {
+            if !policy::is_backward_compatible(resolved, required, &versions) {
+                warn!(
+                    importer = %importer.name(),
+                    required = %import,
+                    picked = %resolved.id().location(),
+                    "Import requires a version of {} which the resolution policy did not pick",
+                    resolved.name()
+                );
+            }
+        }
+        Some(resolved)
+    }
+
+    /// Returns the IRIs which refer to the ontology the IRI identifies: its name, and the
+    /// owl:versionIRI, owl:priorVersion and URL of each of its versions. The IRI may itself be
+    /// any of these. An IRI of no ontology in the environment only refers to itself
+    fn ontology_aliases(&self, iri: NamedNodeRef) -> HashMap<String, ImportMatch> {
+        let iri = iri::normalize_iri(iri.as_str());
+        let refers_to = |ontology: &Ontology| {
+            ontology.name().as_str() == iri
+                || ontology
+                    .version_iri()
+                    .is_some_and(|version| version.as_str() == iri)
+                || ontology.prior_versions().iter().any(|v| v.as_str() == iri)
+                || matches!(ontology.location(),
+                    Some(OntologyLocation::Url(url)) if iri::normalize_iri(url) == iri)
+        };
+        // an ontology's own name wins over another's version IRI
+        let name = self
+            .ontologies
+            .values()
+            .find(|ontology| ontology.name().as_str() == iri)
+            .or_else(|| self.ontologies.values().find(|o| refers_to(o)))
+            .map_or(iri.clone(), |ontology| ontology.name().as_str().to_string());
+
+        let versions: Vec<&Ontology> = self
+            .ontologies
+            .values()
+            .filter(|ontology| ontology.name().as_str() == name)
+            .collect();
+        // an IRI which is both the version IRI of one version and a prior version of another
+        // is recorded as the version IRI, so the later inserts win
+        let mut aliases = HashMap::new();
+        for ontology in &versions {
+            if let Some(OntologyLocation::Url(url)) = ontology.location() {
+                aliases.insert(iri::normalize_iri(url), ImportMatch::Location);
+            }
+        }
+        for prior in versions.iter().flat_map(|v| v.prior_versions()) {
+            aliases.insert(prior.as_str().to_string(), ImportMatch::PriorVersion);
+        }
+        for version in versions.iter().filter_map(|v| v.version_iri()) {
+            aliases.insert(version.as_str().to_string(), ImportMatch::VersionIri);
+        }
+        aliases.insert(name, ImportMatch::Name);
+        aliases
+    }
+
+    /// Returns the owl:imports in the environment which refer to the ontology the IRI
+    /// identifies, sorted by importer: imports of its name, and imports of the version IRI, a
+    /// prior version or the URL of one of its versions. The IRI may be the ontology's name or
+    /// any of these, so asking about a versioned IRI finds the importers of every version.
+    /// Versions of the ontology which import each other are not listed
+    pub fn explain_import(&self, iri: NamedNodeRef) -> Vec<ImportReference> {
+        let aliases = self.ontology_aliases(iri);
+        let mut references = vec![];
+        for ontology in self.ontologies.values() {
+            if aliases.get(ontology.name().as_str()) == Some(&ImportMatch::Name) {
+                continue;
+            }
+            for import in &ontology.imports {
+                if let Some(matched) = aliases.get(&iri::normalize_iri(import.as_str())) {
+                    references.push(ImportReference {
+                        importer: ontology.id().clone(),
+                        import: import.as_str().to_string(),
+                        matched: *matched,
+                    });
+                }
+            }
+        }
+        references.sort_by(|a, b| a.importer.cmp(&b.importer).then(a.import.cmp(&b.import)));
+        references
     }
 
     /// Returns the first graph with the given name
@@ -310,6 +1053,74 @@ impl OntoEnv {
         self.get_graph(ontology.id())
     }
 
+    /// Returns the ontology with the given name which `version` selects: its owl:versionIRI, its
+    /// location, its version (see `Ontology::version`) or its content hash. A prefix of the
+    /// content hash selects it too if nothing else matches. Unlike `get_ontology_by_name`, this
+    /// reaches the versions the resolution policy does not pick
+    pub fn get_ontology_version(&self, name: NamedNodeRef, version: &str) -> Result<&Ontology> {
+        let normalized = iri::normalize_iri(name.as_str());
+        let candidates: Vec<&Ontology> = self
+            .visible_ontologies()
+            .filter(|ontology| ontology.name().as_str() == normalized)
+            .collect();
+        if candidates.is_empty() {
+            return Err(anyhow::anyhow!("Ontology {} not found", name));
+        }
+        let exact: Vec<&Ontology> = candidates
+            .iter()
+            .copied()
+            .filter(|ontology| {
+                ontology
+                    .version_iri()
+                    .is_some_and(|iri| iri.as_str() == version)
+                    || ontology.id().location().as_str() == version
+                    || ontology.version().as_deref() == Some(version)
+                    || ontology.content_hash() == Some(version)
+            })
+            .collect();
+        let matches = if exact.is_empty() {
+            candidates
+                .iter()
+                .copied()
+                .filter(|ontology| {
+                    ontology
+                        .content_hash()
+                        .is_some_and(|hash| hash.starts_with(version))
+                })
+                .collect()
+        } else {
+            exact
+        };
+        let locations = |ontologies: &[&Ontology]| {
+            ontologies
+                .iter()
+                .map(|ontology| ontology.id().location().as_str().to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        match matches.as_slice() {
+            [ontology] => Ok(ontology),
+            [] => Err(anyhow::anyhow!(
+                "No version {} of ontology {}; its versions are at {}",
+                version,
+                name,
+                locations(&candidates)
+            )),
+            _ => Err(anyhow::anyhow!(
+                "Version {} of ontology {} is ambiguous: it matches {}",
+                version,
+                name,
+                locations(&matches)
+            )),
+        }
+    }
+
+    /// Returns the graph of the version of an ontology, see `get_ontology_version`
+    pub fn get_graph_version(&self, name: NamedNodeRef, version: &str) -> Result<Graph> {
+        let ontology = self.get_ontology_version(name, version)?;
+        self.get_graph(ontology.id())
+    }
+
     /// Returns the first ontology with the given location
     pub fn get_ontology_by_location(&self, location: &OntologyLocation) -> Option<&Ontology> {
         // choose the first ontology with the given location
@@ -320,6 +1131,10 @@ impl OntoEnv {
 
     /// Load an OntoEnv from the given path
     pub fn from_file(path: &Path, read_only: bool) -> Result<Self> {
+        Self::load(path, read_only, None)
+    }
+
+    fn load(path: &Path, read_only: bool, scratch_dir: Option<PathBuf>) -> Result<Self> {
         // if path does not exist, return an error
         if !path.exists() {
             return Err(anyhow::anyhow!(
@@ -331,8 +1146,156 @@ impl OntoEnv {
         let file = std::fs::File::open(path)?;
         let reader = BufReader::new(file);
         let mut env: OntoEnv = serde_json::from_reader(reader)?;
+        env.scratch_dir = scratch_dir;
         env.inner_store = Some(env.get_store(read_only)?);
-        Ok(Self { read_only, ..env })
+        env.read_only = read_only;
+        if !read_only {
+            env.migrate_graph_ids()?;
+            env.recover_from_journal()?;
+        } else if env.ontoenv_dir().join("journal").exists() {
+            warn!("The last update was interrupted; open the environment for writing to recover");
+        }
+        Ok(env)
+    }
+
+    // the journal of the store changes made since the environment was last saved (see
+    // `journal`), if the environment keeps one: read-only environments and the in-memory ones
+    // of merge_view, load_snapshot and from_locations do not change a store on disk
+    fn journal_path(&self) -> Option<PathBuf> {
+        if self.read_only || self.merged_view || self.temporary {
+            return None;
+        }
+        Some(self.ontoenv_dir().join("journal"))
+    }
+
+    fn journal(&self, entry: JournalEntry) -> Result<()> {
+        match self.journal_path() {
+            Some(path) => journal::record(&path, &entry),
+            None => Ok(()),
+        }
+    }
+
+    // records in the journal that a change to the store starts; every method which adds graphs
+    // to the store or drops graphs from it calls this first
+    fn begin_journal(&self) -> Result<()> {
+        self.journal(JournalEntry::Begin {
+            timestamp: Utc::now(),
+        })
+    }
+
+    // drops the graph from the store, recording it in the journal first so an interrupted change
+    // does not leave a saved environment listing a graph which is gone
+    fn remove_store_graph(&self, store: &Store, graph: NamedNodeRef) -> Result<()> {
+        self.journal(JournalEntry::Remove {
+            graph: graph.as_str().to_string(),
+        })?;
+        store.remove_named_graph(graph)?;
+        Ok(())
+    }
+
+    /// Finishes an update which was interrupted before the environment was saved, from the
+    /// journal it left: the ontologies whose graphs were loaded completely are added, the graphs
+    /// which were being loaded are dropped along with their ontologies (so the next update reads
+    /// them again) and the graphs which were being dropped are dropped. The environment is then
+    /// saved. Returns false if there was no interrupted update
+    pub fn recover_from_journal(&mut self) -> Result<bool> {
+        let Some(path) = self.journal_path() else {
+            return Ok(false);
+        };
+        let entries = journal::load(&path)?;
+        if entries.is_empty() {
+            journal::clear(&path)?;
+            return Ok(false);
+        }
+        warn!(
+            entries = entries.len(),
+            "Recovering from an interrupted update"
+        );
+        let store = self.store();
+        let mut partial: BTreeSet<String> = BTreeSet::new();
+        let mut dropped: BTreeSet<String> = BTreeSet::new();
+        for entry in entries {
+            match entry {
+                JournalEntry::Begin { .. } => {}
+                JournalEntry::Add { graph } => {
+                    partial.insert(graph);
+                }
+                JournalEntry::Added { ontology } => {
+                    let graph = graph_iri(ontology.id())?;
+                    partial.remove(&graph);
+                    dropped.remove(&graph);
+                    self.forget_graph(&graph);
+                    self.ontologies.insert(ontology.id().clone(), *ontology);
+                }
+                JournalEntry::Remove { graph } => {
+                    partial.remove(&graph);
+                    dropped.insert(graph);
+                }
+            }
+        }
+        for graph in partial.iter().chain(dropped.iter()) {
+            self.forget_graph(graph);
+            store.remove_named_graph(NamedNode::new(graph.as_str())?.as_ref())?;
+        }
+        drop(store);
+        info!(
+            reloaded = partial.len(),
+            dropped = dropped.len(),
+            "Recovered from an interrupted update"
+        );
+        self.build_dependency_graph()?;
+        self.save_to_directory()?;
+        Ok(true)
+    }
+
+    // removes the ontologies stored in the graph from the environment, leaving the store as it is
+    fn forget_graph(&mut self, graph: &str) {
+        self.ontologies
+            .retain(|id, _| graph_iri(id).ok().as_deref() != Some(graph));
+    }
+
+    /// Gives the ontologies of environments created before graph IDs existed a graph ID and
+    /// moves their graphs to the graph names derived from it. The environment is saved right
+    /// away, so the graphs are never moved twice. Returns the number of migrated ontologies
+    fn migrate_graph_ids(&mut self) -> Result<usize> {
+        let legacy: Vec<GraphIdentifier> = self
+            .ontologies
+            .keys()
+            .filter(|id| id.graph_id().is_none())
+            .cloned()
+            .collect();
+        if legacy.is_empty() {
+            return Ok(0);
+        }
+        info!("Migrating {} ontologies to graph IDs", legacy.len());
+        let store = self.store();
+        let mut old_names: BTreeSet<NamedNode> = BTreeSet::new();
+        for id in legacy.iter() {
+            let mut ontology = self
+                .ontologies
+                .remove(id)
+                .ok_or(anyhow::anyhow!("Ontology {} not found", id))?;
+            ontology.with_graph_id(GraphIdentifier::new_graph_id());
+            let new_name = ontology.id().graphname()?;
+            if let GraphName::NamedNode(old_name) = id.legacy_graphname()? {
+                // copy rather than move: ontologies with the same name at different locations
+                // shared one legacy graph
+                let quads = store
+                    .quads_for_pattern(None, None, None, Some(old_name.as_ref().into()))
+                    .map(|quad| {
+                        quad.map(|q| Quad::new(q.subject, q.predicate, q.object, new_name.clone()))
+                    })
+                    .collect::<Result<Vec<Quad>, _>>()?;
+                store.bulk_loader().load_quads(quads)?;
+                old_names.insert(old_name);
+            }
+            self.ontologies.insert(ontology.id().clone(), ontology);
+        }
+        for old_name in old_names {
+            store.remove_named_graph(old_name.as_ref())?;
+        }
+        self.save_to_directory()?;
+        Ok(legacy.len())
     }
 
     /// creates a new directory called .ontoenv in self.root and saves:
@@ -340,7 +1303,15 @@ impl OntoEnv {
     /// - all graphs in the environment as .ttl files
     /// - the dependency graph as a json file
     pub fn save_to_directory(&self) -> Result<()> {
-        let ontoenv_dir = self.config.root.join(".ontoenv");
+        if self.merged_view {
+            return Err(anyhow::anyhow!(
+                "Cannot save a merged view of several environments"
+            ));
+        }
+        if self.temporary {
+            return Err(anyhow::anyhow!("Cannot save a temporary environment"));
+        }
+        let ontoenv_dir = self.ontoenv_dir();
         info!("Saving ontology environment to: {:?}", ontoenv_dir);
         std::fs::create_dir_all(&ontoenv_dir)?;
         // save the configuration
@@ -348,31 +1319,304 @@ impl OntoEnv {
         let config_str = serde_json::to_string_pretty(&self)?;
         let mut file = std::fs::File::create(config_path)?;
         file.write_all(config_str.as_bytes())?;
+        // the changes made to the store since the last save are now part of the saved environment
+        journal::clear(&ontoenv_dir.join("journal"))?;
         Ok(())
     }
 
-    fn update_dependency_graph(&mut self, updated_ids: Option<Vec<GraphIdentifier>>) -> Result<()> {
-        // traverse the owl:imports closure and build the dependency graph
-        let mut stack: VecDeque<GraphIdentifier> = match updated_ids {
-            Some(ids) => ids.into(),
-            None => self.ontologies.keys().cloned().collect(),
+    /// Packages the environment as a bundle which can be published to an OCI registry:
+    /// the manifest (ontoenv.json) and every graph in the store as TriG
+    pub fn to_bundle(&self) -> Result<Vec<ArtifactFile>> {
+        let manifest = serde_json::to_vec_pretty(&self)?;
+        let mut graphs = Vec::new();
+        let mut serializer = RdfSerializer::from_format(RdfFormat::TriG).for_writer(&mut graphs);
+        for quad in self.store().iter() {
+            serializer.serialize_quad(&quad?)?;
+        }
+        serializer.finish()?;
+        Ok(vec![
+            ArtifactFile {
+                name: BUNDLE_MANIFEST.to_string(),
+                media_type: "application/json".to_string(),
+                content: manifest,
+            },
+            ArtifactFile {
+                name: BUNDLE_GRAPHS.to_string(),
+                media_type: "application/trig".to_string(),
+                content: graphs,
+            },
+        ])
+    }
+
+    /// Creates a new environment in root/.ontoenv from a bundle created by `to_bundle`.
+    /// The ontologies keep the locations they had in the published environment, so a
+    /// pulled environment should be queried rather than refreshed.
+    pub fn from_bundle(root: &Path, files: &[ArtifactFile], recreate: bool) -> Result<Self> {
+        let find = |name: &str| {
+            files
+                .iter()
+                .find(|f| f.name == name)
+                .ok_or(anyhow::anyhow!("Bundle does not contain {}", name))
         };
-        let mut seen: HashSet<GraphIdentifier> = HashSet::new();
-        let store = self.store();
+        let manifest = find(BUNDLE_MANIFEST)?;
+        let graphs = find(BUNDLE_GRAPHS)?;
 
-        info!("Using # updated ids: {:?}", stack.len());
+        let ontoenv_dir = root.join(".ontoenv");
+        if ontoenv_dir.exists() {
+            if !recreate {
+                return Err(anyhow::anyhow!(
+                    "OntoEnv directory already exists: {:?}. Use --recreate to replace it.",
+                    ontoenv_dir
+                ));
+            }
+            fs::remove_dir_all(&ontoenv_dir)?;
+        }
 
-        while let Some(ontology) = stack.pop_front() {
-            info!("Building dependency graph for: {:?}", ontology);
+        let mut env: OntoEnv = serde_json::from_slice(&manifest.content)?;
+        env.config.root = root.to_path_buf();
+        env.read_only = false;
+        env.how_created = HowCreated::New;
+        env.inner_store = Some(env.get_store(false)?);
+
+        let quads = RdfParser::from_format(RdfFormat::TriG)
+            .for_reader(graphs.content.as_slice())
+            .collect::<Result<Vec<_>, _>>()?;
+        info!("Loading {} quads from bundle", quads.len());
+        env.store().bulk_loader().load_quads(quads)?;
+        env.save_to_directory()?;
+        Ok(env)
+    }
+
+    /// Creates a temporary environment containing exactly the ontologies at the given locations
+    /// and their imports closures, without scanning any directories, e.g. for one-shot use on a
+    /// few files. Imports are fetched as usual unless the configuration is offline; the search
+    /// directories and include patterns of the configuration are not used. The environment
+    /// lives in memory and cannot be saved, and `update` only reads its files again if they
+    /// changed
+    pub fn from_locations(locations: Vec<OntologyLocation>, config: Config) -> Result<Self> {
+        let mut env = OntoEnv {
+            config,
+            ontologies: BTreeMap::new(),
+            dependency_graph: DiGraph::new(),
+            failed_imports: BTreeMap::new(),
+            read_failures: vec![],
+            profile: None,
+            read_only: false,
+            how_created: HowCreated::New,
+            inner_store: Some(Store::new()?),
+            metrics: None,
+            cancellation: None,
+            retired_graph_ids: HashMap::new(),
+            inspectors: vec![],
+            listeners: vec![],
+            memory_cache: Default::default(),
+            merged_view: false,
+            temporary: true,
+            scratch_dir: None,
+        };
+        let (urls, files): (BTreeSet<OntologyLocation>, BTreeSet<OntologyLocation>) = locations
+            .into_iter()
+            .partition(|location| location.is_url());
+        let plan = UpdatePlan {
+            to_parse: files.into_iter().collect(),
+            to_remove: vec![],
+            to_fetch: urls.into_iter().collect(),
+        };
+        let report = env.apply_update_plan(plan)?;
+        info!(
+            ontologies = env.ontologies.len(),
+            failed = report.failed.len(),
+            "Created temporary environment"
+        );
+        Ok(env)
+    }
+
+    /// Returns true for environments created by `from_locations`
+    pub fn is_temporary(&self) -> bool {
+        self.temporary
+    }
+
+    /// Returns a read-only view which resolves against all of the given environments, e.g. a
+    /// shared base environment and a project environment. An ontology provided by several of
+    /// them is taken from the first environment which provides it; see `merge_view_with` for
+    /// other precedences. The view has the configuration of the first environment and copies
+    /// the graphs into an in-memory store, so it cannot be saved or updated
+    pub fn merge_view(envs: &[&OntoEnv]) -> Result<OntoEnv> {
+        Self::merge_view_with(envs, MergePrecedence::First)
+    }
+
+    /// Like `merge_view`, but with the given precedence between the environments
+    pub fn merge_view_with(envs: &[&OntoEnv], precedence: MergePrecedence) -> Result<OntoEnv> {
+        let first = envs
+            .first()
+            .ok_or(anyhow::anyhow!("No environments to merge"))?;
+        let store = Store::new()?;
+        let mut ontologies: BTreeMap<GraphIdentifier, Ontology> = BTreeMap::new();
+        let mut provided: HashSet<NamedNode> = HashSet::new();
+        for env in envs {
+            let mut names = HashSet::new();
+            for (id, ontology) in &env.ontologies {
+                let shadowed =
+                    precedence == MergePrecedence::First && provided.contains(&ontology.name());
+                // the same ontology (name and location) is shared by several environments
+                if shadowed || ontologies.contains_key(id) {
+                    debug!(ontology = %id, "Ontology is provided by an earlier environment");
+                    continue;
+                }
+                names.insert(ontology.name());
+                // the graphs get new IDs, so graphs of different environments cannot collide
+                let mut ontology = ontology.clone();
+                ontology.with_graph_id(GraphIdentifier::new_graph_id());
+                let graphname = ontology.id().graphname()?;
+                let quads = env
+                    .store()
+                    .quads_for_pattern(None, None, None, Some(env.store_graphname(id)?.as_ref()))
+                    .map(|quad| {
+                        quad.map(|q| Quad::new(q.subject, q.predicate, q.object, graphname.clone()))
+                    })
+                    .collect::<Result<Vec<Quad>, _>>()?;
+                store.bulk_loader().load_quads(quads)?;
+                ontologies.insert(id.clone(), ontology);
+            }
+            provided.extend(names);
+        }
+        info!(
+            environments = envs.len(),
+            ontologies = ontologies.len(),
+            "Created merged view"
+        );
+
+        let mut view = OntoEnv {
+            config: first.config.clone(),
+            ontologies,
+            dependency_graph: DiGraph::new(),
+            failed_imports: BTreeMap::new(),
+            read_failures: vec![],
+            profile: None,
+            read_only: true,
+            how_created: HowCreated::New,
+            inner_store: Some(store),
+            metrics: None,
+            cancellation: None,
+            retired_graph_ids: HashMap::new(),
+            inspectors: vec![],
+            listeners: vec![],
+            memory_cache: Default::default(),
+            merged_view: true,
+            temporary: false,
+            scratch_dir: None,
+        };
+        view.build_dependency_graph()?;
+        Ok(view)
+    }
+
+    /// Writes an OASIS XML catalog (catalog-v001.xml) mapping the name of every ontology in the
+    /// environment to its location, so that Protégé and ROBOT resolve imports to the same files
+    pub fn export_catalog_xml(&self, path: &Path) -> Result<()> {
+        let mut entries: Vec<CatalogEntry> = self
+            .ontologies
+            .values()
+            .map(|ontology| CatalogEntry {
+                name: ontology.name().as_str().to_string(),
+                location: ontology.id().location().clone(),
+            })
+            .collect();
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        catalog::write_catalog(path, &entries)
+    }
+
+    /// Returns the ontologies in the flat listing of the original ontoenv (see `legacy`), sorted
+    /// by name and location
+    pub fn export_legacy_manifest(&self) -> Vec<LegacyOntology> {
+        let mut ontologies: Vec<LegacyOntology> = self
+            .ontologies
+            .values()
+            .map(|ontology| LegacyOntology {
+                name: ontology.name().as_str().to_string(),
+                location: ontology.id().location().as_str().to_string(),
+                imports: ontology
+                    .imports
+                    .iter()
+                    .map(|import| import.as_str().to_string())
+                    .collect(),
+                last_updated: ontology.last_updated,
+            })
+            .collect();
+        ontologies.sort_by(|a, b| (&a.name, &a.location).cmp(&(&b.name, &b.location)));
+        ontologies
+    }
+
+    /// Adds every ontology listed in an existing XML catalog to the environment and returns
+    /// their identifiers
+    pub fn import_catalog_xml(&mut self, path: &Path) -> Result<Vec<GraphIdentifier>> {
+        self.add_catalog_entries(catalog::read_catalog(path)?)
+    }
+
+    /// Adds every ontology listed in an inventory of ontologies, e.g. a DCAT catalog published
+    /// by an organization, to the environment and returns their identifiers. See
+    /// `catalog::read_inventory` for the kinds of inventories
+    pub fn add_catalog(&mut self, location: OntologyLocation) -> Result<Vec<GraphIdentifier>> {
+        if location.is_url() && self.config.offline {
+            return Err(anyhow::anyhow!(
+                "Offline mode is enabled. Cannot fetch {}",
+                location.as_str()
+            ));
+        }
+        let entries = catalog::read_inventory(&location)?;
+        info!(catalog = %location, ontologies = entries.len(), "Read catalog");
+        self.add_catalog_entries(entries)
+    }
+
+    fn add_catalog_entries(&mut self, entries: Vec<CatalogEntry>) -> Result<Vec<GraphIdentifier>> {
+        self.begin_journal()?;
+        let store = self.store();
+        let mut ids = vec![];
+        for entry in entries {
+            info!("Adding {} from catalog: {}", entry.name, entry.location);
+            let id = self.add_or_update_ontology_from_location(entry.location, &store, false)?;
+            if id.name().as_str() != entry.name {
+                warn!(
+                    "Catalog maps {} to a file declaring ontology {}",
+                    entry.name,
+                    id.name()
+                );
+            }
+            ids.push(id);
+        }
+        self.update_dependency_graph(Some(ids.clone()))?;
+        Ok(ids)
+    }
+
+    fn update_dependency_graph(&mut self, updated_ids: Option<Vec<GraphIdentifier>>) -> Result<()> {
+        self.clear_memory_cache();
+        // traverse the owl:imports closure and build the dependency graph
+        let mut stack: VecDeque<GraphIdentifier> = match updated_ids {
+            Some(ids) => ids.into(),
+            None => self.ontologies.keys().cloned().collect(),
+        };
+        let mut seen: HashSet<GraphIdentifier> = HashSet::new();
+        let store = self.store();
+
+        info!("Using # updated ids: {:?}", stack.len());
+
+        while let Some(ontology) = stack.pop_front() {
+            self.check_cancelled()?;
+            info!("Building dependency graph for: {:?}", ontology);
             if seen.contains(&ontology) {
                 continue;
             }
             seen.insert(ontology.clone());
+            self.report_progress(
+                Phase::Resolve,
+                &ontology,
+                seen.len(),
+                seen.len() + stack.len(),
+            );
             let ont = match self.ontologies.get(&ontology) {
                 Some(ont) => ont,
                 None => {
                     let msg = format!("Update graph: Ontology {} not found", ontology);
-                    if self.config.strict {
+                    if self.config.is_strict(StrictRule::MissingImports) {
                         error!("{}", msg);
                         return Err(anyhow::anyhow!(msg));
                     } else {
@@ -381,28 +1625,58 @@ impl OntoEnv {
                     }
                 }
             };
-            let imports = &ont.imports.clone();
-            for import in imports {
-                // check to see if we have a file defining this ontology first
-                let location = if let Some(imp) = self.get_ontology_by_name(import.into()) {
+            // optional imports are read too, so closures can include them on request
+            let imports = self.imports_of(ont, None);
+            for import in &imports {
+                // imports redirected by the import map are read from the mapped location;
+                // otherwise check to see if we have a file defining this ontology first
+                let mapped = self.config.mapped_location(import.as_str())?;
+                let (location, fetched) = if let Some(location) = mapped {
+                    (location, false)
+                } else if let Some(imp) = self.resolve_import(import.into()) {
                     // if we have already re-visited it, skip
                     if seen.contains(imp.id()) || stack.contains(imp.id()) {
                         continue;
                     }
-                    imp.location()
+                    let location = imp
+                        .location()
                         .ok_or(anyhow::anyhow!(format!(
                             "Parsing imports: Ontology {} location not found",
                             imp
                         )))?
-                        .clone()
+                        .clone();
+                    (location, false)
                 } else {
+                    // imports which failed before are retried with exponential backoff
+                    if let Some(failure) = self.failed_imports.get(import.as_str()) {
+                        if !failure.should_retry(Utc::now()) {
+                            debug!(
+                                import = %import,
+                                next_attempt = %failure.next_attempt(),
+                                "Skipping failed import until its next retry"
+                            );
+                            continue;
+                        }
+                    }
                     // otherwise, try to find the ontology by location
-                    OntologyLocation::from_str(import.as_str())?
+                    (OntologyLocation::from_str(import.as_str())?, true)
                 };
-                let imp = match self.add_or_update_ontology_from_location(location, &store) {
-                    Ok(imp) => imp,
+                let rule = read_rule(&location);
+                let imp = match self.add_or_update_ontology_from_location(location, &store, false) {
+                    Ok(imp) => {
+                        if fetched {
+                            self.failed_imports.remove(import.as_str());
+                        }
+                        imp
+                    }
                     Err(e) => {
-                        if self.config.strict {
+                        if fetched && !e.is::<Cancelled>() {
+                            self.failed_imports
+                                .entry(import.as_str().to_string())
+                                .and_modify(|failure| failure.record_attempt(&e))
+                                .or_insert_with(|| ImportFailure::new(&e));
+                        }
+                        if self.config.is_strict(rule) || e.is::<Cancelled>() {
                             return Err(e);
                         } else {
                             warn!("Failed to read ontology file {}: {}", import.as_str(), e);
@@ -414,8 +1688,12 @@ impl OntoEnv {
             }
         }
         drop(store); // drop the store so we can optimize it later
+        self.build_dependency_graph()
+    }
 
-        // put the dependency graph into self.dependency_graph
+    /// Rebuilds self.dependency_graph from the owl:imports of the ontologies in the environment
+    fn build_dependency_graph(&mut self) -> Result<()> {
+        self.clear_memory_cache();
         let mut indexes: HashMap<GraphIdentifier, NodeIndex> = HashMap::new();
         let mut graph: DiGraph<GraphIdentifier, (), petgraph::Directed> = DiGraph::new();
         // add all ontologies in self.ontologies to the graph
@@ -434,10 +1712,10 @@ impl OntoEnv {
                 }
             };
             for import in &ont.imports {
-                let graph_id = match self.get_ontology_by_name(import.into()) {
+                let graph_id = match self.resolve_import_of(ontology, import.into()) {
                     Some(imp) => imp.id(),
                     None => {
-                        if self.config.strict {
+                        if self.config.is_strict(StrictRule::MissingImports) {
                             return Err(anyhow::anyhow!("Import not found: {}", import));
                         }
                         warn!("Import not found: {}", import);
@@ -453,9 +1731,8 @@ impl OntoEnv {
         Ok(())
     }
 
-    /// Remove all ontologies that are no longer in the search directories
-    /// and return a list of the removed ontologies
-    fn remove_old_ontologies(&mut self) -> Result<Vec<GraphIdentifier>> {
+    /// Returns the ontologies whose files are no longer in the search directories, sorted
+    fn find_removed_ontologies(&self) -> Result<Vec<GraphIdentifier>> {
         // check for any ontologies that are no longer in the search directories
         let mut to_remove: Vec<GraphIdentifier> = vec![];
         for ontology in self.ontologies.keys() {
@@ -470,17 +1747,17 @@ impl OntoEnv {
             if let Some(location) = location {
                 // if location is a file and the file does not exist or it is no longer in the set
                 // of included paths, remove the ontology
+                // temporary environments and environments restricted to a root ontology do not
+                // use the include patterns
                 if let OntologyLocation::File(path) = location {
-                    if !path.exists() || !self.config.is_included(path) {
+                    let searched = !self.temporary && self.config.root_ontology.is_none();
+                    if !path.exists() || (searched && !self.config.is_indexed(path)) {
                         to_remove.push(ontology.clone());
                     }
                 }
             }
         }
-        for ontology in to_remove.iter() {
-            debug!("Removing ontology: {:?}", ontology);
-            self.ontologies.remove(ontology);
-        }
+        to_remove.sort();
         Ok(to_remove)
     }
 
@@ -499,8 +1776,16 @@ impl OntoEnv {
                         "Ontology: {:?}, last updated: {:?}; current: {:?}",
                         id, ontology.last_updated, last_updated
                     );
-                    if last_updated >= ontology.last_updated.unwrap() {
+                    // ontologies without a recorded update time are always refreshed
+                    let touched = match ontology.last_updated {
+                        Some(recorded) => last_updated >= recorded,
+                        None => true,
+                    };
+                    // e.g. a fresh checkout sets new modification times on unchanged files
+                    if touched && self.source_changed(ontology, &path)? {
                         updates.push(id.clone());
+                    } else if touched {
+                        debug!(ontology = %id, "File was modified but its content is unchanged");
                     }
                 }
             }
@@ -509,8 +1794,17 @@ impl OntoEnv {
         Ok(updates)
     }
 
-    /// Returns a list of all files in the environment which have been updated (added or changed)
-    /// Does not return files that have been removed
+    // true if the content of the ontology's file differs from when it was read, or if that is
+    // not known
+    fn source_changed(&self, ontology: &Ontology, path: &Path) -> Result<bool> {
+        match ontology.source_hash() {
+            Some(recorded) => Ok(lockfile::source_hash(path)? != recorded),
+            None => Ok(true),
+        }
+    }
+
+    /// Returns a list of all files in the environment which have been updated (added or changed),
+    /// sorted by location. Does not return files that have been removed
     pub fn get_updated_files(&self) -> Result<Vec<OntologyLocation>> {
         // make a cache of all files in the ontologies property
         let mut existing_files: HashSet<OntologyLocation> = HashSet::new();
@@ -530,14 +1824,14 @@ impl OntoEnv {
         if !updated_ids.is_empty() {
             info!("Updating ontologies: {:?}", updated_ids);
         }
-        let mut updated_files: HashSet<OntologyLocation> = updated_ids
+        let mut updated_files: BTreeSet<OntologyLocation> = updated_ids
             .iter()
             .filter_map(|id| {
                 self.ontologies
                     .get(id)
                     .and_then(|ont| ont.location().cloned())
             })
-            .collect::<HashSet<OntologyLocation>>();
+            .collect::<BTreeSet<OntologyLocation>>();
 
         // compute the union of new_files and updated_files
         updated_files.extend(new_files);
@@ -562,178 +1856,1593 @@ impl OntoEnv {
     ///
     /// Then, it reads all the new and updated files and adds them to the environment.
     ///
-    /// Finally, it updates the dependency graph for all the updated ontologies.
-    pub fn update(&mut self) -> Result<()> {
-        // Step one: remove all ontologies that are no longer in the search directories
-        self.remove_old_ontologies()?;
+    /// Then, it updates the dependency graph for all the updated ontologies.
+    ///
+    /// Finally, it checks the configured import constraints. Violations are an error in strict
+    /// mode and a warning otherwise.
+    ///
+    /// Returns a report of the ontologies which were added, updated and removed.
+    ///
+    /// If the cancellation token is cancelled, the update stops before reading the next
+    /// ontology and returns a `cancel::Cancelled` error. Graphs already written to the store
+    /// are complete, and an environment which is not saved afterwards is refreshed from the
+    /// last saved state by the next update.
+    pub fn update(&mut self) -> Result<UpdateReport> {
+        self.update_all(false)
+    }
+
+    /// Like `update`, but with `all` every file is read again and every remote ontology is
+    /// fetched again (unless in offline mode), whether or not it changed
+    pub fn update_all(&mut self, all: bool) -> Result<UpdateReport> {
+        let plan = self.plan_update(all)?;
+        self.apply_update_plan(plan)
+    }
+
+    /// Watches the search directories (see `watch::WatchOptions`) and updates the environment
+    /// whenever included files are added, changed or removed, so the graphs and the dependency
+    /// graph follow the files being edited. `on_update` is called with the environment and the
+    /// result of every update which changed something; failed updates (e.g. a file which does
+    /// not parse in strict mode) are passed to it as well and watching goes on.
+    ///
+    /// Watching stops with the error returned by `on_update`, or with Ok once the cancellation
+    /// token is cancelled
+    pub fn watch(
+        &mut self,
+        options: &WatchOptions,
+        mut on_update: impl FnMut(&mut OntoEnv, Result<UpdateReport>) -> Result<()>,
+    ) -> Result<()> {
+        if self.merged_view || self.temporary {
+            return Err(anyhow::anyhow!(
+                "Only environments with search directories can be watched"
+            ));
+        }
+        let cancelled = |e: &anyhow::Error| e.is::<Cancelled>();
+        let mut last = match self.find_files() {
+            Ok(files) => watch::snapshot(&files),
+            Err(e) if cancelled(&e) => return Ok(()),
+            Err(e) => return Err(e),
+        };
+        info!(files = last.len(), "Watching search directories");
+        let mut changed = false;
+        loop {
+            std::thread::sleep(options.interval);
+            let current = match self.check_cancelled().and_then(|_| self.find_files()) {
+                Ok(files) => watch::snapshot(&files),
+                Err(e) if cancelled(&e) => return Ok(()),
+                Err(e) => return Err(e),
+            };
+            // update once the files stopped changing for an interval
+            if current != last {
+                last = current;
+                changed = true;
+                continue;
+            }
+            if !changed {
+                continue;
+            }
+            changed = false;
+            let result = self.update();
+            match &result {
+                Err(e) if cancelled(e) => return Ok(()),
+                Ok(report) if report.is_empty() => continue,
+                _ => {}
+            }
+            on_update(self, result)?;
+        }
+    }
+
+    /// Like `update`, but the remote ontologies which were fetched `max_age` or more seconds ago
+    /// are fetched again, whatever the configured `cache_ttl`. They are requested
+    /// conditionally, so only those which changed are downloaded and read again
+    pub fn update_with_max_age(&mut self, max_age: u64) -> Result<UpdateReport> {
+        let plan = self.plan_update_with_max_age(false, Some(max_age))?;
+        self.apply_update_plan(plan)
+    }
+
+    /// Returns what `update_all` would do, without changing the environment: the files it
+    /// would read, the ontologies it would remove and the remote ontologies it would fetch.
+    /// With a configured `cache_ttl`, remote ontologies fetched longer ago than it are fetched
+    /// again
+    pub fn plan_update(&self, all: bool) -> Result<UpdatePlan> {
+        self.plan_update_with_max_age(all, self.config.cache_ttl)
+    }
 
+    /// Like `plan_update`, with the remote ontologies fetched `max_age` or more seconds ago (or
+    /// never) planned to be fetched again
+    pub fn plan_update_with_max_age(&self, all: bool, max_age: Option<u64>) -> Result<UpdatePlan> {
         info!("Checking for updates");
-        // Step two: find all new and updated files
-        let updated_files = self.get_updated_files()?;
+        let to_remove = self.find_removed_ontologies()?;
+        let mut to_parse: BTreeSet<OntologyLocation> =
+            self.get_updated_files()?.into_iter().collect();
+        let mut to_fetch: BTreeSet<OntologyLocation> = BTreeSet::new();
+        if all {
+            for (id, ontology) in self.ontologies.iter() {
+                match ontology.location() {
+                    Some(location @ OntologyLocation::File(_)) if !to_remove.contains(id) => {
+                        to_parse.insert(location.clone());
+                    }
+                    Some(location @ OntologyLocation::Url(_)) if !self.config.offline => {
+                        to_fetch.insert(location.clone());
+                    }
+                    _ => {}
+                }
+            }
+        } else if let (Some(max_age), false) = (max_age, self.config.offline) {
+            to_fetch.extend(self.stale_remote_locations(max_age));
+        }
+        Ok(UpdatePlan {
+            to_parse: to_parse.into_iter().collect(),
+            to_remove,
+            to_fetch: to_fetch.into_iter().collect(),
+        })
+    }
+
+    // the locations of remote ontologies which were fetched max_age or more seconds ago
+    fn stale_remote_locations(&self, max_age: u64) -> Vec<OntologyLocation> {
+        let now = Utc::now();
+        self.ontologies
+            .values()
+            .filter(|ontology| match ontology.last_updated {
+                Some(last_updated) => (now - last_updated).num_seconds() >= max_age as i64,
+                None => true,
+            })
+            .filter_map(|ontology| ontology.location())
+            .filter(|location| location.is_url())
+            .cloned()
+            .collect()
+    }
+
+    /// Carries out an update plan (see `plan_update`) and returns a report of what changed.
+    /// Actions may be removed from the plan before it is applied, e.g. to skip fetching some
+    /// remote ontologies
+    #[instrument(name = "update", skip_all, fields(root = %self.config.root.display()))]
+    pub fn apply_update_plan(&mut self, plan: UpdatePlan) -> Result<UpdateReport> {
+        if self.merged_view {
+            return Err(anyhow::anyhow!(
+                "Cannot update a merged view of several environments"
+            ));
+        }
+        let start = Instant::now();
+        let mut report = UpdateReport::default();
+        self.begin_journal()?;
+        // Step one: remove all ontologies that are no longer in the search directories
+        for id in plan.to_remove {
+            let Some(ontology) = self.ontologies.remove(&id) else {
+                continue;
+            };
+            // the stored identifier carries the ontology's graph ID
+            let id = ontology.id().clone();
+            debug!("Removing ontology: {:?}", id);
+            for listener in &self.listeners {
+                listener.on_remove(&id);
+            }
+            report.removed.push(id);
+        }
+        // files which failed to read and were deleted since are not read again
+        self.read_failures
+            .retain(|failure| match &failure.location {
+                OntologyLocation::File(path) => path.exists(),
+                OntologyLocation::Url(_) => true,
+            });
+        // remember their graph IDs: an ontology whose file moved is found again in step three
+        // and keeps its graph
+        self.retired_graph_ids = report
+            .removed
+            .iter()
+            .filter_map(|id| Some((id.name().into_owned(), id.graph_id()?.to_string())))
+            .collect();
+
+        // Step two: the new and updated files (found by plan_update), and the remote ontologies
+        // to fetch again
+        let updated_files = plan.to_parse.into_iter().chain(plan.to_fetch);
+        let known_locations: HashSet<OntologyLocation> = self
+            .ontologies
+            .values()
+            .filter_map(|o| o.location().cloned())
+            .collect();
 
         let store = self.store();
 
-        // Step three: add or update the ontologies from the new and updated files
-        let updated_ids: Vec<GraphIdentifier> = if self.config.strict {
-            let updated_ids: Result<Vec<GraphIdentifier>> = updated_files
-                .into_iter()
-                .map(|file| self.add_or_update_ontology_from_location(file.clone(), &store))
-                .collect();
-            // handle error reporting
-            updated_ids.map_err(|e| {
-                error!("Failed to read ontology file: {}", e);
-                e
-            })?
-        } else {
-            updated_files
-                .into_iter()
-                .map(|file| self.add_or_update_ontology_from_location(file.clone(), &store))
-                .filter_map(|r| r.ok())
-                .collect()
-        };
+        // Step three: add or update the ontologies from the new and updated files. Files at
+        // known locations have changed, so they are read again
+        let to_read = updated_files
+            .map(|file| {
+                let known = known_locations.contains(&file);
+                (file, known)
+            })
+            .collect();
+        let updated_ids = self.read_locations(to_read, &store, &mut report)?;
+
+        // drop the graphs of removed ontologies which did not reappear at another location
+        for (_, graph_id) in std::mem::take(&mut self.retired_graph_ids) {
+            let graph = GraphIdentifier::graph_id_to_graphname(&graph_id)?;
+            self.remove_store_graph(&store, graph.as_ref())?;
+        }
 
         drop(store); // drop the store so we can optimize it later
 
-        // Step four: update the dependency graph for all updated ontologies
-        info!("Updating dependency graphs for updated ontologies");
-        self.update_dependency_graph(Some(updated_ids))?;
+        // Steps four and five
+        self.update_dependencies(updated_ids)?;
 
         // optimize the store for storage + queries
         //if !self.read_only {
         //    self.store().optimize()?;
         //}
 
-        Ok(())
-    }
-
-    /// Returns the GraphViz dot representation of the dependency graph
-    pub fn dep_graph_to_dot(&self) -> Result<String> {
-        self.rooted_dep_graph_to_dot(self.ontologies.keys().cloned().collect())
+        report.added.sort();
+        report.updated.sort();
+        report.removed.sort();
+        report.failed.sort_by(|a, b| a.location.cmp(&b.location));
+        info!(
+            added = report.added.len(),
+            updated = report.updated.len(),
+            removed = report.removed.len(),
+            failed = report.failed.len(),
+            "Updated environment"
+        );
+        self.record_duration(Timer::Update, start);
+        self.publish_changes(&report)?;
+        Ok(report)
     }
 
-    /// Return the GraphViz dot representation of the dependency graph
-    /// rooted at the given graph
-    pub fn rooted_dep_graph_to_dot(&self, roots: Vec<GraphIdentifier>) -> Result<String> {
-        let mut graph = DiGraph::new();
-        let mut stack: VecDeque<GraphIdentifier> = VecDeque::new();
-        let mut seen: HashSet<GraphIdentifier> = HashSet::new();
-        let mut indexes: HashMap<GraphIdentifier, NodeIndex> = HashMap::new();
-        let mut edges: HashSet<(NodeIndex, NodeIndex)> = HashSet::new();
-        for root in roots {
-            stack.push_back(root.clone());
-        }
-        while let Some(ontology) = stack.pop_front() {
-            let index = *indexes
-                .entry(ontology.clone())
-                .or_insert_with(|| graph.add_node(ontology.name().into_owned()));
-            let ont = self
-                .ontologies
-                .get(&ontology)
-                .ok_or(anyhow::anyhow!(format!(
-                    "Listing ontologies: Ontology {} not found",
-                    ontology
-                )))?;
-            for import in &ont.imports {
-                let import = match self.get_ontology_by_name(import.into()) {
-                    Some(imp) => imp.id().clone(),
-                    None => {
-                        error!("Import not found: {}", import);
-                        continue;
+    /// Adds or updates the ontologies at the locations, each with whether it is already in the
+    /// environment, and records them in the report. Up to `Config::parallelism` documents are
+    /// read and parsed at once by worker threads, while the ontologies are added to the store
+    /// one at a time on this thread, in the order of the locations, so which of two ontologies
+    /// with the same name is added first does not depend on timing. Remote ontologies
+    /// which are already in the environment are fetched conditionally, and left as they are if
+    /// they did not change. Returns the identifiers of the added and updated ontologies
+    fn read_locations(
+        &mut self,
+        locations: Vec<(OntologyLocation, bool)>,
+        store: &Store,
+        report: &mut UpdateReport,
+    ) -> Result<Vec<GraphIdentifier>> {
+        let mut updated_ids: Vec<GraphIdentifier> = vec![];
+        let (total, mut done) = (locations.len(), 0);
+        let mut record = |env: &mut OntoEnv,
+                          location: &OntologyLocation,
+                          known: bool,
+                          result: Result<GraphIdentifier>|
+         -> Result<()> {
+            done += 1;
+            env.report_progress(Phase::Read, location, done, total);
+            env.read_failures
+                .retain(|failure| &failure.location != location);
+            match result {
+                Ok(id) => {
+                    if known {
+                        report.updated.push(id.clone());
+                    } else {
+                        report.added.push(id.clone());
                     }
-                };
-                let name: NamedNode = import.name().into_owned();
-                let import_index = *indexes
-                    .entry(import.clone())
-                    .or_insert_with(|| graph.add_node(name));
-                if !seen.contains(&import) {
-                    stack.push_back(import.clone());
+                    updated_ids.push(id);
                 }
-                if !edges.contains(&(index, import_index)) {
-                    graph.add_edge(index, import_index, ());
-                    edges.insert((index, import_index));
+                Err(e) if e.is::<NotModifiedError>() => {}
+                Err(e) if e.is::<Cancelled>() || env.config.is_strict(read_rule(location)) => {
+                    error!("Failed to read ontology file: {}", e);
+                    return Err(e);
+                }
+                Err(e) => {
+                    let failure = UpdateFailure::new(location.clone(), &e);
+                    env.read_failures.push(failure.clone());
+                    report.failed.push(failure);
                 }
             }
-            seen.insert(ontology);
-        }
-        let dot =
-            petgraph::dot::Dot::with_config(&graph, &[petgraph::dot::Config::GraphContentOnly]);
+            Ok(())
+        };
 
-        Ok(format!("digraph {{\nrankdir=LR;\n{:?}}}", dot))
-    }
+        // the ETag and Last-Modified of the last responses of known remote ontologies
+        let validators: HashMap<OntologyLocation, CacheValidators> = locations
+            .iter()
+            .filter(|(location, known)| *known && location.is_url())
+            .filter_map(|(location, _)| {
+                let ontology = self.get_ontology_by_location(location)?;
+                Some((location.clone(), ontology.cache_validators()?.clone()))
+            })
+            .collect();
 
-    fn find_files(&self) -> Result<Vec<OntologyLocation>> {
-        let mut files = vec![];
-        for search_directory in &self.config.search_directories {
-            for entry in walkdir::WalkDir::new(search_directory) {
-                let entry = entry?;
-                if entry.file_type().is_file() && self.config.is_included(entry.path()) {
-                    files.push(OntologyLocation::File(entry.path().to_path_buf()));
-                }
+        let mut workers = self.config.parallelism().min(locations.len());
+        if self.config.limits.max_memory.is_some() {
+            // documents are held in memory until they are added, so the largest file bounds how
+            // many can be read at once
+            let largest = locations
+                .iter()
+                .filter_map(|(location, _)| fs::metadata(location.as_path()?).ok())
+                .map(|metadata| metadata.len())
+                .max()
+                .unwrap_or(0);
+            workers = self.config.limits.read_workers(workers, largest);
+            debug!(workers, largest, "Reading fewer documents at once");
+        }
+        if workers <= 1 {
+            for (location, known) in locations {
+                self.check_cancelled()?;
+                let read = match validators.get(&location) {
+                    Some(validators) => Self::read_for_update(&self.config, &location, validators),
+                    None => None,
+                };
+                let result = self.add_or_update_ontology(location.clone(), store, known, read);
+                record(self, &location, known, result)?;
             }
+            return Ok(updated_ids);
         }
-        Ok(files)
-    }
 
-    /// Add the ontology from the given location to the environment. If the ontology
-    /// already exists in the environment, it is overwritten.
-    pub fn add(&mut self, location: OntologyLocation) -> Result<GraphIdentifier> {
-        let store = self.store();
-        info!("Adding ontology from location: {:?}", location);
-        self.add_or_update_ontology_from_location(location, &store)
+        debug!(
+            workers,
+            documents = locations.len(),
+            "Reading documents in parallel"
+        );
+        // the workers only need the configuration, so they do not borrow the environment
+        let config = self.config.clone();
+        let cancellation = self.cancellation.clone();
+        let next = AtomicUsize::new(0);
+        let no_validators = CacheValidators::default();
+        let downloads = self.config.limits.max_downloads.map(DownloadSlots::new);
+        // documents are added in the order of their locations. Workers read at most `window`
+        // locations past the next one to add, which bounds the documents held in memory while
+        // a slow one is read; the mutex holds the index of the next location to add, and whether
+        // this thread stopped adding
+        let window = 2 * workers;
+        let added = Mutex::new((0usize, false));
+        let turn = Condvar::new();
+        std::thread::scope(|scope| -> Result<()> {
+            let (sender, receiver) = mpsc::sync_channel(workers);
+            for _ in 0..workers {
+                let sender = sender.clone();
+                let (locations, config, cancellation, next, validators, no_validators, downloads) = (
+                    &locations,
+                    &config,
+                    &cancellation,
+                    &next,
+                    &validators,
+                    &no_validators,
+                    &downloads,
+                );
+                let (added, turn) = (&added, &turn);
+                scope.spawn(move || loop {
+                    let index = next.fetch_add(1, Ordering::SeqCst);
+                    let Some((location, _)) = locations.get(index) else {
+                        break;
+                    };
+                    if cancellation.as_ref().is_some_and(|t| t.is_cancelled()) {
+                        break;
+                    }
+                    let mut state = added.lock().unwrap_or_else(PoisonError::into_inner);
+                    while index >= state.0 + window && !state.1 {
+                        state = turn.wait(state).unwrap_or_else(PoisonError::into_inner);
+                    }
+                    if state.1 {
+                        break;
+                    }
+                    drop(state);
+                    let validators = validators.get(location).unwrap_or(no_validators);
+                    let slot = downloads
+                        .as_ref()
+                        .filter(|_| location.is_url())
+                        .map(DownloadSlots::acquire);
+                    let read = Self::read_for_update(config, location, validators);
+                    drop(slot);
+                    // the receiver is gone once adding an ontology failed
+                    if sender.send((index, read)).is_err() {
+                        break;
+                    }
+                });
+            }
+            drop(sender);
+            // wakes the waiting workers for good however this thread stops adding documents
+            let _stop = StopReading(&added, &turn);
+            // documents read ahead of the next one to add, by index
+            let mut pending = BTreeMap::new();
+            let mut next_index = 0;
+            for (index, read) in receiver {
+                pending.insert(index, read);
+                while let Some(read) = pending.remove(&next_index) {
+                    self.check_cancelled()?;
+                    let (location, known) = &locations[next_index];
+                    let result = self.add_or_update_ontology(location.clone(), store, *known, read);
+                    record(self, location, *known, result)?;
+                    next_index += 1;
+                    added.lock().unwrap_or_else(PoisonError::into_inner).0 = next_index;
+                    turn.notify_all();
+                }
+            }
+            Ok(())
+        })?;
+        // the workers stop handing out locations once cancelled
+        self.check_cancelled()?;
+        Ok(updated_ids)
     }
 
-    /// Add or update the ontology from the given location. Overwrites the ontology
-    /// if it already exists in the environment.
-    fn add_or_update_ontology_from_location(
-        &mut self,
-        location: OntologyLocation,
-        store: &Store,
-    ) -> Result<GraphIdentifier> {
-        // find an entry in self.ontologies with the same Location
-        if let Some(ontology) = self.get_ontology_by_location(&location) {
-            info!("Found ontology with the same location: {:?}", ontology);
-            return Ok(ontology.id().clone());
+    // reads the document at the location for `read_locations`, with how long reading it took.
+    // Offline mode skips remote ontologies before reading them
+    fn read_for_update(
+        config: &Config,
+        location: &OntologyLocation,
+        validators: &CacheValidators,
+    ) -> Option<(Result<util::Document>, Duration)> {
+        if location.is_url() && config.offline {
+            return None;
         }
+        let start = Instant::now();
+        let document = Self::read_document_within_limits(config, location, validators);
+        Some((document, start.elapsed()))
+    }
 
-        // if location is a Url and we are in offline mode, skip adding the ontology
-        // and raise a warning
-        if location.is_url() && self.config.offline {
-            warn!("Offline mode is enabled, skipping URL: {:?}", location);
-            if self.config.strict {
+    /// Updates the dependency graph for the updated ontologies and checks the new dependency
+    /// graph against the import and version constraints
+    fn update_dependencies(&mut self, updated_ids: Vec<GraphIdentifier>) -> Result<()> {
+        info!("Updating dependency graphs for updated ontologies");
+        self.update_dependency_graph(Some(updated_ids))?;
+
+        for violation in self.check_import_constraints()? {
+            if self.config.is_strict(StrictRule::Constraints) {
+                return Err(anyhow::anyhow!("Import constraint violated: {}", violation));
+            }
+            warn!("Import constraint violated: {}", violation);
+        }
+        for violation in self.check_version_constraints()? {
+            if self.config.is_strict(StrictRule::Constraints) {
                 return Err(anyhow::anyhow!(
-                    "Offline mode is enabled. Cannot fetch {}",
-                    location.as_str()
+                    "Version constraint violated: {}",
+                    violation
                 ));
             }
-            return Ok(GraphIdentifier::new(location.to_iri().as_ref()));
+            warn!("Version constraint violated: {}", violation);
         }
 
-        // if one is not found and the location is a URL then add the ontology to the environment
-        let graph = match location.graph() {
-            Ok(graph) => graph,
-            Err(e) => {
-                error!("Failed to read ontology {:?}: {}", location, e);
-                return Err(e);
-            }
-        };
+        // forget the failures of imports which were since resolved, e.g. by a local file
+        let missing = self.missing_imports();
+        self.failed_imports
+            .retain(|import, _| missing.iter().any(|m| m.as_str() == import));
 
-        let mut ontology =
-            Ontology::from_graph(&graph, location, self.config.require_ontology_names)?;
-        ontology.with_last_updated(Utc::now());
-        info!(
-            "Adding ontology: {:?} updated: {:?}",
-            ontology.id(),
-            ontology.last_updated
-        );
-        let id = ontology.id().clone();
-        self.ontologies.insert(id.clone(), ontology);
+        // ontologies which are no longer imported leave an environment restricted to a root
+        self.retain_root_closure()?;
 
-        // if the graph is already in the store, remove it and add the new graph
-        let graphname: NamedOrBlankNode = match id.graphname()? {
+        // record how the number of dependencies of each ontology has changed
+        self.record_dependency_snapshot()?;
+        self.record_environment_snapshot()?;
+        self.write_lockfile()
+    }
+
+    /// Returns true if the location matches the pattern: a glob (e.g. `*/models/*.ttl`) or a
+    /// prefix (e.g. `http://qudt.org/`). Patterns for local paths may be relative to the root of
+    /// the environment
+    fn location_matches(&self, location: &OntologyLocation, pattern: &str) -> Result<bool> {
+        let mut patterns = vec![pattern.to_string()];
+        if !pattern.contains("://") {
+            let relative = pattern.trim_start_matches("./");
+            patterns.push(
+                self.config
+                    .root
+                    .join(relative)
+                    .to_string_lossy()
+                    .to_string(),
+            );
+        }
+        for pattern in patterns {
+            let matches = if pattern.contains(['*', '?', '[']) {
+                glob::Pattern::new(&pattern)?.matches(location.as_str())
+            } else {
+                location.as_str().starts_with(&pattern)
+            };
+            if matches {
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Refreshes only the ontologies whose location matches the pattern (see
+    /// `location_matches`), e.g. just the remote QUDT ontologies or just the files under
+    /// ./models, instead of the whole environment. Matching files which were deleted are
+    /// removed, and new matching files in the search directories are added. The strategy decides
+    /// which of the matching ontologies are read again
+    #[instrument(name = "refresh", skip(self, strategy))]
+    pub fn refresh_matching(
+        &mut self,
+        pattern: &str,
+        strategy: RefreshStrategy,
+    ) -> Result<UpdateReport> {
+        let start = Instant::now();
+        let mut report = UpdateReport::default();
+        self.begin_journal()?;
+        let store = self.store();
+
+        let mut matching: Vec<(GraphIdentifier, OntologyLocation)> = vec![];
+        for (id, ontology) in self.ontologies.iter() {
+            if let Some(location) = ontology.location() {
+                if self.location_matches(location, pattern)? {
+                    matching.push((id.clone(), location.clone()));
+                }
+            }
+        }
+        let changed = self.get_updated_indexed_files()?;
+
+        let mut to_read: Vec<(OntologyLocation, bool)> = vec![];
+        for (id, location) in matching {
+            match &location {
+                OntologyLocation::File(path) if !path.exists() => {
+                    debug!("Removing ontology: {:?}", id);
+                    let graphname = self.store_graphname(&id)?;
+                    self.ontologies.remove(&id);
+                    if let GraphName::NamedNode(name) = graphname {
+                        self.remove_store_graph(&store, name.as_ref())?;
+                    }
+                    for listener in &self.listeners {
+                        listener.on_remove(&id);
+                    }
+                    report.removed.push(id);
+                }
+                OntologyLocation::Url(_) if self.config.offline => {
+                    debug!("Offline mode is enabled, not fetching {}", location);
+                }
+                OntologyLocation::File(_) if strategy == RefreshStrategy::Changed => {
+                    if changed.contains(&id) {
+                        to_read.push((location, true));
+                    }
+                }
+                OntologyLocation::File(path) if strategy == RefreshStrategy::HashOnly => {
+                    let ontology = &self.ontologies[&id];
+                    if self.source_changed(ontology, path)? {
+                        to_read.push((location.clone(), true));
+                    }
+                }
+                OntologyLocation::Url(_) if strategy != RefreshStrategy::All => {}
+                _ => to_read.push((location, true)),
+            }
+        }
+        let known: HashSet<&OntologyLocation> =
+            to_read.iter().map(|(location, _)| location).collect();
+        let mut new_files = vec![];
+        for file in self.find_files()? {
+            if !known.contains(&file)
+                && self.get_ontology_by_location(&file).is_none()
+                && self.location_matches(&file, pattern)?
+            {
+                new_files.push((file, false));
+            }
+        }
+        to_read.extend(new_files);
+
+        let updated_ids = self.read_locations(to_read, &store, &mut report)?;
+        drop(store);
+
+        self.update_dependencies(updated_ids)?;
+
+        report.added.sort();
+        report.updated.sort();
+        report.removed.sort();
+        report.failed.sort_by(|a, b| a.location.cmp(&b.location));
+        info!(
+            added = report.added.len(),
+            updated = report.updated.len(),
+            removed = report.removed.len(),
+            failed = report.failed.len(),
+            "Refreshed environment"
+        );
+        self.record_duration(Timer::Update, start);
+        self.publish_changes(&report)?;
+        Ok(report)
+    }
+
+    /// Writes the change manifest of an update to the configured `change_manifest` file and
+    /// POSTs it to the configured `change_webhook`, unless the update changed no graph. A
+    /// webhook which cannot be reached only logs a warning, since the update itself succeeded
+    fn publish_changes(&self, report: &UpdateReport) -> Result<()> {
+        if self.config.change_manifest.is_none() && self.config.change_webhook.is_none() {
+            return Ok(());
+        }
+        let manifest = ChangeManifest::from_report(report, &self.ontologies, &self.config.root)?;
+        if manifest.is_empty() {
+            return Ok(());
+        }
+        if let Some(path) = &self.config.change_manifest {
+            manifest.write(&self.config.root.join(path))?;
+        }
+        if let Some(url) = &self.config.change_webhook {
+            if let Err(e) = manifest.post(url) {
+                warn!(webhook = %url, error = %e, "Failed to post change manifest");
+            }
+        }
+        Ok(())
+    }
+
+    fn history_path(&self) -> std::path::PathBuf {
+        self.ontoenv_dir().join("history.jsonl")
+    }
+
+    /// Returns the number of direct and transitive dependencies of every ontology in the
+    /// environment, sorted by name and location
+    pub fn dependency_counts(&self) -> Result<Vec<DependencyCount>> {
+        let mut counts = vec![];
+        for (id, ontology) in self.ontologies.iter() {
+            let closure = self.get_dependency_closure(id)?;
+            counts.push(DependencyCount {
+                name: ontology.name().as_str().to_string(),
+                location: id.location().to_string(),
+                direct: ontology.imports.len(),
+                transitive: closure.len() - 1,
+            });
+        }
+        counts.sort_by(|a, b| (&a.name, &a.location).cmp(&(&b.name, &b.location)));
+        Ok(counts)
+    }
+
+    /// Persists the current dependency counts to the environment's history if they have
+    /// changed since the last recorded snapshot
+    fn record_dependency_snapshot(&self) -> Result<()> {
+        if self.temporary {
+            return Ok(());
+        }
+        let snapshot = DependencySnapshot {
+            timestamp: Utc::now(),
+            counts: self.dependency_counts()?,
+        };
+        if history::record_snapshot(&self.history_path(), &snapshot)? {
+            info!("Recorded dependency snapshot at {}", snapshot.timestamp);
+        }
+        Ok(())
+    }
+
+    /// Returns how the direct and transitive dependency counts of the ontology with the given
+    /// name evolved over the recorded updates, oldest first
+    pub fn dependency_history(
+        &self,
+        name: NamedNodeRef,
+    ) -> Result<Vec<(DateTime<Utc>, DependencyCount)>> {
+        let mut history = vec![];
+        for snapshot in history::load_history(&self.history_path())? {
+            for count in snapshot.counts {
+                if count.name == name.as_str() {
+                    history.push((snapshot.timestamp, count));
+                }
+            }
+        }
+        Ok(history)
+    }
+
+    fn snapshots_path(&self) -> PathBuf {
+        self.ontoenv_dir().join("snapshots.jsonl")
+    }
+
+    /// Records the ontologies of the environment as a snapshot (see `snapshots`) if they have
+    /// changed since the last one. With `snapshot_graphs`, the graphs whose content was not
+    /// recorded before are stored too
+    fn record_environment_snapshot(&self) -> Result<()> {
+        if self.temporary {
+            return Ok(());
+        }
+        let snapshot = self.current_snapshot(None);
+        if snapshots::record_snapshot(&self.snapshots_path(), &snapshot)? {
+            info!("Recorded environment snapshot {}", snapshot.id);
+            if self.config.snapshot_graphs {
+                self.store_snapshot_graphs()?;
+            }
+        }
+        Ok(())
+    }
+
+    // the ontologies of the environment as a snapshot
+    fn current_snapshot(&self, tag: Option<&str>) -> EnvironmentSnapshot {
+        EnvironmentSnapshot {
+            id: self.fingerprint(),
+            timestamp: Utc::now(),
+            ontologies: self.ontologies.values().cloned().collect(),
+            tag: tag.map(str::to_string),
+        }
+    }
+
+    // stores the graphs of the environment whose content was not recorded before with the
+    // snapshot graphs, then removes the oldest graphs no tagged snapshot needs while they take
+    // more than `max_cache_size`
+    fn store_snapshot_graphs(&self) -> Result<()> {
+        let graphs = GraphStore::in_dir(&self.ontoenv_dir());
+        for (id, ontology) in self.ontologies.iter() {
+            if let Some(hash) = ontology.content_hash() {
+                if !graphs.contains(hash) {
+                    graphs.put(hash, &self.get_graph(id)?)?;
+                }
+            }
+        }
+        let Some(max_size) = self.config.limits.max_cache_size else {
+            return Ok(());
+        };
+        if graphs.size()? <= max_size {
+            return Ok(());
+        }
+        let keep: HashSet<String> = self
+            .snapshots()?
+            .iter()
+            .filter(|s| s.tag.is_some())
+            .flat_map(|s| s.ontologies.iter())
+            .filter_map(|o| o.content_hash().map(str::to_string))
+            .collect();
+        let removed = graphs.evict(max_size, &keep)?;
+        if removed > 0 {
+            info!("Removed {} graphs kept for snapshots", removed);
+        }
+        Ok(())
+    }
+
+    /// Records the ontologies of the environment as a snapshot named `tag`, e.g. before
+    /// updating remote ontologies, so `rollback(tag)` can restore them if a closure breaks.
+    /// Unlike the snapshots recorded by updates, it is recorded even if nothing changed since
+    /// the last one. Tags must be unique
+    pub fn snapshot(&self, tag: &str) -> Result<EnvironmentSnapshot> {
+        if self.merged_view || self.temporary {
+            return Err(anyhow::anyhow!(
+                "Cannot snapshot an environment which is not backed by a directory"
+            ));
+        }
+        if self
+            .snapshots()?
+            .iter()
+            .any(|s| s.tag.as_deref() == Some(tag))
+        {
+            return Err(anyhow::anyhow!("Snapshot {} already exists", tag));
+        }
+        let snapshot = self.current_snapshot(Some(tag));
+        snapshots::append_snapshot(&self.snapshots_path(), &snapshot)?;
+        self.store_snapshot_graphs()?;
+        info!(tag, "Recorded environment snapshot {}", snapshot.id);
+        Ok(snapshot)
+    }
+
+    // the ontologies of the snapshot with their graphs, read from the snapshot graphs, or from
+    // the store if the ontology has not changed since
+    fn snapshot_graphs(&self, snapshot: &EnvironmentSnapshot) -> Result<Vec<(Ontology, Graph)>> {
+        let graphs = GraphStore::in_dir(&self.ontoenv_dir());
+        let mut contents = vec![];
+        for ontology in &snapshot.ontologies {
+            let unavailable = || {
+                anyhow::anyhow!(
+                    "The content of {} in snapshot {} is no longer available",
+                    ontology.id(),
+                    snapshot.id
+                )
+            };
+            let hash = ontology.content_hash().ok_or_else(unavailable)?;
+            let graph = match graphs.get(hash)? {
+                Some(graph) => graph,
+                None => {
+                    let current = self
+                        .ontologies
+                        .values()
+                        .find(|o| o.content_hash() == Some(hash))
+                        .ok_or_else(unavailable)?;
+                    self.get_graph(current.id())?
+                }
+            };
+            contents.push((ontology.clone(), graph));
+        }
+        Ok(contents)
+    }
+
+    /// Restores the ontologies of the environment to those of a snapshot, selected like for
+    /// `load_snapshot` (e.g. by the tag given to `snapshot`), and records the restored state
+    /// as a new snapshot. Ontologies are restored with the content they had then, even if their
+    /// files or remote ontologies have changed since, until the next update reads them again.
+    /// Returns the ontologies added, updated (restored to another content) and removed
+    pub fn rollback(&mut self, selector: &str) -> Result<UpdateReport> {
+        if self.merged_view || self.read_only || self.temporary {
+            return Err(anyhow::anyhow!(
+                "Cannot roll back an environment which is read-only or not backed by a directory"
+            ));
+        }
+        let snapshots = self.snapshots()?;
+        let snapshot = snapshots::find_snapshot(&snapshots, selector)?;
+        // read every graph first, so a missing one leaves the environment as it is
+        let contents = self.snapshot_graphs(snapshot)?;
+        self.begin_journal()?;
+        let store = self.store();
+        let mut report = UpdateReport::default();
+
+        let restored: HashMap<&GraphIdentifier, Option<&str>> = contents
+            .iter()
+            .map(|(ontology, _)| (ontology.id(), ontology.content_hash()))
+            .collect();
+        let current: Vec<(GraphIdentifier, Option<String>)> = self
+            .ontologies
+            .values()
+            .map(|o| (o.id().clone(), o.content_hash().map(str::to_string)))
+            .collect();
+        for (id, hash) in current {
+            match restored.get(&id) {
+                Some(restored_hash) if *restored_hash == hash.as_deref() => continue,
+                Some(_) => report.updated.push(id.clone()),
+                None => report.removed.push(id.clone()),
+            }
+            if let GraphName::NamedNode(name) = self.store_graphname(&id)? {
+                self.remove_store_graph(&store, name.as_ref())?;
+            }
+            self.ontologies.remove(&id);
+        }
+        for (ontology, graph) in contents {
+            let id = ontology.id().clone();
+            if self.ontologies.contains_key(&id) {
+                continue;
+            }
+            let graphname = id.graphname()?;
+            store
+                .bulk_loader()
+                .load_quads(util::graph_to_quads(&graph, graphname.as_ref()))?;
+            if !report.updated.contains(&id) {
+                report.added.push(id.clone());
+            }
+            self.ontologies.insert(id, ontology);
+        }
+        drop(store);
+        self.build_dependency_graph()?;
+
+        report.added.sort();
+        report.updated.sort();
+        report.removed.sort();
+        report.failed.sort_by(|a, b| a.location.cmp(&b.location));
+        for listener in &self.listeners {
+            for id in &report.added {
+                listener.on_add(id);
+            }
+            for id in &report.updated {
+                listener.on_update(id);
+            }
+            for id in &report.removed {
+                listener.on_remove(id);
+            }
+        }
+        info!(
+            added = report.added.len(),
+            updated = report.updated.len(),
+            removed = report.removed.len(),
+            "Rolled back to snapshot {}",
+            snapshot.id
+        );
+        self.record_environment_snapshot()?;
+        self.write_lockfile()?;
+        self.publish_changes(&report)?;
+        Ok(report)
+    }
+
+    /// Returns the snapshots of the environment recorded after its updates, oldest first
+    pub fn snapshots(&self) -> Result<Vec<EnvironmentSnapshot>> {
+        snapshots::load_snapshots(&self.snapshots_path())
+    }
+
+    /// Returns a read-only view of the environment as it was after an earlier update, selected
+    /// by a prefix of the snapshot ID, a timestamp or a date (see `snapshots::find_snapshot`).
+    /// The graphs are read from the snapshot graphs, or from the store if the ontology has not
+    /// changed since; snapshots whose graphs were not kept (see `Config::snapshot_graphs`) cannot
+    /// be loaded once their ontologies changed. Like a merged view, it cannot be saved or updated
+    pub fn load_snapshot(&self, selector: &str) -> Result<OntoEnv> {
+        let snapshots = self.snapshots()?;
+        let snapshot = snapshots::find_snapshot(&snapshots, selector)?;
+        let store = Store::new()?;
+        let mut ontologies: BTreeMap<GraphIdentifier, Ontology> = BTreeMap::new();
+        for (ontology, graph) in self.snapshot_graphs(snapshot)? {
+            let graphname = ontology.id().graphname()?;
+            store
+                .bulk_loader()
+                .load_quads(util::graph_to_quads(&graph, graphname.as_ref()))?;
+            ontologies.insert(ontology.id().clone(), ontology);
+        }
+        info!(
+            snapshot = %snapshot.id,
+            ontologies = ontologies.len(),
+            "Loaded snapshot from {}",
+            snapshot.timestamp
+        );
+
+        let mut view = OntoEnv {
+            config: self.config.clone(),
+            ontologies,
+            dependency_graph: DiGraph::new(),
+            failed_imports: BTreeMap::new(),
+            read_failures: vec![],
+            profile: None,
+            read_only: true,
+            how_created: HowCreated::New,
+            inner_store: Some(store),
+            metrics: None,
+            cancellation: None,
+            retired_graph_ids: HashMap::new(),
+            inspectors: vec![],
+            listeners: vec![],
+            memory_cache: Default::default(),
+            merged_view: true,
+            temporary: false,
+            scratch_dir: None,
+        };
+        view.build_dependency_graph()?;
+        Ok(view)
+    }
+
+    /// Returns the path of the environment's lockfile (see `lockfile`)
+    pub fn lockfile_path(&self) -> PathBuf {
+        self.config.root.join(lockfile::LOCKFILE_NAME)
+    }
+
+    /// Returns the lockfile of the environment: every ontology with its location, the hash of
+    /// its content and when it was retrieved. Ontologies read before content hashes were
+    /// recorded are hashed from the store
+    pub fn lockfile(&self) -> Result<lockfile::Lockfile> {
+        let mut ontologies = vec![];
+        for ontology in self.ontologies.values() {
+            if ontology.content_hash().is_some() {
+                ontologies.push(ontology.clone());
+                continue;
+            }
+            let mut ontology = ontology.clone();
+            let graph = self.get_graph(ontology.id())?;
+            ontology.with_content_hash(lockfile::content_hash(&graph));
+            ontologies.push(ontology);
+        }
+        Ok(lockfile::Lockfile::from_ontologies(
+            &ontologies,
+            &self.config.root,
+        ))
+    }
+
+    fn write_lockfile(&self) -> Result<()> {
+        // scratch environments never write into the project tree
+        if self.temporary || self.is_scratch() {
+            return Ok(());
+        }
+        if self.lockfile()?.save(&self.lockfile_path())? {
+            info!("Wrote lockfile {}", self.lockfile_path().display());
+        }
+        Ok(())
+    }
+
+    /// Makes the environment hold exactly the ontologies recorded in the lockfile at the root of
+    /// the environment, e.g. after checking out a project on another machine. Ontologies whose
+    /// content matches the recorded hash are kept as they are; the others are read or fetched
+    /// again, and ontologies which are not in the lockfile are removed. The imports of the
+    /// recorded ontologies are not fetched, since the lockfile records the whole closure.
+    ///
+    /// An ontology whose content still differs from the lockfile after reading it again (e.g.
+    /// a remote ontology which changed upstream) is an error in strict mode, and otherwise
+    /// reported as failed. The lockfile itself is left as it is
+    pub fn sync_from_lock(&mut self) -> Result<UpdateReport> {
+        if self.merged_view || self.temporary {
+            return Err(anyhow::anyhow!(
+                "Cannot sync an environment which is not backed by a directory"
+            ));
+        }
+        let path = self.lockfile_path();
+        let locked = lockfile::Lockfile::load(&path)?.resolved(&self.config.root);
+        self.begin_journal()?;
+        let store = self.store();
+        let mut report = UpdateReport::default();
+
+        let wanted: HashSet<(&str, &OntologyLocation)> = locked
+            .iter()
+            .map(|entry| (entry.name.as_str(), &entry.location))
+            .collect();
+        let unlocked: Vec<GraphIdentifier> = self
+            .ontologies
+            .values()
+            .filter(|ontology| {
+                !ontology
+                    .location()
+                    .is_some_and(|l| wanted.contains(&(ontology.name().as_str(), l)))
+            })
+            .map(|ontology| ontology.id().clone())
+            .collect();
+        for id in unlocked {
+            debug!("Removing ontology which is not in the lockfile: {:?}", id);
+            if let GraphName::NamedNode(name) = self.store_graphname(&id)? {
+                self.remove_store_graph(&store, name.as_ref())?;
+            }
+            self.ontologies.remove(&id);
+            for listener in &self.listeners {
+                listener.on_remove(&id);
+            }
+            report.removed.push(id);
+        }
+
+        for entry in locked {
+            self.check_cancelled()?;
+            let existing = self
+                .get_ontology_by_location(&entry.location)
+                .filter(|ontology| ontology.name().as_str() == entry.name);
+            if existing.is_some_and(|o| o.content_hash() == Some(entry.hash.as_str())) {
+                continue;
+            }
+            let known = existing.is_some();
+            let result = self
+                .add_or_update_ontology_from_location(entry.location.clone(), &store, true)
+                .and_then(|id| {
+                    let hash = self
+                        .ontologies
+                        .get(&id)
+                        .and_then(|ontology| ontology.content_hash());
+                    if hash != Some(entry.hash.as_str()) {
+                        return Err(anyhow::anyhow!(
+                            "Content of {} at {} does not match the lockfile",
+                            entry.name,
+                            entry.location
+                        ));
+                    }
+                    Ok(id)
+                });
+            self.read_failures
+                .retain(|failure| failure.location != entry.location);
+            match result {
+                Ok(id) if known => report.updated.push(id),
+                Ok(id) => report.added.push(id),
+                Err(e) if self.config.is_strict(read_rule(&entry.location)) => return Err(e),
+                Err(e) if e.is::<Cancelled>() => return Err(e),
+                Err(e) => {
+                    warn!("{}", e);
+                    let failure = UpdateFailure::new(entry.location, &e);
+                    self.read_failures.push(failure.clone());
+                    report.failed.push(failure);
+                }
+            }
+        }
+        drop(store);
+        self.build_dependency_graph()?;
+
+        report.added.sort();
+        report.updated.sort();
+        report.removed.sort();
+        report.failed.sort_by(|a, b| a.location.cmp(&b.location));
+        info!(
+            added = report.added.len(),
+            updated = report.updated.len(),
+            removed = report.removed.len(),
+            failed = report.failed.len(),
+            "Synced environment from {}",
+            path.display()
+        );
+        self.publish_changes(&report)?;
+        Ok(report)
+    }
+
+    /// Returns the owl:imports relationships between the ontologies in the environment as a
+    /// petgraph graph, with a node for each ontology and for each import which does not resolve.
+    /// Imports resolve as they do for closures (see the resolution policy)
+    pub fn dependency_graph(&self) -> DependencyGraph {
+        let mut graph = DependencyGraph::default();
+        for (id, ontology) in &self.ontologies {
+            graph.add_node(DependencyNode::Ontology(id.clone()));
+            for import in &ontology.imports {
+                let to = match self.resolve_import(import.into()) {
+                    Some(imported) => DependencyNode::Ontology(imported.id().clone()),
+                    None => DependencyNode::Missing(import.clone()),
+                };
+                graph.add_import(id, to, import.clone());
+            }
+        }
+        graph
+    }
+
+    /// Returns the GraphViz dot representation of the dependency graph
+    pub fn dep_graph_to_dot(&self) -> Result<String> {
+        self.rooted_dep_graph_to_dot(self.ontologies.keys().cloned().collect())
+    }
+
+    /// Return the GraphViz dot representation of the dependency graph
+    /// rooted at the given graph
+    pub fn rooted_dep_graph_to_dot(&self, roots: Vec<GraphIdentifier>) -> Result<String> {
+        let mut graph = DiGraph::new();
+        let mut stack: VecDeque<GraphIdentifier> = VecDeque::new();
+        let mut seen: HashSet<GraphIdentifier> = HashSet::new();
+        let mut indexes: HashMap<GraphIdentifier, NodeIndex> = HashMap::new();
+        let mut edges: HashSet<(NodeIndex, NodeIndex)> = HashSet::new();
+        for root in roots {
+            stack.push_back(root.clone());
+        }
+        while let Some(ontology) = stack.pop_front() {
+            let index = *indexes
+                .entry(ontology.clone())
+                .or_insert_with(|| graph.add_node(ontology.name().into_owned()));
+            let ont = self
+                .ontologies
+                .get(&ontology)
+                .ok_or(anyhow::anyhow!(format!(
+                    "Listing ontologies: Ontology {} not found",
+                    ontology
+                )))?;
+            for import in &ont.imports {
+                let import = match self.resolve_import(import.into()) {
+                    Some(imp) => imp.id().clone(),
+                    None => {
+                        error!("Import not found: {}", import);
+                        continue;
+                    }
+                };
+                let name: NamedNode = import.name().into_owned();
+                let import_index = *indexes
+                    .entry(import.clone())
+                    .or_insert_with(|| graph.add_node(name));
+                if !seen.contains(&import) {
+                    stack.push_back(import.clone());
+                }
+                if !edges.contains(&(index, import_index)) {
+                    graph.add_edge(index, import_index, ());
+                    edges.insert((index, import_index));
+                }
+            }
+            seen.insert(ontology);
+        }
+        let dot =
+            petgraph::dot::Dot::with_config(&graph, &[petgraph::dot::Config::GraphContentOnly]);
+
+        Ok(format!("digraph {{\nrankdir=LR;\n{:?}}}", dot))
+    }
+
+    #[instrument(name = "scan", skip_all)]
+    fn find_files(&self) -> Result<Vec<OntologyLocation>> {
+        // environments restricted to a root ontology only follow its imports
+        if self.temporary || self.config.root_ontology.is_some() {
+            return Ok(vec![]);
+        }
+        let mut files = vec![];
+        // with follow_symlinks, files are recorded under their canonical path, so a file reached
+        // through several links is only read once
+        let mut seen: HashSet<PathBuf> = HashSet::new();
+        for search_directory in self.config.scanned_directories() {
+            let walker = walkdir::WalkDir::new(&search_directory)
+                .follow_links(self.config.follow_symlinks)
+                .sort_by_file_name();
+            for entry in walker {
+                self.check_cancelled()?;
+                let entry = match entry {
+                    Ok(entry) => entry,
+                    Err(e) if e.loop_ancestor().is_some() => {
+                        warn!(error = %e, "Skipping symlink cycle");
+                        continue;
+                    }
+                    Err(e) => return Err(e.into()),
+                };
+                if !entry.file_type().is_file() || !self.config.is_indexed(entry.path()) {
+                    continue;
+                }
+                let path = if self.config.follow_symlinks {
+                    fs::canonicalize(entry.path())?
+                } else {
+                    entry.path().to_path_buf()
+                };
+                let path = on_disk_casing(&path);
+                if seen.insert(path.clone()) {
+                    files.push(OntologyLocation::File(path));
+                }
+            }
+        }
+        debug!(files = files.len(), "Scanned search directories");
+        Ok(files)
+    }
+
+    /// Add the ontology from the given location to the environment. If the ontology
+    /// already exists in the environment, it is overwritten.
+    pub fn add(&mut self, location: OntologyLocation) -> Result<GraphIdentifier> {
+        self.begin_journal()?;
+        let store = self.store();
+        info!("Adding ontology from location: {:?}", location);
+        self.add_or_update_ontology_from_location(location, &store, false)
+    }
+
+    // the ontologies with an owl:imports which resolves to the ontology, sorted
+    fn importers_of(&self, id: &GraphIdentifier) -> Vec<GraphIdentifier> {
+        self.ontologies
+            .iter()
+            .filter(|(importer, ontology)| {
+                *importer != id
+                    && ontology.imports.iter().any(|import| {
+                        self.resolve_import_of(importer, import.into())
+                            .is_some_and(|resolved| resolved.id() == id)
+                    })
+            })
+            .map(|(importer, _)| importer.clone())
+            .collect()
+    }
+
+    /// Restricts the environment to the imports closure of the ontology at the location, for a
+    /// minimal environment built for one ontology: the ontology is added if it is not in the
+    /// environment yet, and every ontology outside its closure is removed. From then on, updates
+    /// do not scan the search directories; they read the ontologies of the closure again when
+    /// they change, follow new imports and drop the ontologies which are no longer imported.
+    /// Returns the removed ontologies
+    pub fn restrict_to_root(&mut self, location: OntologyLocation) -> Result<Vec<GraphIdentifier>> {
+        if self.merged_view || self.read_only {
+            return Err(anyhow::anyhow!(
+                "Cannot restrict a read-only environment to a root ontology"
+            ));
+        }
+        let before: Vec<GraphIdentifier> = self.ontologies.keys().cloned().collect();
+        let id = self.add(location)?;
+        self.config.root_ontology = Some(id.location().clone());
+        self.update_dependencies(vec![id])?;
+        let mut removed: Vec<GraphIdentifier> = before
+            .into_iter()
+            .filter(|id| !self.ontologies.contains_key(id))
+            .collect();
+        removed.sort();
+        Ok(removed)
+    }
+
+    // removes the ontologies outside the imports closure of the root ontology, see
+    // `restrict_to_root`
+    fn retain_root_closure(&mut self) -> Result<()> {
+        let Some(location) = self.config.root_ontology.clone() else {
+            return Ok(());
+        };
+        let Some(root) = self.get_ontology_by_location(&location) else {
+            warn!("Root ontology {} is not in the environment", location);
+            return Ok(());
+        };
+        let closure: HashSet<GraphIdentifier> = self
+            .get_dependency_closure(&root.id().clone())?
+            .into_iter()
+            .collect();
+        let to_remove: Vec<GraphIdentifier> = self
+            .ontologies
+            .keys()
+            .filter(|id| !closure.contains(*id))
+            .cloned()
+            .collect();
+        if to_remove.is_empty() {
+            return Ok(());
+        }
+        let store = self.store();
+        for id in &to_remove {
+            let graphname = self.store_graphname(id)?;
+            let Some(ontology) = self.ontologies.remove(id) else {
+                continue;
+            };
+            if let GraphName::NamedNode(name) = graphname {
+                self.remove_store_graph(&store, name.as_ref())?;
+            }
+            debug!(ontology = %ontology.id(), "Removed outside the closure of the root");
+            for listener in &self.listeners {
+                listener.on_remove(ontology.id());
+            }
+        }
+        drop(store);
+        info!(
+            removed = to_remove.len(),
+            "Restricted environment to the root ontology"
+        );
+        self.build_dependency_graph()
+    }
+
+    /// Removes the ontology and its graph from the environment. When other ontologies still
+    /// import it, the mode decides whether that is an error, whether they are removed too, or
+    /// whether their imports are left missing; the report lists the imports which no longer
+    /// resolve. Ontologies read from files in the search directories come back with the next
+    /// `update` unless their files are deleted or excluded as well
+    pub fn remove(&mut self, id: &GraphIdentifier, mode: RemoveMode) -> Result<RemoveReport> {
+        if self.merged_view || self.read_only {
+            return Err(anyhow::anyhow!(
+                "Cannot remove ontologies from a read-only environment"
+            ));
+        }
+        if !self.ontologies.contains_key(id) {
+            return Err(anyhow::anyhow!("Ontology {} not found", id));
+        }
+        let importers = self.importers_of(id);
+        let mut to_remove: BTreeSet<GraphIdentifier> = BTreeSet::from([id.clone()]);
+        match mode {
+            RemoveMode::Error if !importers.is_empty() => {
+                let names: Vec<String> = importers.iter().map(|i| i.to_string()).collect();
+                return Err(anyhow::anyhow!(
+                    "Cannot remove {}: it is imported by {}",
+                    id,
+                    names.join(", ")
+                ));
+            }
+            RemoveMode::Detach
+                if self.config.is_strict(StrictRule::MissingImports) && !importers.is_empty() =>
+            {
+                return Err(anyhow::anyhow!(
+                    "Cannot remove {}: its importers would have missing imports in strict mode",
+                    id
+                ));
+            }
+            RemoveMode::Cascade => {
+                let mut stack = importers;
+                while let Some(importer) = stack.pop() {
+                    if to_remove.insert(importer.clone()) {
+                        stack.extend(self.importers_of(&importer));
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        let missing_before = self.missing_imports();
+        self.begin_journal()?;
+        let store = self.store();
+        let mut report = RemoveReport::default();
+        for id in to_remove {
+            let graphname = self.store_graphname(&id)?;
+            let Some(ontology) = self.ontologies.remove(&id) else {
+                continue;
+            };
+            if let GraphName::NamedNode(name) = graphname {
+                self.remove_store_graph(&store, name.as_ref())?;
+            }
+            let id = ontology.id().clone();
+            info!("Removed ontology {}", id);
+            for listener in &self.listeners {
+                listener.on_remove(&id);
+            }
+            report.removed.push(id);
+        }
+        drop(store);
+        self.build_dependency_graph()?;
+
+        report.newly_missing = self
+            .missing_imports()
+            .into_iter()
+            .filter(|import| !missing_before.contains(import))
+            .map(|import| import.as_str().to_string())
+            .collect();
+        for import in &report.newly_missing {
+            warn!("Import no longer resolves: {}", import);
+        }
+        self.write_lockfile()?;
+        Ok(report)
+    }
+
+    /// Reads the document at the location, unless the file is larger than `max_file_size`. With
+    /// `parse_timeout_secs`, parsing stops at the first chunk of the document read after the
+    /// timeout. A panic while reading is returned as an error
+    fn read_document_within_limits(
+        config: &Config,
+        location: &OntologyLocation,
+        validators: &CacheValidators,
+    ) -> Result<util::Document> {
+        let limit_error = |reason: String| ReadLimitError {
+            location: location.to_string(),
+            reason,
+        };
+        if let (OntologyLocation::File(path), Some(max_size)) = (location, config.max_file_size) {
+            let size = fs::metadata(path)?.len();
+            if size > max_size {
+                return Err(limit_error(format!(
+                    "file is {} bytes, more than the maximum of {} bytes",
+                    size, max_size
+                ))
+                .into());
+            }
+        }
+        let mut options = config.base_iri_options();
+        options.deadline = config
+            .parse_timeout_secs
+            .map(|timeout| Instant::now() + Duration::from_secs(timeout));
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            location.read_document_if_modified(&options, validators)
+        }))
+        .unwrap_or_else(|payload| {
+            let message = payload
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| payload.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown panic".to_string());
+            Err(anyhow::anyhow!(
+                "Reading {} panicked: {}",
+                location,
+                message
+            ))
+        });
+        match (result, config.parse_timeout_secs, options.deadline) {
+            (Err(_), Some(timeout), Some(deadline)) if Instant::now() >= deadline => {
+                warn!(timeout_secs = timeout, "Gave up reading ontology");
+                Err(limit_error(format!("reading took longer than {} seconds", timeout)).into())
+            }
+            (result, _, _) => result,
+        }
+    }
+
+    /// Add or update the ontology from the given location. Overwrites the ontology
+    /// if it already exists in the environment. Unless refresh is true, an ontology which was
+    /// already read from the location is returned without reading it again
+    fn add_or_update_ontology_from_location(
+        &mut self,
+        location: OntologyLocation,
+        store: &Store,
+        refresh: bool,
+    ) -> Result<GraphIdentifier> {
+        self.add_or_update_ontology(location, store, refresh, None)
+    }
+
+    // the same file reached with differently cased paths (or, when following symlinks, through
+    // different links) is one location
+    fn normalize_location(&self, location: OntologyLocation) -> OntologyLocation {
+        match location {
+            OntologyLocation::File(path) if self.config.follow_symlinks => {
+                let path = fs::canonicalize(&path).unwrap_or(path);
+                OntologyLocation::File(on_disk_casing(&path))
+            }
+            OntologyLocation::File(path) => OntologyLocation::File(on_disk_casing(&path)),
+            location => location,
+        }
+    }
+
+    // the import whose owl:imports the import map redirects to the location, if any
+    fn import_mapped_to(&self, location: &OntologyLocation) -> Result<Option<NamedNode>> {
+        for import in self.config.import_map.keys() {
+            if let Some(target) = self.config.mapped_location(import)? {
+                if self.normalize_location(target) == *location {
+                    return Ok(Some(NamedNode::new(import)?));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Like `add_or_update_ontology_from_location`, with the document at the location and how
+    /// long reading it took if it was already read (see `read_locations`)
+    fn add_or_update_ontology(
+        &mut self,
+        location: OntologyLocation,
+        store: &Store,
+        refresh: bool,
+        read: Option<(Result<util::Document>, Duration)>,
+    ) -> Result<GraphIdentifier> {
+        self.check_cancelled()?;
+        let location = self.normalize_location(location);
+        // find an entry in self.ontologies with the same Location
+        if let Some(ontology) = self.get_ontology_by_location(&location) {
+            if !refresh {
+                info!("Found ontology with the same location: {:?}", ontology);
+                let id = ontology.id().clone();
+                self.count(Counter::CacheHits);
+                return Ok(id);
+            }
+        }
+
+        // if location is a Url and we are in offline mode, skip adding the ontology
+        // and raise a warning
+        if location.is_url() && self.config.offline {
+            warn!("Offline mode is enabled, skipping URL: {:?}", location);
+            if self.config.is_strict(StrictRule::Network) {
+                return Err(anyhow::anyhow!(
+                    "Offline mode is enabled. Cannot fetch {}",
+                    location.as_str()
+                ));
+            }
+            return Ok(GraphIdentifier::new(location.to_iri()?.as_ref()));
+        }
+
+        // if one is not found and the location is a URL then add the ontology to the environment
+        let (counter, failure_counter, timer) = if location.is_url() {
+            (Counter::Fetches, Counter::FetchFailures, Timer::Fetch)
+        } else {
+            (Counter::Parses, Counter::ParseFailures, Timer::Parse)
+        };
+        // fetches and parses get their own span so slow ontologies show up in the logs
+        let span = if location.is_url() {
+            info_span!("fetch", url = %location)
+        } else {
+            info_span!("parse", file = %location)
+        };
+        let _guard = span.enter();
+        let start = Instant::now();
+        let (document, elapsed) = match read {
+            Some(read) => read,
+            None => (
+                Self::read_document_within_limits(
+                    &self.config,
+                    &location,
+                    &CacheValidators::default(),
+                ),
+                start.elapsed(),
+            ),
+        };
+        let document = match document {
+            Ok(document) => document,
+            // the remote ontology did not change since it was last fetched
+            Err(e) if e.is::<NotModifiedError>() => {
+                self.count(Counter::CacheHits);
+                let id = self
+                    .get_ontology_by_location(&location)
+                    .map(|o| o.id().clone());
+                if let Some(ontology) = id.and_then(|id| self.ontologies.get_mut(&id)) {
+                    ontology.with_last_updated(Utc::now());
+                }
+                return Err(e);
+            }
+            Err(e) => {
+                error!(error = %e, "Failed to read ontology");
+                self.count(failure_counter);
+                return Err(e);
+            }
+        };
+        self.count(counter);
+        self.record_elapsed(timer, elapsed);
+        let mut graph = document.graph;
+        // store the ontology under its normalized name so variations of it resolve to one entry
+        let renamed = iri::normalize_ontology_iris(&mut graph);
+        if renamed > 0 {
+            debug!("Normalized {} ontology IRIs in {:?}", renamed, location);
+        }
+
+        let mut ontology = Ontology::from_graph(
+            &graph,
+            location,
+            self.config.require_ontology_names,
+            &self.config.name_inference,
+        )?;
+        if ontology.is_anonymous() {
+            if let Some(location) = ontology.location().cloned() {
+                ontology.with_anonymous_name(self.anonymous_ontology_name(&location)?);
+            }
+        }
+        ontology.with_traversal_links(&graph, &self.config.traversal_predicates());
+        // the ontology a mapped import is read from keeps the name of the import
+        if let Some(import) = self.import_mapped_to(ontology.id().location())? {
+            if ontology.name() != import {
+                debug!(ontology = %ontology.id(), import = %import, "Naming after mapped import");
+                ontology.with_name(import);
+            }
+        }
+        // an ontology which is read again, or which was removed in this update and whose file
+        // moved here, keeps its graph ID, and with it the name of its graph in the store
+        let existing_graph_id = self
+            .ontologies
+            .get(ontology.id())
+            .and_then(|existing| existing.id().graph_id())
+            .map(str::to_string);
+        let graph_id = existing_graph_id
+            .or_else(|| self.retired_graph_ids.remove(&ontology.name()))
+            .unwrap_or_else(GraphIdentifier::new_graph_id);
+        ontology.with_graph_id(graph_id);
+        if let Some(existing) = self.ontologies.get(ontology.id()) {
+            ontology.with_annotations(existing.annotations().clone());
+        }
+        if self.config.is_strict(StrictRule::Duplicates)
+            && self.config.duplicate_strategy != DuplicateStrategy::Merge
+            && !ontology.is_anonymous()
+        {
+            let duplicate = self
+                .ontologies
+                .values()
+                .find(|other| other.name() == ontology.name() && other.id() != ontology.id());
+            if let Some(duplicate) = duplicate {
+                return Err(anyhow::anyhow!(
+                    "Ontology {} at {} is already in the environment at {}",
+                    ontology.name(),
+                    ontology.id().location(),
+                    duplicate.id().location()
+                ));
+            }
+        }
+        self.inspect(&mut ontology, &graph)?;
+        ontology.with_last_updated(Utc::now());
+        ontology.with_namespace_map(document.prefixes);
+        ontology.with_base_iri(document.base_iri);
+        ontology.with_resolved_url(document.resolved_url);
+        ontology.with_cache_validators(document.validators);
+        ontology.with_content_hash(lockfile::content_hash(&graph));
+        let source_hash = match ontology.location() {
+            Some(OntologyLocation::File(path)) => Some(lockfile::source_hash(path)?),
+            _ => None,
+        };
+        ontology.with_source_hash(source_hash);
+        info!(
+            ontology = %ontology.id(),
+            triples = graph.len(),
+            elapsed_ms = start.elapsed().as_millis() as u64,
+            "Adding ontology"
+        );
+        let id = ontology.id().clone();
+        self.clear_memory_cache();
+        // remove first: inserting would keep the old key, along with its graph ID
+        let existed = self.ontologies.remove(&id).is_some();
+        self.ontologies.insert(id.clone(), ontology);
+
+        // if the graph is already in the store, remove it and add the new graph
+        let graphname: NamedOrBlankNode = match id.graphname()? {
             GraphName::NamedNode(n) => NamedOrBlankNode::NamedNode(n),
             _ => return Err(anyhow::anyhow!("Graph name not found")),
         };
 
+        self.journal(JournalEntry::Add {
+            graph: graph_iri(&id)?,
+        })?;
         if store.contains_named_graph(graphname.as_ref())? {
             store.remove_named_graph(graphname.as_ref())?;
         }
@@ -742,6 +3451,11 @@ impl OntoEnv {
         store
             .bulk_loader()
             .load_quads(util::graph_to_quads(&graph, graphname.as_ref().into()))?;
+        if let Some(ontology) = self.ontologies.get(&id) {
+            self.journal(JournalEntry::Added {
+                ontology: Box::new(ontology.clone()),
+            })?;
+        }
         //for triple in graph.into_iter() {
         //    let q: QuadRef = QuadRef::new(
         //        triple.subject,
@@ -752,45 +3466,480 @@ impl OntoEnv {
         //    store.insert(q)?;
         //}
 
+        for listener in &self.listeners {
+            if existed {
+                listener.on_update(&id);
+            } else {
+                listener.on_add(&id);
+            }
+        }
         Ok(id)
     }
 
-    /// Return a list of all graph identifiers in the environment
+    /// Return a list of all graph identifiers in the environment, sorted by name and location
     pub fn graph_ids(&self) -> Vec<GraphIdentifier> {
         self.ontologies.keys().cloned().collect()
     }
 
-    /// Return a list of all ontologies in the environment
-    pub fn ontologies(&self) -> &HashMap<GraphIdentifier, Ontology> {
+    /// Return all ontologies in the environment, sorted by name and location
+    pub fn ontologies(&self) -> &BTreeMap<GraphIdentifier, Ontology> {
         &self.ontologies
     }
 
     /// returns a list of all graphs in the environment that provide a definition
     /// for the given IRI (using owl:Ontology)
     pub fn get_graphs_by_name(&self, name: NamedNodeRef) -> Vec<GraphIdentifier> {
+        let name = iri::normalize_iri(name.as_str());
         let mut graphs = vec![];
         for ontology in self.ontologies.values() {
-            if ontology.name() == name {
+            if ontology.name().as_str() == name {
                 graphs.push(ontology.id().clone());
             }
         }
         graphs
     }
 
+    /// Returns the name of an ontology without an owl:Ontology declaration at the given location,
+    /// derived from its path relative to the search directory (or root) containing it
+    fn anonymous_ontology_name(&self, location: &OntologyLocation) -> Result<NamedNode> {
+        let mut directories = self.config.search_directories.clone();
+        directories.push(self.config.root.clone());
+        ontology::anonymous_ontology_name(location, &directories)
+    }
+
+    /// Returns the name of the graph in the store which holds the ontology with the given
+    /// identifier. Identifiers without a graph ID (e.g. built with GraphIdentifier::new) use the
+    /// graph ID of the matching ontology in the environment
+    fn store_graphname(&self, id: &GraphIdentifier) -> Result<GraphName> {
+        match self.ontologies.get(id) {
+            Some(ontology) => ontology.id().graphname(),
+            None => id.graphname(),
+        }
+    }
+
     /// Returns the graph for the given graph identifier
     pub fn get_graph(&self, id: &GraphIdentifier) -> Result<Graph> {
         let mut graph = Graph::new();
-        let name = id.graphname()?;
+        let name = self.store_graphname(id)?;
         let store = self.store();
         for quad in store.quads_for_pattern(None, None, None, Some(name.as_ref())) {
             graph.insert(quad?.as_ref());
         }
-        Ok(graph)
+        Ok(graph)
+    }
+
+    /// Returns the number of triples in the graph for the given graph identifier, counting them
+    /// in the store rather than copying the graph
+    pub fn graph_len(&self, id: &GraphIdentifier) -> Result<usize> {
+        let name = self.store_graphname(id)?;
+        let mut len = 0;
+        for quad in self
+            .store()
+            .quads_for_pattern(None, None, None, Some(name.as_ref()))
+        {
+            quad?;
+            len += 1;
+        }
+        Ok(len)
+    }
+
+    /// Returns the triples added and removed going from the graph of `old` to the graph of
+    /// `new`, e.g. two versions of an ontology in the environment (see `diff::GraphDiff`)
+    pub fn diff_graphs(&self, old: &GraphIdentifier, new: &GraphIdentifier) -> Result<GraphDiff> {
+        let old = self.get_graph(old)?;
+        Ok(GraphDiff::between(&old, &self.get_graph(new)?))
+    }
+
+    /// Like `diff_graphs`, but compares the graph in the environment with the document at the
+    /// location (e.g. the upstream release of the ontology) without adding it to the environment
+    pub fn diff_against(
+        &self,
+        id: &GraphIdentifier,
+        location: &OntologyLocation,
+    ) -> Result<GraphDiff> {
+        if location.is_url() && self.config.offline {
+            return Err(anyhow::anyhow!(
+                "Offline mode is enabled. Cannot fetch {}",
+                location.as_str()
+            ));
+        }
+        Ok(GraphDiff::between(&self.get_graph(id)?, &location.graph()?))
+    }
+
+    /// Returns the graphs for the given graph identifiers, in the same order, reading the store
+    /// once instead of looking up each graph. Faster than `get_graph` when fetching many graphs
+    /// (e.g. most of a large environment); identifiers without a graph in the store get an empty
+    /// graph, like with `get_graph`
+    pub fn get_graphs(&self, ids: &[GraphIdentifier]) -> Result<Vec<(GraphIdentifier, Graph)>> {
+        let mut graphs: Vec<(GraphIdentifier, Graph)> =
+            ids.iter().map(|id| (id.clone(), Graph::new())).collect();
+        // the same graph may be requested more than once
+        let mut indexes: HashMap<GraphName, Vec<usize>> = HashMap::new();
+        for (i, id) in ids.iter().enumerate() {
+            indexes
+                .entry(self.store_graphname(id)?)
+                .or_default()
+                .push(i);
+        }
+        for quad in self.store().iter() {
+            let quad = quad?;
+            if let Some(targets) = indexes.get(&quad.graph_name) {
+                for i in targets {
+                    graphs[*i].1.insert(quad.as_ref());
+                }
+            }
+        }
+        Ok(graphs)
+    }
+
+    /// Returns the graph for the given graph identifier as a dataset, with its triples in a
+    /// named graph named after the ontology rather than after its graph in the store, e.g. for
+    /// writing TriG or loading the graph into a quad store
+    pub fn get_named_graph(&self, id: &GraphIdentifier) -> Result<Dataset> {
+        let mut dataset = Dataset::new();
+        let name = self.store_graphname(id)?;
+        let graph_name = GraphName::NamedNode(id.name().into_owned());
+        let store = self.store();
+        for quad in store.quads_for_pattern(None, None, None, Some(name.as_ref())) {
+            let quad = quad?;
+            dataset.insert(QuadRef::new(
+                &quad.subject,
+                &quad.predicate,
+                &quad.object,
+                graph_name.as_ref(),
+            ));
+        }
+        Ok(dataset)
+    }
+
+    /// Returns the triples in the graph with the given identifier which match the pattern.
+    /// Reads directly from the store, so only the matching triples are materialized
+    pub fn triples_for_pattern(
+        &self,
+        id: &GraphIdentifier,
+        subject: Option<SubjectRef>,
+        predicate: Option<NamedNodeRef>,
+        object: Option<TermRef>,
+    ) -> Result<Vec<Triple>> {
+        self.triples_for_pattern_iter(id, subject, predicate, object)?
+            .collect()
+    }
+
+    /// Streams the triples of the graph which match the pattern (see `triples_for_pattern`)
+    /// without collecting them in memory. The iterator reads from a snapshot of the store, so
+    /// it does not borrow the environment
+    pub fn triples_for_pattern_iter(
+        &self,
+        id: &GraphIdentifier,
+        subject: Option<SubjectRef>,
+        predicate: Option<NamedNodeRef>,
+        object: Option<TermRef>,
+    ) -> Result<impl Iterator<Item = Result<Triple>>> {
+        let name = self.store_graphname(id)?;
+        Ok(self
+            .store()
+            .quads_for_pattern(subject, predicate, object, Some(name.as_ref()))
+            .map(|quad| -> Result<Triple> {
+                let quad = quad?;
+                Ok(Triple::new(quad.subject, quad.predicate, quad.object))
+            }))
+    }
+
+    /// Returns the owl:imports of all ontologies in the environment which cannot be resolved
+    /// to an ontology in the environment, sorted and without duplicates
+    pub fn missing_imports(&self) -> Vec<NamedNode> {
+        let mut missing: Vec<NamedNode> = self
+            .ontologies
+            .values()
+            .flat_map(|ontology| ontology.imports.iter())
+            .filter(|import| self.resolve_import((*import).into()).is_none())
+            .cloned()
+            .collect();
+        missing.sort();
+        missing.dedup();
+        missing
+    }
+
+    /// Returns the imports which cannot be resolved (see `missing_imports`) along with why
+    /// fetching them failed during the last updates, if it was attempted
+    pub fn missing_import_details(&self) -> Vec<MissingImport> {
+        self.missing_imports()
+            .into_iter()
+            .map(|import| MissingImport {
+                failure: self.failed_imports.get(import.as_str()).cloned(),
+                import: import.as_str().to_string(),
+            })
+            .collect()
+    }
+
+    /// Forgets the recorded import failures, so the next update fetches every missing import
+    /// again instead of waiting for its retry
+    pub fn clear_failed_imports(&mut self) {
+        self.failed_imports.clear();
+    }
+
+    /// Searches the environment for typed terms (subjects of rdf:type statements) whose IRI
+    /// contains the query, case-insensitively. Returns at most `limit` terms along with the
+    /// graph defining each of them
+    pub fn search_terms(
+        &self,
+        query: &str,
+        limit: usize,
+    ) -> Result<Vec<(NamedNode, GraphIdentifier)>> {
+        let query = query.to_lowercase();
+        let graphs = self.graphs_by_graphname();
+        let mut seen: HashSet<NamedNode> = HashSet::new();
+        let mut results = vec![];
+        let store = self.store();
+        for quad in store.quads_for_pattern(None, Some(TYPE), None, None) {
+            if results.len() >= limit {
+                break;
+            }
+            let quad = quad?;
+            let term = match quad.subject {
+                Subject::NamedNode(term) => term,
+                _ => continue,
+            };
+            if seen.contains(&term) || !term.as_str().to_lowercase().contains(&query) {
+                continue;
+            }
+            if let Some(id) = graphs.get(&quad.graph_name) {
+                seen.insert(term.clone());
+                results.push((term, (*id).clone()));
+            }
+        }
+        Ok(results)
+    }
+
+    /// Returns the identifiers of the ontologies by the name of their graph in the store
+    fn graphs_by_graphname(&self) -> HashMap<GraphName, &GraphIdentifier> {
+        self.ontologies
+            .values()
+            .filter_map(|ontology| {
+                let id = ontology.id();
+                id.graphname().ok().map(|name| (name, id))
+            })
+            .collect()
+    }
+
+    /// Returns an index from every class and property declared (with rdf:type) in the
+    /// environment to the ontologies declaring it
+    pub fn term_index(&self) -> Result<TermIndex> {
+        TermIndex::build(self)
+    }
+
+    /// Returns how many graphs and triples of the environment use the IRI as subject, predicate
+    /// or object, e.g. to see what renaming or deprecating a class or property would affect
+    pub fn term_usage(&self, term: NamedNodeRef) -> Result<TermUsage> {
+        terms::usage(self, term)
+    }
+
+    /// Returns the ontology which declares the term as a class or property. If several
+    /// ontologies declare it, see `preferred_declaration`
+    pub fn defining_ontology(&self, term: NamedNodeRef) -> Option<GraphIdentifier> {
+        let mut candidates: Vec<GraphIdentifier> = terms::declarations(self, Some(term))
+            .ok()?
+            .into_iter()
+            .map(|(_, id)| id)
+            .collect();
+        candidates.sort();
+        candidates.dedup();
+        self.preferred_declaration(term, &candidates)
+    }
+
+    /// Returns the file backing an IRI, for editor jump-to-definition: the file of the ontology
+    /// the IRI names or, if it names no ontology, of the ontology defining it as a term (see
+    /// `defining_ontology`). For local files, also returns where the IRI is declared in the
+    /// file. Returns None if neither an ontology nor a term has the IRI
+    pub fn which_file(&self, iri: NamedNodeRef) -> Result<Option<SourceLocation>> {
+        let ontology = match self.resolve_import(iri) {
+            Some(ontology) => ontology,
+            None => match self
+                .defining_ontology(iri)
+                .and_then(|id| self.ontologies.get(&id))
+            {
+                Some(ontology) => ontology,
+                None => return Ok(None),
+            },
+        };
+        let location = ontology.id().location().clone();
+        let file = match &location {
+            OntologyLocation::File(path) => Some(path.clone()),
+            OntologyLocation::Url(_) => None,
+        };
+        let found = match &file {
+            Some(path) => {
+                let text = fs::read_to_string(path)
+                    .map_err(|e| anyhow::anyhow!("Could not read {}: {}", path.display(), e))?;
+                util::find_iri_in_text(&text, iri.as_str(), ontology.namespace_map())
+            }
+            None => None,
+        };
+        Ok(Some(SourceLocation {
+            ontology: ontology.id().clone(),
+            location,
+            file,
+            line: found.map(|(line, _)| line),
+            offset: found.map(|(_, offset)| offset),
+        }))
+    }
+
+    /// Picks the ontology defining a term among the ontologies declaring it: preferably one
+    /// whose name the term starts with (e.g. Brick for brick:AHU, rather than an extension
+    /// which redeclares it), and among the versions of that ontology the one imports resolve to
+    pub fn preferred_declaration(
+        &self,
+        term: NamedNodeRef,
+        candidates: &[GraphIdentifier],
+    ) -> Option<GraphIdentifier> {
+        let candidate = candidates
+            .iter()
+            .find(|id| {
+                let namespace = id.name().as_str().trim_end_matches(['#', '/']);
+                term.as_str()
+                    .strip_prefix(namespace)
+                    .is_some_and(|rest| rest.starts_with(['#', '/']))
+            })
+            .or(candidates.first())?;
+        match self.resolve_import(candidate.name()) {
+            Some(resolved) if candidates.contains(resolved.id()) => Some(resolved.id().clone()),
+            _ => Some(candidate.clone()),
+        }
+    }
+
+    /// Suggests the ontologies in the environment a graph (e.g. a model) should import: those
+    /// defining classes and properties the graph uses which are not in the owl:imports closure
+    /// of the ontology it declares. An ontology which another suggestion imports is left out
+    pub fn suggest_imports(&self, graph: &Graph) -> Result<Vec<NamedNode>> {
+        Ok(terms::import_usage(self, graph)?.missing)
+    }
+
+    /// Returns the owl:imports of a graph whose closures contain none of the ontologies
+    /// defining the classes and properties the graph uses
+    pub fn unused_imports(&self, graph: &Graph) -> Result<Vec<NamedNode>> {
+        Ok(terms::import_usage(self, graph)?.unused)
+    }
+
+    /// Returns the namespace bound to the given prefix by the ontologies in the environment.
+    /// If several ontologies bind the prefix, the first ontology by name wins
+    pub fn namespace_for_prefix(&self, prefix: &str) -> Option<String> {
+        let mut ontologies: Vec<&Ontology> = self.ontologies.values().collect();
+        ontologies.sort_by_key(|o| o.name());
+        ontologies
+            .iter()
+            .find_map(|o| o.namespace_map().get(prefix).cloned())
+    }
+
+    /// Returns how often the ontologies in the environment bind each namespace to each prefix
+    pub fn prefix_usage(&self) -> PrefixUsage {
+        PrefixUsage::from_ontologies(self.ontologies.values())
+    }
+
+    /// Proposes prefix bindings for the IRIs in the graph, using the prefixes the ontologies in
+    /// the environment most often bind their namespaces to
+    pub fn suggest_prefixes(&self, graph: &Graph) -> Vec<PrefixSuggestion> {
+        self.prefix_usage().suggest(graph)
+    }
+
+    /// Generates a JSON-LD context for the closure (see `jsonld::context`): the prefixes its
+    /// ontologies declare (see `closure_prefixes`) and a term for each class and property it
+    /// declares
+    pub fn jsonld_context(&self, closure: &[GraphIdentifier]) -> Result<serde_json::Value> {
+        let (union, _, _) = self.get_union_graph(closure, Some(false), Some(false))?;
+        let graph = util::dataset_to_graph(&union);
+        Ok(jsonld::context(&self.closure_prefixes(closure), &graph))
+    }
+
+    /// Returns the environment-wide prefix registry: one prefix per namespace, merged from the
+    /// bindings of all ontologies (see `PrefixRegistry` for how conflicts are resolved)
+    pub fn prefixes(&self) -> PrefixRegistry {
+        PrefixRegistry::from_usage(&self.prefix_usage())
+    }
+
+    /// Expands a CURIE such as brick:Sensor to an IRI using the prefix registry
+    pub fn expand(&self, curie: &str) -> Result<NamedNode> {
+        let iri = self
+            .prefixes()
+            .expand(curie)
+            .ok_or_else(|| anyhow::anyhow!("Unknown prefix in {}", curie))?;
+        Ok(NamedNode::new(iri)?)
+    }
+
+    /// Shrinks an IRI to a CURIE using the prefix registry, if a namespace matches it
+    pub fn shrink(&self, iri: NamedNodeRef) -> Option<String> {
+        self.prefixes().shrink(iri.as_str())
+    }
+
+    /// Merges the prefix declarations of the source documents of the given ontologies, e.g. of
+    /// a closure, so the closure can be written with the prefixes its authors chose. Ontologies
+    /// earlier in the list take precedence: a prefix keeps the first namespace it is bound to,
+    /// and a namespace the first prefix bound to it
+    pub fn closure_prefixes(&self, ids: &[GraphIdentifier]) -> BTreeMap<String, String> {
+        let mut prefixes = BTreeMap::new();
+        let mut namespaces = HashSet::new();
+        for ontology in ids.iter().filter_map(|id| self.ontologies.get(id)) {
+            for (prefix, namespace) in ontology.namespace_map() {
+                if prefixes.contains_key(prefix) || namespaces.contains(namespace) {
+                    continue;
+                }
+                namespaces.insert(namespace.clone());
+                prefixes.insert(prefix.clone(), namespace.clone());
+            }
+        }
+        prefixes
+    }
+
+    /// Returns a fingerprint of the ontologies in the environment: their names, locations,
+    /// graph IDs and content hashes. It changes whenever an ontology is added, removed or read
+    /// again with different content
+    pub fn fingerprint(&self) -> String {
+        cache::closure_fingerprint(self.ontologies.values())
+    }
+
+    /// Records how the closure was resolved: the location, version IRI and content hash of
+    /// each of its ontologies, the imports of its ontologies which do not resolve, and the
+    /// fingerprint of the environment. The first ontology of the closure is its root
+    pub fn resolution_report(
+        &self,
+        closure: &[GraphIdentifier],
+    ) -> Result<resolution::ResolutionReport> {
+        let root = closure
+            .first()
+            .ok_or(anyhow::anyhow!("The closure is empty"))?;
+        let mut ontologies = vec![];
+        let mut unresolved = vec![];
+        for id in closure {
+            let ontology = self
+                .ontologies
+                .get(id)
+                .ok_or(anyhow::anyhow!("Ontology {} not found", id))?;
+            ontologies.push(resolution::ResolvedOntology {
+                name: id.name().as_str().to_string(),
+                location: ontology.location().map(|l| l.to_string()),
+                version_iri: ontology.version_iri().map(|v| v.as_str().to_string()),
+                content_hash: ontology.content_hash().map(String::from),
+            });
+            for import in &ontology.imports {
+                if self.resolve_import_of(id, import.as_ref()).is_none() {
+                    unresolved.push(resolution::UnresolvedImport {
+                        importer: id.name().as_str().to_string(),
+                        import: import.as_str().to_string(),
+                    });
+                }
+            }
+        }
+        Ok(resolution::ResolutionReport {
+            root: root.name().as_str().to_string(),
+            generated: Utc::now(),
+            environment_fingerprint: self.fingerprint(),
+            ontologies,
+            unresolved,
+        })
     }
 
     /// Returns a table of metadata for the given graph
-    pub fn graph_metadata(&self, id: &GraphIdentifier) -> HashMap<String, String> {
-        let mut metadata = HashMap::new();
+    pub fn graph_metadata(&self, id: &GraphIdentifier) -> BTreeMap<String, String> {
+        let mut metadata = BTreeMap::new();
         if let Some(ontology) = self.ontologies.get(id) {
             metadata.insert("name".to_string(), ontology.name().to_string());
             metadata.insert(
@@ -802,6 +3951,22 @@ impl OntoEnv {
             if let Some(last_updated) = ontology.last_updated {
                 metadata.insert("last_updated".to_string(), last_updated.to_string());
             }
+            if let Some(base_iri) = ontology.base_iri() {
+                metadata.insert("base_iri".to_string(), base_iri.to_string());
+            }
+            if let Some(resolved_url) = ontology.resolved_url() {
+                metadata.insert("resolved_url".to_string(), resolved_url.to_string());
+            }
+            if ontology.is_anonymous() {
+                metadata.insert("anonymous".to_string(), "true".to_string());
+            }
+            for (inspector, value) in ontology.metadata() {
+                let value = match value {
+                    serde_json::Value::String(s) => s.clone(),
+                    value => value.to_string(),
+                };
+                metadata.insert(inspector.clone(), value);
+            }
             // add all metadata from the graph ontology object
             for (key, value) in ontology.version_properties().iter() {
                 metadata.insert(key.to_string(), value.to_string());
@@ -810,43 +3975,304 @@ impl OntoEnv {
         metadata
     }
 
-    /// Returns the names of all graphs within the dependency closure of the provided graph
+    /// Returns the imports of the ontology with the optional import groups (see
+    /// `config::OptionalImports`) for the given features. Imports in the ontology's other groups
+    /// are left out; with None, all of its groups are included
+    fn imports_of(&self, ontology: &Ontology, features: Option<&[&str]>) -> Vec<NamedNode> {
+        let name = iri::normalize_iri(ontology.name().as_str());
+        let mut included: Vec<NamedNode> = vec![];
+        let mut excluded: HashSet<String> = HashSet::new();
+        for group in &self.config.optional_imports {
+            if iri::normalize_iri(&group.ontology) != name {
+                continue;
+            }
+            let enabled = match features {
+                Some(features) => features.contains(&group.feature.as_str()),
+                None => true,
+            };
+            for import in &group.imports {
+                if !enabled {
+                    excluded.insert(iri::normalize_iri(import));
+                } else if let Ok(import) = NamedNode::new(import) {
+                    included.push(import);
+                } else {
+                    warn!(ontology = %name, "Invalid optional import: {}", import);
+                }
+            }
+        }
+        let mut imports: Vec<NamedNode> = ontology
+            .imports
+            .iter()
+            .filter(|import| !excluded.contains(&iri::normalize_iri(import.as_str())))
+            .cloned()
+            .collect();
+        for import in included {
+            if !imports.contains(&import) {
+                imports.push(import);
+            }
+        }
+        imports
+    }
+
+    /// Returns the names of all graphs within the dependency closure of the provided graph.
+    /// The provided graph comes first, followed by its dependencies sorted by name and location.
+    /// Optional imports (see `config::OptionalImports`) are left out; use
+    /// `get_closure_with_features` to include them
     pub fn get_dependency_closure(&self, id: &GraphIdentifier) -> Result<Vec<GraphIdentifier>> {
-        let mut closure: HashSet<GraphIdentifier> = HashSet::new();
-        let mut stack: VecDeque<GraphIdentifier> = VecDeque::new();
+        self.get_closure_with_features(id, &[])
+    }
+
+    /// Like `get_dependency_closure`, but includes the optional imports of the ontologies in the
+    /// closure which belong to one of the given features, e.g. `&["inference"]`
+    pub fn get_closure_with_features(
+        &self,
+        id: &GraphIdentifier,
+        features: &[&str],
+    ) -> Result<Vec<GraphIdentifier>> {
+        self.get_closure_excluding(id, features, &[])
+    }
+
+    /// Like `get_closure_with_features`, but the imports of the ontologies named in `cut_points`
+    /// are not followed: a cut point is included in the closure when it is imported, but its own
+    /// imports are not (unless another ontology of the closure imports them too). E.g. cutting at
+    /// QUDT includes Brick and QUDT but not the vocabularies QUDT imports
+    pub fn get_closure_excluding(
+        &self,
+        id: &GraphIdentifier,
+        features: &[&str],
+        cut_points: &[NamedNodeRef],
+    ) -> Result<Vec<GraphIdentifier>> {
+        self.get_closure_following(id, features, cut_points, &[])
+    }
+
+    /// Like `get_closure_excluding`, but also follows the given predicates (e.g. rdfs:seeAlso or
+    /// owl:priorVersion) from every ontology of the closure, in addition to owl:imports and the
+    /// environment's `traversal_predicates`. Targets of these predicates which are not in the
+    /// environment are skipped with a warning, even in strict mode
+    #[instrument(name = "closure", skip_all, fields(iri = %id.name()))]
+    pub fn get_closure_following(
+        &self,
+        id: &GraphIdentifier,
+        features: &[&str],
+        cut_points: &[NamedNodeRef],
+        predicates: &[NamedNodeRef],
+    ) -> Result<Vec<GraphIdentifier>> {
+        let start = Instant::now();
+        let key = format!(
+            "{}\n{:?}\n{:?}\n{:?}",
+            self.memory_cache_key(std::slice::from_ref(id)),
+            features,
+            cut_points,
+            predicates
+        );
+        if let Some(closure) = self.memory_cache.lock().ok().and_then(|c| c.closure(&key)) {
+            self.count(Counter::CacheHits);
+            return Ok(closure);
+        }
+        // the original graph goes first, the others are sorted
+        let mut closure: Vec<GraphIdentifier> = self
+            .closure_walk(id, features, cut_points, predicates)?
+            .into_iter()
+            .map(|node| node.id)
+            .filter(|member| member != id)
+            .collect();
+        closure.sort();
+        closure.insert(0, id.clone());
+        info!(size = closure.len(), "Computed dependency closure");
+        self.record_duration(Timer::Closure, start);
+        if let Ok(mut cache) = self.memory_cache.lock() {
+            cache.put_closure(key, closure.clone());
+        }
+        Ok(closure)
+    }
+
+    /// Returns the dependency closure of the ontology like `get_dependency_closure`, with how
+    /// each ontology entered it: its depth (0 for the ontology itself, 1 for its direct imports)
+    /// and the ontology of the closure importing it along a shortest import chain. Ontologies
+    /// are in breadth-first order, so depths never decrease
+    pub fn get_closure_nodes(&self, id: &GraphIdentifier) -> Result<Vec<ClosureNode>> {
+        self.closure_walk(id, &[], &[], &[])
+    }
+
+    // breadth-first walk of the imports (and links over the predicates) from the ontology,
+    // recording where each ontology of the closure was first reached from
+    fn closure_walk(
+        &self,
+        id: &GraphIdentifier,
+        features: &[&str],
+        cut_points: &[NamedNodeRef],
+        predicates: &[NamedNodeRef],
+    ) -> Result<Vec<ClosureNode>> {
+        let mut nodes: Vec<ClosureNode> = vec![];
+        let mut visited: HashSet<GraphIdentifier> = HashSet::new();
+        let mut stack: VecDeque<ClosureNode> = VecDeque::new();
 
         // TODO: how to handle a graph which is not in the environment?
 
-        stack.push_back(id.clone());
-        while let Some(graph) = stack.pop_front() {
-            closure.insert(graph.clone());
+        stack.extend(self.merge_parts(id).into_iter().map(|part| ClosureNode {
+            id: part,
+            depth: 0,
+            parent: None,
+        }));
+        while let Some(node) = stack.pop_front() {
+            self.check_cancelled()?;
+            // an ontology imported by several others can be queued more than once; the first
+            // time is along a shortest chain
+            if !visited.insert(node.id.clone()) {
+                continue;
+            }
+            self.report_progress(
+                Phase::Closure,
+                &node.id,
+                visited.len(),
+                visited.len() + stack.len(),
+            );
+            let graph = node.id.clone();
+            let depth = node.depth + 1;
+            nodes.push(node);
             let ontology = self
                 .ontologies
                 .get(&graph)
                 .ok_or(anyhow::anyhow!("Ontology not found"))?;
-            for import in &ontology.imports {
+            if cut_points.contains(&ontology.name().as_ref()) {
+                debug!(ontology = %ontology.name(), "Not following the imports of a cut point");
+                continue;
+            }
+            let mut targets: Vec<GraphIdentifier> = vec![];
+            for import in &self.imports_of(ontology, Some(features)) {
                 // get graph identifier for import
-                let import = match self.get_ontology_by_name(import.into()) {
-                    Some(imp) => imp.id().clone(),
+                match self.resolve_import_of(&graph, import.into()) {
+                    Some(imp) => targets.push(imp.id().clone()),
                     None => {
-                        if self.config.strict {
+                        if self.config.is_strict(StrictRule::MissingImports) {
                             return Err(anyhow::anyhow!("Import not found: {}", import));
                         }
-                        warn!("Import not found: {}", import);
-                        continue;
+                        warn!(import = %import, "Import not found");
                     }
                 };
-                if !closure.contains(&import) {
-                    stack.push_back(import);
+            }
+            for target in self.traversal_links(&graph, predicates)? {
+                match self.resolve_import_of(&graph, target.as_ref()) {
+                    Some(linked) => targets.push(linked.id().clone()),
+                    None => warn!(target = %target, "Linked ontology not found"),
+                }
+            }
+            for target in targets {
+                for part in self.merge_parts(&target) {
+                    if !visited.contains(&part) {
+                        stack.push_back(ClosureNode {
+                            id: part,
+                            depth,
+                            parent: Some(graph.clone()),
+                        });
+                    }
                 }
             }
         }
-        // remove the original graph from the closure
-        closure.remove(id);
-        let mut closure: Vec<GraphIdentifier> = closure.into_iter().collect();
-        closure.insert(0, id.clone());
-        info!("Dependency closure for {:?}: {:?}", id, closure.len());
-        Ok(closure)
+        Ok(nodes)
+    }
+
+    /// Returns the dependency closure of the ontology (see `get_dependency_closure`) in load
+    /// order, e.g. for loading it into a reasoner: every ontology comes after the ontologies it
+    /// imports. This is `closure_layers`, flattened
+    pub fn closure_topological_order(&self, id: &GraphIdentifier) -> Result<Vec<GraphIdentifier>> {
+        Ok(self.closure_layers(id)?.into_iter().flatten().collect())
+    }
+
+    // the objects of the predicates on the ontology in its graph in the store
+    fn traversal_links(
+        &self,
+        id: &GraphIdentifier,
+        predicates: &[NamedNodeRef],
+    ) -> Result<Vec<NamedNode>> {
+        if predicates.is_empty() {
+            return Ok(vec![]);
+        }
+        let store = self.store();
+        let graphname = self.store_graphname(id)?;
+        let mut links = vec![];
+        for predicate in predicates {
+            for quad in store.quads_for_pattern(
+                Some(id.name().into()),
+                Some(*predicate),
+                None,
+                Some(graphname.as_ref()),
+            ) {
+                if let Term::NamedNode(target) = quad?.object {
+                    if target.as_ref() != id.name() && !links.contains(&target) {
+                        links.push(target);
+                    }
+                }
+            }
+        }
+        Ok(links)
+    }
+
+    /// Returns the dependency closure of the ontology in layers of independent ontologies: the
+    /// first layer holds the ontologies which import nothing else in the closure, and every
+    /// other layer the ontologies whose imports are all in earlier layers. Each layer is sorted
+    /// by name and location, so the order is deterministic. Ontologies which import each other
+    /// (directly or transitively) cannot be ordered; such a cycle is logged and its ontologies are
+    /// placed together in the first layer after everything the cycle imports
+    pub fn closure_layers(&self, id: &GraphIdentifier) -> Result<Vec<Vec<GraphIdentifier>>> {
+        let closure = self.get_dependency_closure(id)?;
+        let mut graph: DiGraph<GraphIdentifier, ()> = DiGraph::new();
+        let indexes: HashMap<&GraphIdentifier, NodeIndex> = closure
+            .iter()
+            .map(|id| (id, graph.add_node(id.clone())))
+            .collect();
+        for (member, index) in &indexes {
+            let ontology = self
+                .ontologies
+                .get(*member)
+                .ok_or(anyhow::anyhow!("Ontology {} not found", member))?;
+            for import in self.imports_of(ontology, Some(&[])) {
+                let imported = self
+                    .resolve_import(import.as_ref())
+                    .and_then(|imported| indexes.get(imported.id()));
+                if let Some(imported) = imported {
+                    graph.update_edge(*index, *imported, ());
+                }
+            }
+        }
+
+        // Tarjan's algorithm returns the strongly connected components (single ontologies or
+        // import cycles) with the components an edge points to before the component it leaves
+        // from, i.e. imported ontologies first
+        let components = petgraph::algo::tarjan_scc(&graph);
+        let mut component_layer: HashMap<NodeIndex, usize> = HashMap::new();
+        let mut layers: Vec<Vec<GraphIdentifier>> = vec![];
+        for component in components {
+            let layer = component
+                .iter()
+                .flat_map(|index| graph.neighbors(*index))
+                .filter(|imported| !component.contains(imported))
+                .map(|imported| component_layer[&imported] + 1)
+                .max()
+                .unwrap_or(0);
+            if component.len() > 1 || graph.contains_edge(component[0], component[0]) {
+                let mut names: Vec<String> = component
+                    .iter()
+                    .map(|index| graph[*index].name().to_string())
+                    .collect();
+                names.sort();
+                warn!(
+                    ontologies = ?names,
+                    "Import cycle in the closure; loading its ontologies together"
+                );
+            }
+            for index in &component {
+                component_layer.insert(*index, layer);
+            }
+            if layers.len() <= layer {
+                layers.resize(layer + 1, vec![]);
+            }
+            layers[layer].extend(component.iter().map(|index| graph[*index].clone()));
+        }
+        for layer in &mut layers {
+            layer.sort();
+        }
+        Ok(layers)
     }
 
     /// Returns a graph containing the union of all graphs_ids, along with a list of
@@ -857,14 +4283,363 @@ impl OntoEnv {
         rewrite_sh_prefixes: Option<bool>,
         remove_owl_imports: Option<bool>,
     ) -> Result<(Dataset, Vec<GraphIdentifier>, Option<Vec<FailedImport>>)> {
+        let (union, successful_imports, failed_imports, _) =
+            self.union_graph(graph_ids, rewrite_sh_prefixes, remove_owl_imports, false)?;
+        Ok((union, successful_imports, failed_imports))
+    }
+
+    /// Like `get_union_graph`, but a triple which occurs in several graphs is only kept in the
+    /// first graph (in the order of graph_ids) which contains it. Also returns the number of
+    /// duplicate triples which were dropped
+    pub fn get_deduplicated_union_graph(
+        &self,
+        graph_ids: &[GraphIdentifier],
+        rewrite_sh_prefixes: Option<bool>,
+        remove_owl_imports: Option<bool>,
+    ) -> Result<(
+        Dataset,
+        Vec<GraphIdentifier>,
+        Option<Vec<FailedImport>>,
+        usize,
+    )> {
+        self.union_graph(graph_ids, rewrite_sh_prefixes, remove_owl_imports, true)
+    }
+
+    /// Like `get_union_graph`, but keeps each graph of the closure as its own named graph in the
+    /// returned dataset instead of flattening them, for consumers which care about graph
+    /// boundaries (provenance, per-graph SHACL validation). Each graph is named after its
+    /// ontology, or after its location if an earlier graph already has the ontology's name
+    pub fn get_union_dataset(
+        &self,
+        graph_ids: &[GraphIdentifier],
+        rewrite_sh_prefixes: Option<bool>,
+        remove_owl_imports: Option<bool>,
+    ) -> Result<(Dataset, Vec<GraphIdentifier>, Option<Vec<FailedImport>>)> {
+        let (union, successful_imports, failed_imports) =
+            self.get_union_graph(graph_ids, rewrite_sh_prefixes, remove_owl_imports)?;
+        // the quads of the union are in the graphs of the store, which are named by graph ID
+        let mut names: HashMap<GraphName, GraphName> = HashMap::new();
+        for id in &successful_imports {
+            let mut name: GraphName = id.name().into_owned().into();
+            if names.values().any(|n| n == &name) {
+                name = id.location().to_iri()?.into();
+            }
+            names.insert(self.store_graphname(id)?, name);
+        }
+        let mut dataset = Dataset::new();
+        for quad in union.iter() {
+            let graph_name = quad.graph_name.into_owned();
+            let graph_name = names.get(&graph_name).unwrap_or(&graph_name);
+            dataset.insert(QuadRef::new(
+                quad.subject,
+                quad.predicate,
+                quad.object,
+                graph_name.as_ref(),
+            ));
+        }
+        Ok((dataset, successful_imports, failed_imports))
+    }
+
+    /// Like `get_union_graph` (or `get_deduplicated_union_graph` with `deduplicate`), but the
+    /// union is cached in `.ontoenv/cache/closures` and reused until the content of any ontology
+    /// of the closure changes. Unions with failed imports are not cached. Also returns whether
+    /// the union came from the cache
+    pub fn get_cached_union_graph(
+        &self,
+        graph_ids: &[GraphIdentifier],
+        rewrite_sh_prefixes: Option<bool>,
+        remove_owl_imports: Option<bool>,
+        deduplicate: bool,
+    ) -> Result<(Dataset, Option<Vec<FailedImport>>, bool)> {
+        let rewrite_sh_prefixes = rewrite_sh_prefixes.unwrap_or(true);
+        let remove_owl_imports = remove_owl_imports.unwrap_or(true);
+        let mut key = format!(
+            "rewrite_sh_prefixes={} remove_owl_imports={} deduplicate={}",
+            rewrite_sh_prefixes, remove_owl_imports, deduplicate
+        );
+        for id in graph_ids {
+            key.push_str(&format!("\n{} {}", id.name(), id.location()));
+        }
+        let ontologies = graph_ids
+            .iter()
+            .map(|id| {
+                self.ontologies
+                    .get(id)
+                    .ok_or(anyhow::anyhow!("Ontology {} not found", id))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let fingerprint = cache::closure_fingerprint(ontologies);
+        // a merged view's graph IDs are not those of the environment at its root, and a
+        // temporary environment has no directory
+        let cache = (!self.merged_view && !self.temporary)
+            .then(|| cache::ClosureCache::in_dir(&self.ontoenv_dir()));
+
+        if let Some(cache) = &cache {
+            match cache.get(&key, &fingerprint) {
+                Ok(Some(union)) => {
+                    info!("Using the cached union of {} graphs", graph_ids.len());
+                    return Ok((union, None, true));
+                }
+                Ok(None) => {}
+                Err(e) => warn!("Failed to read cached union: {}", e),
+            }
+        }
+        let (union, _, failed_imports, _) = self.union_graph(
+            graph_ids,
+            Some(rewrite_sh_prefixes),
+            Some(remove_owl_imports),
+            deduplicate,
+        )?;
+        if let (Some(cache), None) = (&cache, &failed_imports) {
+            let max_size = self.config.limits.max_cache_size;
+            if let Err(e) = cache.put(&key, &fingerprint, &union, max_size) {
+                warn!("Failed to cache union: {}", e);
+            }
+        }
+        Ok((union, failed_imports, false))
+    }
+
+    /// Attaches user metadata to an ontology, e.g. `owner=controls-team` or `status=deprecated`,
+    /// replacing any earlier value of the key. Annotations are saved with the environment and
+    /// kept when the ontology is read again
+    pub fn set_annotation(&mut self, id: &GraphIdentifier, key: &str, value: &str) -> Result<()> {
+        if key.is_empty() {
+            return Err(anyhow::anyhow!("Annotation keys cannot be empty"));
+        }
+        self.ontologies
+            .get_mut(id)
+            .ok_or(anyhow::anyhow!("Ontology {} not found", id))?
+            .with_annotation(key, value);
+        Ok(())
+    }
+
+    /// Removes an annotation from an ontology, returning its value if it was set
+    pub fn remove_annotation(&mut self, id: &GraphIdentifier, key: &str) -> Result<Option<String>> {
+        Ok(self
+            .ontologies
+            .get_mut(id)
+            .ok_or(anyhow::anyhow!("Ontology {} not found", id))?
+            .remove_annotation(key))
+    }
+
+    /// Returns the annotations of an ontology, if it is in the environment
+    pub fn annotations(&self, id: &GraphIdentifier) -> Option<&BTreeMap<String, String>> {
+        self.ontologies.get(id).map(Ontology::annotations)
+    }
+
+    /// Returns the ontologies with the annotation, optionally only those where it has the given
+    /// value, sorted by name and location
+    pub fn ontologies_with_annotation(
+        &self,
+        key: &str,
+        value: Option<&str>,
+    ) -> Vec<GraphIdentifier> {
+        let mut ids: Vec<GraphIdentifier> = self
+            .ontologies
+            .values()
+            .filter(|ontology| match ontology.annotations().get(key) {
+                Some(v) => value.map_or(true, |value| v == value),
+                None => false,
+            })
+            .map(|ontology| ontology.id().clone())
+            .collect();
+        ids.sort();
+        ids
+    }
+
+    /// Groups the ontologies by where they come from: the directory of their file, or the origin
+    /// of their URL (e.g. `https://brickschema.org`). Both the sources and the ontologies of
+    /// each are sorted
+    pub fn ontologies_by_source(&self) -> BTreeMap<String, Vec<GraphIdentifier>> {
+        let mut sources: BTreeMap<String, Vec<GraphIdentifier>> = BTreeMap::new();
+        for id in self.ontologies.keys() {
+            let source = match id.location() {
+                OntologyLocation::File(path) => path
+                    .parent()
+                    .map(|dir| dir.display().to_string())
+                    .unwrap_or_default(),
+                OntologyLocation::Url(address) => match url::Url::parse(address) {
+                    Ok(parsed) => parsed.origin().ascii_serialization(),
+                    Err(_) => address.clone(),
+                },
+            };
+            sources.entry(source).or_default().push(id.clone());
+        }
+        sources
+    }
+
+    /// Returns the ontology names which several locations declare, sorted by name, with the
+    /// ontology the resolution policy picks for each
+    pub fn duplicate_names(&self) -> Result<Vec<DuplicateName>> {
+        let mut names: BTreeMap<String, Vec<&Ontology>> = BTreeMap::new();
+        for ontology in self.visible_ontologies() {
+            names
+                .entry(ontology.name().as_str().to_string())
+                .or_default()
+                .push(ontology);
+        }
+        let policy = policy::policy_from_name(self.resolution_policy_name())
+            .unwrap_or_else(|| Box::new(policy::DefaultPolicy));
+        let mut duplicates = vec![];
+        for (name, candidates) in names {
+            if candidates.len() < 2 {
+                continue;
+            }
+            let selected = policy.resolve(&name, &candidates).ok_or(anyhow::anyhow!(
+                "The resolution policy picked no ontology named {}",
+                name
+            ))?;
+            let mut ids: Vec<GraphIdentifier> = candidates.iter().map(|o| o.id().clone()).collect();
+            ids.sort_by(|a, b| a.location().as_str().cmp(b.location().as_str()));
+            duplicates.push(DuplicateName {
+                name,
+                selected: selected.id().clone(),
+                candidates: ids,
+            });
+        }
+        Ok(duplicates)
+    }
+
+    /// Returns the ontologies nothing uses, sorted: with roots configured (see `Config::roots`
+    /// and `restrict_to_root`), those outside the imports closures of all roots; otherwise
+    /// those no other ontology imports. Roots are never orphans
+    pub fn orphans(&self) -> Result<Vec<GraphIdentifier>> {
+        let mut roots: Vec<GraphIdentifier> = vec![];
+        for name in &self.config.roots {
+            let node = NamedNode::new(name.as_str())?;
+            match self.get_ontology_by_name(node.as_ref()) {
+                Some(ontology) => roots.push(ontology.id().clone()),
+                None => warn!("Root ontology {} is not in the environment", name),
+            }
+        }
+        if let Some(location) = &self.config.root_ontology {
+            if let Some(ontology) = self.get_ontology_by_location(location) {
+                roots.push(ontology.id().clone());
+            }
+        }
+        let mut orphans: Vec<GraphIdentifier> = if roots.is_empty() {
+            let mut orphans = vec![];
+            for ontology in self.visible_ontologies() {
+                if self.get_dependents(&ontology.name())?.is_empty() {
+                    orphans.push(ontology.id().clone());
+                }
+            }
+            orphans
+        } else {
+            let mut reachable: HashSet<GraphIdentifier> = HashSet::new();
+            for root in &roots {
+                reachable.extend(self.get_dependency_closure(root)?);
+            }
+            self.visible_ontologies()
+                .map(|ontology| ontology.id().clone())
+                .filter(|id| !reachable.contains(id))
+                .collect()
+        };
+        orphans.sort();
+        Ok(orphans)
+    }
+
+    /// Removes the cached unions of closures (see `get_cached_union_graph`), returning how many
+    /// there were
+    pub fn clear_closure_cache(&self) -> Result<usize> {
+        cache::ClosureCache::in_dir(&self.ontoenv_dir()).clear()
+    }
+
+    /// Streams the quads of the union of the graphs, rewritten like those of `get_union_graph`,
+    /// without collecting them in memory. Graphs which are not in the store are skipped with a
+    /// warning. Unlike `get_union_graph`, a quad may be returned twice if rewriting sh:prefixes
+    /// makes two quads of a graph equal
+    pub fn union_graph_iter(
+        &self,
+        graph_ids: &[GraphIdentifier],
+        rewrite_sh_prefixes: Option<bool>,
+        remove_owl_imports: Option<bool>,
+    ) -> Result<impl Iterator<Item = Result<Quad>>> {
+        let first_id = graph_ids
+            .first()
+            .ok_or(anyhow::anyhow!("No graphs found"))?;
+        let rewriter = transform::UnionRewriter::new(
+            first_id.name().into_owned(),
+            graph_ids.iter().map(|id| id.name().into_owned()),
+            rewrite_sh_prefixes.unwrap_or(true),
+            remove_owl_imports.unwrap_or(true),
+        );
+        let store = self.store();
+        // the graphs to stream, with the ontology declaration of each, which is left out
+        let mut graphs: Vec<(GraphName, Option<Quad>)> = vec![];
+        for id in graph_ids {
+            let name = self.store_graphname(id)?;
+            let GraphName::NamedNode(graphname) = &name else {
+                continue;
+            };
+            if !store.contains_named_graph(graphname.as_ref())? {
+                warn!("Graph not found for {}, leaving it out of the union", id);
+                continue;
+            }
+            let declaration = store
+                .quads_for_pattern(None, Some(TYPE), Some(ONTOLOGY.into()), Some(name.as_ref()))
+                .next()
+                .transpose()?;
+            graphs.push((name, declaration));
+        }
+        Ok(graphs.into_iter().flat_map(move |(name, declaration)| {
+            let rewriter = rewriter.clone();
+            store
+                .quads_for_pattern(None, None, None, Some(name.as_ref()))
+                .filter_map(move |quad| match quad {
+                    Ok(quad) if Some(&quad) == declaration.as_ref() => None,
+                    Ok(quad) => rewriter.rewrite(quad).map(Ok),
+                    Err(e) => Some(Err(e.into())),
+                })
+        }))
+    }
+
+    fn union_graph(
+        &self,
+        graph_ids: &[GraphIdentifier],
+        rewrite_sh_prefixes: Option<bool>,
+        remove_owl_imports: Option<bool>,
+        deduplicate: bool,
+    ) -> Result<(
+        Dataset,
+        Vec<GraphIdentifier>,
+        Option<Vec<FailedImport>>,
+        usize,
+    )> {
+        let start = Instant::now();
+        let key = format!(
+            "{:?} {:?} {}\n{}",
+            rewrite_sh_prefixes,
+            remove_owl_imports,
+            deduplicate,
+            self.memory_cache_key(graph_ids)
+        );
+        if let Some((union, successful_imports, duplicates)) = self
+            .memory_cache
+            .lock()
+            .ok()
+            .and_then(|mut c| c.union(&key))
+        {
+            debug!(
+                "Using the union of {} graphs computed before",
+                graph_ids.len()
+            );
+            self.count(Counter::CacheHits);
+            return Ok((union, successful_imports, None, duplicates));
+        }
         // compute union of all graphs
         let mut union: Dataset = Dataset::new();
         let store = self.store();
         let mut failed_imports: Vec<FailedImport> = vec![];
         let mut successful_imports: Vec<GraphIdentifier> = vec![];
-        for id in graph_ids {
-            let graphname: NamedOrBlankNode = match id.graphname()? {
-                GraphName::NamedNode(n) => NamedOrBlankNode::NamedNode(n),
+        // fingerprints of the triples seen so far; cheaper than keeping the triples themselves
+        let mut seen: HashSet<u128> = HashSet::new();
+        let mut duplicates = 0;
+        for (index, id) in graph_ids.iter().enumerate() {
+            self.report_progress(Phase::Union, id, index + 1, graph_ids.len());
+            let name = self.store_graphname(id)?;
+            let graphname: NamedOrBlankNode = match &name {
+                GraphName::NamedNode(n) => NamedOrBlankNode::NamedNode(n.clone()),
                 _ => continue,
             };
 
@@ -877,18 +4652,23 @@ impl OntoEnv {
             }
 
             let mut count = 0;
-            for quad in store.quads_for_pattern(None, None, None, Some(id.graphname()?.as_ref())) {
+            for quad in store.quads_for_pattern(None, None, None, Some(name.as_ref())) {
+                let quad = quad?;
+                if deduplicate && !seen.insert(util::triple_fingerprint(quad.as_ref().into())) {
+                    duplicates += 1;
+                    continue;
+                }
                 count += 1;
-                union.insert(quad?.as_ref());
+                union.insert(quad.as_ref());
             }
             // get the Ontology declaration: this is the triple ?name rdf:type
-            // owl:Ontology inside the 'id.graphname()' graph
+            // owl:Ontology inside the ontology's graph
             let mut ontology: Option<Subject> = None;
             for quad in store.quads_for_pattern(
                 None,
                 Some(TYPE),
                 Some(ONTOLOGY.into()),
-                None,
+                Some(name.as_ref()),
             ) {
                 let quad = quad?;
                 ontology = Some(quad.subject.clone());
@@ -898,13 +4678,7 @@ impl OntoEnv {
             // remove the ontology declaration from the 'union' graph
             if let Some(ontology) = ontology {
                 let ontology_ref = ontology.as_ref();
-                let graphname = id.graphname()?;
-                let to_remove = QuadRef::new(
-                    ontology_ref,
-                    TYPE,
-                    ONTOLOGY,
-                    graphname.as_ref(),
-                );
+                let to_remove = QuadRef::new(ontology_ref, TYPE, ONTOLOGY, name.as_ref());
                 union.remove(to_remove);
             }
             successful_imports.push(id.clone());
@@ -923,35 +4697,184 @@ impl OntoEnv {
         // remove owl:imports
         if remove_owl_imports.unwrap_or(true) {
             let to_remove: Vec<NamedNodeRef> = graph_ids.iter().map(|id| id.into()).collect();
-            println!("Removing owl:imports: {:?}", to_remove);
+            debug!("Removing owl:imports: {:?}", to_remove);
             transform::remove_owl_imports(&mut union, Some(&to_remove));
         }
         transform::remove_ontology_declarations(&mut union, root_ontology);
+        // record the location of every part of merged duplicates
+        if self.config.duplicate_strategy == DuplicateStrategy::Merge {
+            for id in &successful_imports {
+                let merged = successful_imports
+                    .iter()
+                    .any(|other| other != id && other.name() == id.name());
+                if merged {
+                    let graphname = self.store_graphname(id)?;
+                    let location = id.location().to_iri()?;
+                    union.insert(QuadRef::new(
+                        id.name(),
+                        PROV_WAS_DERIVED_FROM,
+                        location.as_ref(),
+                        graphname.as_ref(),
+                    ));
+                }
+            }
+        }
         let failed_imports = if failed_imports.is_empty() {
             None
         } else {
             Some(failed_imports)
         };
-        Ok((union, successful_imports, failed_imports))
+        if duplicates > 0 {
+            info!("Dropped {} duplicate triples from the union", duplicates);
+        }
+        self.record_duration(Timer::Union, start);
+        // unions with failed imports are computed again, in case the graphs appear
+        if failed_imports.is_none() {
+            if let Ok(mut cache) = self.memory_cache.lock() {
+                cache.put_union(
+                    key,
+                    (union.clone(), successful_imports.clone(), duplicates),
+                    &self.config.limits,
+                );
+            }
+        }
+        Ok((union, successful_imports, failed_imports, duplicates))
     }
 
-    /// Returns a list of issues with the environment
-    pub fn doctor(&self) {
+    /// Returns the union of the imports closure of the ontology together with the triples the
+    /// reasoner infers from it
+    pub fn materialize(&self, id: &GraphIdentifier, reasoner: &dyn Reasoner) -> Result<Graph> {
+        let closure = self.get_dependency_closure(id)?;
+        let (union, _, failed_imports) = self.get_union_graph(&closure, Some(true), Some(true))?;
+        for imp in failed_imports.unwrap_or_default() {
+            warn!("{}", imp);
+        }
+        let mut graph = util::dataset_to_graph(&union);
+        info!("Reasoning over {:?} with {}", id, reasoner.name());
+        let inferred = reasoner.classify(&graph)?;
+        info!("Inferred {} triples", inferred.len());
+        for triple in inferred.iter() {
+            graph.insert(triple);
+        }
+        Ok(graph)
+    }
+
+    /// Uploads the graphs to a SPARQL 1.1 Graph Store Protocol endpoint (e.g. a Fuseki dataset's
+    /// /data service), each as a named graph named after its ontology. If `closure` is true, the
+    /// imports closures of the graphs are uploaded as well. Returns the uploaded graphs
+    pub fn push_to_sparql_endpoint(
+        &self,
+        endpoint: &str,
+        auth: &SparqlAuth,
+        graphs: &[GraphIdentifier],
+        closure: bool,
+    ) -> Result<Vec<GraphIdentifier>> {
+        let mut to_push: Vec<GraphIdentifier> = vec![];
+        for id in graphs {
+            let ids = if closure {
+                self.get_dependency_closure(id)?
+            } else {
+                vec![id.clone()]
+            };
+            for id in ids {
+                if !to_push.contains(&id) {
+                    to_push.push(id);
+                }
+            }
+        }
+        let client = reqwest::blocking::Client::new();
+        for id in to_push.iter() {
+            let graph = self.get_graph(id)?;
+            sparql::put_graph(&client, endpoint, auth, id.name(), &graph)?;
+        }
+        Ok(to_push)
+    }
+
+    /// Returns all ontologies whose imports closure violates one of the configured
+    /// import constraints
+    pub fn check_import_constraints(&self) -> Result<Vec<ConstraintViolation>> {
+        constraints::check_import_constraints(self)
+    }
+
+    /// Returns the ontologies whose version violates the configured version constraints, with
+    /// the ontologies whose imports closure includes them
+    pub fn check_version_constraints(&self) -> Result<Vec<VersionViolation>> {
+        constraints::check_version_constraints(self)
+    }
+
+    /// Assembles the data, shapes and ontology graphs for validating the given graph with
+    /// SHACL. The shapes graph is the imports closure of `shapes`, or the imports closure of
+    /// `data` if no shapes graph is given
+    pub fn shacl_inputs(
+        &self,
+        data: &GraphIdentifier,
+        shapes: Option<&GraphIdentifier>,
+    ) -> Result<ShaclInputs> {
+        let data_graph = self.get_graph(data)?;
+
+        let data_closure = self.get_dependency_closure(data)?;
+        let ont_graph = if data_closure.len() > 1 {
+            let (union, _, _) = self.get_union_graph(&data_closure[1..], Some(true), Some(true))?;
+            util::dataset_to_graph(&union)
+        } else {
+            Graph::new()
+        };
+
+        let shapes_closure = match shapes {
+            Some(shapes) => self.get_dependency_closure(shapes)?,
+            None => data_closure,
+        };
+        let (shapes_union, _, _) =
+            self.get_union_graph(&shapes_closure, Some(true), Some(true))?;
+
+        Ok(ShaclInputs {
+            data_graph,
+            shapes_graph: util::dataset_to_graph(&shapes_union),
+            ont_graph,
+        })
+    }
+
+    /// Validates the imports closure of the ontology with the built-in SHACL Core validator (see
+    /// `shacl`). The union of the closure is both the data graph and the shapes graph, so the
+    /// shapes shipped by any of its imports apply to the whole closure
+    #[cfg(feature = "shacl")]
+    pub fn validate(&self, id: &GraphIdentifier) -> Result<shacl::ValidationReport> {
+        let closure = self.get_dependency_closure(id)?;
+        let (union, _, _) = self.get_union_graph(&closure, Some(true), Some(true))?;
+        let graph = util::dataset_to_graph(&union);
+        shacl::validate(&graph, &graph)
+    }
+
+    /// Runs the doctor checks and returns the problems found in the environment
+    pub fn problems(&self) -> Result<Vec<OntologyProblem>> {
         let mut doctor = Doctor::new();
         doctor.add_check(Box::new(DuplicateOntology {}));
         doctor.add_check(Box::new(OntologyDeclaration {}));
+        doctor.add_check(Box::new(ImportConstraintCheck {}));
+        doctor.add_check(Box::new(VersionConstraintCheck {}));
+        doctor.add_check(Box::new(LocationCasing {}));
+        doctor.add_check(Box::new(ImportCycles {}));
+        doctor.add_check(Box::new(OwlStructure {}));
+        doctor.add_check(Box::new(LiteralSubjects {}));
+        doctor.run(self)
+    }
 
-        let problems = doctor.run(self).unwrap();
+    /// Returns a list of issues with the environment
+    pub fn doctor(&self) -> Result<()> {
+        let problems = self.problems()?;
 
         // for each problem, print two columns. The first column is the message
         // and the second column is a list of locations for that problem. The locations
         // should be stacked on top of one another
-        let mut messages: HashMap<String, Vec<String>> = HashMap::new();
+        let mut messages: BTreeMap<String, Vec<String>> = BTreeMap::new();
         for problem in problems {
             let message = problem.message;
             let locations: Vec<String> = problem.locations.iter().map(|l| l.to_string()).collect();
             messages.entry(message).or_default().extend(locations);
         }
+        for locations in messages.values_mut() {
+            locations.sort();
+        }
 
         // print the messages
         for (message, locations) in messages {
@@ -960,24 +4883,27 @@ impl OntoEnv {
                 println!("  - {}", location);
             }
         }
+        Ok(())
     }
 
-    /// Returns a list of all ontologies that depend on the given ontology
+    /// Returns a list of all ontologies that depend on the given ontology, sorted by name and
+    /// location. Ontologies importing it by its name or by any of its version IRIs and aliases
+    /// are included (see `explain_import`)
     pub fn get_dependents(&self, id: &NamedNode) -> Result<Vec<GraphIdentifier>> {
-        let mut dependents = vec![];
-        for ontology in self.ontologies.values() {
-            if ontology.imports.contains(&id) {
-                dependents.push(ontology.id().clone());
-            }
-        }
+        let mut dependents: Vec<GraphIdentifier> = self
+            .explain_import(id.as_ref())
+            .into_iter()
+            .map(|reference| reference.importer)
+            .collect();
+        dependents.dedup();
         Ok(dependents)
     }
 
     /// Outputs a human-readable dump of the environment, including all ontologies
     /// and their metadata and imports
-    pub fn dump(&self, contains: Option<&str>) {
+    pub fn dump(&self, contains: Option<&str>) -> Result<()> {
         let mut ontologies = self.ontologies.clone();
-        let mut groups: HashMap<NamedNode, Vec<Ontology>> = HashMap::new();
+        let mut groups: BTreeMap<NamedNode, Vec<Ontology>> = BTreeMap::new();
         for ontology in ontologies.values_mut() {
             let name = ontology.name();
             groups.entry(name).or_default().push(ontology.clone());
@@ -993,8 +4919,13 @@ impl OntoEnv {
             let group = groups.get(&name).unwrap();
             println!("┌ Ontology: {}", name);
             for ontology in group {
-                let g = self.get_graph(ontology.id()).unwrap();
-                println!("├─ Location: {}", ontology.location().unwrap());
+                let g = self.get_graph(ontology.id())?;
+                println!(
+                    "├─ Location: {}",
+                    ontology
+                        .location()
+                        .map_or("n/a".to_string(), |loc| loc.to_string())
+                );
                 // sorted keys
                 let mut sorted_keys: Vec<NamedNode> =
                     ontology.version_properties().keys().cloned().collect();
@@ -1021,7 +4952,24 @@ impl OntoEnv {
                             .unwrap()
                     );
                 }
-                println!("│ ├─ Last updated: {}", ontology.last_updated.unwrap());
+                println!(
+                    "│ ├─ Last updated: {}",
+                    ontology
+                        .last_updated
+                        .map_or("n/a".to_string(), |t| t.to_string())
+                );
+                if let Some(base_iri) = ontology.base_iri() {
+                    println!("│ ├─ Base IRI: {}", base_iri);
+                }
+                if let Some(resolved_url) = ontology.resolved_url() {
+                    println!("│ ├─ Resolved URL: {}", resolved_url);
+                }
+                if ontology.is_anonymous() {
+                    println!("│ ├─ Anonymous (no owl:Ontology declaration)");
+                }
+                for (key, value) in ontology.annotations() {
+                    println!("│ ├─ {}: {}", key, value);
+                }
                 if !ontology.imports.is_empty() {
                     println!("│ ├─ Triples: {}", g.len());
                     println!("│ ├─ Imports:");
@@ -1039,5 +4987,6 @@ impl OntoEnv {
             }
             println!("└────────────────────────────────────────────────────────────────────────");
         }
+        Ok(())
     }
 }
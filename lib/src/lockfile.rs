@@ -0,0 +1,148 @@
+// ontoenv.lock records the exact set of graphs of an environment: every ontology with where it
+// was read from, a hash of its content and when it was retrieved. It is written at the root of
+// the environment after every update so it can be committed alongside the ontologies, and
+// `OntoEnv::sync_from_lock` reproduces the recorded graphs on another machine
+
+use crate::ontology::{Ontology, OntologyLocation};
+use crate::util::sorted_triples;
+use anyhow::Result;
+use chrono::prelude::*;
+use oxigraph::model::Graph;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::Path;
+
+/// Name of the lockfile at the root of an environment
+pub const LOCKFILE_NAME: &str = "ontoenv.lock";
+/// Version of the lockfile format; lockfiles of other versions are not read
+pub const LOCKFILE_VERSION: u32 = 1;
+
+/// An ontology recorded in the lockfile
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct LockedOntology {
+    pub name: String,
+    // local files are recorded relative to the root of the environment, so the lockfile works
+    // in every checkout
+    pub location: OntologyLocation,
+    // see content_hash
+    pub hash: String,
+    pub retrieved: Option<DateTime<Utc>>,
+}
+
+/// The ontologies of an environment, sorted by name and location
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Lockfile {
+    pub version: u32,
+    pub ontologies: Vec<LockedOntology>,
+}
+
+/// Returns the SHA-256 hash of the triples of the graph, in the stable order of
+/// `util::sorted_triples`, so the hash does not depend on how the source document is laid out
+/// or on blank node labels
+pub fn content_hash(graph: &Graph) -> String {
+    let mut hasher = Sha256::new();
+    for triple in sorted_triples(graph) {
+        hasher.update(triple.to_string().as_bytes());
+        hasher.update(b"\n");
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+/// Returns the SHA-256 hash of the bytes of the file, which changes with any edit, unlike
+/// `content_hash`, but is much cheaper than parsing the file
+pub fn source_hash(path: &Path) -> Result<String> {
+    Ok(format!("{:x}", Sha256::digest(fs::read(path)?)))
+}
+
+impl Lockfile {
+    /// Records the ontologies, with the files among them relative to the root. Ontologies
+    /// without a location or a content hash are left out
+    pub fn from_ontologies<'a>(
+        ontologies: impl IntoIterator<Item = &'a Ontology>,
+        root: &Path,
+    ) -> Self {
+        let mut locked: Vec<LockedOntology> = ontologies
+            .into_iter()
+            .filter_map(|ontology| {
+                let location = match ontology.location()? {
+                    OntologyLocation::File(path) => OntologyLocation::File(
+                        path.strip_prefix(root).unwrap_or(path).to_path_buf(),
+                    ),
+                    location => location.clone(),
+                };
+                Some(LockedOntology {
+                    name: ontology.name().as_str().to_string(),
+                    location,
+                    hash: ontology.content_hash()?.to_string(),
+                    retrieved: ontology.last_updated,
+                })
+            })
+            .collect();
+        locked.sort_by(|a, b| a.name.cmp(&b.name).then(a.location.cmp(&b.location)));
+        Lockfile {
+            version: LOCKFILE_VERSION,
+            ontologies: locked,
+        }
+    }
+
+    /// Returns the locked ontologies with their files resolved against the root
+    pub fn resolved(&self, root: &Path) -> Vec<LockedOntology> {
+        self.ontologies
+            .iter()
+            .map(|locked| match &locked.location {
+                OntologyLocation::File(path) if path.is_relative() => LockedOntology {
+                    location: OntologyLocation::File(root.join(path)),
+                    ..locked.clone()
+                },
+                _ => locked.clone(),
+            })
+            .collect()
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Could not read lockfile {}: {}", path.display(), e))?;
+        let lockfile: Lockfile = serde_json::from_str(&content)?;
+        if lockfile.version != LOCKFILE_VERSION {
+            return Err(anyhow::anyhow!(
+                "Lockfile {} has version {}, expected {}",
+                path.display(),
+                lockfile.version,
+                LOCKFILE_VERSION
+            ));
+        }
+        Ok(lockfile)
+    }
+
+    /// Writes the lockfile, unless the file already has the same content. Returns true if the
+    /// file was written
+    pub fn save(&self, path: &Path) -> Result<bool> {
+        let content = serde_json::to_string_pretty(self)? + "\n";
+        if fs::read_to_string(path).is_ok_and(|existing| existing == content) {
+            return Ok(false);
+        }
+        fs::write(path, content)?;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use oxigraph::model::{BlankNode, NamedNodeRef, TripleRef};
+
+    #[test]
+    fn test_content_hash_ignores_blank_node_labels() {
+        let subject = NamedNodeRef::new("http://example.org/a").unwrap();
+        let predicate = NamedNodeRef::new("http://example.org/p").unwrap();
+        let hash = |label: &str| {
+            let mut graph = Graph::new();
+            let node = BlankNode::new(label).unwrap();
+            graph.insert(TripleRef::new(subject, predicate, &node));
+            content_hash(&graph)
+        };
+        assert_eq!(hash("x"), hash("y"));
+        assert_ne!(hash("x"), content_hash(&Graph::new()));
+    }
+}
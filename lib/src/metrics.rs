@@ -0,0 +1,114 @@
+// a hook for applications to collect metrics about an environment (e.g. to export them to
+// Prometheus or OpenTelemetry). ontoenv only calls the sink; it never reports metrics itself
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Counter {
+    // ontologies fetched from a URL
+    Fetches,
+    FetchFailures,
+    // ontologies parsed from a local file
+    Parses,
+    ParseFailures,
+    // ontologies which were already in the environment and did not need to be read again
+    CacheHits,
+}
+
+impl Counter {
+    /// Metric name following the Prometheus naming conventions
+    pub fn name(&self) -> &'static str {
+        match self {
+            Counter::Fetches => "ontoenv_fetches_total",
+            Counter::FetchFailures => "ontoenv_fetch_failures_total",
+            Counter::Parses => "ontoenv_parses_total",
+            Counter::ParseFailures => "ontoenv_parse_failures_total",
+            Counter::CacheHits => "ontoenv_cache_hits_total",
+        }
+    }
+}
+
+impl fmt::Display for Counter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Timer {
+    // fetching and parsing an ontology from a URL
+    Fetch,
+    // parsing an ontology from a local file
+    Parse,
+    // a whole OntoEnv::update
+    Update,
+    // computing the imports closure of an ontology
+    Closure,
+    // computing the union graph of an imports closure
+    Union,
+}
+
+impl Timer {
+    /// Metric name following the Prometheus naming conventions
+    pub fn name(&self) -> &'static str {
+        match self {
+            Timer::Fetch => "ontoenv_fetch_seconds",
+            Timer::Parse => "ontoenv_parse_seconds",
+            Timer::Update => "ontoenv_update_seconds",
+            Timer::Closure => "ontoenv_closure_seconds",
+            Timer::Union => "ontoenv_union_seconds",
+        }
+    }
+}
+
+impl fmt::Display for Timer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Receives the metrics of an environment. Both methods default to doing nothing, so
+/// implementations only need to handle what they are interested in
+pub trait MetricsSink: Send + Sync {
+    fn increment(&self, _counter: Counter, _value: u64) {}
+    fn record_duration(&self, _timer: Timer, _duration: Duration) {}
+}
+
+/// A sink which keeps the metrics in memory, e.g. for tests or for periodically copying them
+/// into another metrics library
+#[derive(Debug, Default)]
+pub struct InMemoryMetrics {
+    counters: Mutex<HashMap<Counter, u64>>,
+    // number of recorded durations and their sum for each timer
+    timers: Mutex<HashMap<Timer, (u64, Duration)>>,
+}
+
+impl InMemoryMetrics {
+    pub fn counter(&self, counter: Counter) -> u64 {
+        let counters = self.counters.lock().unwrap_or_else(|e| e.into_inner());
+        counters.get(&counter).copied().unwrap_or(0)
+    }
+
+    /// Returns how many durations were recorded for the timer and their sum
+    pub fn timer(&self, timer: Timer) -> (u64, Duration) {
+        let timers = self.timers.lock().unwrap_or_else(|e| e.into_inner());
+        timers.get(&timer).copied().unwrap_or_default()
+    }
+}
+
+impl MetricsSink for InMemoryMetrics {
+    fn increment(&self, counter: Counter, value: u64) {
+        let mut counters = self.counters.lock().unwrap_or_else(|e| e.into_inner());
+        *counters.entry(counter).or_insert(0) += value;
+    }
+
+    fn record_duration(&self, timer: Timer, duration: Duration) {
+        let mut timers = self.timers.lock().unwrap_or_else(|e| e.into_inner());
+        let entry = timers.entry(timer).or_default();
+        entry.0 += 1;
+        entry.1 += duration;
+    }
+}
@@ -0,0 +1,338 @@
+// A minimal client for the OCI distribution API, used to push and pull environment bundles to
+// container registries (e.g. ghcr.io) as ORAS-style artifacts: every file in the bundle is
+// stored as its own layer, named by the 'org.opencontainers.image.title' annotation.
+//
+// Credentials are read from the ONTOENV_REGISTRY_USERNAME and ONTOENV_REGISTRY_PASSWORD
+// environment variables; anonymous access is used if they are not set.
+
+use anyhow::Result;
+use reqwest::blocking::{Client, RequestBuilder, Response};
+use reqwest::header::{ACCEPT, CONTENT_TYPE, LOCATION, WWW_AUTHENTICATE};
+use reqwest::StatusCode;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use tracing::{debug, info};
+
+pub const MANIFEST_MEDIA_TYPE: &str = "application/vnd.oci.image.manifest.v1+json";
+pub const BUNDLE_ARTIFACT_TYPE: &str = "application/vnd.ontoenv.bundle.v1";
+const EMPTY_CONFIG_MEDIA_TYPE: &str = "application/vnd.oci.empty.v1+json";
+const TITLE_ANNOTATION: &str = "org.opencontainers.image.title";
+
+// names of the files in an environment bundle
+pub const BUNDLE_MANIFEST: &str = "ontoenv.json";
+pub const BUNDLE_GRAPHS: &str = "graphs.trig";
+
+/// A file stored in a registry artifact
+#[derive(Debug, Clone)]
+pub struct ArtifactFile {
+    pub name: String,
+    pub media_type: String,
+    pub content: Vec<u8>,
+}
+
+/// A reference to an artifact in a registry: <registry>/<repository>:<tag>
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reference {
+    pub registry: String,
+    pub repository: String,
+    pub tag: String,
+}
+
+impl Reference {
+    /// Parses a reference like 'ghcr.io/org/ontologies:1.4'. The tag defaults to 'latest'
+    pub fn parse(s: &str) -> Result<Self> {
+        let (registry, rest) = s
+            .split_once('/')
+            .ok_or(anyhow::anyhow!("Invalid registry reference: {}", s))?;
+        let (repository, tag) = match rest.rsplit_once(':') {
+            Some((repository, tag)) if !tag.contains('/') => (repository, tag),
+            _ => (rest, "latest"),
+        };
+        if repository.is_empty() || tag.is_empty() {
+            return Err(anyhow::anyhow!("Invalid registry reference: {}", s));
+        }
+        Ok(Reference {
+            registry: registry.to_string(),
+            repository: repository.to_string(),
+            tag: tag.to_string(),
+        })
+    }
+
+    fn base_url(&self) -> String {
+        // plain http is only used for registries running on the local machine
+        let scheme =
+            if self.registry.starts_with("localhost") || self.registry.starts_with("127.0.0.1") {
+                "http"
+            } else {
+                "https"
+            };
+        format!("{}://{}/v2/{}", scheme, self.registry, self.repository)
+    }
+}
+
+impl std::fmt::Display for Reference {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}/{}:{}", self.registry, self.repository, self.tag)
+    }
+}
+
+/// Returns the OCI digest ('sha256:<hex>') of the content
+pub fn digest(content: &[u8]) -> String {
+    format!("sha256:{:x}", Sha256::digest(content))
+}
+
+struct RegistryClient {
+    client: Client,
+    reference: Reference,
+    token: Option<String>,
+}
+
+impl RegistryClient {
+    fn new(reference: Reference) -> Self {
+        RegistryClient {
+            client: Client::new(),
+            reference,
+            token: None,
+        }
+    }
+
+    /// Sends the request built by `build`, authenticating and retrying once if the registry
+    /// asks for a bearer token
+    fn send(&mut self, build: impl Fn(&Client) -> RequestBuilder) -> Result<Response> {
+        let mut request = build(&self.client);
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        let resp = request.send()?;
+        if resp.status() != StatusCode::UNAUTHORIZED || self.token.is_some() {
+            return Ok(resp);
+        }
+        let challenge = resp
+            .headers()
+            .get(WWW_AUTHENTICATE)
+            .and_then(|h| h.to_str().ok())
+            .ok_or(anyhow::anyhow!("Registry requires authentication"))?
+            .to_string();
+        self.token = Some(self.fetch_token(&challenge)?);
+        let request = build(&self.client).bearer_auth(self.token.as_ref().unwrap());
+        Ok(request.send()?)
+    }
+
+    /// Fetches a bearer token as described by the WWW-Authenticate challenge
+    fn fetch_token(&self, challenge: &str) -> Result<String> {
+        let params = challenge.strip_prefix("Bearer ").ok_or(anyhow::anyhow!(
+            "Unsupported authentication challenge: {}",
+            challenge
+        ))?;
+        let mut realm = None;
+        let mut query: Vec<(String, String)> = vec![];
+        for param in params.split(',') {
+            if let Some((key, value)) = param.trim().split_once('=') {
+                let value = value.trim_matches('"').to_string();
+                match key {
+                    "realm" => realm = Some(value),
+                    "service" | "scope" => query.push((key.to_string(), value)),
+                    _ => {}
+                }
+            }
+        }
+        let realm = realm.ok_or(anyhow::anyhow!("No realm in challenge: {}", challenge))?;
+        debug!("Fetching registry token from {}", realm);
+        let mut request = self.client.get(&realm).query(&query);
+        if let Ok(username) = std::env::var("ONTOENV_REGISTRY_USERNAME") {
+            request = request.basic_auth(username, std::env::var("ONTOENV_REGISTRY_PASSWORD").ok());
+        }
+        let resp = request.send()?;
+        if !resp.status().is_success() {
+            return Err(anyhow::anyhow!(
+                "Failed to authenticate with {}: {}",
+                realm,
+                resp.status()
+            ));
+        }
+        let body: Value = resp.json()?;
+        body.get("token")
+            .or_else(|| body.get("access_token"))
+            .and_then(|t| t.as_str())
+            .map(|t| t.to_string())
+            .ok_or(anyhow::anyhow!("No token in response from {}", realm))
+    }
+
+    fn upload_blob(&mut self, content: &[u8]) -> Result<String> {
+        let digest = digest(content);
+        let base = self.reference.base_url();
+        let resp = self.send(|c| c.post(format!("{}/blobs/uploads/", base)))?;
+        if resp.status() != StatusCode::ACCEPTED {
+            return Err(anyhow::anyhow!(
+                "Failed to start blob upload: {}",
+                resp.status()
+            ));
+        }
+        let location = resp
+            .headers()
+            .get(LOCATION)
+            .and_then(|h| h.to_str().ok())
+            .ok_or(anyhow::anyhow!(
+                "Registry did not return an upload location"
+            ))?;
+        // the upload location may be relative to the registry
+        let location = if location.starts_with('/') {
+            let scheme = base.split("://").next().unwrap_or("https");
+            format!("{}://{}{}", scheme, self.reference.registry, location)
+        } else {
+            location.to_string()
+        };
+        let separator = if location.contains('?') { '&' } else { '?' };
+        let url = format!("{}{}digest={}", location, separator, digest);
+        let resp = self.send(|c| {
+            c.put(&url)
+                .header(CONTENT_TYPE, "application/octet-stream")
+                .body(content.to_vec())
+        })?;
+        if !resp.status().is_success() {
+            return Err(anyhow::anyhow!(
+                "Failed to upload blob {}: {}",
+                digest,
+                resp.status()
+            ));
+        }
+        Ok(digest)
+    }
+}
+
+/// Pushes the files as an artifact to the registry and returns the digest of the manifest
+pub fn push(reference: &Reference, files: &[ArtifactFile]) -> Result<String> {
+    let mut client = RegistryClient::new(reference.clone());
+    let config = b"{}";
+    let config_digest = client.upload_blob(config)?;
+    let mut layers = vec![];
+    for file in files {
+        info!(
+            "Pushing {} ({} bytes) to {}",
+            file.name,
+            file.content.len(),
+            reference
+        );
+        let digest = client.upload_blob(&file.content)?;
+        layers.push(json!({
+            "mediaType": file.media_type,
+            "digest": digest,
+            "size": file.content.len(),
+            "annotations": {TITLE_ANNOTATION: file.name},
+        }));
+    }
+    let manifest = json!({
+        "schemaVersion": 2,
+        "mediaType": MANIFEST_MEDIA_TYPE,
+        "artifactType": BUNDLE_ARTIFACT_TYPE,
+        "config": {
+            "mediaType": EMPTY_CONFIG_MEDIA_TYPE,
+            "digest": config_digest,
+            "size": config.len(),
+        },
+        "layers": layers,
+    });
+    let manifest = serde_json::to_vec(&manifest)?;
+    let url = format!("{}/manifests/{}", reference.base_url(), reference.tag);
+    let resp = client.send(|c| {
+        c.put(&url)
+            .header(CONTENT_TYPE, MANIFEST_MEDIA_TYPE)
+            .body(manifest.clone())
+    })?;
+    if !resp.status().is_success() {
+        return Err(anyhow::anyhow!(
+            "Failed to push manifest to {}: {}",
+            reference,
+            resp.status()
+        ));
+    }
+    Ok(digest(&manifest))
+}
+
+/// Pulls all files of the artifact from the registry, verifying their digests
+pub fn pull(reference: &Reference) -> Result<Vec<ArtifactFile>> {
+    let mut client = RegistryClient::new(reference.clone());
+    let base = reference.base_url();
+    let url = format!("{}/manifests/{}", base, reference.tag);
+    let resp = client.send(|c| c.get(&url).header(ACCEPT, MANIFEST_MEDIA_TYPE))?;
+    if !resp.status().is_success() {
+        return Err(anyhow::anyhow!(
+            "Failed to fetch manifest for {}: {}",
+            reference,
+            resp.status()
+        ));
+    }
+    let manifest: Value = resp.json()?;
+    let layers = manifest
+        .get("layers")
+        .and_then(|l| l.as_array())
+        .ok_or(anyhow::anyhow!("Manifest for {} has no layers", reference))?;
+
+    let mut files = vec![];
+    for layer in layers {
+        let layer_digest = layer
+            .get("digest")
+            .and_then(|d| d.as_str())
+            .ok_or(anyhow::anyhow!("Layer without digest in {}", reference))?;
+        let name = layer
+            .pointer(&format!(
+                "/annotations/{}",
+                TITLE_ANNOTATION.replace('/', "~1")
+            ))
+            .and_then(|n| n.as_str())
+            .unwrap_or(layer_digest)
+            .to_string();
+        let media_type = layer
+            .get("mediaType")
+            .and_then(|m| m.as_str())
+            .unwrap_or("application/octet-stream")
+            .to_string();
+        let url = format!("{}/blobs/{}", base, layer_digest);
+        let resp = client.send(|c| c.get(&url))?;
+        if !resp.status().is_success() {
+            return Err(anyhow::anyhow!(
+                "Failed to fetch {}: {}",
+                name,
+                resp.status()
+            ));
+        }
+        let content = resp.bytes()?.to_vec();
+        if digest(&content) != layer_digest {
+            return Err(anyhow::anyhow!("Digest mismatch for {}", name));
+        }
+        files.push(ArtifactFile {
+            name,
+            media_type,
+            content,
+        });
+    }
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_reference() {
+        let r = Reference::parse("ghcr.io/org/ontologies:1.4").unwrap();
+        assert_eq!(r.registry, "ghcr.io");
+        assert_eq!(r.repository, "org/ontologies");
+        assert_eq!(r.tag, "1.4");
+
+        let r = Reference::parse("localhost:5000/ontologies").unwrap();
+        assert_eq!(r.registry, "localhost:5000");
+        assert_eq!(r.repository, "ontologies");
+        assert_eq!(r.tag, "latest");
+
+        assert!(Reference::parse("ontologies").is_err());
+    }
+
+    #[test]
+    fn test_digest() {
+        assert_eq!(
+            digest(b"{}"),
+            "sha256:44136fa355b3678a1146ad16f7e8649e94fb4fc21fe77e8310c060f61caaff8a"
+        );
+    }
+}
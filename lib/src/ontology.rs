@@ -1,16 +1,216 @@
 use crate::consts::*;
-use crate::util::{read_file, read_url};
+use crate::io::http::CacheValidators;
+use crate::iri::normalize_iri;
+use crate::util::{
+    read_file, read_file_document, read_file_with_prefixes, read_url,
+    read_url_document_if_modified, read_url_with_prefixes, BaseIriOptions, Document,
+};
 use anyhow::Result;
 use chrono::prelude::*;
-use log::{debug, info, warn};
+use tracing::{debug, info, warn};
 use oxigraph::model::{
-    Graph as OxigraphGraph, GraphName, NamedNode, NamedNodeRef, Subject, SubjectRef, TermRef,
+    Graph as OxigraphGraph, GraphName, NamedNode, NamedNodeRef, SubjectRef, TermRef,
 };
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_with::{serde_as, DeserializeAs, SerializeAs};
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 use std::hash::Hash;
-use std::path::PathBuf;
+use std::path::{Component, Path, PathBuf};
+use url::Url;
+
+/// Converts a local path into a file IRI: file:///home/user/ontology.ttl, or
+/// file:///C:/Users/user/ontology.ttl and file://server/share/ontology.ttl for Windows drive and
+/// UNC paths. Characters which are not allowed in IRIs, such as spaces, are percent-encoded.
+/// Relative paths are resolved against the current directory
+pub fn path_to_file_iri(path: &Path) -> Result<NamedNode> {
+    let absolute = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir()?.join(path)
+    };
+    let url = Url::from_file_path(&absolute)
+        .map_err(|_| anyhow::anyhow!("Cannot convert path {:?} to a file IRI", path))?;
+    Ok(NamedNode::new(url.as_str())?)
+}
+
+/// Returns the name given to an ontology without an owl:Ontology declaration: for files,
+/// urn:ontoenv:anonymous: followed by the path of the file relative to the first of the
+/// directories containing it, so the name is the same on every machine. Files outside the
+/// directories use their file IRI, and URLs use the URL
+pub fn anonymous_ontology_name(
+    location: &OntologyLocation,
+    directories: &[PathBuf],
+) -> Result<NamedNode> {
+    let path = match location {
+        OntologyLocation::File(path) => path,
+        OntologyLocation::Url(url) => return Ok(NamedNode::new(url.clone())?),
+    };
+    // compare file IRIs so the relative path is percent-encoded and uses forward slashes
+    let file_iri = path_to_file_iri(path)?;
+    for directory in directories {
+        let directory_iri = path_to_file_iri(directory)?;
+        let prefix = format!("{}/", directory_iri.as_str().trim_end_matches('/'));
+        if let Some(relative) = file_iri.as_str().strip_prefix(&prefix) {
+            return Ok(NamedNode::new(format!(
+                "urn:ontoenv:anonymous:{}",
+                relative
+            ))?);
+        }
+    }
+    Ok(file_iri)
+}
+
+/// A declaration the name of an ontology can be inferred from when its document has no
+/// owl:Ontology declaration, e.g. SKOS vocabularies and datasets described with VoID (see
+/// `Config::name_inference`)
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum NameSource {
+    /// The subject of an `rdf:type void:Dataset` declaration
+    VoidDataset,
+    /// The subject of an `rdf:type skos:ConceptScheme` declaration
+    SkosConceptScheme,
+    /// The value of a dcterms:identifier, if it is an IRI
+    DctermsIdentifier,
+}
+
+impl std::str::FromStr for NameSource {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "void" | "void:dataset" | "void_dataset" => Ok(NameSource::VoidDataset),
+            "skos" | "skos:conceptscheme" | "skos_concept_scheme" => {
+                Ok(NameSource::SkosConceptScheme)
+            }
+            "dcterms:identifier" | "dcterms_identifier" => Ok(NameSource::DctermsIdentifier),
+            _ => Err(anyhow::anyhow!(
+                "Unknown name source {}, expected void:Dataset, skos:ConceptScheme or \
+                 dcterms:identifier",
+                s
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for NameSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let name = match self {
+            NameSource::VoidDataset => "void:Dataset",
+            NameSource::SkosConceptScheme => "skos:ConceptScheme",
+            NameSource::DctermsIdentifier => "dcterms:identifier",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Infers the name of an ontology from the first of the sources, in order, the graph has a
+/// declaration for. If the graph declares several resources of one kind (e.g. two concept
+/// schemes), the smallest IRI is used, so the name does not depend on the order of the document
+pub fn infer_ontology_name(
+    graph: &OxigraphGraph,
+    sources: &[NameSource],
+) -> Option<(NamedNode, NameSource)> {
+    for source in sources {
+        let mut candidates: Vec<NamedNode> = match source {
+            NameSource::VoidDataset | NameSource::SkosConceptScheme => {
+                let class = match source {
+                    NameSource::VoidDataset => VOID_DATASET,
+                    _ => SKOS_CONCEPT_SCHEME,
+                };
+                graph
+                    .subjects_for_predicate_object(TYPE, class)
+                    .filter_map(|subject| match subject {
+                        SubjectRef::NamedNode(name) => Some(name.into_owned()),
+                        _ => None,
+                    })
+                    .collect()
+            }
+            NameSource::DctermsIdentifier => graph
+                .triples_for_predicate(DC_IDENTIFIER)
+                .filter_map(|triple| match triple.object {
+                    TermRef::NamedNode(name) => Some(name.into_owned()),
+                    // identifiers are usually literals; only those which are IRIs can name
+                    // an ontology
+                    TermRef::Literal(literal) => NamedNode::new(literal.value().trim()).ok(),
+                    _ => None,
+                })
+                .collect(),
+        };
+        candidates.sort();
+        if let Some(name) = candidates.into_iter().next() {
+            let name = NamedNode::new(normalize_iri(name.as_str())).unwrap_or(name);
+            return Some((name, *source));
+        }
+    }
+    None
+}
+
+/// Returns the path with the casing its components have on disk. On case-insensitive file
+/// systems (the default on Windows and macOS) the same file can be reached with differently
+/// cased paths, which would otherwise register as distinct locations. Paths on case-sensitive
+/// file systems and paths which do not exist are returned unchanged
+pub fn on_disk_casing(path: &Path) -> PathBuf {
+    // a cheap probe: on a case-sensitive file system the path with its case swapped (almost
+    // always) does not exist
+    let original = path.to_string_lossy();
+    let swapped: String = original
+        .chars()
+        .map(|c| {
+            if c.is_ascii_lowercase() {
+                c.to_ascii_uppercase()
+            } else {
+                c.to_ascii_lowercase()
+            }
+        })
+        .collect();
+    if swapped == original || !Path::new(&swapped).exists() {
+        return path.to_path_buf();
+    }
+    let mut result = PathBuf::new();
+    for component in path.components() {
+        let name = match component {
+            Component::Normal(name) => name,
+            other => {
+                result.push(other);
+                continue;
+            }
+        };
+        let parent = if result.as_os_str().is_empty() {
+            Path::new(".")
+        } else {
+            result.as_path()
+        };
+        let entries: Vec<_> = match std::fs::read_dir(parent) {
+            Ok(entries) => entries
+                .filter_map(|e| e.ok())
+                .map(|e| e.file_name())
+                .collect(),
+            Err(_) => vec![],
+        };
+        let on_disk = if entries.iter().any(|entry| entry == name) {
+            name.to_os_string()
+        } else {
+            let lowercase = name.to_string_lossy().to_lowercase();
+            entries
+                .into_iter()
+                .find(|entry| entry.to_string_lossy().to_lowercase() == lowercase)
+                .unwrap_or_else(|| name.to_os_string())
+        };
+        result.push(on_disk);
+    }
+    result
+}
+
+/// Converts a file IRI (see path_to_file_iri) back into a local path
+pub fn file_iri_to_path(iri: &str) -> Result<PathBuf> {
+    let url = Url::parse(iri)?;
+    if url.scheme() != "file" {
+        return Err(anyhow::anyhow!("{} is not a file IRI", iri));
+    }
+    url.to_file_path()
+        .map_err(|_| anyhow::anyhow!("Cannot convert file IRI {} to a path", iri))
+}
 //
 // custom derive for NamedNode
 fn namednode_ser<S>(namednode: &NamedNode, serializer: S) -> Result<S::Ok, S::Error>
@@ -33,9 +233,15 @@ pub struct GraphIdentifier {
     location: OntologyLocation,
     #[serde(serialize_with = "namednode_ser", deserialize_with = "namednode_de")]
     name: NamedNode,
+    // stable ID of the ontology's graph in the store. It is kept when the ontology's file
+    // moves, so the graph keeps its name. Environments created before graph IDs existed store
+    // graphs under a name derived from the ontology name and location until they are migrated
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    graph_id: Option<String>,
 }
 
-// equality for GraphIdentifier is based on the name and location
+// equality for GraphIdentifier is based on the name and location; the graph ID is not part of
+// the identity, so identifiers built from a name and location find the stored ontology
 impl PartialEq for GraphIdentifier {
     fn eq(&self, other: &Self) -> bool {
         self.name == other.name && self.location == other.location
@@ -49,6 +255,21 @@ impl Hash for GraphIdentifier {
     }
 }
 
+// graph identifiers are ordered by name, then by location
+impl Ord for GraphIdentifier {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.name
+            .cmp(&other.name)
+            .then_with(|| self.location.cmp(&other.location))
+    }
+}
+
+impl PartialOrd for GraphIdentifier {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 impl std::fmt::Display for GraphIdentifier {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{} @ {}", self.name, self.location)
@@ -71,10 +292,27 @@ impl GraphIdentifier {
     pub fn new(name: NamedNodeRef) -> Self {
         // location is same as name
         GraphIdentifier {
-            location: OntologyLocation::from_str(name.as_str()).unwrap(),
+            location: OntologyLocation::from_str(name.as_str())
+                .unwrap_or_else(|_| OntologyLocation::Url(name.as_str().to_string())),
             name: name.into(),
+            graph_id: None,
         }
     }
+
+    /// Returns a new random graph ID
+    pub fn new_graph_id() -> String {
+        uuid::Uuid::new_v4().to_string()
+    }
+
+    /// Returns the stable ID of the ontology's graph in the store, if it has one
+    pub fn graph_id(&self) -> Option<&str> {
+        self.graph_id.as_deref()
+    }
+
+    pub fn with_graph_id(&mut self, graph_id: String) {
+        self.graph_id = Some(graph_id);
+    }
+
     pub fn location(&self) -> &OntologyLocation {
         &self.location
     }
@@ -85,10 +323,38 @@ impl GraphIdentifier {
 
     pub fn to_filename(&self) -> String {
         let name = self.name.as_str().replace(':', "+");
-        let location = self.location.as_str().replace("file://", "");
+        let location = self.location_key().replace(':', "+");
         format!("{}-{}", name, location).replace('/', "_")
     }
+
+    /// Returns the location as a string which is valid in an IRI: the path of the file IRI for
+    /// files (so Windows paths use forward slashes) and the URL otherwise
+    fn location_key(&self) -> String {
+        match &self.location {
+            OntologyLocation::File(p) => match path_to_file_iri(p) {
+                Ok(iri) => iri.as_str().trim_start_matches("file://").to_string(),
+                Err(_) => p.to_string_lossy().replace('\\', "/"),
+            },
+            OntologyLocation::Url(u) => u.clone(),
+        }
+    }
+    /// Returns the name of the ontology's graph in the store: urn:ontoenv:graph:<graph ID>, or
+    /// for identifiers without a graph ID the legacy name derived from the ontology name and
+    /// location (see legacy_graphname)
     pub fn graphname(&self) -> Result<GraphName> {
+        match &self.graph_id {
+            Some(graph_id) => Ok(GraphName::NamedNode(Self::graph_id_to_graphname(graph_id)?)),
+            None => self.legacy_graphname(),
+        }
+    }
+
+    /// Returns the name of the graph in the store with the given graph ID
+    pub fn graph_id_to_graphname(graph_id: &str) -> Result<NamedNode> {
+        Ok(NamedNode::new(format!("urn:ontoenv:graph:{}", graph_id))?)
+    }
+
+    /// Returns the graph name used by environments created before graph IDs existed
+    pub fn legacy_graphname(&self) -> Result<GraphName> {
         // if self.name is a URL, use that as the graphname
         if self.name.as_str().starts_with("http") {
             return Ok(GraphName::NamedNode(self.name.clone()));
@@ -96,7 +362,7 @@ impl GraphIdentifier {
 
         // graphname is the self.name + URL-safe self.location
         let name = self.name.as_str().replace(':', "+");
-        let location = self.location.as_str().replace("file://", "");
+        let location = self.location_key();
         Ok(GraphName::NamedNode(NamedNode::new(format!(
             "urn://{}-{}",
             name, location
@@ -104,7 +370,7 @@ impl GraphIdentifier {
     }
 }
 
-#[derive(Serialize, Deserialize, Hash, Clone, Eq, PartialEq, Debug)]
+#[derive(Serialize, Deserialize, Hash, Clone, Eq, PartialEq, PartialOrd, Ord, Debug)]
 pub enum OntologyLocation {
     #[serde(rename = "file")]
     File(PathBuf),
@@ -116,7 +382,10 @@ pub enum OntologyLocation {
 impl std::fmt::Display for OntologyLocation {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            OntologyLocation::File(p) => write!(f, "file://{}", p.to_str().unwrap_or_default()),
+            OntologyLocation::File(p) => match path_to_file_iri(p) {
+                Ok(iri) => write!(f, "{}", iri.as_str()),
+                Err(_) => write!(f, "file://{}", p.to_string_lossy()),
+            },
             OntologyLocation::Url(u) => write!(f, "{}", u),
         }
     }
@@ -144,6 +413,33 @@ impl OntologyLocation {
         }
     }
 
+    /// Returns the graph at this location along with the prefixes declared in the document
+    pub fn graph_with_prefixes(&self) -> Result<(OxigraphGraph, BTreeMap<String, String>)> {
+        match self {
+            OntologyLocation::File(p) => read_file_with_prefixes(p),
+            OntologyLocation::Url(u) => read_url_with_prefixes(u),
+        }
+    }
+
+    /// Reads the document at this location, resolving relative IRIs according to the options
+    pub fn read_document(&self, options: &BaseIriOptions) -> Result<Document> {
+        self.read_document_if_modified(options, &CacheValidators::default())
+    }
+
+    /// Reads the document at this location like `read_document`; a URL is fetched conditionally
+    /// with the validators of its last response, failing with `NotModifiedError` if it did not
+    /// change. Files are always read
+    pub fn read_document_if_modified(
+        &self,
+        options: &BaseIriOptions,
+        validators: &CacheValidators,
+    ) -> Result<Document> {
+        match self {
+            OntologyLocation::File(p) => read_file_document(p, options),
+            OntologyLocation::Url(u) => read_url_document_if_modified(u, options, validators),
+        }
+    }
+
     pub fn is_file(&self) -> bool {
         match self {
             OntologyLocation::File(_) => true,
@@ -160,27 +456,35 @@ impl OntologyLocation {
 
     pub fn from_str(s: &str) -> Result<Self> {
         if s.starts_with("http") || s.starts_with("<http") {
-            Ok(OntologyLocation::Url(s.to_string()))
-        } else {
-            // remove any leading file://
-            let s = s.trim_start_matches("file://");
-            let mut p = PathBuf::from(s);
-            // make sure p is absolute
-            if !p.is_absolute() {
-                p = std::env::current_dir()?.join(p);
+            Ok(OntologyLocation::Url(normalize_iri(s)))
+        } else if s.starts_with("file:") {
+            // file IRIs like file:///C:/ontology.ttl; fall back to stripping the scheme for
+            // relative ones like file://ontology.ttl
+            match file_iri_to_path(s) {
+                Ok(p) => Ok(OntologyLocation::File(p)),
+                Err(_) => Self::from_path(Path::new(s.trim_start_matches("file://"))),
             }
-            Ok(OntologyLocation::File(p))
+        } else {
+            Self::from_path(Path::new(s))
         }
     }
 
-    pub fn to_iri(&self) -> NamedNode {
-        // if it is a file, convert it to a file:// IRI
+    /// Returns the location of a local file; relative paths are resolved against the current
+    /// directory
+    pub fn from_path(path: &Path) -> Result<Self> {
+        if path.is_absolute() {
+            Ok(OntologyLocation::File(path.to_path_buf()))
+        } else {
+            Ok(OntologyLocation::File(std::env::current_dir()?.join(path)))
+        }
+    }
+
+    /// Returns the location as an IRI: a file IRI for files (see path_to_file_iri) and the URL
+    /// otherwise
+    pub fn to_iri(&self) -> Result<NamedNode> {
         match self {
-            OntologyLocation::File(p) => {
-                let p = p.to_str().unwrap_or_default();
-                NamedNode::new(format!("file://{}", p)).unwrap()
-            }
-            OntologyLocation::Url(u) => NamedNode::new(u.clone()).unwrap(),
+            OntologyLocation::File(p) => path_to_file_iri(p),
+            OntologyLocation::Url(u) => Ok(NamedNode::new(u.clone())?),
         }
     }
 
@@ -222,8 +526,45 @@ pub struct Ontology {
     pub imports: Vec<NamedNode>,
     location: Option<OntologyLocation>,
     pub last_updated: Option<DateTime<Utc>>,
-    #[serde_as(as = "HashMap<LocalType, _>")]
-    version_properties: HashMap<NamedNode, String>,
+    #[serde_as(as = "BTreeMap<LocalType, _>")]
+    version_properties: BTreeMap<NamedNode, String>,
+    // prefix -> namespace declarations from the ontology's source document
+    #[serde(default)]
+    namespace_map: BTreeMap<String, String>,
+    // the base IRI relative IRIs in the source document were resolved against
+    #[serde(default)]
+    base_iri: Option<String>,
+    // for a fetched ontology, the URL it was served from after following redirects
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    resolved_url: Option<String>,
+    // hash of the ontology's triples (see lockfile::content_hash)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    content_hash: Option<String>,
+    // hash of the bytes of the source file (see lockfile::source_hash), so files whose
+    // modification time changed without their content are not read again
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    source_hash: Option<String>,
+    // for a fetched ontology, the ETag and Last-Modified of the response it was read from, so
+    // it is fetched again only if it changed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cache_validators: Option<CacheValidators>,
+    // the source document has no owl:Ontology declaration, so the name was made up from its
+    // location (see anonymous_ontology_name)
+    #[serde(default)]
+    anonymous: bool,
+    // metadata extracted by the environment's inspectors, by inspector name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    metadata: BTreeMap<String, serde_json::Value>,
+    // the owl:priorVersion and owl:backwardCompatibleWith values of the ontology declaration
+    #[serde_as(as = "Vec<LocalType>")]
+    #[serde(default)]
+    prior_versions: Vec<NamedNode>,
+    #[serde_as(as = "Vec<LocalType>")]
+    #[serde(default)]
+    backward_compatible_with: Vec<NamedNode>,
+    // user metadata (e.g. owner=controls-team), kept when the ontology is read again
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    annotations: BTreeMap<String, String>,
 }
 
 // impl display; name + location + last updated, then indented version properties
@@ -248,13 +589,25 @@ impl Default for Ontology {
         Ontology {
             id: GraphIdentifier {
                 location: OntologyLocation::File(PathBuf::new()),
-                name: NamedNode::new("<n/a>").unwrap(),
+                name: NamedNode::new_unchecked("urn:ontoenv:n/a"),
+                graph_id: None,
             },
-            name: NamedNode::new("<n/a>").unwrap(),
+            name: NamedNode::new_unchecked("urn:ontoenv:n/a"),
             imports: vec![],
             location: None,
             last_updated: None,
-            version_properties: HashMap::new(),
+            version_properties: BTreeMap::new(),
+            namespace_map: BTreeMap::new(),
+            base_iri: None,
+            resolved_url: None,
+            content_hash: None,
+            source_hash: None,
+            cache_validators: None,
+            anonymous: false,
+            metadata: BTreeMap::new(),
+            prior_versions: vec![],
+            backward_compatible_with: vec![],
+            annotations: BTreeMap::new(),
         }
     }
 }
@@ -268,14 +621,190 @@ impl Ontology {
         self.last_updated = Some(last_updated);
     }
 
+    pub fn with_namespace_map(&mut self, namespace_map: BTreeMap<String, String>) {
+        self.namespace_map = namespace_map;
+    }
+
+    /// Returns the prefix -> namespace declarations of the ontology's source document
+    pub fn namespace_map(&self) -> &BTreeMap<String, String> {
+        &self.namespace_map
+    }
+
+    pub fn with_base_iri(&mut self, base_iri: Option<String>) {
+        self.base_iri = base_iri;
+    }
+
+    /// Returns the base IRI relative IRIs in the ontology's source document were resolved
+    /// against, if any
+    pub fn base_iri(&self) -> Option<&str> {
+        self.base_iri.as_deref()
+    }
+
+    pub fn with_resolved_url(&mut self, resolved_url: Option<String>) {
+        self.resolved_url = resolved_url;
+    }
+
+    /// Returns the URL a fetched ontology was served from, which differs from its location
+    /// when the server redirected the request
+    pub fn resolved_url(&self) -> Option<&str> {
+        self.resolved_url.as_deref()
+    }
+
+    pub fn with_content_hash(&mut self, content_hash: String) {
+        self.content_hash = Some(content_hash);
+    }
+
+    /// Returns the hash of the ontology's triples when it was last read
+    pub fn content_hash(&self) -> Option<&str> {
+        self.content_hash.as_deref()
+    }
+
+    pub fn with_source_hash(&mut self, source_hash: Option<String>) {
+        self.source_hash = source_hash;
+    }
+
+    /// Returns the hash of the source file's bytes when it was last read; none for remote
+    /// ontologies
+    pub fn source_hash(&self) -> Option<&str> {
+        self.source_hash.as_deref()
+    }
+
+    pub fn with_cache_validators(&mut self, cache_validators: Option<CacheValidators>) {
+        self.cache_validators = cache_validators.filter(|v| !v.is_empty());
+    }
+
+    /// Returns the ETag and Last-Modified of the response a fetched ontology was read from
+    pub fn cache_validators(&self) -> Option<&CacheValidators> {
+        self.cache_validators.as_ref()
+    }
+
     pub fn id(&self) -> &GraphIdentifier {
         &self.id
     }
 
-    pub fn version_properties(&self) -> &HashMap<NamedNode, String> {
+    /// Returns true if the ontology's source document has no owl:Ontology declaration
+    pub fn is_anonymous(&self) -> bool {
+        self.anonymous
+    }
+
+    /// Renames an anonymous ontology; declared ontologies keep the name they declare
+    pub fn with_anonymous_name(&mut self, name: NamedNode) {
+        if self.anonymous {
+            self.id.name = name.clone();
+            self.name = name;
+        }
+    }
+
+    /// Names the ontology, whatever its source document declares (see `Config::import_map`)
+    pub fn with_name(&mut self, name: NamedNode) {
+        self.id.name = name.clone();
+        self.name = name;
+    }
+
+    /// Adds the objects of the predicates on the ontology in its graph to its imports, so they
+    /// are fetched and included in closures like owl:imports (see
+    /// `Config::traversal_predicates`)
+    pub fn with_traversal_links(&mut self, graph: &OxigraphGraph, predicates: &[NamedNode]) {
+        for predicate in predicates {
+            for object in graph.objects_for_subject_predicate(self.name.as_ref(), predicate) {
+                let TermRef::NamedNode(target) = object else {
+                    continue;
+                };
+                if target != self.name.as_ref() && !self.imports.iter().any(|i| *i == target) {
+                    self.imports.push(target.into_owned());
+                }
+            }
+        }
+    }
+
+    pub fn with_graph_id(&mut self, graph_id: String) {
+        self.id.with_graph_id(graph_id);
+    }
+
+    /// Returns the metadata extracted by the environment's inspectors (see
+    /// `inspector::OntologyInspector`), by inspector name
+    pub fn metadata(&self) -> &BTreeMap<String, serde_json::Value> {
+        &self.metadata
+    }
+
+    pub fn with_metadata(&mut self, inspector: &str, value: serde_json::Value) {
+        self.metadata.insert(inspector.to_string(), value);
+    }
+
+    /// Returns the user metadata attached to the ontology (see `OntoEnv::set_annotation`)
+    pub fn annotations(&self) -> &BTreeMap<String, String> {
+        &self.annotations
+    }
+
+    pub fn with_annotation(&mut self, key: &str, value: &str) {
+        self.annotations.insert(key.to_string(), value.to_string());
+    }
+
+    pub fn with_annotations(&mut self, annotations: BTreeMap<String, String>) {
+        self.annotations = annotations;
+    }
+
+    /// Removes the annotation, returning its value if it was set
+    pub fn remove_annotation(&mut self, key: &str) -> Option<String> {
+        self.annotations.remove(key)
+    }
+
+    pub fn version_properties(&self) -> &BTreeMap<NamedNode, String> {
         &self.version_properties
     }
 
+    /// Returns the owl:versionIRI of the ontology, if it declares one
+    pub fn version_iri(&self) -> Option<NamedNode> {
+        let value = self.version_properties.get(&VERSION_IRI.into_owned())?;
+        NamedNode::new(value.trim_start_matches('<').trim_end_matches('>')).ok()
+    }
+
+    /// Returns the version of the ontology: its owl:versionInfo, or else the version in its
+    /// owl:versionIRI (the last path segment which looks like one, e.g. `1.3` in
+    /// https://brickschema.org/schema/1.3/Brick)
+    pub fn version(&self) -> Option<String> {
+        if let Some(info) = self.version_properties.get(&VERSION_INFO.into_owned()) {
+            // literals are stored in N-Triples syntax, e.g. "1.3"^^xsd:string
+            let info = info
+                .strip_prefix('"')
+                .and_then(|rest| rest.rsplit_once('"'))
+                .map(|(value, _)| value)
+                .unwrap_or(info);
+            return Some(info.to_string());
+        }
+        let iri = self.version_iri()?;
+        iri.as_str()
+            .split(['/', '#'])
+            .filter(|segment| {
+                segment
+                    .trim_start_matches(['v', 'V'])
+                    .starts_with(|c: char| c.is_ascii_digit())
+            })
+            .last()
+            .map(|segment| segment.to_string())
+    }
+
+    /// Returns the versions this ontology declares as its owl:priorVersion
+    pub fn prior_versions(&self) -> &[NamedNode] {
+        &self.prior_versions
+    }
+
+    /// Returns the versions this ontology declares it is owl:backwardCompatibleWith
+    pub fn backward_compatible_with(&self) -> &[NamedNode] {
+        &self.backward_compatible_with
+    }
+
+    /// Returns true if `version` (a version IRI or location) refers to this ontology
+    pub fn is_version(&self, version: NamedNodeRef) -> bool {
+        self.version_iri()
+            .is_some_and(|iri| iri.as_ref() == version)
+            || self
+                .id
+                .location()
+                .to_iri()
+                .is_ok_and(|iri| iri.as_ref() == version)
+    }
+
     pub fn location(&self) -> Option<&OntologyLocation> {
         self.location.as_ref()
     }
@@ -303,34 +832,51 @@ impl Ontology {
         serde_json::to_string_pretty(self).unwrap()
     }
 
+    /// Reads the ontology declared in the graph. Without an owl:Ontology declaration, the name
+    /// is inferred from the first of the `name_sources` the graph declares; failing that, the
+    /// ontology is anonymous, unless `require_ontology_names` is set
     pub fn from_graph(
         graph: &OxigraphGraph,
         location: OntologyLocation,
         require_ontology_names: bool,
+        name_sources: &[NameSource],
     ) -> Result<Self> {
         // get the rdf:type owl:Ontology declarations
         let decls: Vec<SubjectRef> = graph
             .subjects_for_predicate_object(TYPE, ONTOLOGY)
             .collect::<Vec<_>>();
+        let inferred = if decls.is_empty() {
+            infer_ontology_name(graph, name_sources)
+        } else {
+            None
+        };
+        let anonymous = decls.is_empty() && inferred.is_none();
 
         // ontology_name is the subject of the first declaration
-        let ontology_name: Subject = match decls.first() {
-            Some(decl) => match decl {
-                SubjectRef::NamedNode(s) => Subject::NamedNode((*s).into()),
+        let ontology_name: NamedNode = match (decls.first(), inferred) {
+            (Some(decl), _) => match decl {
+                SubjectRef::NamedNode(s) => s.into_owned(),
                 _ => return Err(anyhow::anyhow!("Ontology name is not an IRI")),
             },
-            None => {
+            (None, Some((name, source))) => {
+                debug!(
+                    "No ontology declaration found in {}. Using its {} {} as its name",
+                    location, source, name
+                );
+                name
+            }
+            (None, None) => {
                 if require_ontology_names {
                     return Err(anyhow::anyhow!(
                         "No ontology declaration found in {}",
                         location
                     ));
                 }
-                warn!(
-                    "No ontology declaration found in {}. Using this as the ontology name",
+                debug!(
+                    "No ontology declaration found in {}. Treating it as an anonymous ontology",
                     location
                 );
-                Subject::NamedNode(location.to_iri())
+                location.to_iri()?
             }
         };
         debug!("got ontology name: {}", ontology_name);
@@ -339,10 +885,10 @@ impl Ontology {
             .collect::<Vec<_>>();
 
         // get each of the ONNTOLOGY_VERSION_IRIS values, if they exist on the ontology
-        let mut version_properties: HashMap<NamedNode, String> =
+        let mut version_properties: BTreeMap<NamedNode, String> =
             ONTOLOGY_VERSION_IRIS
                 .iter()
-                .fold(HashMap::new(), |mut acc, &iri| {
+                .fold(BTreeMap::new(), |mut acc, &iri| {
                     if let Some(o) = graph.object_for_subject_predicate(ontology_name.as_ref(), iri)
                     {
                         match o {
@@ -392,29 +938,52 @@ impl Ontology {
             ontology_name, location
         );
 
-        let ontology_name: NamedNode = match ontology_name {
-            Subject::NamedNode(s) => s,
-            _ => panic!("Ontology name is not an IRI"),
-        };
-
+        // imports which are not IRIs are skipped; the doctor reports them
         let imports: Vec<NamedNode> = imports
             .iter()
-            .map(|t| match t {
-                TermRef::NamedNode(s) => Ok(NamedNode::new(s.as_str())?),
-                _ => panic!("Import is not an IRI"),
+            .filter_map(|t| match t {
+                TermRef::NamedNode(s) => Some(s.into_owned()),
+                other => {
+                    warn!("Skipping owl:imports value which is not an IRI: {}", other);
+                    None
+                }
             })
-            .collect::<Result<Vec<NamedNode>>>()?;
+            .collect();
+
+        let version_links = |predicate: NamedNodeRef| {
+            graph
+                .objects_for_subject_predicate(ontology_name.as_ref(), predicate)
+                .filter_map(|t| match t {
+                    TermRef::NamedNode(s) => Some(s.into_owned()),
+                    _ => None,
+                })
+                .collect::<Vec<NamedNode>>()
+        };
+        let prior_versions = version_links(PRIOR_VERSION);
+        let backward_compatible_with = version_links(BACKWARD_COMPATIBLE_WITH);
 
         Ok(Ontology {
             id: GraphIdentifier {
                 location: location.clone(),
                 name: ontology_name.clone(),
+                graph_id: None,
             },
             name: ontology_name,
             imports,
             location: Some(location),
             version_properties,
             last_updated: None,
+            namespace_map: BTreeMap::new(),
+            base_iri: None,
+            resolved_url: None,
+            content_hash: None,
+            source_hash: None,
+            cache_validators: None,
+            anonymous,
+            metadata: BTreeMap::new(),
+            prior_versions,
+            backward_compatible_with,
+            annotations: BTreeMap::new(),
         })
     }
 
@@ -427,7 +996,24 @@ impl Ontology {
 mod tests {
     use super::*;
 
-    use oxigraph::model::NamedNode;
+    use oxigraph::model::{LiteralRef, NamedNode, TripleRef};
+
+    #[test]
+    fn test_on_disk_casing() {
+        let dir = tempdir::TempDir::new("ontoenv-casing").unwrap();
+        std::fs::create_dir(dir.path().join("Models")).unwrap();
+        let path = dir.path().join("Models").join("Building.ttl");
+        std::fs::write(&path, "").unwrap();
+        assert_eq!(on_disk_casing(&path), path);
+
+        // only case-insensitive file systems find the file with another casing
+        let other = dir.path().join("models").join("building.ttl");
+        if other.exists() {
+            assert_eq!(on_disk_casing(&other), path);
+        } else {
+            assert_eq!(on_disk_casing(&other), other);
+        }
+    }
 
     #[test]
     fn test_ontology_location() {
@@ -441,6 +1027,35 @@ mod tests {
         assert!(file_location.is_file());
     }
 
+    #[test]
+    fn test_default_ontology() {
+        let ontology = Ontology::default();
+        assert!(ontology.imports.is_empty());
+        assert_eq!(ontology.name().as_str(), ontology.id().name().as_str());
+    }
+
+    #[test]
+    fn test_graph_id() {
+        let name = NamedNodeRef::new("urn:ontology").unwrap();
+        let legacy = GraphIdentifier::new(name);
+        let mut id = GraphIdentifier::new(name);
+        id.with_graph_id("1234".to_string());
+        // the graph ID only decides where the graph is stored
+        assert_eq!(legacy, id);
+        assert_eq!(
+            id.graphname().unwrap(),
+            GraphName::NamedNode(NamedNode::new("urn:ontoenv:graph:1234").unwrap())
+        );
+        assert_eq!(
+            legacy.graphname().unwrap(),
+            legacy.legacy_graphname().unwrap()
+        );
+        assert_ne!(
+            GraphIdentifier::new_graph_id(),
+            GraphIdentifier::new_graph_id()
+        );
+    }
+
     #[test]
     fn test_ontology_location_display() {
         let url = "http://example.com/ontology.ttl";
@@ -457,10 +1072,115 @@ mod tests {
         let file = "/tmp/ontology.ttl";
         let url_location = OntologyLocation::from_str(url).unwrap();
         let file_location = OntologyLocation::from_str(file).unwrap();
-        assert_eq!(url_location.to_iri(), NamedNode::new(url).unwrap());
+        assert_eq!(url_location.to_iri().unwrap(), NamedNode::new(url).unwrap());
         assert_eq!(
-            file_location.to_iri(),
+            file_location.to_iri().unwrap(),
             NamedNode::new(format!("file://{}", file)).unwrap()
         );
     }
+
+    #[test]
+    fn test_file_iri_roundtrip() {
+        let location = OntologyLocation::from_str("/tmp/my ontologies/ontology.ttl").unwrap();
+        let iri = location.to_iri().unwrap();
+        assert_eq!(iri.as_str(), "file:///tmp/my%20ontologies/ontology.ttl");
+        assert_eq!(OntologyLocation::from_str(iri.as_str()).unwrap(), location);
+        assert!(file_iri_to_path("http://example.org/ontology.ttl").is_err());
+    }
+
+    #[test]
+    fn test_relative_location() {
+        let location = OntologyLocation::from_str("ontology.ttl").unwrap();
+        let expected = std::env::current_dir().unwrap().join("ontology.ttl");
+        assert_eq!(location, OntologyLocation::File(expected));
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn test_windows_file_iris() {
+        let drive = OntologyLocation::File(PathBuf::from(r"C:\Users\me\ontology.ttl"));
+        assert_eq!(
+            drive.to_iri().unwrap().as_str(),
+            "file:///C:/Users/me/ontology.ttl"
+        );
+        assert_eq!(drive.to_string(), "file:///C:/Users/me/ontology.ttl");
+        assert_eq!(
+            OntologyLocation::from_str("file:///C:/Users/me/ontology.ttl").unwrap(),
+            drive
+        );
+
+        let unc = OntologyLocation::File(PathBuf::from(r"\\server\share\ontology.ttl"));
+        assert_eq!(
+            unc.to_iri().unwrap().as_str(),
+            "file://server/share/ontology.ttl"
+        );
+
+        // graph names and file names must not contain backslashes or drive colons
+        let id = GraphIdentifier {
+            location: drive,
+            name: NamedNode::new("http://example.org/ontology").unwrap(),
+            graph_id: None,
+        };
+        assert!(id.graphname().is_ok());
+        let id = GraphIdentifier {
+            location: OntologyLocation::File(PathBuf::from(r"C:\Users\me\ontology.ttl")),
+            name: NamedNode::new("urn:ontology").unwrap(),
+            graph_id: None,
+        };
+        assert!(id.graphname().is_ok());
+        assert!(!id.to_filename().contains('\\'));
+    }
+
+    #[test]
+    fn test_infer_ontology_name() {
+        let scheme = NamedNode::new("http://example.org/scheme").unwrap();
+        let dataset = NamedNode::new("http://example.org/dataset").unwrap();
+        let mut graph = OxigraphGraph::new();
+        graph.insert(TripleRef::new(&scheme, TYPE, SKOS_CONCEPT_SCHEME));
+        graph.insert(TripleRef::new(&dataset, TYPE, VOID_DATASET));
+        graph.insert(TripleRef::new(
+            &dataset,
+            DC_IDENTIFIER,
+            LiteralRef::new_simple_literal("urn:example:identifier"),
+        ));
+
+        // the sources are tried in order
+        let inferred = |sources: &[NameSource]| {
+            infer_ontology_name(&graph, sources).map(|(name, _)| name.as_str().to_string())
+        };
+        assert_eq!(
+            inferred(&[NameSource::SkosConceptScheme, NameSource::VoidDataset]).as_deref(),
+            Some("http://example.org/scheme")
+        );
+        assert_eq!(
+            inferred(&[NameSource::VoidDataset, NameSource::SkosConceptScheme]).as_deref(),
+            Some("http://example.org/dataset")
+        );
+        assert_eq!(
+            inferred(&[NameSource::DctermsIdentifier]).as_deref(),
+            Some("urn:example:identifier")
+        );
+        assert_eq!(inferred(&[]), None);
+
+        let ontology = Ontology::from_graph(
+            &graph,
+            OntologyLocation::Url("http://example.org/data.ttl".to_string()),
+            true,
+            &[NameSource::SkosConceptScheme],
+        )
+        .unwrap();
+        assert_eq!(ontology.name(), scheme);
+        assert!(!ontology.is_anonymous());
+        assert!(Ontology::from_graph(
+            &graph,
+            OntologyLocation::Url("http://example.org/data.ttl".to_string()),
+            true,
+            &[],
+        )
+        .is_err());
+        assert_eq!(
+            "skos:ConceptScheme".parse::<NameSource>().unwrap(),
+            NameSource::SkosConceptScheme
+        );
+    }
 }
@@ -1,10 +1,16 @@
 // define a trait for a resolution policy. Given an ontology name and a set of possible ontologies,
-// the policy should return the ontology that should be used.
+// the policy should return the ontology that should be used. Also holds the version requirements
+// the configuration can place on ontologies, see constraints::check_version_constraints
 
 use crate::consts::ONTOLOGY_VERSION_IRIS;
 use crate::ontology::Ontology;
+use anyhow::Result;
 use oxigraph::model::NamedNode;
 use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::VecDeque;
+use std::fmt;
+use std::str::FromStr;
 
 pub trait ResolutionPolicy {
     fn resolve<'a>(&self, name: &str, ontologies: &'a [&'a Ontology]) -> Option<&'a Ontology>;
@@ -16,6 +22,7 @@ pub fn policy_from_name(name: &str) -> Option<Box<dyn ResolutionPolicy>> {
         "default" => Some(Box::new(DefaultPolicy)),
         "latest" => Some(Box::new(LatestPolicy)),
         "version" => Some(Box::new(VersionPolicy)),
+        "compatible" => Some(Box::new(CompatibilityPolicy)),
         _ => None,
     }
 }
@@ -97,3 +104,158 @@ impl ResolutionPolicy for VersionPolicy {
         "version"
     }
 }
+
+/// Returns true if `newer` declares that it is owl:backwardCompatibleWith `older`, directly or
+/// through other versions among `ontologies`
+pub fn is_backward_compatible(
+    newer: &Ontology,
+    older: &Ontology,
+    ontologies: &[&Ontology],
+) -> bool {
+    let mut seen: Vec<&Ontology> = vec![newer];
+    let mut stack: VecDeque<&Ontology> = VecDeque::from([newer]);
+    while let Some(ontology) = stack.pop_front() {
+        for version in ontology.backward_compatible_with() {
+            if older.is_version(version.as_ref()) {
+                return true;
+            }
+            for o in ontologies.iter().filter(|o| o.is_version(version.as_ref())) {
+                if !seen.contains(o) {
+                    seen.push(*o);
+                    stack.push_back(*o);
+                }
+            }
+        }
+    }
+    false
+}
+
+/// A resolution policy that follows the owl:priorVersion and owl:backwardCompatibleWith
+/// declarations of the ontologies: it returns the version which no other version names as its
+/// prior (or compatible) version. If the declarations do not single out one version, it falls
+/// back to the version policy
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct CompatibilityPolicy;
+
+impl ResolutionPolicy for CompatibilityPolicy {
+    fn resolve<'a>(&self, name: &str, ontologies: &'a [&'a Ontology]) -> Option<&'a Ontology> {
+        let candidates: Vec<&Ontology> = ontologies
+            .iter()
+            .filter(|o| o.name() == name)
+            .copied()
+            .collect();
+        let superseded = |ontology: &Ontology| {
+            candidates.iter().any(|other| {
+                other
+                    .prior_versions()
+                    .iter()
+                    .chain(other.backward_compatible_with())
+                    .any(|version| ontology.is_version(version.as_ref()))
+            })
+        };
+        let latest: Vec<&Ontology> = candidates
+            .iter()
+            .filter(|o| !superseded(**o))
+            .copied()
+            .collect();
+        match latest.as_slice() {
+            [ontology] => Some(*ontology),
+            [] => VersionPolicy.resolve(name, ontologies),
+            _ => VersionPolicy
+                .resolve(name, &latest)
+                .and_then(|picked| ontologies.iter().find(|o| o.id() == picked.id()).copied()),
+        }
+    }
+
+    fn policy_name(&self) -> &'static str {
+        "compatible"
+    }
+}
+
+/// Compares two version strings like `1.3`, `v1.4.1` or `2.1-rc1` component by component:
+/// numeric components are compared as numbers, others as text, and missing components count
+/// as 0, so `1.3` and `1.3.0` are equal
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let components = |v: &str| -> Vec<String> {
+        v.trim()
+            .trim_start_matches(['v', 'V'])
+            .split(['.', '-', '_', '+'])
+            .map(|c| c.to_string())
+            .collect()
+    };
+    let (a, b) = (components(a), components(b));
+    for i in 0..a.len().max(b.len()) {
+        let x = a.get(i).map(String::as_str).unwrap_or("0");
+        let y = b.get(i).map(String::as_str).unwrap_or("0");
+        let ordering = match (x.parse::<u64>(), y.parse::<u64>()) {
+            (Ok(x), Ok(y)) => x.cmp(&y),
+            _ => x.cmp(y),
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
+
+// the comparisons a version requirement is made of, longest operators first so `>=` is not
+// read as `>`
+const VERSION_OPERATORS: [&str; 7] = [">=", "<=", "==", "!=", ">", "<", "="];
+
+/// A requirement on the version of an ontology, e.g. `>=1.3` or `>=1.3, <2`: a version
+/// satisfies it if it satisfies each of the comma-separated comparisons. A version without an
+/// operator must match exactly
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionRequirement {
+    comparisons: Vec<(&'static str, String)>,
+}
+
+impl VersionRequirement {
+    /// Returns true if the version satisfies the requirement
+    pub fn matches(&self, version: &str) -> bool {
+        self.comparisons.iter().all(|(op, required)| {
+            let ordering = compare_versions(version, required);
+            match *op {
+                ">=" => ordering != Ordering::Less,
+                "<=" => ordering != Ordering::Greater,
+                ">" => ordering == Ordering::Greater,
+                "<" => ordering == Ordering::Less,
+                "!=" => ordering != Ordering::Equal,
+                _ => ordering == Ordering::Equal,
+            }
+        })
+    }
+}
+
+impl FromStr for VersionRequirement {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut comparisons = vec![];
+        for comparison in s.split(',') {
+            let comparison = comparison.trim();
+            let op = VERSION_OPERATORS
+                .iter()
+                .find(|op| comparison.starts_with(**op))
+                .copied()
+                .unwrap_or("=");
+            let version = comparison.trim_start_matches(op).trim();
+            if version.is_empty() {
+                return Err(anyhow::anyhow!("Invalid version requirement: {}", s));
+            }
+            comparisons.push((op, version.to_string()));
+        }
+        Ok(VersionRequirement { comparisons })
+    }
+}
+
+impl fmt::Display for VersionRequirement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let comparisons: Vec<String> = self
+            .comparisons
+            .iter()
+            .map(|(op, version)| format!("{}{}", op, version))
+            .collect();
+        write!(f, "{}", comparisons.join(", "))
+    }
+}
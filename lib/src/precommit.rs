@@ -0,0 +1,229 @@
+// fast checks of individual RDF files against the environment, meant to run as a git
+// pre-commit hook on the staged files. Only the given files are parsed (for staged files, the
+// content which is about to be committed rather than the working tree copy); everything else is
+// read from the environment as of its last refresh, so the checks stay well under a second.
+
+use crate::consts::{IMPORTS, ONTOLOGY, TYPE};
+use crate::doctor::OntologyProblem;
+use crate::iri::normalize_ontology_iris;
+use crate::ontology::OntologyLocation;
+use crate::util::{parse_file_content, Document};
+use crate::OntoEnv;
+use anyhow::Result;
+use oxigraph::model::{NamedNode, SubjectRef, TermRef};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const HOOK_SCRIPT: &str = "#!/bin/sh\n# installed by ontoenv\nexec ontoenv hook pre-commit\n";
+
+/// Returns the files staged in the git repository containing `dir` which are added, copied
+/// or modified
+pub fn staged_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    let toplevel = git(dir, &["rev-parse", "--show-toplevel"])?;
+    let toplevel = PathBuf::from(toplevel.trim());
+    let staged = git(
+        dir,
+        &["diff", "--cached", "--name-only", "--diff-filter=ACM", "-z"],
+    )?;
+    Ok(staged
+        .split('\0')
+        .filter(|f| !f.is_empty())
+        .map(|f| toplevel.join(f))
+        .collect())
+}
+
+/// Returns the content of the file as staged in git, which differs from the working tree copy
+/// if only some of its changes are staged (e.g. with `git add -p`)
+pub fn staged_content(file: &Path) -> Result<Vec<u8>> {
+    let dir = file.parent().unwrap_or(Path::new("."));
+    let name = file
+        .file_name()
+        .ok_or(anyhow::anyhow!("Not a file: {}", file.display()))?;
+    // ':./<name>' is the index entry of the file relative to the directory git runs in
+    git_bytes(dir, &["show", &format!(":./{}", name.to_string_lossy())])
+}
+
+fn git(dir: &Path, args: &[&str]) -> Result<String> {
+    Ok(String::from_utf8(git_bytes(dir, args)?)?)
+}
+
+fn git_bytes(dir: &Path, args: &[&str]) -> Result<Vec<u8>> {
+    let output = Command::new("git").args(args).current_dir(dir).output()?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(output.stdout)
+}
+
+/// Installs a pre-commit hook running `ontoenv hook pre-commit` into the git repository
+/// containing `dir`. Will not overwrite a hook which was not installed by ontoenv
+pub fn install_hook(dir: &Path) -> Result<PathBuf> {
+    let hooks = git(dir, &["rev-parse", "--git-path", "hooks"])?;
+    let hooks = dir.join(hooks.trim());
+    std::fs::create_dir_all(&hooks)?;
+    let path = hooks.join("pre-commit");
+    if path.exists() && std::fs::read_to_string(&path)? != HOOK_SCRIPT {
+        return Err(anyhow::anyhow!(
+            "A pre-commit hook already exists at {:?}; add 'ontoenv hook pre-commit' to it instead",
+            path
+        ));
+    }
+    std::fs::write(&path, HOOK_SCRIPT)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))?;
+    }
+    Ok(path)
+}
+
+/// A parsed file and the name and imports of the ontology it declares
+struct CheckedFile {
+    location: OntologyLocation,
+    name: Option<NamedNode>,
+    imports: Vec<NamedNode>,
+}
+
+/// Checks the files: that they parse, declare exactly one ontology, do not redefine an
+/// ontology from another file in the environment, only import resolvable ontologies, and
+/// respect the configured import constraints
+pub fn check_files(env: &OntoEnv, files: &[PathBuf]) -> Result<Vec<OntologyProblem>> {
+    let options = env.config.base_iri_options();
+    check_documents(env, files, |location| location.read_document(&options))
+}
+
+/// Like `check_files`, but checks the content of the files staged in git (see `staged_content`)
+pub fn check_staged_files(env: &OntoEnv, files: &[PathBuf]) -> Result<Vec<OntologyProblem>> {
+    let options = env.config.base_iri_options();
+    check_documents(env, files, |location| {
+        let path = location
+            .as_path()
+            .ok_or(anyhow::anyhow!("Not a file: {}", location))?;
+        parse_file_content(path, &staged_content(path)?, &options)
+    })
+}
+
+fn check_documents(
+    env: &OntoEnv,
+    files: &[PathBuf],
+    read: impl Fn(&OntologyLocation) -> Result<Document>,
+) -> Result<Vec<OntologyProblem>> {
+    let mut problems = vec![];
+    let mut checked = vec![];
+    for file in files {
+        let location = OntologyLocation::from_path(file)?;
+        let mut graph = match read(&location) {
+            Ok(document) => document.graph,
+            Err(e) => {
+                problems.push(OntologyProblem {
+                    locations: vec![location],
+                    graph: None,
+                    message: format!("Failed to parse: {}", e),
+                });
+                continue;
+            }
+        };
+        // compare names and imports the way the environment stores them
+        normalize_ontology_iris(&mut graph);
+        let names: Vec<NamedNode> = graph
+            .subjects_for_predicate_object(TYPE, ONTOLOGY)
+            .filter_map(|s| match s {
+                SubjectRef::NamedNode(n) => Some(n.into_owned()),
+                _ => None,
+            })
+            .collect();
+        let name = match names.len() {
+            1 => names.into_iter().next(),
+            0 => {
+                problems.push(OntologyProblem {
+                    locations: vec![location.clone()],
+                    graph: None,
+                    message: "No ontology declaration found".to_string(),
+                });
+                None
+            }
+            _ => {
+                problems.push(OntologyProblem {
+                    locations: vec![location.clone()],
+                    graph: None,
+                    message: "Multiple ontology declarations found".to_string(),
+                });
+                None
+            }
+        };
+        let imports = match &name {
+            Some(name) => graph
+                .objects_for_subject_predicate(name, IMPORTS)
+                .filter_map(|o| match o {
+                    TermRef::NamedNode(n) => Some(n.into_owned()),
+                    _ => None,
+                })
+                .collect(),
+            None => vec![],
+        };
+        checked.push(CheckedFile {
+            location,
+            name,
+            imports,
+        });
+    }
+
+    // ontologies declared by the checked files can be imported by each other, even if they
+    // are not in the environment yet
+    let declared: HashSet<&NamedNode> = checked.iter().filter_map(|c| c.name.as_ref()).collect();
+
+    for file in checked.iter() {
+        let name = match &file.name {
+            Some(name) => name,
+            None => continue,
+        };
+        for ontology in env.ontologies().values() {
+            if &ontology.name() == name && ontology.id().location() != &file.location {
+                problems.push(OntologyProblem {
+                    locations: vec![file.location.clone(), ontology.id().location().clone()],
+                    graph: None,
+                    message: format!("Multiple ontologies with name {}", name),
+                });
+            }
+        }
+
+        let mut reachable: Vec<NamedNode> = vec![];
+        for import in file.imports.iter() {
+            reachable.push(import.clone());
+            match env.get_ontology_by_name(import.as_ref()) {
+                Some(ontology) => {
+                    for member in env.get_dependency_closure(ontology.id())? {
+                        reachable.push(member.name().into_owned());
+                    }
+                }
+                None if !declared.contains(import) => problems.push(OntologyProblem {
+                    locations: vec![file.location.clone()],
+                    graph: None,
+                    message: format!("Unresolved import {}", import),
+                }),
+                None => {}
+            }
+        }
+        reachable.sort();
+        reachable.dedup();
+
+        for constraint in env.config.import_constraints.iter() {
+            if !constraint.applies_to(&file.location, &env.config.root) {
+                continue;
+            }
+            for forbidden in reachable.iter().filter(|n| constraint.forbids(n.as_str())) {
+                problems.push(OntologyProblem {
+                    locations: vec![file.location.clone()],
+                    graph: None,
+                    message: format!("Forbidden import of {} ({})", forbidden, constraint),
+                });
+            }
+        }
+    }
+    Ok(problems)
+}
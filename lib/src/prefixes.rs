@@ -0,0 +1,300 @@
+// prefix bindings learned from the source documents of the ontologies in an environment, used to
+// suggest prefixes for other graphs (e.g. instance data written without any @prefix lines) and
+// to expand and shrink CURIEs against the environment as a whole (see `PrefixRegistry`)
+
+use crate::ontology::Ontology;
+use crate::util::graph_iris;
+use oxigraph::model::Graph;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ops::Bound;
+
+/// How often the ontologies of an environment bind each namespace to each prefix
+#[derive(Debug, Clone, Default)]
+pub struct PrefixUsage {
+    // namespace -> prefix -> number of ontologies binding the prefix to the namespace
+    bindings: BTreeMap<String, BTreeMap<String, usize>>,
+}
+
+/// A prefix binding proposed for a graph
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PrefixSuggestion {
+    pub prefix: String,
+    pub namespace: String,
+    // number of distinct IRIs of the graph in the namespace
+    pub terms: usize,
+    // number of ontologies in the environment which bind the prefix to the namespace
+    pub ontologies: usize,
+}
+
+impl PrefixUsage {
+    pub fn from_ontologies<'a>(ontologies: impl IntoIterator<Item = &'a Ontology>) -> Self {
+        let mut usage = PrefixUsage::default();
+        for ontology in ontologies {
+            for (prefix, namespace) in ontology.namespace_map() {
+                usage.record(prefix, namespace);
+            }
+        }
+        usage
+    }
+
+    /// Records one more binding of the prefix to the namespace. Empty (default) prefixes are
+    /// ignored, since every document binds them to its own namespace
+    pub fn record(&mut self, prefix: &str, namespace: &str) {
+        if prefix.is_empty() || namespace.is_empty() {
+            return;
+        }
+        *self
+            .bindings
+            .entry(namespace.to_string())
+            .or_default()
+            .entry(prefix.to_string())
+            .or_default() += 1;
+    }
+
+    /// Returns the known namespaces, sorted
+    pub fn namespaces(&self) -> impl Iterator<Item = &str> {
+        self.bindings.keys().map(String::as_str)
+    }
+
+    /// Returns the number of ontologies which bind the namespace, to any prefix
+    pub fn frequency(&self, namespace: &str) -> usize {
+        self.bindings
+            .get(namespace)
+            .map_or(0, |prefixes| prefixes.values().sum())
+    }
+
+    /// Returns the prefixes bound to the namespace with their number of ontologies, most used
+    /// first; ties go to the shorter prefix
+    pub fn prefixes(&self, namespace: &str) -> Vec<(&str, usize)> {
+        let mut prefixes: Vec<(&str, usize)> = self
+            .bindings
+            .get(namespace)
+            .map(|prefixes| {
+                prefixes
+                    .iter()
+                    .map(|(prefix, count)| (prefix.as_str(), *count))
+                    .collect()
+            })
+            .unwrap_or_default();
+        prefixes.sort_by(|(a, a_count), (b, b_count)| {
+            b_count
+                .cmp(a_count)
+                .then(a.len().cmp(&b.len()))
+                .then(a.cmp(b))
+        });
+        prefixes
+    }
+
+    /// Returns the longest known namespace the IRI starts with
+    pub fn namespace_of(&self, iri: &str) -> Option<&str> {
+        // every namespace which is a prefix of the IRI sorts before it, and the longest of them
+        // sorts last
+        self.bindings
+            .range::<str, _>((Bound::Unbounded, Bound::Included(iri)))
+            .rev()
+            .map(|(namespace, _)| namespace.as_str())
+            .find(|namespace| iri.starts_with(namespace))
+    }
+
+    /// Proposes a prefix for every known namespace the graph uses, sorted by prefix. Each
+    /// namespace gets its most used prefix which is not already taken by a namespace with more
+    /// terms in the graph
+    pub fn suggest(&self, graph: &Graph) -> Vec<PrefixSuggestion> {
+        let mut terms: HashMap<&str, usize> = HashMap::new();
+        for iri in graph_iris(graph) {
+            if let Some(namespace) = self.namespace_of(iri.as_str()) {
+                *terms.entry(namespace).or_default() += 1;
+            }
+        }
+        let mut namespaces: Vec<(&str, usize)> = terms.into_iter().collect();
+        namespaces.sort_by(|(a, a_terms), (b, b_terms)| b_terms.cmp(a_terms).then(a.cmp(b)));
+
+        let mut taken: HashSet<&str> = HashSet::new();
+        let mut suggestions = vec![];
+        for (namespace, terms) in namespaces {
+            let prefix = self
+                .prefixes(namespace)
+                .into_iter()
+                .find(|(prefix, _)| !taken.contains(prefix));
+            if let Some((prefix, ontologies)) = prefix {
+                taken.insert(prefix);
+                suggestions.push(PrefixSuggestion {
+                    prefix: prefix.to_string(),
+                    namespace: namespace.to_string(),
+                    terms,
+                    ontologies,
+                });
+            }
+        }
+        suggestions.sort_by(|a, b| a.prefix.cmp(&b.prefix));
+        suggestions
+    }
+}
+
+/// One prefix per namespace and one namespace per prefix, merged from the bindings of all
+/// ontologies of an environment (see `OntoEnv::prefixes`). Namespaces bound by more ontologies
+/// choose first, each taking its most used prefix which is still free; a namespace whose
+/// prefixes are all taken is left out
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PrefixRegistry {
+    // prefix -> namespace
+    prefixes: BTreeMap<String, String>,
+    // namespace -> prefix
+    namespaces: BTreeMap<String, String>,
+}
+
+impl PrefixRegistry {
+    pub fn from_usage(usage: &PrefixUsage) -> Self {
+        let mut namespaces: Vec<(&str, usize)> = usage
+            .namespaces()
+            .map(|namespace| (namespace, usage.frequency(namespace)))
+            .collect();
+        namespaces.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then(a.cmp(b)));
+        let mut registry = PrefixRegistry::default();
+        for (namespace, _) in namespaces {
+            let prefix = usage
+                .prefixes(namespace)
+                .into_iter()
+                .map(|(prefix, _)| prefix)
+                .find(|prefix| !registry.prefixes.contains_key(*prefix));
+            if let Some(prefix) = prefix {
+                registry
+                    .prefixes
+                    .insert(prefix.to_string(), namespace.to_string());
+                registry
+                    .namespaces
+                    .insert(namespace.to_string(), prefix.to_string());
+            }
+        }
+        registry
+    }
+
+    /// Returns the prefix -> namespace bindings, sorted by prefix
+    pub fn prefixes(&self) -> &BTreeMap<String, String> {
+        &self.prefixes
+    }
+
+    /// Returns the namespace bound to the prefix
+    pub fn namespace(&self, prefix: &str) -> Option<&str> {
+        self.prefixes.get(prefix).map(String::as_str)
+    }
+
+    /// Expands a CURIE such as brick:Sensor to the full IRI. Returns None if the prefix is not
+    /// bound or the string is not a CURIE
+    pub fn expand(&self, curie: &str) -> Option<String> {
+        let (prefix, local) = curie.split_once(':')?;
+        self.namespace(prefix)
+            .map(|namespace| format!("{}{}", namespace, local))
+    }
+
+    /// Shrinks an IRI to a CURIE with the prefix of the longest namespace it starts with.
+    /// Returns None if no namespace matches
+    pub fn shrink(&self, iri: &str) -> Option<String> {
+        // as in `PrefixUsage::namespace_of`, the longest matching namespace sorts last
+        self.namespaces
+            .range::<str, _>((Bound::Unbounded, Bound::Included(iri)))
+            .rev()
+            .find(|(namespace, _)| iri.starts_with(namespace.as_str()))
+            .map(|(namespace, prefix)| format!("{}:{}", prefix, &iri[namespace.len()..]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use oxigraph::model::vocab::rdf;
+    use oxigraph::model::{NamedNodeRef, TripleRef};
+
+    #[test]
+    fn test_prefix_suggestions() {
+        let mut usage = PrefixUsage::default();
+        usage.record("brick", "https://brickschema.org/schema/Brick#");
+        usage.record("brick", "https://brickschema.org/schema/Brick#");
+        usage.record("b", "https://brickschema.org/schema/Brick#");
+        usage.record("rdf", "http://www.w3.org/1999/02/22-rdf-syntax-ns#");
+        // the same prefix for another namespace
+        usage.record("brick", "https://brickschema.org/schema/Brick/ref#");
+        usage.record("", "http://example.org/building#");
+
+        assert_eq!(usage.frequency("https://brickschema.org/schema/Brick#"), 3);
+        assert_eq!(
+            usage.namespace_of("https://brickschema.org/schema/Brick/ref#hasExternalReference"),
+            Some("https://brickschema.org/schema/Brick/ref#")
+        );
+        assert_eq!(usage.namespace_of("http://example.org/building#ahu1"), None);
+
+        let mut graph = Graph::new();
+        let ahu = NamedNodeRef::new("http://example.org/building#ahu1").unwrap();
+        let ahu_class = NamedNodeRef::new("https://brickschema.org/schema/Brick#AHU").unwrap();
+        let reference =
+            NamedNodeRef::new("https://brickschema.org/schema/Brick/ref#hasExternalReference")
+                .unwrap();
+        let timeseries =
+            NamedNodeRef::new("https://brickschema.org/schema/Brick/ref#hasTimeseriesReference")
+                .unwrap();
+        graph.insert(TripleRef::new(ahu, rdf::TYPE, ahu_class));
+        graph.insert(TripleRef::new(ahu, reference, ahu_class));
+        graph.insert(TripleRef::new(ahu, timeseries, ahu_class));
+
+        // the ref namespace has more terms in the graph, so it gets "brick" and the Brick
+        // namespace falls back to "b"
+        let suggestions = usage.suggest(&graph);
+        let prefixes: Vec<(&str, &str, usize)> = suggestions
+            .iter()
+            .map(|s| (s.prefix.as_str(), s.namespace.as_str(), s.terms))
+            .collect();
+        assert_eq!(
+            prefixes,
+            vec![
+                ("b", "https://brickschema.org/schema/Brick#", 1),
+                ("brick", "https://brickschema.org/schema/Brick/ref#", 2),
+                ("rdf", "http://www.w3.org/1999/02/22-rdf-syntax-ns#", 1),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_prefix_registry() {
+        let mut usage = PrefixUsage::default();
+        usage.record("brick", "https://brickschema.org/schema/Brick#");
+        usage.record("brick", "https://brickschema.org/schema/Brick#");
+        usage.record("b", "https://brickschema.org/schema/Brick#");
+        // a less used namespace bound to the same prefix does not get it
+        usage.record("brick", "https://brickschema.org/schema/Brick/ref#");
+        usage.record("ref", "https://brickschema.org/schema/Brick/ref#");
+        // "b" is still free, since the Brick namespace took "brick"
+        usage.record("b", "http://example.org/b#");
+        // every prefix of this namespace is taken, so it is left out
+        usage.record("ref", "http://example.org/other#");
+
+        let registry = PrefixRegistry::from_usage(&usage);
+        let prefixes: Vec<(&str, &str)> = registry
+            .prefixes()
+            .iter()
+            .map(|(p, n)| (p.as_str(), n.as_str()))
+            .collect();
+        assert_eq!(
+            prefixes,
+            vec![
+                ("b", "http://example.org/b#"),
+                ("brick", "https://brickschema.org/schema/Brick#"),
+                ("ref", "https://brickschema.org/schema/Brick/ref#"),
+            ]
+        );
+
+        assert_eq!(
+            registry.expand("brick:Sensor").as_deref(),
+            Some("https://brickschema.org/schema/Brick#Sensor")
+        );
+        assert_eq!(registry.expand("unknown:Sensor"), None);
+        assert_eq!(registry.expand("Sensor"), None);
+        assert_eq!(
+            registry
+                .shrink("https://brickschema.org/schema/Brick/ref#hasExternalReference")
+                .as_deref(),
+            Some("ref:hasExternalReference")
+        );
+        assert_eq!(registry.shrink("http://example.org/other#x"), None);
+    }
+}
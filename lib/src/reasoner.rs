@@ -0,0 +1,381 @@
+// pluggable reasoners which compute the triples entailed by a graph, used to attach inference
+// products to imports closures (see OntoEnv::materialize and the release module)
+
+use crate::consts::TYPE;
+use crate::util;
+use anyhow::Result;
+use oxigraph::model::vocab::rdfs;
+use oxigraph::model::{Graph, NamedNodeRef, SubjectRef, TermRef, Triple, TripleRef};
+use std::process::Command;
+use tracing::info;
+
+const SYMMETRIC_PROPERTY: NamedNodeRef<'_> =
+    NamedNodeRef::new_unchecked("http://www.w3.org/2002/07/owl#SymmetricProperty");
+const TRANSITIVE_PROPERTY: NamedNodeRef<'_> =
+    NamedNodeRef::new_unchecked("http://www.w3.org/2002/07/owl#TransitiveProperty");
+const INVERSE_OF: NamedNodeRef<'_> =
+    NamedNodeRef::new_unchecked("http://www.w3.org/2002/07/owl#inverseOf");
+const EQUIVALENT_CLASS: NamedNodeRef<'_> =
+    NamedNodeRef::new_unchecked("http://www.w3.org/2002/07/owl#equivalentClass");
+const EQUIVALENT_PROPERTY: NamedNodeRef<'_> =
+    NamedNodeRef::new_unchecked("http://www.w3.org/2002/07/owl#equivalentProperty");
+
+pub trait Reasoner {
+    fn name(&self) -> &str;
+    /// Returns the triples entailed by the graph which are not already in it
+    fn classify(&self, graph: &Graph) -> Result<Graph>;
+}
+
+/// Returns the reasoner with the given name: 'rdfs' or 'owlrl'
+pub fn reasoner_by_name(name: &str) -> Result<Box<dyn Reasoner>> {
+    match name.to_lowercase().as_str() {
+        "rdfs" => Ok(Box::new(RdfsReasoner {})),
+        "owlrl" | "owl-rl" => Ok(Box::new(OwlRlReasoner {})),
+        _ => Err(anyhow::anyhow!("Unknown reasoner: {}", name)),
+    }
+}
+
+fn as_subject(term: TermRef) -> Option<SubjectRef> {
+    match term {
+        TermRef::NamedNode(n) => Some(n.into()),
+        TermRef::BlankNode(b) => Some(b.into()),
+        _ => None,
+    }
+}
+
+fn as_named(term: TermRef) -> Option<NamedNodeRef> {
+    match term {
+        TermRef::NamedNode(n) => Some(n),
+        _ => None,
+    }
+}
+
+/// Collects a rule's conclusions which are not yet in the graph
+struct Conclusions<'a> {
+    graph: &'a Graph,
+    new: Vec<Triple>,
+}
+
+impl<'a> Conclusions<'a> {
+    fn add(&mut self, triple: TripleRef) {
+        if !self.graph.contains(triple) {
+            self.new.push(triple.into_owned());
+        }
+    }
+}
+
+/// Applies the rules to the graph until no new triples are entailed and returns the entailed
+/// triples
+fn saturate(graph: &Graph, rules: &[fn(&mut Conclusions)]) -> Graph {
+    let mut closure = graph.clone();
+    let mut inferred = Graph::new();
+    loop {
+        let mut conclusions = Conclusions {
+            graph: &closure,
+            new: vec![],
+        };
+        for rule in rules {
+            rule(&mut conclusions);
+        }
+        let new = conclusions.new;
+        if new.is_empty() {
+            break;
+        }
+        for triple in new.iter() {
+            closure.insert(triple);
+            inferred.insert(triple);
+        }
+    }
+    inferred
+}
+
+// rdfs2: p rdfs:domain c . s p o => s rdf:type c
+fn rdfs_domain(c: &mut Conclusions) {
+    let g = c.graph;
+    for t in g.triples_for_predicate(rdfs::DOMAIN) {
+        let (Some(p), Some(class)) = (as_named(t.subject.into()), as_named(t.object)) else {
+            continue;
+        };
+        for s in g.triples_for_predicate(p).map(|t| t.subject) {
+            c.add(TripleRef::new(s, TYPE, class));
+        }
+    }
+}
+
+// rdfs3: p rdfs:range c . s p o => o rdf:type c
+fn rdfs_range(c: &mut Conclusions) {
+    let g = c.graph;
+    for t in g.triples_for_predicate(rdfs::RANGE) {
+        let (Some(p), Some(class)) = (as_named(t.subject.into()), as_named(t.object)) else {
+            continue;
+        };
+        for o in g
+            .triples_for_predicate(p)
+            .filter_map(|t| as_subject(t.object))
+        {
+            c.add(TripleRef::new(o, TYPE, class));
+        }
+    }
+}
+
+// rdfs5 and rdfs11: transitivity of rdfs:subPropertyOf and rdfs:subClassOf
+fn rdfs_transitive_hierarchies(c: &mut Conclusions) {
+    let g = c.graph;
+    for predicate in [rdfs::SUB_CLASS_OF, rdfs::SUB_PROPERTY_OF] {
+        for t in g.triples_for_predicate(predicate) {
+            let Some(middle) = as_subject(t.object) else {
+                continue;
+            };
+            for o in g.objects_for_subject_predicate(middle, predicate) {
+                c.add(TripleRef::new(t.subject, predicate, o));
+            }
+        }
+    }
+}
+
+// rdfs7: p rdfs:subPropertyOf q . s p o => s q o
+fn rdfs_subproperty(c: &mut Conclusions) {
+    let g = c.graph;
+    for t in g.triples_for_predicate(rdfs::SUB_PROPERTY_OF) {
+        let (Some(p), Some(q)) = (as_named(t.subject.into()), as_named(t.object)) else {
+            continue;
+        };
+        for u in g.triples_for_predicate(p) {
+            c.add(TripleRef::new(u.subject, q, u.object));
+        }
+    }
+}
+
+// rdfs9: c rdfs:subClassOf d . s rdf:type c => s rdf:type d
+fn rdfs_subclass(c: &mut Conclusions) {
+    let g = c.graph;
+    for t in g.triples_for_predicate(rdfs::SUB_CLASS_OF) {
+        if !matches!(t.object, TermRef::NamedNode(_)) {
+            continue;
+        }
+        for s in g.subjects_for_predicate_object(TYPE, t.subject) {
+            c.add(TripleRef::new(s, TYPE, t.object));
+        }
+    }
+}
+
+// prp-symp: p a owl:SymmetricProperty . s p o => o p s
+fn owl_symmetric(c: &mut Conclusions) {
+    let g = c.graph;
+    for p in g.subjects_for_predicate_object(TYPE, SYMMETRIC_PROPERTY) {
+        let Some(p) = as_named(p.into()) else {
+            continue;
+        };
+        for t in g.triples_for_predicate(p) {
+            if let Some(o) = as_subject(t.object) {
+                c.add(TripleRef::new(o, p, t.subject));
+            }
+        }
+    }
+}
+
+// prp-trp: p a owl:TransitiveProperty . a p b . b p c => a p c
+fn owl_transitive(c: &mut Conclusions) {
+    let g = c.graph;
+    for p in g.subjects_for_predicate_object(TYPE, TRANSITIVE_PROPERTY) {
+        let Some(p) = as_named(p.into()) else {
+            continue;
+        };
+        for t in g.triples_for_predicate(p) {
+            let Some(middle) = as_subject(t.object) else {
+                continue;
+            };
+            for o in g.objects_for_subject_predicate(middle, p) {
+                c.add(TripleRef::new(t.subject, p, o));
+            }
+        }
+    }
+}
+
+// prp-inv1 and prp-inv2: p owl:inverseOf q . s p o => o q s, and s q o => o p s
+fn owl_inverse(c: &mut Conclusions) {
+    let g = c.graph;
+    for t in g.triples_for_predicate(INVERSE_OF) {
+        let (Some(p), Some(q)) = (as_named(t.subject.into()), as_named(t.object)) else {
+            continue;
+        };
+        for (from, to) in [(p, q), (q, p)] {
+            for u in g.triples_for_predicate(from) {
+                if let Some(o) = as_subject(u.object) {
+                    c.add(TripleRef::new(o, to, u.subject));
+                }
+            }
+        }
+    }
+}
+
+// cax-eqc and prp-eqp: equivalent classes and properties are mutual subclasses/subproperties
+fn owl_equivalence(c: &mut Conclusions) {
+    let g = c.graph;
+    for (equivalent, sub) in [
+        (EQUIVALENT_CLASS, rdfs::SUB_CLASS_OF),
+        (EQUIVALENT_PROPERTY, rdfs::SUB_PROPERTY_OF),
+    ] {
+        for t in g.triples_for_predicate(equivalent) {
+            c.add(TripleRef::new(t.subject, sub, t.object));
+            if let Some(o) = as_subject(t.object) {
+                c.add(TripleRef::new(o, sub, t.subject));
+            }
+        }
+    }
+}
+
+const RDFS_RULES: [fn(&mut Conclusions); 5] = [
+    rdfs_domain,
+    rdfs_range,
+    rdfs_transitive_hierarchies,
+    rdfs_subproperty,
+    rdfs_subclass,
+];
+
+/// Forward-chaining RDFS entailment (domain, range, subclass and subproperty rules)
+pub struct RdfsReasoner {}
+
+impl Reasoner for RdfsReasoner {
+    fn name(&self) -> &str {
+        "rdfs"
+    }
+
+    fn classify(&self, graph: &Graph) -> Result<Graph> {
+        Ok(saturate(graph, &RDFS_RULES))
+    }
+}
+
+/// RDFS entailment plus the OWL 2 RL rules for symmetric, transitive, inverse and equivalent
+/// classes and properties
+pub struct OwlRlReasoner {}
+
+impl Reasoner for OwlRlReasoner {
+    fn name(&self) -> &str {
+        "owlrl"
+    }
+
+    fn classify(&self, graph: &Graph) -> Result<Graph> {
+        let mut rules: Vec<fn(&mut Conclusions)> = RDFS_RULES.to_vec();
+        rules.extend([
+            owl_symmetric as fn(&mut Conclusions),
+            owl_transitive,
+            owl_inverse,
+            owl_equivalence,
+        ]);
+        Ok(saturate(graph, &rules))
+    }
+}
+
+/// Runs an external reasoner (e.g. HermiT or ELK through ROBOT) as a command. The graph is
+/// written to a temporary Turtle file; '{input}' and '{output}' in the arguments are replaced
+/// with the paths of the input and output files. For example:
+/// `robot reason --reasoner ELK --input {input} --output {output}`
+pub struct ExternalReasoner {
+    pub command: String,
+    pub args: Vec<String>,
+}
+
+impl ExternalReasoner {
+    /// Parses a command line like "robot reason --input {input} --output {output}"
+    pub fn from_command_line(command_line: &str) -> Result<Self> {
+        let mut parts = command_line.split_whitespace().map(|s| s.to_string());
+        let command = parts
+            .next()
+            .ok_or(anyhow::anyhow!("Empty reasoner command"))?;
+        Ok(ExternalReasoner {
+            command,
+            args: parts.collect(),
+        })
+    }
+}
+
+impl Reasoner for ExternalReasoner {
+    fn name(&self) -> &str {
+        &self.command
+    }
+
+    fn classify(&self, graph: &Graph) -> Result<Graph> {
+        let dir = tempfile::tempdir()?;
+        let input = dir.path().join("input.ttl");
+        let output = dir.path().join("output.ttl");
+        util::write_graph_to_file(graph, &input)?;
+        let args: Vec<String> = self
+            .args
+            .iter()
+            .map(|a| {
+                a.replace("{input}", input.to_str().unwrap_or_default())
+                    .replace("{output}", output.to_str().unwrap_or_default())
+            })
+            .collect();
+        info!("Running reasoner: {} {}", self.command, args.join(" "));
+        let status = Command::new(&self.command).args(&args).status()?;
+        if !status.success() {
+            return Err(anyhow::anyhow!(
+                "Reasoner {} failed with {}",
+                self.command,
+                status
+            ));
+        }
+        let result = util::read_file(&output)?;
+        let mut inferred = Graph::new();
+        for triple in result.iter() {
+            if !graph.contains(triple) {
+                inferred.insert(triple);
+            }
+        }
+        Ok(inferred)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(s: &str) -> NamedNodeRef {
+        NamedNodeRef::new(s).unwrap()
+    }
+
+    #[test]
+    fn test_rdfs_reasoner() {
+        let (a, b, c, x, y, p) = (
+            node("urn:a"),
+            node("urn:b"),
+            node("urn:c"),
+            node("urn:x"),
+            node("urn:y"),
+            node("urn:p"),
+        );
+        let mut graph = Graph::new();
+        graph.insert(TripleRef::new(a, rdfs::SUB_CLASS_OF, b));
+        graph.insert(TripleRef::new(b, rdfs::SUB_CLASS_OF, c));
+        graph.insert(TripleRef::new(p, rdfs::DOMAIN, a));
+        graph.insert(TripleRef::new(x, p, y));
+        let inferred = RdfsReasoner {}.classify(&graph).unwrap();
+        assert!(inferred.contains(TripleRef::new(a, rdfs::SUB_CLASS_OF, c)));
+        assert!(inferred.contains(TripleRef::new(x, TYPE, a)));
+        assert!(inferred.contains(TripleRef::new(x, TYPE, b)));
+        assert!(inferred.contains(TripleRef::new(x, TYPE, c)));
+        assert_eq!(inferred.len(), 4);
+    }
+
+    #[test]
+    fn test_owlrl_reasoner() {
+        let (x, y, z, p, q) = (
+            node("urn:x"),
+            node("urn:y"),
+            node("urn:z"),
+            node("urn:p"),
+            node("urn:q"),
+        );
+        let mut graph = Graph::new();
+        graph.insert(TripleRef::new(p, TYPE, TRANSITIVE_PROPERTY));
+        graph.insert(TripleRef::new(p, INVERSE_OF, q));
+        graph.insert(TripleRef::new(x, p, y));
+        graph.insert(TripleRef::new(y, p, z));
+        let inferred = OwlRlReasoner {}.classify(&graph).unwrap();
+        assert!(inferred.contains(TripleRef::new(x, p, z)));
+        assert!(inferred.contains(TripleRef::new(z, q, x)));
+        assert!(inferred.contains(TripleRef::new(y, q, x)));
+    }
+}
@@ -0,0 +1,208 @@
+// builds versioned release artifacts for a root ontology, following the steps of OBO/ROBOT-style
+// release workflows: the full imports closure, a slim variant and a reasoned variant, each
+// stamped with a version IRI and release date and written to a dist directory
+
+use crate::consts::{ISSUED, ONTOLOGY, TYPE, VERSION_INFO, VERSION_IRI};
+use crate::ontology::GraphIdentifier;
+use crate::reasoner::{RdfsReasoner, Reasoner};
+use crate::util::{self, SerializerOptions};
+use crate::OntoEnv;
+use anyhow::Result;
+use chrono::prelude::*;
+use oxigraph::model::vocab::xsd;
+use oxigraph::model::{
+    Graph, Literal, NamedNode, NamedNodeRef, SubjectRef, TermRef, Triple, TripleRef,
+};
+use std::fmt;
+use std::path::PathBuf;
+use tracing::info;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReleaseKind {
+    // the union of the imports closure
+    Full,
+    // the closure without blank-node structures (OWL restrictions, lists, SHACL property shapes)
+    Slim,
+    // the closure with the inferences of the release's reasoner materialized
+    Reasoned,
+}
+
+impl ReleaseKind {
+    fn suffix(&self) -> &'static str {
+        match self {
+            ReleaseKind::Full => "",
+            ReleaseKind::Slim => "-slim",
+            ReleaseKind::Reasoned => "-reasoned",
+        }
+    }
+}
+
+impl fmt::Display for ReleaseKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReleaseKind::Full => write!(f, "full"),
+            ReleaseKind::Slim => write!(f, "slim"),
+            ReleaseKind::Reasoned => write!(f, "reasoned"),
+        }
+    }
+}
+
+pub struct ReleaseOptions {
+    pub version: String,
+    pub dist_dir: PathBuf,
+    pub date: NaiveDate,
+    // computes the inferences added to the reasoned artifact
+    pub reasoner: Box<dyn Reasoner>,
+    // layout of the written artifacts
+    pub serializer: SerializerOptions,
+}
+
+impl ReleaseOptions {
+    /// Release options for the given version, dated today and using RDFS reasoning
+    pub fn new(version: &str, dist_dir: PathBuf) -> Self {
+        ReleaseOptions {
+            version: version.to_string(),
+            dist_dir,
+            date: Utc::now().date_naive(),
+            reasoner: Box::new(RdfsReasoner {}),
+            serializer: SerializerOptions::default(),
+        }
+    }
+}
+
+/// A file written by a release
+#[derive(Debug, Clone)]
+pub struct ReleaseArtifact {
+    pub kind: ReleaseKind,
+    pub path: PathBuf,
+    pub version_iri: NamedNode,
+    pub triples: usize,
+}
+
+/// Returns the last segment of the ontology IRI, used to name the release files
+fn file_stem(name: NamedNodeRef) -> String {
+    name.as_str()
+        .trim_end_matches(['/', '#'])
+        .rsplit(['/', '#', ':'])
+        .next()
+        .filter(|s| !s.is_empty())
+        .unwrap_or("ontology")
+        .trim_end_matches(".ttl")
+        .trim_end_matches(".owl")
+        .to_string()
+}
+
+/// Replaces the version IRI, version info and release date of the ontology declaration
+pub fn stamp_version(
+    graph: &mut Graph,
+    ontology: NamedNodeRef,
+    version_iri: NamedNodeRef,
+    version: &str,
+    date: NaiveDate,
+) {
+    let old: Vec<Triple> = [VERSION_IRI, VERSION_INFO, ISSUED]
+        .iter()
+        .flat_map(|p| {
+            graph
+                .objects_for_subject_predicate(ontology, *p)
+                .map(|o| TripleRef::new(ontology, *p, o).into_owned())
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    for triple in old.iter() {
+        graph.remove(triple);
+    }
+    graph.insert(TripleRef::new(ontology, TYPE, ONTOLOGY));
+    graph.insert(TripleRef::new(ontology, VERSION_IRI, version_iri));
+    graph.insert(&Triple::new(
+        ontology,
+        VERSION_INFO,
+        Literal::new_simple_literal(version),
+    ));
+    graph.insert(&Triple::new(
+        ontology,
+        ISSUED,
+        Literal::new_typed_literal(date.format("%Y-%m-%d").to_string(), xsd::DATE),
+    ));
+}
+
+/// Removes all triples whose subject or object is a blank node
+pub fn slim(graph: &Graph) -> Graph {
+    let mut slim = Graph::new();
+    for triple in graph.iter() {
+        if matches!(triple.subject, SubjectRef::NamedNode(_))
+            && !matches!(triple.object, TermRef::BlankNode(_))
+        {
+            slim.insert(triple);
+        }
+    }
+    slim
+}
+
+/// Writes the full, slim and reasoned release artifacts for the root ontology into the dist
+/// directory. The version IRI of each artifact is <ontology>/releases/<version>/<file>
+pub fn release(
+    env: &OntoEnv,
+    root: &GraphIdentifier,
+    options: &ReleaseOptions,
+) -> Result<Vec<ReleaseArtifact>> {
+    let name = root.name();
+    let closure = env.get_dependency_closure(root)?;
+    let (union, _, failed_imports) = env.get_union_graph(&closure, Some(true), Some(true))?;
+    if let Some(imp) = failed_imports.and_then(|f| f.into_iter().next()) {
+        return Err(anyhow::anyhow!("Cannot release {}: {}", name, imp));
+    }
+    let full = util::dataset_to_graph(&union);
+    let slim_graph = slim(&full);
+    let mut reasoned = full.clone();
+    info!("Reasoning over {} with {}", name, options.reasoner.name());
+    for triple in options.reasoner.classify(&full)?.iter() {
+        reasoned.insert(triple);
+    }
+
+    std::fs::create_dir_all(&options.dist_dir)?;
+    let stem = file_stem(name);
+    let base = name.as_str().trim_end_matches(['/', '#']);
+    let mut artifacts = vec![];
+    for (kind, mut graph) in [
+        (ReleaseKind::Full, full),
+        (ReleaseKind::Slim, slim_graph),
+        (ReleaseKind::Reasoned, reasoned),
+    ] {
+        let file = format!("{}{}.ttl", stem, kind.suffix());
+        let version_iri =
+            NamedNode::new(format!("{}/releases/{}/{}", base, options.version, file))?;
+        stamp_version(
+            &mut graph,
+            name,
+            version_iri.as_ref(),
+            &options.version,
+            options.date,
+        );
+        let path = options.dist_dir.join(&file);
+        info!("Writing {} release of {} to {:?}", kind, name, path);
+        util::write_graph_to_file_with(&graph, &path, &options.serializer)?;
+        artifacts.push(ReleaseArtifact {
+            kind,
+            path,
+            version_iri,
+            triples: graph.len(),
+        });
+    }
+    Ok(artifacts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_stem() {
+        let name = NamedNodeRef::new("https://brickschema.org/schema/1.3/Brick#").unwrap();
+        assert_eq!(file_stem(name), "Brick");
+        let name = NamedNodeRef::new("http://purl.obolibrary.org/obo/go.owl").unwrap();
+        assert_eq!(file_stem(name), "go");
+        let name = NamedNodeRef::new("urn:ont1").unwrap();
+        assert_eq!(file_stem(name), "ont1");
+    }
+}
@@ -0,0 +1,310 @@
+// `ontoenv report`: a concise summary of an environment (its ontologies and their versions, the
+// imports which do not resolve, the problems found by the doctor and the dependency graph as a
+// mermaid diagram) rendered as Markdown or HTML, so it can be committed next to the ontologies
+// or attached to CI runs. Markdown renders the diagram natively on GitHub and GitLab; the HTML
+// page loads mermaid from a CDN
+
+use crate::depgraph::DependencyNode;
+use crate::docgen::{escape_html, DocFormat};
+use crate::failures::MissingImport;
+use crate::OntoEnv;
+use anyhow::Result;
+use chrono::prelude::*;
+use std::collections::{BTreeMap, HashMap};
+
+const MERMAID_SCRIPT: &str = "<script type=\"module\">import mermaid from 'https://cdn.jsdelivr.net/npm/mermaid@10/dist/mermaid.esm.min.mjs'; mermaid.initialize({ startOnLoad: true });</script>";
+
+/// An ontology of the environment, as listed in the report
+#[derive(Debug, Clone, PartialEq)]
+pub struct OntologyRow {
+    pub name: String,
+    pub version_iri: Option<String>,
+    pub location: String,
+    pub imports: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct EnvironmentReport {
+    pub generated: DateTime<Utc>,
+    // sorted by name and location
+    pub ontologies: Vec<OntologyRow>,
+    pub missing: Vec<MissingImport>,
+    // doctor message -> locations with the problem, sorted
+    pub problems: BTreeMap<String, Vec<String>>,
+    // (importer, imported, whether the import resolved)
+    pub edges: Vec<(String, String, bool)>,
+}
+
+/// Collects the report of the environment
+pub fn build(env: &OntoEnv) -> Result<EnvironmentReport> {
+    let ontologies = env
+        .ontologies()
+        .iter()
+        .map(|(id, ontology)| OntologyRow {
+            name: id.name().to_string(),
+            version_iri: ontology.version_iri().map(|iri| iri.as_str().to_string()),
+            location: id.location().to_string(),
+            imports: ontology.imports.len(),
+        })
+        .collect();
+
+    let mut problems: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for problem in env.problems()? {
+        problems
+            .entry(problem.message)
+            .or_default()
+            .extend(problem.locations.iter().map(|l| l.to_string()));
+    }
+    for locations in problems.values_mut() {
+        locations.sort();
+        locations.dedup();
+    }
+
+    let node_name = |node: &DependencyNode| match node {
+        DependencyNode::Ontology(id) => id.name().to_string(),
+        DependencyNode::Missing(import) => import.as_str().to_string(),
+    };
+    let mut edges: Vec<(String, String, bool)> = env
+        .dependency_graph()
+        .edges()
+        .map(|(from, to, _)| {
+            let resolved = matches!(to, DependencyNode::Ontology(_));
+            (node_name(from), node_name(to), resolved)
+        })
+        .collect();
+    edges.sort();
+    edges.dedup();
+
+    Ok(EnvironmentReport {
+        generated: Utc::now(),
+        ontologies,
+        missing: env.missing_import_details(),
+        problems,
+        edges,
+    })
+}
+
+impl EnvironmentReport {
+    pub fn render(&self, format: DocFormat) -> String {
+        match format {
+            DocFormat::Html => self.to_html(),
+            DocFormat::Markdown => self.to_markdown(),
+        }
+    }
+
+    /// Returns the dependency graph as a mermaid flowchart. Nodes are numbered in the order
+    /// they first appear and labelled with the ontology name; missing imports are dashed
+    pub fn mermaid(&self) -> String {
+        let mut ids: HashMap<String, String> = HashMap::new();
+        let mut nodes = String::new();
+        let mut links = String::new();
+        let mut node = |name: &str, missing: bool, nodes: &mut String| -> String {
+            if let Some(id) = ids.get(name) {
+                return id.clone();
+            }
+            let id = format!("n{}", ids.len());
+            let class = if missing { ":::missing" } else { "" };
+            nodes.push_str(&format!(
+                "    {}[\"{}\"]{}\n",
+                id,
+                name.replace('"', "#quot;"),
+                class
+            ));
+            ids.insert(name.to_string(), id.clone());
+            id
+        };
+        for row in &self.ontologies {
+            node(&row.name, false, &mut nodes);
+        }
+        for (from, to, resolved) in &self.edges {
+            let from = node(from, false, &mut nodes);
+            let to = node(to, !resolved, &mut nodes);
+            let arrow = if *resolved { "-->" } else { "-.->" };
+            links.push_str(&format!("    {} {} {}\n", from, arrow, to));
+        }
+        format!(
+            "graph TD\n{}{}    classDef missing stroke-dasharray: 5 5\n",
+            nodes, links
+        )
+    }
+
+    fn summary(&self) -> String {
+        format!(
+            "{} ontologies, {} missing imports, {} problems. Generated {}",
+            self.ontologies.len(),
+            self.missing.len(),
+            self.problems.len(),
+            self.generated.to_rfc3339_opts(SecondsFormat::Secs, true)
+        )
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let cell = |s: &str| s.replace('|', "\\|");
+        let mut out = String::new();
+        out.push_str(&format!("# Environment Report\n\n{}\n\n", self.summary()));
+
+        out.push_str(
+            "## Ontologies\n\n| Name | Version | Location | Imports |\n| --- | --- | --- | --- |\n",
+        );
+        for row in &self.ontologies {
+            out.push_str(&format!(
+                "| `{}` | {} | `{}` | {} |\n",
+                cell(&row.name),
+                row.version_iri
+                    .as_deref()
+                    .map(|v| format!("`{}`", cell(v)))
+                    .unwrap_or_default(),
+                cell(&row.location),
+                row.imports
+            ));
+        }
+        out.push('\n');
+
+        out.push_str("## Missing Imports\n\n");
+        if self.missing.is_empty() {
+            out.push_str("None\n\n");
+        } else {
+            for missing in &self.missing {
+                match &missing.failure {
+                    Some(failure) => {
+                        out.push_str(&format!("- `{}`: {}\n", missing.import, failure.error))
+                    }
+                    None => out.push_str(&format!("- `{}`\n", missing.import)),
+                }
+            }
+            out.push('\n');
+        }
+
+        out.push_str("## Doctor\n\n");
+        if self.problems.is_empty() {
+            out.push_str("No problems found\n\n");
+        } else {
+            for (message, locations) in &self.problems {
+                out.push_str(&format!("- {}\n", message));
+                for location in locations {
+                    out.push_str(&format!("  - `{}`\n", location));
+                }
+            }
+            out.push('\n');
+        }
+
+        out.push_str(&format!(
+            "## Dependencies\n\n```mermaid\n{}```\n",
+            self.mermaid()
+        ));
+        out
+    }
+
+    pub fn to_html(&self) -> String {
+        let mut out = String::new();
+        out.push_str(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Environment Report</title>\n</head>\n<body>\n",
+        );
+        out.push_str(&format!(
+            "<h1>Environment Report</h1>\n<p>{}</p>\n",
+            escape_html(&self.summary())
+        ));
+
+        out.push_str("<h2>Ontologies</h2>\n<table>\n<tr><th>Name</th><th>Version</th><th>Location</th><th>Imports</th></tr>\n");
+        for row in &self.ontologies {
+            out.push_str(&format!(
+                "<tr><td><code>{}</code></td><td><code>{}</code></td><td><code>{}</code></td><td>{}</td></tr>\n",
+                escape_html(&row.name),
+                escape_html(row.version_iri.as_deref().unwrap_or_default()),
+                escape_html(&row.location),
+                row.imports
+            ));
+        }
+        out.push_str("</table>\n");
+
+        out.push_str("<h2>Missing Imports</h2>\n");
+        if self.missing.is_empty() {
+            out.push_str("<p>None</p>\n");
+        } else {
+            out.push_str("<ul>\n");
+            for missing in &self.missing {
+                let error = missing
+                    .failure
+                    .as_ref()
+                    .map(|f| format!(": {}", escape_html(&f.error)))
+                    .unwrap_or_default();
+                out.push_str(&format!(
+                    "<li><code>{}</code>{}</li>\n",
+                    escape_html(&missing.import),
+                    error
+                ));
+            }
+            out.push_str("</ul>\n");
+        }
+
+        out.push_str("<h2>Doctor</h2>\n");
+        if self.problems.is_empty() {
+            out.push_str("<p>No problems found</p>\n");
+        } else {
+            out.push_str("<ul>\n");
+            for (message, locations) in &self.problems {
+                out.push_str(&format!("<li>{}\n<ul>\n", escape_html(message)));
+                for location in locations {
+                    out.push_str(&format!(
+                        "<li><code>{}</code></li>\n",
+                        escape_html(location)
+                    ));
+                }
+                out.push_str("</ul>\n</li>\n");
+            }
+            out.push_str("</ul>\n");
+        }
+
+        out.push_str(&format!(
+            "<h2>Dependencies</h2>\n<pre class=\"mermaid\">\n{}</pre>\n{}\n",
+            escape_html(&self.mermaid()),
+            MERMAID_SCRIPT
+        ));
+        out.push_str("</body>\n</html>\n");
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mermaid() {
+        let report = EnvironmentReport {
+            generated: Utc::now(),
+            ontologies: vec![
+                OntologyRow {
+                    name: "http://example.org/a".to_string(),
+                    version_iri: None,
+                    location: "a.ttl".to_string(),
+                    imports: 2,
+                },
+                OntologyRow {
+                    name: "http://example.org/b".to_string(),
+                    version_iri: Some("http://example.org/b/1.0".to_string()),
+                    location: "b.ttl".to_string(),
+                    imports: 0,
+                },
+            ],
+            missing: vec![],
+            problems: BTreeMap::new(),
+            edges: vec![
+                (
+                    "http://example.org/a".to_string(),
+                    "http://example.org/b".to_string(),
+                    true,
+                ),
+                (
+                    "http://example.org/a".to_string(),
+                    "http://example.org/c".to_string(),
+                    false,
+                ),
+            ],
+        };
+        assert_eq!(
+            report.mermaid(),
+            "graph TD\n    n0[\"http://example.org/a\"]\n    n1[\"http://example.org/b\"]\n    n2[\"http://example.org/c\"]:::missing\n    n0 --> n1\n    n0 -.-> n2\n    classDef missing stroke-dasharray: 5 5\n"
+        );
+    }
+}
@@ -0,0 +1,179 @@
+// a record of how the imports closure of an ontology was resolved: the version and location
+// chosen for every ontology of the closure, the imports which did not resolve and the fingerprint
+// of the environment they were resolved in (see `OntoEnv::resolution_report`). Embedded in
+// written closures, as a named graph or a JSON file next to them, it makes the artifact describe
+// where its contents came from
+
+use crate::consts::{CREATED, VERSION_IRI};
+use anyhow::Result;
+use chrono::prelude::*;
+use oxigraph::model::vocab::xsd;
+use oxigraph::model::{
+    BlankNode, Dataset, Graph, GraphNameRef, Literal, NamedNode, NamedNodeRef, QuadRef, TripleRef,
+};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Name of the named graph holding the report in a written closure; also the subject of its
+/// triples
+pub const REPORT_GRAPH: NamedNodeRef<'_> = NamedNodeRef::new_unchecked("urn:ontoenv:resolution");
+
+const ROOT: NamedNodeRef<'_> = NamedNodeRef::new_unchecked("urn:ontoenv:root");
+const FINGERPRINT: NamedNodeRef<'_> =
+    NamedNodeRef::new_unchecked("urn:ontoenv:environmentFingerprint");
+const RESOLVED: NamedNodeRef<'_> = NamedNodeRef::new_unchecked("urn:ontoenv:resolved");
+const ONTOLOGY: NamedNodeRef<'_> = NamedNodeRef::new_unchecked("urn:ontoenv:ontology");
+const LOCATION: NamedNodeRef<'_> = NamedNodeRef::new_unchecked("urn:ontoenv:location");
+const CONTENT_HASH: NamedNodeRef<'_> = NamedNodeRef::new_unchecked("urn:ontoenv:contentHash");
+const UNRESOLVED: NamedNodeRef<'_> = NamedNodeRef::new_unchecked("urn:ontoenv:unresolved");
+const IMPORTER: NamedNodeRef<'_> = NamedNodeRef::new_unchecked("urn:ontoenv:importer");
+const IMPORT: NamedNodeRef<'_> = NamedNodeRef::new_unchecked("urn:ontoenv:import");
+
+/// How the report is embedded in a written closure
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReportFormat {
+    // a named graph of the written TriG dataset
+    Graph,
+    // a JSON file next to the closure
+    Json,
+}
+
+impl std::str::FromStr for ReportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "graph" => Ok(ReportFormat::Graph),
+            "json" => Ok(ReportFormat::Json),
+            _ => Err(anyhow::anyhow!("Unknown resolution report format: {}", s)),
+        }
+    }
+}
+
+/// An ontology of the closure and the version of it which was chosen
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ResolvedOntology {
+    pub name: String,
+    pub location: Option<String>,
+    pub version_iri: Option<String>,
+    // see lockfile::content_hash
+    pub content_hash: Option<String>,
+}
+
+/// An owl:imports of an ontology of the closure which no ontology in the environment has
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct UnresolvedImport {
+    pub importer: String,
+    pub import: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ResolutionReport {
+    // the name of the root of the closure
+    pub root: String,
+    pub generated: DateTime<Utc>,
+    // see `OntoEnv::fingerprint`
+    pub environment_fingerprint: String,
+    // in closure order
+    pub ontologies: Vec<ResolvedOntology>,
+    pub unresolved: Vec<UnresolvedImport>,
+}
+
+/// Returns the path of the JSON report written next to the closure file, e.g.
+/// output.resolution.json for output.ttl
+pub fn sidecar_path(closure_file: &Path) -> PathBuf {
+    closure_file.with_extension("resolution.json")
+}
+
+impl ResolutionReport {
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    /// Returns the report as RDF. The report is the subject `REPORT_GRAPH`; each resolved
+    /// ontology and unresolved import is a blank node linked to it
+    pub fn to_graph(&self) -> Result<Graph> {
+        let mut graph = Graph::new();
+        let report = REPORT_GRAPH;
+        graph.insert(TripleRef::new(
+            report,
+            ROOT,
+            NamedNode::new(&self.root)?.as_ref(),
+        ));
+        graph.insert(TripleRef::new(
+            report,
+            CREATED,
+            Literal::new_typed_literal(self.generated.to_rfc3339(), xsd::DATE_TIME).as_ref(),
+        ));
+        graph.insert(TripleRef::new(
+            report,
+            FINGERPRINT,
+            Literal::new_simple_literal(&self.environment_fingerprint).as_ref(),
+        ));
+        for ontology in &self.ontologies {
+            let node = BlankNode::default();
+            graph.insert(TripleRef::new(report, RESOLVED, node.as_ref()));
+            graph.insert(TripleRef::new(
+                node.as_ref(),
+                ONTOLOGY,
+                NamedNode::new(&ontology.name)?.as_ref(),
+            ));
+            if let Some(location) = &ontology.location {
+                graph.insert(TripleRef::new(
+                    node.as_ref(),
+                    LOCATION,
+                    Literal::new_simple_literal(location).as_ref(),
+                ));
+            }
+            if let Some(version_iri) = &ontology.version_iri {
+                graph.insert(TripleRef::new(
+                    node.as_ref(),
+                    VERSION_IRI,
+                    NamedNode::new(version_iri)?.as_ref(),
+                ));
+            }
+            if let Some(hash) = &ontology.content_hash {
+                graph.insert(TripleRef::new(
+                    node.as_ref(),
+                    CONTENT_HASH,
+                    Literal::new_simple_literal(hash).as_ref(),
+                ));
+            }
+        }
+        for unresolved in &self.unresolved {
+            let node = BlankNode::default();
+            graph.insert(TripleRef::new(report, UNRESOLVED, node.as_ref()));
+            graph.insert(TripleRef::new(
+                node.as_ref(),
+                IMPORTER,
+                NamedNode::new(&unresolved.importer)?.as_ref(),
+            ));
+            graph.insert(TripleRef::new(
+                node.as_ref(),
+                IMPORT,
+                NamedNode::new(&unresolved.import)?.as_ref(),
+            ));
+        }
+        Ok(graph)
+    }
+
+    /// Adds the report to the dataset, in the named graph `REPORT_GRAPH`
+    pub fn add_to_dataset(&self, dataset: &mut Dataset) -> Result<()> {
+        let graph_name = GraphNameRef::NamedNode(REPORT_GRAPH);
+        for triple in self.to_graph()?.iter() {
+            dataset.insert(QuadRef::new(
+                triple.subject,
+                triple.predicate,
+                triple.object,
+                graph_name,
+            ));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,216 @@
+// A line-delimited JSON-RPC 2.0 interface to an environment, meant for editor plugins and
+// other tools which want to query the environment without linking against the library.
+//
+// Each line on the input is one request; each response is written as one line on the output.
+// Supported methods:
+//
+// - resolve {"iri"}: the name and location of the ontology with the given name, or null
+// - closure {"iri"}: the names of the ontologies in the imports closure of the ontology
+// - missingImports {"iri"?, "path"?}: the owl:imports which cannot be resolved in the environment;
+//   either for the given ontology, for the ontology in the given file, or for the whole environment
+// - searchTerms {"query", "limit"?}: typed terms whose IRI contains the query
+// - prefix {"prefix"?}: the namespace bound to the prefix, or all known prefixes if none is given
+
+use crate::consts::{IMPORTS, ONTOLOGY, TYPE};
+use crate::ontology::OntologyLocation;
+use crate::OntoEnv;
+use anyhow::Result;
+use oxigraph::model::{NamedNode, SubjectRef, TermRef};
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::io::{BufRead, Write};
+
+pub(crate) const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+pub(crate) const INTERNAL_ERROR: i64 = -32603;
+
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl From<anyhow::Error> for RpcError {
+    fn from(e: anyhow::Error) -> Self {
+        RpcError {
+            code: INTERNAL_ERROR,
+            message: e.to_string(),
+        }
+    }
+}
+
+fn invalid_params(message: &str) -> RpcError {
+    RpcError {
+        code: INVALID_PARAMS,
+        message: message.to_string(),
+    }
+}
+
+fn string_param<'a>(params: &'a Map<String, Value>, name: &str) -> Option<&'a str> {
+    params.get(name).and_then(|v| v.as_str())
+}
+
+fn iri_param(params: &Map<String, Value>) -> Result<NamedNode, RpcError> {
+    let iri = string_param(params, "iri").ok_or(invalid_params("missing 'iri' parameter"))?;
+    NamedNode::new(iri).map_err(|e| invalid_params(&e.to_string()))
+}
+
+/// Serves requests from the reader until it is closed, writing one response per request which is
+/// not a notification
+pub fn serve<R: BufRead, W: Write>(env: &OntoEnv, reader: R, mut writer: W) -> Result<()> {
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<Value>(&line) {
+            Ok(request) => handle_request(env, &request),
+            Err(e) => Some(error_response(Value::Null, PARSE_ERROR, &e.to_string())),
+        };
+        let Some(response) = response else {
+            continue;
+        };
+        writeln!(writer, "{}", response)?;
+        writer.flush()?;
+    }
+    Ok(())
+}
+
+/// Handles a single JSON-RPC request and returns the response. A request without an id is a
+/// notification: it is handled, but gets no response
+pub fn handle_request(env: &OntoEnv, request: &Value) -> Option<Value> {
+    let Some(id) = request.get("id").cloned() else {
+        // requests which are not even valid are answered, with a null id
+        if request.get("method").and_then(|m| m.as_str()).is_none() {
+            return Some(error_response(
+                Value::Null,
+                INVALID_REQUEST,
+                "missing 'method'",
+            ));
+        }
+        respond(env, request, Value::Null);
+        return None;
+    };
+    Some(respond(env, request, id))
+}
+
+// handles the request and returns the response with the given id
+fn respond(env: &OntoEnv, request: &Value, id: Value) -> Value {
+    let method = match request.get("method").and_then(|m| m.as_str()) {
+        Some(method) => method,
+        None => return error_response(id, INVALID_REQUEST, "missing 'method'"),
+    };
+    let empty = Map::new();
+    let params = request
+        .get("params")
+        .and_then(|p| p.as_object())
+        .unwrap_or(&empty);
+
+    let result = match method {
+        "resolve" => resolve(env, params),
+        "closure" => closure(env, params),
+        "missingImports" => missing_imports(env, params),
+        "searchTerms" => search_terms(env, params),
+        "prefix" => prefix(env, params),
+        _ => Err(RpcError {
+            code: METHOD_NOT_FOUND,
+            message: format!("unknown method '{}'", method),
+        }),
+    };
+    match result {
+        Ok(result) => json!({"jsonrpc": "2.0", "id": id, "result": result}),
+        Err(e) => error_response(id, e.code, &e.message),
+    }
+}
+
+pub(crate) fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({"jsonrpc": "2.0", "id": id, "error": {"code": code, "message": message}})
+}
+
+fn resolve(env: &OntoEnv, params: &Map<String, Value>) -> Result<Value, RpcError> {
+    let iri = iri_param(params)?;
+    Ok(match env.get_ontology_by_name(iri.as_ref()) {
+        Some(ontology) => json!({
+            "name": ontology.name().as_str(),
+            "location": ontology.id().location().to_string(),
+        }),
+        None => Value::Null,
+    })
+}
+
+fn closure(env: &OntoEnv, params: &Map<String, Value>) -> Result<Value, RpcError> {
+    let iri = iri_param(params)?;
+    let ontology = env
+        .get_ontology_by_name(iri.as_ref())
+        .ok_or(invalid_params(&format!("ontology {} not found", iri)))?;
+    let closure = env.get_dependency_closure(ontology.id())?;
+    let names: Vec<&str> = closure.iter().map(|id| id.name().as_str()).collect();
+    Ok(json!(names))
+}
+
+fn missing_imports(env: &OntoEnv, params: &Map<String, Value>) -> Result<Value, RpcError> {
+    let imports: Vec<NamedNode> = if params.contains_key("iri") {
+        let iri = iri_param(params)?;
+        env.get_ontology_by_name(iri.as_ref())
+            .ok_or(invalid_params(&format!("ontology {} not found", iri)))?
+            .imports
+            .clone()
+    } else if let Some(path) = string_param(params, "path") {
+        // read the imports straight from the file, which need not be part of the environment
+        let graph = OntologyLocation::from_str(path)?
+            .read_document(&env.config.base_iri_options())?
+            .graph;
+        let mut imports = vec![];
+        for ontology in graph.subjects_for_predicate_object(TYPE, ONTOLOGY) {
+            if let SubjectRef::NamedNode(ontology) = ontology {
+                for import in graph.objects_for_subject_predicate(ontology, IMPORTS) {
+                    if let TermRef::NamedNode(import) = import {
+                        imports.push(import.into_owned());
+                    }
+                }
+            }
+        }
+        imports
+    } else {
+        return Ok(json!(env
+            .missing_imports()
+            .iter()
+            .map(|i| i.as_str())
+            .collect::<Vec<_>>()));
+    };
+    let missing: Vec<&str> = imports
+        .iter()
+        .filter(|import| env.get_ontology_by_name((*import).into()).is_none())
+        .map(|import| import.as_str())
+        .collect();
+    Ok(json!(missing))
+}
+
+fn search_terms(env: &OntoEnv, params: &Map<String, Value>) -> Result<Value, RpcError> {
+    let query =
+        string_param(params, "query").ok_or(invalid_params("missing 'query' parameter"))?;
+    let limit = params.get("limit").and_then(|l| l.as_u64()).unwrap_or(50) as usize;
+    let terms: Vec<Value> = env
+        .search_terms(query, limit)?
+        .into_iter()
+        .map(|(term, id)| json!({"iri": term.as_str(), "ontology": id.name().as_str()}))
+        .collect();
+    Ok(json!(terms))
+}
+
+fn prefix(env: &OntoEnv, params: &Map<String, Value>) -> Result<Value, RpcError> {
+    if let Some(prefix) = string_param(params, "prefix") {
+        return Ok(json!(env.namespace_for_prefix(prefix)));
+    }
+    // all prefixes; the first ontology by name wins on conflicts
+    let mut ontologies: Vec<_> = env.ontologies().values().collect();
+    ontologies.sort_by_key(|o| o.name());
+    let mut prefixes: BTreeMap<&str, &str> = BTreeMap::new();
+    for ontology in ontologies {
+        for (prefix, namespace) in ontology.namespace_map() {
+            prefixes.entry(prefix.as_str()).or_insert(namespace.as_str());
+        }
+    }
+    Ok(json!(prefixes))
+}
@@ -0,0 +1,432 @@
+// JSON Schemas for the machine-readable outputs of the CLI (`status --json`, `doctor --json`,
+// `refresh --json`, `refresh --dry-run --json` and `list-missing --json`), for ontoenv.lock and
+// for change manifests. They are the serde serializations of EnvironmentStatus, DoctorReport,
+// UpdateReport, UpdatePlan, a list of MissingImports, Lockfile and ChangeManifest; the tests
+// below check that those serializations conform to the schemas. Bump SCHEMA_VERSION whenever a
+// schema changes incompatibly.
+
+use serde_json::{json, Value};
+
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// The names of the available schemas
+pub const SCHEMA_NAMES: [&str; 7] = [
+    "status", "doctor", "update", "plan", "missing", "lockfile", "changes",
+];
+
+fn schema_id(name: &str) -> String {
+    format!(
+        "https://github.com/gtfierro/ontoenv-rs/schemas/v{}/{}.json",
+        SCHEMA_VERSION, name
+    )
+}
+
+/// Definitions shared between the schemas
+fn definitions() -> Value {
+    json!({
+        "location": {
+            "description": "A local file or a URL an ontology was read from",
+            "oneOf": [
+                {
+                    "type": "object",
+                    "properties": {"file": {"type": "string"}},
+                    "required": ["file"],
+                    "additionalProperties": false
+                },
+                {
+                    "type": "object",
+                    "properties": {"url": {"type": "string"}},
+                    "required": ["url"],
+                    "additionalProperties": false
+                }
+            ]
+        },
+        "graphIdentifier": {
+            "description": "An ontology in the environment: its name and where it was read from",
+            "type": "object",
+            "properties": {
+                "location": {"$ref": "#/$defs/location"},
+                "name": {"type": "string", "format": "iri"},
+                "graph_id": {
+                    "description": "Stable ID of the ontology's graph in the store",
+                    "type": "string"
+                }
+            },
+            "required": ["location", "name"]
+        }
+    })
+}
+
+fn with_header(name: &str, title: &str, mut schema: Value) -> Value {
+    let object = schema.as_object_mut().expect("schemas are objects");
+    object.insert(
+        "$schema".to_string(),
+        json!("https://json-schema.org/draft/2020-12/schema"),
+    );
+    object.insert("$id".to_string(), json!(schema_id(name)));
+    object.insert("title".to_string(), json!(title));
+    object.insert("$defs".to_string(), definitions());
+    schema
+}
+
+/// Returns the JSON Schema with the given name (see SCHEMA_NAMES)
+pub fn schema(name: &str) -> Option<Value> {
+    let schema = match name {
+        "status" => with_header(
+            name,
+            "ontoenv status --json",
+            json!({
+                "type": "object",
+                "properties": {
+                    "exists": {"type": "boolean"},
+                    "num_ontologies": {"type": "integer", "minimum": 0},
+                    "last_updated": {"type": ["string", "null"], "format": "date-time"},
+                    "store_size": {"type": "integer", "minimum": 0},
+                    "how_created": {
+                        "enum": ["New", "SameConfig", "RecreatedDifferentConfig", "RecreatedFlag"]
+                    }
+                },
+                "required": ["exists", "num_ontologies", "last_updated", "store_size", "how_created"]
+            }),
+        ),
+        "doctor" => with_header(
+            name,
+            "ontoenv doctor --json",
+            json!({
+                "type": "object",
+                "properties": {
+                    "problems": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "locations": {
+                                    "type": "array",
+                                    "items": {"$ref": "#/$defs/location"}
+                                },
+                                "graph": {"type": "string"},
+                                "message": {"type": "string"}
+                            },
+                            "required": ["locations", "message"]
+                        }
+                    }
+                },
+                "required": ["problems"]
+            }),
+        ),
+        "update" => with_header(
+            name,
+            "ontoenv refresh --json",
+            json!({
+                "type": "object",
+                "properties": {
+                    "added": {"type": "array", "items": {"$ref": "#/$defs/graphIdentifier"}},
+                    "updated": {"type": "array", "items": {"$ref": "#/$defs/graphIdentifier"}},
+                    "removed": {"type": "array", "items": {"$ref": "#/$defs/graphIdentifier"}},
+                    "failed": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "location": {"$ref": "#/$defs/location"},
+                                "error": {"type": "string"},
+                                "line": {
+                                    "description": "The line the parser stopped at, for documents which failed to parse",
+                                    "type": "integer",
+                                    "minimum": 1
+                                }
+                            },
+                            "required": ["location", "error"]
+                        }
+                    }
+                },
+                "required": ["added", "updated", "removed", "failed"]
+            }),
+        ),
+        "plan" => with_header(
+            name,
+            "ontoenv refresh --dry-run --json",
+            json!({
+                "type": "object",
+                "properties": {
+                    "to_parse": {"type": "array", "items": {"$ref": "#/$defs/location"}},
+                    "to_remove": {"type": "array", "items": {"$ref": "#/$defs/graphIdentifier"}},
+                    "to_fetch": {"type": "array", "items": {"$ref": "#/$defs/location"}}
+                },
+                "required": ["to_parse", "to_remove", "to_fetch"]
+            }),
+        ),
+        "missing" => with_header(
+            name,
+            "ontoenv list-missing --json",
+            json!({
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "import": {"type": "string", "format": "iri"},
+                        "failure": {
+                            "description": "Why fetching the import failed, if it was attempted",
+                            "type": ["object", "null"],
+                            "properties": {
+                                "error": {"type": "string"},
+                                "status": {"type": "integer", "minimum": 100},
+                                "attempts": {"type": "integer", "minimum": 1},
+                                "last_attempt": {"type": "string", "format": "date-time"}
+                            },
+                            "required": ["error", "attempts", "last_attempt"]
+                        }
+                    },
+                    "required": ["import", "failure"]
+                }
+            }),
+        ),
+        "lockfile" => with_header(
+            name,
+            "ontoenv.lock",
+            json!({
+                "type": "object",
+                "properties": {
+                    "version": {"type": "integer", "enum": [1]},
+                    "ontologies": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "name": {"type": "string", "format": "iri"},
+                                "location": {"$ref": "#/$defs/location"},
+                                "hash": {
+                                    "description": "SHA-256 of the ontology's sorted triples",
+                                    "type": "string"
+                                },
+                                "retrieved": {"type": ["string", "null"], "format": "date-time"}
+                            },
+                            "required": ["name", "location", "hash", "retrieved"]
+                        }
+                    }
+                },
+                "required": ["version", "ontologies"]
+            }),
+        ),
+        "changes" => with_header(
+            name,
+            "Change manifest written or posted after an update",
+            json!({
+                "type": "object",
+                "properties": {
+                    "version": {"type": "integer", "enum": [1]},
+                    "root": {"type": "string"},
+                    "timestamp": {"type": "string", "format": "date-time"},
+                    "changes": {
+                        "type": "array",
+                        "items": {
+                            "type": "object",
+                            "properties": {
+                                "change": {"enum": ["added", "updated", "removed"]},
+                                "name": {"type": "string", "format": "iri"},
+                                "graph": {
+                                    "description": "Name of the ontology's graph in the store",
+                                    "type": "string",
+                                    "format": "iri"
+                                },
+                                "location": {"$ref": "#/$defs/location"},
+                                "fingerprint": {
+                                    "description": "SHA-256 of the graph's sorted triples, null for removed graphs",
+                                    "type": ["string", "null"]
+                                }
+                            },
+                            "required": ["change", "name", "graph", "location", "fingerprint"]
+                        }
+                    }
+                },
+                "required": ["version", "root", "timestamp", "changes"]
+            }),
+        ),
+        _ => return None,
+    };
+    Some(schema)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::changes::{ChangeKind, ChangeManifest, ChangedGraph, CHANGE_MANIFEST_VERSION};
+    use crate::config::HowCreated;
+    use crate::doctor::{DoctorReport, OntologyProblem};
+    use crate::errors::FetchError;
+    use crate::failures::{ImportFailure, MissingImport};
+    use crate::lockfile::{LockedOntology, Lockfile, LOCKFILE_VERSION};
+    use crate::ontology::{GraphIdentifier, OntologyLocation};
+    use crate::{EnvironmentStatus, UpdateFailure, UpdatePlan, UpdateReport};
+    use chrono::Utc;
+    use oxigraph::model::NamedNodeRef;
+    use std::path::PathBuf;
+
+    // checks the subset of JSON Schema used above: type, enum, properties, required,
+    // additionalProperties, items, oneOf and local $refs
+    fn conforms(value: &Value, schema: &Value, root: &Value) -> bool {
+        if let Some(reference) = schema["$ref"].as_str() {
+            let name = reference.trim_start_matches("#/$defs/");
+            return conforms(value, &root["$defs"][name], root);
+        }
+        if let Some(options) = schema["oneOf"].as_array() {
+            return options.iter().filter(|o| conforms(value, o, root)).count() == 1;
+        }
+        if let Some(values) = schema["enum"].as_array() {
+            if !values.contains(value) {
+                return false;
+            }
+        }
+        let types: Vec<&str> = match &schema["type"] {
+            Value::String(t) => vec![t.as_str()],
+            Value::Array(ts) => ts.iter().filter_map(|t| t.as_str()).collect(),
+            _ => vec![],
+        };
+        if !types.is_empty()
+            && !types.iter().any(|t| match *t {
+                "object" => value.is_object(),
+                "array" => value.is_array(),
+                "string" => value.is_string(),
+                "integer" => value.is_u64() || value.is_i64(),
+                "boolean" => value.is_boolean(),
+                "null" => value.is_null(),
+                _ => false,
+            })
+        {
+            return false;
+        }
+        if let Some(object) = value.as_object() {
+            let properties = schema["properties"].as_object();
+            for required in schema["required"].as_array().into_iter().flatten() {
+                if !object.contains_key(required.as_str().unwrap()) {
+                    return false;
+                }
+            }
+            for (key, v) in object {
+                match properties.and_then(|p| p.get(key)) {
+                    Some(property) if !conforms(v, property, root) => return false,
+                    None if schema["additionalProperties"] == json!(false) => return false,
+                    _ => {}
+                }
+            }
+        }
+        if let Some(items) = value.as_array() {
+            if !items.iter().all(|i| conforms(i, &schema["items"], root)) {
+                return false;
+            }
+        }
+        true
+    }
+
+    fn check(name: &str, value: impl serde::Serialize) {
+        let schema = schema(name).unwrap();
+        let value = serde_json::to_value(value).unwrap();
+        assert!(
+            conforms(&value, &schema, &schema),
+            "{} does not conform to the {} schema",
+            value,
+            name
+        );
+    }
+
+    fn id() -> GraphIdentifier {
+        GraphIdentifier::new(NamedNodeRef::new("http://example.org/ontology1").unwrap())
+    }
+
+    #[test]
+    fn test_schemas_match_serializations() {
+        assert!(SCHEMA_NAMES.iter().all(|n| schema(n).is_some()));
+        assert!(schema("unknown").is_none());
+
+        check(
+            "status",
+            EnvironmentStatus {
+                exists: true,
+                num_ontologies: 3,
+                last_updated: Some(Utc::now()),
+                store_size: 1024,
+                how_created: HowCreated::SameConfig,
+            },
+        );
+        check(
+            "doctor",
+            DoctorReport {
+                problems: vec![OntologyProblem {
+                    locations: vec![
+                        OntologyLocation::File(PathBuf::from("/tmp/ontology1.ttl")),
+                        OntologyLocation::Url("http://example.org/ontology1".to_string()),
+                    ],
+                    graph: None,
+                    message: "Multiple ontologies with the same name".to_string(),
+                }],
+            },
+        );
+        check(
+            "update",
+            UpdateReport {
+                added: vec![id()],
+                updated: vec![],
+                removed: vec![id()],
+                failed: vec![UpdateFailure {
+                    location: OntologyLocation::File(PathBuf::from("/tmp/broken.ttl")),
+                    error: "Failed to parse".to_string(),
+                    line: Some(3),
+                }],
+            },
+        );
+        check(
+            "plan",
+            UpdatePlan {
+                to_parse: vec![OntologyLocation::File(PathBuf::from("/tmp/ontology1.ttl"))],
+                to_remove: vec![id()],
+                to_fetch: vec![OntologyLocation::Url(
+                    "http://example.org/ontology2".to_string(),
+                )],
+            },
+        );
+        let error = anyhow::Error::new(FetchError {
+            url: "http://example.org/ontology2".to_string(),
+            status: 404,
+        });
+        check(
+            "missing",
+            vec![
+                MissingImport {
+                    import: "http://example.org/ontology2".to_string(),
+                    failure: Some(ImportFailure::new(&error)),
+                },
+                MissingImport {
+                    import: "http://example.org/ontology3".to_string(),
+                    failure: None,
+                },
+            ],
+        );
+        check(
+            "lockfile",
+            Lockfile {
+                version: LOCKFILE_VERSION,
+                ontologies: vec![LockedOntology {
+                    name: "http://example.org/ontology1".to_string(),
+                    location: OntologyLocation::File(PathBuf::from("ontology1.ttl")),
+                    hash: "e3b0c44298fc1c149afbf4c8996fb924".to_string(),
+                    retrieved: Some(Utc::now()),
+                }],
+            },
+        );
+        check(
+            "changes",
+            ChangeManifest {
+                version: CHANGE_MANIFEST_VERSION,
+                root: "/tmp".to_string(),
+                timestamp: Utc::now(),
+                changes: vec![ChangedGraph {
+                    change: ChangeKind::Removed,
+                    name: "http://example.org/ontology1".to_string(),
+                    graph: "urn:ontoenv:graph:1".to_string(),
+                    location: OntologyLocation::File(PathBuf::from("/tmp/ontology1.ttl")),
+                    fingerprint: None,
+                }],
+            },
+        );
+    }
+}
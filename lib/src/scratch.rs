@@ -0,0 +1,182 @@
+// scratch environments (see `OntoEnv::scratch`) keep their store and manifest in the per-user
+// cache directory (e.g. ~/.cache/ontoenv/scratch) instead of root/.ontoenv, so nothing is written
+// into the project tree. The directory is only accessible to the user, so other users of a
+// shared machine can neither read nor plant scratch environments.
+// The directory is named after a hash of the project root and the current day: constructing the
+// same scratch environment again on the same day reuses it, and the directories of earlier days
+// are removed when a new one is created
+
+use anyhow::Result;
+use chrono::prelude::*;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// the cache directory of the current user: $XDG_CACHE_HOME or ~/.cache (~/Library/Caches on
+// macOS), or %LOCALAPPDATA% on Windows
+fn user_cache_dir() -> Option<PathBuf> {
+    let var = |name: &str| {
+        std::env::var_os(name)
+            .map(PathBuf::from)
+            .filter(|dir| dir.is_absolute())
+    };
+    if cfg!(windows) {
+        return var("LOCALAPPDATA");
+    }
+    if let Some(dir) = var("XDG_CACHE_HOME") {
+        return Some(dir);
+    }
+    let home = var("HOME")?;
+    if cfg!(target_os = "macos") {
+        Some(home.join("Library").join("Caches"))
+    } else {
+        Some(home.join(".cache"))
+    }
+}
+
+/// Returns the directory holding the scratch environments of the current user, creating it if
+/// needed: ontoenv/scratch in the user's cache directory, or ontoenv-scratch-$USER in the
+/// temporary directory if there is none. Fails if other users can access it
+pub fn scratch_base() -> Result<PathBuf> {
+    let base = match user_cache_dir() {
+        Some(dir) => dir.join("ontoenv").join("scratch"),
+        None => {
+            let user: String = std::env::var("USER")
+                .or_else(|_| std::env::var("USERNAME"))
+                .unwrap_or_default()
+                .chars()
+                .filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+                .collect();
+            std::env::temp_dir().join(format!("ontoenv-scratch-{}", user))
+        }
+    };
+    create_private_dir(&base)?;
+    Ok(base)
+}
+
+// creates the directory readable and writable only by the current user, and refuses an
+// existing one which is a symlink or which other users can access: a directory someone else
+// created (in a shared temporary directory) is then either refused or not writable
+#[cfg(unix)]
+fn create_private_dir(dir: &Path) -> Result<()> {
+    use std::os::unix::fs::{DirBuilderExt, PermissionsExt};
+    fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(dir)?;
+    let metadata = fs::symlink_metadata(dir)?;
+    if !metadata.is_dir() || metadata.permissions().mode() & 0o077 != 0 {
+        return Err(anyhow::anyhow!(
+            "Refusing to use the scratch directory {}, which other users can access",
+            dir.display()
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn create_private_dir(dir: &Path) -> Result<()> {
+    fs::create_dir_all(dir)?;
+    Ok(())
+}
+
+// the first 16 hex digits of the hash of the (canonical, if it exists) project root
+fn project_key(root: &Path) -> String {
+    let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    let hash = format!("{:x}", Sha256::digest(root.to_string_lossy().as_bytes()));
+    hash[..16].to_string()
+}
+
+/// Returns the scratch directory of the project at the root for the day, under the base
+pub fn scratch_dir_in(base: &Path, root: &Path, day: NaiveDate) -> PathBuf {
+    base.join(format!("{}-{}", project_key(root), day.format("%Y%m%d")))
+}
+
+/// Returns today's scratch directory of the project at the root
+pub fn scratch_dir(root: &Path) -> Result<PathBuf> {
+    Ok(scratch_dir_in(
+        &scratch_base()?,
+        root,
+        Local::now().date_naive(),
+    ))
+}
+
+/// Removes the scratch directories of the project at the root from days other than the given
+/// one, returning how many there were
+pub fn remove_stale_in(base: &Path, root: &Path, day: NaiveDate) -> Result<usize> {
+    let current = scratch_dir_in(base, root, day);
+    let prefix = format!("{}-", project_key(root));
+    let entries = match fs::read_dir(base) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e.into()),
+    };
+    let mut removed = 0;
+    for entry in entries {
+        let path = entry?.path();
+        let stale = path != current
+            && path.is_dir()
+            && path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with(&prefix));
+        if stale {
+            fs::remove_dir_all(&path)?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+/// Removes the scratch directories of the project at the root from earlier days
+pub fn remove_stale(root: &Path) -> Result<usize> {
+    remove_stale_in(&scratch_base()?, root, Local::now().date_naive())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scratch_dirs_are_per_project_and_day() {
+        let base = tempfile::tempdir().unwrap();
+        let day = NaiveDate::from_ymd_opt(2024, 5, 1).unwrap();
+        let next_day = day.succ_opt().unwrap();
+        let a = Path::new("/projects/a");
+        let b = Path::new("/projects/b");
+        assert_eq!(
+            scratch_dir_in(base.path(), a, day),
+            scratch_dir_in(base.path(), a, day)
+        );
+        assert_ne!(
+            scratch_dir_in(base.path(), a, day),
+            scratch_dir_in(base.path(), b, day)
+        );
+
+        for dir in [
+            scratch_dir_in(base.path(), a, day),
+            scratch_dir_in(base.path(), a, next_day),
+            scratch_dir_in(base.path(), b, day),
+        ] {
+            fs::create_dir_all(dir).unwrap();
+        }
+        // only the earlier day of the same project is stale
+        assert_eq!(remove_stale_in(base.path(), a, next_day).unwrap(), 1);
+        assert!(!scratch_dir_in(base.path(), a, day).exists());
+        assert!(scratch_dir_in(base.path(), a, next_day).exists());
+        assert!(scratch_dir_in(base.path(), b, day).exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_shared_scratch_base_is_refused() {
+        use std::os::unix::fs::PermissionsExt;
+        let parent = tempfile::tempdir().unwrap();
+        let base = parent.path().join("scratch");
+        create_private_dir(&base).unwrap();
+        let mode = fs::metadata(&base).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o700);
+
+        fs::set_permissions(&base, fs::Permissions::from_mode(0o777)).unwrap();
+        assert!(create_private_dir(&base).is_err());
+    }
+}
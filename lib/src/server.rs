@@ -0,0 +1,334 @@
+// a small read-only HTTP server exposing environment metadata as a REST/JSON API, for
+// dashboards over large shared environments. Requests are handled one at a time, so a client
+// gets IO_TIMEOUT to send its request and read the response, and requests with overlong lines or
+// too many headers are rejected.
+//
+// Endpoints (all GET, list endpoints take 'offset' and 'limit' query parameters):
+//
+// - /api/status: number of ontologies and triples in the environment
+// - /api/ontologies?q=: ontologies sorted by name, optionally filtered to names containing q
+// - /api/ontology?name=: name, location, version properties, imports and dependents of an ontology
+// - /api/imports?name=: the imports of an ontology, whether they resolve, and its imports closure
+// - /api/problems: the problems reported by the doctor
+
+use crate::OntoEnv;
+use anyhow::Result;
+use oxigraph::model::NamedNode;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::time::Duration;
+use tracing::{info, warn};
+
+const DEFAULT_LIMIT: usize = 100;
+const MAX_LIMIT: usize = 1000;
+// how long a client may take to send its request or to read the response
+const IO_TIMEOUT: Duration = Duration::from_secs(10);
+// longest request line or header, in bytes
+const MAX_LINE_LENGTH: usize = 8 * 1024;
+const MAX_HEADERS: usize = 100;
+
+/// Serves the API on the given address (e.g. 127.0.0.1:8080) until the process is stopped
+pub fn serve(env: &OntoEnv, addr: &str) -> Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    info!("Serving environment metadata on http://{}", addr);
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                warn!("Failed to accept connection: {}", e);
+                continue;
+            }
+        };
+        if let Err(e) = handle_connection(env, stream) {
+            warn!("Failed to handle request: {}", e);
+        }
+    }
+    Ok(())
+}
+
+// reads one line of at most MAX_LINE_LENGTH bytes; None if it is longer
+fn read_line_limited(reader: &mut impl BufRead) -> Result<Option<String>> {
+    let mut line = String::new();
+    reader
+        .take(MAX_LINE_LENGTH as u64 + 1)
+        .read_line(&mut line)?;
+    if line.len() > MAX_LINE_LENGTH {
+        return Ok(None);
+    }
+    Ok(Some(line))
+}
+
+fn handle_connection(env: &OntoEnv, stream: TcpStream) -> Result<()> {
+    stream.set_read_timeout(Some(IO_TIMEOUT))?;
+    stream.set_write_timeout(Some(IO_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let Some(request_line) = read_line_limited(&mut reader)? else {
+        return respond(stream, 414, json!({"error": "request line too long"}));
+    };
+    // skip the headers; the API does not use them
+    let mut headers = 0;
+    loop {
+        let Some(header) = read_line_limited(&mut reader)? else {
+            return respond(stream, 431, json!({"error": "header too long"}));
+        };
+        if header.trim().is_empty() {
+            break;
+        }
+        headers += 1;
+        if headers > MAX_HEADERS {
+            return respond(stream, 431, json!({"error": "too many headers"}));
+        }
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default();
+    let target = parts.next().unwrap_or_default();
+    let (status, body) = if method != "GET" {
+        (405, json!({"error": "only GET is supported"}))
+    } else {
+        let (path, query) = match target.split_once('?') {
+            Some((path, query)) => (path, parse_query(query)),
+            None => (target, HashMap::new()),
+        };
+        handle_api(env, path, &query)
+    };
+    info!("{} {} -> {}", method, target, status);
+    respond(stream, status, body)
+}
+
+fn respond(mut stream: TcpStream, status: u16, body: Value) -> Result<()> {
+    let body = body.to_string();
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        414 => "URI Too Long",
+        431 => "Request Header Fields Too Large",
+        _ => "Internal Server Error",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nAccess-Control-Allow-Origin: *\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason,
+        body.len(),
+        body
+    )?;
+    stream.flush()?;
+    Ok(())
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[i + 1..i + 3])
+                    .ok()
+                    .and_then(|h| u8::from_str_radix(h, 16).ok());
+                match hex {
+                    Some(b) => {
+                        decoded.push(b);
+                        i += 2;
+                    }
+                    None => decoded.push(b'%'),
+                }
+            }
+            b => decoded.push(b),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+fn parse_query(query: &str) -> HashMap<String, String> {
+    query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| match pair.split_once('=') {
+            Some((k, v)) => (percent_decode(k), percent_decode(v)),
+            None => (percent_decode(pair), String::new()),
+        })
+        .collect()
+}
+
+/// Returns one page of the items along with the paging information
+fn paginate(items: Vec<Value>, query: &HashMap<String, String>) -> Value {
+    let offset = query
+        .get("offset")
+        .and_then(|o| o.parse().ok())
+        .unwrap_or(0);
+    let limit = query
+        .get("limit")
+        .and_then(|l| l.parse().ok())
+        .unwrap_or(DEFAULT_LIMIT)
+        .min(MAX_LIMIT);
+    let total = items.len();
+    let page: Vec<Value> = items.into_iter().skip(offset).take(limit).collect();
+    json!({"total": total, "offset": offset, "limit": limit, "items": page})
+}
+
+fn name_param(query: &HashMap<String, String>) -> Result<NamedNode, (u16, Value)> {
+    let name = query
+        .get("name")
+        .ok_or((400, json!({"error": "missing 'name' parameter"})))?;
+    NamedNode::new(name.as_str()).map_err(|e| (400, json!({"error": e.to_string()})))
+}
+
+/// Handles a GET request for the path and returns the status code and JSON body
+pub fn handle_api(env: &OntoEnv, path: &str, query: &HashMap<String, String>) -> (u16, Value) {
+    let result = match path.trim_end_matches('/') {
+        "/api/status" => status(env),
+        "/api/ontologies" => ontologies(env, query),
+        "/api/ontology" => ontology(env, query),
+        "/api/imports" => imports(env, query),
+        "/api/problems" => problems(env, query),
+        _ => Err((404, json!({"error": format!("no such endpoint: {}", path)}))),
+    };
+    match result {
+        Ok(body) => (200, body),
+        Err(e) => e,
+    }
+}
+
+fn internal_error(e: anyhow::Error) -> (u16, Value) {
+    (500, json!({"error": e.to_string()}))
+}
+
+fn status(env: &OntoEnv) -> Result<Value, (u16, Value)> {
+    Ok(json!({
+        "ontologies": env.num_graphs(),
+        "triples": env.num_triples().map_err(internal_error)?,
+    }))
+}
+
+fn ontologies(env: &OntoEnv, query: &HashMap<String, String>) -> Result<Value, (u16, Value)> {
+    let mut ontologies: Vec<_> = env.ontologies().values().collect();
+    if let Some(q) = query.get("q") {
+        ontologies.retain(|o| o.name().as_str().contains(q.as_str()));
+    }
+    ontologies.sort_by(|a, b| {
+        a.name()
+            .cmp(&b.name())
+            .then_with(|| a.id().location().as_str().cmp(b.id().location().as_str()))
+    });
+    let items = ontologies
+        .into_iter()
+        .map(|o| {
+            json!({
+                "name": o.name().as_str(),
+                "location": o.id().location().to_string(),
+                "imports": o.imports.len(),
+                "last_updated": o.last_updated,
+            })
+        })
+        .collect();
+    Ok(paginate(items, query))
+}
+
+fn ontology(env: &OntoEnv, query: &HashMap<String, String>) -> Result<Value, (u16, Value)> {
+    let name = name_param(query)?;
+    let ontology = env.get_ontology_by_name(name.as_ref()).ok_or((
+        404,
+        json!({"error": format!("ontology {} not found", name)}),
+    ))?;
+    let version_properties: HashMap<&str, &str> = ontology
+        .version_properties()
+        .iter()
+        .map(|(k, v)| (k.as_str(), v.as_str()))
+        .collect();
+    let mut dependents: Vec<String> = env
+        .get_dependents(&name)
+        .map_err(internal_error)?
+        .iter()
+        .map(|id| id.name().as_str().to_string())
+        .collect();
+    dependents.sort();
+    Ok(json!({
+        "name": ontology.name().as_str(),
+        "location": ontology.id().location().to_string(),
+        "last_updated": ontology.last_updated,
+        "version_properties": version_properties,
+        "imports": ontology.imports.iter().map(|i| i.as_str()).collect::<Vec<_>>(),
+        "dependents": dependents,
+    }))
+}
+
+fn imports(env: &OntoEnv, query: &HashMap<String, String>) -> Result<Value, (u16, Value)> {
+    let name = name_param(query)?;
+    let ontology = env.get_ontology_by_name(name.as_ref()).ok_or((
+        404,
+        json!({"error": format!("ontology {} not found", name)}),
+    ))?;
+    let imports: Vec<Value> = ontology
+        .imports
+        .iter()
+        .map(|i| {
+            json!({
+                "name": i.as_str(),
+                "resolved": env.get_ontology_by_name(i.into()).is_some(),
+            })
+        })
+        .collect();
+    let closure: Vec<String> = env
+        .get_dependency_closure(ontology.id())
+        .map_err(internal_error)?
+        .iter()
+        .map(|id| id.name().as_str().to_string())
+        .collect();
+    Ok(json!({"name": name.as_str(), "imports": imports, "closure": closure}))
+}
+
+fn problems(env: &OntoEnv, query: &HashMap<String, String>) -> Result<Value, (u16, Value)> {
+    let items = env
+        .problems()
+        .map_err(internal_error)?
+        .into_iter()
+        .map(|p| {
+            json!({
+                "message": p.message,
+                "locations": p.locations.iter().map(|l| l.to_string()).collect::<Vec<_>>(),
+            })
+        })
+        .collect();
+    Ok(paginate(items, query))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_query() {
+        let query = parse_query("name=http%3A%2F%2Fexample.org%2Font%231&limit=10&flag");
+        assert_eq!(query.get("name").unwrap(), "http://example.org/ont#1");
+        assert_eq!(query.get("limit").unwrap(), "10");
+        assert_eq!(query.get("flag").unwrap(), "");
+        assert_eq!(percent_decode("a+b%2"), "a b%2");
+    }
+
+    #[test]
+    fn test_paginate() {
+        let items: Vec<Value> = (0..5).map(|i| json!(i)).collect();
+        let query = parse_query("offset=3&limit=10");
+        let page = paginate(items, &query);
+        assert_eq!(page["total"], 5);
+        assert_eq!(page["items"], json!([3, 4]));
+    }
+
+    #[test]
+    fn test_read_line_limited() -> Result<()> {
+        let mut reader = "GET /api/status HTTP/1.1\r\nHost: x\r\n".as_bytes();
+        let line = read_line_limited(&mut reader)?;
+        assert_eq!(line.as_deref(), Some("GET /api/status HTTP/1.1\r\n"));
+        let long = format!("X-Long: {}\r\n", "a".repeat(MAX_LINE_LENGTH));
+        assert!(read_line_limited(&mut long.as_bytes())?.is_none());
+        Ok(())
+    }
+}
@@ -0,0 +1,812 @@
+// a small SHACL Core validator (enabled with the "shacl" feature), so imports closures can be
+// checked against the shapes their ontologies ship without leaving ontoenv. It supports all four
+// kinds of targets as well as implicit class targets, sh:property with predicate and inverse
+// paths, and the sh:minCount, sh:maxCount, sh:class, sh:datatype, sh:nodeKind, sh:in,
+// sh:hasValue and sh:pattern constraints. Other constraints and paths are reported as skipped,
+// and a data graph with skipped constraints does not conform, since it was not fully checked;
+// for full SHACL (SPARQL constraints, rules) validate `OntoEnv::shacl_inputs` with e.g. pySHACL
+
+use crate::consts::{CLASS, RDFS_CLASS, TYPE};
+use anyhow::Result;
+use oxigraph::model::vocab::{rdf, rdfs};
+use oxigraph::model::{Graph, NamedNodeRef, SubjectRef, Term, TermRef, TripleRef};
+use regex::Regex;
+use serde::Serialize;
+use std::collections::{BTreeSet, HashSet, VecDeque};
+use std::fmt;
+use tracing::warn;
+
+const SH: &str = "http://www.w3.org/ns/shacl#";
+const NODE_SHAPE: NamedNodeRef<'_> =
+    NamedNodeRef::new_unchecked("http://www.w3.org/ns/shacl#NodeShape");
+const PROPERTY_SHAPE: NamedNodeRef<'_> =
+    NamedNodeRef::new_unchecked("http://www.w3.org/ns/shacl#PropertyShape");
+const TARGET_CLASS: NamedNodeRef<'_> =
+    NamedNodeRef::new_unchecked("http://www.w3.org/ns/shacl#targetClass");
+const TARGET_NODE: NamedNodeRef<'_> =
+    NamedNodeRef::new_unchecked("http://www.w3.org/ns/shacl#targetNode");
+const TARGET_SUBJECTS_OF: NamedNodeRef<'_> =
+    NamedNodeRef::new_unchecked("http://www.w3.org/ns/shacl#targetSubjectsOf");
+const TARGET_OBJECTS_OF: NamedNodeRef<'_> =
+    NamedNodeRef::new_unchecked("http://www.w3.org/ns/shacl#targetObjectsOf");
+const PROPERTY: NamedNodeRef<'_> =
+    NamedNodeRef::new_unchecked("http://www.w3.org/ns/shacl#property");
+const PATH: NamedNodeRef<'_> = NamedNodeRef::new_unchecked("http://www.w3.org/ns/shacl#path");
+const INVERSE_PATH: NamedNodeRef<'_> =
+    NamedNodeRef::new_unchecked("http://www.w3.org/ns/shacl#inversePath");
+const DEACTIVATED: NamedNodeRef<'_> =
+    NamedNodeRef::new_unchecked("http://www.w3.org/ns/shacl#deactivated");
+const SEVERITY: NamedNodeRef<'_> =
+    NamedNodeRef::new_unchecked("http://www.w3.org/ns/shacl#severity");
+const MESSAGE: NamedNodeRef<'_> = NamedNodeRef::new_unchecked("http://www.w3.org/ns/shacl#message");
+const INFO: NamedNodeRef<'_> = NamedNodeRef::new_unchecked("http://www.w3.org/ns/shacl#Info");
+const WARNING: NamedNodeRef<'_> = NamedNodeRef::new_unchecked("http://www.w3.org/ns/shacl#Warning");
+const MIN_COUNT: NamedNodeRef<'_> =
+    NamedNodeRef::new_unchecked("http://www.w3.org/ns/shacl#minCount");
+const MAX_COUNT: NamedNodeRef<'_> =
+    NamedNodeRef::new_unchecked("http://www.w3.org/ns/shacl#maxCount");
+const SH_CLASS: NamedNodeRef<'_> = NamedNodeRef::new_unchecked("http://www.w3.org/ns/shacl#class");
+const DATATYPE: NamedNodeRef<'_> =
+    NamedNodeRef::new_unchecked("http://www.w3.org/ns/shacl#datatype");
+const NODE_KIND: NamedNodeRef<'_> =
+    NamedNodeRef::new_unchecked("http://www.w3.org/ns/shacl#nodeKind");
+const IN: NamedNodeRef<'_> = NamedNodeRef::new_unchecked("http://www.w3.org/ns/shacl#in");
+const HAS_VALUE: NamedNodeRef<'_> =
+    NamedNodeRef::new_unchecked("http://www.w3.org/ns/shacl#hasValue");
+const PATTERN: NamedNodeRef<'_> = NamedNodeRef::new_unchecked("http://www.w3.org/ns/shacl#pattern");
+const FLAGS: NamedNodeRef<'_> = NamedNodeRef::new_unchecked("http://www.w3.org/ns/shacl#flags");
+// the parameters of the SHACL Core and SPARQL constraint components this validator does not check
+const UNSUPPORTED: [&str; 20] = [
+    "node",
+    "and",
+    "or",
+    "not",
+    "xone",
+    "qualifiedValueShape",
+    "minInclusive",
+    "maxInclusive",
+    "minExclusive",
+    "maxExclusive",
+    "minLength",
+    "maxLength",
+    "languageIn",
+    "uniqueLang",
+    "equals",
+    "disjoint",
+    "lessThan",
+    "lessThanOrEquals",
+    "closed",
+    "sparql",
+];
+
+/// The severity of a validation result, from the sh:severity of the shape (sh:Violation if it
+/// has none)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Violation,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Severity::Info => write!(f, "info"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Violation => write!(f, "violation"),
+        }
+    }
+}
+
+/// A focus node which does not conform to a constraint of a shape. Nodes are written as in
+/// N-Triples, e.g. `<urn:ex#sensor1>`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ValidationResult {
+    pub focus_node: String,
+    // the path of the property shape (`^<p>` for inverse paths), for property shapes only
+    pub path: Option<String>,
+    // the value which failed the constraint, for constraints on single values
+    pub value: Option<String>,
+    pub source_shape: String,
+    // the constraint component, e.g. sh:minCount
+    pub constraint: String,
+    pub severity: Severity,
+    // the sh:message of the shape, or a description of the failed constraint
+    pub message: String,
+}
+
+impl fmt::Display for ValidationResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.severity, self.focus_node)?;
+        if let Some(path) = &self.path {
+            write!(f, " {}", path)?;
+        }
+        write!(
+            f,
+            ": {} ({} of {})",
+            self.message, self.constraint, self.source_shape
+        )
+    }
+}
+
+/// A constraint of a shape which was not checked because the validator does not support it
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct SkippedConstraint {
+    pub source_shape: String,
+    // the constraint component, e.g. sh:or, or sh:path for shapes with an unsupported path
+    pub constraint: String,
+}
+
+impl fmt::Display for SkippedConstraint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "skipped: {} of {}", self.constraint, self.source_shape)
+    }
+}
+
+/// The outcome of validating a data graph: it conforms if there are no results and no
+/// constraint was skipped
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ValidationReport {
+    pub conforms: bool,
+    pub results: Vec<ValidationResult>,
+    // the unsupported constraints of the shapes which applied to a focus node, sorted
+    pub skipped: Vec<SkippedConstraint>,
+}
+
+impl ValidationReport {
+    /// Returns the number of results with the given severity
+    pub fn count(&self, severity: Severity) -> usize {
+        self.results
+            .iter()
+            .filter(|result| result.severity == severity)
+            .count()
+    }
+}
+
+/// Validates the data graph against the shapes in the shapes graph. Class membership
+/// (sh:targetClass, sh:class) follows rdfs:subClassOf in the data graph, so the ontologies
+/// defining the classes should be part of it. Fails if a shape has an invalid sh:pattern
+pub fn validate(data: &Graph, shapes: &Graph) -> Result<ValidationReport> {
+    let graphs = Graphs { data, shapes };
+    let mut results = vec![];
+    let mut skipped = BTreeSet::new();
+    for shape in graphs.targeted_shapes() {
+        if graphs.is_deactivated(&shape) {
+            continue;
+        }
+        for focus in graphs.target_nodes(&shape) {
+            graphs.check_shape(&shape, &focus, &mut results, &mut skipped)?;
+        }
+    }
+    for constraint in skipped.iter() {
+        warn!(
+            shape = %constraint.source_shape,
+            "Skipping the unsupported constraint {}", constraint.constraint
+        );
+    }
+    Ok(ValidationReport {
+        conforms: results.is_empty() && skipped.is_empty(),
+        results,
+        skipped: skipped.into_iter().collect(),
+    })
+}
+
+fn as_subject(term: &Term) -> Option<SubjectRef<'_>> {
+    match term {
+        Term::NamedNode(node) => Some(node.as_ref().into()),
+        Term::BlankNode(node) => Some(node.as_ref().into()),
+        _ => None,
+    }
+}
+
+// the constraint component named after the constraint parameter, e.g. sh:minCount
+fn component(parameter: NamedNodeRef) -> String {
+    format!("sh:{}", parameter.as_str().trim_start_matches(SH))
+}
+
+#[derive(Clone, Copy)]
+struct Graphs<'a> {
+    data: &'a Graph,
+    shapes: &'a Graph,
+}
+
+impl Graphs<'_> {
+    // the objects of the shape's triples with the predicate
+    fn parameters(&self, shape: &Term, predicate: NamedNodeRef) -> Vec<Term> {
+        match as_subject(shape) {
+            Some(subject) => self
+                .shapes
+                .objects_for_subject_predicate(subject, predicate)
+                .map(TermRef::into_owned)
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    fn parameter(&self, shape: &Term, predicate: NamedNodeRef) -> Option<Term> {
+        self.parameters(shape, predicate).into_iter().next()
+    }
+
+    fn integer_parameter(&self, shape: &Term, predicate: NamedNodeRef) -> Option<usize> {
+        match self.parameter(shape, predicate)? {
+            Term::Literal(literal) => literal.value().parse().ok(),
+            _ => None,
+        }
+    }
+
+    fn is_deactivated(&self, shape: &Term) -> bool {
+        matches!(self.parameter(shape, DEACTIVATED), Some(Term::Literal(l)) if l.value() == "true")
+    }
+
+    // shapes which are also classes target their instances
+    fn is_implicit_class_target(&self, shape: &Term) -> bool {
+        let Some(subject) = as_subject(shape) else {
+            return false;
+        };
+        [CLASS, RDFS_CLASS]
+            .iter()
+            .any(|class| self.shapes.contains(TripleRef::new(subject, TYPE, *class)))
+    }
+
+    // the shapes with targets, in the order they are found
+    fn targeted_shapes(&self) -> Vec<Term> {
+        let mut seen: HashSet<Term> = HashSet::new();
+        let mut shapes = vec![];
+        for predicate in [
+            TARGET_CLASS,
+            TARGET_NODE,
+            TARGET_SUBJECTS_OF,
+            TARGET_OBJECTS_OF,
+        ] {
+            for triple in self.shapes.triples_for_predicate(predicate) {
+                shapes.push(Term::from(triple.subject.into_owned()));
+            }
+        }
+        for shape_type in [NODE_SHAPE, PROPERTY_SHAPE] {
+            for subject in self.shapes.subjects_for_predicate_object(TYPE, shape_type) {
+                let shape = Term::from(subject.into_owned());
+                if self.is_implicit_class_target(&shape) {
+                    shapes.push(shape);
+                }
+            }
+        }
+        shapes.retain(|shape| seen.insert(shape.clone()));
+        shapes
+    }
+
+    fn target_nodes(&self, shape: &Term) -> Vec<Term> {
+        let mut nodes = vec![];
+        for class in self.parameters(shape, TARGET_CLASS) {
+            nodes.extend(self.instances_of(&class));
+        }
+        if self.is_implicit_class_target(shape) {
+            nodes.extend(self.instances_of(shape));
+        }
+        nodes.extend(self.parameters(shape, TARGET_NODE));
+        for predicate in self.parameters(shape, TARGET_SUBJECTS_OF) {
+            if let Term::NamedNode(predicate) = predicate {
+                nodes.extend(
+                    self.data
+                        .triples_for_predicate(predicate.as_ref())
+                        .map(|t| Term::from(t.subject.into_owned())),
+                );
+            }
+        }
+        for predicate in self.parameters(shape, TARGET_OBJECTS_OF) {
+            if let Term::NamedNode(predicate) = predicate {
+                nodes.extend(
+                    self.data
+                        .triples_for_predicate(predicate.as_ref())
+                        .map(|t| t.object.into_owned()),
+                );
+            }
+        }
+        let mut seen: HashSet<Term> = HashSet::new();
+        nodes.retain(|node| seen.insert(node.clone()));
+        nodes
+    }
+
+    // the class and its subclasses in the data graph
+    fn subclasses(&self, class: &Term) -> HashSet<Term> {
+        let mut classes: HashSet<Term> = HashSet::from([class.clone()]);
+        let mut queue: VecDeque<Term> = VecDeque::from([class.clone()]);
+        while let Some(class) = queue.pop_front() {
+            for subclass in self
+                .data
+                .subjects_for_predicate_object(rdfs::SUB_CLASS_OF, class.as_ref())
+            {
+                let subclass = Term::from(subclass.into_owned());
+                if classes.insert(subclass.clone()) {
+                    queue.push_back(subclass);
+                }
+            }
+        }
+        classes
+    }
+
+    fn instances_of(&self, class: &Term) -> Vec<Term> {
+        let mut instances = vec![];
+        for class in self.subclasses(class) {
+            instances.extend(
+                self.data
+                    .subjects_for_predicate_object(TYPE, class.as_ref())
+                    .map(|s| Term::from(s.into_owned())),
+            );
+        }
+        instances
+    }
+
+    // whether the node has the class or one of its subclasses as an rdf:type
+    fn is_instance(&self, node: &Term, class: &Term) -> bool {
+        let Some(subject) = as_subject(node) else {
+            return false;
+        };
+        let classes = self.subclasses(class);
+        self.data
+            .objects_for_subject_predicate(subject, TYPE)
+            .any(|t| classes.contains(&t.into_owned()))
+    }
+
+    // the members of the RDF list starting at the head, in the shapes graph
+    fn list(&self, head: &Term) -> Vec<Term> {
+        let mut items = vec![];
+        let mut seen: HashSet<Term> = HashSet::new();
+        let mut node = head.clone();
+        while node != Term::from(rdf::NIL.into_owned()) && seen.insert(node.clone()) {
+            if let Some(first) = self.parameter(&node, rdf::FIRST) {
+                items.push(first);
+            }
+            match self.parameter(&node, rdf::REST) {
+                Some(rest) => node = rest,
+                None => break,
+            }
+        }
+        items
+    }
+
+    // the values of the path for the focus node, or None for unsupported paths
+    fn path_values(&self, path: &Term, focus: &Term) -> Option<Vec<Term>> {
+        match path {
+            Term::NamedNode(predicate) => Some(match as_subject(focus) {
+                Some(subject) => self
+                    .data
+                    .objects_for_subject_predicate(subject, predicate.as_ref())
+                    .map(TermRef::into_owned)
+                    .collect(),
+                None => vec![],
+            }),
+            Term::BlankNode(_) => match self.parameter(path, INVERSE_PATH)? {
+                Term::NamedNode(predicate) => Some(
+                    self.data
+                        .subjects_for_predicate_object(predicate.as_ref(), focus.as_ref())
+                        .map(|s| Term::from(s.into_owned()))
+                        .collect(),
+                ),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    fn path_label(&self, path: &Term) -> String {
+        match self.parameter(path, INVERSE_PATH) {
+            Some(predicate) => format!("^{}", predicate),
+            None => path.to_string(),
+        }
+    }
+
+    // validates the focus node against the shape: a property shape (one with sh:path) checks
+    // the values of its path, a node shape the focus node itself and its property shapes
+    fn check_shape(
+        &self,
+        shape: &Term,
+        focus: &Term,
+        results: &mut Vec<ValidationResult>,
+        skipped: &mut BTreeSet<SkippedConstraint>,
+    ) -> Result<()> {
+        let mut skip = |constraint: String| {
+            skipped.insert(SkippedConstraint {
+                source_shape: shape.to_string(),
+                constraint,
+            })
+        };
+        let path = self.parameter(shape, PATH);
+        let values = match &path {
+            Some(path) => match self.path_values(path, focus) {
+                Some(values) => values,
+                None => {
+                    skip(component(PATH));
+                    return Ok(());
+                }
+            },
+            None => vec![focus.clone()],
+        };
+        for name in UNSUPPORTED {
+            let iri = format!("{}{}", SH, name);
+            let parameter = NamedNodeRef::new_unchecked(&iri);
+            if self.parameter(shape, parameter).is_some() {
+                skip(component(parameter));
+            }
+        }
+        self.check_constraints(shape, focus, path.as_ref(), &values, results)?;
+        if path.is_none() {
+            for property in self.parameters(shape, PROPERTY) {
+                if !self.is_deactivated(&property) {
+                    self.check_shape(&property, focus, results, skipped)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn check_constraints(
+        &self,
+        shape: &Term,
+        focus: &Term,
+        path: Option<&Term>,
+        values: &[Term],
+        results: &mut Vec<ValidationResult>,
+    ) -> Result<()> {
+        let severity = match self.parameter(shape, SEVERITY) {
+            Some(Term::NamedNode(n)) if n.as_ref() == INFO => Severity::Info,
+            Some(Term::NamedNode(n)) if n.as_ref() == WARNING => Severity::Warning,
+            _ => Severity::Violation,
+        };
+        let message = match self.parameter(shape, MESSAGE) {
+            Some(Term::Literal(literal)) => Some(literal.value().to_string()),
+            _ => None,
+        };
+        let path = path.map(|path| self.path_label(path));
+        let mut fail = |parameter: NamedNodeRef, value: Option<&Term>, description: String| {
+            results.push(ValidationResult {
+                focus_node: focus.to_string(),
+                path: path.clone(),
+                value: value.map(Term::to_string),
+                source_shape: shape.to_string(),
+                constraint: component(parameter),
+                severity,
+                message: message.clone().unwrap_or(description),
+            })
+        };
+
+        if let Some(min) = self.integer_parameter(shape, MIN_COUNT) {
+            if values.len() < min {
+                let description =
+                    format!("Expected at least {} value(s), found {}", min, values.len());
+                fail(MIN_COUNT, None, description);
+            }
+        }
+        if let Some(max) = self.integer_parameter(shape, MAX_COUNT) {
+            if values.len() > max {
+                let description =
+                    format!("Expected at most {} value(s), found {}", max, values.len());
+                fail(MAX_COUNT, None, description);
+            }
+        }
+        for expected in self.parameters(shape, HAS_VALUE) {
+            if !values.contains(&expected) {
+                fail(HAS_VALUE, None, format!("Missing the value {}", expected));
+            }
+        }
+        for class in self.parameters(shape, SH_CLASS) {
+            for value in values.iter().filter(|v| !self.is_instance(v, &class)) {
+                let description = format!("Value is not an instance of {}", class);
+                fail(SH_CLASS, Some(value), description);
+            }
+        }
+        for datatype in self.parameters(shape, DATATYPE) {
+            for value in values {
+                let matches = match (value, &datatype) {
+                    (Term::Literal(literal), Term::NamedNode(datatype)) => {
+                        literal.datatype() == datatype.as_ref()
+                    }
+                    _ => false,
+                };
+                if !matches {
+                    let description = format!("Value does not have the datatype {}", datatype);
+                    fail(DATATYPE, Some(value), description);
+                }
+            }
+        }
+        for kind in self.parameters(shape, NODE_KIND) {
+            let Term::NamedNode(kind) = kind else {
+                continue;
+            };
+            let allowed = kind.as_str().trim_start_matches(SH);
+            for value in values {
+                let actual = match value {
+                    Term::NamedNode(_) => "IRI",
+                    Term::BlankNode(_) => "BlankNode",
+                    _ => "Literal",
+                };
+                // e.g. sh:BlankNodeOrIRI allows both blank nodes and IRIs
+                if !allowed.split("Or").any(|k| k == actual) {
+                    let description = format!("Value is not of the node kind sh:{}", allowed);
+                    fail(NODE_KIND, Some(value), description);
+                }
+            }
+        }
+        for head in self.parameters(shape, IN) {
+            let members = self.list(&head);
+            for value in values.iter().filter(|v| !members.contains(v)) {
+                fail(
+                    IN,
+                    Some(value),
+                    "Value is not one of the allowed values".to_string(),
+                );
+            }
+        }
+        if let Some(Term::Literal(pattern)) = self.parameter(shape, PATTERN) {
+            let flags = match self.parameter(shape, FLAGS) {
+                Some(Term::Literal(flags)) => format!("(?{})", flags.value()),
+                _ => String::new(),
+            };
+            let regex = Regex::new(&format!("{}{}", flags, pattern.value()))?;
+            for value in values {
+                let matches = match value {
+                    Term::NamedNode(node) => regex.is_match(node.as_str()),
+                    Term::Literal(literal) => regex.is_match(literal.value()),
+                    _ => false,
+                };
+                if !matches {
+                    let description = format!("Value does not match {:?}", pattern.value());
+                    fail(PATTERN, Some(value), description);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use oxigraph::io::{RdfFormat, RdfParser};
+
+    fn parse(turtle: &str) -> Graph {
+        let mut graph = Graph::new();
+        for quad in RdfParser::from_format(RdfFormat::Turtle).for_reader(turtle.as_bytes()) {
+            graph.insert(quad.unwrap().as_ref());
+        }
+        graph
+    }
+
+    #[test]
+    fn test_validate() -> Result<()> {
+        let shapes = parse(
+            r#"
+            @prefix sh: <http://www.w3.org/ns/shacl#> .
+            @prefix xsd: <http://www.w3.org/2001/XMLSchema#> .
+            @prefix ex: <urn:ex#> .
+            ex:SensorShape a sh:NodeShape ;
+                sh:targetClass ex:Sensor ;
+                sh:property [
+                    sh:path ex:hasUnit ;
+                    sh:minCount 1 ;
+                    sh:class ex:Unit ;
+                ] , [
+                    sh:path ex:label ;
+                    sh:datatype xsd:string ;
+                    sh:pattern "^s" ;
+                    sh:severity sh:Warning ;
+                ] .
+            "#,
+        );
+        let data = parse(
+            r#"
+            @prefix rdfs: <http://www.w3.org/2000/01/rdf-schema#> .
+            @prefix ex: <urn:ex#> .
+            ex:TempSensor rdfs:subClassOf ex:Sensor .
+            ex:degC a ex:Unit .
+            ex:s1 a ex:TempSensor ; ex:hasUnit ex:degC ; ex:label "s1" .
+            ex:s2 a ex:Sensor ; ex:label "t2" .
+            ex:s3 a ex:Sensor ; ex:hasUnit ex:s1 ; ex:label "s3" .
+            "#,
+        );
+        let report = validate(&data, &shapes)?;
+        assert!(!report.conforms);
+        let failures: Vec<(&str, &str, Severity)> = report
+            .results
+            .iter()
+            .map(|r| (r.focus_node.as_str(), r.constraint.as_str(), r.severity))
+            .collect();
+        assert_eq!(failures.len(), 3);
+        assert!(failures.contains(&("<urn:ex#s2>", "sh:minCount", Severity::Violation)));
+        assert!(failures.contains(&("<urn:ex#s2>", "sh:pattern", Severity::Warning)));
+        assert!(failures.contains(&("<urn:ex#s3>", "sh:class", Severity::Violation)));
+        assert_eq!(report.count(Severity::Violation), 2);
+
+        assert!(validate(&parse("<urn:ex#s4> a <urn:ex#Sensor> ."), &Graph::new())?.conforms);
+        Ok(())
+    }
+
+    // the focus nodes and constraints of the results, sorted
+    fn failures(report: &ValidationReport) -> Vec<(&str, &str)> {
+        let mut failures: Vec<(&str, &str)> = report
+            .results
+            .iter()
+            .map(|r| (r.focus_node.as_str(), r.constraint.as_str()))
+            .collect();
+        failures.sort();
+        failures
+    }
+
+    #[test]
+    fn test_value_constraints() -> Result<()> {
+        let shapes = parse(
+            r#"
+            @prefix sh: <http://www.w3.org/ns/shacl#> .
+            @prefix ex: <urn:ex#> .
+            ex:PumpShape a sh:NodeShape ;
+                sh:targetClass ex:Pump ;
+                sh:property [
+                    sh:path ex:feeds ;
+                    sh:maxCount 1 ;
+                    sh:nodeKind sh:BlankNodeOrIRI ;
+                ] , [
+                    sh:path ex:status ;
+                    sh:in ( "on" "off" ) ;
+                ] , [
+                    sh:path ex:medium ;
+                    sh:hasValue ex:water ;
+                ] .
+            "#,
+        );
+        let data = parse(
+            r#"
+            @prefix ex: <urn:ex#> .
+            ex:p1 a ex:Pump ; ex:feeds ex:v1 ; ex:status "on" ; ex:medium ex:water .
+            ex:p2 a ex:Pump ; ex:feeds ex:v1, [] ; ex:medium ex:water, ex:air .
+            ex:p3 a ex:Pump ; ex:feeds "v1" ; ex:status "broken" ; ex:medium ex:air .
+            "#,
+        );
+        let report = validate(&data, &shapes)?;
+        assert_eq!(
+            failures(&report),
+            vec![
+                ("<urn:ex#p2>", "sh:maxCount"),
+                ("<urn:ex#p3>", "sh:hasValue"),
+                ("<urn:ex#p3>", "sh:in"),
+                ("<urn:ex#p3>", "sh:nodeKind"),
+            ]
+        );
+        let in_result = report
+            .results
+            .iter()
+            .find(|r| r.constraint == "sh:in")
+            .unwrap();
+        assert_eq!(in_result.value.as_deref(), Some("\"broken\""));
+        assert_eq!(in_result.path.as_deref(), Some("<urn:ex#status>"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_targets() -> Result<()> {
+        let shapes = parse(
+            r#"
+            @prefix sh: <http://www.w3.org/ns/shacl#> .
+            @prefix ex: <urn:ex#> .
+            ex:NodeTarget a sh:NodeShape ;
+                sh:targetNode ex:a, ex:missing ;
+                sh:property [ sh:path ex:label ; sh:minCount 1 ] .
+            ex:SubjectsTarget a sh:NodeShape ;
+                sh:targetSubjectsOf ex:feeds ;
+                sh:property [ sh:path ex:label ; sh:minCount 1 ] .
+            ex:ObjectsTarget a sh:NodeShape ;
+                sh:targetObjectsOf ex:feeds ;
+                sh:nodeKind sh:IRI .
+            "#,
+        );
+        let data = parse(
+            r#"
+            @prefix ex: <urn:ex#> .
+            ex:a ex:label "a" ; ex:feeds ex:b .
+            ex:c ex:feeds "d" .
+            "#,
+        );
+        // targeted nodes need not occur in the data graph
+        assert_eq!(
+            failures(&validate(&data, &shapes)?),
+            vec![
+                ("\"d\"", "sh:nodeKind"),
+                ("<urn:ex#c>", "sh:minCount"),
+                ("<urn:ex#missing>", "sh:minCount"),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_inverse_paths_and_deactivated_shapes() -> Result<()> {
+        let shapes = parse(
+            r#"
+            @prefix sh: <http://www.w3.org/ns/shacl#> .
+            @prefix ex: <urn:ex#> .
+            ex:ValveShape a sh:NodeShape ;
+                sh:targetClass ex:Valve ;
+                sh:property [
+                    sh:path [ sh:inversePath ex:feeds ] ;
+                    sh:minCount 1 ;
+                    sh:class ex:Pump ;
+                ] , [
+                    sh:path ex:label ;
+                    sh:minCount 1 ;
+                    sh:deactivated true ;
+                ] .
+            ex:DeactivatedShape a sh:NodeShape ;
+                sh:targetClass ex:Valve ;
+                sh:deactivated true ;
+                sh:nodeKind sh:Literal .
+            "#,
+        );
+        let data = parse(
+            r#"
+            @prefix ex: <urn:ex#> .
+            ex:p1 a ex:Pump ; ex:feeds ex:v1 .
+            ex:t1 a ex:Tank ; ex:feeds ex:v2 .
+            ex:v1 a ex:Valve .
+            ex:v2 a ex:Valve .
+            ex:v3 a ex:Valve .
+            "#,
+        );
+        let report = validate(&data, &shapes)?;
+        assert_eq!(
+            failures(&report),
+            vec![("<urn:ex#v2>", "sh:class"), ("<urn:ex#v3>", "sh:minCount")]
+        );
+        let class_result = report
+            .results
+            .iter()
+            .find(|r| r.constraint == "sh:class")
+            .unwrap();
+        assert_eq!(class_result.path.as_deref(), Some("^<urn:ex#feeds>"));
+        assert_eq!(class_result.value.as_deref(), Some("<urn:ex#t1>"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_skipped_constraints() -> Result<()> {
+        let shapes = parse(
+            r#"
+            @prefix sh: <http://www.w3.org/ns/shacl#> .
+            @prefix ex: <urn:ex#> .
+            ex:PumpShape a sh:NodeShape ;
+                sh:targetClass ex:Pump ;
+                sh:or ( [ sh:class ex:Pump ] [ sh:class ex:Fan ] ) ;
+                sh:property [
+                    sh:path ex:label ;
+                    sh:minCount 1 ;
+                    sh:minLength 2 ;
+                ] , [
+                    sh:path ( ex:feeds ex:label ) ;
+                    sh:minCount 1 ;
+                ] .
+            "#,
+        );
+        let data = parse(
+            r#"
+            @prefix ex: <urn:ex#> .
+            ex:p1 a ex:Pump ; ex:label "p1" .
+            "#,
+        );
+        // the supported constraints hold, but the graph was not fully checked
+        let report = validate(&data, &shapes)?;
+        assert!(report.results.is_empty());
+        assert!(!report.conforms);
+        let skipped: Vec<&str> = report
+            .skipped
+            .iter()
+            .map(|s| s.constraint.as_str())
+            .collect();
+        assert_eq!(skipped.len(), 3);
+        assert!(skipped.contains(&"sh:or"));
+        assert!(skipped.contains(&"sh:minLength"));
+        assert!(skipped.contains(&"sh:path"));
+        assert!(report
+            .skipped
+            .iter()
+            .any(|s| s.constraint == "sh:or" && s.source_shape == "<urn:ex#PumpShape>"));
+        Ok(())
+    }
+}
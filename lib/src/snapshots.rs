@@ -0,0 +1,306 @@
+// metadata snapshots of the environment, recorded after every update one per line in
+// .ontoenv/snapshots.jsonl: the ontologies (names, locations, graph IDs and content hashes) and
+// the fingerprint of the environment. Snapshots taken with `OntoEnv::snapshot` also carry a tag
+// naming them, and keep a copy of their graphs once per content hash in
+// .ontoenv/snapshots/graphs, so `OntoEnv::load_snapshot` can rebuild a read-only view of the
+// environment as it was then, even once its files or remote ontologies have changed, and
+// `OntoEnv::rollback` can restore it. Updates keep copies only with `Config::snapshot_graphs`;
+// otherwise their snapshots can only be restored while their graphs are unchanged. The copies
+// are kept within `ResourceLimits::max_cache_size`, removing the oldest which no tagged snapshot
+// needs first
+
+use crate::ontology::Ontology;
+use crate::util;
+use anyhow::Result;
+use chrono::prelude::*;
+use oxigraph::model::Graph;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// The ontologies of the environment after one update
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct EnvironmentSnapshot {
+    // the fingerprint of the environment (see `OntoEnv::fingerprint`)
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    pub ontologies: Vec<Ontology>,
+    // the name given to a snapshot taken explicitly, e.g. "before-qudt-upgrade"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tag: Option<String>,
+}
+
+/// Appends the snapshot to the snapshot file, unless the most recently recorded snapshot has the
+/// same ontologies
+pub fn record_snapshot(path: &Path, snapshot: &EnvironmentSnapshot) -> Result<bool> {
+    if let Some(last) = last_snapshot(path)? {
+        if last.id == snapshot.id {
+            return Ok(false);
+        }
+    }
+    append_snapshot(path, snapshot)?;
+    Ok(true)
+}
+
+/// Appends the snapshot to the snapshot file, even if the last snapshot has the same ontologies
+pub fn append_snapshot(path: &Path, snapshot: &EnvironmentSnapshot) -> Result<()> {
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", serde_json::to_string(snapshot)?)?;
+    Ok(())
+}
+
+/// Reads all snapshots from the snapshot file, oldest first. A missing file has no snapshots
+pub fn load_snapshots(path: &Path) -> Result<Vec<EnvironmentSnapshot>> {
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+    let reader = BufReader::new(fs::File::open(path)?);
+    let mut snapshots = vec![];
+    for line in reader.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        snapshots.push(serde_json::from_str(&line)?);
+    }
+    Ok(snapshots)
+}
+
+/// Reads the most recently recorded snapshot from the end of the snapshot file, without reading
+/// the earlier ones. A missing file has no snapshots
+pub fn last_snapshot(path: &Path) -> Result<Option<EnvironmentSnapshot>> {
+    let mut file = match fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let len = file.metadata()?.len();
+    // read ever larger chunks from the end until they hold the whole last line
+    let mut chunk: u64 = 4096;
+    loop {
+        let start = len.saturating_sub(chunk);
+        file.seek(SeekFrom::Start(start))?;
+        let mut buf = vec![];
+        (&mut file).take(len - start).read_to_end(&mut buf)?;
+        let end = buf
+            .iter()
+            .rposition(|b| !b.is_ascii_whitespace())
+            .map_or(0, |i| i + 1);
+        let line = match buf[..end].iter().rposition(|b| *b == b'\n') {
+            Some(newline) => &buf[newline + 1..end],
+            None if start == 0 => &buf[..end],
+            None => {
+                chunk *= 2;
+                continue;
+            }
+        };
+        if line.is_empty() {
+            return Ok(None);
+        }
+        return Ok(Some(serde_json::from_slice(line)?));
+    }
+}
+
+/// Finds the snapshot selected by `selector`: the tag of a snapshot, a prefix of a snapshot ID,
+/// or an RFC 3339 timestamp or a date (YYYY-MM-DD) selecting the latest snapshot recorded at or
+/// before it (the end of the day, for dates)
+pub fn find_snapshot<'a>(
+    snapshots: &'a [EnvironmentSnapshot],
+    selector: &str,
+) -> Result<&'a EnvironmentSnapshot> {
+    if let Some(tagged) = snapshots
+        .iter()
+        .find(|s| s.tag.as_deref() == Some(selector))
+    {
+        return Ok(tagged);
+    }
+    let time = DateTime::parse_from_rfc3339(selector)
+        .map(|t| t.with_timezone(&Utc))
+        .ok()
+        .or_else(|| {
+            NaiveDate::parse_from_str(selector, "%Y-%m-%d")
+                .ok()
+                .and_then(|day| day.and_hms_opt(23, 59, 59))
+                .map(|t| t.and_utc())
+        });
+    if let Some(time) = time {
+        return snapshots
+            .iter()
+            .rev()
+            .find(|s| s.timestamp <= time)
+            .ok_or(anyhow::anyhow!("No snapshot recorded before {}", selector));
+    }
+    let matching: Vec<&EnvironmentSnapshot> = snapshots
+        .iter()
+        .filter(|s| s.id.starts_with(selector))
+        .collect();
+    match matching.as_slice() {
+        [] => Err(anyhow::anyhow!("No snapshot with ID {}", selector)),
+        // the same ontologies may have been recorded again after a change was reverted
+        [.., last] if matching.iter().all(|s| s.id == last.id) => Ok(*last),
+        _ => Err(anyhow::anyhow!("Snapshot ID {} is ambiguous", selector)),
+    }
+}
+
+/// The graphs of recorded snapshots, one Turtle file per content hash
+#[derive(Debug, Clone)]
+pub struct GraphStore {
+    dir: PathBuf,
+}
+
+impl GraphStore {
+    /// The graphs of the environment whose store and manifest are in the directory (see
+    /// `OntoEnv::ontoenv_dir`)
+    pub fn in_dir(ontoenv_dir: &Path) -> Self {
+        GraphStore {
+            dir: ontoenv_dir.join("snapshots").join("graphs"),
+        }
+    }
+
+    fn path(&self, hash: &str) -> PathBuf {
+        self.dir.join(format!("{}.ttl", hash))
+    }
+
+    pub fn contains(&self, hash: &str) -> bool {
+        self.path(hash).exists()
+    }
+
+    pub fn put(&self, hash: &str, graph: &Graph) -> Result<()> {
+        fs::create_dir_all(&self.dir)?;
+        util::write_graph_to_file(graph, &self.path(hash))
+    }
+
+    /// Returns the graph with the content hash, if it was stored
+    pub fn get(&self, hash: &str) -> Result<Option<Graph>> {
+        let path = self.path(hash);
+        if !path.exists() {
+            return Ok(None);
+        }
+        util::read_file(&path).map(Some)
+    }
+
+    /// Returns the stored graphs, oldest first, with their content hash and size in bytes
+    fn entries(&self) -> Result<Vec<(SystemTime, u64, String, PathBuf)>> {
+        let dir = match fs::read_dir(&self.dir) {
+            Ok(dir) => dir,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => return Err(e.into()),
+        };
+        let mut entries = vec![];
+        for entry in dir {
+            let entry = entry?;
+            let path = entry.path();
+            if !path.extension().is_some_and(|ext| ext == "ttl") {
+                continue;
+            }
+            let Some(hash) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            let metadata = entry.metadata()?;
+            entries.push((metadata.modified()?, metadata.len(), hash.to_string(), path));
+        }
+        entries.sort();
+        Ok(entries)
+    }
+
+    /// Returns the number of bytes the stored graphs take
+    pub fn size(&self) -> Result<u64> {
+        Ok(self.entries()?.iter().map(|(_, size, _, _)| size).sum())
+    }
+
+    /// Removes the oldest graphs while the stored graphs take more than `max_size` bytes,
+    /// keeping those whose content hash is in `keep`. Returns the number of removed graphs
+    pub fn evict(&self, max_size: u64, keep: &HashSet<String>) -> Result<usize> {
+        let entries = self.entries()?;
+        let mut total: u64 = entries.iter().map(|(_, size, _, _)| size).sum();
+        let mut removed = 0;
+        for (_, size, hash, path) in entries {
+            if total <= max_size {
+                break;
+            }
+            if keep.contains(&hash) {
+                continue;
+            }
+            fs::remove_file(&path)?;
+            total -= size;
+            removed += 1;
+        }
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(id: &str, timestamp: &str) -> EnvironmentSnapshot {
+        EnvironmentSnapshot {
+            id: id.to_string(),
+            timestamp: DateTime::parse_from_rfc3339(timestamp)
+                .unwrap()
+                .with_timezone(&Utc),
+            ontologies: vec![],
+            tag: None,
+        }
+    }
+
+    #[test]
+    fn test_find_snapshot() {
+        let snapshots = vec![
+            snapshot("abc123", "2024-05-01T10:00:00Z"),
+            snapshot("abd456", "2024-05-03T10:00:00Z"),
+            snapshot("ff0000", "2024-05-03T12:00:00Z"),
+        ];
+        assert_eq!(find_snapshot(&snapshots, "abc").unwrap().id, "abc123");
+        assert!(find_snapshot(&snapshots, "ab").is_err());
+        assert!(find_snapshot(&snapshots, "00").is_err());
+        assert_eq!(
+            find_snapshot(&snapshots, "2024-05-02").unwrap().id,
+            "abc123"
+        );
+        assert_eq!(
+            find_snapshot(&snapshots, "2024-05-03").unwrap().id,
+            "ff0000"
+        );
+        assert_eq!(
+            find_snapshot(&snapshots, "2024-05-03T11:00:00Z")
+                .unwrap()
+                .id,
+            "abd456"
+        );
+        assert!(find_snapshot(&snapshots, "2024-04-30").is_err());
+
+        let mut tagged = snapshot("abc999", "2024-05-04T10:00:00Z");
+        tagged.tag = Some("ff".to_string());
+        let snapshots = [snapshots, vec![tagged]].concat();
+        assert_eq!(find_snapshot(&snapshots, "ff").unwrap().id, "abc999");
+    }
+
+    #[test]
+    fn test_last_snapshot() -> Result<()> {
+        let dir = tempdir::TempDir::new("ontoenv-snapshots")?;
+        let path = dir.path().join("snapshots.jsonl");
+        assert!(last_snapshot(&path)?.is_none());
+
+        let mut first = snapshot("abc123", "2024-05-01T10:00:00Z");
+        // longer than the first chunk read from the end of the file
+        first.tag = Some("x".repeat(10000));
+        append_snapshot(&path, &first)?;
+        assert_eq!(last_snapshot(&path)?.unwrap().id, "abc123");
+
+        assert!(record_snapshot(
+            &path,
+            &snapshot("abd456", "2024-05-03T10:00:00Z")
+        )?);
+        assert!(!record_snapshot(
+            &path,
+            &snapshot("abd456", "2024-05-03T12:00:00Z")
+        )?);
+        assert_eq!(last_snapshot(&path)?.unwrap().id, "abd456");
+        assert_eq!(load_snapshots(&path)?.len(), 2);
+        Ok(())
+    }
+}
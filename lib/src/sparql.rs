@@ -0,0 +1,66 @@
+// uploading graphs to a remote triple store (e.g. Jena Fuseki) with the SPARQL 1.1 Graph Store
+// HTTP Protocol
+
+use anyhow::Result;
+use oxigraph::io::{RdfFormat, RdfSerializer};
+use oxigraph::model::{Graph, NamedNodeRef};
+use reqwest::blocking::Client;
+use reqwest::header::CONTENT_TYPE;
+use tracing::info;
+
+/// Credentials for a SPARQL endpoint
+#[derive(Debug, Clone, Default)]
+pub enum SparqlAuth {
+    #[default]
+    None,
+    Basic {
+        username: String,
+        password: Option<String>,
+    },
+    Bearer(String),
+}
+
+/// Replaces the named graph `graph_name` at the Graph Store Protocol endpoint (e.g.
+/// http://localhost:3030/ds/data) with the given graph
+pub fn put_graph(
+    client: &Client,
+    endpoint: &str,
+    auth: &SparqlAuth,
+    graph_name: NamedNodeRef,
+    graph: &Graph,
+) -> Result<()> {
+    let mut body = Vec::new();
+    let mut serializer = RdfSerializer::from_format(RdfFormat::NTriples).for_writer(&mut body);
+    for triple in graph.iter() {
+        serializer.serialize_triple(triple)?;
+    }
+    serializer.finish()?;
+
+    info!(
+        "Uploading {} triples to graph {} at {}",
+        graph.len(),
+        graph_name,
+        endpoint
+    );
+    let mut request = client
+        .put(endpoint)
+        .query(&[("graph", graph_name.as_str())])
+        .header(CONTENT_TYPE, RdfFormat::NTriples.media_type())
+        .body(body);
+    request = match auth {
+        SparqlAuth::None => request,
+        SparqlAuth::Basic { username, password } => request.basic_auth(username, password.as_ref()),
+        SparqlAuth::Bearer(token) => request.bearer_auth(token),
+    };
+    let resp = request.send()?;
+    if !resp.status().is_success() {
+        return Err(anyhow::anyhow!(
+            "Failed to upload graph {} to {}: {} {}",
+            graph_name,
+            endpoint,
+            resp.status(),
+            resp.text().unwrap_or_default()
+        ));
+    }
+    Ok(())
+}
@@ -0,0 +1,239 @@
+// reverse index from the terms declared in the environment (classes and properties) to the
+// ontologies declaring them, and how often a term is used across the graphs of the environment
+
+use crate::consts::{DECLARATION_TYPES, IMPORTS, ONTOLOGY, TYPE};
+use crate::ontology::GraphIdentifier;
+use crate::util::graph_iris;
+use crate::OntoEnv;
+use anyhow::Result;
+use oxigraph::model::{
+    Graph, GraphName, NamedNode, NamedNodeRef, Quad, Subject, SubjectRef, TermRef,
+};
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+/// Maps every class and property declared in the environment to the ontologies declaring it
+#[derive(Debug, Clone, Default)]
+pub struct TermIndex {
+    // term -> declaring ontologies, sorted and without duplicates
+    terms: HashMap<NamedNode, Vec<GraphIdentifier>>,
+}
+
+impl TermIndex {
+    pub fn build(env: &OntoEnv) -> Result<Self> {
+        let mut terms: HashMap<NamedNode, Vec<GraphIdentifier>> = HashMap::new();
+        for (term, id) in declarations(env, None)? {
+            terms.entry(term).or_default().push(id);
+        }
+        for ids in terms.values_mut() {
+            ids.sort();
+            ids.dedup();
+        }
+        Ok(TermIndex { terms })
+    }
+
+    /// Returns the ontologies declaring the term, sorted by name and location
+    pub fn declaring_ontologies(&self, term: NamedNodeRef) -> &[GraphIdentifier] {
+        self.terms
+            .get(&term.into_owned())
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Returns the number of declared terms
+    pub fn len(&self) -> usize {
+        self.terms.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty()
+    }
+}
+
+/// Returns the (term, ontology) pairs of the rdf:type declarations of classes and properties in
+/// the environment, optionally only those of one term
+pub(crate) fn declarations(
+    env: &OntoEnv,
+    term: Option<NamedNodeRef>,
+) -> Result<Vec<(NamedNode, GraphIdentifier)>> {
+    let graphs = env.graphs_by_graphname();
+    let store = env.store();
+    let mut declarations = vec![];
+    for declaration_type in DECLARATION_TYPES {
+        for quad in store.quads_for_pattern(
+            term.map(Into::into),
+            Some(TYPE),
+            Some(declaration_type.into()),
+            None,
+        ) {
+            let quad = quad?;
+            if let (Subject::NamedNode(term), Some(id)) =
+                (quad.subject, graphs.get(&quad.graph_name))
+            {
+                declarations.push((term, (*id).clone()));
+            }
+        }
+    }
+    Ok(declarations)
+}
+
+/// How often an IRI is used in the graphs of the environment (see `OntoEnv::term_usage`)
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TermUsage {
+    pub term: String,
+    // triples using the term, over all graphs. A triple using it in several positions counts
+    // once here, and once for each position below
+    pub triples: usize,
+    pub as_subject: usize,
+    pub as_predicate: usize,
+    pub as_object: usize,
+    // sorted by ontology name and location
+    pub graphs: Vec<GraphUsage>,
+}
+
+/// The number of triples of an ontology's graph using a term
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub struct GraphUsage {
+    pub ontology: GraphIdentifier,
+    pub triples: usize,
+}
+
+pub(crate) fn usage(env: &OntoEnv, term: NamedNodeRef) -> Result<TermUsage> {
+    let graphs = env.graphs_by_graphname();
+    let store = env.store();
+    let mut usage = TermUsage {
+        term: term.as_str().to_string(),
+        ..TermUsage::default()
+    };
+    let mut quads: HashSet<Quad> = HashSet::new();
+    let patterns = [
+        store.quads_for_pattern(Some(term.into()), None, None, None),
+        store.quads_for_pattern(None, Some(term), None, None),
+        store.quads_for_pattern(None, None, Some(term.into()), None),
+    ];
+    for (position, pattern) in patterns.into_iter().enumerate() {
+        for quad in pattern {
+            let quad = quad?;
+            // graphs which belong to no ontology of the environment are not counted
+            if !graphs.contains_key(&quad.graph_name) {
+                continue;
+            }
+            match position {
+                0 => usage.as_subject += 1,
+                1 => usage.as_predicate += 1,
+                _ => usage.as_object += 1,
+            }
+            quads.insert(quad);
+        }
+    }
+    let mut by_graph: HashMap<&GraphName, usize> = HashMap::new();
+    for quad in &quads {
+        *by_graph.entry(&quad.graph_name).or_default() += 1;
+    }
+    usage.triples = quads.len();
+    usage.graphs = by_graph
+        .into_iter()
+        .map(|(graph_name, triples)| GraphUsage {
+            ontology: (*graphs[graph_name]).clone(),
+            triples,
+        })
+        .collect();
+    usage.graphs.sort();
+    Ok(usage)
+}
+
+/// The ontologies of the environment a graph (e.g. a model) uses terms of, compared with the
+/// ontologies it imports
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ImportUsage {
+    // ontologies defining terms the graph uses which its imports closure does not include
+    pub missing: Vec<NamedNode>,
+    // owl:imports of the graph whose closures include no ontology the graph uses terms of
+    pub unused: Vec<NamedNode>,
+}
+
+pub(crate) fn import_usage(env: &OntoEnv, graph: &Graph) -> Result<ImportUsage> {
+    let named = |subject: SubjectRef<'_>| match subject {
+        SubjectRef::NamedNode(node) => Some(node),
+        _ => None,
+    };
+    // the ontologies the graph declares and the terms it declares itself are not looked up
+    let own: HashSet<NamedNodeRef> = graph
+        .subjects_for_predicate_object(TYPE, ONTOLOGY)
+        .filter_map(named)
+        .collect();
+    let local: HashSet<NamedNodeRef> = DECLARATION_TYPES
+        .iter()
+        .flat_map(|declaration_type| graph.subjects_for_predicate_object(TYPE, *declaration_type))
+        .filter_map(named)
+        .collect();
+    let declared: BTreeSet<NamedNodeRef> = own
+        .iter()
+        .flat_map(|ontology| graph.objects_for_subject_predicate(*ontology, IMPORTS))
+        .filter_map(|import| match import {
+            TermRef::NamedNode(import) => Some(import),
+            _ => None,
+        })
+        .collect();
+
+    let index = env.term_index()?;
+    let mut used: BTreeSet<NamedNode> = BTreeSet::new();
+    for iri in graph_iris(graph) {
+        if local.contains(&iri) {
+            continue;
+        }
+        if let Some(id) = env.preferred_declaration(iri, index.declaring_ontologies(iri)) {
+            if !own.contains(&id.name()) {
+                used.insert(id.name().into_owned());
+            }
+        }
+    }
+
+    // the names of the ontologies in the closure of each ontology
+    let closure_names = |name: NamedNodeRef| -> Result<HashSet<NamedNode>> {
+        Ok(match env.resolve_import(name) {
+            Some(ontology) => env
+                .get_dependency_closure(ontology.id())?
+                .iter()
+                .map(|id| id.name().into_owned())
+                .collect(),
+            None => HashSet::new(),
+        })
+    };
+
+    let mut covered: HashSet<NamedNode> = HashSet::new();
+    let mut unused = vec![];
+    for import in &declared {
+        if env.resolve_import(*import).is_none() {
+            // imports which are not in the environment cannot be checked
+            continue;
+        }
+        let closure = closure_names(*import)?;
+        if !closure.iter().any(|name| used.contains(name)) {
+            unused.push(import.into_owned());
+        }
+        covered.extend(closure);
+    }
+
+    let candidates: Vec<NamedNode> = used
+        .into_iter()
+        .filter(|name| !covered.contains(name))
+        .collect();
+    let closures = candidates
+        .iter()
+        .map(|candidate| closure_names(candidate.as_ref()))
+        .collect::<Result<Vec<_>>>()?;
+    // an ontology which another suggestion imports is not suggested, unless they import each
+    // other
+    let missing = candidates
+        .iter()
+        .enumerate()
+        .filter(|(i, candidate)| {
+            !closures.iter().enumerate().any(|(j, closure)| {
+                j != *i && closure.contains(*candidate) && !closures[*i].contains(&candidates[j])
+            })
+        })
+        .map(|(_, candidate)| candidate.clone())
+        .collect();
+    Ok(ImportUsage { missing, unused })
+}
@@ -0,0 +1,245 @@
+// helpers for the tests of applications embedding ontoenv (enabled with the "testing" feature):
+// throwaway environments built from inline Turtle, and a mock HTTP server serving ontologies, so
+// integration tests neither hit the network nor depend on fixture files
+
+use crate::config::Config;
+use crate::OntoEnv;
+use anyhow::Result;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use tempdir::TempDir;
+use tracing::warn;
+
+/// Prefixes declared at the top of every Turtle document written by `TestEnvironment`
+pub const TURTLE_PREFIXES: &str = "@prefix owl: <http://www.w3.org/2002/07/owl#> .
+@prefix rdf: <http://www.w3.org/1999/02/22-rdf-syntax-ns#> .
+@prefix rdfs: <http://www.w3.org/2000/01/rdf-schema#> .
+@prefix xsd: <http://www.w3.org/2001/XMLSchema#> .
+@prefix skos: <http://www.w3.org/2004/02/skos/core#> .
+@prefix dcterms: <http://purl.org/dc/terms/> .
+";
+
+/// An environment in a temporary directory, which is removed when it is dropped. Derefs to the
+/// `OntoEnv`, so it can be used like one
+pub struct TestEnvironment {
+    env: OntoEnv,
+    // declared after the environment so it outlives the store kept in it
+    dir: TempDir,
+}
+
+impl TestEnvironment {
+    /// Creates an environment from (file name, Turtle) pairs, e.g.
+    /// `[("a.ttl", "<urn:a> a owl:Ontology .")]`, and loads it. The environment is offline and
+    /// not strict; the owl, rdf, rdfs, xsd, skos and dcterms prefixes are declared in every file
+    pub fn from_turtle(files: &[(&str, &str)]) -> Result<Self> {
+        Self::with_config(files, |_| {})
+    }
+
+    /// Like `from_turtle`, but lets `configure` change the configuration first, e.g. turn off
+    /// offline mode to fetch ontologies from a `MockServer`
+    pub fn with_config(
+        files: &[(&str, &str)],
+        configure: impl FnOnce(&mut Config),
+    ) -> Result<Self> {
+        let dir = TempDir::new("ontoenv-test")?;
+        for (file, turtle) in files {
+            write_turtle(dir.path(), file, turtle)?;
+        }
+        let mut config = Config::new(
+            dir.path().to_path_buf(),
+            Some(vec![dir.path().to_path_buf()]),
+            &["*.ttl"],
+            &[""],
+            false,
+            false,
+            true,
+            "default".to_string(),
+            false,
+        )?;
+        configure(&mut config);
+        let mut env = OntoEnv::new(config, true)?;
+        env.update()?;
+        Ok(TestEnvironment { env, dir })
+    }
+
+    /// Returns the directory of the environment, which is also its only search directory
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// Writes a Turtle document to the directory of the environment (with the prefixes of
+    /// `from_turtle`), returning its path. Call `update` to load it
+    pub fn write(&self, file: &str, turtle: &str) -> Result<PathBuf> {
+        write_turtle(self.dir.path(), file, turtle)
+    }
+}
+
+impl Deref for TestEnvironment {
+    type Target = OntoEnv;
+
+    fn deref(&self) -> &OntoEnv {
+        &self.env
+    }
+}
+
+impl DerefMut for TestEnvironment {
+    fn deref_mut(&mut self) -> &mut OntoEnv {
+        &mut self.env
+    }
+}
+
+fn write_turtle(dir: &Path, file: &str, turtle: &str) -> Result<PathBuf> {
+    let path = dir.join(file);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&path, format!("{}\n{}\n", TURTLE_PREFIXES, turtle))?;
+    Ok(path)
+}
+
+// path -> (content type, body)
+type Documents = Arc<Mutex<HashMap<String, (String, String)>>>;
+
+/// An HTTP server on a free local port serving the documents added to it (404 for any other
+/// path) and recording the paths requested. It stops when dropped
+pub struct MockServer {
+    addr: SocketAddr,
+    documents: Documents,
+    requests: Arc<Mutex<Vec<String>>>,
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl MockServer {
+    /// Starts the server on 127.0.0.1
+    pub fn start() -> Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let documents: Documents = Arc::new(Mutex::new(HashMap::new()));
+        let requests = Arc::new(Mutex::new(vec![]));
+        let stop = Arc::new(AtomicBool::new(false));
+        let handle = {
+            let (documents, requests, stop) = (documents.clone(), requests.clone(), stop.clone());
+            std::thread::spawn(move || {
+                for stream in listener.incoming() {
+                    if stop.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    let result = stream
+                        .map_err(anyhow::Error::from)
+                        .and_then(|stream| respond(stream, &documents, &requests));
+                    if let Err(e) = result {
+                        warn!("Mock server failed to handle request: {}", e);
+                    }
+                }
+            })
+        };
+        Ok(MockServer {
+            addr,
+            documents,
+            requests,
+            stop,
+            handle: Some(handle),
+        })
+    }
+
+    /// Returns the URL of the path on the server, e.g. `url("/brick.ttl")`
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}/{}", self.addr, path.trim_start_matches('/'))
+    }
+
+    /// Serves the Turtle document (with the prefixes of `TestEnvironment`) at the path,
+    /// returning its URL
+    pub fn serve_turtle(&self, path: &str, turtle: &str) -> String {
+        let body = format!("{}\n{}\n", TURTLE_PREFIXES, turtle);
+        self.serve(path, "text/turtle", &body)
+    }
+
+    /// Serves the body with the content type at the path, returning its URL
+    pub fn serve(&self, path: &str, content_type: &str, body: &str) -> String {
+        let path = format!("/{}", path.trim_start_matches('/'));
+        self.documents
+            .lock()
+            .unwrap()
+            .insert(path.clone(), (content_type.to_string(), body.to_string()));
+        self.url(&path)
+    }
+
+    /// Returns the paths requested so far, in order
+    pub fn requests(&self) -> Vec<String> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        // wake the server up from waiting for a connection
+        let _ = TcpStream::connect(self.addr);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn respond(
+    mut stream: TcpStream,
+    documents: &Documents,
+    requests: &Mutex<Vec<String>>,
+) -> Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 || header.trim().is_empty() {
+            break;
+        }
+    }
+    // e.g. GET /brick.ttl HTTP/1.1; the query string is not part of the path
+    let target = request_line.split_whitespace().nth(1).unwrap_or("/");
+    let path = target.split('?').next().unwrap_or(target).to_string();
+    requests.lock().unwrap().push(path.clone());
+    let response = match documents.lock().unwrap().get(&path) {
+        Some((content_type, body)) => format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            content_type,
+            body.len(),
+            body
+        ),
+        None => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+    };
+    stream.write_all(response.as_bytes())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use oxigraph::model::NamedNodeRef;
+
+    #[test]
+    fn test_environment_with_mock_server() -> Result<()> {
+        let server = MockServer::start()?;
+        let lib = server.url("lib.ttl");
+        server.serve_turtle("lib.ttl", &format!("<{}> a owl:Ontology .", lib));
+        let app = format!("<urn:app> a owl:Ontology ; owl:imports <{}> .", lib);
+        let mut env = TestEnvironment::with_config(&[("app.ttl", &app)], |config| {
+            config.offline = false;
+        })?;
+        assert_eq!(env.num_graphs(), 2);
+        assert_eq!(server.requests(), vec!["/lib.ttl".to_string()]);
+
+        env.write("other.ttl", "<urn:other> a owl:Ontology .")?;
+        env.update()?;
+        let other = NamedNodeRef::new("urn:other")?;
+        assert!(env.get_ontology_by_name(other).is_some());
+        Ok(())
+    }
+}
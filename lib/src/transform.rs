@@ -1,5 +1,6 @@
 use crate::consts::{DECLARE, IMPORTS, ONTOLOGY, PREFIXES, TYPE};
-use oxigraph::model::{Dataset, Graph, Quad, QuadRef, SubjectRef, Triple, TripleRef, NamedNodeRef, TermRef};
+use oxigraph::model::{Dataset, Graph, Quad, QuadRef, SubjectRef, Triple, TripleRef, NamedNodeRef, TermRef, NamedNode, Subject, Term};
+use std::collections::HashSet;
 
 /// Rewrites all sh:prefixes in the graph to point to the provided root
 pub fn rewrite_sh_prefixes(graph: &mut Dataset, root: SubjectRef) {
@@ -147,3 +148,55 @@ pub fn remove_ontology_declarations_graph(graph: &mut Graph, root: SubjectRef) {
         graph.remove(triple.as_ref());
     }
 }
+
+/// Applies the changes `rewrite_sh_prefixes`, `remove_owl_imports` and
+/// `remove_ontology_declarations` make to a union one quad at a time, for unions which are
+/// streamed instead of collected into a Dataset
+#[derive(Debug, Clone)]
+pub struct UnionRewriter {
+    root: NamedNode,
+    // owl:imports of these ontologies are removed, if remove_owl_imports is set
+    imported: HashSet<NamedNode>,
+    rewrite_sh_prefixes: bool,
+    remove_owl_imports: bool,
+}
+
+impl UnionRewriter {
+    pub fn new(
+        root: NamedNode,
+        imported: impl IntoIterator<Item = NamedNode>,
+        rewrite_sh_prefixes: bool,
+        remove_owl_imports: bool,
+    ) -> Self {
+        UnionRewriter {
+            root,
+            imported: imported.into_iter().collect(),
+            rewrite_sh_prefixes,
+            remove_owl_imports,
+        }
+    }
+
+    /// Returns the quad as it appears in the union, or None if it is left out
+    pub fn rewrite(&self, quad: Quad) -> Option<Quad> {
+        let is_root = quad.subject.as_ref() == SubjectRef::NamedNode(self.root.as_ref());
+        if quad.predicate == TYPE && quad.object.as_ref() == TermRef::from(ONTOLOGY) {
+            return is_root.then_some(quad);
+        }
+        if self.remove_owl_imports && quad.predicate == IMPORTS {
+            if let Term::NamedNode(object) = &quad.object {
+                if self.imported.contains(object) {
+                    return None;
+                }
+            }
+        }
+        if self.rewrite_sh_prefixes && quad.predicate == PREFIXES {
+            let root = Term::NamedNode(self.root.clone());
+            return Some(Quad::new(quad.subject, PREFIXES, root, quad.graph_name));
+        }
+        if self.rewrite_sh_prefixes && quad.predicate == DECLARE {
+            let root = Subject::NamedNode(self.root.clone());
+            return Some(Quad::new(root, DECLARE, quad.object, quad.graph_name));
+        }
+        Some(quad)
+    }
+}
@@ -1,125 +1,672 @@
+use crate::io::http::{self, CacheValidators, HttpOptions};
+use crate::ontology::path_to_file_iri;
 use anyhow::Result;
 
-use std::io::{Read, Seek};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::io::{Cursor, Read, Seek};
 use std::path::Path;
+use std::time::Instant;
 
-use reqwest::header::CONTENT_TYPE;
-
-use oxigraph::io::{RdfFormat, RdfParser, RdfSerializer};
+use oxigraph::io::{RdfFormat, RdfParseError, RdfParser, RdfSerializer};
 use oxigraph::model::graph::Graph as OxigraphGraph;
 use oxigraph::model::Dataset;
-use oxigraph::model::{GraphNameRef, Quad, QuadRef, Triple, TripleRef};
+use oxigraph::model::{
+    BlankNode, BlankNodeRef, GraphNameRef, NamedNodeRef, Quad, QuadRef, Subject, SubjectRef, Term,
+    TermRef, Triple, TripleRef,
+};
 
 use std::io::BufReader;
 
-use log::{debug, info};
+use tracing::{debug, info};
+
+/// How the `write_*` functions lay out the documents they write. The default writes Turtle
+/// (or TriG) with full IRIs, grouping the triples of each subject
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SerializerOptions {
+    // prefix -> namespace declarations used to abbreviate IRIs
+    pub prefixes: BTreeMap<String, String>,
+    // IRIs under the base IRI are written relative to it, through the empty prefix (oxigraph
+    // does not write relative IRIs). Takes the place of any empty prefix in `prefixes`
+    pub base_iri: Option<String>,
+    // write one triple per line with full IRIs (N-Triples, or N-Quads instead of TriG), which
+    // is still valid Turtle and gives the smallest diffs. Prefixes are ignored
+    pub compact: bool,
+}
+
+impl SerializerOptions {
+    /// Options declaring the given prefixes
+    pub fn with_prefixes(prefixes: BTreeMap<String, String>) -> Self {
+        SerializerOptions {
+            prefixes,
+            ..Default::default()
+        }
+    }
+
+    // a serializer for Turtle or TriG following the options
+    fn serializer(&self, format: RdfFormat) -> Result<RdfSerializer> {
+        if self.compact {
+            return Ok(RdfSerializer::from_format(match format {
+                RdfFormat::TriG => RdfFormat::NQuads,
+                _ => RdfFormat::NTriples,
+            }));
+        }
+        let mut serializer = RdfSerializer::from_format(format);
+        for (prefix, namespace) in &self.prefixes {
+            if prefix.is_empty() && self.base_iri.is_some() {
+                continue;
+            }
+            serializer = serializer.with_prefix(prefix, namespace)?;
+        }
+        if let Some(base_iri) = &self.base_iri {
+            serializer = serializer.with_prefix("", base_iri)?;
+        }
+        Ok(serializer)
+    }
+}
 
 pub fn write_dataset_to_file(dataset: &Dataset, file: &str) -> Result<()> {
+    write_dataset_to_file_with(dataset, file, &SerializerOptions::default())
+}
+
+/// Writes the union of the graphs of the dataset to a Turtle file, laid out following the
+/// options
+pub fn write_dataset_to_file_with(
+    dataset: &Dataset,
+    file: &str,
+    options: &SerializerOptions,
+) -> Result<()> {
     info!(
         "Writing dataset to file: {} with length {}",
         file,
         dataset.len()
     );
     let mut file = std::fs::File::create(file)?;
-    let mut serializer = RdfSerializer::from_format(RdfFormat::Turtle).for_writer(&mut file);
+    let mut serializer = options.serializer(RdfFormat::Turtle)?.for_writer(&mut file);
+    for triple in sorted_triples(&dataset_to_graph(dataset)) {
+        serializer.serialize_triple(&triple)?;
+    }
+    serializer.finish()?;
+    Ok(())
+}
+
+pub fn write_dataset_to_trig(dataset: &Dataset, file: &str) -> Result<()> {
+    write_dataset_to_trig_with(dataset, file, &SerializerOptions::default())
+}
+
+/// Writes the dataset to a TriG file, keeping the graph names of the quads. Like
+/// `write_dataset_to_file`, the graphs and their triples are written in a stable order. Blank
+/// node labels are prefixed with the index of their graph, so graphs never share blank nodes
+pub fn write_dataset_to_trig_with(
+    dataset: &Dataset,
+    file: &str,
+    options: &SerializerOptions,
+) -> Result<()> {
+    info!(
+        "Writing dataset to TriG file: {} with length {}",
+        file,
+        dataset.len()
+    );
+    let mut graphs: BTreeMap<String, (GraphNameRef, OxigraphGraph)> = BTreeMap::new();
     for quad in dataset.iter() {
-        serializer.serialize_triple(TripleRef {
-            subject: quad.subject,
-            predicate: quad.predicate,
-            object: quad.object,
-        })?;
+        graphs
+            .entry(quad.graph_name.to_string())
+            .or_insert_with(|| (quad.graph_name, OxigraphGraph::new()))
+            .1
+            .insert(TripleRef::new(quad.subject, quad.predicate, quad.object));
+    }
+    let mut file = std::fs::File::create(file)?;
+    let mut serializer = options.serializer(RdfFormat::TriG)?.for_writer(&mut file);
+    for (index, (graph_name, graph)) in graphs.values().enumerate() {
+        let relabel = |b: &BlankNode| BlankNode::new_unchecked(format!("g{}{}", index, b.as_str()));
+        for triple in sorted_triples(graph) {
+            let subject: Subject = match &triple.subject {
+                Subject::BlankNode(b) => relabel(b).into(),
+                s => s.clone(),
+            };
+            let object: Term = match &triple.object {
+                Term::BlankNode(b) => relabel(b).into(),
+                o => o.clone(),
+            };
+            serializer.serialize_quad(QuadRef::new(
+                &subject,
+                &triple.predicate,
+                &object,
+                *graph_name,
+            ))?;
+        }
     }
     serializer.finish()?;
     Ok(())
 }
 
-pub fn read_file(file: &Path) -> Result<OxigraphGraph> {
-    debug!("Reading file: {}", file.to_str().unwrap());
-    let filename = file;
-    let file = std::fs::File::open(file)?;
-    let content: BufReader<_> = BufReader::new(file);
-    let content_type = filename.extension().and_then(|ext| ext.to_str());
-    let content_type = content_type.and_then(|ext| match ext {
-        "ttl" => Some(RdfFormat::Turtle),
-        "xml" => Some(RdfFormat::RdfXml),
-        "n3" => Some(RdfFormat::Turtle),
-        "nt" => Some(RdfFormat::NTriples),
-        _ => None,
+pub fn write_graph_to_file(graph: &OxigraphGraph, file: &Path) -> Result<()> {
+    write_graph_to_file_with(graph, file, &SerializerOptions::default())
+}
+
+/// Writes the graph to a Turtle file, laid out following the options
+pub fn write_graph_to_file_with(
+    graph: &OxigraphGraph,
+    file: &Path,
+    options: &SerializerOptions,
+) -> Result<()> {
+    info!(
+        "Writing graph to file: {:?} with length {}",
+        file,
+        graph.len()
+    );
+    let mut file = std::fs::File::create(file)?;
+    let mut serializer = options.serializer(RdfFormat::Turtle)?.for_writer(&mut file);
+    for triple in sorted_triples(graph) {
+        serializer.serialize_triple(&triple)?;
+    }
+    serializer.finish()?;
+    Ok(())
+}
+
+// a term as a string in which all blank nodes are equal, since their labels are arbitrary
+fn term_key(term: TermRef) -> String {
+    match term {
+        TermRef::BlankNode(_) => "_:".to_string(),
+        term => term.to_string(),
+    }
+}
+
+// describes each blank node by the triples it appears in, so blank nodes can be ordered
+// without using their labels
+fn blank_node_signatures(graph: &OxigraphGraph) -> HashMap<BlankNodeRef, String> {
+    let mut parts: HashMap<BlankNodeRef, Vec<String>> = HashMap::new();
+    for t in graph.iter() {
+        if let SubjectRef::BlankNode(b) = t.subject {
+            parts
+                .entry(b)
+                .or_default()
+                .push(format!("> {} {}", t.predicate, term_key(t.object)));
+        }
+        if let TermRef::BlankNode(b) = t.object {
+            parts.entry(b).or_default().push(format!(
+                "< {} {}",
+                term_key(t.subject.into()),
+                t.predicate
+            ));
+        }
+    }
+    parts
+        .into_iter()
+        .map(|(b, mut parts)| {
+            parts.sort();
+            (b, parts.join(" "))
+        })
+        .collect()
+}
+
+fn relabel(node: BlankNodeRef, labels: &mut HashMap<BlankNode, BlankNode>) -> BlankNode {
+    let next = labels.len();
+    labels
+        .entry(node.into_owned())
+        .or_insert_with(|| BlankNode::new_unchecked(format!("b{}", next)))
+        .clone()
+}
+
+/// Returns the triples of the graph in a stable order, so that writing the same graph always
+/// produces the same output. Blank nodes are relabeled b0, b1, ... in the order they first
+/// appear when the triples are sorted by the triples each blank node appears in rather than
+/// by its label
+pub fn sorted_triples(graph: &OxigraphGraph) -> Vec<Triple> {
+    let signatures = blank_node_signatures(graph);
+    let key = |term: TermRef| match term {
+        TermRef::BlankNode(b) => format!("_:{}", signatures.get(&b).map_or("", |s| s.as_str())),
+        term => term.to_string(),
+    };
+    let mut triples: Vec<TripleRef> = graph.iter().collect();
+    triples.sort_by_cached_key(|t| {
+        (
+            key(t.subject.into()),
+            t.predicate.as_str().to_string(),
+            key(t.object),
+        )
     });
-    let parser = RdfParser::from_format(content_type.unwrap_or(RdfFormat::Turtle));
+    let mut labels: HashMap<BlankNode, BlankNode> = HashMap::new();
+    let mut triples: Vec<Triple> = triples
+        .into_iter()
+        .map(|t| {
+            let subject: Subject = match t.subject {
+                SubjectRef::BlankNode(b) => relabel(b, &mut labels).into(),
+                s => s.into_owned(),
+            };
+            let object: Term = match t.object {
+                TermRef::BlankNode(b) => relabel(b, &mut labels).into(),
+                o => o.into_owned(),
+            };
+            Triple::new(subject, t.predicate, object)
+        })
+        .collect();
+    triples.sort_by_cached_key(|t| t.to_string());
+    triples
+}
+
+/// Flattens a dataset into a single graph, dropping the graph names of the quads
+pub fn dataset_to_graph(dataset: &Dataset) -> OxigraphGraph {
     let mut graph = OxigraphGraph::new();
-    let parser = parser.for_reader(content);
-    for quad in parser {
+    for quad in dataset.iter() {
+        graph.insert(TripleRef::new(quad.subject, quad.predicate, quad.object));
+    }
+    graph
+}
+
+/// Returns the IRIs a graph uses: named subjects, predicates, named objects and the datatypes
+/// of literals
+pub fn graph_iris(graph: &OxigraphGraph) -> HashSet<NamedNodeRef> {
+    let mut iris = HashSet::new();
+    for triple in graph.iter() {
+        if let SubjectRef::NamedNode(subject) = triple.subject {
+            iris.insert(subject);
+        }
+        iris.insert(triple.predicate);
+        match triple.object {
+            TermRef::NamedNode(object) => {
+                iris.insert(object);
+            }
+            TermRef::Literal(literal) => {
+                iris.insert(literal.datatype());
+            }
+            _ => {}
+        }
+    }
+    iris
+}
+
+/// A 128-bit fingerprint of a triple, built from two independently keyed 64-bit hashes. Used to
+/// detect duplicate triples without keeping copies of them; a collision between two different
+/// triples is astronomically unlikely
+pub fn triple_fingerprint(triple: TripleRef) -> u128 {
+    let mut first = DefaultHasher::new();
+    0u8.hash(&mut first);
+    triple.hash(&mut first);
+    let mut second = DefaultHasher::new();
+    1u8.hash(&mut second);
+    triple.hash(&mut second);
+    ((first.finish() as u128) << 64) | second.finish() as u128
+}
+
+/// Finds where an IRI is written in the text of an RDF document, returning the 1-based line and
+/// the byte offset of the match. The IRI may be written in full (`<iri>` in Turtle and N-Triples,
+/// `"iri"` in RDF/XML attributes) or as a prefixed name using one of the namespaces. A line
+/// starting with the IRI (i.e. where it is the subject of statements) is preferred to any other
+/// mention of it; prefix declarations are skipped. The parsers do not report positions, so this
+/// is a textual search and can miss IRIs written relative to a base IRI
+pub fn find_iri_in_text(
+    text: &str,
+    iri: &str,
+    namespaces: &BTreeMap<String, String>,
+) -> Option<(usize, usize)> {
+    let mut spellings = vec![format!("<{}>", iri), format!("\"{}\"", iri)];
+    for (prefix, namespace) in namespaces {
+        if let Some(local) = iri.strip_prefix(namespace.as_str()) {
+            if !local.is_empty() {
+                spellings.push(format!("{}:{}", prefix, local));
+            }
+        }
+    }
+    // a prefixed name must not be part of a longer name
+    let is_name_char = |c: char| c.is_alphanumeric() || matches!(c, '_' | '-' | ':');
+    let find_in_line = |line: &str| {
+        spellings.iter().find_map(|spelling| {
+            line.match_indices(spelling.as_str())
+                .find(|(at, _)| {
+                    spelling.starts_with(['<', '"'])
+                        || (!line[..*at].ends_with(is_name_char)
+                            && !line[at + spelling.len()..].starts_with(is_name_char))
+                })
+                .map(|(at, _)| at)
+        })
+    };
+
+    let mut mention = None;
+    let mut offset = 0;
+    for (number, line) in text.split_inclusive('\n').enumerate() {
+        let trimmed = line.trim_start();
+        let declares_prefix = ["@prefix", "PREFIX", "@base", "BASE", "xmlns"]
+            .iter()
+            .any(|keyword| trimmed.starts_with(keyword));
+        if !declares_prefix {
+            if let Some(at) = find_in_line(line) {
+                let found = (number + 1, offset + at);
+                if line[..at].trim().is_empty() || line[..at].trim_end().ends_with("rdf:about=") {
+                    return Some(found);
+                }
+                mention.get_or_insert(found);
+            }
+        }
+        offset += line.len();
+    }
+    mention
+}
+
+/// Controls the base IRI relative IRIs are resolved against in documents which do not declare
+/// one themselves (e.g. with @base in Turtle), the formats of files by extension, and when to
+/// give up parsing
+#[derive(Debug, Clone)]
+pub struct BaseIriOptions {
+    // resolve against the URL of the document: the file:// URL of a local file or the URL
+    // a remote document was fetched from
+    pub use_document_url: bool,
+    // base IRI used when the document URL is not used
+    pub default_base_iri: Option<String>,
+    // return an error instead of resolving relative IRIs against a base the document
+    // does not declare
+    pub reject_relative_iris: bool,
+    // file extension -> format name (see rdf_format_from_name), on top of the built-in
+    // extensions; the empty extension is used for files without one
+    pub formats: BTreeMap<String, String>,
+    // parsing fails at the first chunk of the document read after this instant
+    pub deadline: Option<Instant>,
+}
+
+impl Default for BaseIriOptions {
+    fn default() -> Self {
+        BaseIriOptions {
+            use_document_url: true,
+            default_base_iri: None,
+            reject_relative_iris: false,
+            formats: BTreeMap::new(),
+            deadline: None,
+        }
+    }
+}
+
+impl BaseIriOptions {
+    /// Returns the base IRI to use for a document at the given URL which does not declare one
+    fn fallback(&self, document_url: Option<&str>) -> Option<String> {
+        document_url
+            .filter(|_| self.use_document_url)
+            .map(|url| url.to_string())
+            .or_else(|| self.default_base_iri.clone())
+    }
+}
+
+// fails reads once the deadline has passed, so a parser reading from it stops within one chunk
+struct DeadlineReader<R> {
+    inner: R,
+    deadline: Option<Instant>,
+}
+
+impl<R: Read> Read for DeadlineReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        if self
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                "parsing took longer than the timeout",
+            ));
+        }
+        self.inner.read(buf)
+    }
+}
+
+/// A parsed RDF document
+#[derive(Debug, Clone)]
+pub struct Document {
+    pub graph: OxigraphGraph,
+    // prefix -> namespace declarations found in the document
+    pub prefixes: BTreeMap<String, String>,
+    // the base IRI relative IRIs were resolved against, if any
+    pub base_iri: Option<String>,
+    // for a fetched document, the URL it was served from after following redirects
+    pub resolved_url: Option<String>,
+    // for a fetched document, the ETag and Last-Modified headers of the response
+    pub validators: Option<CacheValidators>,
+}
+
+fn parse_document<R: Read>(
+    content: R,
+    format: RdfFormat,
+    base_iri: Option<&str>,
+) -> Result<Document> {
+    let mut parser = RdfParser::from_format(format);
+    if let Some(base_iri) = base_iri {
+        parser = parser
+            .with_base_iri(base_iri)
+            .map_err(|e| anyhow::anyhow!("Invalid base IRI {}: {}", base_iri, e))?;
+    }
+    let mut graph = OxigraphGraph::new();
+    let mut parser = parser.for_reader(content);
+    for quad in &mut parser {
         let quad = quad?;
         let triple = Triple::new(quad.subject, quad.predicate, quad.object);
         graph.insert(&triple);
     }
+    let prefixes = parser
+        .prefixes()
+        .map(|(prefix, namespace)| (prefix.to_string(), namespace.to_string()))
+        .collect();
+    let base_iri = parser.base_iri().map(|b| b.to_string());
+    Ok(Document {
+        graph,
+        prefixes,
+        base_iri,
+        resolved_url: None,
+        validators: None,
+    })
+}
+
+/// Returns the line (counted from 1) at which parsing a document failed, if the error is a
+/// syntax error whose parser reports positions (the RDF/XML parser does not)
+pub fn parse_error_line(error: &anyhow::Error) -> Option<usize> {
+    error.chain().find_map(|cause| {
+        let Some(RdfParseError::Syntax(syntax)) = cause.downcast_ref::<RdfParseError>() else {
+            return None;
+        };
+        syntax.location().map(|range| range.start.line as usize + 1)
+    })
+}
+
+/// Parses the document, resolving relative IRIs according to the options. Documents are first
+/// parsed without a base IRI, so any base they declare is honored; only if that fails are they
+/// parsed again with the fallback base IRI
+fn parse_document_with_base<R: Read + Seek>(
+    content: &mut R,
+    format: RdfFormat,
+    document_url: Option<&str>,
+    options: &BaseIriOptions,
+) -> Result<Document> {
+    let deadline = options.deadline;
+    content.rewind()?;
+    let reader = DeadlineReader {
+        inner: &mut *content,
+        deadline,
+    };
+    let error = match parse_document(reader, format, None) {
+        Ok(document) => return Ok(document),
+        Err(e) => e,
+    };
+    let Some(base_iri) = options.fallback(document_url) else {
+        return Err(error);
+    };
+    content.rewind()?;
+    let reader = DeadlineReader {
+        inner: &mut *content,
+        deadline,
+    };
+    let document = parse_document(reader, format, Some(&base_iri))?;
+    if options.reject_relative_iris {
+        return Err(anyhow::anyhow!(
+            "Document contains relative IRIs but does not declare a base IRI"
+        ));
+    }
+    debug!("Resolved relative IRIs against {}", base_iri);
+    Ok(document)
+}
+
+/// Returns the RDF format with the given name: turtle, ntriples, nquads, trig, rdfxml or n3.
+/// "sniff" returns None, i.e. the format is guessed from the content of each document
+pub fn rdf_format_from_name(name: &str) -> Result<Option<RdfFormat>> {
+    match name.to_lowercase().as_str() {
+        "turtle" | "ttl" => Ok(Some(RdfFormat::Turtle)),
+        "ntriples" | "nt" => Ok(Some(RdfFormat::NTriples)),
+        "nquads" | "nq" => Ok(Some(RdfFormat::NQuads)),
+        "trig" => Ok(Some(RdfFormat::TriG)),
+        "rdfxml" | "xml" => Ok(Some(RdfFormat::RdfXml)),
+        "n3" => Ok(Some(RdfFormat::N3)),
+        "sniff" => Ok(None),
+        "jsonld" | "json-ld" => Err(anyhow::anyhow!("JSON-LD documents are not supported")),
+        _ => Err(anyhow::anyhow!("Unknown RDF format: {}", name)),
+    }
+}
+
+/// Returns the format of a file from its extension. The configured formats (extension ->
+/// format name) take precedence over the built-in extensions; None means the format should be
+/// sniffed from the content
+pub fn format_for_path(
+    path: &Path,
+    formats: &BTreeMap<String, String>,
+) -> Result<Option<RdfFormat>> {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+    if let Some(name) = formats.get(&extension) {
+        return rdf_format_from_name(name);
+    }
+    Ok(match extension.as_str() {
+        "ttl" => Some(RdfFormat::Turtle),
+        "xml" => Some(RdfFormat::RdfXml),
+        "n3" => Some(RdfFormat::Turtle),
+        "nt" => Some(RdfFormat::NTriples),
+        "nq" => Some(RdfFormat::NQuads),
+        "trig" => Some(RdfFormat::TriG),
+        _ => None,
+    })
+}
 
-    Ok(graph)
+/// Guesses the format of a document from its first bytes: RDF/XML if it starts like an XML
+/// document, otherwise Turtle (which also reads N-Triples)
+fn sniff_format<R: Read + Seek>(content: &mut R) -> Result<RdfFormat> {
+    content.rewind()?;
+    let mut head = Vec::with_capacity(512);
+    content.by_ref().take(512).read_to_end(&mut head)?;
+    content.rewind()?;
+    let head = String::from_utf8_lossy(&head);
+    let head = head.trim_start_matches('\u{feff}').trim_start();
+    if head.starts_with("<?xml") || head.starts_with("<rdf:RDF") || head.starts_with("<!DOCTYPE") {
+        return Ok(RdfFormat::RdfXml);
+    }
+    Ok(RdfFormat::Turtle)
 }
 
-fn read_format<T: Read + Seek>(mut original_content: BufReader<T>, format: Option<RdfFormat>) -> Result<OxigraphGraph> {
+pub fn read_file(file: &Path) -> Result<OxigraphGraph> {
+    read_file_with_prefixes(file).map(|(graph, _)| graph)
+}
+
+/// Reads the file into a graph, also returning the prefix declarations found in the file
+pub fn read_file_with_prefixes(file: &Path) -> Result<(OxigraphGraph, BTreeMap<String, String>)> {
+    read_file_document(file, &BaseIriOptions::default()).map(|doc| (doc.graph, doc.prefixes))
+}
+
+/// Reads the file, resolving relative IRIs according to the options
+pub fn read_file_document(file: &Path, options: &BaseIriOptions) -> Result<Document> {
+    debug!("Reading file: {}", file.display());
+    let content = BufReader::new(std::fs::File::open(file)?);
+    parse_file_document(file, content, options)
+}
+
+/// Parses the content as the document of the file at the path, e.g. the version of the file
+/// staged in git: the format is that of the file, and relative IRIs resolve against its URL
+pub fn parse_file_content(
+    file: &Path,
+    content: &[u8],
+    options: &BaseIriOptions,
+) -> Result<Document> {
+    parse_file_document(file, Cursor::new(content), options)
+}
+
+fn parse_file_document<R: Read + Seek>(
+    filename: &Path,
+    mut content: R,
+    options: &BaseIriOptions,
+) -> Result<Document> {
+    let format = match format_for_path(filename, &options.formats)? {
+        Some(format) => format,
+        None => sniff_format(&mut content)?,
+    };
+    let document_url = std::path::absolute(filename)
+        .ok()
+        .and_then(|path| path_to_file_iri(&path).ok());
+    parse_document_with_base(
+        &mut content,
+        format,
+        document_url.as_ref().map(|url| url.as_str()),
+        options,
+    )
+}
+
+fn read_format<T: Read + Seek>(
+    mut original_content: BufReader<T>,
+    format: Option<RdfFormat>,
+    document_url: &str,
+    options: &BaseIriOptions,
+) -> Result<Document> {
     let format = format.unwrap_or(RdfFormat::Turtle);
+    let mut error = None;
     for format in [
         format,
         RdfFormat::Turtle,
         RdfFormat::RdfXml,
         RdfFormat::NTriples,
     ] {
-        let content = original_content.get_mut();
-        content.rewind()?;
-        let parser = RdfParser::from_format(format);
-        let mut graph = OxigraphGraph::new();
-        let parser = parser.for_reader(content);
-
-        // Process each quad from the parser
-        for quad in parser {
-            match quad {
-                Ok(q) => {
-                    let triple = Triple::new(q.subject, q.predicate, q.object);
-                    graph.insert(&triple);
-                }
-                Err(_) => {
-                    // Break the outer loop if an error occurs
-                    break;
-                }
-            }
-        }
-
-        // If we successfully processed quads and did not encounter an error
-        if !graph.is_empty() {
-            return Ok(graph);
+        match parse_document_with_base(&mut original_content, format, Some(document_url), options) {
+            // If we successfully processed quads and did not encounter an error
+            Ok(document) if !document.graph.is_empty() => return Ok(document),
+            Ok(_) => {}
+            Err(e) => error = Some(e),
         }
     }
-    Err(anyhow::anyhow!("Failed to parse graph"))
+    Err(error.unwrap_or_else(|| anyhow::anyhow!("Failed to parse graph")))
 }
 
 pub fn read_url(file: &str) -> Result<OxigraphGraph> {
+    read_url_with_prefixes(file).map(|(graph, _)| graph)
+}
+
+/// Fetches the URL into a graph, also returning the prefix declarations found in the document
+pub fn read_url_with_prefixes(file: &str) -> Result<(OxigraphGraph, BTreeMap<String, String>)> {
+    read_url_document(file, &BaseIriOptions::default()).map(|doc| (doc.graph, doc.prefixes))
+}
+
+/// Fetches the URL, resolving relative IRIs according to the options. When the server
+/// redirects, relative IRIs are resolved against the URL the document was served from
+pub fn read_url_document(file: &str, options: &BaseIriOptions) -> Result<Document> {
+    read_url_document_if_modified(file, options, &CacheValidators::default())
+}
+
+/// Fetches the URL like `read_url_document`, unless the server answers that it did not change
+/// since the response the validators came from (see `http::fetch_if_modified`)
+pub fn read_url_document_if_modified(
+    file: &str,
+    options: &BaseIriOptions,
+    validators: &CacheValidators,
+) -> Result<Document> {
     debug!("Reading url: {}", file);
+    let response = http::fetch_if_modified(file, &HttpOptions::default(), validators)?;
 
-    let client = reqwest::blocking::Client::new();
-    let resp = client
-        .get(file)
-        .header(CONTENT_TYPE, "application/x-turtle")
-        .send()?;
-    if !resp.status().is_success() {
-        return Err(anyhow::anyhow!("Failed to fetch ontology from {}", file));
-    }
-    let content_type = resp.headers().get("Content-Type");
-    let content_type = content_type.and_then(|ct| ct.to_str().ok());
-    let content_type = content_type.and_then(|ext| match ext {
-        "application/x-turtle" => Some(RdfFormat::Turtle),
-        "text/turtle" => Some(RdfFormat::Turtle),
-        "application/rdf+xml" => Some(RdfFormat::RdfXml),
-        "text/rdf+n3" => Some(RdfFormat::NTriples),
-        _ => {
-            debug!("Unknown content type: {}", ext);
-            None
-        }
-    });
+    // without a known content type, fall back to the extension of the URL's path
+    let content_type = match response.format() {
+        Some(format) => Some(format),
+        None => match url::Url::parse(&response.url) {
+            Ok(url) => format_for_path(Path::new(url.path()), &options.formats)?,
+            Err(_) => None,
+        },
+    };
 
-    let content: BufReader<_> = BufReader::new(std::io::Cursor::new(resp.bytes()?));
-    read_format(content, content_type)
+    let content = BufReader::new(std::io::Cursor::new(response.body));
+    let mut document = read_format(content, content_type, &response.url, options)?;
+    document.resolved_url = Some(response.url);
+    document.validators = Some(response.validators);
+    Ok(document)
 }
 
 // return a "impl IntoIterator<Item = impl Into<Quad>>" for a graph. Iter through
@@ -165,6 +712,31 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_find_iri_in_text() {
+        let text = "@prefix ex: <http://example.org/> .\n\
+                    ex:b a ex:a .\n\
+                    ex:a a <http://www.w3.org/2002/07/owl#Class> .\n\
+                    ex:ab a ex:a .\n";
+        let namespaces = BTreeMap::from([("ex".to_string(), "http://example.org/".to_string())]);
+        // the line where the IRI is the subject wins over the earlier mention
+        let (line, offset) = find_iri_in_text(text, "http://example.org/a", &namespaces).unwrap();
+        assert_eq!(line, 3);
+        assert_eq!(&text[offset..offset + 4], "ex:a");
+        // prefixed names which only start with the name do not match
+        assert_eq!(
+            find_iri_in_text(text, "http://example.org/ab", &namespaces).map(|(line, _)| line),
+            Some(4)
+        );
+        assert!(find_iri_in_text(text, "http://example.org/c", &namespaces).is_none());
+
+        let xml = "<owl:Class rdf:about=\"http://example.org/a\"/>\n";
+        assert_eq!(
+            find_iri_in_text(xml, "http://example.org/a", &BTreeMap::new()),
+            Some((1, 21))
+        );
+    }
+
     #[test]
     fn test_read_url() {
         let graph =
@@ -206,4 +778,50 @@ mod tests {
 
         write_dataset_to_file(&graph, "model_out.ttl").unwrap();
     }
+
+    #[test]
+    fn test_write_graph_with_serializer_options() {
+        let path = Path::new("fixtures/fileendings/model.ttl");
+        let (graph, prefixes) = read_file_with_prefixes(path).unwrap();
+        let dir = tempfile::tempdir().unwrap();
+
+        let pretty = dir.path().join("pretty.ttl");
+        let mut options = SerializerOptions::with_prefixes(prefixes);
+        options.base_iri = Some("urn:ex#".to_string());
+        write_graph_to_file_with(&graph, &pretty, &options).unwrap();
+        let content = std::fs::read_to_string(&pretty).unwrap();
+        assert!(content.contains("@prefix brick: <https://brickschema.org/schema/Brick#>"));
+        assert!(content.contains("@prefix : <urn:ex#>"));
+        assert_eq!(read_file(&pretty).unwrap().len(), graph.len());
+
+        // compact output has one triple per line and is still Turtle
+        let compact = dir.path().join("compact.ttl");
+        options.compact = true;
+        write_graph_to_file_with(&graph, &compact, &options).unwrap();
+        let content = std::fs::read_to_string(&compact).unwrap();
+        assert!(!content.contains("@prefix"));
+        assert_eq!(content.lines().count(), graph.len());
+        assert_eq!(read_file(&compact).unwrap().len(), graph.len());
+    }
+
+    #[test]
+    fn test_sorted_triples_are_stable() {
+        // blank nodes get new labels every time a file is parsed
+        let path = Path::new("fixtures/brick-stuff/Brick-1.3.ttl");
+        let first = sorted_triples(&read_file(path).unwrap());
+        let second = sorted_triples(&read_file(path).unwrap());
+        assert_eq!(first.len(), read_file(path).unwrap().len());
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn test_parse_deadline() {
+        let path = Path::new("ontology.ttl");
+        let content = b"<urn:ex#a> a <urn:ex#B> .";
+        let mut options = BaseIriOptions::default();
+        assert!(parse_file_content(path, content, &options).is_ok());
+        // a deadline which has passed stops parsing at the first read
+        options.deadline = Some(Instant::now());
+        assert!(parse_file_content(path, content, &options).is_err());
+    }
 }
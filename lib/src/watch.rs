@@ -0,0 +1,65 @@
+// watching the search directories of an environment for changes. The watcher polls: every
+// interval it takes a snapshot of the size and modification time of the included files, and
+// updates the environment once the snapshot changed and then stayed the same for an interval
+// (editors often write a file in several steps). Polling needs no platform notifier and also
+// works on network filesystems and in containers with mounted directories
+
+use crate::ontology::OntologyLocation;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+/// How `OntoEnv::watch` polls the search directories
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchOptions {
+    // time between two snapshots of the search directories
+    pub interval: Duration,
+}
+
+impl Default for WatchOptions {
+    fn default() -> Self {
+        WatchOptions {
+            interval: Duration::from_secs(1),
+        }
+    }
+}
+
+/// The size and modification time of every watched file
+pub type FileSnapshot = BTreeMap<PathBuf, (u64, Option<SystemTime>)>;
+
+/// Takes a snapshot of the files among the locations. Files which disappear while the snapshot
+/// is taken are left out, like files which were never there
+pub fn snapshot(locations: &[OntologyLocation]) -> FileSnapshot {
+    locations
+        .iter()
+        .filter_map(|location| match location {
+            OntologyLocation::File(path) => {
+                let metadata = fs::metadata(path).ok()?;
+                Some((path.clone(), (metadata.len(), metadata.modified().ok())))
+            }
+            OntologyLocation::Url(_) => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_sees_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.ttl");
+        fs::write(&path, "").unwrap();
+        let locations = vec![
+            OntologyLocation::File(path.clone()),
+            OntologyLocation::File(dir.path().join("missing.ttl")),
+        ];
+        let before = snapshot(&locations);
+        assert_eq!(before.len(), 1);
+        assert_eq!(before, snapshot(&locations));
+        fs::write(&path, "<urn:a> <urn:b> <urn:c> .").unwrap();
+        assert_ne!(before, snapshot(&locations));
+    }
+}
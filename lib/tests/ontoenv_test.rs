@@ -1,9 +1,39 @@
 use anyhow::Result;
-use ontoenv::config::{Config, HowCreated};
-use ontoenv::ontology::OntologyLocation;
-use ontoenv::OntoEnv;
-use oxigraph::model::NamedNodeRef;
+use ontoenv::cancel::{CancellationToken, Cancelled};
+use ontoenv::changes::{ChangeKind, ChangeManifest};
+use ontoenv::config::{
+    Config, DuplicateStrategy, HowCreated, ImportConstraint, OptionalImports, Profile,
+    ResourceLimits, StrictRule,
+};
+use ontoenv::depgraph::petgraph::algo::toposort;
+use ontoenv::depgraph::{DependencyNode, ImportStatus};
+use ontoenv::diff::DiffFormat;
+use ontoenv::docgen::DocFormat;
+use ontoenv::events::{EnvironmentListener, Phase, Progress};
+use ontoenv::inspector::{OntologyInspector, ShaclDeclarations};
+use ontoenv::legacy;
+use ontoenv::lockfile::Lockfile;
+use ontoenv::metrics::{Counter, InMemoryMetrics, Timer};
+use ontoenv::ontology::{GraphIdentifier, NameSource, OntologyLocation};
+use ontoenv::policy::{CompatibilityPolicy, VersionRequirement};
+use ontoenv::reasoner::{RdfsReasoner, Reasoner};
+use ontoenv::release::ReleaseOptions;
+use ontoenv::report;
+use ontoenv::resolution::{self, ResolutionReport, UnresolvedImport};
+use ontoenv::sparql::SparqlAuth;
+use ontoenv::util::{
+    read_file, write_dataset_to_file_with, write_dataset_to_trig, SerializerOptions,
+};
+use ontoenv::watch::WatchOptions;
+use ontoenv::{ImportMatch, MergePrecedence, OntoEnv, RefreshStrategy, RemoveMode, UpdateReport};
+use oxigraph::io::{RdfFormat, RdfParser};
+use oxigraph::model::vocab::rdf;
+use oxigraph::model::{Graph, GraphNameRef, NamedNode, NamedNodeRef, QuadRef, TermRef, TripleRef};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use tempdir::TempDir;
 
 // the tests directory contains a number of test files that are used to test the OntoEnv.
@@ -77,10 +107,25 @@ fn default_config(dir: &TempDir) -> Config {
         true,
         true,
         "default".to_string(),
+        false,
     )
     .unwrap()
 }
 
+// writes a Turtle file into the directory, declaring the owl, rdfs and dcterms prefixes for the
+// body
+fn write_ontology(dir: &TempDir, name: &str, body: &str) -> std::io::Result<()> {
+    std::fs::write(
+        dir.path().join(name),
+        format!(
+            "@prefix owl: <http://www.w3.org/2002/07/owl#> .\n\
+             @prefix rdfs: <http://www.w3.org/2000/01/rdf-schema#> .\n\
+             @prefix dcterms: <http://purl.org/dc/terms/> .\n{}\n",
+            body
+        ),
+    )
+}
+
 fn default_config_ttl_only(dir: &TempDir) -> Config {
     Config::new(
         dir.path().into(),
@@ -91,6 +136,7 @@ fn default_config_ttl_only(dir: &TempDir) -> Config {
         true,
         true,
         "default".to_string(),
+        false,
     )
     .unwrap()
 }
@@ -105,6 +151,7 @@ fn default_config_with_subdir(dir: &TempDir, path: &str) -> Config {
         false,
         true,
         "default".to_string(),
+        false,
     )
     .unwrap()
 }
@@ -168,6 +215,7 @@ fn test_ontoenv_num_triples() -> Result<()> {
         false,
         true,
         "default".to_string(),
+        false,
     )?;
     let mut env = OntoEnv::new(cfg1, false)?;
     env.update()?;
@@ -468,9 +516,9 @@ fn test_ontoenv_dag_structure() -> Result<()> {
     let ont_graph = env.get_ontology_by_name(ont2).unwrap();
     let closure = env.get_dependency_closure(ont_graph.id()).unwrap();
     assert_eq!(closure.len(), 2);
-    let union = env.get_union_graph(&closure, None, None)?;
+    let (union, _, _) = env.get_union_graph(&closure, None, None)?;
     assert_eq!(union.len(), 4);
-    let union = env.get_union_graph(&closure, None, Some(false))?;
+    let (union, _, _) = env.get_union_graph(&closure, None, Some(false))?;
     assert_eq!(union.len(), 5);
 
     // ont3 => {ont3, ont2, ont1}
@@ -478,9 +526,9 @@ fn test_ontoenv_dag_structure() -> Result<()> {
     let ont_graph = env.get_ontology_by_name(ont3).unwrap();
     let closure = env.get_dependency_closure(ont_graph.id()).unwrap();
     assert_eq!(closure.len(), 3);
-    let union = env.get_union_graph(&closure, None, None)?;
+    let (union, _, _) = env.get_union_graph(&closure, None, None)?;
     assert_eq!(union.len(), 5);
-    let union = env.get_union_graph(&closure, None, Some(false))?;
+    let (union, _, _) = env.get_union_graph(&closure, None, Some(false))?;
     assert_eq!(union.len(), 8);
 
     // ont5 => {ont5, ont4, ont3, ont2, ont1}
@@ -488,11 +536,3759 @@ fn test_ontoenv_dag_structure() -> Result<()> {
     let ont_graph = env.get_ontology_by_name(ont5).unwrap();
     let closure = env.get_dependency_closure(ont_graph.id()).unwrap();
     assert_eq!(closure.len(), 5);
-    let union = env.get_union_graph(&closure, None, None)?;
+    let (union, _, _) = env.get_union_graph(&closure, None, None)?;
     assert_eq!(union.len(), 7);
-    let union = env.get_union_graph(&closure, None, Some(false))?;
+    let (union, _, _) = env.get_union_graph(&closure, None, Some(false))?;
     // print the union
     assert_eq!(union.len(), 14);
 
     Ok(())
 }
+
+#[test]
+fn test_ontoenv_deduplicated_union() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/duplicates/model.ttl" => "model.ttl",
+                  "fixtures/duplicates/vocab.ttl" => "vocab.ttl"});
+    let mut env = OntoEnv::new(default_config(&dir), false)?;
+    env.update()?;
+
+    let model = NamedNodeRef::new("http://example.org/model")?;
+    let id = env.get_ontology_by_name(model).unwrap().id().clone();
+    let closure = env.get_dependency_closure(&id)?;
+    let (union, _, _) = env.get_union_graph(&closure, None, None)?;
+
+    // the two Sensor triples copied into the model are only kept in the model's graph
+    let (deduplicated, successful, failed, duplicates) =
+        env.get_deduplicated_union_graph(&closure, None, None)?;
+    assert_eq!(duplicates, 2);
+    assert_eq!(deduplicated.len(), union.len() - 2);
+    assert_eq!(successful.len(), 2);
+    assert!(failed.is_none());
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_location_casing() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/rdftest/ontology1.ttl" => "Vocab.ttl",
+                  "fixtures/rdftest/ontology2.ttl" => "vocab.ttl"});
+    // on case-insensitive file systems the second copy overwrites the first, and there is
+    // only one location
+    if std::fs::read_dir(dir.path())?.count() < 2 {
+        return Ok(());
+    }
+    let mut env = OntoEnv::new(default_config(&dir), false)?;
+    env.update()?;
+    assert_eq!(env.num_graphs(), 2);
+    let problems = env.problems()?;
+    let casing: Vec<_> = problems
+        .iter()
+        .filter(|p| p.message.starts_with("Locations differ only by case"))
+        .collect();
+    assert_eq!(casing.len(), 1);
+    assert_eq!(casing[0].locations.len(), 2);
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_union_dataset() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/rdftest/ontology1.ttl" => "ontology1.ttl",
+                  "fixtures/rdftest/ontology2.ttl" => "ontology2.ttl",
+                  "fixtures/rdftest/ontology3.ttl" => "ontology3.ttl"});
+    let mut env = OntoEnv::new(default_config(&dir), false)?;
+    env.update()?;
+
+    let ontology3 = NamedNodeRef::new("http://example.org/ontology3")?;
+    let id = env.get_ontology_by_name(ontology3).unwrap().id().clone();
+    let closure = env.get_dependency_closure(&id)?;
+    let (union, _, _) = env.get_union_graph(&closure, None, None)?;
+    let (dataset, successful, failed) = env.get_union_dataset(&closure, None, None)?;
+    assert_eq!(dataset.len(), union.len());
+    assert_eq!(successful.len(), 3);
+    assert!(failed.is_none());
+
+    // every graph of the closure keeps its own triples, named after its ontology
+    let class1 = NamedNodeRef::new("http://example.org#Class1")?;
+    let class3 = NamedNodeRef::new("http://example.org#Class3")?;
+    let ontology1 = NamedNodeRef::new("http://example.org/ontology1")?;
+    let ontology2 = NamedNodeRef::new("http://example.org/ontology2")?;
+    let owl_class = NamedNodeRef::new("http://www.w3.org/2002/07/owl#Class")?;
+    assert!(dataset.contains(QuadRef::new(class1, rdf::TYPE, owl_class, ontology1)));
+    assert!(dataset.contains(QuadRef::new(class3, rdf::TYPE, owl_class, ontology2)));
+    assert!(!dataset.contains(QuadRef::new(class3, rdf::TYPE, owl_class, ontology1)));
+
+    let path = dir.path().join("closure.trig");
+    write_dataset_to_trig(&dataset, path.to_str().unwrap())?;
+    let parser = RdfParser::from_format(RdfFormat::TriG);
+    let quads = parser
+        .for_reader(std::fs::File::open(&path)?)
+        .collect::<Result<Vec<_>, _>>()?;
+    assert_eq!(quads.len(), dataset.len());
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_import_constraints() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/rdftest/ontology1.ttl" => "ontology1.ttl",
+                  "fixtures/rdftest/ontology2.ttl" => "ontology2.ttl",
+                  "fixtures/rdftest/ontology3.ttl" => "ontology3.ttl",
+                  "fixtures/rdftest/ontology4.ttl" => "ontology4.ttl",
+                  "fixtures/rdftest/ontology5.ttl" => "ontology5.ttl",
+                  "fixtures/rdftest/ontology6.ttl" => "ontology6.ttl"});
+
+    let mut cfg = default_config_with_subdir(&dir, "");
+    // ontology5 transitively imports ontology1 through ontology2, 3 and 4
+    cfg.import_constraints = vec![
+        ImportConstraint::from_str("ontology5.ttl=http://example.org/ontology1")?,
+        ImportConstraint::from_str("ontology1.ttl=http://example.org/*")?,
+    ];
+    let mut env = OntoEnv::new(cfg, false)?;
+    env.update()?;
+    assert_eq!(env.num_graphs(), 6);
+
+    let violations = env.check_import_constraints()?;
+    assert_eq!(violations.len(), 1);
+    assert_eq!(
+        violations[0].ontology.name(),
+        NamedNodeRef::new("http://example.org/ontology5")?
+    );
+    assert_eq!(violations[0].forbidden.as_str(), "http://example.org/ontology1");
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_optional_imports() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/rdftest/ontology1.ttl" => "ontology1.ttl",
+                  "fixtures/rdftest/ontology2.ttl" => "ontology2.ttl",
+                  "fixtures/rdftest/ontology3.ttl" => "ontology3.ttl",
+                  "fixtures/rdftest/ontology4.ttl" => "ontology4.ttl"});
+    let mut cfg = default_config(&dir);
+    // ontology3 imports ontology2 only with the feature, and ontology4 on top of its imports
+    cfg.optional_imports = vec![OptionalImports::new(
+        "http://example.org/ontology3",
+        "extras",
+        &[
+            "http://example.org/ontology2",
+            "http://example.org/ontology4",
+        ],
+    )];
+    let mut env = OntoEnv::new(cfg, false)?;
+    env.update()?;
+    let ontology3 = env
+        .get_ontology_by_name(NamedNodeRef::new("http://example.org/ontology3")?)
+        .unwrap()
+        .id()
+        .clone();
+
+    let closure = env.get_dependency_closure(&ontology3)?;
+    let names: Vec<&str> = closure.iter().map(|id| id.name().as_str()).collect();
+    assert_eq!(
+        names,
+        vec![
+            "http://example.org/ontology3",
+            "http://example.org/ontology1"
+        ]
+    );
+
+    let closure = env.get_closure_with_features(&ontology3, &["extras"])?;
+    let names: Vec<&str> = closure.iter().map(|id| id.name().as_str()).collect();
+    assert_eq!(
+        names,
+        vec![
+            "http://example.org/ontology3",
+            "http://example.org/ontology1",
+            "http://example.org/ontology2",
+            "http://example.org/ontology4"
+        ]
+    );
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_closure_cut_points() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/rdftest/ontology1.ttl" => "ontology1.ttl",
+                  "fixtures/rdftest/ontology2.ttl" => "ontology2.ttl",
+                  "fixtures/rdftest/ontology3.ttl" => "ontology3.ttl",
+                  "fixtures/rdftest/ontology4.ttl" => "ontology4.ttl",
+                  "fixtures/rdftest/ontology6.ttl" => "ontology6.ttl"});
+    let cfg = default_config(&dir);
+    let mut env = OntoEnv::new(cfg, false)?;
+    env.update()?;
+    let ontology6 = env
+        .get_ontology_by_name(NamedNodeRef::new("http://example.org/ontology6")?)
+        .unwrap()
+        .id()
+        .clone();
+    let ontology3 = NamedNodeRef::new("http://example.org/ontology3")?;
+    let ontology4 = NamedNodeRef::new("http://example.org/ontology4")?;
+
+    // ontology6 imports ontology3 (which imports ontology1 and ontology2) and ontology4
+    // (which imports ontology1)
+    let closure = env.get_closure_excluding(&ontology6, &[], &[ontology3, ontology4])?;
+    let names: Vec<&str> = closure.iter().map(|id| id.name().as_str()).collect();
+    assert_eq!(
+        names,
+        vec![
+            "http://example.org/ontology6",
+            "http://example.org/ontology3",
+            "http://example.org/ontology4"
+        ]
+    );
+
+    // ontology1 is still reached through ontology4
+    let closure = env.get_closure_excluding(&ontology6, &[], &[ontology3])?;
+    let names: Vec<&str> = closure.iter().map(|id| id.name().as_str()).collect();
+    assert_eq!(
+        names,
+        vec![
+            "http://example.org/ontology6",
+            "http://example.org/ontology1",
+            "http://example.org/ontology3",
+            "http://example.org/ontology4"
+        ]
+    );
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_dependency_history() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/rdftest/ontology1.ttl" => "ontology1.ttl",
+                  "fixtures/rdftest/ontology2.ttl" => "ontology2.ttl",
+                  "fixtures/rdftest/ontology3.ttl" => "ontology3.ttl",
+                  "fixtures/rdftest/ontology4.ttl" => "ontology4.ttl",
+                  "fixtures/rdftest/ontology5.ttl" => "ontology5.ttl"});
+    let cfg = default_config(&dir);
+    let mut env = OntoEnv::new(cfg, false)?;
+    env.update()?;
+
+    let ont5 = NamedNodeRef::new("http://example.org/ontology5")?;
+    let history = env.dependency_history(ont5)?;
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].1.direct, 3);
+    assert_eq!(history[0].1.transitive, 4);
+
+    // an update which does not change the dependency graph is not recorded
+    env.update()?;
+    assert_eq!(env.dependency_history(ont5)?.len(), 1);
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_rpc() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/rdftest/ontology1.ttl" => "ontology1.ttl",
+                  "fixtures/rdftest/ontology2.ttl" => "ontology2.ttl",
+                  "fixtures/rdftest/ontology3.ttl" => "ontology3.ttl"});
+    let cfg = default_config(&dir);
+    let mut env = OntoEnv::new(cfg, false)?;
+    env.update()?;
+
+    let request = serde_json::json!({"jsonrpc": "2.0", "id": 1, "method": "closure",
+                                     "params": {"iri": "http://example.org/ontology3"}});
+    let response = ontoenv::rpc::handle_request(&env, &request).unwrap();
+    assert_eq!(response["id"], 1);
+    assert_eq!(response["result"].as_array().unwrap().len(), 3);
+    assert_eq!(response["result"][0], "http://example.org/ontology3");
+
+    let request = serde_json::json!({"jsonrpc": "2.0", "id": 2, "method": "prefix",
+                                     "params": {"prefix": "owl"}});
+    let response = ontoenv::rpc::handle_request(&env, &request).unwrap();
+    assert_eq!(response["result"], "http://www.w3.org/2002/07/owl#");
+
+    let request = serde_json::json!({"jsonrpc": "2.0", "id": 3, "method": "nope"});
+    let response = ontoenv::rpc::handle_request(&env, &request).unwrap();
+    assert_eq!(response["error"]["code"], -32601);
+
+    // notifications get no response
+    let request = serde_json::json!({"jsonrpc": "2.0", "method": "prefix",
+                                     "params": {"prefix": "owl"}});
+    assert!(ontoenv::rpc::handle_request(&env, &request).is_none());
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_bundle_roundtrip() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/rdftest/ontology1.ttl" => "ontology1.ttl",
+                  "fixtures/rdftest/ontology2.ttl" => "ontology2.ttl",
+                  "fixtures/rdftest/ontology3.ttl" => "ontology3.ttl"});
+    let cfg = default_config(&dir);
+    let mut env = OntoEnv::new(cfg, false)?;
+    env.update()?;
+    let bundle = env.to_bundle()?;
+    assert_eq!(bundle.len(), 2);
+
+    let target = TempDir::new("ontoenv")?;
+    let pulled = OntoEnv::from_bundle(target.path(), &bundle, false)?;
+    assert_eq!(pulled.num_graphs(), 3);
+    assert_eq!(pulled.num_triples()?, env.num_triples()?);
+    let ont3 = NamedNodeRef::new("http://example.org/ontology3")?;
+    let id = pulled.get_ontology_by_name(ont3).unwrap().id().clone();
+    assert_eq!(pulled.get_dependency_closure(&id)?.len(), 3);
+
+    // the target directory now holds an environment
+    assert!(OntoEnv::from_bundle(target.path(), &bundle, false).is_err());
+    teardown(dir);
+    teardown(target);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_catalog_xml() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/rdftest/ontology1.ttl" => "ontology1.ttl",
+                  "fixtures/rdftest/ontology2.ttl" => "ontology2.ttl",
+                  "fixtures/rdftest/ontology3.ttl" => "ontology3.ttl"});
+    let cfg = default_config(&dir);
+    let mut env = OntoEnv::new(cfg, false)?;
+    env.update()?;
+
+    let catalog_path = dir.path().join("catalog-v001.xml");
+    env.export_catalog_xml(&catalog_path)?;
+    let entries = ontoenv::catalog::read_catalog(&catalog_path)?;
+    assert_eq!(entries.len(), 3);
+    assert_eq!(entries[0].name, "http://example.org/ontology1");
+    assert!(entries[0].location.as_str().ends_with("ontology1.ttl"));
+
+    // a fresh environment without search directories picks up the ontologies from the catalog
+    let target = TempDir::new("ontoenv")?;
+    let mut cfg = default_config(&target);
+    cfg.search_directories = vec![];
+    let mut other = OntoEnv::new(cfg, false)?;
+    let ids = other.import_catalog_xml(&catalog_path)?;
+    assert_eq!(ids.len(), 3);
+    assert_eq!(other.num_graphs(), 3);
+    teardown(dir);
+    teardown(target);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_release() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/rdftest/ontology1.ttl" => "ontology1.ttl",
+                  "fixtures/rdftest/ontology2.ttl" => "ontology2.ttl",
+                  "fixtures/rdftest/ontology3.ttl" => "ontology3.ttl"});
+    let cfg = default_config(&dir);
+    let mut env = OntoEnv::new(cfg, false)?;
+    env.update()?;
+
+    let ont3 = NamedNodeRef::new("http://example.org/ontology3")?;
+    let id = env.get_ontology_by_name(ont3).unwrap().id().clone();
+    let dist = dir.path().join("dist");
+    let options = ReleaseOptions::new("1.0.0", dist.clone());
+    let artifacts = ontoenv::release::release(&env, &id, &options)?;
+    assert_eq!(artifacts.len(), 3);
+    assert!(dist.join("ontology3.ttl").exists());
+    assert!(dist.join("ontology3-slim.ttl").exists());
+    assert!(dist.join("ontology3-reasoned.ttl").exists());
+    assert_eq!(
+        artifacts[0].version_iri.as_str(),
+        "http://example.org/ontology3/releases/1.0.0/ontology3.ttl"
+    );
+    assert!(artifacts[1].triples <= artifacts[0].triples);
+    assert!(artifacts[2].triples >= artifacts[0].triples);
+    teardown(dir);
+    Ok(())
+}
+
+// marks every owl:Class in the graph as an ex:Marked
+struct MarkingReasoner {}
+
+impl Reasoner for MarkingReasoner {
+    fn name(&self) -> &str {
+        "marking"
+    }
+
+    fn classify(&self, graph: &Graph) -> Result<Graph> {
+        let class = NamedNodeRef::new("http://www.w3.org/2002/07/owl#Class")?;
+        let marked = NamedNodeRef::new("http://example.org#Marked")?;
+        let mut inferred = Graph::new();
+        for s in graph.subjects_for_predicate_object(ontoenv::consts::TYPE, class) {
+            inferred.insert(TripleRef::new(s, ontoenv::consts::TYPE, marked));
+        }
+        Ok(inferred)
+    }
+}
+
+#[test]
+fn test_ontoenv_materialize() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/rdftest/ontology1.ttl" => "ontology1.ttl",
+                  "fixtures/rdftest/ontology2.ttl" => "ontology2.ttl",
+                  "fixtures/rdftest/ontology3.ttl" => "ontology3.ttl"});
+    let cfg = default_config(&dir);
+    let mut env = OntoEnv::new(cfg, false)?;
+    env.update()?;
+
+    let ont3 = NamedNodeRef::new("http://example.org/ontology3")?;
+    let id = env.get_ontology_by_name(ont3).unwrap().id().clone();
+    let closure = env.materialize(&id, &RdfsReasoner {})?;
+    let marked_closure = env.materialize(&id, &MarkingReasoner {})?;
+    let marked = NamedNodeRef::new("http://example.org#Marked")?;
+    let class4 = NamedNodeRef::new("http://example.org#Class4")?;
+    let class1 = NamedNodeRef::new("http://example.org#Class1")?;
+    assert!(marked_closure.contains(TripleRef::new(class4, ontoenv::consts::TYPE, marked)));
+    assert!(marked_closure.contains(TripleRef::new(class1, ontoenv::consts::TYPE, marked)));
+    assert_eq!(marked_closure.len(), closure.len() + 4);
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_normalized_iris() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/rdftest/ontology1.ttl" => "ontology1.ttl"});
+    std::fs::write(
+        dir.path().join("variant.ttl"),
+        "@prefix owl: <http://www.w3.org/2002/07/owl#> .\n\
+         <HTTP://Example.ORG:80/variant> a owl:Ontology ;\n\
+             owl:imports <http://EXAMPLE.org/ontology1> .\n",
+    )?;
+    let cfg = default_config(&dir);
+    let mut env = OntoEnv::new(cfg, false)?;
+    env.update()?;
+    assert_eq!(env.num_graphs(), 2);
+
+    let variant = env
+        .get_ontology_by_name(NamedNodeRef::new("http://example.org/variant")?)
+        .unwrap();
+    assert_eq!(variant.imports[0].as_str(), "http://example.org/ontology1");
+    assert_eq!(env.get_dependency_closure(variant.id())?.len(), 2);
+    let iri = ontoenv::iri::parse_iri("https://Example.org/variant ")?;
+    assert!(env.get_ontology_by_name(iri.as_ref()).is_none());
+    let iri = ontoenv::iri::parse_iri("HTTP://example.org/variant")?;
+    assert!(env.get_ontology_by_name(iri.as_ref()).is_some());
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_update_report() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/rdftest/ontology1.ttl" => "ontology1.ttl",
+                  "fixtures/rdftest/ontology2.ttl" => "ontology2.ttl",
+                  "fixtures/rdftest/ontology3.ttl" => "ontology3.ttl"});
+    let cfg = default_config(&dir);
+    let mut env = OntoEnv::new(cfg, false)?;
+    let report = env.update()?;
+    assert_eq!(report.added.len(), 3);
+    assert!(report.updated.is_empty());
+    assert!(report.removed.is_empty());
+    assert!(report.failed.is_empty());
+
+    std::fs::remove_file(dir.path().join("ontology3.ttl"))?;
+    let report = env.update()?;
+    assert!(report.added.is_empty());
+    assert_eq!(report.removed.len(), 1);
+    assert_eq!(report.removed[0].name().as_str(), "http://example.org/ontology3");
+    let json = serde_json::to_value(&report)?;
+    assert_eq!(json["removed"][0]["name"], "http://example.org/ontology3");
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_anonymous_ontologies() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/anonymous/models/building.ttl" => "models/building.ttl",
+                  "fixtures/rdftest/ontology1.ttl" => "ontology1.ttl"});
+    let cfg = default_config(&dir);
+    let mut env = OntoEnv::new(cfg, false)?;
+    env.update()?;
+    assert_eq!(env.num_graphs(), 2);
+
+    // the name is derived from the path relative to the search directory
+    let name = NamedNodeRef::new("urn:ontoenv:anonymous:models/building.ttl")?;
+    let ontology = env.get_ontology_by_name(name).unwrap();
+    assert!(ontology.is_anonymous());
+    assert_eq!(env.get_graph(ontology.id())?.len(), 2);
+    let metadata = env.graph_metadata(ontology.id());
+    assert_eq!(metadata.get("anonymous").map(String::as_str), Some("true"));
+
+    let declared = NamedNodeRef::new("http://example.org/ontology1")?;
+    assert!(!env.get_ontology_by_name(declared).unwrap().is_anonymous());
+
+    let problems = env.problems()?;
+    assert_eq!(problems.len(), 1);
+    assert_eq!(
+        problems[0].message,
+        "No ontology declaration found; using the anonymous name <urn:ontoenv:anonymous:models/building.ttl>"
+    );
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_moved_file_keeps_graph() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/rdftest/ontology1.ttl" => "ontology1.ttl",
+                  "fixtures/rdftest/ontology2.ttl" => "ontology2.ttl"});
+    let cfg = default_config(&dir);
+    let mut env = OntoEnv::new(cfg, false)?;
+    env.update()?;
+    let num_triples = env.num_triples()?;
+
+    let name = NamedNodeRef::new("http://example.org/ontology1")?;
+    let before = env.get_ontology_by_name(name).unwrap().id().clone();
+    let graph_id = before.graph_id().unwrap().to_string();
+
+    std::fs::create_dir(dir.path().join("moved"))?;
+    std::fs::rename(
+        dir.path().join("ontology1.ttl"),
+        dir.path().join("moved/ontology1.ttl"),
+    )?;
+    let report = env.update()?;
+    assert_eq!(report.removed, vec![before.clone()]);
+    assert_eq!(report.added.len(), 1);
+
+    // the ontology moved, but its graph in the store did not
+    let after = env.get_ontology_by_name(name).unwrap().id().clone();
+    assert_ne!(after.location(), before.location());
+    assert_eq!(after.graph_id(), Some(graph_id.as_str()));
+    assert_eq!(after.graphname()?, before.graphname()?);
+    assert_eq!(env.num_triples()?, num_triples);
+    assert_eq!(env.get_graph(&after)?.len(), 3);
+
+    // removing the file drops its graph
+    std::fs::remove_file(dir.path().join("moved/ontology1.ttl"))?;
+    env.update()?;
+    assert!(env.num_triples()? < num_triples);
+    teardown(dir);
+    Ok(())
+}
+
+struct ClassCounter;
+
+impl OntologyInspector for ClassCounter {
+    fn name(&self) -> &str {
+        "classes"
+    }
+
+    fn inspect(&self, _ontology: NamedNodeRef, graph: &Graph) -> Result<Option<Value>> {
+        let owl_class = NamedNodeRef::new("http://www.w3.org/2002/07/owl#Class")?;
+        let count = graph
+            .subjects_for_predicate_object(rdf::TYPE, owl_class)
+            .count();
+        Ok(Some(Value::from(count)))
+    }
+}
+
+#[test]
+fn test_ontoenv_inspectors() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/rdftest/ontology1.ttl" => "ontology1.ttl",
+                  "fixtures/rdftest/ontology2.ttl" => "ontology2.ttl"});
+    let cfg = default_config(&dir);
+    let mut env = OntoEnv::new(cfg, false)?;
+    env.add_inspector(Arc::new(ClassCounter));
+    env.add_inspector(Arc::new(ShaclDeclarations));
+    env.update()?;
+
+    let name = NamedNodeRef::new("http://example.org/ontology1")?;
+    let ontology = env.get_ontology_by_name(name).unwrap();
+    assert_eq!(ontology.metadata().get("classes"), Some(&Value::from(2)));
+    // inspectors which find nothing store nothing
+    assert!(!ontology.metadata().contains_key("shacl_declarations"));
+    let metadata = env.graph_metadata(ontology.id());
+    assert_eq!(metadata.get("classes").map(String::as_str), Some("2"));
+
+    // the metadata is saved with the environment
+    env.save_to_directory()?;
+    let path = dir.path().join(".ontoenv/ontoenv.json");
+    let env = OntoEnv::from_file(&path, true)?;
+    let ontology = env.get_ontology_by_name(name).unwrap();
+    assert_eq!(ontology.metadata().get("classes"), Some(&Value::from(2)));
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_failed_imports() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/failures/importer.ttl" => "importer.ttl"});
+    let mut cfg = default_config(&dir);
+    cfg.strict = false;
+    cfg.offline = false;
+    let mut env = OntoEnv::new(cfg, false)?;
+    env.update()?;
+    let missing = env.missing_import_details();
+    assert_eq!(missing.len(), 1);
+    assert_eq!(missing[0].import, "http://127.0.0.1:9/unreachable.ttl");
+    let failure = missing[0].failure.clone().unwrap();
+    assert_eq!(failure.attempts, 1);
+
+    // the import is not fetched again until its retry is due
+    let path = dir.path().join("importer.ttl");
+    let content = std::fs::read_to_string(&path)?;
+    std::fs::write(&path, content + "\n")?;
+    assert_eq!(env.update()?.updated.len(), 1);
+    let missing = env.missing_import_details();
+    assert_eq!(missing[0].failure, Some(failure.clone()));
+
+    // the failures are saved with the environment
+    env.save_to_directory()?;
+    let mut env = OntoEnv::from_file(&dir.path().join(".ontoenv/ontoenv.json"), true)?;
+    assert_eq!(env.missing_import_details(), missing);
+
+    env.clear_failed_imports();
+    assert_eq!(env.missing_import_details()[0].failure, None);
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_refresh_matching() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/rdftest/ontology1.ttl" => "ontology1.ttl",
+                  "fixtures/rdftest/ontology2.ttl" => "models/ontology2.ttl"});
+    let cfg = default_config(&dir);
+    let mut env = OntoEnv::new(cfg, false)?;
+    env.update()?;
+    assert_eq!(env.num_graphs(), 2);
+
+    for file in ["ontology1.ttl", "models/ontology2.ttl"] {
+        let path = dir.path().join(file);
+        let content = std::fs::read_to_string(&path)?;
+        std::fs::write(&path, content + "\nex:Class5 rdf:type owl:Class .\n")?;
+    }
+    let ontology1 = NamedNodeRef::new("http://example.org/ontology1")?;
+    let ontology2 = NamedNodeRef::new("http://example.org/ontology2")?;
+
+    // only the changed file under models/ is read again
+    let report = env.refresh_matching("./models", RefreshStrategy::Changed)?;
+    assert_eq!(report.updated.len(), 1);
+    assert_eq!(report.updated[0].name(), ontology2);
+    assert_eq!(env.get_graph_by_name(ontology1)?.len(), 3);
+    assert_eq!(env.get_graph_by_name(ontology2)?.len(), 4);
+
+    // new matching files are added
+    copy_file(
+        &PathBuf::from("fixtures/rdftest/ontology3.ttl"),
+        &dir.path().join("models/ontology3.ttl"),
+    )?;
+    let report = env.refresh_matching("*/models/*.ttl", RefreshStrategy::Changed)?;
+    assert_eq!(report.added.len(), 1);
+    assert!(report.updated.is_empty());
+    assert_eq!(env.num_graphs(), 3);
+
+    // a full update picks up the remaining change
+    let report = env.update()?;
+    assert_eq!(report.updated.len(), 1);
+    assert_eq!(report.updated[0].name(), ontology1);
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_compatible_versions() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/versions/versioned-1.0.ttl" => "versioned-1.0.ttl",
+                  "fixtures/versions/versioned-2.0.ttl" => "versioned-2.0.ttl",
+                  "fixtures/versions/importer.ttl" => "importer.ttl"});
+    let mut cfg = default_config(&dir);
+    cfg.resolution_policy = "compatible".to_string();
+    let mut env = OntoEnv::new(cfg, false)?;
+    env.update()?;
+    assert_eq!(env.num_graphs(), 3);
+
+    let name = NamedNodeRef::new("http://example.org/versioned")?;
+    let latest = env
+        .get_ontology_with_policy(name, &CompatibilityPolicy)
+        .unwrap();
+    let latest_version = NamedNodeRef::new("http://example.org/versioned/2.0")?;
+    assert!(latest.is_version(latest_version));
+    assert_eq!(latest.prior_versions().len(), 1);
+
+    // the importer requires version 1.0, which version 2.0 is backward compatible with
+    let importer = env
+        .get_ontology_by_name(NamedNodeRef::new("http://example.org/importer")?)
+        .unwrap();
+    let closure = env.get_dependency_closure(importer.id())?;
+    assert_eq!(closure.len(), 2);
+    assert_eq!(closure[1], *latest.id());
+    assert!(env.missing_imports().is_empty());
+    teardown(dir);
+    Ok(())
+}
+
+#[derive(Default)]
+struct RecordingListener {
+    events: Mutex<Vec<String>>,
+}
+
+impl RecordingListener {
+    fn record(&self, event: &str, id: &GraphIdentifier) {
+        let event = format!("{} {}", event, id.name().as_str());
+        self.events.lock().unwrap().push(event);
+    }
+
+    fn take(&self) -> Vec<String> {
+        std::mem::take(&mut *self.events.lock().unwrap())
+    }
+}
+
+impl EnvironmentListener for RecordingListener {
+    fn on_add(&self, id: &GraphIdentifier) {
+        self.record("add", id);
+    }
+
+    fn on_update(&self, id: &GraphIdentifier) {
+        self.record("update", id);
+    }
+
+    fn on_remove(&self, id: &GraphIdentifier) {
+        self.record("remove", id);
+    }
+}
+
+#[test]
+fn test_ontoenv_listeners() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/rdftest/ontology1.ttl" => "ontology1.ttl",
+                  "fixtures/rdftest/ontology2.ttl" => "ontology2.ttl"});
+    let cfg = default_config(&dir);
+    let mut env = OntoEnv::new(cfg, false)?;
+    let listener = Arc::new(RecordingListener::default());
+    env.add_listener(listener.clone());
+    env.update()?;
+    assert_eq!(
+        listener.take(),
+        vec![
+            "add http://example.org/ontology1",
+            "add http://example.org/ontology2"
+        ]
+    );
+
+    // changed files are read again
+    let path = dir.path().join("ontology1.ttl");
+    let content = std::fs::read_to_string(&path)?;
+    std::fs::write(&path, content + "\nex:Class4 rdf:type owl:Class .\n")?;
+    let report = env.update()?;
+    assert_eq!(report.updated.len(), 1);
+    assert_eq!(listener.take(), vec!["update http://example.org/ontology1"]);
+    let name = NamedNodeRef::new("http://example.org/ontology1")?;
+    assert_eq!(env.get_graph_by_name(name)?.len(), 4);
+
+    std::fs::remove_file(dir.path().join("ontology2.ttl"))?;
+    env.update()?;
+    assert_eq!(listener.take(), vec!["remove http://example.org/ontology2"]);
+    teardown(dir);
+    Ok(())
+}
+
+// counts the named graphs in the store of the environment, closing it to open the store
+fn store_graph_count(env: OntoEnv) -> Result<usize> {
+    let path = env.store_path()?;
+    drop(env);
+    let store = oxigraph::store::Store::open(path)?;
+    Ok(store.named_graphs().count())
+}
+
+#[test]
+fn test_ontoenv_edited_file_keeps_graph() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/rdftest/ontology1.ttl" => "ontology1.ttl",
+                  "fixtures/rdftest/ontology2.ttl" => "ontology2.ttl"});
+    let cfg = default_config(&dir);
+    let mut env = OntoEnv::new(cfg.clone(), false)?;
+    env.update()?;
+    let graphs = store_graph_count(env)?;
+
+    // the edited file is read again into the graph it had, rather than into a new one
+    let path = dir.path().join("ontology1.ttl");
+    let mut content = std::fs::read_to_string(&path)?;
+    content.push_str(
+        "\n<http://example.org/ontology1#Extra> a <http://www.w3.org/2002/07/owl#Class> .\n",
+    );
+    std::fs::write(&path, content)?;
+    let mut env = OntoEnv::new(cfg, false)?;
+    env.update()?;
+    let name = NamedNodeRef::new("http://example.org/ontology1")?;
+    assert_eq!(env.get_graph_by_name(name)?.len(), 4);
+    assert_eq!(store_graph_count(env)?, graphs);
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_metrics() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/rdftest/ontology1.ttl" => "ontology1.ttl",
+                  "fixtures/rdftest/ontology2.ttl" => "ontology2.ttl",
+                  "fixtures/rdftest/ontology3.ttl" => "ontology3.ttl"});
+    let cfg = default_config(&dir);
+    let mut env = OntoEnv::new(cfg, false)?;
+    let metrics = Arc::new(InMemoryMetrics::default());
+    env.set_metrics_sink(metrics.clone());
+    env.update()?;
+    assert_eq!(metrics.counter(Counter::Parses), 3);
+    assert_eq!(metrics.counter(Counter::ParseFailures), 0);
+    assert_eq!(metrics.counter(Counter::Fetches), 0);
+    assert_eq!(metrics.timer(Timer::Parse).0, 3);
+    assert_eq!(metrics.timer(Timer::Update).0, 1);
+
+    let ont3 = NamedNodeRef::new("http://example.org/ontology3")?;
+    let id = env.get_ontology_by_name(ont3).unwrap().id().clone();
+    let closure = env.get_dependency_closure(&id)?;
+    env.get_union_graph(&closure, None, None)?;
+    assert!(metrics.timer(Timer::Closure).0 >= 1);
+    assert_eq!(metrics.timer(Timer::Union).0, 1);
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_metadata_api() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/rdftest/ontology1.ttl" => "ontology1.ttl",
+                  "fixtures/rdftest/ontology2.ttl" => "ontology2.ttl",
+                  "fixtures/rdftest/ontology3.ttl" => "ontology3.ttl"});
+    let cfg = default_config(&dir);
+    let mut env = OntoEnv::new(cfg, false)?;
+    env.update()?;
+
+    let query = HashMap::from([("limit".to_string(), "2".to_string())]);
+    let (status, body) = ontoenv::server::handle_api(&env, "/api/ontologies", &query);
+    assert_eq!(status, 200);
+    assert_eq!(body["total"], 3);
+    assert_eq!(body["items"].as_array().unwrap().len(), 2);
+    assert_eq!(body["items"][0]["name"], "http://example.org/ontology1");
+
+    let query = HashMap::from([("name".to_string(), "http://example.org/ontology1".to_string())]);
+    let (status, body) = ontoenv::server::handle_api(&env, "/api/ontology", &query);
+    assert_eq!(status, 200);
+    assert_eq!(body["dependents"].as_array().unwrap().len(), 2);
+
+    let query = HashMap::from([("name".to_string(), "http://example.org/missing".to_string())]);
+    let (status, _) = ontoenv::server::handle_api(&env, "/api/imports", &query);
+    assert_eq!(status, 404);
+    let (status, _) = ontoenv::server::handle_api(&env, "/api/nothing", &HashMap::new());
+    assert_eq!(status, 404);
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_push_to_sparql_endpoint() -> Result<()> {
+    use std::io::{BufRead, BufReader, Read, Write};
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/rdftest/ontology1.ttl" => "ontology1.ttl",
+                  "fixtures/rdftest/ontology2.ttl" => "ontology2.ttl"});
+    let cfg = default_config(&dir);
+    let mut env = OntoEnv::new(cfg, false)?;
+    env.update()?;
+
+    // a fake Graph Store endpoint which records the request lines
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    let endpoint = format!("http://{}/ds/data", listener.local_addr()?);
+    let server = std::thread::spawn(move || {
+        let mut requests = vec![];
+        for stream in listener.incoming().take(2) {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut length = 0;
+            loop {
+                let mut header = String::new();
+                reader.read_line(&mut header).unwrap();
+                if header.trim().is_empty() {
+                    break;
+                }
+                if let Some(v) = header.to_lowercase().strip_prefix("content-length:") {
+                    length = v.trim().parse().unwrap();
+                }
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).unwrap();
+            stream
+                .write_all(b"HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n")
+                .unwrap();
+            requests.push(request_line);
+        }
+        requests
+    });
+
+    let ont2 = NamedNodeRef::new("http://example.org/ontology2")?;
+    let id = env.get_ontology_by_name(ont2).unwrap().id().clone();
+    let pushed = env.push_to_sparql_endpoint(&endpoint, &SparqlAuth::None, &[id], true)?;
+    assert_eq!(pushed.len(), 2);
+    let requests = server.join().unwrap();
+    assert!(requests[0].starts_with("PUT /ds/data?graph=http%3A%2F%2Fexample.org%2Fontology2"));
+    assert!(requests[1].starts_with("PUT /ds/data?graph=http%3A%2F%2Fexample.org%2Fontology1"));
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_docgen() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/rdftest/ontology1.ttl" => "ontology1.ttl",
+                  "fixtures/rdftest/ontology2.ttl" => "ontology2.ttl",
+                  "fixtures/rdftest/ontology3.ttl" => "ontology3.ttl"});
+    let cfg = default_config(&dir);
+    let mut env = OntoEnv::new(cfg, false)?;
+    env.update()?;
+
+    let ont3 = NamedNodeRef::new("http://example.org/ontology3")?;
+    let id = env.get_ontology_by_name(ont3).unwrap().id().clone();
+    let doc = ontoenv::docgen::document(&env, &id, false)?;
+    assert_eq!(doc.classes.len(), 1);
+    let doc = ontoenv::docgen::document(&env, &id, true)?;
+    assert_eq!(doc.classes.len(), 4);
+    assert_eq!(doc.prefixes.get("ex").unwrap(), "http://example.org#");
+
+    let out = dir.path().join("docs");
+    let path = ontoenv::docgen::write_docs(&doc, DocFormat::Markdown, &out)?;
+    let markdown = std::fs::read_to_string(path)?;
+    assert!(markdown.contains("### <a id=\"ex-Class4\"></a>ex:Class4"));
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_precommit_checks() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/rdftest/ontology1.ttl" => "ontology1.ttl",
+                  "fixtures/rdftest/ontology2.ttl" => "ontology2.ttl"});
+    let cfg = default_config(&dir);
+    let mut env = OntoEnv::new(cfg, false)?;
+    env.update()?;
+
+    // a new file importing a known and an unknown ontology
+    let new_file = dir.path().join("new.ttl");
+    std::fs::write(
+        &new_file,
+        "@prefix owl: <http://www.w3.org/2002/07/owl#> .\n\
+         <http://example.org/new> a owl:Ontology ;\n\
+             owl:imports <http://example.org/ontology2>, <http://example.org/unknown> .\n",
+    )?;
+    // a copy of ontology1 under another file name
+    let duplicate = dir.path().join("copy.ttl");
+    std::fs::copy(dir.path().join("ontology1.ttl"), &duplicate)?;
+    let broken = dir.path().join("broken.ttl");
+    std::fs::write(&broken, "this is not turtle")?;
+
+    let problems = ontoenv::precommit::check_files(&env, &[new_file, duplicate, broken])?;
+    let messages: Vec<&str> = problems.iter().map(|p| p.message.as_str()).collect();
+    assert_eq!(messages.len(), 3);
+    assert!(messages.contains(&"Unresolved import http://example.org/unknown"));
+    assert!(messages.contains(&"Multiple ontologies with name http://example.org/ontology1"));
+    assert!(messages.iter().any(|m| m.starts_with("Failed to parse")));
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_precommit_checks_staged_content() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/rdftest/ontology1.ttl" => "ontology1.ttl",
+                  "fixtures/rdftest/ontology2.ttl" => "ontology2.ttl"});
+    let git = |args: &[&str]| -> Result<bool> {
+        let status = std::process::Command::new("git")
+            .args(args)
+            .current_dir(dir.path())
+            .output()?
+            .status;
+        Ok(status.success())
+    };
+    if !git(&["init", "-q"]).unwrap_or(false) {
+        // git is not installed
+        return Ok(());
+    }
+    let mut env = OntoEnv::new(default_config(&dir), false)?;
+    env.update()?;
+
+    // the staged file imports an unknown ontology; the working tree copy no longer does
+    let file = dir.path().join("new.ttl");
+    let ontology = |imports: &str| {
+        format!(
+            "<http://example.org/new> a <http://www.w3.org/2002/07/owl#Ontology> ;\n  \
+             <http://www.w3.org/2002/07/owl#imports> {} .\n",
+            imports
+        )
+    };
+    std::fs::write(&file, ontology("<http://example.org/unknown>"))?;
+    assert!(git(&["add", "new.ttl"])?);
+    std::fs::write(&file, ontology("<http://example.org/ontology2>"))?;
+
+    let staged = ontoenv::precommit::staged_files(dir.path())?;
+    assert_eq!(staged.len(), 1);
+    let problems = ontoenv::precommit::check_staged_files(&env, &staged)?;
+    assert_eq!(problems.len(), 1);
+    let message = "Unresolved import http://example.org/unknown";
+    assert_eq!(problems[0].message, message);
+    assert!(ontoenv::precommit::check_files(&env, &[file])?.is_empty());
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_deterministic_ordering() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/rdftest/ontology3.ttl" => "ontology3.ttl",
+                  "fixtures/rdftest/ontology1.ttl" => "ontology1.ttl",
+                  "fixtures/rdftest/ontology2.ttl" => "ontology2.ttl"});
+    let cfg = default_config(&dir);
+    let mut env = OntoEnv::new(cfg, false)?;
+    let report = env.update()?;
+    let added: Vec<&str> = report.added.iter().map(|id| id.name().as_str()).collect();
+    assert_eq!(
+        added,
+        vec![
+            "http://example.org/ontology1",
+            "http://example.org/ontology2",
+            "http://example.org/ontology3"
+        ]
+    );
+    let names: Vec<&str> = env.ontologies().keys().map(|id| id.name().as_str()).collect();
+    assert_eq!(names, added);
+
+    // the root comes first, followed by its dependencies in order
+    let ont3 = NamedNodeRef::new("http://example.org/ontology3")?;
+    let id = env.get_ontology_by_name(ont3).unwrap().id().clone();
+    let closure = env.get_dependency_closure(&id)?;
+    let closure_names: Vec<&str> = closure.iter().map(|id| id.name().as_str()).collect();
+    assert_eq!(
+        closure_names,
+        vec![
+            "http://example.org/ontology3",
+            "http://example.org/ontology1",
+            "http://example.org/ontology2"
+        ]
+    );
+
+    // writing the union graph twice produces identical files
+    let first = dir.path().join("first.ttl");
+    let second = dir.path().join("second.ttl");
+    let (union, _, _) = env.get_union_graph(&closure, None, None)?;
+    ontoenv::util::write_dataset_to_file(&union, first.to_str().unwrap())?;
+    let (union, _, _) = env.get_union_graph(&closure, None, None)?;
+    ontoenv::util::write_dataset_to_file(&union, second.to_str().unwrap())?;
+    assert_eq!(std::fs::read(&first)?, std::fs::read(&second)?);
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_base_iri() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/base/relative.ttl" => "relative.ttl",
+                  "fixtures/base/declared.ttl" => "declared.ttl"});
+
+    // @base is honored, otherwise relative IRIs resolve against the file's URL
+    let cfg = default_config(&dir);
+    let mut env = OntoEnv::new(cfg, false)?;
+    env.update()?;
+    let declared = NamedNodeRef::new("http://example.org/declared/ontology")?;
+    let ontology = env.get_ontology_by_name(declared).unwrap();
+    assert_eq!(ontology.base_iri(), Some("http://example.org/declared/"));
+    let file_url = ontoenv::ontology::path_to_file_iri(&dir.path().join("relative.ttl"))?;
+    let relative = env
+        .ontologies()
+        .keys()
+        .map(|id| id.name().as_str().to_string())
+        .find(|name| name.starts_with("file:"))
+        .unwrap();
+    assert!(relative.ends_with("/ontology"));
+    let ontology = env
+        .get_ontology_by_name(NamedNodeRef::new(&relative)?)
+        .unwrap();
+    assert_eq!(ontology.base_iri(), Some(file_url.as_str()));
+    let metadata = env.graph_metadata(ontology.id());
+    assert_eq!(metadata["base_iri"], file_url.as_str());
+
+    // a configured default base IRI replaces the file's URL
+    let mut cfg = default_config(&dir);
+    cfg.use_location_base_iri = false;
+    cfg.default_base_iri = Some("http://example.org/default/".to_string());
+    let mut env = OntoEnv::new(cfg, true)?;
+    env.update()?;
+    let default = NamedNodeRef::new("http://example.org/default/ontology")?;
+    assert!(env.get_ontology_by_name(default).is_some());
+    assert!(env.get_ontology_by_name(declared).is_some());
+
+    // strict mode can reject relative IRIs without a declared base
+    let mut cfg = default_config(&dir);
+    cfg.reject_relative_iris = true;
+    let mut env = OntoEnv::new(cfg, true)?;
+    assert!(env.update().is_err());
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_cancellation() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/rdftest/ontology1.ttl" => "ontology1.ttl",
+                  "fixtures/rdftest/ontology2.ttl" => "ontology2.ttl",
+                  "fixtures/rdftest/ontology3.ttl" => "ontology3.ttl"});
+    let cfg = default_config(&dir);
+    let mut env = OntoEnv::new(cfg, false)?;
+    let token = CancellationToken::new();
+    env.set_cancellation_token(Some(token.clone()));
+    token.cancel();
+    let err = env.update().unwrap_err();
+    assert!(err.is::<Cancelled>());
+    assert_eq!(env.num_graphs(), 0);
+
+    // a fresh token lets the update run to completion
+    env.set_cancellation_token(Some(CancellationToken::new()));
+    env.update()?;
+    assert_eq!(env.num_graphs(), 3);
+    let ont3 = NamedNodeRef::new("http://example.org/ontology3")?;
+    let id = env.get_ontology_by_name(ont3).unwrap().id().clone();
+
+    env.set_cancellation_token(Some(CancellationToken::with_timeout(
+        std::time::Duration::ZERO,
+    )));
+    let err = env.get_dependency_closure(&id).unwrap_err();
+    assert_eq!(
+        err.downcast_ref::<Cancelled>(),
+        Some(&Cancelled { timed_out: true })
+    );
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_merge_view() -> Result<()> {
+    let base_dir = TempDir::new("ontoenv")?;
+    setup!(&base_dir, {"fixtures/rdftest/ontology1.ttl" => "ontology1.ttl",
+                       "fixtures/rdftest/ontology4.ttl" => "ontology4.ttl"});
+    let mut base = OntoEnv::new(default_config(&base_dir), false)?;
+    base.update()?;
+
+    // the project imports ontology1 from the base but also has its own copy
+    let project_dir = TempDir::new("ontoenv")?;
+    setup!(&project_dir, {"fixtures/rdftest/ontology1.ttl" => "ontology1.ttl",
+                          "fixtures/rdftest/ontology2.ttl" => "ontology2.ttl"});
+    let mut project = OntoEnv::new(default_config(&project_dir), false)?;
+    project.update()?;
+
+    let ontology1 = NamedNodeRef::new("http://example.org/ontology1")?;
+    let ontology4 = NamedNodeRef::new("http://example.org/ontology4")?;
+    let in_project = |id: &GraphIdentifier| {
+        id.location()
+            .as_path()
+            .is_some_and(|path| path.starts_with(project_dir.path()))
+    };
+
+    // the project comes first, so its copy of ontology1 hides the one in the base
+    let view = OntoEnv::merge_view(&[&project, &base])?;
+    assert_eq!(view.num_graphs(), 3);
+    let ont1 = view.get_ontology_by_name(ontology1).unwrap();
+    assert!(in_project(ont1.id()));
+    assert_eq!(view.get_graph(ont1.id())?.len(), 3);
+
+    // ontology4 from the base resolves its import against the view
+    let ont4 = view.get_ontology_by_name(ontology4).unwrap().id().clone();
+    let closure = view.get_dependency_closure(&ont4)?;
+    assert_eq!(closure.len(), 2);
+    assert_eq!(closure[1].name(), ontology1);
+    assert!(in_project(&closure[1]));
+    assert!(view.save_to_directory().is_err());
+
+    // with the policy precedence both copies are kept
+    let view = OntoEnv::merge_view_with(&[&project, &base], MergePrecedence::Policy)?;
+    assert_eq!(view.num_graphs(), 4);
+    assert_eq!(view.get_graphs_by_name(ontology1).len(), 2);
+
+    teardown(project_dir);
+    teardown(base_dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_suggest_prefixes() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/rdftest/ontology1.ttl" => "ontology1.ttl",
+                  "fixtures/rdftest/ontology2.ttl" => "ontology2.ttl"});
+    let mut env = OntoEnv::new(default_config(&dir), false)?;
+    env.update()?;
+
+    // both ontologies bind ex, owl and rdf
+    let usage = env.prefix_usage();
+    assert_eq!(usage.frequency("http://example.org#"), 2);
+
+    let mut graph = Graph::new();
+    let thing = NamedNodeRef::new("http://example.org#Thing")?;
+    let unknown = NamedNodeRef::new("http://unknown.org/vocab#Thing")?;
+    let class = NamedNodeRef::new("http://www.w3.org/2002/07/owl#Class")?;
+    graph.insert(TripleRef::new(thing, rdf::TYPE, class));
+    graph.insert(TripleRef::new(unknown, rdf::TYPE, class));
+
+    let suggestions = env.suggest_prefixes(&graph);
+    let prefixes: Vec<(&str, &str)> = suggestions
+        .iter()
+        .map(|s| (s.prefix.as_str(), s.namespace.as_str()))
+        .collect();
+    assert_eq!(
+        prefixes,
+        vec![
+            ("ex", "http://example.org#"),
+            ("owl", "http://www.w3.org/2002/07/owl#"),
+            ("rdf", "http://www.w3.org/1999/02/22-rdf-syntax-ns#"),
+        ]
+    );
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_defining_ontology() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/terms/vocab.ttl" => "vocab.ttl",
+                  "fixtures/terms/extension.ttl" => "extension.ttl"});
+    let mut env = OntoEnv::new(default_config(&dir), false)?;
+    env.update()?;
+
+    let index = env.term_index()?;
+    assert_eq!(index.len(), 3);
+    let pump = NamedNodeRef::new("http://example.org/vocab#Pump")?;
+    assert_eq!(index.declaring_ontologies(pump).len(), 2);
+
+    // both ontologies declare vocab:Pump, but it is in the namespace of the vocabulary
+    let defining = env.defining_ontology(pump).unwrap();
+    assert_eq!(defining.name().as_str(), "http://example.org/vocab");
+    let feeds = NamedNodeRef::new("http://example.org/vocab#feeds")?;
+    let defining = env.defining_ontology(feeds).unwrap();
+    assert_eq!(defining.name().as_str(), "http://example.org/vocab");
+    let valve = NamedNodeRef::new("http://example.org/extension#Valve")?;
+    let defining = env.defining_ontology(valve).unwrap();
+    assert_eq!(defining.name().as_str(), "http://example.org/extension");
+
+    let unknown = NamedNodeRef::new("http://example.org/vocab#Unknown")?;
+    assert!(env.defining_ontology(unknown).is_none());
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_suggest_imports() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/rdftest/ontology1.ttl" => "ontology1.ttl",
+                  "fixtures/terms/vocab.ttl" => "vocab.ttl",
+                  "fixtures/terms/extension.ttl" => "extension.ttl"});
+    let mut env = OntoEnv::new(default_config(&dir), false)?;
+    env.update()?;
+
+    // the model uses terms of the vocabulary and the extension, which imports the vocabulary,
+    // and imports ontology1, whose terms it does not use
+    let model = read_file(&PathBuf::from("fixtures/terms/model.ttl"))?;
+    let suggested = env.suggest_imports(&model)?;
+    let suggested: Vec<&str> = suggested.iter().map(|name| name.as_str()).collect();
+    assert_eq!(suggested, vec!["http://example.org/extension"]);
+    let unused = env.unused_imports(&model)?;
+    let unused: Vec<&str> = unused.iter().map(|name| name.as_str()).collect();
+    assert_eq!(unused, vec!["http://example.org/ontology1"]);
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_configured_formats() -> Result<()> {
+    // an RDF/XML file with a .owl extension and a Turtle file without an extension
+    let dir = TempDir::new("fileendings")?;
+    setup!(&dir, {"fixtures/fileendings/model.xml" => "model.owl",
+                  "fixtures/fileendings/model" => "other/model"});
+    let mut cfg = default_config(&dir);
+    assert!(cfg.add_format("owl", "jsonld").is_err());
+    assert!(cfg.add_format("owl", "owl").is_err());
+    cfg.add_format(".OWL", "rdfxml")?;
+    assert!(cfg.is_included(&dir.path().join("model.owl")));
+    assert!(!cfg.is_included(&dir.path().join("other/model")));
+    let mut env = OntoEnv::new(cfg, false)?;
+    env.update()?;
+    assert_eq!(env.num_graphs(), 1);
+    assert_eq!(env.num_triples()?, 5);
+    teardown(dir);
+
+    let dir = TempDir::new("fileendings")?;
+    setup!(&dir, {"fixtures/fileendings/model" => "other/model"});
+    let mut cfg = default_config(&dir);
+    cfg.add_format_str("=sniff")?;
+    // the environment's own files are never read as ontologies
+    assert!(!cfg.is_included(&dir.path().join(".ontoenv/store/LOCK")));
+    let mut env = OntoEnv::new(cfg, false)?;
+    env.update()?;
+    assert_eq!(env.num_graphs(), 1);
+    assert_eq!(env.num_triples()?, 5);
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_read_limits() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/ont1.ttl" => "ont1.ttl",
+                  "fixtures/brick-stuff/Brick-1.3.ttl" => "Brick-1.3.ttl"});
+    let brick_size = std::fs::metadata(dir.path().join("Brick-1.3.ttl"))?.len();
+    let mut cfg = default_config(&dir);
+    cfg.strict = false;
+    cfg.max_file_size = Some(brick_size - 1);
+    cfg.parse_timeout_secs = Some(60);
+    let mut env = OntoEnv::new(cfg, false)?;
+    let report = env.update()?;
+    // Brick is skipped and reported, the rest is read
+    assert_eq!(report.added.len(), 1);
+    assert_eq!(report.failed.len(), 1);
+    let failure = &report.failed[0];
+    assert!(failure.location.as_str().ends_with("Brick-1.3.ttl"));
+    assert!(failure.error.contains("maximum"));
+    teardown(dir);
+
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/ont1.ttl" => "ont1.ttl",
+                  "fixtures/brick-stuff/Brick-1.3.ttl" => "Brick-1.3.ttl"});
+    let mut cfg = default_config(&dir);
+    cfg.max_file_size = Some(brick_size - 1);
+    let mut env = OntoEnv::new(cfg, false)?;
+    // strict mode fails the update
+    assert!(env.update().is_err());
+    teardown(dir);
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_ontoenv_follow_symlinks() -> Result<()> {
+    use std::os::unix::fs::symlink;
+
+    let outside = TempDir::new("ontoenv-outside")?;
+    std::fs::copy("fixtures/ont1.ttl", outside.path().join("ont1.ttl"))?;
+    let make_dir = || -> Result<TempDir> {
+        let dir = TempDir::new("ontoenv")?;
+        setup!(&dir, {"fixtures/ont3.ttl" => "ont3.ttl",
+                      "fixtures/ont4.ttl" => "sub/ont4.ttl"});
+        // a second link to ont4, a file outside the search directory and a cycle
+        symlink(dir.path().join("sub/ont4.ttl"), dir.path().join("ont4.ttl"))?;
+        symlink(outside.path().join("ont1.ttl"), dir.path().join("ont1.ttl"))?;
+        symlink(dir.path(), dir.path().join("sub/loop"))?;
+        Ok(dir)
+    };
+
+    // links are not followed by default
+    let dir = make_dir()?;
+    let mut env = OntoEnv::new(default_config(&dir), false)?;
+    env.update()?;
+    assert_eq!(env.num_graphs(), 2);
+    teardown(dir);
+
+    let dir = make_dir()?;
+    let mut cfg = default_config(&dir);
+    cfg.follow_symlinks = true;
+    let mut env = OntoEnv::new(cfg, false)?;
+    env.update()?;
+    assert_eq!(env.num_graphs(), 3);
+    let ont1 = env
+        .get_ontology_by_name(NamedNodeRef::new("urn:ont1")?)
+        .unwrap();
+    let canonical = std::fs::canonicalize(outside.path().join("ont1.ttl"))?;
+    assert_eq!(ont1.location(), Some(&OntologyLocation::File(canonical)));
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_union_graph_iter() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/brick-stuff/Brick-1.3.ttl" => "Brick-1.3.ttl",
+                  "fixtures/brick-stuff/support/brickpatches.ttl" => "support/brickpatches.ttl",
+                  "fixtures/brick-stuff/support/shacl.ttl" => "support/shacl.ttl",
+                  "fixtures/brick-stuff/support/dash.ttl" => "support/dash.ttl"});
+    let mut cfg = default_config(&dir);
+    cfg.strict = false;
+    let mut env = OntoEnv::new(cfg, false)?;
+    env.update()?;
+
+    let brick = NamedNodeRef::new("https://brickschema.org/schema/1.3/Brick")?;
+    let brick = env.get_ontology_by_name(brick).unwrap();
+    let closure = env.get_dependency_closure(brick.id())?;
+    assert!(closure.len() > 1);
+    // the streamed union has the same quads as the collected one
+    for (rewrite, remove) in [(Some(true), Some(true)), (Some(false), Some(false))] {
+        let (union, _, _) = env.get_union_graph(&closure, rewrite, remove)?;
+        let streamed = env
+            .union_graph_iter(&closure, rewrite, remove)?
+            .collect::<Result<HashSet<_>>>()?;
+        let collected: HashSet<_> = union.iter().map(|quad| quad.into_owned()).collect();
+        assert_eq!(streamed, collected);
+    }
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_plan_update() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, { "fixtures/ont1.ttl" => "ont1.ttl",
+                   "fixtures/ont2.ttl" => "ont2.ttl",
+                   "fixtures/ont3.ttl" => "ont3.ttl",
+                   "fixtures/ont4.ttl" => "ont4.ttl" });
+    let mut env = OntoEnv::new(default_config(&dir), false)?;
+    let plan = env.plan_update(false)?;
+    assert_eq!(plan.to_parse.len(), 4);
+    env.apply_update_plan(plan)?;
+    assert_eq!(env.num_graphs(), 4);
+    assert!(env.plan_update(false)?.is_empty());
+
+    // planning does not change the environment
+    std::fs::remove_file(dir.path().join("ont2.ttl"))?;
+    let plan = env.plan_update(false)?;
+    assert!(plan.to_parse.is_empty());
+    let removed: Vec<&str> = plan.to_remove.iter().map(|id| id.name().as_str()).collect();
+    assert_eq!(removed, vec!["urn:ont2"]);
+    assert_eq!(env.num_graphs(), 4);
+
+    // a full update reads every file again; actions dropped from the plan are not carried out
+    let mut plan = env.plan_update(true)?;
+    assert_eq!(plan.to_parse.len(), 3);
+    assert!(plan.to_fetch.is_empty());
+    plan.to_parse.retain(|l| l.as_str().ends_with("ont1.ttl"));
+    let report = env.apply_update_plan(plan)?;
+    assert_eq!(report.updated.len(), 1);
+    assert_eq!(report.removed.len(), 1);
+    assert_eq!(env.num_graphs(), 3);
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_closure_cache() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/rdftest/ontology1.ttl" => "ontology1.ttl",
+                  "fixtures/rdftest/ontology2.ttl" => "ontology2.ttl",
+                  "fixtures/rdftest/ontology3.ttl" => "ontology3.ttl"});
+    let mut env = OntoEnv::new(default_config(&dir), false)?;
+    env.update()?;
+    let ont3 = NamedNodeRef::new("http://example.org/ontology3")?;
+    let closure = env.get_dependency_closure(env.get_ontology_by_name(ont3).unwrap().id())?;
+
+    let (union, failed, cached) = env.get_cached_union_graph(&closure, None, None, false)?;
+    assert!(failed.is_none());
+    assert!(!cached);
+    let (cached_union, _, cached) = env.get_cached_union_graph(&closure, None, None, false)?;
+    assert!(cached);
+    assert_eq!(cached_union, union);
+    // other options are cached separately
+    let (_, _, cached) = env.get_cached_union_graph(&closure, None, Some(false), false)?;
+    assert!(!cached);
+
+    // reading the ontologies again keeps the cached unions as long as their content is the same
+    env.update_all(true)?;
+    let (_, _, cached) = env.get_cached_union_graph(&closure, None, None, false)?;
+    assert!(cached);
+    let ont1 = dir.path().join("ontology1.ttl");
+    let content = std::fs::read_to_string(&ont1)?;
+    std::fs::write(&ont1, content + "\n<urn:a> a owl:Class .\n")?;
+    env.update_all(true)?;
+    let (_, _, cached) = env.get_cached_union_graph(&closure, None, None, false)?;
+    assert!(!cached);
+    assert_eq!(env.clear_closure_cache()?, 2);
+    let (_, _, cached) = env.get_cached_union_graph(&closure, None, None, false)?;
+    assert!(!cached);
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_annotations() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, { "fixtures/ont1.ttl" => "ont1.ttl",
+                   "fixtures/ont3.ttl" => "ont3.ttl",
+                   "fixtures/ont4.ttl" => "ont4.ttl" });
+    let mut env = OntoEnv::new(default_config(&dir), false)?;
+    env.update()?;
+    let ont1 = env
+        .get_ontology_by_name(NamedNodeRef::new("urn:ont1")?)
+        .unwrap()
+        .id()
+        .clone();
+    let ont3 = env
+        .get_ontology_by_name(NamedNodeRef::new("urn:ont3")?)
+        .unwrap()
+        .id()
+        .clone();
+    env.set_annotation(&ont1, "owner", "controls-team")?;
+    env.set_annotation(&ont1, "status", "deprecated")?;
+    env.set_annotation(&ont3, "owner", "platform-team")?;
+    assert!(env.set_annotation(&ont1, "", "empty").is_err());
+
+    assert_eq!(env.ontologies_with_annotation("owner", None).len(), 2);
+    let owned = env.ontologies_with_annotation("owner", Some("controls-team"));
+    assert_eq!(owned, vec![ont1.clone()]);
+    assert_eq!(
+        env.remove_annotation(&ont3, "owner")?,
+        Some("platform-team".to_string())
+    );
+    assert_eq!(
+        env.ontologies_with_annotation("owner", None),
+        vec![ont1.clone()]
+    );
+
+    // annotations are kept when the ontology is read again and when the environment is saved
+    env.update_all(true)?;
+    env.save_to_directory()?;
+    drop(env);
+    let env = OntoEnv::from_file(&dir.path().join(".ontoenv/ontoenv.json"), true)?;
+    let annotations = env.annotations(&ont1).unwrap();
+    assert_eq!(annotations.get("owner").unwrap(), "controls-team");
+    assert_eq!(annotations.get("status").unwrap(), "deprecated");
+    assert!(env.annotations(&ont3).unwrap().is_empty());
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_dependents_of_versions() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/versions/versioned-1.0.ttl" => "versioned-1.0.ttl",
+                  "fixtures/versions/versioned-2.0.ttl" => "versioned-2.0.ttl",
+                  "fixtures/versions/importer.ttl" => "importer.ttl"});
+    // imports the ontology by its name rather than a version IRI
+    std::fs::write(
+        dir.path().join("other.ttl"),
+        "@prefix owl: <http://www.w3.org/2002/07/owl#> .\n\
+         <http://example.org/other> a owl:Ontology ;\n\
+             owl:imports <http://example.org/versioned> .\n",
+    )?;
+    let mut env = OntoEnv::new(default_config(&dir), false)?;
+    env.update()?;
+
+    let names = |ids: Vec<GraphIdentifier>| -> Vec<String> {
+        ids.iter()
+            .map(|id| id.name().as_str().to_string())
+            .collect()
+    };
+    let expected = vec![
+        "http://example.org/importer".to_string(),
+        "http://example.org/other".to_string(),
+    ];
+    // the same importers are found by the name and by each version IRI
+    for iri in [
+        "http://example.org/versioned",
+        "http://example.org/versioned/1.0",
+        "http://example.org/versioned/2.0",
+    ] {
+        assert_eq!(names(env.get_dependents(&NamedNode::new(iri)?)?), expected);
+    }
+
+    let references = env.explain_import(NamedNodeRef::new("http://example.org/versioned")?);
+    let matches: Vec<(&str, ImportMatch)> = references
+        .iter()
+        .map(|r| (r.import.as_str(), r.matched))
+        .collect();
+    assert_eq!(
+        matches,
+        vec![
+            ("http://example.org/versioned/1.0", ImportMatch::VersionIri),
+            ("http://example.org/versioned", ImportMatch::Name),
+        ]
+    );
+    let unknown = NamedNode::new("http://example.org/unknown")?;
+    assert!(env.get_dependents(&unknown)?.is_empty());
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_from_locations() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, { "fixtures/ont1.ttl" => "ont1.ttl",
+                   "fixtures/ont2.ttl" => "ont2.ttl",
+                   "fixtures/ont3.ttl" => "ont3.ttl",
+                   "fixtures/ont4.ttl" => "ont4.ttl" });
+    let locations = ["ont1.ttl", "ont3.ttl", "ont4.ttl"]
+        .iter()
+        .map(|file| OntologyLocation::File(dir.path().join(file)))
+        .collect();
+    let mut env = OntoEnv::from_locations(locations, default_config(&dir))?;
+    assert!(env.is_temporary());
+    // ont2.ttl is in the search directory, but was not asked for
+    assert_eq!(env.num_graphs(), 3);
+    assert!(env
+        .get_ontology_by_name(NamedNodeRef::new("urn:ont2")?)
+        .is_none());
+    let ont1 = env
+        .get_ontology_by_name(NamedNodeRef::new("urn:ont1")?)
+        .unwrap()
+        .id()
+        .clone();
+    assert_eq!(env.get_dependency_closure(&ont1)?.len(), 3);
+
+    // nothing is written to the directory, and updating does not scan it
+    assert!(env.save_to_directory().is_err());
+    assert!(!dir.path().join(".ontoenv").exists());
+    let report = env.update()?;
+    assert!(report.added.is_empty());
+    assert_eq!(env.num_graphs(), 3);
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_get_named_graph() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, { "fixtures/ont1.ttl" => "ont1.ttl",
+                   "fixtures/ont3.ttl" => "ont3.ttl",
+                   "fixtures/ont4.ttl" => "ont4.ttl" });
+    let mut env = OntoEnv::new(default_config(&dir), false)?;
+    env.update()?;
+    let name = NamedNodeRef::new("urn:ont1")?;
+    let id = env.get_ontology_by_name(name).unwrap().id().clone();
+    let graph = env.get_graph(&id)?;
+    let dataset = env.get_named_graph(&id)?;
+    assert_eq!(dataset.len(), graph.len());
+    // the triples are in a graph named after the ontology, not after its graph in the store
+    assert!(dataset
+        .iter()
+        .all(|quad| quad.graph_name == GraphNameRef::NamedNode(name)));
+    for triple in graph.iter() {
+        assert!(dataset.contains(triple.in_graph(name)));
+    }
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_lockfile_sync() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, { "fixtures/ont1.ttl" => "ont1.ttl",
+                   "fixtures/ont3.ttl" => "ont3.ttl",
+                   "fixtures/ont4.ttl" => "ont4.ttl" });
+    let mut env = OntoEnv::new(default_config(&dir), false)?;
+    env.update()?;
+    let lock_path = env.lockfile_path();
+    let lock = Lockfile::load(&lock_path)?;
+    assert_eq!(lock.ontologies.len(), 3);
+    // local files are recorded relative to the root, so the lockfile can be shared
+    assert!(lock
+        .ontologies
+        .iter()
+        .all(|o| matches!(&o.location, OntologyLocation::File(p) if p.is_relative())));
+    let locked = std::fs::read_to_string(&lock_path)?;
+
+    // a new ontology is recorded by the next update
+    std::fs::copy("fixtures/ont2.ttl", dir.path().join("ont2.ttl"))?;
+    env.update()?;
+    assert_eq!(Lockfile::load(&lock_path)?.ontologies.len(), 4);
+
+    // syncing from the earlier lockfile removes it again and keeps the others as they are
+    std::fs::write(&lock_path, &locked)?;
+    let report = env.sync_from_lock()?;
+    assert_eq!(report.removed.len(), 1);
+    assert!(report.added.is_empty() && report.updated.is_empty());
+    assert_eq!(env.num_graphs(), 3);
+    drop(env);
+
+    // a new environment gets exactly the recorded graphs, without scanning for ont2.ttl
+    let mut env = OntoEnv::new(default_config(&dir), true)?;
+    let report = env.sync_from_lock()?;
+    assert_eq!(report.added.len(), 3);
+    assert_eq!(env.num_graphs(), 3);
+    let ont1 = env
+        .get_ontology_by_name(NamedNodeRef::new("urn:ont1")?)
+        .unwrap()
+        .id()
+        .clone();
+    assert_eq!(env.get_dependency_closure(&ont1)?.len(), 3);
+
+    // content which no longer matches the lockfile fails the sync in strict mode
+    let ont4 = dir.path().join("ont4.ttl");
+    let content = std::fs::read_to_string(&ont4)?;
+    std::fs::write(&ont4, content + "\n<urn:ont4#A> a <urn:ont4#B> .\n")?;
+    drop(env);
+    let mut env = OntoEnv::new(default_config(&dir), true)?;
+    assert!(env.sync_from_lock().is_err());
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_watch() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, { "fixtures/ont1.ttl" => "ont1.ttl",
+                   "fixtures/ont3.ttl" => "ont3.ttl",
+                   "fixtures/ont4.ttl" => "ont4.ttl" });
+    let mut env = OntoEnv::new(default_config(&dir), false)?;
+    env.update()?;
+    assert_eq!(env.num_graphs(), 3);
+    // stops watching if the file is never picked up
+    let token = CancellationToken::with_timeout(Duration::from_secs(30));
+    env.set_cancellation_token(Some(token.clone()));
+
+    let new_file = dir.path().join("ont2.ttl");
+    let writer = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(200));
+        std::fs::copy("fixtures/ont2.ttl", new_file).unwrap();
+    });
+    let options = WatchOptions {
+        interval: Duration::from_millis(50),
+    };
+    let mut reports = vec![];
+    env.watch(&options, |_, result| {
+        reports.push(result?);
+        token.cancel();
+        Ok(())
+    })?;
+    writer.join().unwrap();
+
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].added.len(), 1);
+    assert_eq!(reports[0].added[0].name(), NamedNodeRef::new("urn:ont2")?);
+    assert_eq!(env.num_graphs(), 4);
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_change_manifest() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, { "fixtures/ont1.ttl" => "ont1.ttl",
+                   "fixtures/ont3.ttl" => "ont3.ttl",
+                   "fixtures/ont4.ttl" => "ont4.ttl" });
+    let mut config = default_config(&dir);
+    config.change_manifest = Some(PathBuf::from("out/changes.json"));
+    let mut env = OntoEnv::new(config, false)?;
+    env.update()?;
+    let path = dir.path().join("out/changes.json");
+    let read = || -> Result<ChangeManifest> {
+        Ok(serde_json::from_str(&std::fs::read_to_string(&path)?)?)
+    };
+    let manifest = read()?;
+    assert_eq!(manifest.changes.len(), 3);
+    assert!(manifest
+        .changes
+        .iter()
+        .all(|c| c.change == ChangeKind::Added && c.fingerprint.is_some()));
+    assert!(manifest
+        .changes
+        .iter()
+        .all(|c| c.graph.starts_with("urn:ontoenv:graph:")));
+
+    // an update which changes nothing leaves the manifest of the last change
+    env.update()?;
+    assert_eq!(read()?, manifest);
+
+    std::fs::remove_file(dir.path().join("ont4.ttl"))?;
+    env.update()?;
+    let manifest = read()?;
+    assert_eq!(manifest.changes.len(), 1);
+    assert_eq!(manifest.changes[0].change, ChangeKind::Removed);
+    assert_eq!(manifest.changes[0].name, "urn:ont4");
+    assert!(manifest.changes[0].fingerprint.is_none());
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_parallel_update() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, { "fixtures/ont1.ttl" => "ont1.ttl",
+                   "fixtures/ont2.ttl" => "ont2.ttl",
+                   "fixtures/ont3.ttl" => "ont3.ttl",
+                   "fixtures/ont4.ttl" => "ont4.ttl",
+                   "fixtures/brick-stuff/Brick-1.3.ttl" => "Brick-1.3.ttl",
+                   "fixtures/brick-stuff/support/SCHEMA_QUDT_NoOWL-v2.1.ttl" => "support/SCHEMA_QUDT_NoOWL-v2.1.ttl" });
+    std::fs::write(dir.path().join("broken.ttl"), "this is not turtle")?;
+    std::fs::write(dir.path().join("also-broken.ttl"), "nor is this")?;
+    let summary = |env: &OntoEnv| -> Vec<(String, usize)> {
+        env.ontologies()
+            .values()
+            .map(|o| {
+                let triples = env.get_graph(o.id()).unwrap().len();
+                (o.name().as_str().to_string(), triples)
+            })
+            .collect()
+    };
+
+    let mut config = default_config(&dir);
+    config.strict = false;
+    config.max_parallel = Some(1);
+    let mut env = OntoEnv::new(config.clone(), false)?;
+    let sequential = env.update()?;
+    let expected = summary(&env);
+    drop(env);
+
+    config.max_parallel = Some(4);
+    let mut env = OntoEnv::new(config, true)?;
+    let parallel = env.update()?;
+    assert_eq!(summary(&env), expected);
+    assert_eq!(parallel.added, sequential.added);
+    // failures are reported in the same order, whichever worker finished first
+    let failed = |report: &UpdateReport| -> Vec<String> {
+        report
+            .failed
+            .iter()
+            .map(|f| f.location.as_str().to_string())
+            .collect()
+    };
+    assert_eq!(failed(&parallel), failed(&sequential));
+    let failures = failed(&parallel);
+    assert_eq!(failures.len(), 2);
+    assert!(failures[0].ends_with("also-broken.ttl"));
+    let ont1 = env
+        .get_ontology_by_name(NamedNodeRef::new("urn:ont1")?)
+        .unwrap()
+        .id()
+        .clone();
+    assert_eq!(env.get_dependency_closure(&ont1)?.len(), 3);
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_memory_cache() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/rdftest/ontology1.ttl" => "ontology1.ttl",
+                  "fixtures/rdftest/ontology2.ttl" => "ontology2.ttl",
+                  "fixtures/rdftest/ontology3.ttl" => "ontology3.ttl"});
+    let mut env = OntoEnv::new(default_config(&dir), false)?;
+    let metrics = Arc::new(InMemoryMetrics::default());
+    env.set_metrics_sink(metrics.clone());
+    env.update()?;
+    let ont3 = NamedNodeRef::new("http://example.org/ontology3")?;
+    let id = env.get_ontology_by_name(ont3).unwrap().id().clone();
+
+    // the closures computed by the update and the second union come from memory
+    let closures = metrics.timer(Timer::Closure).0;
+    let hits = metrics.counter(Counter::CacheHits);
+    let closure = env.get_dependency_closure(&id)?;
+    let (union, _, _) = env.get_union_graph(&closure, None, None)?;
+    assert_eq!(env.get_dependency_closure(&id)?, closure);
+    let (again, successful, failed) = env.get_union_graph(&closure, None, None)?;
+    assert_eq!(again, union);
+    assert_eq!(successful, closure);
+    assert!(failed.is_none());
+    assert_eq!(metrics.timer(Timer::Closure).0, closures);
+    assert_eq!(metrics.timer(Timer::Union).0, 1);
+    assert!(metrics.counter(Counter::CacheHits) >= hits + 2);
+    // other options are computed separately
+    env.get_union_graph(&closure, None, Some(false))?;
+    assert_eq!(metrics.timer(Timer::Union).0, 2);
+
+    // updating the environment forgets them
+    let ont2 = dir.path().join("ontology2.ttl");
+    let content = std::fs::read_to_string(&ont2)?;
+    std::fs::write(&ont2, content + "\n<urn:a> a owl:Class .\n")?;
+    env.update_all(true)?;
+    let closure = env.get_dependency_closure(&id)?;
+    let (changed, _, _) = env.get_union_graph(&closure, None, None)?;
+    assert_eq!(changed.len(), union.len() + 1);
+    assert_eq!(metrics.timer(Timer::Union).0, 3);
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_which_file() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/rdftest/ontology1.ttl" => "ontology1.ttl",
+                  "fixtures/rdftest/ontology2.ttl" => "ontology2.ttl",
+                  "fixtures/rdftest/ontology3.ttl" => "ontology3.ttl"});
+    let mut env = OntoEnv::new(default_config(&dir), false)?;
+    env.update()?;
+
+    // an ontology is found at its owl:Ontology declaration
+    let ont3 = NamedNodeRef::new("http://example.org/ontology3")?;
+    let source = env.which_file(ont3)?.unwrap();
+    assert_eq!(source.ontology.name(), ont3);
+    assert!(source.file.as_ref().unwrap().ends_with("ontology3.ttl"));
+    assert_eq!(source.line, Some(6));
+
+    // a term is found in the file of the ontology defining it, written with its prefix
+    let source = env
+        .which_file(NamedNodeRef::new("http://example.org#Class2")?)?
+        .unwrap();
+    let ont1 = NamedNodeRef::new("http://example.org/ontology1")?;
+    assert_eq!(source.ontology.name(), ont1);
+    assert!(source.file.as_ref().unwrap().ends_with("ontology1.ttl"));
+    assert_eq!(source.line, Some(9));
+    let text = std::fs::read_to_string(source.file.unwrap())?;
+    assert!(text[source.offset.unwrap()..].starts_with("ex:Class2"));
+
+    assert!(env
+        .which_file(NamedNodeRef::new("http://example.org#Missing")?)?
+        .is_none());
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_dependency_graph() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/rdftest/ontology1.ttl" => "ontology1.ttl",
+                  "fixtures/rdftest/ontology2.ttl" => "ontology2.ttl",
+                  "fixtures/rdftest/ontology3.ttl" => "ontology3.ttl"});
+    std::fs::write(
+        dir.path().join("importer.ttl"),
+        "<urn:importer> a <http://www.w3.org/2002/07/owl#Ontology> ;\n\
+         <http://www.w3.org/2002/07/owl#imports> <urn:missing> .\n",
+    )?;
+    let mut cfg = default_config(&dir);
+    cfg.strict = false;
+    let mut env = OntoEnv::new(cfg, false)?;
+    env.update()?;
+
+    let deps = env.dependency_graph();
+    let graph = deps.graph();
+    // four ontologies and the missing import
+    assert_eq!(graph.node_count(), 5);
+    assert_eq!(graph.edge_count(), 4);
+    let missing = NamedNode::new("urn:missing")?;
+    assert_eq!(deps.missing(), vec![&missing]);
+    let (from, to, edge) = deps
+        .edges()
+        .find(|(_, _, edge)| edge.status == ImportStatus::Missing)
+        .unwrap();
+    assert!(matches!(from, DependencyNode::Ontology(id) if id.name().as_str() == "urn:importer"));
+    assert_eq!(to, &DependencyNode::Missing(missing.clone()));
+    assert_eq!(edge.import, missing);
+
+    // importers come before the ontologies they import
+    let id = |name: &str| -> Result<GraphIdentifier> {
+        let name = NamedNodeRef::new(name)?;
+        Ok(env.get_ontology_by_name(name).unwrap().id().clone())
+    };
+    let order = toposort(graph, None).unwrap();
+    let position = |name: &str| -> Result<usize> {
+        let index = deps.node_index(&id(name)?).unwrap();
+        Ok(order.iter().position(|i| *i == index).unwrap())
+    };
+    assert!(position("http://example.org/ontology3")? < position("http://example.org/ontology2")?);
+    assert!(position("http://example.org/ontology2")? < position("http://example.org/ontology1")?);
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_trailing_separators() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/rdftest/ontology1.ttl" => "ontology1.ttl"});
+    std::fs::write(
+        dir.path().join("importer.ttl"),
+        "<urn:importer> a <http://www.w3.org/2002/07/owl#Ontology> ;\n\
+         <http://www.w3.org/2002/07/owl#imports> <http://example.org/ontology1/> , \
+         <http://example.org/ontology1#> .\n",
+    )?;
+    let mut cfg = default_config(&dir);
+    cfg.strict = false;
+    let mut env = OntoEnv::new(cfg, false)?;
+    env.update()?;
+    let importer = NamedNodeRef::new("urn:importer")?;
+    let id = env.get_ontology_by_name(importer).unwrap().id().clone();
+    // by default the imports do not resolve
+    assert_eq!(env.missing_imports().len(), 2);
+    assert_eq!(env.get_dependency_closure(&id)?.len(), 1);
+
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/rdftest/ontology1.ttl" => "ontology1.ttl"});
+    std::fs::write(
+        dir.path().join("importer.ttl"),
+        "<urn:importer> a <http://www.w3.org/2002/07/owl#Ontology> ;\n\
+         <http://www.w3.org/2002/07/owl#imports> <http://example.org/ontology1/> , \
+         <http://example.org/ontology1#> .\n",
+    )?;
+    let mut cfg = default_config(&dir);
+    cfg.ignore_trailing_separators = true;
+    let mut env = OntoEnv::new(cfg, false)?;
+    env.update()?;
+    assert!(env.missing_imports().is_empty());
+    let id = env.get_ontology_by_name(importer).unwrap().id().clone();
+    let closure = env.get_dependency_closure(&id)?;
+    assert_eq!(closure.len(), 2);
+    let ont1 = NamedNodeRef::new("http://example.org/ontology1")?;
+    assert!(closure.iter().any(|id| id.name() == ont1));
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_get_graphs() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/rdftest/ontology1.ttl" => "ontology1.ttl",
+                  "fixtures/rdftest/ontology2.ttl" => "ontology2.ttl",
+                  "fixtures/rdftest/ontology3.ttl" => "ontology3.ttl"});
+    let mut env = OntoEnv::new(default_config(&dir), false)?;
+    env.update()?;
+    let id = |name: &str| -> Result<GraphIdentifier> {
+        let name = NamedNodeRef::new(name)?;
+        Ok(env.get_ontology_by_name(name).unwrap().id().clone())
+    };
+    let ids = vec![
+        id("http://example.org/ontology3")?,
+        id("http://example.org/ontology1")?,
+        id("http://example.org/ontology3")?,
+    ];
+    // the graphs are returned in the order they were asked for, like with get_graph
+    let graphs = env.get_graphs(&ids)?;
+    assert_eq!(graphs.len(), 3);
+    for ((graph_id, graph), id) in graphs.iter().zip(&ids) {
+        assert_eq!(graph_id, id);
+        assert_eq!(graph, &env.get_graph(id)?);
+        assert!(!graph.is_empty());
+        assert_eq!(env.graph_len(id)?, graph.len());
+    }
+    assert!(env.get_graphs(&[])?.is_empty());
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_closure_order() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/rdftest/ontology1.ttl" => "ontology1.ttl",
+                  "fixtures/rdftest/ontology2.ttl" => "ontology2.ttl",
+                  "fixtures/rdftest/ontology3.ttl" => "ontology3.ttl"});
+    // a and b import each other; c imports a
+    for (name, imports) in [("a", "urn:b"), ("b", "urn:a"), ("c", "urn:a")] {
+        std::fs::write(
+            dir.path().join(format!("{}.ttl", name)),
+            format!(
+                "<urn:{}> a <http://www.w3.org/2002/07/owl#Ontology> ;\n\
+                 <http://www.w3.org/2002/07/owl#imports> <{}> .\n",
+                name, imports
+            ),
+        )?;
+    }
+    let mut env = OntoEnv::new(default_config(&dir), false)?;
+    env.update()?;
+    let id = |name: &str| -> Result<GraphIdentifier> {
+        let name = NamedNodeRef::new(name)?;
+        Ok(env.get_ontology_by_name(name).unwrap().id().clone())
+    };
+    let names = |ids: &[GraphIdentifier]| -> Vec<String> {
+        ids.iter()
+            .map(|id| id.name().as_str().to_string())
+            .collect()
+    };
+
+    // ontology3 imports ontology1 and ontology2, which imports ontology1
+    let ont3 = id("http://example.org/ontology3")?;
+    assert_eq!(
+        names(&env.closure_topological_order(&ont3)?),
+        vec![
+            "http://example.org/ontology1",
+            "http://example.org/ontology2",
+            "http://example.org/ontology3"
+        ]
+    );
+    assert_eq!(env.closure_layers(&ont3)?.len(), 3);
+
+    // the cycle is loaded together, before the ontology importing it
+    let layers = env.closure_layers(&id("urn:c")?)?;
+    assert_eq!(layers.len(), 2);
+    assert_eq!(names(&layers[0]), vec!["urn:a", "urn:b"]);
+    assert_eq!(names(&layers[1]), vec!["urn:c"]);
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_name_inference() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/ont1.ttl" => "ont1.ttl"});
+    std::fs::write(
+        dir.path().join("colors.ttl"),
+        "@prefix skos: <http://www.w3.org/2004/02/skos/core#> .\n\
+         <http://example.org/colors> a skos:ConceptScheme .\n\
+         <http://example.org/colors#red> a skos:Concept ;\n\
+         skos:inScheme <http://example.org/colors> .\n",
+    )?;
+    let colors = NamedNodeRef::new("http://example.org/colors")?;
+
+    // by default the vocabulary is anonymous
+    let mut env = OntoEnv::new(default_config(&dir), false)?;
+    env.update()?;
+    assert!(env.get_ontology_by_name(colors).is_none());
+
+    let mut cfg = default_config(&dir);
+    cfg.name_inference = vec![NameSource::VoidDataset, NameSource::SkosConceptScheme];
+    let mut env = OntoEnv::new(cfg, true)?;
+    env.update()?;
+    let ontology = env.get_ontology_by_name(colors).unwrap();
+    assert!(!ontology.is_anonymous());
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_doctor_import_cycles() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/rdftest/ontology1.ttl" => "ontology1.ttl",
+                  "fixtures/rdftest/ontology2.ttl" => "ontology2.ttl"});
+    // a -> b -> c -> a, and d imports itself
+    for (name, imports) in [
+        ("a", "urn:b"),
+        ("b", "urn:c"),
+        ("c", "urn:a"),
+        ("d", "urn:d"),
+    ] {
+        std::fs::write(
+            dir.path().join(format!("{}.ttl", name)),
+            format!(
+                "<urn:{}> a <http://www.w3.org/2002/07/owl#Ontology> ;\n\
+                 <http://www.w3.org/2002/07/owl#imports> <{}> .\n",
+                name, imports
+            ),
+        )?;
+    }
+    let mut env = OntoEnv::new(default_config(&dir), false)?;
+    env.update()?;
+    let cycles: Vec<_> = env
+        .problems()?
+        .into_iter()
+        .filter(|p| p.message.starts_with("Import cycle"))
+        .collect();
+    assert_eq!(cycles.len(), 2);
+    assert_eq!(
+        cycles[0].message,
+        "Import cycle: urn:a -> urn:b -> urn:c -> urn:a"
+    );
+    assert_eq!(cycles[0].locations.len(), 3);
+    assert!(cycles[0].locations[0].as_str().ends_with("a.ttl"));
+    assert_eq!(cycles[1].message, "Import cycle: urn:d -> urn:d");
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_scratch() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/rdftest/ontology1.ttl" => "ontology1.ttl",
+                  "fixtures/rdftest/ontology2.ttl" => "ontology2.ttl"});
+    let mut env = OntoEnv::scratch(default_config(&dir), false)?;
+    assert!(env.is_scratch());
+    assert_eq!(env.get_how_created(), HowCreated::New);
+    env.update()?;
+    env.save_to_directory()?;
+    let scratch_dir = env.ontoenv_dir();
+    assert!(!scratch_dir.starts_with(dir.path()));
+    assert!(scratch_dir.join("ontoenv.json").exists());
+    // nothing is written into the project tree
+    assert!(!dir.path().join(".ontoenv").exists());
+    assert!(!env.lockfile_path().exists());
+    drop(env);
+
+    // constructing it again reuses the environment
+    let env = OntoEnv::scratch(default_config(&dir), false)?;
+    assert_eq!(env.get_how_created(), HowCreated::SameConfig);
+    assert_eq!(env.ontoenv_dir(), scratch_dir);
+    assert_eq!(env.num_graphs(), 2);
+    drop(env);
+
+    let env = OntoEnv::scratch(default_config_ttl_only(&dir), false)?;
+    assert_eq!(env.get_how_created(), HowCreated::RecreatedDifferentConfig);
+    assert_eq!(env.num_graphs(), 0);
+    drop(env);
+    std::fs::remove_dir_all(scratch_dir)?;
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_remove() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/rdftest/ontology1.ttl" => "ontology1.ttl",
+                  "fixtures/rdftest/ontology2.ttl" => "ontology2.ttl",
+                  "fixtures/rdftest/ontology3.ttl" => "ontology3.ttl"});
+    let ont1 = NamedNodeRef::new("http://example.org/ontology1")?;
+    let ont2 = NamedNodeRef::new("http://example.org/ontology2")?;
+    let ont3 = NamedNodeRef::new("http://example.org/ontology3")?;
+    let mut env = OntoEnv::new(default_config(&dir), false)?;
+    env.update()?;
+    let id1 = env.get_ontology_by_name(ont1).unwrap().id().clone();
+
+    // ontology1 is still imported by ontology2 and ontology3
+    assert!(env.remove(&id1, RemoveMode::Error).is_err());
+    // leaving their imports missing is not allowed in strict mode
+    assert!(env.remove(&id1, RemoveMode::Detach).is_err());
+    assert_eq!(env.num_graphs(), 3);
+
+    // nothing imports ontology3
+    let id3 = env.get_ontology_by_name(ont3).unwrap().id().clone();
+    let report = env.remove(&id3, RemoveMode::Error)?;
+    assert_eq!(report.removed, vec![id3.clone()]);
+    assert!(report.newly_missing.is_empty());
+    assert!(env.get_graph(&id3)?.is_empty());
+
+    let report = env.remove(&id1, RemoveMode::Cascade)?;
+    assert_eq!(report.removed.len(), 2);
+    assert_eq!(env.num_graphs(), 0);
+
+    let mut cfg = default_config(&dir);
+    cfg.strict = false;
+    let mut env = OntoEnv::new(cfg, true)?;
+    env.update()?;
+    let id1 = env.get_ontology_by_name(ont1).unwrap().id().clone();
+    let report = env.remove(&id1, RemoveMode::Detach)?;
+    assert_eq!(report.removed, vec![id1]);
+    assert_eq!(report.newly_missing, vec![ont1.as_str().to_string()]);
+    assert!(env.get_ontology_by_name(ont2).is_some());
+    assert!(env.missing_imports().contains(&ont1.into_owned()));
+    teardown(dir);
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_ontoenv_daemon_requests() -> Result<()> {
+    use ontoenv::daemon;
+    use serde_json::json;
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/rdftest/ontology1.ttl" => "ontology1.ttl",
+                  "fixtures/rdftest/ontology2.ttl" => "ontology2.ttl"});
+    let mut env = OntoEnv::new(default_config(&dir), false)?;
+    // no daemon is running
+    assert!(daemon::Client::connect(dir.path())?.is_none());
+
+    let (response, shutdown) = daemon::handle_request(
+        &mut env,
+        &json!({"jsonrpc": "2.0", "id": 1, "method": "update", "params": {}}),
+    );
+    assert!(!shutdown);
+    let response = response.unwrap();
+    assert_eq!(response["result"]["added"].as_array().unwrap().len(), 2);
+
+    // methods of the JSON-RPC interface are answered too
+    let (response, _) = daemon::handle_request(
+        &mut env,
+        &json!({"jsonrpc": "2.0", "id": 2, "method": "closure",
+                "params": {"iri": "http://example.org/ontology2"}}),
+    );
+    assert_eq!(response.unwrap()["result"].as_array().unwrap().len(), 2);
+
+    let (response, _) = daemon::handle_request(
+        &mut env,
+        &json!({"jsonrpc": "2.0", "id": 3, "method": "get",
+                "params": {"iri": "http://example.org/ontology1"}}),
+    );
+    assert!(response.unwrap()["result"]
+        .as_str()
+        .unwrap()
+        .contains("<http://example.org/ontology1>"));
+
+    let (response, _) = daemon::handle_request(
+        &mut env,
+        &json!({"jsonrpc": "2.0", "id": 4, "method": "union",
+                "params": {"iri": "http://example.org/ontology2"}}),
+    );
+    let response = response.unwrap();
+    let graph = response["result"]["graph"].as_str().unwrap();
+    assert!(graph.contains("<http://example.org/ontology1>"));
+    assert!(graph.contains("<http://example.org/ontology2>"));
+
+    let (response, shutdown) = daemon::handle_request(
+        &mut env,
+        &json!({"jsonrpc": "2.0", "id": 5, "method": "shutdown"}),
+    );
+    assert!(shutdown);
+    assert_eq!(response.unwrap()["id"], 5);
+    teardown(dir);
+    Ok(())
+}
+
+#[cfg(unix)]
+#[test]
+fn test_ontoenv_daemon_serves_several_clients() -> Result<()> {
+    use ontoenv::daemon;
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/rdftest/ontology1.ttl" => "ontology1.ttl",
+                  "fixtures/rdftest/ontology2.ttl" => "ontology2.ttl"});
+    let mut env = OntoEnv::new(default_config(&dir), false)?;
+    env.update()?;
+    std::thread::scope(|scope| -> Result<()> {
+        let server = scope.spawn(|| daemon::serve(&mut env));
+        let mut first = None;
+        for _ in 0..100 {
+            first = daemon::Client::connect(dir.path())?;
+            if first.is_some() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(50));
+        }
+        let mut first = first.expect("the daemon did not start");
+        let mut second = daemon::Client::connect(dir.path())?.unwrap();
+        // holding on to the first client does not keep the second one waiting
+        assert_eq!(second.closure("http://example.org/ontology2")?.len(), 2);
+        let (name, _) = first.resolve("http://example.org/ontology1")?.unwrap();
+        assert_eq!(name, "http://example.org/ontology1");
+        let union = second.union("http://example.org/ontology2", None, None)?;
+        assert!(union.failed_imports.is_empty());
+        assert!(union.graph.len() > first.get_graph("http://example.org/ontology2")?.len());
+        first.shutdown()?;
+        server.join().unwrap()
+    })?;
+    assert!(daemon::Client::connect(dir.path())?.is_none());
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_profiles() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    for profile in ["dev", "release"] {
+        std::fs::create_dir_all(dir.path().join(profile))?;
+        std::fs::write(
+            dir.path().join(profile).join("shared.ttl"),
+            format!(
+                "<urn:shared> a <http://www.w3.org/2002/07/owl#Ontology> ;\n\
+                 <http://www.w3.org/2000/01/rdf-schema#label> \"{}\" .\n",
+                profile
+            ),
+        )?;
+    }
+    std::fs::write(
+        dir.path().join("release").join("app.ttl"),
+        "<urn:app> a <http://www.w3.org/2002/07/owl#Ontology> ;\n\
+         <http://www.w3.org/2002/07/owl#imports> <urn:shared> .\n",
+    )?;
+    let mut cfg = default_config(&dir);
+    for profile in ["dev=dir=dev", "release=dir=release;policy=latest"] {
+        let (name, profile) = Profile::from_str(profile)?;
+        cfg.profiles.insert(name, profile);
+    }
+    let mut env = OntoEnv::new(cfg, false)?;
+    env.update()?;
+    // all profiles share the store
+    assert_eq!(env.num_graphs(), 3);
+    assert!(env.set_profile(Some("staging")).is_err());
+
+    let shared = NamedNodeRef::new("urn:shared")?;
+    let in_profile = |env: &OntoEnv, profile: &str| {
+        env.get_ontology_by_name(shared)
+            .and_then(|o| o.location().cloned())
+            .is_some_and(|l| l.as_str().contains(profile))
+    };
+    env.set_profile(Some("release"))?;
+    assert!(in_profile(&env, "release"));
+    let app = env
+        .get_ontology_by_name(NamedNodeRef::new("urn:app")?)
+        .unwrap();
+    let closure = env.get_dependency_closure(app.id())?;
+    assert_eq!(closure.len(), 2);
+    assert!(closure
+        .iter()
+        .any(|id| id.location().as_str().contains("release") && id.name() == shared));
+
+    env.set_profile(Some("dev"))?;
+    assert!(in_profile(&env, "dev"));
+    // the app is not part of the dev profile
+    assert!(env
+        .get_ontology_by_name(NamedNodeRef::new("urn:app")?)
+        .is_none());
+    env.save_to_directory()?;
+    drop(env);
+
+    // the profile is saved with the environment
+    let env = OntoEnv::from_file(&dir.path().join(".ontoenv/ontoenv.json"), true)?;
+    assert_eq!(env.profile(), Some("dev"));
+    assert!(in_profile(&env, "dev"));
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_closure_prefixes() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    std::fs::write(
+        dir.path().join("app.ttl"),
+        "@prefix owl: <http://www.w3.org/2002/07/owl#> .\n\
+         @prefix ex: <http://example.org/app#> .\n\
+         @prefix shared: <http://example.org/shared#> .\n\
+         <urn:app> a owl:Ontology ; owl:imports <urn:lib> .\n\
+         ex:Thing a owl:Class ; ex:uses shared:Part .\n",
+    )?;
+    std::fs::write(
+        dir.path().join("lib.ttl"),
+        "@prefix owl: <http://www.w3.org/2002/07/owl#> .\n\
+         @prefix ex: <http://example.org/lib#> .\n\
+         @prefix lib: <http://example.org/lib#> .\n\
+         @prefix common: <http://example.org/shared#> .\n\
+         <urn:lib> a owl:Ontology .\n\
+         ex:Part a owl:Class ; ex:sameAs common:Part .\n",
+    )?;
+    let mut env = OntoEnv::new(default_config(&dir), false)?;
+    env.update()?;
+    let app = env
+        .get_ontology_by_name(NamedNodeRef::new("urn:app")?)
+        .unwrap();
+    let closure = env.get_dependency_closure(app.id())?;
+    let prefixes = env.closure_prefixes(&closure);
+    // the importing ontology's bindings win, and each namespace keeps a single prefix
+    assert_eq!(prefixes["ex"], "http://example.org/app#");
+    assert_eq!(prefixes["lib"], "http://example.org/lib#");
+    assert_eq!(prefixes["shared"], "http://example.org/shared#");
+    assert!(!prefixes.contains_key("common"));
+    assert_eq!(prefixes["owl"], "http://www.w3.org/2002/07/owl#");
+
+    let (union, _, _) = env.get_union_graph(&closure, None, None)?;
+    let out = dir.path().join("closure.ttl");
+    write_dataset_to_file_with(
+        &union,
+        out.to_str().unwrap(),
+        &SerializerOptions::with_prefixes(prefixes),
+    )?;
+    let written = std::fs::read_to_string(&out)?;
+    assert!(written.contains("@prefix ex: <http://example.org/app#> ."));
+    assert!(written.contains("@prefix lib: <http://example.org/lib#> ."));
+    assert!(written.contains("lib:Part"));
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_resolution_report() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    std::fs::write(
+        dir.path().join("app.ttl"),
+        "@prefix owl: <http://www.w3.org/2002/07/owl#> .\n\
+         <urn:app> a owl:Ontology ; owl:imports <urn:lib>, <urn:missing> .\n",
+    )?;
+    std::fs::write(
+        dir.path().join("lib.ttl"),
+        "@prefix owl: <http://www.w3.org/2002/07/owl#> .\n\
+         <urn:lib> a owl:Ontology ; owl:versionIRI <urn:lib:1.0> .\n",
+    )?;
+    let mut cfg = default_config(&dir);
+    cfg.strict = false;
+    let mut env = OntoEnv::new(cfg, false)?;
+    env.update()?;
+    let app = env
+        .get_ontology_by_name(NamedNodeRef::new("urn:app")?)
+        .unwrap();
+    let closure = env.get_dependency_closure(app.id())?;
+    let report = env.resolution_report(&closure)?;
+    assert_eq!(report.root, "urn:app");
+    assert_eq!(report.environment_fingerprint, env.fingerprint());
+    assert_eq!(report.ontologies.len(), 2);
+    let lib = report
+        .ontologies
+        .iter()
+        .find(|o| o.name == "urn:lib")
+        .unwrap();
+    assert_eq!(lib.version_iri.as_deref(), Some("urn:lib:1.0"));
+    assert!(lib.location.as_ref().unwrap().ends_with("lib.ttl"));
+    assert_eq!(
+        report.unresolved,
+        vec![UnresolvedImport {
+            importer: "urn:app".to_string(),
+            import: "urn:missing".to_string(),
+        }]
+    );
+
+    // the JSON sidecar round-trips
+    let path = resolution::sidecar_path(&dir.path().join("output.ttl"));
+    assert!(path.ends_with("output.resolution.json"));
+    report.save(&path)?;
+    assert_eq!(ResolutionReport::load(&path)?, report);
+
+    // and the named graph records the same resolution
+    let (mut dataset, _, _) = env.get_union_dataset(&closure, None, None)?;
+    report.add_to_dataset(&mut dataset)?;
+    let graph_name = GraphNameRef::NamedNode(resolution::REPORT_GRAPH);
+    let version = NamedNodeRef::new("http://www.w3.org/2002/07/owl#versionIRI")?;
+    let lib_version = NamedNodeRef::new("urn:lib:1.0")?;
+    assert!(dataset
+        .quads_for_graph_name(graph_name)
+        .any(|q| q.predicate == version && q.object == TermRef::NamedNode(lib_version)));
+
+    // the fingerprint changes with the environment
+    std::fs::write(
+        dir.path().join("other.ttl"),
+        "<urn:other> a <http://www.w3.org/2002/07/owl#Ontology> .\n",
+    )?;
+    env.update()?;
+    assert_ne!(env.fingerprint(), report.environment_fingerprint);
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_prefix_registry() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/rdftest/ontology1.ttl" => "ontology1.ttl",
+                  "fixtures/rdftest/ontology2.ttl" => "ontology2.ttl"});
+    let mut env = OntoEnv::new(default_config(&dir), false)?;
+    env.update()?;
+    let registry = env.prefixes();
+    assert_eq!(registry.namespace("ex"), Some("http://example.org#"));
+    assert_eq!(
+        registry.namespace("owl"),
+        Some("http://www.w3.org/2002/07/owl#")
+    );
+    assert_eq!(
+        env.expand("ex:Thing")?,
+        NamedNode::new("http://example.org#Thing")?
+    );
+    assert!(env.expand("nope:Thing").is_err());
+    assert_eq!(
+        env.shrink(NamedNodeRef::new("http://www.w3.org/2002/07/owl#Class")?)
+            .as_deref(),
+        Some("owl:Class")
+    );
+    assert_eq!(env.shrink(NamedNodeRef::new("urn:unknown")?), None);
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_strict_rules() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    std::fs::write(
+        dir.path().join("app.ttl"),
+        "<urn:app> a <http://www.w3.org/2002/07/owl#Ontology> ;\n\
+         <http://www.w3.org/2002/07/owl#imports> <http://example.org/unreachable> .\n",
+    )?;
+    // strict mode makes the unreachable import fatal
+    let cfg = default_config(&dir);
+    assert!(cfg.is_strict(StrictRule::Network));
+    assert!(!cfg.is_strict(StrictRule::Duplicates));
+    let mut env = OntoEnv::new(cfg.clone(), true)?;
+    assert!(env.update().is_err());
+    drop(env);
+
+    // unless network errors and missing imports are relaxed on their own
+    let mut cfg = default_config(&dir);
+    cfg.set_strict_str("network=false")?;
+    cfg.set_strict(StrictRule::MissingImports, false);
+    assert!(cfg.is_strict(StrictRule::Parse));
+    let mut env = OntoEnv::new(cfg, true)?;
+    env.update()?;
+    assert_eq!(env.missing_imports().len(), 1);
+    drop(env);
+
+    // duplicate names are only rejected on request
+    for file in ["first.ttl", "second.ttl"] {
+        std::fs::write(
+            dir.path().join(file),
+            "<urn:dup> a <http://www.w3.org/2002/07/owl#Ontology> .\n",
+        )?;
+    }
+    let mut cfg = default_config(&dir);
+    cfg.strict = false;
+    let mut env = OntoEnv::new(cfg.clone(), true)?;
+    env.update()?;
+    assert_eq!(env.num_graphs(), 3);
+    drop(env);
+    cfg.set_strict(StrictRule::Duplicates, true);
+    let mut env = OntoEnv::new(cfg, true)?;
+    let report = env.update()?;
+    assert_eq!(env.num_graphs(), 2);
+    assert_eq!(report.failed.len(), 1);
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_strict_constraints() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    write_ontology(
+        &dir,
+        "app.ttl",
+        "<urn:app> a owl:Ontology ; owl:imports <urn:lib> .",
+    )?;
+    write_ontology(&dir, "lib.ttl", "<urn:lib> a owl:Ontology .")?;
+    let mut cfg = default_config(&dir);
+    cfg.import_constraints = vec![ImportConstraint::from_str("app.ttl=urn:lib")?];
+    // strict mode makes the violated import constraint fatal
+    assert!(cfg.is_strict(StrictRule::Constraints));
+    let mut env = OntoEnv::new(cfg.clone(), true)?;
+    assert!(env.update().is_err());
+    drop(env);
+
+    // unless constraints are relaxed on their own
+    cfg.set_strict_str("constraints=false")?;
+    assert!(cfg.is_strict(StrictRule::Parse));
+    let mut env = OntoEnv::new(cfg, true)?;
+    env.update()?;
+    assert_eq!(env.check_import_constraints()?.len(), 1);
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_jsonld_context() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    std::fs::write(
+        dir.path().join("app.ttl"),
+        "@prefix owl: <http://www.w3.org/2002/07/owl#> .\n\
+         @prefix app: <http://example.org/app#> .\n\
+         <urn:app> a owl:Ontology ; owl:imports <urn:lib> .\n\
+         app:Building a owl:Class .\n\
+         app:hasPart a owl:ObjectProperty .\n",
+    )?;
+    std::fs::write(
+        dir.path().join("lib.ttl"),
+        "@prefix owl: <http://www.w3.org/2002/07/owl#> .\n\
+         @prefix rdfs: <http://www.w3.org/2000/01/rdf-schema#> .\n\
+         @prefix xsd: <http://www.w3.org/2001/XMLSchema#> .\n\
+         @prefix lib: <http://example.org/lib#> .\n\
+         <urn:lib> a owl:Ontology .\n\
+         lib:Room a owl:Class .\n\
+         lib:area a owl:DatatypeProperty ; rdfs:range xsd:decimal .\n",
+    )?;
+    let mut env = OntoEnv::new(default_config(&dir), false)?;
+    env.update()?;
+    let app = env
+        .get_ontology_by_name(NamedNodeRef::new("urn:app")?)
+        .unwrap();
+    let closure = env.get_dependency_closure(app.id())?;
+    let context = env.jsonld_context(&closure)?;
+    let context = &context["@context"];
+    assert_eq!(context["app"], "http://example.org/app#");
+    assert_eq!(context["lib"], "http://example.org/lib#");
+    assert_eq!(context["Building"], "app:Building");
+    assert_eq!(context["Room"], "lib:Room");
+    assert_eq!(context["hasPart"]["@type"], "@id");
+    assert_eq!(context["area"]["@id"], "lib:area");
+    assert_eq!(context["area"]["@type"], "xsd:decimal");
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_environment_report() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    std::fs::write(
+        dir.path().join("app.ttl"),
+        "@prefix owl: <http://www.w3.org/2002/07/owl#> .\n\
+         <urn:app> a owl:Ontology ; owl:versionIRI <urn:app/1.0> ;\n\
+         owl:imports <urn:lib>, <urn:missing> .\n",
+    )?;
+    std::fs::write(
+        dir.path().join("lib.ttl"),
+        "@prefix owl: <http://www.w3.org/2002/07/owl#> .\n\
+         <urn:lib> a owl:Ontology .\n",
+    )?;
+    let mut cfg = default_config(&dir);
+    cfg.strict = false;
+    let mut env = OntoEnv::new(cfg, false)?;
+    env.update()?;
+
+    let report = report::build(&env)?;
+    assert_eq!(report.ontologies.len(), 2);
+    let app = report
+        .ontologies
+        .iter()
+        .find(|row| row.name == "urn:app")
+        .unwrap();
+    assert_eq!(app.version_iri.as_deref(), Some("urn:app/1.0"));
+    assert_eq!(app.imports, 2);
+    let missing: Vec<&str> = report.missing.iter().map(|m| m.import.as_str()).collect();
+    assert_eq!(missing, vec!["urn:missing"]);
+    assert!(report
+        .edges
+        .contains(&("urn:app".to_string(), "urn:lib".to_string(), true)));
+    assert!(report
+        .edges
+        .contains(&("urn:app".to_string(), "urn:missing".to_string(), false)));
+
+    let markdown = report.render(DocFormat::Markdown);
+    assert!(markdown.contains("| `urn:app` | `urn:app/1.0` |"));
+    assert!(markdown.contains("```mermaid\ngraph TD\n"));
+    assert!(markdown.contains("- `urn:missing`"));
+    let html = report.render(DocFormat::Html);
+    assert!(html.contains("<pre class=\"mermaid\">"));
+    assert!(html.contains("<td><code>urn:lib</code></td>"));
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_load_snapshot() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    let file = dir.path().join("app.ttl");
+    let ontology = "@prefix owl: <http://www.w3.org/2002/07/owl#> .\n\
+                    <urn:app> a owl:Ontology .\n";
+    std::fs::write(&file, format!("{}<urn:app#A> a owl:Class .\n", ontology))?;
+    let mut cfg = default_config(&dir);
+    cfg.snapshot_graphs = true;
+    let mut env = OntoEnv::new(cfg, false)?;
+    env.update()?;
+    let first = env.snapshots()?.last().unwrap().id.clone();
+
+    std::fs::write(&file, format!("{}<urn:app#B> a owl:Class .\n", ontology))?;
+    env.update_all(true)?;
+    let snapshots = env.snapshots()?;
+    assert_eq!(snapshots.len(), 2);
+    assert_eq!(snapshots[0].id, first);
+
+    // the file changed, but the snapshot still has the content of the first update
+    let a_is_class = TripleRef::new(
+        NamedNodeRef::new("urn:app#A")?,
+        ontoenv::consts::TYPE,
+        ontoenv::consts::CLASS,
+    );
+    let view = env.load_snapshot(&first[..8])?;
+    let app = view
+        .get_ontology_by_name(NamedNodeRef::new("urn:app")?)
+        .unwrap();
+    let graph = view.get_graph(app.id())?;
+    assert!(graph.contains(a_is_class));
+    assert!(view.save_to_directory().is_err());
+
+    // a date selects the latest snapshot of that day
+    let today = snapshots[1].timestamp.format("%Y-%m-%d").to_string();
+    let latest = env.load_snapshot(&today)?;
+    let app = latest
+        .get_ontology_by_name(NamedNodeRef::new("urn:app")?)
+        .unwrap();
+    assert!(!latest.get_graph(app.id())?.contains(a_is_class));
+    assert!(env.load_snapshot("2000-01-01").is_err());
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_snapshot_graphs_retention() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    let app = |class: &str| format!("<urn:app> a owl:Ontology . <urn:app#{class}> a owl:Class .");
+    write_ontology(&dir, "app.ttl", &app("A"))?;
+    let mut cfg = default_config(&dir);
+    cfg.limits.max_cache_size = Some(0);
+    let mut env = OntoEnv::new(cfg, false)?;
+    env.update()?;
+    let first = env.snapshots()?.last().unwrap().id.clone();
+    // updates record only which ontologies the environment held
+    let graphs = dir.path().join(".ontoenv/snapshots/graphs");
+    assert!(!graphs.exists());
+
+    // so their snapshots cannot be loaded once the ontologies changed
+    write_ontology(&dir, "app.ttl", &app("B"))?;
+    env.update_all(true)?;
+    assert!(env.load_snapshot(&first[..8]).is_err());
+
+    // tagged snapshots keep their graphs, even beyond max_cache_size
+    env.snapshot("before")?;
+    assert_eq!(std::fs::read_dir(&graphs)?.count(), 1);
+    write_ontology(&dir, "app.ttl", &app("C"))?;
+    env.update_all(true)?;
+    assert!(env.load_snapshot("before").is_ok());
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_export_legacy_manifest() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    std::fs::write(
+        dir.path().join("app.ttl"),
+        "@prefix owl: <http://www.w3.org/2002/07/owl#> .\n\
+         <urn:app> a owl:Ontology ; owl:imports <urn:lib> .\n",
+    )?;
+    std::fs::write(
+        dir.path().join("lib.ttl"),
+        "@prefix owl: <http://www.w3.org/2002/07/owl#> .\n\
+         <urn:lib> a owl:Ontology .\n",
+    )?;
+    let mut env = OntoEnv::new(default_config(&dir), false)?;
+    env.update()?;
+
+    let listing = env.export_legacy_manifest();
+    let names: Vec<&str> = listing.iter().map(|o| o.name.as_str()).collect();
+    assert_eq!(names, vec!["urn:app", "urn:lib"]);
+    assert_eq!(listing[0].imports, vec!["urn:lib".to_string()]);
+    assert!(listing[0].location.ends_with("app.ttl"));
+    assert!(listing[0].last_updated.is_some());
+
+    let path = dir.path().join(legacy::LEGACY_MANIFEST_NAME);
+    legacy::write_legacy_manifest(&path, &listing)?;
+    let json: Value = serde_json::from_str(&std::fs::read_to_string(&path)?)?;
+    assert_eq!(json[1]["name"], "urn:lib");
+    assert_eq!(json[1]["imports"], serde_json::json!([]));
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_update_skips_unchanged_content() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    let file = dir.path().join("app.ttl");
+    let content = "@prefix owl: <http://www.w3.org/2002/07/owl#> .\n\
+                   <urn:app> a owl:Ontology .\n";
+    std::fs::write(&file, content)?;
+    let mut env = OntoEnv::new(default_config(&dir), false)?;
+    env.update()?;
+    let app = NamedNodeRef::new("urn:app")?;
+    assert!(env
+        .get_ontology_by_name(app)
+        .unwrap()
+        .source_hash()
+        .is_some());
+
+    // writing the same content again moves the modification time, but the file is not read
+    std::fs::write(&file, content)?;
+    let report = env.update()?;
+    assert!(report.updated.is_empty());
+
+    std::fs::write(&file, format!("{}<urn:app#A> a owl:Class .\n", content))?;
+    let report = env.refresh_matching("*/app.ttl", RefreshStrategy::HashOnly)?;
+    assert_eq!(report.updated.len(), 1);
+    let report = env.refresh_matching("*/app.ttl", RefreshStrategy::HashOnly)?;
+    assert!(report.updated.is_empty());
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_conditional_fetch() -> Result<()> {
+    use std::io::{BufRead, BufReader, Write};
+    let dir = TempDir::new("ontoenv")?;
+    // a server which sends the ontology with an ETag, then answers that it did not change.
+    // Returns the If-None-Match header of every request
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    let url = format!("http://{}/lib.ttl", listener.local_addr()?);
+    let body = format!(
+        "@prefix owl: <http://www.w3.org/2002/07/owl#> .\n<{}> a owl:Ontology .\n",
+        url
+    );
+    let server = std::thread::spawn(move || {
+        let mut etags = vec![];
+        for stream in listener.incoming().take(2) {
+            let mut stream = stream.unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut etag = None;
+            loop {
+                let mut header = String::new();
+                reader.read_line(&mut header).unwrap();
+                if header.trim().is_empty() {
+                    break;
+                }
+                if let Some(v) = header.to_lowercase().strip_prefix("if-none-match:") {
+                    etag = Some(v.trim().to_string());
+                }
+            }
+            let response = match etag {
+                Some(_) => "HTTP/1.1 304 Not Modified\r\nConnection: close\r\n\r\n".to_string(),
+                None => format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/turtle\r\nETag: \"v1\"\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                ),
+            };
+            stream.write_all(response.as_bytes()).unwrap();
+            etags.push(etag);
+        }
+        etags
+    });
+    std::fs::write(
+        dir.path().join("app.ttl"),
+        format!(
+            "@prefix owl: <http://www.w3.org/2002/07/owl#> .\n\
+             <urn:app> a owl:Ontology ; owl:imports <{}> .\n",
+            url
+        ),
+    )?;
+    let mut cfg = default_config(&dir);
+    cfg.offline = false;
+    let mut env = OntoEnv::new(cfg, false)?;
+    env.update()?;
+    let lib = NamedNode::new(&url)?;
+    let validators = env
+        .get_ontology_by_name(lib.as_ref())
+        .unwrap()
+        .cache_validators()
+        .cloned()
+        .unwrap();
+    assert_eq!(validators.etag.as_deref(), Some("\"v1\""));
+
+    // fetched just now, so it is not stale yet
+    let plan = env.plan_update_with_max_age(false, Some(3600))?;
+    assert!(plan.to_fetch.is_empty());
+    let plan = env.plan_update_with_max_age(false, Some(0))?;
+    assert_eq!(plan.to_fetch.len(), 1);
+    let report = env.update_with_max_age(0)?;
+    assert!(report.is_empty());
+    let etags = server.join().unwrap();
+    assert_eq!(etags, vec![None, Some("\"v1\"".to_string())]);
+    assert!(env.get_ontology_by_name(lib.as_ref()).is_some());
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_term_usage() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/terms/vocab.ttl" => "vocab.ttl",
+                  "fixtures/terms/extension.ttl" => "extension.ttl"});
+    std::fs::write(
+        dir.path().join("plant.ttl"),
+        "@prefix owl: <http://www.w3.org/2002/07/owl#> .\n\
+         @prefix vocab: <http://example.org/vocab#> .\n\
+         <urn:plant> a owl:Ontology .\n\
+         <urn:plant#p1> a vocab:Pump ; vocab:feeds <urn:plant#p2> .\n",
+    )?;
+    let mut env = OntoEnv::new(default_config(&dir), false)?;
+    env.update()?;
+
+    let pump = NamedNodeRef::new("http://example.org/vocab#Pump")?;
+    let usage = env.term_usage(pump)?;
+    assert_eq!(usage.triples, 3);
+    assert_eq!((usage.as_subject, usage.as_object), (2, 1));
+    let names: Vec<&str> = usage
+        .graphs
+        .iter()
+        .map(|g| g.ontology.name().as_str())
+        .collect();
+    assert_eq!(
+        names,
+        vec![
+            "http://example.org/extension",
+            "http://example.org/vocab",
+            "urn:plant"
+        ]
+    );
+
+    let feeds = NamedNodeRef::new("http://example.org/vocab#feeds")?;
+    let usage = env.term_usage(feeds)?;
+    assert_eq!(usage.triples, 2);
+    assert_eq!((usage.as_subject, usage.as_predicate), (1, 1));
+
+    let unknown = NamedNodeRef::new("http://example.org/vocab#Unknown")?;
+    let usage = env.term_usage(unknown)?;
+    assert_eq!(usage.triples, 0);
+    assert!(usage.graphs.is_empty());
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_import_map() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    std::fs::write(
+        dir.path().join("app.ttl"),
+        "@prefix owl: <http://www.w3.org/2002/07/owl#> .\n\
+         <urn:app> a owl:Ontology ; owl:imports <http://example.org/vocab/2.1> .\n",
+    )?;
+    // the pinned copy declares another name, and the search does not include it
+    let vendor = dir.path().join("vendor");
+    std::fs::create_dir(&vendor)?;
+    std::fs::write(
+        vendor.join("vocab.n3"),
+        "@prefix owl: <http://www.w3.org/2002/07/owl#> .\n\
+         <http://example.org/vocab> a owl:Ontology .\n\
+         <http://example.org/vocab#Pump> a owl:Class .\n",
+    )?;
+    let mut cfg = default_config(&dir);
+    cfg.add_import_mapping_str("http://example.org/vocab/2.1=vendor/vocab.n3")?;
+    let mut env = OntoEnv::new(cfg, false)?;
+    env.update()?;
+
+    let vocab = NamedNodeRef::new("http://example.org/vocab/2.1")?;
+    let ontology = env.get_ontology_by_name(vocab).unwrap();
+    assert!(ontology.id().location().as_str().ends_with("vocab.n3"));
+    assert!(env.missing_imports().is_empty());
+    let app = env
+        .get_ontology_by_name(NamedNodeRef::new("urn:app")?)
+        .unwrap()
+        .id()
+        .clone();
+    let closure = env.get_dependency_closure(&app)?;
+    assert_eq!(closure.len(), 2);
+    assert_eq!(closure[1].name(), vocab);
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_restrict_to_root() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    write_ontology(
+        &dir,
+        "app.ttl",
+        "<urn:app> a owl:Ontology ; owl:imports <urn:lib> .",
+    )?;
+    write_ontology(&dir, "lib.ttl", "<urn:lib> a owl:Ontology .")?;
+    write_ontology(&dir, "other.ttl", "<urn:other> a owl:Ontology .")?;
+    let mut env = OntoEnv::new(default_config(&dir), false)?;
+    env.update()?;
+    assert_eq!(env.ontologies().len(), 3);
+
+    let location = OntologyLocation::File(dir.path().join("app.ttl"));
+    let removed = env.restrict_to_root(location)?;
+    assert_eq!(removed.len(), 1);
+    assert_eq!(removed[0].name().as_str(), "urn:other");
+    assert_eq!(env.ontologies().len(), 2);
+
+    // new files are not picked up, and ontologies which are no longer imported are dropped
+    write_ontology(&dir, "new.ttl", "<urn:new> a owl:Ontology .")?;
+    write_ontology(&dir, "app.ttl", "<urn:app> a owl:Ontology .")?;
+    env.update()?;
+    let names: Vec<&str> = env
+        .ontologies()
+        .keys()
+        .map(|id| id.name().as_str())
+        .collect();
+    assert_eq!(names, vec!["urn:app"]);
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_merge_duplicates() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    write_ontology(
+        &dir,
+        "app.ttl",
+        "<urn:app> a owl:Ontology ; owl:imports <urn:lib> .",
+    )?;
+    write_ontology(
+        &dir,
+        "lib-classes.ttl",
+        "<urn:lib> a owl:Ontology . <urn:lib#Pump> a owl:Class .",
+    )?;
+    write_ontology(
+        &dir,
+        "lib-properties.ttl",
+        "<urn:lib> a owl:Ontology . <urn:lib#feeds> a owl:ObjectProperty .",
+    )?;
+    let mut cfg = default_config(&dir);
+    cfg.strict_duplicates = Some(true);
+    cfg.duplicate_strategy = DuplicateStrategy::Merge;
+    let mut env = OntoEnv::new(cfg, false)?;
+    env.update()?;
+    assert_eq!(env.ontologies().len(), 3);
+
+    let app = env
+        .get_ontology_by_name(NamedNodeRef::new("urn:app")?)
+        .unwrap()
+        .id()
+        .clone();
+    let closure = env.get_dependency_closure(&app)?;
+    let lib = NamedNodeRef::new("urn:lib")?;
+    assert_eq!(closure.len(), 3);
+    assert_eq!(closure[1].name(), lib);
+    assert_eq!(closure[2].name(), lib);
+
+    let (union, _, _) = env.get_union_graph(&closure, None, None)?;
+    for term in ["urn:lib#Pump", "urn:lib#feeds"] {
+        let term = NamedNodeRef::new(term)?;
+        assert_eq!(union.quads_for_subject(term).count(), 1);
+    }
+    let derived_from = NamedNodeRef::new("http://www.w3.org/ns/prov#wasDerivedFrom")?;
+    let locations: HashSet<String> = union
+        .quads_for_subject(lib)
+        .filter(|quad| quad.predicate == derived_from)
+        .map(|quad| quad.object.to_string())
+        .collect();
+    assert_eq!(locations.len(), 2);
+    assert!(locations.iter().any(|l| l.contains("lib-classes.ttl")));
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_add_catalog() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/rdftest/ontology1.ttl" => "vendor/ontology1.ttl",
+                  "fixtures/rdftest/ontology2.ttl" => "vendor/ontology2.ttl",
+                  "fixtures/rdftest/ontology3.ttl" => "vendor/ontology3.ttl"});
+    std::fs::write(
+        dir.path().join("vendor/catalog.ttl"),
+        "@prefix dcat: <http://www.w3.org/ns/dcat#> .\n\
+         @prefix void: <http://rdfs.org/ns/void#> .\n\
+         <http://example.org/ontology1> a dcat:Dataset ;\n\
+             dcat:distribution [ dcat:downloadURL <ontology1.ttl> ] .\n\
+         <http://example.org/ontology2> a void:Dataset ; void:dataDump <ontology2.ttl> .\n",
+    )?;
+    std::fs::write(
+        dir.path().join("vendor/manifest.csv"),
+        "iri,location\nhttp://example.org/ontology3,ontology3.ttl\n",
+    )?;
+    std::fs::write(
+        dir.path().join("vendor/manifest.yaml"),
+        "---\n\"http://example.org/ontology1\": ontology1.ttl\n",
+    )?;
+
+    let catalog = OntologyLocation::File(dir.path().join("vendor/catalog.ttl"));
+    let entries = ontoenv::catalog::read_inventory(&catalog)?;
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].name, "http://example.org/ontology1");
+    assert!(entries[0].location.as_str().ends_with("ontology1.ttl"));
+    let yaml = OntologyLocation::File(dir.path().join("vendor/manifest.yaml"));
+    assert_eq!(ontoenv::catalog::read_inventory(&yaml)?, entries[..1]);
+
+    let mut cfg = default_config(&dir);
+    cfg.search_directories = vec![];
+    let mut env = OntoEnv::new(cfg, false)?;
+    let ids = env.add_catalog(catalog)?;
+    assert_eq!(ids.len(), 2);
+    let manifest = OntologyLocation::File(dir.path().join("vendor/manifest.csv"));
+    let ids = env.add_catalog(manifest)?;
+    assert_eq!(ids.len(), 1);
+    assert_eq!(ids[0].name().as_str(), "http://example.org/ontology3");
+    assert_eq!(env.num_graphs(), 3);
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_traversal_predicates() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    write_ontology(
+        &dir,
+        "profile.ttl",
+        "<urn:profile> a owl:Ontology ; dcterms:requires <urn:vocab> ; rdfs:seeAlso <urn:notes> .",
+    )?;
+    write_ontology(&dir, "vocab.ttl", "<urn:vocab> a owl:Ontology .")?;
+    write_ontology(&dir, "notes.ttl", "<urn:notes> a owl:Ontology .")?;
+    let mut cfg = default_config(&dir);
+    cfg.add_traversal_predicate("dcterms:requires")?;
+    assert_eq!(
+        cfg.traversal_predicates,
+        vec!["http://purl.org/dc/terms/requires".to_string()]
+    );
+    let mut env = OntoEnv::new(cfg, false)?;
+    env.update()?;
+
+    let profile = env
+        .get_ontology_by_name(NamedNodeRef::new("urn:profile")?)
+        .unwrap()
+        .id()
+        .clone();
+    let closure = env.get_dependency_closure(&profile)?;
+    let names: Vec<&str> = closure.iter().map(|id| id.name().as_str()).collect();
+    assert_eq!(names, vec!["urn:profile", "urn:vocab"]);
+
+    let see_also = ontoenv::config::parse_predicate("rdfs:seeAlso")?;
+    let closure = env.get_closure_following(&profile, &[], &[], &[see_also.as_ref()])?;
+    let names: Vec<&str> = closure.iter().map(|id| id.name().as_str()).collect();
+    assert_eq!(names, vec!["urn:profile", "urn:notes", "urn:vocab"]);
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_resource_limits() -> Result<()> {
+    let limits = ResourceLimits {
+        max_memory: Some(10_000),
+        ..Default::default()
+    };
+    assert_eq!(limits.read_workers(8, 100), 8);
+    assert_eq!(limits.read_workers(8, 200), 5);
+    assert_eq!(limits.read_workers(8, 1_000_000), 1);
+    assert_eq!(ResourceLimits::default().read_workers(8, 1_000_000), 8);
+
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/rdftest/ontology1.ttl" => "ontology1.ttl",
+                  "fixtures/rdftest/ontology2.ttl" => "ontology2.ttl",
+                  "fixtures/rdftest/ontology3.ttl" => "ontology3.ttl"});
+    let mut cfg = default_config(&dir);
+    cfg.limits = ResourceLimits {
+        max_memory: Some(1),
+        memory_cache_unions: Some(0),
+        max_downloads: Some(1),
+        max_cache_size: Some(1),
+    };
+    let mut env = OntoEnv::new(cfg, false)?;
+    env.update()?;
+    assert_eq!(env.num_graphs(), 3);
+
+    // unions are neither kept in memory nor on disk
+    let ont3 = NamedNodeRef::new("http://example.org/ontology3")?;
+    let id = env.get_ontology_by_name(ont3).unwrap().id().clone();
+    let closure = env.get_dependency_closure(&id)?;
+    let (union, _, cached) = env.get_cached_union_graph(&closure, None, None, false)?;
+    assert!(!cached && !union.is_empty());
+    let (_, _, cached) = env.get_cached_union_graph(&closure, None, None, false)?;
+    assert!(!cached);
+    teardown(dir);
+    Ok(())
+}
+
+#[derive(Default)]
+struct ProgressListener {
+    events: Mutex<Vec<Progress>>,
+}
+
+impl EnvironmentListener for ProgressListener {
+    fn on_progress(&self, progress: &Progress) {
+        self.events.lock().unwrap().push(progress.clone());
+    }
+}
+
+#[test]
+fn test_ontoenv_progress() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/rdftest/ontology1.ttl" => "ontology1.ttl",
+                  "fixtures/rdftest/ontology2.ttl" => "ontology2.ttl",
+                  "fixtures/rdftest/ontology3.ttl" => "ontology3.ttl"});
+    let cfg = default_config(&dir);
+    let mut env = OntoEnv::new(cfg, false)?;
+    let listener = Arc::new(ProgressListener::default());
+    env.add_listener(listener.clone());
+    env.update()?;
+    let events = std::mem::take(&mut *listener.events.lock().unwrap());
+    let reads: Vec<&Progress> = events.iter().filter(|e| e.phase == Phase::Read).collect();
+    assert_eq!(reads.len(), 3);
+    assert_eq!((reads[2].current, reads[2].total), (3, 3));
+    assert_eq!(reads[2].percent, 100.0);
+    assert!(events.iter().any(|e| e.phase == Phase::Resolve));
+
+    let ont3 = NamedNodeRef::new("http://example.org/ontology3")?;
+    let id = env.get_ontology_by_name(ont3).unwrap().id().clone();
+    let closure = env.get_dependency_closure(&id)?;
+    env.get_union_graph(&closure, None, None)?;
+    let events = std::mem::take(&mut *listener.events.lock().unwrap());
+    let closures = events.iter().filter(|e| e.phase == Phase::Closure).count();
+    assert_eq!(closures, 3);
+    let last = events.last().unwrap();
+    assert_eq!(last.phase, Phase::Union);
+    assert_eq!((last.current, last.total), (3, 3));
+    let json = serde_json::to_value(last)?;
+    assert_eq!(json["phase"], "union");
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_version_constraints() -> Result<()> {
+    let requirement: VersionRequirement = ">=1.3, <2".parse()?;
+    assert!(requirement.matches("1.3"));
+    assert!(requirement.matches("v1.4.1"));
+    assert!(!requirement.matches("1.2.9"));
+    assert!(!requirement.matches("2.0"));
+    assert_eq!(requirement.to_string(), ">=1.3, <2");
+
+    let dir = TempDir::new("ontoenv")?;
+    write_ontology(
+        &dir,
+        "lib.ttl",
+        "<urn:lib> a owl:Ontology ; owl:versionInfo \"1.2\" .",
+    )?;
+    write_ontology(
+        &dir,
+        "vocab.ttl",
+        "<urn:vocab> a owl:Ontology ; owl:versionIRI <http://example.org/vocab/2.1/> .",
+    )?;
+    write_ontology(
+        &dir,
+        "app.ttl",
+        "<urn:app> a owl:Ontology ; owl:imports <urn:lib> .",
+    )?;
+    let mut cfg = default_config(&dir);
+    cfg.add_version_constraint_str("urn:lib>=1.3")?;
+    cfg.add_version_constraint("urn:vocab", ">=2")?;
+    assert!(cfg.add_version_constraint("urn:app", ">=").is_err());
+    let mut env = OntoEnv::new(cfg.clone(), false)?;
+    env.update()?;
+
+    let vocab = env
+        .get_ontology_by_name(NamedNodeRef::new("urn:vocab")?)
+        .unwrap();
+    assert_eq!(vocab.version(), Some("2.1".to_string()));
+
+    let violations = env.check_version_constraints()?;
+    assert_eq!(violations.len(), 1);
+    assert_eq!(violations[0].ontology.name().as_str(), "urn:lib");
+    assert_eq!(violations[0].version.as_deref(), Some("1.2"));
+    let dependents: Vec<&str> = violations[0]
+        .dependents
+        .iter()
+        .map(|d| d.name().as_str())
+        .collect();
+    assert_eq!(dependents, vec!["urn:app"]);
+    let problems = env.problems()?;
+    assert!(problems
+        .iter()
+        .any(|p| p.message == "urn:lib has version 1.2, but >=1.3 is required"));
+
+    // strict environments refuse to update
+    let mut cfg = cfg;
+    cfg.strict = true;
+    let mut env = OntoEnv::new(cfg, true)?;
+    assert!(env.update().is_err());
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_diff() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    let upstream = TempDir::new("upstream")?;
+    let content = |label: &str| {
+        format!(
+            "@prefix owl: <http://www.w3.org/2002/07/owl#> .\n\
+             @prefix rdfs: <http://www.w3.org/2000/01/rdf-schema#> .\n\
+             <urn:lib> a owl:Ontology ; rdfs:label \"{}\" .\n\
+             <urn:lib#A> rdfs:subClassOf [ a owl:Restriction ; owl:onProperty <urn:lib#p> ] .\n",
+            label
+        )
+    };
+    std::fs::write(dir.path().join("lib.ttl"), content("old"))?;
+    std::fs::write(upstream.path().join("lib.ttl"), content("new"))?;
+    let cfg = default_config(&dir);
+    let mut env = OntoEnv::new(cfg, false)?;
+    env.update()?;
+    let id = env
+        .get_ontology_by_name(NamedNodeRef::new("urn:lib")?)
+        .unwrap()
+        .id()
+        .clone();
+
+    assert!(env.diff_graphs(&id, &id)?.is_empty());
+
+    // the restriction is a blank node in both versions, so only the label changed
+    let location = OntologyLocation::from_path(&upstream.path().join("lib.ttl"))?;
+    let diff = env.diff_against(&id, &location)?;
+    assert_eq!(diff.added.len(), 1);
+    assert_eq!(diff.removed.len(), 1);
+    assert_eq!(
+        diff.added[0].to_string(),
+        "<urn:lib> <http://www.w3.org/2000/01/rdf-schema#label> \"new\""
+    );
+    let unified = diff.render(DiffFormat::Unified, "a", "b");
+    assert!(unified.starts_with("--- a\n+++ b\n-<urn:lib>"));
+    let summary = diff.render(DiffFormat::Summary, "a", "b");
+    assert!(summary.contains("+1     -1  urn:lib\n"));
+    teardown(dir);
+    teardown(upstream);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_closure_nodes() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    write_ontology(
+        &dir,
+        "a.ttl",
+        "<urn:a> a owl:Ontology ; owl:imports <urn:b>, <urn:c> .",
+    )?;
+    write_ontology(
+        &dir,
+        "b.ttl",
+        "<urn:b> a owl:Ontology ; owl:imports <urn:d> .",
+    )?;
+    write_ontology(
+        &dir,
+        "c.ttl",
+        "<urn:c> a owl:Ontology ; owl:imports <urn:d> .",
+    )?;
+    write_ontology(&dir, "d.ttl", "<urn:d> a owl:Ontology .")?;
+    let cfg = default_config(&dir);
+    let mut env = OntoEnv::new(cfg, false)?;
+    env.update()?;
+
+    let a = env
+        .get_ontology_by_name(NamedNodeRef::new("urn:a")?)
+        .unwrap()
+        .id()
+        .clone();
+    let nodes = env.get_closure_nodes(&a)?;
+    let entries: Vec<(&str, usize, Option<&str>)> = nodes
+        .iter()
+        .map(|node| {
+            (
+                node.id.name().as_str(),
+                node.depth,
+                node.parent.as_ref().map(|p| p.name().as_str()),
+            )
+        })
+        .collect();
+    assert_eq!(entries.len(), 4);
+    assert_eq!(entries[0], ("urn:a", 0, None));
+    assert!(entries.contains(&("urn:b", 1, Some("urn:a"))));
+    assert!(entries.contains(&("urn:c", 1, Some("urn:a"))));
+    // d is imported by both b and c, and entered the closure through the first one visited
+    assert_eq!(entries[3], ("urn:d", 2, Some(entries[1].0)));
+
+    // the same members as the plain closure
+    let mut members: Vec<GraphIdentifier> = nodes.into_iter().map(|node| node.id).collect();
+    members[1..].sort();
+    assert_eq!(members, env.get_dependency_closure(&a)?);
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_snapshot_rollback() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    let file = dir.path().join("app.ttl");
+    let ontology = "@prefix owl: <http://www.w3.org/2002/07/owl#> .\n\
+                    <urn:app> a owl:Ontology .\n";
+    std::fs::write(&file, format!("{}<urn:app#A> a owl:Class .\n", ontology))?;
+    let mut env = OntoEnv::new(default_config(&dir), false)?;
+    env.update()?;
+    let tagged = env.snapshot("before")?;
+    assert_eq!(tagged.tag.as_deref(), Some("before"));
+    assert!(env.snapshot("before").is_err());
+
+    std::fs::write(&file, format!("{}<urn:app#B> a owl:Class .\n", ontology))?;
+    std::fs::write(
+        dir.path().join("lib.ttl"),
+        "@prefix owl: <http://www.w3.org/2002/07/owl#> .\n<urn:lib> a owl:Ontology .\n",
+    )?;
+    env.update_all(true)?;
+    assert_eq!(env.num_graphs(), 2);
+
+    let report = env.rollback("before")?;
+    let names = |ids: &[GraphIdentifier]| -> Vec<String> {
+        ids.iter()
+            .map(|id| id.name().as_str().to_string())
+            .collect()
+    };
+    assert_eq!(names(&report.updated), vec!["urn:app"]);
+    assert_eq!(names(&report.removed), vec!["urn:lib"]);
+    assert!(report.added.is_empty());
+    assert_eq!(env.num_graphs(), 1);
+    let app = env
+        .get_ontology_by_name(NamedNodeRef::new("urn:app")?)
+        .unwrap();
+    let a_is_class = TripleRef::new(
+        NamedNodeRef::new("urn:app#A")?,
+        ontoenv::consts::TYPE,
+        ontoenv::consts::CLASS,
+    );
+    assert!(env.get_graph(app.id())?.contains(a_is_class));
+    assert_eq!(env.fingerprint(), tagged.id);
+    assert!(env.rollback("nothing").is_err());
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_journal_recovery() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    let prefix = "@prefix owl: <http://www.w3.org/2002/07/owl#> .\n";
+    std::fs::write(
+        dir.path().join("app.ttl"),
+        format!("{}<urn:app> a owl:Ontology .\n", prefix),
+    )?;
+    let mut env = OntoEnv::new(default_config(&dir), false)?;
+    env.update()?;
+    env.save_to_directory()?;
+    let journal = dir.path().join(".ontoenv/journal");
+    assert!(!journal.exists());
+
+    // an update whose environment is never saved, as if the process was killed
+    std::fs::write(
+        dir.path().join("lib.ttl"),
+        format!("{}<urn:lib> a owl:Ontology .\n", prefix),
+    )?;
+    env.update()?;
+    assert!(journal.exists());
+    drop(env);
+
+    // the ontology whose graph was loaded is rolled forward
+    let path = dir.path().join(".ontoenv/ontoenv.json");
+    let env = OntoEnv::from_file(&path, false)?;
+    assert!(!journal.exists());
+    assert_eq!(env.num_graphs(), 2);
+    let lib = env
+        .get_ontology_by_name(NamedNodeRef::new("urn:lib")?)
+        .unwrap()
+        .id()
+        .clone();
+    drop(env);
+
+    // a graph which was being loaded is dropped, so the next update reads its file again
+    let graph = GraphIdentifier::graph_id_to_graphname(lib.graph_id().unwrap())?;
+    std::fs::write(
+        &journal,
+        format!("{{\"op\":\"add\",\"graph\":\"{}\"}}\n", graph.as_str()),
+    )?;
+    let mut env = OntoEnv::from_file(&path, false)?;
+    assert!(!journal.exists());
+    assert_eq!(env.num_graphs(), 1);
+    assert!(env.get_graph(&lib).map_or(true, |g| g.is_empty()));
+    let report = env.update()?;
+    assert_eq!(report.added.len(), 1);
+    assert_eq!(env.num_graphs(), 2);
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_journal_records_removals() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    write_ontology(&dir, "app.ttl", "<urn:app> a owl:Ontology .")?;
+    write_ontology(&dir, "lib.ttl", "<urn:lib> a owl:Ontology .")?;
+    let mut env = OntoEnv::new(default_config(&dir), false)?;
+    env.update()?;
+    env.save_to_directory()?;
+    let journal = dir.path().join(".ontoenv/journal");
+    assert!(!journal.exists());
+
+    // a removal whose environment is never saved, as if the process was killed
+    let lib = env
+        .get_ontology_by_name(NamedNodeRef::new("urn:lib")?)
+        .unwrap()
+        .id()
+        .clone();
+    env.remove(&lib, RemoveMode::Error)?;
+    assert!(journal.exists());
+    drop(env);
+
+    // the saved environment no longer lists the ontology whose graph is gone
+    let path = dir.path().join(".ontoenv/ontoenv.json");
+    let env = OntoEnv::from_file(&path, false)?;
+    assert!(!journal.exists());
+    assert_eq!(env.num_graphs(), 1);
+    assert!(env
+        .get_ontology_by_name(NamedNodeRef::new("urn:lib")?)
+        .is_none());
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_doctor_owl_structure() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    let prefixes = "@prefix owl: <http://www.w3.org/2002/07/owl#> .\n\
+                    @prefix rdfs: <http://www.w3.org/2000/01/rdf-schema#> .\n";
+    for (file, turtle) in [
+        (
+            "onto.ttl",
+            "<urn:onto> a owl:Ontology ; owl:imports <urn:lib>, \"urn:other\" .\n\
+             <urn:onto#A> a owl:Class ; rdfs:subClassOf <urn:lib#Base>, <urn:onto#Missing> .\n\
+             <urn:onto#p> a owl:ObjectProperty ; owl:inverseOf <urn:onto#q> ; \
+             rdfs:range <urn:onto#A> .\n",
+        ),
+        (
+            "lib.ttl",
+            "<urn:lib> a owl:Ontology .\n<urn:lib#Base> a owl:Class .\n",
+        ),
+        (
+            "extra.ttl",
+            "<urn:extra> a owl:Ontology .\n<urn:extra/v1> owl:imports <urn:lib> .\n",
+        ),
+    ] {
+        std::fs::write(dir.path().join(file), format!("{}{}", prefixes, turtle))?;
+    }
+    std::fs::write(
+        dir.path().join("broken.ttl"),
+        format!("{}\"label\" a owl:Class .\n", prefixes),
+    )?;
+    let mut cfg = default_config(&dir);
+    cfg.strict = false;
+    let mut env = OntoEnv::new(cfg, false)?;
+    let report = env.update()?;
+    // the update records where the file failed to parse
+    assert_eq!(report.failed.len(), 1);
+    assert_eq!(report.failed[0].line, Some(3));
+    let problems = env.problems()?;
+    let find = |message: &str| problems.iter().find(|p| p.message == message);
+
+    // problems name the line describing the subject
+    let undefined = find("Undefined classes used in axioms: <urn:onto#Missing> (line 4)").unwrap();
+    assert_eq!(undefined.graph.as_deref(), Some("urn:onto"));
+    assert!(undefined.locations[0].as_str().ends_with("onto.ttl"));
+    assert!(find("Undefined properties used in axioms: <urn:onto#q> (line 5)").is_some());
+    assert!(find("owl:imports of <urn:onto> is not an IRI: \"urn:other\" (line 3)").is_some());
+    let untyped =
+        find("<urn:extra/v1> has ontology properties but is not typed owl:Ontology (line 4)");
+    assert_eq!(untyped.unwrap().graph.as_deref(), Some("urn:extra"));
+    // the literal is on the line after the prefixes
+    let at_line = "Literal in subject position at line 3";
+    let literal = problems
+        .iter()
+        .find(|p| p.message.starts_with(at_line))
+        .unwrap();
+    assert!(literal.locations[0].as_str().ends_with("broken.ttl"));
+
+    let json = serde_json::to_value(&problems)?;
+    assert!(json
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|p| p["graph"] == "urn:onto"));
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_duplicates_and_sources() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    let ontology =
+        |name: &str| format!("<{}> a <http://www.w3.org/2002/07/owl#Ontology> .\n", name);
+    for (file, name) in [
+        ("dev/shared.ttl", "urn:shared"),
+        ("release/shared.ttl", "urn:shared"),
+        ("release/app.ttl", "urn:app"),
+    ] {
+        let path = dir.path().join(file);
+        std::fs::create_dir_all(path.parent().unwrap())?;
+        std::fs::write(path, ontology(name))?;
+    }
+    let mut cfg = default_config(&dir);
+    cfg.strict = false;
+    let mut env = OntoEnv::new(cfg, false)?;
+    env.update()?;
+
+    let sources = env.ontologies_by_source();
+    let release = dir.path().join("release").display().to_string();
+    let names: Vec<&str> = sources[&release]
+        .iter()
+        .map(|id| id.name().as_str())
+        .collect();
+    assert_eq!(names, vec!["urn:app", "urn:shared"]);
+    assert_eq!(sources.len(), 2);
+
+    let duplicates = env.duplicate_names()?;
+    assert_eq!(duplicates.len(), 1);
+    assert_eq!(duplicates[0].name, "urn:shared");
+    assert_eq!(duplicates[0].candidates.len(), 2);
+    assert!(duplicates[0].candidates.contains(&duplicates[0].selected));
+    let json = serde_json::to_value(&duplicates)?;
+    assert_eq!(json[0]["name"], "urn:shared");
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_orphans() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    let ontology = |name: &str, imports: &[&str]| {
+        let mut ttl = format!("<{}> a <http://www.w3.org/2002/07/owl#Ontology> .\n", name);
+        for import in imports {
+            ttl.push_str(&format!(
+                "<{}> <http://www.w3.org/2002/07/owl#imports> <{}> .\n",
+                name, import
+            ));
+        }
+        ttl
+    };
+    for (file, name, imports) in [
+        ("app.ttl", "urn:app", vec!["urn:lib"]),
+        ("lib.ttl", "urn:lib", vec![]),
+        ("tool.ttl", "urn:tool", vec!["urn:lib"]),
+        ("stale.ttl", "urn:stale", vec![]),
+    ] {
+        std::fs::write(dir.path().join(file), ontology(name, &imports))?;
+    }
+    let mut cfg = default_config(&dir);
+    let names = |ids: Vec<GraphIdentifier>| -> Vec<String> {
+        ids.iter()
+            .map(|id| id.name().as_str().to_string())
+            .collect()
+    };
+
+    // without roots, the ontologies nothing imports
+    let mut env = OntoEnv::new(cfg.clone(), false)?;
+    env.update()?;
+    assert_eq!(
+        names(env.orphans()?),
+        vec!["urn:app", "urn:stale", "urn:tool"]
+    );
+    drop(env);
+
+    // with a root, everything outside its closure
+    cfg.add_root("urn:app")?;
+    let mut env = OntoEnv::new(cfg, true)?;
+    env.update()?;
+    assert_eq!(names(env.orphans()?), vec!["urn:stale", "urn:tool"]);
+    teardown(dir);
+    Ok(())
+}
+
+#[test]
+fn test_ontoenv_get_graph_version() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    let ontology = |version: &str| {
+        format!(
+            "<urn:shared> a <http://www.w3.org/2002/07/owl#Ontology> ;\n  \
+             <http://www.w3.org/2002/07/owl#versionInfo> \"{}\" ;\n  \
+             <http://www.w3.org/2002/07/owl#versionIRI> <urn:shared/{}> .\n",
+            version, version
+        )
+    };
+    for version in ["1.0", "2.0"] {
+        let path = dir.path().join(version).join("shared.ttl");
+        std::fs::create_dir_all(path.parent().unwrap())?;
+        std::fs::write(path, ontology(version))?;
+    }
+    let mut cfg = default_config(&dir);
+    cfg.strict = false;
+    let mut env = OntoEnv::new(cfg, false)?;
+    env.update()?;
+    let name = NamedNode::new("urn:shared")?;
+    let version_info = |graph: &Graph| -> Vec<String> {
+        graph
+            .iter()
+            .filter(|t| t.predicate.as_str().ends_with("#versionInfo"))
+            .map(|t| t.object.to_string())
+            .collect()
+    };
+
+    let graph = env.get_graph_version(name.as_ref(), "1.0")?;
+    assert_eq!(version_info(&graph), vec!["\"1.0\""]);
+    let graph = env.get_graph_version(name.as_ref(), "urn:shared/2.0")?;
+    assert_eq!(version_info(&graph), vec!["\"2.0\""]);
+    let older = env.get_ontology_version(name.as_ref(), "1.0")?;
+    let hash = older.content_hash().unwrap().to_string();
+    let by_hash = env.get_ontology_version(name.as_ref(), &hash[..12])?;
+    assert_eq!(by_hash.id(), older.id());
+    // the name is normalized like in get_ontology_by_name
+    let upper = NamedNode::new("URN:shared")?;
+    let by_upper = env.get_ontology_version(upper.as_ref(), "1.0")?;
+    assert_eq!(by_upper.id(), older.id());
+
+    let missing = env.get_ontology_version(name.as_ref(), "3.0");
+    assert!(missing.unwrap_err().to_string().contains("No version 3.0"));
+    assert!(env
+        .get_ontology_version(NamedNode::new("urn:other")?.as_ref(), "1.0")
+        .is_err());
+    teardown(dir);
+    Ok(())
+}
+
+#[cfg(feature = "shacl")]
+#[test]
+fn test_ontoenv_validate() -> Result<()> {
+    let dir = TempDir::new("ontoenv")?;
+    setup!(&dir, {"fixtures/shacl/model.ttl" => "model.ttl",
+                  "fixtures/shacl/shapes.ttl" => "shapes.ttl"});
+    let mut env = OntoEnv::new(default_config(&dir), false)?;
+    env.update()?;
+
+    // the shapes shipped by the import apply to the data of the root, using the class
+    // hierarchy of the closure
+    let model = env
+        .get_ontology_by_name(NamedNodeRef::new("http://example.org/model")?)
+        .unwrap()
+        .id()
+        .clone();
+    let report = env.validate(&model)?;
+    assert!(!report.conforms);
+    let mut failures: Vec<(&str, &str)> = report
+        .results
+        .iter()
+        .map(|r| (r.focus_node.as_str(), r.constraint.as_str()))
+        .collect();
+    failures.sort();
+    assert_eq!(
+        failures,
+        vec![
+            ("<http://example.org/model#pump2>", "sh:minCount"),
+            ("<http://example.org/model#pump3>", "sh:class"),
+            ("<http://example.org/model#pump3>", "sh:maxCount"),
+        ]
+    );
+
+    // the closure of the shapes alone has no data for them
+    let shapes = env
+        .get_ontology_by_name(NamedNodeRef::new("http://example.org/shapes")?)
+        .unwrap()
+        .id()
+        .clone();
+    assert!(env.validate(&shapes)?.conforms);
+    teardown(dir);
+    Ok(())
+}
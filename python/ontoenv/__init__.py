@@ -0,0 +1,11 @@
+from ontoenv._ontoenv import Config, OntoEnv
+
+__all__ = ["Config", "OntoEnv"]
+
+try:
+    # the daemon is only available on Unix
+    from ontoenv._ontoenv import DaemonClient
+
+    __all__.append("DaemonClient")
+except ImportError:
+    pass
@@ -0,0 +1,99 @@
+"""
+An rdflib Store which reads graphs directly out of an OntoEnv environment.
+
+The store is registered with rdflib under the name "OntoEnv", so a graph backed by the
+environment can be created with:
+
+    from rdflib import Graph, URIRef
+    g = Graph(store="OntoEnv", identifier=URIRef("https://brickschema.org/schema/1.4/Brick"))
+
+Triples are fetched from the environment's store on demand for each pattern, rather than
+being copied into an rdflib Memory store, and are converted to rdflib terms as they are
+iterated. The store shares the process-wide OntoEnv instance, so it does not contend with
+other OntoEnv objects for the store's lock. It is read-only: opening it does not update the
+environment or write to its directory.
+"""
+from typing import Dict, Iterator, Optional, Tuple
+
+from rdflib.store import Store, VALID_STORE
+from rdflib.term import URIRef
+
+from ontoenv._ontoenv import OntoEnv
+
+
+class OntoEnvStore(Store):
+    context_aware = False
+    formula_aware = False
+    transaction_aware = False
+    graph_aware = False
+
+    def __init__(self, configuration: Optional[str] = None, identifier=None):
+        super().__init__(configuration, identifier)
+        self._env: Optional[OntoEnv] = None
+        self._namespaces: Dict[str, URIRef] = {}
+        self._prefixes: Dict[URIRef, str] = {}
+
+    def open(self, configuration: str, create: bool = False) -> int:
+        """
+        Open the environment rooted at the given directory (the directory containing .ontoenv)
+        read-only. Raises ValueError if a different environment is already open in this process
+        """
+        self._env = OntoEnv.open_read_only(configuration)
+        return VALID_STORE
+
+    def close(self, commit_pending_transaction: bool = False) -> None:
+        self._env = None
+
+    def _get_env(self) -> OntoEnv:
+        # if the store was not explicitly opened, use the environment in the current directory
+        if self._env is None:
+            self._env = OntoEnv.open_read_only(".")
+        return self._env
+
+    @staticmethod
+    def _graph_name(context) -> str:
+        if context is None or not isinstance(context.identifier, URIRef):
+            raise ValueError(
+                "OntoEnvStore requires a Graph whose identifier is the URI of an ontology"
+            )
+        return str(context.identifier)
+
+    def triples(self, triple_pattern, context=None) -> Iterator[Tuple[tuple, Iterator]]:
+        s, p, o = triple_pattern
+        graph = self._graph_name(context)
+        for triple in self._get_env().triples(graph, s, p, o):
+            yield triple, iter([context])
+
+    def __len__(self, context=None) -> int:
+        return self._get_env().graph_size(self._graph_name(context))
+
+    def contexts(self, triple=None):
+        return iter([])
+
+    def add(self, triple, context, quoted=False):
+        raise TypeError("OntoEnvStore is read-only")
+
+    def addN(self, quads):
+        raise TypeError("OntoEnvStore is read-only")
+
+    def remove(self, triple, context=None):
+        raise TypeError("OntoEnvStore is read-only")
+
+    def bind(self, prefix: str, namespace: URIRef, override: bool = True) -> None:
+        # namespace bindings are kept in memory; they are not part of the environment
+        if not override and (prefix in self._namespaces or namespace in self._prefixes):
+            return
+        old = self._namespaces.pop(prefix, None)
+        if old is not None:
+            self._prefixes.pop(old, None)
+        self._namespaces[prefix] = namespace
+        self._prefixes[namespace] = prefix
+
+    def namespace(self, prefix: str) -> Optional[URIRef]:
+        return self._namespaces.get(prefix)
+
+    def prefix(self, namespace: URIRef) -> Optional[str]:
+        return self._prefixes.get(namespace)
+
+    def namespaces(self):
+        yield from self._namespaces.items()
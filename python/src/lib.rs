@@ -1,17 +1,20 @@
 #![feature(once_cell_try)]
 use ::ontoenv as ontoenvrs;
 use ::ontoenv::consts::{ONTOLOGY, TYPE, IMPORTS};
+use ::ontoenv::iri::parse_iri;
 use ::ontoenv::ontology::OntologyLocation;
 use ::ontoenv::transform;
 use anyhow::Error;
-use oxigraph::model::{BlankNode, Literal, NamedNode, SubjectRef, Term};
+use oxigraph::model::{BlankNode, Literal, NamedNode, Subject, SubjectRef, Term, Triple};
 use pyo3::{
     prelude::*,
     types::{IntoPyDict, PyString, PyTuple},
 };
 use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::panic::{catch_unwind, AssertUnwindSafe};
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, Mutex, Once, OnceLock};
+use std::sync::{Arc, Mutex, MutexGuard, Once, OnceLock};
 
 static INIT: Once = Once::new();
 static ONTOENV_SINGLETON: OnceLock<Arc<Mutex<ontoenvrs::OntoEnv>>> = OnceLock::new();
@@ -20,19 +23,61 @@ fn anyhow_to_pyerr(e: Error) -> PyErr {
     PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string())
 }
 
-#[allow(dead_code)]
+fn value_error(e: impl std::fmt::Display) -> PyErr {
+    PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string())
+}
+
+/// Locks the environment. A poisoned lock means an earlier call panicked while holding it;
+/// this is reported as a RuntimeError instead of panicking again
+fn lock_env(inner: &Mutex<ontoenvrs::OntoEnv>) -> PyResult<MutexGuard<'_, ontoenvrs::OntoEnv>> {
+    inner.lock().map_err(|_| {
+        PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+            "OntoEnv is unusable because an earlier operation failed unexpectedly",
+        )
+    })
+}
+
+/// Runs a library call, turning a panic into a RuntimeError so it does not abort the
+/// Python interpreter
+fn catch_panic<T>(f: impl FnOnce() -> anyhow::Result<T>) -> PyResult<T> {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(result) => result.map_err(anyhow_to_pyerr),
+        Err(panic) => {
+            let message = panic
+                .downcast_ref::<&str>()
+                .map(|s| s.to_string())
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown error".to_string());
+            Err(PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(format!(
+                "Internal error in ontoenv: {}",
+                message
+            )))
+        }
+    }
+}
+
+/// Returns the ontology with the name, or an error if the environment has none
+fn find_ontology<'a>(
+    env: &'a ontoenvrs::OntoEnv,
+    iri: &NamedNode,
+) -> anyhow::Result<&'a ontoenvrs::ontology::Ontology> {
+    env.get_ontology_by_name(iri.as_ref())
+        .ok_or_else(|| anyhow::anyhow!("Ontology {} not found", iri))
+}
+
 struct MyTerm(Term);
-impl From<Result<Bound<'_, PyAny>, pyo3::PyErr>> for MyTerm {
-    fn from(s: Result<Bound<'_, PyAny>, pyo3::PyErr>) -> Self {
-        let s = s.unwrap();
-        let typestr = s.get_type().name().unwrap();
+impl TryFrom<&Bound<'_, PyAny>> for MyTerm {
+    type Error = PyErr;
+
+    fn try_from(s: &Bound<'_, PyAny>) -> PyResult<Self> {
+        let typestr = s.get_type().name()?;
         let typestr = typestr.to_string();
         let data_type: Option<NamedNode> = match s.getattr("datatype") {
             Ok(dt) => {
                 if dt.is_none() {
                     None
                 } else {
-                    Some(NamedNode::new(dt.to_string()).unwrap())
+                    Some(NamedNode::new(dt.to_string()).map_err(value_error)?)
                 }
             }
             Err(_) => None,
@@ -48,21 +93,27 @@ impl From<Result<Bound<'_, PyAny>, pyo3::PyErr>> for MyTerm {
             Err(_) => None,
         };
         let n: Term = match typestr.borrow() {
-            "URIRef" => Term::NamedNode(NamedNode::new(s.to_string()).unwrap()),
+            "URIRef" => Term::NamedNode(NamedNode::new(s.to_string()).map_err(value_error)?),
             "Literal" => match (data_type, lang) {
                 (Some(dt), None) => Term::Literal(Literal::new_typed_literal(s.to_string(), dt)),
-                (None, Some(l)) => {
-                    Term::Literal(Literal::new_language_tagged_literal(s.to_string(), l).unwrap())
-                }
+                (None, Some(l)) => Term::Literal(
+                    Literal::new_language_tagged_literal(s.to_string(), l).map_err(value_error)?,
+                ),
                 (_, _) => Term::Literal(Literal::new_simple_literal(s.to_string())),
             },
-            "BNode" => Term::BlankNode(BlankNode::new(s.to_string()).unwrap()),
-            _ => Term::NamedNode(NamedNode::new(s.to_string()).unwrap()),
+            "BNode" => Term::BlankNode(BlankNode::new(s.to_string()).map_err(value_error)?),
+            _ => Term::NamedNode(NamedNode::new(s.to_string()).map_err(value_error)?),
         };
-        MyTerm(n)
+        Ok(MyTerm(n))
     }
 }
 
+fn term_from_python(term: Option<&Bound<'_, PyAny>>) -> PyResult<Option<Term>> {
+    term.filter(|t| !t.is_none())
+        .map(|t| MyTerm::try_from(t).map(|t| t.0))
+        .transpose()
+}
+
 fn term_to_python<'a>(
     py: Python,
     rdflib: &Bound<'a, PyModule>,
@@ -117,6 +168,47 @@ fn term_to_python<'a>(
     Ok(res)
 }
 
+/// Copies the triples of an oxigraph Graph into a new rdflib.Graph
+fn graph_to_python<'a>(
+    py: Python<'a>,
+    rdflib: &Bound<'a, PyModule>,
+    graph: &oxigraph::model::Graph,
+) -> PyResult<Bound<'a, PyAny>> {
+    let res = rdflib.getattr("Graph")?.call0()?;
+    for triple in graph.iter() {
+        let t = PyTuple::new(
+            py,
+            &[
+                term_to_python(py, rdflib, triple.subject.into_owned().into())?,
+                term_to_python(py, rdflib, triple.predicate.into_owned().into())?,
+                term_to_python(py, rdflib, triple.object.into_owned())?,
+            ],
+        )?;
+        res.getattr("add")?.call1((t,))?;
+    }
+    Ok(res)
+}
+
+/// Copies the triples of an rdflib.Graph into a new oxigraph Graph
+fn graph_from_python(graph: &Bound<'_, PyAny>) -> PyResult<oxigraph::model::Graph> {
+    let mut res = oxigraph::model::Graph::new();
+    for triple in graph.try_iter()? {
+        let triple = triple?;
+        let subject: Subject = match MyTerm::try_from(&triple.get_item(0)?)?.0 {
+            Term::NamedNode(node) => node.into(),
+            Term::BlankNode(node) => node.into(),
+            term => return Err(value_error(format!("Invalid subject: {}", term))),
+        };
+        let predicate = match MyTerm::try_from(&triple.get_item(1)?)?.0 {
+            Term::NamedNode(node) => node,
+            term => return Err(value_error(format!("Invalid predicate: {}", term))),
+        };
+        let object = MyTerm::try_from(&triple.get_item(2)?)?.0;
+        res.insert(&Triple::new(subject, predicate, object));
+    }
+    Ok(res)
+}
+
 #[pyclass]
 #[derive(Clone)]
 struct Config {
@@ -126,7 +218,7 @@ struct Config {
 #[pymethods]
 impl Config {
     #[new]
-    #[pyo3(signature = (search_directories=None, require_ontology_names=false, strict=false, offline=false, resolution_policy="default".to_owned(), root=".".to_owned(), includes=None, excludes=None))]
+    #[pyo3(signature = (search_directories=None, require_ontology_names=false, strict=false, offline=false, resolution_policy="default".to_owned(), root=".".to_owned(), includes=None, excludes=None, profiles=None, strict_parse=None, strict_missing_imports=None, strict_network=None, strict_duplicates=None, strict_constraints=None, import_map=None, duplicate_strategy="pick".to_owned(), version_constraints=None))]
     fn new(
         search_directories: Option<Vec<String>>,
         require_ontology_names: bool,
@@ -136,8 +228,17 @@ impl Config {
         root: String,
         includes: Option<Vec<String>>,
         excludes: Option<Vec<String>>,
+        profiles: Option<HashMap<String, String>>,
+        strict_parse: Option<bool>,
+        strict_missing_imports: Option<bool>,
+        strict_network: Option<bool>,
+        strict_duplicates: Option<bool>,
+        strict_constraints: Option<bool>,
+        import_map: Option<HashMap<String, String>>,
+        duplicate_strategy: String,
+        version_constraints: Option<HashMap<String, String>>,
     ) -> PyResult<Self> {
-        Ok(Config {
+        let mut config = Config {
             cfg: ontoenvrs::config::Config::new(
                 root.to_string().into(),
                 search_directories.map(|dirs| {
@@ -162,7 +263,33 @@ impl Config {
                 false,
             )
             .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))?,
-        })
+        };
+        // profiles are given as name -> '<key>=<value>[;<key>=<value>...]'
+        for (name, settings) in profiles.unwrap_or_default() {
+            let (name, profile) =
+                ontoenvrs::config::Profile::from_str(&format!("{}={}", name, settings))
+                    .map_err(value_error)?;
+            config.cfg.profiles.insert(name, profile);
+        }
+        config.cfg.strict_parse = strict_parse;
+        config.cfg.strict_missing_imports = strict_missing_imports;
+        config.cfg.strict_network = strict_network;
+        config.cfg.strict_duplicates = strict_duplicates;
+        config.cfg.strict_constraints = strict_constraints;
+        for (import, location) in import_map.unwrap_or_default() {
+            config
+                .cfg
+                .add_import_mapping(&import, &location)
+                .map_err(value_error)?;
+        }
+        config.cfg.duplicate_strategy = duplicate_strategy.parse().map_err(value_error)?;
+        for (name, requirement) in version_constraints.unwrap_or_default() {
+            config
+                .cfg
+                .add_version_constraint(&name, &requirement)
+                .map_err(value_error)?;
+        }
+        Ok(config)
     }
 }
 
@@ -174,13 +301,14 @@ struct OntoEnv {
 #[pymethods]
 impl OntoEnv {
     #[new]
-    #[pyo3(signature = (config=None, path=Some(Path::new(".").to_owned()), recreate=false, read_only=false))]
+    #[pyo3(signature = (config=None, path=Some(Path::new(".").to_owned()), recreate=false, read_only=false, profile=None))]
     fn new(
         _py: Python,
         config: Option<Config>,
         path: Option<PathBuf>,
         recreate: bool,
         read_only: bool,
+        profile: Option<String>,
     ) -> PyResult<Self> {
         // wrap env_logger::init() in a Once to ensure it's only called once. This can
         // happen if a user script creates multiple OntoEnv instances
@@ -196,7 +324,9 @@ impl OntoEnv {
             // if no Config provided, but there is a path, load the OntoEnv from file
             // otherwise, create a new OntoEnv
             if config.is_none() && config_path.is_some() && config_path.as_ref().unwrap().exists(){
-                if let Ok(env) = ontoenvrs::OntoEnv::from_file(&config_path.unwrap(), read_only) {
+                if let Ok(env) =
+                    catch_panic(|| ontoenvrs::OntoEnv::from_file(&config_path.unwrap(), read_only))
+                {
                     println!("Loaded OntoEnv from file");
                     return Ok(Arc::new(Mutex::new(env)));
                 }
@@ -205,8 +335,7 @@ impl OntoEnv {
             // if config is provided, create a new OntoEnv with the provided config
             if let Some(c) = config {
                 println!("Creating new OntoEnv with provided config");
-                let inner = ontoenvrs::OntoEnv::new(c.cfg.clone(), recreate)
-                    .map_err(anyhow_to_pyerr)?;
+                let inner = catch_panic(|| ontoenvrs::OntoEnv::new(c.cfg.clone(), recreate))?;
                 return Ok(Arc::new(Mutex::new(inner)));
             }
 
@@ -218,36 +347,119 @@ impl OntoEnv {
 
         {
             let inner = env.clone();
-            let mut env = inner.lock().unwrap();
-            env.update().map_err(anyhow_to_pyerr)?;
-            env.save_to_directory().map_err(anyhow_to_pyerr)?;
+            let mut env = lock_env(&inner)?;
+            if env.profile() != profile.as_deref() {
+                catch_panic(|| env.set_profile(profile.as_deref()))?;
+            }
+            catch_panic(|| {
+                env.update()?;
+                env.save_to_directory()
+            })?;
+        }
+
+        Ok(OntoEnv { inner: env.clone() })
+    }
+
+    /// Open the environment at the path read-only, without updating it or writing to its
+    /// directory. If an OntoEnv was already opened in this process, it is returned instead,
+    /// and a ValueError is raised if its root is not the path
+    #[staticmethod]
+    #[pyo3(signature = (path=Path::new(".").to_owned()))]
+    fn open_read_only(path: PathBuf) -> PyResult<Self> {
+        INIT.call_once(|| {
+            env_logger::init();
+        });
+
+        let root = path.canonicalize().map_err(|e| {
+            value_error(format!("Cannot open OntoEnv at {}: {}", path.display(), e))
+        })?;
+        let env = ONTOENV_SINGLETON.get_or_try_init(|| {
+            let config_path = root.join(".ontoenv").join("ontoenv.json");
+            if !config_path.exists() {
+                return Err(value_error(format!(
+                    "No OntoEnv found at {}",
+                    path.display()
+                )));
+            }
+            let env = catch_panic(|| ontoenvrs::OntoEnv::from_file(&config_path, true))?;
+            Ok(Arc::new(Mutex::new(env)))
+        })?;
+
+        {
+            let env = lock_env(env)?;
+            if !env.root().canonicalize().is_ok_and(|r| r == root) {
+                return Err(value_error(format!(
+                    "OntoEnv at {} is already open in this process; cannot open {}",
+                    env.root().display(),
+                    path.display()
+                )));
+            }
         }
 
         Ok(OntoEnv { inner: env.clone() })
     }
 
+    /// Create a temporary OntoEnv with exactly the ontologies at the given files or URLs and
+    /// their imports closures, without scanning any directories. It is kept in memory only
+    #[staticmethod]
+    fn from_locations(locations: Vec<String>, config: Config) -> PyResult<Self> {
+        let locations = locations
+            .iter()
+            .map(|location| OntologyLocation::from_str(location))
+            .collect::<anyhow::Result<Vec<_>>>()
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))?;
+        let env = catch_panic(|| ontoenvrs::OntoEnv::from_locations(locations, config.cfg))?;
+        Ok(OntoEnv {
+            inner: Arc::new(Mutex::new(env)),
+        })
+    }
+
+    /// Create or reuse the scratch OntoEnv of the project at the config's root. Its state lives
+    /// in a private per-user cache directory instead of the project tree, and is reused when the
+    /// same scratch environment is created again on the same day
+    #[staticmethod]
+    #[pyo3(signature = (config, recreate=false))]
+    fn scratch(config: Config, recreate: bool) -> PyResult<Self> {
+        let env = catch_panic(|| {
+            let mut env = ontoenvrs::OntoEnv::scratch(config.cfg, recreate)?;
+            env.update()?;
+            env.save_to_directory()?;
+            Ok(env)
+        })?;
+        Ok(OntoEnv {
+            inner: Arc::new(Mutex::new(env)),
+        })
+    }
+
     fn update(&self) -> PyResult<()> {
         let inner = self.inner.clone();
-        let mut env = inner.lock().unwrap();
-        env.update().map_err(anyhow_to_pyerr)?;
-        env.save_to_directory().map_err(anyhow_to_pyerr)?;
-        Ok(())
+        let mut env = lock_env(&inner)?;
+        catch_panic(|| {
+            env.update()?;
+            // temporary environments only live in memory
+            if !env.is_temporary() {
+                env.save_to_directory()?;
+            }
+            Ok(())
+        })
     }
 
     fn is_read_only(&self) -> PyResult<bool> {
         let inner = self.inner.clone();
-        let env = inner.lock().unwrap();
-        Ok(env.is_read_only())
+        let env = lock_env(&inner)?;
+        catch_panic(|| Ok(env.is_read_only()))
     }
 
     fn __repr__(&self) -> PyResult<String> {
         let inner = self.inner.clone();
-        let env = inner.lock().unwrap();
-        Ok(format!(
-            "<OntoEnv: {} graphs, {} triples>",
-            env.num_graphs(),
-            env.num_triples().map_err(anyhow_to_pyerr)?
-        ))
+        let env = lock_env(&inner)?;
+        catch_panic(|| {
+            Ok(format!(
+                "<OntoEnv: {} graphs, {} triples>",
+                env.num_graphs(),
+                env.num_triples()?
+            ))
+        })
     }
 
     // The following methods will now access the inner OntoEnv in a thread-safe manner:
@@ -259,30 +471,35 @@ impl OntoEnv {
         uri: &str,
     ) -> PyResult<()> {
         let inner = self.inner.clone();
-        let env = inner.lock().unwrap();
+        let env = lock_env(&inner)?;
         let rdflib = py.import("rdflib")?;
-        let iri = NamedNode::new(uri)
+        let iri = parse_iri(uri)
             .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))?;
-        let ont = env.get_ontology_by_name(iri.as_ref()).ok_or_else(|| {
-            PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Ontology {} not found", iri))
-        })?;
-        let mut graph = ont.graph().map_err(anyhow_to_pyerr)?;
+        let mut graph = catch_panic(|| find_ontology(&env, &iri)?.graph())?;
 
         let uriref_constructor = rdflib.getattr("URIRef")?;
         let type_uri = uriref_constructor.call1((TYPE.as_str(),))?;
         let ontology_uri = uriref_constructor.call1((ONTOLOGY.as_str(),))?;
         let kwargs = [("predicate", type_uri), ("object", ontology_uri)].into_py_dict(py)?;
         let result = destination_graph.call_method("value", (), Some(&kwargs))?;
-        if !result.is_none() {
-            let ontology = NamedNode::new(result.extract::<String>()?)
-                .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))?;
-            let base_ontology: SubjectRef = SubjectRef::NamedNode(ontology.as_ref());
-
-            transform::rewrite_sh_prefixes_graph(&mut graph, base_ontology);
-            transform::remove_ontology_declarations_graph(&mut graph, base_ontology);
-        }
-        // remove the owl:import statement for the 'uri' ontology
-        transform::remove_owl_imports_graph(&mut graph, Some(&[(&iri).into()]));   
+        let ontology = if result.is_none() {
+            None
+        } else {
+            Some(
+                NamedNode::new(result.extract::<String>()?)
+                    .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))?,
+            )
+        };
+        catch_panic(|| {
+            if let Some(ontology) = &ontology {
+                let base_ontology: SubjectRef = SubjectRef::NamedNode(ontology.as_ref());
+                transform::rewrite_sh_prefixes_graph(&mut graph, base_ontology);
+                transform::remove_ontology_declarations_graph(&mut graph, base_ontology);
+            }
+            // remove the owl:import statement for the 'uri' ontology
+            transform::remove_owl_imports_graph(&mut graph, Some(&[(&iri).into()]));
+            Ok(())
+        })?;
 
         Python::with_gil(|_py| {
             for triple in graph.into_iter() {
@@ -309,16 +526,11 @@ impl OntoEnv {
     /// List the ontologies in the imports closure of the given ontology
     #[pyo3(signature = (uri))]
     fn list_closure(&self, py: Python, uri: &str) -> PyResult<Vec<String>> {
-        let iri = NamedNode::new(uri)
+        let iri = parse_iri(uri)
             .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))?;
         let inner = self.inner.clone();
-        let env = inner.lock().unwrap();
-        let ont = env.get_ontology_by_name(iri.as_ref()).ok_or_else(|| {
-            PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Ontology {} not found", iri))
-        })?;
-        let closure = env
-            .get_dependency_closure(ont.id())
-            .map_err(anyhow_to_pyerr)?;
+        let env = lock_env(&inner)?;
+        let closure = catch_panic(|| env.get_dependency_closure(find_ontology(&env, &iri)?.id()))?;
         let names: Vec<String> = closure.iter().map(|ont| ont.name().to_string()).collect();
         Ok(names)
     }
@@ -336,28 +548,23 @@ impl OntoEnv {
         remove_owl_imports: bool,
     ) -> PyResult<Bound<'a, PyAny>> {
         let rdflib = py.import("rdflib")?;
-        let iri = NamedNode::new(uri)
+        let iri = parse_iri(uri)
             .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))?;
         let inner = self.inner.clone();
-        let env = inner.lock().unwrap();
-        let ont = env.get_ontology_by_name(iri.as_ref()).ok_or_else(|| {
-            PyErr::new::<pyo3::exceptions::PyValueError, _>(format!("Ontology {} not found", iri))
+        let env = lock_env(&inner)?;
+        let (graph, successful_imports, failed_imports) = catch_panic(|| {
+            let closure = env.get_dependency_closure(find_ontology(&env, &iri)?.id())?;
+            env.get_union_graph(
+                &closure,
+                Some(rewrite_sh_prefixes),
+                Some(remove_owl_imports),
+            )
         })?;
-        let closure = env
-            .get_dependency_closure(ont.id())
-            .map_err(anyhow_to_pyerr)?;
         // if destination_graph is null, create a new rdflib.Graph()
         let destination_graph = match destination_graph {
             Some(g) => g.clone(),
             None => rdflib.getattr("Graph")?.call0()?,
         };
-        let (graph, successful_imports, failed_imports) = env
-            .get_union_graph(
-                &closure,
-                Some(rewrite_sh_prefixes),
-                Some(remove_owl_imports),
-            )
-            .map_err(anyhow_to_pyerr)?;
         Python::with_gil(|_py| {
             for triple in graph.into_iter() {
                 let s: Term = triple.subject.into();
@@ -390,13 +597,98 @@ impl OntoEnv {
         })
     }
 
+    /// Return an iterator over the triples of the ontology with the given URI which match the
+    /// (subject, predicate, object) pattern. None matches any term. Triples are read out of the
+    /// store one at a time as the iterator advances.
+    #[pyo3(signature = (uri, subject=None, predicate=None, object=None))]
+    fn triples(
+        &self,
+        uri: &str,
+        subject: Option<&Bound<'_, PyAny>>,
+        predicate: Option<&Bound<'_, PyAny>>,
+        object: Option<&Bound<'_, PyAny>>,
+    ) -> PyResult<TripleIterator> {
+        let iri = parse_iri(uri)
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))?;
+        // patterns which can never match (e.g. a literal subject) return no triples
+        let subject: Option<Subject> = match term_from_python(subject)? {
+            Some(Term::NamedNode(n)) => Some(n.into()),
+            Some(Term::BlankNode(b)) => Some(b.into()),
+            Some(_) => return Ok(TripleIterator::empty()),
+            None => None,
+        };
+        let predicate: Option<NamedNode> = match term_from_python(predicate)? {
+            Some(Term::NamedNode(n)) => Some(n),
+            Some(_) => return Ok(TripleIterator::empty()),
+            None => None,
+        };
+        let object: Option<Term> = term_from_python(object)?;
+        let inner = self.inner.clone();
+        let env = lock_env(&inner)?;
+        let triples = catch_panic(|| {
+            env.triples_for_pattern_iter(
+                find_ontology(&env, &iri)?.id(),
+                subject.as_ref().map(|s| s.as_ref()),
+                predicate.as_ref().map(|p| p.as_ref()),
+                object.as_ref().map(|o| o.as_ref()),
+            )
+        })?;
+        Ok(TripleIterator {
+            triples: Box::new(triples),
+        })
+    }
+
+    /// Return the number of triples in the ontology with the given URI
+    fn graph_size(&self, uri: &str) -> PyResult<usize> {
+        let iri = parse_iri(uri)
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))?;
+        let inner = self.inner.clone();
+        let env = lock_env(&inner)?;
+        catch_panic(|| env.graph_len(find_ontology(&env, &iri)?.id()))
+    }
+
+    /// Assemble the inputs for validating the ontology with the given URI with pySHACL. Returns a
+    /// (data_graph, shacl_graph, ont_graph) tuple of rdflib.Graphs: the ontology itself, the
+    /// merged imports closure of shapes_uri (or of data_uri if not provided), and the merged
+    /// imports closure of data_uri without the data graph
+    #[pyo3(signature = (data_uri, shapes_uri=None))]
+    fn shacl_inputs<'a>(
+        &self,
+        py: Python<'a>,
+        data_uri: &str,
+        shapes_uri: Option<&str>,
+    ) -> PyResult<Bound<'a, PyTuple>> {
+        let rdflib = py.import("rdflib")?;
+        let data_iri = parse_iri(data_uri).map_err(value_error)?;
+        let shapes_iri = shapes_uri.map(parse_iri).transpose().map_err(value_error)?;
+        let inputs = {
+            let inner = self.inner.clone();
+            let env = lock_env(&inner)?;
+            catch_panic(|| {
+                let data = find_ontology(&env, &data_iri)?;
+                let shapes = match &shapes_iri {
+                    Some(iri) => Some(find_ontology(&env, iri)?),
+                    None => None,
+                };
+                env.shacl_inputs(data.id(), shapes.map(|s| s.id()))
+            })?
+        };
+        PyTuple::new(
+            py,
+            &[
+                graph_to_python(py, &rdflib, &inputs.data_graph)?,
+                graph_to_python(py, &rdflib, &inputs.shapes_graph)?,
+                graph_to_python(py, &rdflib, &inputs.ont_graph)?,
+            ],
+        )
+    }
+
     /// Print the contents of the OntoEnv
     #[pyo3(signature = (includes=None))]
     fn dump(&self, py: Python, includes: Option<String>) -> PyResult<()> {
         let inner = self.inner.clone();
-        let env = inner.lock().unwrap();
-        env.dump(includes.as_deref());
-        Ok(())
+        let env = lock_env(&inner)?;
+        catch_panic(|| env.dump(includes.as_deref()))
     }
 
     /// Import the dependencies of the given graph into the graph. Removes the owl:imports
@@ -426,49 +718,76 @@ impl OntoEnv {
     /// Add a new ontology to the OntoEnv
     fn add(&self, location: &Bound<'_, PyAny>) -> PyResult<()> {
         let inner = self.inner.clone();
-        let mut env = inner.lock().unwrap();
+        let mut env = lock_env(&inner)?;
         let location =
             OntologyLocation::from_str(&location.to_string()).map_err(anyhow_to_pyerr)?;
-        env.add(location).map_err(anyhow_to_pyerr)?;
-        env.save_to_directory().map_err(anyhow_to_pyerr)?;
-        Ok(())
+        catch_panic(|| {
+            env.add(location)?;
+            env.save_to_directory()
+        })
     }
 
     /// Refresh the OntoEnv by re-loading all remote graphs and loading
     /// any local graphs which have changed since the last update
     fn refresh(&self) -> PyResult<()> {
         let inner = self.inner.clone();
-        let mut env = inner.lock().unwrap();
-        env.update().map_err(anyhow_to_pyerr)?;
-        env.save_to_directory().map_err(anyhow_to_pyerr)?;
-        Ok(())
+        let mut env = lock_env(&inner)?;
+        catch_panic(|| {
+            env.update()?;
+            env.save_to_directory()
+        })
     }
 
     /// Get the names of all ontologies that depend on the given ontology
     fn get_dependents(&self, uri: &str) -> PyResult<Vec<String>> {
-        let iri = NamedNode::new(uri)
+        let iri = parse_iri(uri)
             .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))?;
         let inner = self.inner.clone();
-        let env = inner.lock().unwrap();
-        let dependents = env
-            .get_dependents(&iri)
-            .map_err(anyhow_to_pyerr)?;
+        let env = lock_env(&inner)?;
+        let dependents = catch_panic(|| env.get_dependents(&iri))?;
         let names: Vec<String> = dependents.iter().map(|ont| ont.name().to_string()).collect();
         Ok(names)
     }
 
-    /// Export the graph with the given URI to an rdflib.Graph
-    fn get_graph(&self, py: Python, uri: &Bound<'_, PyString>) -> PyResult<Py<PyAny>> {
+    /// Suggest which ontologies in the environment the given rdflib.Graph (e.g. a model) should
+    /// import, based on the classes and properties it uses
+    fn suggest_imports(&self, graph: &Bound<'_, PyAny>) -> PyResult<Vec<String>> {
+        let graph = graph_from_python(graph)?;
+        let inner = self.inner.clone();
+        let env = lock_env(&inner)?;
+        let names = catch_panic(|| env.suggest_imports(&graph))?;
+        Ok(names.iter().map(|name| name.as_str().to_string()).collect())
+    }
+
+    /// Get the owl:imports of the given rdflib.Graph which provide none of the classes and
+    /// properties it uses
+    fn unused_imports(&self, graph: &Bound<'_, PyAny>) -> PyResult<Vec<String>> {
+        let graph = graph_from_python(graph)?;
+        let inner = self.inner.clone();
+        let env = lock_env(&inner)?;
+        let names = catch_panic(|| env.unused_imports(&graph))?;
+        Ok(names.iter().map(|name| name.as_str().to_string()).collect())
+    }
+
+    /// Export the graph with the given URI to an rdflib.Graph, optionally the given version of
+    /// it (its versionIRI, location, version or content hash) instead of the policy's pick
+    #[pyo3(signature = (uri, version=None))]
+    fn get_graph(
+        &self,
+        py: Python,
+        uri: &Bound<'_, PyString>,
+        version: Option<String>,
+    ) -> PyResult<Py<PyAny>> {
         let rdflib = py.import("rdflib")?;
-        let iri = NamedNode::new(uri.to_string())
+        let iri = parse_iri(&uri.to_string())
             .map_err(|e| PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string()))?;
         let graph = {
             let inner = self.inner.clone();
-            let env = inner.lock().unwrap();
-            let graph = env
-                .get_graph_by_name(iri.as_ref())
-                .map_err(anyhow_to_pyerr)?;
-            graph
+            let env = lock_env(&inner)?;
+            catch_panic(|| match &version {
+                Some(version) => env.get_graph_version(iri.as_ref(), version),
+                None => env.get_graph_by_name(iri.as_ref()),
+            })?
         };
         let res = rdflib.getattr("Graph")?.call0()?;
         for triple in graph.into_iter() {
@@ -490,38 +809,215 @@ impl OntoEnv {
         Ok(res.into())
     }
 
+    /// Export the graphs with the given URIs to rdflib.Graphs, in the same order. Reads the
+    /// store once, so it is faster than calling get_graph for each of many graphs
+    fn get_graphs(&self, py: Python, uris: Vec<String>) -> PyResult<Vec<Py<PyAny>>> {
+        let rdflib = py.import("rdflib")?;
+        let iris = uris
+            .iter()
+            .map(|uri| parse_iri(uri).map_err(value_error))
+            .collect::<PyResult<Vec<_>>>()?;
+        let graphs = {
+            let inner = self.inner.clone();
+            let env = lock_env(&inner)?;
+            catch_panic(|| {
+                let ids = iris
+                    .iter()
+                    .map(|iri| find_ontology(&env, iri).map(|ontology| ontology.id().clone()))
+                    .collect::<anyhow::Result<Vec<_>>>()?;
+                env.get_graphs(&ids)
+            })?
+        };
+        graphs
+            .iter()
+            .map(|(_, graph)| Ok(graph_to_python(py, &rdflib, graph)?.into()))
+            .collect()
+    }
+
+    /// Generate a JSON-LD context (as a dict) from the prefixes, classes and properties of the
+    /// imports closure of the given ontology
+    fn jsonld_context(&self, py: Python, uri: &str) -> PyResult<Py<PyAny>> {
+        let iri = parse_iri(uri).map_err(value_error)?;
+        let context = {
+            let inner = self.inner.clone();
+            let env = lock_env(&inner)?;
+            catch_panic(|| {
+                let closure = env.get_dependency_closure(find_ontology(&env, &iri)?.id())?;
+                env.jsonld_context(&closure)
+            })?
+        };
+        let json = py.import("json")?;
+        Ok(json.call_method1("loads", (context.to_string(),))?.into())
+    }
+
+    /// The environment-wide prefix -> namespace registry, merged from the prefixes the
+    /// ontologies declare
+    fn prefixes(&self) -> PyResult<HashMap<String, String>> {
+        let inner = self.inner.clone();
+        let env = lock_env(&inner)?;
+        catch_panic(|| {
+            Ok(env
+                .prefixes()
+                .prefixes()
+                .iter()
+                .map(|(prefix, namespace)| (prefix.clone(), namespace.clone()))
+                .collect())
+        })
+    }
+
+    /// Expand a CURIE such as brick:Sensor to an IRI
+    fn expand(&self, curie: &str) -> PyResult<String> {
+        let inner = self.inner.clone();
+        let env = lock_env(&inner)?;
+        Ok(catch_panic(|| env.expand(curie))?.into_string())
+    }
+
+    /// Shrink an IRI to a CURIE, or return None if no prefix is bound to its namespace
+    fn shrink(&self, iri: &str) -> PyResult<Option<String>> {
+        let iri = parse_iri(iri).map_err(value_error)?;
+        let inner = self.inner.clone();
+        let env = lock_env(&inner)?;
+        catch_panic(|| Ok(env.shrink(iri.as_ref())))
+    }
+
     /// Get the names of all ontologies in the OntoEnv
     fn get_ontology_names(&self) -> PyResult<Vec<String>> {
         let inner = self.inner.clone();
-        let env = inner.lock().unwrap();
-        let names: Vec<String> = env
-            .ontologies()
-            .keys()
-            .map(|k| k.name().to_string())
-            .collect();
-        Ok(names)
+        let env = lock_env(&inner)?;
+        catch_panic(|| {
+            Ok(env
+                .ontologies()
+                .keys()
+                .map(|k| k.name().to_string())
+                .collect())
+        })
     }
 
     /// Convert the OntoEnv to an rdflib.Dataset
     fn to_rdflib_dataset(&self, py: Python) -> PyResult<Py<PyAny>> {
         // rdflib.ConjunctiveGraph(store="Oxigraph")
         let inner = self.inner.clone();
-        let env = inner.lock().unwrap();
+        let env = lock_env(&inner)?;
         let rdflib = py.import("rdflib")?;
         let dataset = rdflib.getattr("Dataset")?;
 
         // call Dataset(store="Oxigraph")
         let kwargs = [("store", "Oxigraph")].into_py_dict(py)?;
         let store = dataset.call((), Some(&kwargs))?;
-        let path = env.store_path().map_err(anyhow_to_pyerr)?.to_string();
+        let path = catch_panic(|| env.store_path())?.to_string();
         store.getattr("open")?.call1((path,))?;
         Ok(store.into())
     }
 }
 
+/// The iterator returned by `OntoEnv.triples`. It reads from a snapshot of the store taken
+/// when it was created, so it does not hold the environment's lock while it is consumed
+#[pyclass(unsendable)]
+struct TripleIterator {
+    triples: Box<dyn Iterator<Item = anyhow::Result<Triple>>>,
+}
+
+impl TripleIterator {
+    fn empty() -> Self {
+        TripleIterator {
+            triples: Box::new(std::iter::empty()),
+        }
+    }
+}
+
+#[pymethods]
+impl TripleIterator {
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+
+    fn __next__<'a>(&mut self, py: Python<'a>) -> PyResult<Option<Bound<'a, PyTuple>>> {
+        let Some(triple) = catch_panic(|| self.triples.next().transpose())? else {
+            return Ok(None);
+        };
+        let rdflib = py.import("rdflib")?;
+        Ok(Some(PyTuple::new(
+            py,
+            &[
+                term_to_python(py, &rdflib, triple.subject.into())?,
+                term_to_python(py, &rdflib, triple.predicate.into())?,
+                term_to_python(py, &rdflib, triple.object)?,
+            ],
+        )?))
+    }
+}
+
+/// A client of the `ontoenv daemon` serving an environment, which answers requests without
+/// opening the environment's store in this process. Each request is sent over a connection of
+/// its own, so a client kept for the whole session does not keep other processes waiting
+#[cfg(unix)]
+#[pyclass]
+struct DaemonClient {
+    inner: Mutex<ontoenvrs::daemon::Client>,
+}
+
+#[cfg(unix)]
+impl DaemonClient {
+    fn client(&self) -> PyResult<MutexGuard<'_, ontoenvrs::daemon::Client>> {
+        self.inner.lock().map_err(|_| {
+            PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+                "DaemonClient is unusable because an earlier operation failed unexpectedly",
+            )
+        })
+    }
+}
+
+#[cfg(unix)]
+#[pymethods]
+impl DaemonClient {
+    /// Connect to the daemon serving the environment at the path, or return None if no daemon
+    /// is running
+    #[staticmethod]
+    #[pyo3(signature = (path=Path::new(".").to_owned()))]
+    fn connect(path: PathBuf) -> PyResult<Option<Self>> {
+        let client = catch_panic(|| ontoenvrs::daemon::Client::connect(&path))?;
+        Ok(client.map(|client| DaemonClient {
+            inner: Mutex::new(client),
+        }))
+    }
+
+    /// Get the name and location of the ontology with the given name, or None
+    fn resolve(&self, uri: &str) -> PyResult<Option<(String, String)>> {
+        let mut client = self.client()?;
+        catch_panic(|| client.resolve(uri))
+    }
+
+    /// List the names of the ontologies in the imports closure of the ontology
+    fn list_closure(&self, uri: &str) -> PyResult<Vec<String>> {
+        let mut client = self.client()?;
+        catch_panic(|| client.closure(uri))
+    }
+
+    /// Export the graph with the given URI to an rdflib.Graph
+    fn get_graph(&self, py: Python, uri: &str) -> PyResult<Py<PyAny>> {
+        let rdflib = py.import("rdflib")?;
+        let mut client = self.client()?;
+        let graph = catch_panic(|| client.get_graph(uri))?;
+        Ok(graph_to_python(py, &rdflib, &graph)?.into())
+    }
+
+    /// Make the daemon update its environment, and return the update report as a JSON string
+    #[pyo3(signature = (all=false))]
+    fn update(&self, all: bool) -> PyResult<String> {
+        let mut client = self.client()?;
+        let report = catch_panic(|| client.update(all))?;
+        Ok(report.to_string())
+    }
+}
+
+// the native module is wrapped by the pure-Python 'ontoenv' package (see python/ontoenv)
 #[pymodule]
+#[pyo3(name = "_ontoenv")]
 fn ontoenv(_py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<Config>()?;
     m.add_class::<OntoEnv>()?;
+    m.add_class::<TripleIterator>()?;
+    #[cfg(unix)]
+    m.add_class::<DaemonClient>()?;
     Ok(())
 }
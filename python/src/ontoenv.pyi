@@ -1,4 +1,5 @@
-from typing import Optional, List, Union
+from pathlib import Path
+from typing import Any, Dict, Optional, List, Tuple, Union
 
 class Config:
     """
@@ -13,6 +14,21 @@ class Config:
         root: Root directory for the environment.
         includes: Optional list of patterns to include.
         excludes: Optional list of patterns to exclude.
+        profiles: Optional named subsets of the environment, as name -> settings of the form
+            'dir=<directory>;include=<pattern>;exclude=<pattern>;policy=<policy>'.
+        strict_parse: Fail on files which cannot be read or parsed; defaults to strict.
+        strict_missing_imports: Fail on imports which do not resolve; defaults to strict.
+        strict_network: Fail on remote ontologies which cannot be fetched; defaults to strict.
+        strict_duplicates: Fail on ontologies with the same name as one at another location;
+            defaults to False.
+        import_map: Optional import IRI -> location (a URL, or a path relative to the root)
+            owl:imports of the IRI are read from; the ontology read there keeps the name of
+            the import.
+        duplicate_strategy: How imports of an ontology found at several locations resolve:
+            "pick" one of them with the resolution policy, or "merge" all of them.
+        version_constraints: Optional ontology name -> version requirement (e.g. ">=1.3" or
+            ">=1.3, <2"). Updates warn about (in strict mode, fail on) ontologies whose
+            owl:versionInfo or owl:versionIRI violates the requirement.
     """
     def __init__(
         self,
@@ -24,6 +40,14 @@ class Config:
         root: str = ".",
         includes: Optional[List[str]] = None,
         excludes: Optional[List[str]] = None,
+        profiles: Optional[Dict[str, str]] = None,
+        strict_parse: Optional[bool] = None,
+        strict_missing_imports: Optional[bool] = None,
+        strict_network: Optional[bool] = None,
+        strict_duplicates: Optional[bool] = None,
+        import_map: Optional[Dict[str, str]] = None,
+        duplicate_strategy: str = "pick",
+        version_constraints: Optional[Dict[str, str]] = None,
     ) -> None:
         """
         Initialize the Config object with the given parameters.
@@ -39,6 +63,7 @@ class OntoEnv:
         path: Path to the ontology environment.
         recreate: Flag to recreate the environment.
         read_only: Flag to set the environment as read-only.
+        profile: Optional profile of the configuration to resolve ontologies in.
     """
     def __init__(
         self,
@@ -46,12 +71,45 @@ class OntoEnv:
         path: Optional[Union[str, Path]] = ".",
         recreate: bool = False,
         read_only: bool = False,
+        profile: Optional[str] = None,
     ) -> None:
         """
         Initialize the OntoEnv object with the given parameters.
         """
         ...
 
+    @staticmethod
+    def from_locations(locations: List[str], config: Config) -> "OntoEnv":
+        """
+        Create a temporary OntoEnv containing exactly the ontologies at the given files or URLs
+        and their imports closures, without scanning any directories. The OntoEnv is kept in
+        memory only and is not saved.
+
+        Args:
+            locations: Paths of files or URLs of ontologies.
+            config: The configuration; its search directories are not used.
+
+        Returns:
+            The temporary OntoEnv.
+        """
+        ...
+
+    @staticmethod
+    def scratch(config: Config, recreate: bool = False) -> "OntoEnv":
+        """
+        Create or reuse the scratch OntoEnv of the project at the config's root. Its state is
+        kept in a per-user temporary directory rather than in the project tree, and is reused
+        when the same scratch OntoEnv is created again on the same day.
+
+        Args:
+            config: The configuration.
+            recreate: Flag to recreate the scratch environment even if it exists.
+
+        Returns:
+            The updated scratch OntoEnv.
+        """
+        ...
+
     def update(self) -> None:
         """
         Update the ontology environment by reloading all ontologies.
@@ -113,6 +171,50 @@ class OntoEnv:
         """
         ...
 
+    def triples(
+        self,
+        uri: str,
+        subject: Optional[Any] = None,
+        predicate: Optional[Any] = None,
+        object: Optional[Any] = None,
+    ) -> List[Tuple[Any, Any, Any]]:
+        """
+        Return the triples of an ontology which match a pattern, read directly from the store.
+
+        Args:
+            uri: The URI of the ontology.
+            subject: Optional rdflib term the subject must equal.
+            predicate: Optional rdflib term the predicate must equal.
+            object: Optional rdflib term the object must equal.
+
+        Returns:
+            A list of (subject, predicate, object) tuples of rdflib terms.
+        """
+        ...
+
+    def graph_size(self, uri: str) -> int:
+        """
+        Return the number of triples in the ontology with the given URI.
+        """
+        ...
+
+    def shacl_inputs(
+        self, data_uri: str, shapes_uri: Optional[str] = None
+    ) -> Tuple[Any, Any, Any]:
+        """
+        Assemble the inputs for validating an ontology with pySHACL.
+
+        Args:
+            data_uri: The URI of the ontology to validate.
+            shapes_uri: Optional URI of the ontology whose imports closure holds the shapes.
+                Defaults to the imports closure of data_uri.
+
+        Returns:
+            A (data_graph, shacl_graph, ont_graph) tuple of rdflib.Graphs which can be passed
+            to pyshacl.validate.
+        """
+        ...
+
     def dump(self, includes: Optional[str] = None) -> None:
         """
         Print the contents of the OntoEnv.
@@ -146,12 +248,98 @@ class OntoEnv:
         """
         ...
 
-    def get_graph(self, uri: str) -> None:
+    def suggest_imports(self, graph) -> List[str]:
+        """
+        Suggest which ontologies in the OntoEnv the given graph (e.g. a model) should import,
+        based on the classes and properties it uses.
+
+        Args:
+            graph: The rdflib.Graph to check.
+
+        Returns:
+            A list of ontology names.
+        """
+        ...
+
+    def unused_imports(self, graph) -> List[str]:
+        """
+        Get the owl:imports of the given graph which provide none of the classes and properties it uses.
+
+        Args:
+            graph: The rdflib.Graph to check.
+
+        Returns:
+            A list of ontology names.
+        """
+        ...
+
+    def get_graph(self, uri: str, version: Optional[str] = None) -> None:
         """
         Export the graph with the given URI to an rdflib.Graph.
 
         Args:
             uri: The URI of the graph to export.
+            version: Export this version of the ontology instead of the one the resolution
+                policy picks: its versionIRI, location, version or (a prefix of) its content hash.
+        """
+        ...
+
+    def get_graphs(self, uris: List[str]) -> List[Any]:
+        """
+        Export the graphs with the given URIs to rdflib.Graphs, reading the store once.
+
+        Args:
+            uris: The URIs of the graphs to export.
+
+        Returns:
+            The rdflib.Graphs, in the same order as the URIs.
+        """
+        ...
+
+    def jsonld_context(self, uri: str) -> Dict[str, Any]:
+        """
+        Generate a JSON-LD context from the imports closure of an ontology: the prefixes its
+        ontologies declare, and a term for each class and property, named after its local name.
+
+        Args:
+            uri: The URI of the ontology.
+
+        Returns:
+            The context document, as {"@context": {...}}.
+        """
+        ...
+
+    def prefixes(self) -> Dict[str, str]:
+        """
+        The environment-wide prefix registry: one prefix per namespace, merged from the
+        prefixes the ontologies declare. Namespaces declared by more ontologies choose first.
+
+        Returns:
+            A dictionary of prefixes to namespaces.
+        """
+        ...
+
+    def expand(self, curie: str) -> str:
+        """
+        Expand a CURIE such as brick:Sensor to a full IRI.
+
+        Args:
+            curie: The CURIE to expand.
+
+        Returns:
+            The IRI. Raises a ValueError if no ontology binds the prefix.
+        """
+        ...
+
+    def shrink(self, iri: str) -> Optional[str]:
+        """
+        Shrink an IRI to a CURIE using the prefix registry.
+
+        Args:
+            iri: The IRI to shrink.
+
+        Returns:
+            The CURIE, or None if no prefix is bound to a namespace of the IRI.
         """
         ...
 
@@ -169,3 +357,45 @@ class OntoEnv:
         Convert the OntoEnv to an rdflib.Dataset.
         """
         ...
+
+class DaemonClient:
+    """
+    A client of the `ontoenv daemon` serving an environment. Each request is sent over a
+    connection of its own, so a client can be kept for a whole session. Only available on Unix.
+    """
+    @staticmethod
+    def connect(path: Union[str, Path] = ".") -> Optional["DaemonClient"]:
+        """
+        Connect to the daemon serving the environment at the path.
+
+        Returns:
+            The client, or None if no daemon is running.
+        """
+        ...
+
+    def resolve(self, uri: str) -> Optional[Tuple[str, str]]:
+        """
+        Get the name and location of the ontology with the given name, or None.
+        """
+        ...
+
+    def list_closure(self, uri: str) -> List[str]:
+        """
+        List the names of the ontologies in the imports closure of the ontology.
+        """
+        ...
+
+    def get_graph(self, uri: str) -> Any:
+        """
+        Export the graph with the given URI to an rdflib.Graph.
+        """
+        ...
+
+    def update(self, all: bool = False) -> str:
+        """
+        Make the daemon update its environment.
+
+        Returns:
+            The update report as a JSON string.
+        """
+        ...